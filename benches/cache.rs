@@ -0,0 +1,76 @@
+//! Benchmark of the analysis cache, see `--cache-dir`.
+//!
+//! Prepares the generation context of the kernel in `tests/data/linux-6.18`
+//! without cache, with a cold cache, and with a warm one, and prints the
+//! times. Run with `cargo bench --bench cache`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use btf2json::cli::Cli;
+use btf2json::GenerationContext;
+use clap::Parser;
+use xz2::read::XzDecoder;
+
+/// Number of runs of which the fastest is reported.
+const RUNS: usize = 5;
+
+/// Decompresses `name.xz` from `src` into `dst` and returns the path.
+fn unpack(src: &Path, dst: &Path, name: &str) -> PathBuf {
+    let mut data = Vec::new();
+    XzDecoder::new(File::open(src.join(format!("{}.xz", name))).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    let path = dst.join(name);
+    fs::write(&path, data).unwrap();
+    path
+}
+
+/// Returns the time it takes to prepare the generation context.
+fn prepare(btf: &Path, map: &Path, cache_dir: Option<&Path>) -> Duration {
+    let mut args = vec![
+        "btf2json".as_ref(),
+        "--btf".as_ref(),
+        btf.as_os_str(),
+        "--map".as_ref(),
+        map.as_os_str(),
+        "--banner".as_ref(),
+        "Linux version 6.18.0".as_ref(),
+    ];
+    if let Some(dir) = cache_dir {
+        args.extend(["--cache-dir".as_ref(), dir.as_os_str()]);
+    }
+    let cli = Cli::parse_from(args);
+    let start = Instant::now();
+    GenerationContext::try_from(&cli).unwrap();
+    start.elapsed()
+}
+
+fn main() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/linux-6.18");
+    let dst = env::temp_dir().join(format!("btf2json-bench-cache-{}", std::process::id()));
+    fs::create_dir_all(&dst).unwrap();
+    let btf = unpack(&src, &dst, "vmlinux.btf");
+    let map = unpack(&src, &dst, "System.map");
+    let cache_dir = dst.join("cache");
+
+    let fastest = |f: &dyn Fn() -> Duration| (0..RUNS).map(|_| f()).min().unwrap();
+    let uncached = fastest(&|| prepare(&btf, &map, None));
+    let cold = fastest(&|| {
+        let _ = fs::remove_dir_all(&cache_dir);
+        prepare(&btf, &map, Some(&cache_dir))
+    });
+    let warm = fastest(&|| prepare(&btf, &map, Some(&cache_dir)));
+    fs::remove_dir_all(&dst).unwrap();
+
+    println!("uncached:   {:>10.2?}", uncached);
+    println!("cold cache: {:>10.2?}", cold);
+    println!(
+        "warm cache: {:>10.2?} ({:.2}x)",
+        warm,
+        uncached.as_secs_f64() / warm.as_secs_f64()
+    );
+}
@@ -0,0 +1,43 @@
+/*
+ * C interface of btf2json, built with
+ * `cargo rustc --lib --release --features ffi --crate-type cdylib`, which
+ * produces libbtf2json.so.
+ */
+
+#ifndef BTF2JSON_H
+#define BTF2JSON_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The ISF file was generated. */
+#define BTF2JSON_OK 0
+/* The ISF file could not be generated, like exit code 1 of the command line. */
+#define BTF2JSON_ERR_GENERATION 1
+/* The arguments are invalid, like exit code 3 of the command line. */
+#define BTF2JSON_ERR_ARGUMENTS 3
+/* The generation panicked. */
+#define BTF2JSON_ERR_PANIC 4
+
+/*
+ * Generates the ISF file from the BTF file at btf_path.
+ *
+ * map_path is an optional System.map, banner an optional Linux banner, both
+ * may be NULL. On success, the ISF JSON is stored in *out_json and
+ * BTF2JSON_OK is returned. Otherwise, *out_json is NULL, an error code is
+ * returned, and the message is stored in *out_err unless out_err is NULL.
+ *
+ * Strings stored in *out_json and *out_err are released with btf2json_free.
+ */
+int btf2json_generate(const char *btf_path, const char *map_path,
+		      const char *banner, char **out_json, char **out_err);
+
+/* Releases a string returned by btf2json_generate. NULL is ignored. */
+void btf2json_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BTF2JSON_H */
@@ -0,0 +1,167 @@
+//! Detection of the architecture of the kernel.
+
+use crate::cli::{Architecture, GenerateArgs};
+use crate::decompress;
+use crate::diagnostics;
+use crate::elf;
+use crate::input::{self, InputData};
+use crate::symbols::{self, Banner};
+
+use std::path::Path;
+
+use crate::btf::Endian;
+use anyhow::{bail, Result};
+
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_PPC64, EM_RISCV, EM_S390, EM_X86_64};
+
+/// Start of the x86_64 kernel image mapping.
+const X86_64_KERNEL_START: u64 = 0xffffffff80000000;
+/// Start of the largest arm64 kernel address space (52-bit VA).
+const ARM64_KERNEL_START: u64 = 0xfff0000000000000;
+/// Start of the ppc64 kernel linear mapping (KERNELBASE).
+const PPC64_KERNEL_START: u64 = 0xc000000000000000;
+
+/// Returns the architecture of the kernel that the profile is generated for.
+///
+/// The architecture is detected from the ELF header of the BTF source or
+/// memory image, the banner, or the address of `_stext` (or `_text`) in the
+/// System.map or kallsyms file, in that order. An explicit architecture on the
+/// command line takes precedence.
+pub fn detect(cli: &GenerateArgs) -> Result<Architecture> {
+    let detected = detect_from_inputs(cli);
+
+    match (cli.arch.given(), detected) {
+        (None, Some(arch)) => Ok(arch),
+        (None, None) if cli.types_only => {
+            log::info!("Unable to detect architecture, assuming x86_64");
+            Ok(Architecture::X86_64)
+        }
+        (None, None) => {
+            bail!("Unable to detect architecture, please pass --arch")
+        }
+        (Some(arch), Some(detected)) if arch != detected => {
+            log::warn!(
+                target: diagnostics::INPUTS,
+                code = "arch_mismatch",
+                arch:? = arch,
+                detected:? = detected;
+                "Architecture {:?} was given, but the inputs are for {:?}. Symbol addresses will likely be wrong!",
+                arch,
+                detected
+            );
+            Ok(arch)
+        }
+        (Some(arch), _) => Ok(arch),
+    }
+}
+
+fn detect_from_inputs(cli: &GenerateArgs) -> Option<Architecture> {
+    if cli.live {
+        return from_host();
+    }
+
+    let elf_source = cli
+        .btf
+        .as_ref()
+        .or(cli.dwarf.as_ref())
+        .filter(|btf| !input::is_stdin(btf))
+        .or(cli.image.as_ref());
+    if let Some(arch) = elf_source.and_then(|path| from_elf(path)) {
+        log::debug!("Detected architecture {:?} from ELF header", arch);
+        return Some(arch);
+    }
+    if let Some(arch) = Banner::try_from(cli).ok().and_then(|b| from_banner(&b)) {
+        log::debug!("Detected architecture {:?} from banner", arch);
+        return Some(arch);
+    }
+    // Standard input can only be read once, for the symbols.
+    let arch = cli
+        .map
+        .iter()
+        .chain(&cli.kallsyms)
+        .filter(|map| !input::is_stdin(map))
+        .find_map(|map| from_system_map(map));
+    if let Some(arch) = arch {
+        log::debug!("Detected architecture {:?} from symbol map", arch);
+        return Some(arch);
+    }
+
+    None
+}
+
+fn from_host() -> Option<Architecture> {
+    match std::env::consts::ARCH {
+        "x86_64" => Some(Architecture::X86_64),
+        "aarch64" => Some(Architecture::Arm64),
+        "x86" => Some(Architecture::X86),
+        "arm" => Some(Architecture::Arm),
+        "riscv64" => Some(Architecture::Riscv64),
+        "powerpc64" if cfg!(target_endian = "little") => Some(Architecture::Ppc64le),
+        "s390x" => Some(Architecture::S390x),
+        _ => None,
+    }
+}
+
+fn from_elf(path: &Path) -> Option<Architecture> {
+    let raw = InputData::map_file(path).ok()?;
+    // Compressed images are not decompressed just for this.
+    if decompress::is_arm64_image(&raw) {
+        return Some(Architecture::Arm64);
+    }
+    from_elf_data(&raw)
+}
+
+/// Returns the architecture in the ELF header of `raw`, if it is an ELF file.
+pub fn from_elf_data(raw: &[u8]) -> Option<Architecture> {
+    match elf::get_machine(raw)? {
+        EM_X86_64 => Some(Architecture::X86_64),
+        EM_AARCH64 => Some(Architecture::Arm64),
+        EM_386 => Some(Architecture::X86),
+        EM_ARM => Some(Architecture::Arm),
+        EM_RISCV => Some(Architecture::Riscv64),
+        EM_PPC64 if elf::is_elf(raw).ok()? == Endian::Little => Some(Architecture::Ppc64le),
+        // 31-bit kernels predate BTF.
+        EM_S390 => Some(Architecture::S390x),
+        machine => {
+            log::debug!("Unsupported ELF machine {}", machine);
+            None
+        }
+    }
+}
+
+fn from_banner(banner: &Banner) -> Option<Architecture> {
+    let banner = banner.to_string();
+    if banner.contains("x86_64") || banner.contains("amd64") {
+        Some(Architecture::X86_64)
+    } else if banner.contains("aarch64") || banner.contains("arm64") {
+        Some(Architecture::Arm64)
+    } else if ["i386", "i486", "i586", "i686"]
+        .iter()
+        .any(|arch| banner.contains(arch))
+    {
+        Some(Architecture::X86)
+    } else if banner.contains("armv") || banner.contains("arm-linux") {
+        Some(Architecture::Arm)
+    } else if banner.contains("riscv64") {
+        Some(Architecture::Riscv64)
+    } else if banner.contains("ppc64le") || banner.contains("powerpc64le") {
+        Some(Architecture::Ppc64le)
+    } else if banner.contains("s390x") {
+        Some(Architecture::S390x)
+    } else {
+        None
+    }
+}
+
+fn from_system_map(map: &Path) -> Option<Architecture> {
+    let stext = ["_stext", "_text"]
+        .into_iter()
+        .find_map(|name| symbols::map_address(&[map], name).ok())?;
+    match stext {
+        X86_64_KERNEL_START.. => Some(Architecture::X86_64),
+        ARM64_KERNEL_START..X86_64_KERNEL_START => Some(Architecture::Arm64),
+        // Big endian ppc64 is not supported, which only the BTF tells.
+        PPC64_KERNEL_START..ARM64_KERNEL_START => Some(Architecture::Ppc64le),
+        _ => None,
+    }
+}
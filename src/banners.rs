@@ -0,0 +1,211 @@
+//! Linux banners in memory images, as listed by `btf2json banners`.
+//!
+//! Images may hold more than one banner, e.g., of a kernel that was replaced
+//! with kexec, or in stale pages, so all distinct banners are listed with
+//! where they are.
+
+use crate::cli::{BannersArgs, BannersFormat, ImageFormat};
+use crate::elf;
+use crate::image;
+use crate::input::InputData;
+use crate::lime::{self, Lime};
+use crate::metadata::Build;
+use crate::vmcore::{self, Vmcore};
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use anyhow::{bail, Error, Result};
+use base64::prelude::*;
+use serde::Serialize;
+
+/// Location of a banner in a memory image.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Occurrence {
+    /// Offset in the image file.
+    pub offset: u64,
+    /// Physical address, in raw and LiME images.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub physical_address: Option<u64>,
+    /// Virtual address, in the mapped segments of ELF vmcores.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_address: Option<u64>,
+}
+
+/// How likely a banner is the one of a kernel.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Confidence {
+    /// The banner has the compiler and the build date, as `linux_banner` has.
+    High,
+    /// The banner lacks parts, e.g., as it was built with a custom
+    /// `KBUILD_BUILD_TIMESTAMP` or is a copy in some other string.
+    Low,
+}
+
+/// A distinct banner in a memory image.
+#[derive(Serialize, Debug)]
+pub struct Candidate {
+    /// Banner as in memory, ending with a newline.
+    pub banner: String,
+    /// Base64 of the banner, as in the constant data of `linux_banner` in ISF
+    /// files.
+    pub base64: String,
+    pub confidence: Confidence,
+    /// Why the banner has its confidence.
+    pub note: String,
+    /// Locations of the banner, in the order of the image.
+    pub occurrences: Vec<Occurrence>,
+}
+
+/// Distinct banners of a memory image, in the order of their first
+/// occurrence.
+pub struct Banners(Vec<Candidate>);
+
+impl TryFrom<&BannersArgs> for Banners {
+    type Error = Error;
+
+    fn try_from(args: &BannersArgs) -> Result<Banners> {
+        let raw = InputData::map_file(&args.image)?;
+        let banners = Banners::from_image(&raw, args.image_format)?;
+        if banners.0.is_empty() {
+            bail!("No Linux banner in {}", args.image.display());
+        }
+        Ok(banners)
+    }
+}
+
+impl Banners {
+    /// Returns the banners in the memory image `raw` with the layout `format`.
+    ///
+    /// ELF vmcores are only searched in their loadable segments and LiME
+    /// images in their ranges, unless `format` is raw.
+    pub fn from_image(raw: &[u8], format: ImageFormat) -> Result<Self> {
+        let detect = format == ImageFormat::Auto;
+        let vmcore = if detect && (vmcore::is_compressed_kdump(raw) || elf::is_core(raw)) {
+            Some(Vmcore::parse(raw)?)
+        } else {
+            None
+        };
+        let lime = if detect && vmcore.is_none() && lime::is_lime(raw) {
+            Some(Lime::parse(raw)?)
+        } else {
+            None
+        };
+
+        let found = match (&vmcore, &lime) {
+            (Some(vmcore), _) => banners_in(raw, &vmcore.segments()),
+            (_, Some(lime)) => banners_in(raw, &lime.segments()),
+            _ => image::find_banners(raw),
+        };
+        let mut candidates: Vec<Candidate> = Vec::new();
+        let mut indices = HashMap::new();
+        for (off, text) in found {
+            let occurrence = Occurrence {
+                offset: off as u64,
+                physical_address: match (&vmcore, &lime) {
+                    (Some(_), _) => None,
+                    (_, Some(lime)) => lime.phys_addr(off),
+                    _ => Some(off as u64),
+                },
+                virtual_address: vmcore.as_ref().and_then(|vmcore| vmcore.virt_addr(off)),
+            };
+            let idx = *indices.entry(text).or_insert_with(|| {
+                candidates.push(Candidate::new(text));
+                candidates.len() - 1
+            });
+            candidates[idx].occurrences.push(occurrence);
+        }
+        log::debug!("Found {} distinct Linux banners", candidates.len());
+
+        Ok(Self(candidates))
+    }
+
+    /// Returns the banners in the order of their first occurrence.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.0
+    }
+
+    /// Returns the banners in `format`.
+    pub fn format(&self, format: BannersFormat) -> Result<String> {
+        match format {
+            BannersFormat::Json => Ok(serde_json::to_string_pretty(&self.0)? + "\n"),
+            BannersFormat::Text => Ok(self.text()),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for (idx, candidate) in self.0.iter().enumerate() {
+            if idx > 0 {
+                text.push('\n');
+            }
+            let locations: Vec<String> = candidate
+                .occurrences
+                .iter()
+                .map(|occurrence| {
+                    let mut location = format!("{:#x}", occurrence.offset);
+                    if let Some(paddr) = occurrence.physical_address {
+                        let _ = write!(location, " (physical {:#x})", paddr);
+                    }
+                    if let Some(vaddr) = occurrence.virtual_address {
+                        let _ = write!(location, " (virtual {:#x})", vaddr);
+                    }
+                    location
+                })
+                .collect();
+            let confidence = match candidate.confidence {
+                Confidence::High => "high",
+                Confidence::Low => "low",
+            };
+            let _ = writeln!(text, "{}", candidate.banner.trim_end());
+            let _ = writeln!(text, "  confidence: {} ({})", confidence, candidate.note);
+            let _ = writeln!(text, "  offsets: {}", locations.join(", "));
+            let _ = writeln!(text, "  base64: {}", candidate.base64);
+        }
+        text
+    }
+}
+
+impl Candidate {
+    /// Returns the candidate for the banner `text`, without its newline.
+    fn new(text: &str) -> Self {
+        let banner = format!("{}\n", text);
+        let build = Build::from_banner(&banner);
+        let missing: Vec<&str> = [
+            (build.compiler.is_none(), "no compiler"),
+            (build.build_date.is_none(), "no build date"),
+        ]
+        .into_iter()
+        .filter_map(|(missing, note)| missing.then_some(note))
+        .collect();
+        let (confidence, note) = if missing.is_empty() {
+            (
+                Confidence::High,
+                "has the compiler and the build date".to_owned(),
+            )
+        } else {
+            (Confidence::Low, missing.join(", "))
+        };
+        Self {
+            base64: BASE64_STANDARD.encode(&banner),
+            banner,
+            confidence,
+            note,
+            occurrences: Vec::new(),
+        }
+    }
+}
+
+/// Returns the banners of [`image::find_banners`] in the `segments` of `raw`,
+/// with offsets relative to `raw`.
+fn banners_in<'a>(raw: &'a [u8], segments: &[std::ops::Range<usize>]) -> Vec<(usize, &'a str)> {
+    segments
+        .iter()
+        .flat_map(|segment| {
+            image::find_banners(&raw[segment.clone()])
+                .into_iter()
+                .map(|(off, text)| (segment.start + off, text))
+        })
+        .collect()
+}
@@ -0,0 +1,396 @@
+//! Generation of the ISF files of many kernels, as done by `btf2json batch`.
+//!
+//! The kernels are either found in a directory by the names that `/boot` uses,
+//! or listed in a manifest. A kernel that fails does not stop the others, its
+//! reason is part of the summary.
+
+use crate::cli::{BatchArgs, BatchFormat, Compression, GenerateArgs};
+use crate::isf::Isf;
+use crate::output;
+use crate::GenerationContext;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+const VMLINUX_PREFIX: &str = "vmlinux-";
+const VMLINUZ_PREFIX: &str = "vmlinuz-";
+const MAP_PREFIX: &str = "System.map-";
+
+/// Inputs of one kernel.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Kernel {
+    /// Kernel or BTF file, `None` for a System.map without a kernel.
+    pub btf: Option<PathBuf>,
+    pub map: Option<PathBuf>,
+    pub banner: Option<String>,
+}
+
+impl Kernel {
+    /// Returns the name of the kernel in the summary.
+    fn name(&self) -> String {
+        let path = self.btf.as_ref().or(self.map.as_ref());
+        let name = path.map(|path| path.file_name().map_or(path.as_os_str(), |name| name));
+        name.map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    }
+}
+
+/// Returns the kernels in `dir`, by release.
+///
+/// `vmlinux-<release>` is preferred over `vmlinuz-<release>`, as it has the
+/// symbol table and is not compressed.
+pub fn discover(dir: &Path) -> Result<Vec<Kernel>> {
+    let mut releases: BTreeMap<String, [Option<PathBuf>; 3]> = BTreeMap::new();
+    let entries = fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(str::to_owned)
+        else {
+            continue;
+        };
+        let Some((idx, release)) = [VMLINUX_PREFIX, VMLINUZ_PREFIX, MAP_PREFIX]
+            .iter()
+            .enumerate()
+            .find_map(|(idx, prefix)| Some((idx, name.strip_prefix(prefix)?.to_owned())))
+            .filter(|(_, release)| !release.is_empty())
+        else {
+            log::debug!("Ignoring {}", path.display());
+            continue;
+        };
+        if !path.is_file() {
+            log::debug!("Ignoring {}, which is not a file", path.display());
+            continue;
+        }
+        releases.entry(release).or_default()[idx] = Some(path);
+    }
+
+    Ok(releases
+        .into_values()
+        .map(|[vmlinux, vmlinuz, map]| Kernel {
+            btf: vmlinux.or(vmlinuz),
+            map,
+            banner: None,
+        })
+        .collect())
+}
+
+/// Reads the kernels from the manifest at `path`.
+///
+/// Relative paths are relative to the directory of the manifest.
+pub fn read_manifest(path: &Path) -> Result<Vec<Kernel>> {
+    let raw = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    let mut kernels: Vec<Kernel> = serde_json::from_slice(&raw)
+        .with_context(|| format!("Invalid manifest {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for (idx, kernel) in kernels.iter_mut().enumerate() {
+        let Some(btf) = &kernel.btf else {
+            bail!("Kernel {} of manifest {} has no btf", idx, path.display());
+        };
+        kernel.btf = Some(dir.join(btf));
+        kernel.map = kernel.map.as_ref().map(|map| dir.join(map));
+    }
+    Ok(kernels)
+}
+
+/// Outcome of generating the ISF file of a kernel.
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum Outcome {
+    Ok {
+        /// Path of the ISF file.
+        output: PathBuf,
+        symbols: usize,
+        /// Symbols with a type other than the `void` fallback.
+        typed_symbols: usize,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+/// Summary of a kernel.
+#[derive(Serialize, Debug)]
+pub struct KernelReport {
+    pub kernel: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// Summary of all kernels of a batch, in the order of the kernels.
+pub struct Batch(Vec<KernelReport>);
+
+impl Batch {
+    /// Checks the options of `args` that do not depend on the kernels.
+    pub fn check_args(args: &BatchArgs) -> Result<()> {
+        let generate = &args.generate;
+        if generate.output_dir.is_none() {
+            bail!("batch requires --output-dir");
+        }
+        let per_kernel = [
+            ("--btf", generate.btf.is_some()),
+            ("--dwarf", generate.dwarf.is_some()),
+            ("--package", generate.package.is_some()),
+            ("--image", generate.image.is_some()),
+            ("--map", !generate.map.is_empty()),
+            ("--kallsyms", generate.kallsyms.is_some()),
+            ("--banner", generate.banner.is_some()),
+            ("--banner-file", generate.banner_file.is_some()),
+            ("--output", generate.output.is_some()),
+            ("--bundle", generate.bundle.is_some()),
+        ];
+        if let Some((option, _)) = per_kernel.iter().find(|(_, given)| *given) {
+            bail!(
+                "{} cannot be given to batch, the inputs are given by --input-dir or --manifest",
+                option
+            );
+        }
+        if generate.warnings_as_errors {
+            bail!("--warnings-as-errors cannot be given to batch, the warnings of its kernels are not told apart");
+        }
+        if args.jobs == 0 {
+            bail!("--jobs must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// Generates the ISF files of the kernels of `args`.
+    ///
+    /// Only fails if the kernels cannot be found, failures of single kernels
+    /// are in the summary.
+    pub fn run(args: &BatchArgs) -> Result<Self> {
+        Self::check_args(args)?;
+        let kernels = match (&args.input_dir, &args.manifest) {
+            (Some(dir), _) => discover(dir)?,
+            (_, Some(manifest)) => read_manifest(manifest)?,
+            (None, None) => bail!("batch requires --input-dir or --manifest"),
+        };
+        if kernels.is_empty() {
+            bail!("No kernels found");
+        }
+        log::info!(
+            "Generating {} kernels with {} threads",
+            kernels.len(),
+            args.jobs.min(kernels.len())
+        );
+
+        let next = AtomicUsize::new(0);
+        let claimed = Mutex::new(HashMap::new());
+        let reports = Mutex::new(BTreeMap::new());
+        thread::scope(|scope| {
+            for _ in 0..args.jobs.min(kernels.len()) {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(kernel) = kernels.get(idx) else {
+                        break;
+                    };
+                    let report = KernelReport {
+                        kernel: kernel.name(),
+                        outcome: generate_kernel(&args.generate, kernel, &claimed),
+                    };
+                    reports.lock().unwrap().insert(idx, report);
+                });
+            }
+        });
+
+        Ok(Self(reports.into_inner().unwrap().into_values().collect()))
+    }
+
+    /// Returns the summaries of the kernels.
+    pub fn reports(&self) -> &[KernelReport] {
+        &self.0
+    }
+
+    /// Returns the number of kernels that failed.
+    pub fn failures(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|report| matches!(report.outcome, Outcome::Failed { .. }))
+            .count()
+    }
+
+    /// Returns the summary in `format`.
+    pub fn format(&self, format: BatchFormat) -> Result<String> {
+        match format {
+            BatchFormat::Json => Ok(serde_json::to_string_pretty(&self.0)? + "\n"),
+            BatchFormat::Table => Ok(self.table()),
+        }
+    }
+
+    fn table(&self) -> String {
+        let rows: Vec<[String; 4]> = self
+            .0
+            .iter()
+            .map(|report| match &report.outcome {
+                Outcome::Ok {
+                    output,
+                    symbols,
+                    typed_symbols,
+                } => [
+                    report.kernel.clone(),
+                    String::from("ok"),
+                    coverage(*typed_symbols, *symbols),
+                    output.display().to_string(),
+                ],
+                Outcome::Failed { reason } => [
+                    report.kernel.clone(),
+                    String::from("failed"),
+                    String::from("-"),
+                    reason.clone(),
+                ],
+            })
+            .collect();
+        let header = ["kernel", "status", "typed symbols", "output or reason"].map(String::from);
+        let width = |col: usize| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[col].len())
+                .max()
+                .unwrap_or(0)
+        };
+        let (w0, w1, w2) = (width(0), width(1), width(2));
+
+        let mut table = String::new();
+        for row in [&header].into_iter().chain(&rows) {
+            let _ = writeln!(
+                table,
+                "{:<w0$}  {:<w1$}  {:>w2$}  {}",
+                row[0], row[1], row[2], row[3]
+            );
+        }
+        let _ = writeln!(
+            table,
+            "\n{} of {} kernels succeeded",
+            self.0.len() - self.failures(),
+            self.0.len()
+        );
+        table
+    }
+}
+
+/// Formats the share of typed symbols.
+fn coverage(typed: usize, total: usize) -> String {
+    if total == 0 {
+        return String::from("0/0");
+    }
+    format!(
+        "{}/{} ({:.1}%)",
+        typed,
+        total,
+        typed as f64 * 100.0 / total as f64
+    )
+}
+
+/// Generates the ISF file of `kernel` with the options `args`.
+///
+/// `claimed` has the names of the ISF files of this batch, by the kernels that
+/// wrote them, so that kernels with the same banner are reported instead of
+/// replacing each other's files.
+fn generate_kernel(
+    args: &GenerateArgs,
+    kernel: &Kernel,
+    claimed: &Mutex<HashMap<String, String>>,
+) -> Outcome {
+    let Some(btf) = &kernel.btf else {
+        return Outcome::Failed {
+            reason: String::from("No kernel for this System.map"),
+        };
+    };
+    let mut args = args.clone();
+    args.btf = Some(btf.clone());
+    args.map = kernel.map.iter().cloned().collect();
+    args.banner = kernel.banner.clone();
+
+    // Bugs in one kernel do not stop the others.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        generate_isf(&args, &kernel.name(), claimed)
+    }))
+    .unwrap_or_else(|payload| {
+        let msg = payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied())
+            .unwrap_or("unknown panic");
+        Err(anyhow!("Panicked: {}", msg))
+    });
+    match result {
+        Ok(outcome) => {
+            log::info!("{}: done", kernel.name());
+            outcome
+        }
+        Err(err) => {
+            log::warn!("{}: {:#}", kernel.name(), err);
+            Outcome::Failed {
+                reason: format!("{:#}", err),
+            }
+        }
+    }
+}
+
+fn generate_isf(
+    args: &GenerateArgs,
+    name: &str,
+    claimed: &Mutex<HashMap<String, String>>,
+) -> Result<Outcome> {
+    let dir = args.output_dir.as_deref().context("No --output-dir")?;
+    let ctx = GenerationContext::try_from(args)?;
+    let Some(banner) = ctx.banner() else {
+        bail!("No Linux banner found");
+    };
+    if let Some(other) = claimed
+        .lock()
+        .unwrap()
+        .insert(banner.isf_file_name(), name.to_owned())
+    {
+        bail!("Same banner as {}", other);
+    }
+    let path = output::banner_path(dir, banner, args.force)?;
+
+    let mut isf = Isf::try_from(ctx)?;
+    let mut problems = Vec::new();
+    if isf.skipped_types() > 0 {
+        problems.push(format!(
+            "Type construction failed: {} types were skipped",
+            isf.skipped_types()
+        ));
+    }
+    if args.strict {
+        problems.extend(isf.check_report().check().iter().map(ToString::to_string));
+        if let Err(err) = isf.check_layout() {
+            problems.push(err.to_string());
+        }
+    }
+    problems.extend(isf.apply_quirks().iter().map(ToString::to_string));
+    if !problems.is_empty() {
+        if args.strict {
+            bail!("{}", problems.join("; "));
+        }
+        log::warn!(
+            "{}: writing ISF file despite failed verification: {}",
+            name,
+            problems.join("; ")
+        );
+    }
+
+    let summary = isf.untyped_report().summary;
+    output::write(&isf, &path, Some(Compression::Xz))?;
+    if let Some(index) = &args.banners_index {
+        output::update_banners_index(index, &isf, &path)?;
+    }
+    Ok(Outcome::Ok {
+        output: path,
+        symbols: summary.symbols,
+        typed_symbols: summary.typed,
+    })
+}
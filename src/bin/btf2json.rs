@@ -1,48 +1,700 @@
+use anyhow::{Context, Result};
 use clap::Parser;
+use log::LevelFilter;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 use std::process::exit;
+use std::time::Instant;
 
-use btf2json::cli::Cli;
+use btf2json::banners::Banners;
+use btf2json::batch::Batch;
+use btf2json::bundle::{Bundle, Verification};
+use btf2json::cli::{
+    BannersArgs, BatchArgs, Cli, Command, Compression, GenerateArgs, GraphArgs, LogFormat,
+    MergeArgs, OffsetsArgs, QueryArgs, StatsArgs, SymdbArgs, SymdbCommand, Vol2Args,
+};
+use btf2json::diagnostics::{self, Collector};
+use btf2json::error::VerificationKind;
+use btf2json::graph::TypeGraph;
 use btf2json::isf::Isf;
-use btf2json::GenerationContext;
+use btf2json::merge::Merged;
+use btf2json::offsets::Offsets;
+use btf2json::output;
+use btf2json::progress::Phase;
+use btf2json::query::Query;
+use btf2json::schema;
+use btf2json::stats::Stats;
+use btf2json::symbols::{self, SymDb};
+use btf2json::symdb;
+use btf2json::vol2;
+use btf2json::{Error, GenerationContext};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Exit code if the ISF file cannot be generated or written.
+const EXIT_GENERATION: i32 = 1;
+/// Exit code if the ISF file fails a verification.
+const EXIT_VERIFICATION: i32 = 2;
+/// Exit code if the command line arguments are invalid.
+const EXIT_ARGUMENTS: i32 = 3;
+
 fn main() {
-    let cli = Cli::parse();
-
-    env_logger::Builder::new()
-        .filter_level(if cli.debug {
-            log::LevelFilter::Trace
-        } else if cli.verbose {
-            log::LevelFilter::Debug
-        } else {
-            log::LevelFilter::Error
-        })
-        .init();
+    let cli = Cli::try_parse().unwrap_or_else(|err| {
+        // Help and version requests are not errors.
+        if !err.use_stderr() {
+            err.exit();
+        }
+        let _ = err.print();
+        exit(EXIT_ARGUMENTS);
+    });
 
-    if cli.version {
-        println!("v{}", VERSION);
-    } else {
-        let ctx = match GenerationContext::try_from(&cli) {
-            Ok(ctx) => ctx,
+    // The log file is opened before anything is generated.
+    let trace = !cli.generate_args().trace_type.is_empty();
+    let logging = init_logging(
+        cli.log_level(),
+        cli.log_format,
+        cli.log_file.as_deref(),
+        trace,
+    );
+    if let Err(err) = logging {
+        print_error(cli.log_format, &format!("{:#}", err));
+        exit(EXIT_GENERATION);
+    }
+
+    match &cli.command {
+        _ if cli.version => println!("v{}", VERSION),
+        Some(Command::Generate(args)) => generate(args, cli.log_format),
+        Some(Command::Query(args)) => query(args),
+        Some(Command::Stats(args)) => stats(args),
+        Some(Command::Graph(args)) => graph(args),
+        Some(Command::Offsets(args)) => offsets(args),
+        Some(Command::Vol2(args)) => vol2(args),
+        Some(Command::Banners(args)) => banners(args),
+        Some(Command::Batch(args)) => batch(args),
+        Some(Command::Merge(args)) => merge(args),
+        Some(Command::Symdb(args)) => symdb(args),
+        Some(Command::Check { file }) => check(file),
+        Some(Command::Validate { file }) => validate(file),
+        None => {
+            print_warning(
+                cli.log_format,
+                None,
+                "generating without a subcommand is deprecated, use `btf2json generate` with the same options",
+            );
+            generate(&cli.legacy, cli.log_format);
+        }
+    }
+}
+
+/// Logger that passes log messages on to several loggers.
+struct Tee(Vec<env_logger::Logger>);
+
+impl log::Log for Tee {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.0.iter().any(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &log::Record) {
+        for logger in &self.0 {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        for logger in &self.0 {
+            logger.flush();
+        }
+    }
+}
+
+/// Sets up logging of messages up to `level` to standard error or, if
+/// `log_file` is given, to that file, with only warnings and errors also going
+/// to standard error.
+///
+/// With `trace`, the diagnostics of `--trace-type` are logged at any level,
+/// to the log file if there is one. Warnings are always counted for the
+/// summary, whatever the level. With [`LogFormat::Json`], each message is a
+/// JSON object on its own line, in the log file too.
+fn init_logging(
+    level: LevelFilter,
+    format: LogFormat,
+    log_file: Option<&Path>,
+    trace: bool,
+) -> Result<()> {
+    let formatted = |level| {
+        let mut builder = env_logger::Builder::new();
+        builder.filter_level(level);
+        if format == LogFormat::Json {
+            builder.format(|buf, record| diagnostics::write_json(buf, record));
+        }
+        builder
+    };
+    let builder = |level| {
+        let mut builder = formatted(level);
+        if trace {
+            builder.filter_module(btf2json::TRACE_TARGET, LevelFilter::Trace);
+        }
+        builder
+    };
+    let logger: Box<dyn log::Log> = match log_file {
+        None => Box::new(builder(level).build()),
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Unable to open log file {}", path.display()))?;
+            let terminal = formatted(level.min(LevelFilter::Warn)).build();
+            let file = builder(level)
+                .write_style(env_logger::WriteStyle::Never)
+                .target(env_logger::Target::Pipe(Box::new(file)))
+                .build();
+            Box::new(Tee(vec![terminal, file]))
+        }
+    };
+    log::set_boxed_logger(Box::new(Collector::new(logger)))?;
+    let level = if trace { LevelFilter::Trace } else { level };
+    log::set_max_level(level.max(LevelFilter::Warn));
+    Ok(())
+}
+
+/// Checks the existing ISF file at `file` against the ISF JSON schema.
+fn validate(file: &Path) {
+    match schema::read_isf(file).and_then(|isf| schema::validate(&isf)) {
+        Ok(()) => println!("{}: valid", file.display()),
+        Err(err) => {
+            eprintln!("{}: invalid ISF file: {:#}", file.display(), err);
+            exit(EXIT_VERIFICATION);
+        }
+    }
+}
+
+/// Prints the type or symbol selected by `args`.
+fn query(args: &QueryArgs) {
+    match Query::try_from(args).and_then(|query| query.run(args)) {
+        Ok(out) => println!("{}", out),
+        Err(err) => {
+            eprintln!("{:#}", err);
+            exit(EXIT_GENERATION);
+        }
+    }
+}
+
+/// Prints the statistics of the inputs in `args`.
+fn stats(args: &StatsArgs) {
+    match Stats::try_from(&args.generate).and_then(|stats| stats.format(args.format)) {
+        Ok(out) => print!("{}", out),
+        Err(err) => {
+            eprintln!("Unable to gather statistics: {:#}", err);
+            exit(EXIT_GENERATION);
+        }
+    }
+}
+
+/// Writes the dependency graph of the types of the inputs in `args`.
+fn graph(args: &GraphArgs) {
+    let graph = TypeGraph::try_from(args).unwrap_or_else(|err| {
+        eprintln!("Unable to build the type graph: {:#}", err);
+        exit(EXIT_GENERATION);
+    });
+    if args.root.is_empty() {
+        eprintln!(
+            "warning: the graph of all types has {} nodes and {} edges, use --root and --depth for a smaller one",
+            graph.nr_nodes(),
+            graph.nr_edges()
+        );
+    }
+    let written: Result<()> = match &args.generate.output {
+        Some(path) => File::create(path)
+            .with_context(|| format!("Unable to create {}", path.display()))
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                graph.write_dot(&mut writer)?;
+                Ok(writer.flush()?)
+            }),
+        None => graph.write_dot(io::stdout().lock()).map_err(Into::into),
+    };
+    if let Err(err) = written {
+        eprintln!("Unable to write the type graph: {:#}", err);
+        exit(EXIT_GENERATION);
+    }
+}
+
+/// Prints the offsets of the fields in `args`.
+///
+/// Fails if any field was not found, after all were looked up.
+fn offsets(args: &OffsetsArgs) {
+    let offsets = Offsets::try_from(args).unwrap_or_else(|err| {
+        eprintln!("Unable to look up the offsets: {:#}", err);
+        exit(EXIT_GENERATION);
+    });
+    match offsets.format(args.format) {
+        Ok(out) => print!("{}", out),
+        Err(err) => {
+            eprintln!("Unable to print the offsets: {:#}", err);
+            exit(EXIT_GENERATION);
+        }
+    }
+    if offsets.failures() > 0 {
+        exit(EXIT_GENERATION);
+    }
+}
+
+/// Writes the Volatility 2 profile of the inputs in `args`.
+fn vol2(args: &Vol2Args) {
+    let profile = vol2::Profile::try_from(args).unwrap_or_else(|err| {
+        eprintln!("Unable to generate the profile: {:#}", err);
+        exit(EXIT_GENERATION);
+    });
+    let written: Result<()> = match &args.generate.output {
+        Some(path) => File::create(path)
+            .with_context(|| format!("Unable to create {}", path.display()))
+            .and_then(|file| Ok(profile.write_zip(BufWriter::new(file))?)),
+        None => profile.write_zip(io::stdout().lock()).map_err(Into::into),
+    };
+    if let Err(err) = written {
+        eprintln!("Unable to write the profile: {:#}", err);
+        exit(EXIT_GENERATION);
+    }
+}
+
+/// Prints the banners in the memory image of `args`.
+fn banners(args: &BannersArgs) {
+    match Banners::try_from(args).and_then(|banners| banners.format(args.format)) {
+        Ok(out) => print!("{}", out),
+        Err(err) => {
+            eprintln!("Unable to list the banners: {:#}", err);
+            exit(EXIT_GENERATION);
+        }
+    }
+}
+
+/// Generates the ISF files of the kernels in `args` and prints the summary.
+///
+/// Fails if any kernel failed, after all kernels were tried.
+fn batch(args: &BatchArgs) {
+    if let Err(err) = Batch::check_args(args) {
+        eprintln!("{:#}", err);
+        exit(EXIT_ARGUMENTS);
+    }
+    let batch = Batch::run(args).unwrap_or_else(|err| {
+        eprintln!("Unable to run batch: {:#}", err);
+        exit(EXIT_GENERATION);
+    });
+    match batch.format(args.format) {
+        Ok(out) => print!("{}", out),
+        Err(err) => {
+            eprintln!("Unable to print the summary: {:#}", err);
+            exit(EXIT_GENERATION);
+        }
+    }
+    if batch.failures() > 0 {
+        exit(EXIT_GENERATION);
+    }
+}
+
+/// Merges the ISF files of `args` and writes the result.
+///
+/// Conflicting definitions and failed verifications of the result are
+/// reported, but do not fail the merge.
+fn merge(args: &MergeArgs) {
+    let merged = Merged::try_from(args).unwrap_or_else(|err| {
+        eprintln!("Unable to merge the ISF files: {:#}", err);
+        exit(EXIT_GENERATION);
+    });
+    for conflict in &merged.conflicts {
+        eprintln!("warning: {}", conflict);
+    }
+    for problem in &merged.problems {
+        eprintln!("warning: {}", problem);
+    }
+    let written = match &args.output {
+        Some(path) => output::write(&merged.isf, path, args.compress),
+        None => merged.isf.dump_stdout(),
+    };
+    if let Err(err) = written {
+        eprintln!("Unable to write ISF file: {:#}", err);
+        exit(EXIT_GENERATION);
+    }
+}
+
+/// Prints the entries of the symdb of `args`, or checks them.
+///
+/// The check fails if any line is not a valid entry, after all were listed.
+fn symdb(args: &SymdbArgs) {
+    let symdb = SymDb::load(args.symdb.as_deref()).unwrap_or_else(|err| {
+        eprintln!("Unable to read the symdb: {:#}", err);
+        exit(EXIT_GENERATION);
+    });
+    match &args.command {
+        SymdbCommand::Dump { format } => match symdb::dump(&symdb, *format) {
+            Ok(out) => print!("{}", out),
             Err(err) => {
-                println!("Unable to gather information for ISF generation: {}", err);
-                exit(1);
+                eprintln!("Unable to print the symdb: {:#}", err);
+                exit(EXIT_GENERATION);
+            }
+        },
+        SymdbCommand::Check => {
+            let problems = symdb::check(&symdb);
+            if problems.is_empty() {
+                println!("{}: ok, {} entries", symdb.name(), symdb.entries().len());
+            } else {
+                for problem in &problems {
+                    eprintln!("{}", problem);
+                }
+                eprintln!(
+                    "{}: {} invalid lines, {} entries",
+                    symdb.name(),
+                    problems.len(),
+                    symdb.entries().len()
+                );
+                exit(EXIT_VERIFICATION);
+            }
+        }
+    }
+}
+
+/// Runs the verifications of the generation on the existing ISF file at
+/// `file`.
+fn check(file: &Path) {
+    let raw = match schema::read_isf(file) {
+        Ok(raw) => raw,
+        Err(err) => {
+            eprintln!("Unable to read ISF file: {:#}", err);
+            exit(EXIT_GENERATION);
+        }
+    };
+    let mut problems = Vec::new();
+    if let Err(err) = schema::validate(&raw) {
+        problems.push(format!("{:#}", err));
+    }
+    match serde_json::from_value::<Isf>(raw) {
+        Ok(isf) => {
+            problems.extend(isf.check_report().check().iter().map(ToString::to_string));
+            if let Err(err) = isf.check_layout() {
+                problems.push(err.to_string());
+            }
+        }
+        Err(err) => problems.push(format!("Unable to read ISF file: {}", err)),
+    }
+    if problems.is_empty() {
+        println!("{}: ok", file.display());
+    } else {
+        for problem in problems {
+            eprintln!("{}: {}", file.display(), problem);
+        }
+        exit(EXIT_VERIFICATION);
+    }
+}
+
+/// Generates an ISF file with the options `cli`, the summary of the warnings
+/// in `log_format`.
+fn generate(cli: &GenerateArgs, log_format: LogFormat) {
+    let ctx = match GenerationContext::try_from(cli) {
+        Ok(ctx) => ctx,
+        Err(err) => {
+            print_error(
+                log_format,
+                &format!(
+                    "Unable to gather information for ISF generation: {:#}",
+                    anyhow::Error::from(err)
+                ),
+            );
+            exit(EXIT_GENERATION);
+        }
+    };
+    if cli.strict && ctx.skipped_records() > 0 {
+        print_error(
+            log_format,
+            &format!(
+                "{} BTF types could not be parsed and were skipped",
+                ctx.skipped_records()
+            ),
+        );
+        exit(EXIT_VERIFICATION);
+    }
+    if !cli.no_consistency_check {
+        let mismatches = ctx.check_consistency();
+        if cli.strict && !mismatches.is_empty() {
+            for mismatch in mismatches {
+                print_error(log_format, &format!("Inconsistent inputs: {}", mismatch));
             }
+            print_error(
+                log_format,
+                "Pass --no-consistency-check for intentionally mixed inputs",
+            );
+            exit(EXIT_VERIFICATION);
+        }
+        for mismatch in mismatches {
+            print_warning(
+                log_format,
+                Some("inconsistent_inputs"),
+                &format!("inconsistent inputs: {}", mismatch),
+            );
+            diagnostics::count(diagnostics::INPUTS);
+        }
+    }
+    if let Some(out_of_range) = ctx.check_address_range() {
+        if cli.strict {
+            print_error(
+                log_format,
+                &format!("Symbol addresses out of range: {}", out_of_range),
+            );
+            exit(EXIT_VERIFICATION);
+        }
+        print_warning(
+            log_format,
+            Some("symbols_out_of_range"),
+            &format!("symbol addresses out of range: {}", out_of_range),
+        );
+        diagnostics::count(diagnostics::INPUTS);
+    }
+    // Types-only profiles have at most the synthetic banner symbol.
+    if !cli.types_only && ctx.missing_symdb() {
+        let location = symbols::installed_symdb_path().map_or_else(
+            || format!("${}", symbols::SYMDB_ENV),
+            |path| path.display().to_string(),
+        );
+        print_warning(
+            log_format,
+            Some("missing_symdb"),
+            &format!(
+                "no symdb available, symbols without type in the BTF file will be \
+                 untyped; use --symdb or install one at {}",
+                location
+            ),
+        );
+        diagnostics::count(diagnostics::SYMBOLS);
+    }
+    // The file for the banner is checked before the lengthy generation.
+    let banner_path = cli.output_dir.as_ref().map(|dir| {
+        let Some(banner) = ctx.banner() else {
+            print_error(log_format, "--output-dir requires a banner, use --banner");
+            exit(EXIT_ARGUMENTS);
         };
-        match Isf::try_from(ctx) {
-            Ok(mut isf) => {
-                // We do not fail if types are broken.
-                let _ = isf.fix_symbol_types();
-                if cfg!(debug_assertions) {
-                    let _ = isf.check_user_types();
+        output::banner_path(dir, banner, cli.force).unwrap_or_else(|err| {
+            print_error(log_format, &format!("Unable to write ISF file: {:#}", err));
+            exit(EXIT_GENERATION);
+        })
+    });
+    let artifacts = cli.bundle.as_ref().map(|_| ctx.artifacts());
+    let progress = ctx.progress();
+    match Isf::try_from(ctx) {
+        Ok(mut isf) => {
+            let start = Instant::now();
+            let mut verification = Verification::default();
+            if isf.skipped_types() > 0 {
+                verification.types = Some(format!(
+                    "Type construction failed: {} types were skipped",
+                    isf.skipped_types()
+                ));
+            }
+            if cfg!(debug_assertions)
+                || cli.bundle.is_some()
+                || cli.strict
+                || cli.check
+                || cli.check_report.is_some()
+            {
+                let report = isf.check_report();
+                if let Some(path) = &cli.check_report {
+                    if let Err(err) = report.write(path) {
+                        print_error(
+                            log_format,
+                            &format!("Unable to write check report: {:#}", err),
+                        );
+                        exit(EXIT_GENERATION);
+                    }
+                }
+                for err in report.check() {
+                    let problem = match &err {
+                        Error::IsfVerification {
+                            kind: VerificationKind::SymbolTypes,
+                            ..
+                        } => &mut verification.symbol_types,
+                        Error::IsfVerification {
+                            kind: VerificationKind::Enums,
+                            ..
+                        } => &mut verification.enums,
+                        Error::IsfVerification {
+                            kind: VerificationKind::Descriptors,
+                            ..
+                        } => &mut verification.descriptors,
+                        _ => &mut verification.user_types,
+                    };
+                    *problem = Some(err.to_string());
+                }
+                if let Err(err) = isf.check_layout() {
+                    verification.layout = Some(err.to_string());
                 }
-                isf.dump_stdout()
             }
-            Err(err) => {
-                println!("Unable to generate ISF file: {}", err);
-                exit(1);
+            // Undefined types are reported by the checks before the quirks
+            // void or stub them. We do not fail if types are broken, unless
+            // asked to.
+            for err in isf.apply_quirks() {
+                let problem = match &err {
+                    Error::IsfVerification {
+                        kind: VerificationKind::SymbolTypes,
+                        ..
+                    } => &mut verification.symbol_types,
+                    _ => &mut verification.user_types,
+                };
+                problem.get_or_insert(err.to_string());
+            }
+            if let Some(path) = &cli.untyped_report {
+                if let Err(err) = isf.untyped_report().write(path) {
+                    print_error(
+                        log_format,
+                        &format!("Unable to write untyped symbol report: {:#}", err),
+                    );
+                    exit(EXIT_GENERATION);
+                }
+            }
+            let problems: Vec<&String> = [
+                &verification.types,
+                &verification.symbol_types,
+                &verification.user_types,
+                &verification.enums,
+                &verification.descriptors,
+                &verification.layout,
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if !problems.is_empty() {
+                if cli.strict {
+                    for problem in problems {
+                        print_error(log_format, problem);
+                    }
+                    exit(EXIT_VERIFICATION);
+                }
+                log::warn!(
+                    target: diagnostics::VERIFICATION,
+                    code = "failed_verification",
+                    problems = problems.len();
+                    "Writing ISF file despite failed verification: {}",
+                    problems
+                        .iter()
+                        .map(|problem| problem.as_str())
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                );
             }
+            if cli.warnings_as_errors
+                && print_summary(log_format, "Failing because of --warnings-as-errors with")
+            {
+                exit(EXIT_VERIFICATION);
+            }
+            if cli.validate {
+                if let Err(err) = isf.validate() {
+                    print_error(
+                        log_format,
+                        &format!("Generated ISF file is invalid: {:#}", err),
+                    );
+                    exit(EXIT_VERIFICATION);
+                }
+            }
+            isf.timings().record("verification", start.elapsed());
+            let stats = isf.stats();
+            log::debug!("ISF elements: {}", isf.summary());
+            let start = Instant::now();
+            progress.start(Phase::Serialize, None);
+            if let Some(dir) = &cli.bundle {
+                let bundle = Bundle::new(dir, cli.reproducible);
+                if let Err(err) =
+                    bundle.write(&isf, artifacts.as_deref().unwrap_or(&[]), &verification)
+                {
+                    print_error(log_format, &format!("Unable to write bundle: {:#}", err));
+                    exit(EXIT_GENERATION);
+                }
+            } else if let Some(path) = &banner_path {
+                if let Err(err) = output::write(&isf, path, Some(Compression::Xz)) {
+                    print_error(log_format, &format!("Unable to write ISF file: {:#}", err));
+                    exit(EXIT_GENERATION);
+                }
+                if let Some(index) = &cli.banners_index {
+                    if let Err(err) = output::update_banners_index(index, &isf, path) {
+                        print_error(
+                            log_format,
+                            &format!("Unable to update banners index: {:#}", err),
+                        );
+                        exit(EXIT_GENERATION);
+                    }
+                }
+                println!("{}", path.display());
+            } else if let Some(path) = &cli.output {
+                if let Err(err) = output::write(&isf, path, cli.compress) {
+                    print_error(log_format, &format!("Unable to write ISF file: {:#}", err));
+                    exit(EXIT_GENERATION);
+                }
+                if let Some(provenance) = isf.provenance() {
+                    if let Err(err) = provenance.write(&output::provenance_path(path)) {
+                        print_error(
+                            log_format,
+                            &format!("Unable to write provenance file: {:#}", err),
+                        );
+                        exit(EXIT_GENERATION);
+                    }
+                }
+            } else if let Err(err) = isf.dump_stdout() {
+                print_error(log_format, &format!("Unable to write ISF file: {:#}", err));
+                exit(EXIT_GENERATION);
+            }
+            progress.finish(
+                Phase::Serialize,
+                stats.base_types + stats.enums + stats.user_types + stats.symbols,
+            );
+            isf.timings().record("write", start.elapsed());
+            if cli.timings {
+                eprintln!("{}", isf.timings());
+            }
+            print_summary(log_format, "Generation finished with");
+        }
+        Err(err) => {
+            print_error(
+                log_format,
+                &format!(
+                    "Unable to generate ISF file: {:#}",
+                    anyhow::Error::from(err)
+                ),
+            );
+            print_summary(log_format, "Generation finished with");
+            exit(EXIT_GENERATION);
         }
     }
 }
+
+/// Prints the warning `message` with `code`, if any, to standard error,
+/// whatever the log level.
+fn print_warning(format: LogFormat, code: Option<&str>, message: &str) {
+    match format {
+        LogFormat::Text => eprintln!("warning: {}", message),
+        LogFormat::Json => eprintln!(
+            "{}",
+            diagnostics::event(log::Level::Warn, code, "btf2json", message)
+        ),
+    }
+}
+
+/// Prints the error `message` that ends the run to standard error.
+fn print_error(format: LogFormat, message: &str) {
+    match format {
+        LogFormat::Text => eprintln!("{}", message),
+        LogFormat::Json => eprintln!(
+            "{}",
+            diagnostics::event(log::Level::Error, None, "btf2json", message)
+        ),
+    }
+}
+
+/// Prints the number of warnings by category, if there were any, to standard
+/// error after `intro` or as a JSON object, and returns whether there were
+/// any.
+fn print_summary(format: LogFormat, intro: &str) -> bool {
+    let summary = diagnostics::summary();
+    if summary.is_empty() {
+        return false;
+    }
+    match format {
+        LogFormat::Text => eprintln!("{} {}", intro, summary),
+        LogFormat::Json => eprintln!("{}", summary.to_json()),
+    }
+    true
+}
@@ -6,66 +6,237 @@
 //! Provides a stable API for working with BTF to the rest of the crate.
 // TODO: Still way too leaky...
 
-use crate::cli::Cli;
+use crate::cli::{GenerateArgs, ImageFormat, LIVE_BTF};
+use crate::decompress;
+use crate::diagnostics;
+use crate::dwarf;
 use crate::elf;
-
-use std::collections::{BTreeSet, HashMap, VecDeque};
+use crate::error;
+use crate::image;
+use crate::input::{self, InputData};
+use crate::lime::{self, Lime};
+use crate::names;
+use crate::package;
+use crate::progress::{self, Phase, Progress};
+use crate::vmcore::{self, Vmcore};
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::convert::TryFrom;
 use std::fmt;
-use std::fs::File;
 use std::iter::Iterator;
-use std::path::Path;
+use std::ops::{Bound, Range, RangeBounds};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering as AtomicOrdering};
 
-use anyhow::{bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use btf_rs::BtfType;
-use memmap::Mmap;
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
 
 const BTF_MAGIC_BE: [u8; 2] = [0xeb, 0x9f];
 const BTF_MAGIC_LE: [u8; 2] = [0x9f, 0xeb];
 
 /// Represents a partitioning of the types into the categories that Volatility
-/// distinguishes between, plus a processed view of all typedefs. The starting
-/// point for generating an ISF file.
-pub type VolIdSets = (BTreeSet<Id>, BTreeSet<Id>, BTreeSet<Id>, Typedefs);
-
+/// distinguishes between, plus the forward declarations and a processed view of
+/// all typedefs. The starting point for generating an ISF file.
+pub type VolIdSets = (
+    BTreeSet<Id>,
+    BTreeSet<Id>,
+    BTreeSet<Id>,
+    BTreeSet<Id>,
+    Typedefs,
+);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
 pub enum Endian {
     Big,
     Little,
 }
 
+impl fmt::Display for Endian {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Big => write!(f, "big"),
+            Self::Little => write!(f, "little"),
+        }
+    }
+}
+
+/// Name of a type or member, shared by all of its uses.
+pub type Name = Rc<str>;
+
+/// Interned names of a BTF file, such that each name is allocated once.
+#[derive(Default)]
+struct Names {
+    /// Names in the string table, by offset.
+    strtab: HashMap<u32, Name>,
+    /// All names, including placeholders that are not in the string table.
+    all: HashSet<Name>,
+}
+
+/// Default of [`Btf::set_max_type_depth`], far more than the chains and
+/// descriptors of real kernels nest.
+pub const DEFAULT_MAX_TYPE_DEPTH: usize = 128;
+
 /// Representation of a BTF file.
 pub struct Btf {
+    /// Byte order of the BTF section. `btf_rs` detects it from the magic and
+    /// swaps all values on parsing, so it only matters for the ISF.
     pub endian: Endian,
-    raw: Rc<Mmap>,
+    raw: Rc<InputData>,
+    /// Location of the BTF section in `raw`.
+    section: Range<usize>,
     name: String,
     btf: btf_rs::Btf,
+    /// BTF that `btf` is split from, if any.
+    base: Option<BaseBtf>,
+    /// Name of the kernel module if `raw` is one, in which case `base` is
+    /// vmlinux.
+    module: Option<String>,
+    /// Name and contents of the vmlinux whose DWARF `raw` was generated from,
+    /// see `--dwarf`.
+    kernel: Option<(String, Rc<InputData>)>,
+    ids: IdSpace,
+    /// Number of type IDs, including `void` and the types of the base BTF.
+    nr_types: u32,
+    /// Narrow integer types, including those of the base BTF, by raw ID.
+    narrow_ints: HashMap<u32, IntBits>,
+    /// Types whose record could not be parsed, which are left out of the scan.
+    skipped: BTreeSet<Id>,
+    /// Memoized results of [`Btf::resolve_type_chain`], by starting node.
+    chains: RefCell<HashMap<Id, ResolvedType>>,
+    /// Maximum length of type chains and nesting of type descriptors, see
+    /// [`Btf::set_max_type_depth`].
+    max_type_depth: usize,
+    /// Memoized string table entries, `None` for types without an entry.
+    strtab_entries: RefCell<HashMap<Id, Option<Name>>>,
+    /// Memoized placeholder names of anonymous types.
+    unnamed: RefCell<HashMap<Id, Name>>,
+    /// Interned names, see [`Btf::intern`].
+    names: RefCell<Names>,
+    /// Numbers of the anonymous types in the order of their IDs, if they are
+    /// named like dwarf2json does.
+    dwarf2json_unnamed: Option<HashMap<Id, usize>>,
+    /// Names of the anonymous types, if they are named after their content.
+    stable_unnamed: Option<HashMap<Id, String>>,
+    /// Names of the module types that are prefixed with the module name, see
+    /// [`Btf::namespace_module_types`].
+    namespaced: HashMap<Id, Name>,
 }
 
-impl TryFrom<&Cli> for Btf {
-    type Error = Error;
-
-    fn try_from(cli: &Cli) -> Result<Self> {
-        if cli.btf.is_some() {
-            let file_path: &Path = Path::new(cli.btf.as_ref().unwrap());
-            let file = File::open(file_path)?;
-            let mmap = unsafe { Mmap::map(&file)? };
-            let (endian, btf_sec) = get_btf_section(&mmap)?;
-            let btf = btf_rs::Btf::from_bytes(btf_sec)?;
-            Ok(Btf {
-                endian,
-                raw: Rc::new(mmap),
-                name: file_path
-                    .file_name()
-                    .context("")?
-                    .to_owned()
-                    .into_string()
-                    .expect(""),
-                btf,
-            })
-        } else if cli.image.is_some() {
+/// BTF file that a split BTF file, e.g. of a kernel module, builds upon.
+pub struct BaseBtf {
+    endian: Endian,
+    raw: Rc<InputData>,
+    /// Location of the BTF section in `raw`.
+    section: Range<usize>,
+    name: String,
+    btf: btf_rs::Btf,
+    /// Number of types, including `void`.
+    nr_types: u32,
+    /// Narrow integer types by raw ID.
+    narrow_ints: HashMap<u32, IntBits>,
+}
+
+impl BaseBtf {
+    fn load(path: &Path) -> Result<Self> {
+        let raw = load_btf_file(path)?;
+        let (hdr, btf_sec) = get_btf_section(&raw, &path.display().to_string(), false)?;
+        if hdr.is_split(btf_sec) {
+            bail!(
+                "Base BTF {} is split BTF itself, only one level of split BTF is supported, \
+                 use the BTF of vmlinux as base",
+                path.display()
+            );
+        }
+        let btf = btf_rs::Btf::from_bytes(btf_sec)
+            .with_context(|| format!("Unable to parse base BTF {}", path.display()))?;
+        Ok(Self {
+            endian: hdr.endian,
+            nr_types: hdr.count_types(btf_sec)? + 1,
+            narrow_ints: hdr.narrow_ints(btf_sec, 1)?,
+            section: subslice_range(&raw, btf_sec),
+            raw: Rc::new(raw),
+            name: input::file_name(path)?,
+            btf,
+        })
+    }
+
+    /// Returns the raw base BTF file.
+    pub fn raw(&self) -> Rc<InputData> {
+        self.raw.clone()
+    }
+
+    /// Returns the location of the BTF section within the raw base BTF file.
+    pub fn section_range(&self) -> Range<usize> {
+        self.section.clone()
+    }
+
+    /// Returns the name of the base BTF file.
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Returns the number of types, including `void`.
+    fn nr_types(&self) -> u32 {
+        self.nr_types
+    }
+}
+
+impl TryFrom<&GenerateArgs> for Btf {
+    type Error = error::Error;
+
+    fn try_from(cli: &GenerateArgs) -> error::Result<Self> {
+        Ok(Btf::from_cli(cli)?)
+    }
+}
+
+impl Btf {
+    /// Loads the BTF from the sources given on the CLI.
+    fn from_cli(cli: &GenerateArgs) -> Result<Self> {
+        let live_btf = cli.live.then(|| PathBuf::from(LIVE_BTF));
+        if let Some(live_btf) = &live_btf {
+            if !live_btf.exists() {
+                bail!(
+                    "{} does not exist, is the kernel built with CONFIG_DEBUG_INFO_BTF?",
+                    LIVE_BTF
+                );
+            }
+        }
+
+        if let Some(vmlinux) = &cli.dwarf {
+            return Btf::from_dwarf(vmlinux);
+        }
+        if let (Some(vmlinux), Some(module)) = (&cli.btf, &cli.module) {
+            return Btf::load_module(module, vmlinux);
+        }
+        if let Some(btf) = cli.btf.as_ref().or(live_btf.as_ref()) {
+            let file_path: &Path = Path::new(btf);
+            let raw = load_btf_file(file_path)?;
+            let base = cli.base_btf.as_deref().map(BaseBtf::load).transpose()?;
+            Ok(Btf::from_input(
+                input::file_name(file_path)?,
+                raw,
+                base,
+                cli.scan,
+            )?)
+        } else if let Some(package) = &cli.package {
+            let (name, data) = package::extract(package)?.vmlinux;
+            Ok(Btf::from_bytes(name, data)?)
+        } else if let Some(image) = &cli.image {
             log::debug!("Got memory image, extracting BTF section.");
-            bail!("BTF extraction from memory image is not implemented!")
+            let raw = InputData::map_file(image)?;
+            let (hdr, section) = find_btf_in_image(&raw, cli.image_format)?;
+            Ok(Btf::from_section(
+                input::file_name(image)?,
+                raw,
+                hdr,
+                section,
+                None,
+            )?)
         } else {
             bail!("No source for BTF information provided!")
         }
@@ -73,42 +244,356 @@ impl TryFrom<&Cli> for Btf {
 }
 
 impl Btf {
-    const MAX_BTF_ID: Id = Id(0xFFFFFFFF);
+    /// Loads the types of the DWARF debug information of the vmlinux at
+    /// `path`, see [`dwarf::to_btf`].
+    ///
+    /// The vmlinux remains the kernel file, e.g., for its symbol table.
+    fn from_dwarf(path: &Path) -> Result<Self> {
+        let raw = load_btf_file(path)?;
+        let name = input::file_name(path)?;
+        let section = dwarf::to_btf(&raw)
+            .with_context(|| format!("Unable to read types from the DWARF of {}", name))?;
+        let mut btf = Btf::from_input(name.clone(), InputData::from(section), None, false)?;
+        btf.kernel = Some((name, Rc::new(raw)));
+        Ok(btf)
+    }
+
+    /// Loads the BTF of the kernel module at `path`, which is split from the
+    /// BTF of `vmlinux`.
+    ///
+    /// Module types that share their name with a vmlinux type are prefixed
+    /// with the module name, see [`Btf::namespace_module_types`].
+    fn load_module(path: &Path, vmlinux: &Path) -> Result<Self> {
+        let raw = load_btf_file(path)?;
+        let name = input::file_name(path)?;
+        if elf::has_section(&raw, ".BTF.base") {
+            bail!(
+                "{} has distilled base BTF (.BTF.base), which is not supported",
+                name
+            );
+        }
+        let module = module_name(&name);
+        let base = BaseBtf::load(vmlinux)?;
+        let mut btf = Btf::from_input(name, raw, Some(base), false)?;
+        btf.namespace_module_types(&module);
+        btf.module = Some(module);
+        Ok(btf)
+    }
+
+    /// Loads the BTF file at `path`.
+    ///
+    /// The file can be a raw BTF section, an ELF file with a `.BTF` section, or
+    /// a compressed kernel image.
+    pub fn from_file(path: &Path) -> error::Result<Self> {
+        Btf::from_input(input::file_name(path)?, load_btf_file(path)?, None, false)
+    }
+
+    /// Loads the BTF file `data` that is referred to as `name`.
+    ///
+    /// Accepts the same formats as [`Btf::from_file`].
+    pub fn from_bytes(name: impl Into<String>, data: Vec<u8>) -> error::Result<Self> {
+        let name = name.into();
+        let raw = unpack_btf_file(InputData::from(data), &name)?;
+        Btf::from_input(name, raw, None, false)
+    }
+
+    /// Parses the BTF section in `raw`, which is split from `base` if given.
+    ///
+    /// If `scan` is true, files that are neither a BTF section nor an ELF file
+    /// are searched for a BTF section, see [`get_btf_section`].
+    fn from_input(
+        name: String,
+        raw: InputData,
+        base: Option<BaseBtf>,
+        scan: bool,
+    ) -> error::Result<Self> {
+        let (hdr, btf_sec) = get_btf_section(&raw, &name, scan)?;
+        let section = subslice_range(&raw, btf_sec);
+        Btf::from_section(name, raw, hdr, section, base)
+    }
 
-    /// Returns the memory-mapped BTF file.
-    pub fn raw(&self) -> Rc<Mmap> {
+    /// Parses the BTF section at `section` in `raw`, whose header is `hdr`,
+    /// which is split from `base` if given.
+    ///
+    /// Records that cannot be parsed are skipped, see
+    /// [`BtfHeader::repair_records`].
+    fn from_section(
+        name: String,
+        raw: InputData,
+        hdr: BtfHeader,
+        section: Range<usize>,
+        base: Option<BaseBtf>,
+    ) -> error::Result<Self> {
+        let btf_sec = &raw[section.clone()];
+        let parse_error = |source: Error| error::Error::BtfParse {
+            name: name.clone(),
+            source,
+        };
+        let section_types = hdr.count_types(btf_sec).map_err(parse_error)?;
+        log::debug!("Section defines {} types", section_types);
+        let base_strings = match &base {
+            Some(base) => {
+                let section = &base.raw[base.section.clone()];
+                Some((BtfHeader::parse(section)?, section))
+            }
+            None => None,
+        };
+        let (parsed, invalid, unknown) = hdr
+            .repair_records(btf_sec, base_strings.as_ref().map(|(h, s)| (h, *s)))
+            .map_err(parse_error)?;
+        let (btf, base, ids, nr_types, narrow_ints) = match base {
+            Some(base) => {
+                if base.endian != hdr.endian {
+                    return Err(anyhow!("Base BTF and split BTF differ in endianness").into());
+                }
+                let btf = btf_rs::Btf::from_split_bytes(&parsed, &base.btf).map_err(parse_error)?;
+                let split_start = base.nr_types();
+                log::debug!("Split BTF starts at ID {}", split_start);
+                let mut narrow_ints = hdr.narrow_ints(btf_sec, split_start).map_err(parse_error)?;
+                narrow_ints.extend(base.narrow_ints.iter());
+                (
+                    btf,
+                    Some(base),
+                    IdSpace::new(Some(split_start)),
+                    split_start + section_types,
+                    narrow_ints,
+                )
+            }
+            None => (
+                btf_rs::Btf::from_bytes(&parsed)
+                    .context("Split BTF requires a base BTF")
+                    .map_err(parse_error)?,
+                None,
+                IdSpace::new(None),
+                section_types + 1,
+                hdr.narrow_ints(btf_sec, 1).map_err(parse_error)?,
+            ),
+        };
+        // IDs of the section start after the ones of the base BTF.
+        let first_id = nr_types - section_types;
+        let skipped = invalid
+            .into_iter()
+            .map(|(idx, name_off)| {
+                let id = ids.mint(first_id + idx);
+                log::warn!(
+                    target: diagnostics::SKIPPED_RECORDS,
+                    code = "unparsable_btf_record",
+                    id:% = id,
+                    name_offset = name_off;
+                    "[{}] Skipping BTF type, whose name offset {:#x} is no string of {}",
+                    id,
+                    name_off,
+                    name
+                );
+                id
+            })
+            .chain(unknown.into_iter().map(|(idx, kind)| {
+                let id = ids.mint(first_id + idx);
+                log::warn!(
+                    target: diagnostics::SKIPPED_RECORDS,
+                    code = "unknown_btf_kind",
+                    id:% = id,
+                    kind = kind;
+                    "[{}] Skipping BTF type of unknown kind {} of {}",
+                    id,
+                    kind,
+                    name
+                );
+                id
+            }))
+            .collect();
+        Ok(Btf {
+            endian: hdr.endian,
+            section,
+            raw: Rc::new(raw),
+            name,
+            btf,
+            base,
+            module: None,
+            kernel: None,
+            ids,
+            nr_types,
+            narrow_ints,
+            skipped,
+            chains: RefCell::default(),
+            max_type_depth: DEFAULT_MAX_TYPE_DEPTH,
+            strtab_entries: RefCell::default(),
+            unnamed: RefCell::default(),
+            names: RefCell::default(),
+            dwarf2json_unnamed: None,
+            stable_unnamed: None,
+            namespaced: HashMap::new(),
+        })
+    }
+
+    /// Returns the raw BTF file.
+    pub fn raw(&self) -> Rc<InputData> {
         self.raw.clone()
     }
 
+    /// Returns the BTF section within the raw BTF file.
+    pub fn section(&self) -> &[u8] {
+        &self.raw[self.section.clone()]
+    }
+
+    /// Returns the location of the BTF section within the raw BTF file.
+    pub fn section_range(&self) -> Range<usize> {
+        self.section.clone()
+    }
+
     /// Returns the name of the file that the BTF information was obtained from.
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    /// Returns the base BTF if this is a split BTF.
+    pub fn base(&self) -> Option<&BaseBtf> {
+        self.base.as_ref()
+    }
+
+    /// Returns the name of the kernel module if the BTF is one, see
+    /// `--module`.
+    pub fn module(&self) -> Option<&str> {
+        self.module.as_deref()
+    }
+
+    /// Returns the name of the file that defines the type `id`, i.e., the base
+    /// BTF file for types of the base BTF of a split BTF.
+    pub fn source_of(&self, id: Id) -> &String {
+        match (&self.base, id.origin()) {
+            (Some(base), IdOrigin::Base) => base.name(),
+            _ => self.name(),
+        }
+    }
+
+    /// Returns the raw file of the kernel, i.e., the base BTF file for a kernel
+    /// module, the vmlinux for BTF generated from DWARF, and the BTF file
+    /// otherwise.
+    pub fn kernel_raw(&self) -> Rc<InputData> {
+        match (&self.module, &self.base, &self.kernel) {
+            (Some(_), Some(base), _) => base.raw(),
+            (_, _, Some((_, raw))) => raw.clone(),
+            _ => self.raw(),
+        }
+    }
+
+    /// Returns the name of the file of the kernel, see [`Btf::kernel_raw`].
+    pub fn kernel_name(&self) -> &String {
+        match (&self.module, &self.base, &self.kernel) {
+            (Some(_), Some(base), _) => base.name(),
+            (_, _, Some((name, _))) => name,
+            _ => self.name(),
+        }
+    }
+
     /// Starts at the given node in the type tree and walks up to the root.
+    ///
+    /// A chain that runs into a cycle, which only corrupted BTF has, ends in
+    /// `void` where the cycle closes, with the path up to there kept. So does
+    /// a chain of more than [`Btf::max_type_depth`] nodes where it exceeds the
+    /// limit.
     pub fn resolve_type_chain(&self, tx: TypeEx) -> ResolvedType {
+        if let Some(rt) = self.chains.borrow().get(&tx.id) {
+            return rt.clone();
+        }
+        let id = tx.id;
+        let (rt, _) = self.walk_type_chain(tx);
+        self.chains.borrow_mut().insert(id, rt.clone());
+        rt
+    }
+
+    /// Returns the root node that the typedef `tx` resolves to, and whether
+    /// there are indirections on the way.
+    ///
+    /// Unlike [`Btf::resolve_type_chain`], the chain is only memoized if it was
+    /// cut off, so that its warning is not repeated when it is resolved again.
+    fn resolve_typedef(&self, tx: TypeEx) -> TypedefTarget {
+        if let Some(rt) = self.chains.borrow().get(&tx.id) {
+            return TypedefTarget::from(rt);
+        }
+        let id = tx.id;
+        let (rt, cut_off) = self.walk_type_chain(tx);
+        let target = TypedefTarget::from(&rt);
+        if cut_off {
+            self.chains.borrow_mut().insert(id, rt);
+        }
+        target
+    }
+
+    /// Walks the type chain starting at `tx`, see [`Btf::resolve_type_chain`].
+    ///
+    /// Returns the resolved type and whether the chain was cut off, at a cycle
+    /// or at the depth limit.
+    fn walk_type_chain(&self, tx: TypeEx) -> (ResolvedType, bool) {
         let mut rt = ResolvedType {
             path: ResolutionPath::new(),
             tx,
         };
         rt.path.record_node(&rt.tx);
+        let start = rt.tx.id;
+        let mut visited = HashSet::from([start.id]);
         loop {
             let Some(trait_object) = rt.tx.t.t.as_btf_type() else {
-                return rt;
+                return (rt, false);
             };
+            if visited.len() >= self.max_type_depth {
+                log::warn!(
+                    target: diagnostics::REPAIRED_TYPES,
+                    code = "type_chain_too_deep",
+                    id:% = start,
+                    max_depth = self.max_type_depth;
+                    "Type chain starting at [{}] is longer than {} types, resolving it to void there, see --max-type-depth",
+                    start,
+                    self.max_type_depth
+                );
+                let rt = ResolvedType {
+                    path: rt.path,
+                    tx: TypeEx {
+                        t: btf_rs::Type::Void.into(),
+                        id: self.ids.mint(0),
+                    },
+                };
+                return (rt, true);
+            }
             rt.tx = match (
                 self.btf.resolve_chained_type(trait_object),
                 trait_object.get_type_id(),
             ) {
+                (Ok(_), Ok(id)) if !visited.insert(id) => {
+                    log::warn!(
+                        target: diagnostics::REPAIRED_TYPES,
+                        code = "type_chain_cycle",
+                        id:% = start,
+                        cycle_id:% = self.ids.mint(id);
+                        "Type chain starting at [{}] has a cycle at [{}], resolving it to void",
+                        start,
+                        self.ids.mint(id)
+                    );
+                    let rt = ResolvedType {
+                        path: rt.path,
+                        tx: TypeEx {
+                            t: btf_rs::Type::Void.into(),
+                            id: self.ids.mint(0),
+                        },
+                    };
+                    return (rt, true);
+                }
                 (Ok(t), Ok(id)) => {
                     let tx = TypeEx {
                         t: t.into(),
-                        id: id.into(),
+                        id: self.ids.mint(id),
                     };
+                    if let btf_rs::Type::TypeTag(tt) = &tx.t.t {
+                        log::trace!(
+                            "[{}] skipping type tag {:?}",
+                            tx.id,
+                            self.resolve_name(tt).unwrap_or_default()
+                        );
+                    }
                     rt.path.record_node(&tx);
                     tx
                 }
-                _ => return rt,
+                _ => return (rt, false),
             };
         }
     }
@@ -117,10 +602,11 @@ impl Btf {
     ///
     /// Optionally, uses `typedefs` to derive alternative names for the type.
     /// Only typedefs that reach the type without indirections lead to
-    /// alternative names.
+    /// alternative names. They follow the name of the type itself in ascending
+    /// order of the typedef IDs.
     pub fn get_names_by_id(&self, id: Id, typedefs: Option<&Typedefs>) -> Result<Vec<String>> {
         let mut names = Vec::new();
-        let t = self.btf.resolve_type_by_id(id.into())?;
+        let t = self.get_type_by_id(id)?.t;
 
         if let btf_rs::Type::Void = t {
             log::trace!("[{}] is void", id);
@@ -130,11 +616,11 @@ impl Btf {
         names.push(match self.get_strtab_entry_by_id(id) {
             Ok(name) => {
                 log::trace!("[{}] name strtab: {}", id, &name);
-                name
+                name.to_string()
             }
             Err(_) => {
                 log::trace!("[{}] is anonymous", id);
-                format!("unnamed_{}_{}", t.name(), id)
+                self.unnamed_type_name(&t, id).to_string()
             }
         });
 
@@ -144,7 +630,7 @@ impl Btf {
         };
         log::trace!("[{}] bk typedefs: {:?}", id, tds_bk);
         names.extend(tds_bk.iter().filter_map(|td_bk| {
-            let Some(rt) = typedefs.and_then(|td| td.fw.get(td_bk)) else {
+            let Some(target) = typedefs.and_then(|td| td.fw.get(td_bk)) else {
                 panic!(
                     "{}",
                     format!(
@@ -153,13 +639,13 @@ impl Btf {
                     )
                 );
             };
-            if rt.path.has_indirections() {
+            if target.indirect {
                 log::trace!("[{}] omiting typedef {} due to indirections", id, td_bk);
                 None
             } else {
                 let name = self.get_strtab_entry_by_id(*td_bk).unwrap();
                 log::trace!("[{}] name typedef: {}", id, name);
-                Some(name)
+                Some(name.to_string())
             }
         }));
 
@@ -169,85 +655,510 @@ impl Btf {
     /// Returns the string table entry of the type with `id`.
     ///
     /// Fails if the entry is empty.
-    pub fn get_strtab_entry_by_id(&self, id: Id) -> Result<String> {
-        let t = self.btf.resolve_type_by_id(id.into())?;
-        let t = t
-            .as_btf_type()
-            .context(format!("Type {} has no string table entry.", id))?;
-        let name = self.btf.resolve_name(t)?;
+    pub fn get_strtab_entry_by_id(&self, id: Id) -> Result<Name> {
+        if let Some(name) = self.namespaced.get(&id) {
+            return Ok(name.clone());
+        }
+        let cached = self.strtab_entries.borrow().get(&id).cloned();
+        let entry = match cached {
+            Some(entry) => entry,
+            None => {
+                let t = self.get_type_by_id(id)?.t;
+                let entry = match t.as_btf_type() {
+                    Some(t) => Some(self.resolve_name(t)?),
+                    None => None,
+                };
+                self.strtab_entries.borrow_mut().insert(id, entry.clone());
+                entry
+            }
+        };
+        match entry {
+            None => bail!("Type {} has no string table entry.", id),
+            Some(name) if name.is_empty() => bail!("Type {} has no name.", id),
+            Some(name) => Ok(name),
+        }
+    }
+
+    /// Memoizes `name` as the string table entry of the type with `id`, e.g.,
+    /// from the analysis cache.
+    pub(crate) fn memoize_strtab_entry(&self, id: Id, name: &str) {
+        let name = self.intern(name);
+        self.strtab_entries.borrow_mut().insert(id, Some(name));
+    }
+
+    /// Returns the name of `t` in the string table, which is empty if it has
+    /// none.
+    pub fn resolve_name<T: BtfType + ?Sized>(&self, t: &T) -> Result<Name> {
+        let Ok(offset) = t.get_name_offset() else {
+            return Ok(self.btf.resolve_name(t)?.into());
+        };
+        if let Some(name) = self.names.borrow().strtab.get(&offset) {
+            return Ok(name.clone());
+        }
+        let name = self.intern(&self.btf.resolve_name(t)?);
+        self.names.borrow_mut().strtab.insert(offset, name.clone());
+        Ok(name)
+    }
+
+    /// Returns the shared copy of `name`.
+    pub fn intern(&self, name: &str) -> Name {
+        if let Some(name) = self.names.borrow().all.get(name) {
+            return name.clone();
+        }
+        let name: Name = name.into();
+        self.names.borrow_mut().all.insert(name.clone());
+        name
+    }
+
+    /// Returns the IDs in `range`, which is given in raw, absolute type IDs.
+    ///
+    /// Both ends of the range are respected as given, i.e., `0..n` excludes
+    /// `n`, `0..=n` includes it, and `..` covers all IDs up to and including
+    /// `u32::MAX`. The IDs are not checked for referring to an existing type.
+    pub fn id_range(&self, range: impl RangeBounds<u32>) -> IdRange {
+        self.ids.range(range)
+    }
+
+    /// Returns the number of type IDs, including `void` and, for split BTF,
+    /// the types of the base BTF.
+    ///
+    /// All IDs below this number refer to a type.
+    pub fn nr_types(&self) -> u32 {
+        self.nr_types
+    }
+
+    /// Limits type chains and the nesting of type descriptors to `depth`
+    /// levels, see `--max-type-depth`.
+    ///
+    /// Longer chains, e.g., of typedefs or pointers in generated code, end in
+    /// `void` where they exceed the limit. It must be set before types are
+    /// resolved, as the chains are memoized.
+    pub fn set_max_type_depth(&mut self, depth: usize) {
+        self.max_type_depth = depth;
+        self.chains.get_mut().clear();
+    }
+
+    /// Returns the limit of [`Btf::set_max_type_depth`].
+    pub fn max_type_depth(&self) -> usize {
+        self.max_type_depth
+    }
+
+    /// Returns the number of types whose record could not be parsed, which
+    /// are anonymous and left out of the ISF file.
+    pub fn skipped_records(&self) -> usize {
+        self.skipped.len()
+    }
+
+    /// Returns all types in ascending order of their IDs, starting with `void`.
+    ///
+    /// For split BTF the types of the base BTF come first. Types that cannot
+    /// be looked up are left out with a warning.
+    pub fn types(&self) -> impl Iterator<Item = TypeEx> + '_ {
+        self.id_range(..self.nr_types)
+            .filter_map(|id| match self.get_type_by_id(id) {
+                Ok(t) => Some(TypeEx { t, id }),
+                Err(err) => {
+                    log::warn!(
+                        target: diagnostics::SKIPPED_RECORDS,
+                        code = "unparsable_btf_record",
+                        id:% = id;
+                        "[{}] Skipping BTF type that cannot be looked up: {:#}",
+                        id,
+                        err
+                    );
+                    None
+                }
+            })
+    }
+
+    /// Returns all structs in ascending order of their IDs.
+    pub fn structs(&self) -> impl Iterator<Item = TypeEx> + '_ {
+        self.types().filter(|tx| tx.t.is_struct())
+    }
+
+    /// Returns all variables in ascending order of their IDs.
+    pub fn variables(&self) -> impl Iterator<Item = Declaration> + '_ {
+        self.types().filter_map(|tx| match &tx.t.t {
+            btf_rs::Type::Var(var) => self.declaration(&tx, var, Linkage::of_var(var)),
+            _ => None,
+        })
+    }
+
+    /// Returns all functions in ascending order of their IDs.
+    ///
+    /// The type of a function is its prototype.
+    pub fn functions(&self) -> impl Iterator<Item = Declaration> + '_ {
+        self.types().filter_map(|tx| match &tx.t.t {
+            btf_rs::Type::Func(func) => self.declaration(&tx, func, Linkage::of_func(func)),
+            _ => None,
+        })
+    }
+
+    /// Returns the variables placed in data sections, in ascending order of the
+    /// IDs of the sections.
+    ///
+    /// The sections of a kernel module are skipped, they are not the ones of
+    /// vmlinux that share their names.
+    pub fn section_variables(&self) -> impl Iterator<Item = SectionVariable> + '_ {
+        let module = self.module.is_some();
+        self.types().flat_map(move |tx| {
+            if module && tx.id.origin() == IdOrigin::Split {
+                return Vec::new();
+            }
+            let btf_rs::Type::Datasec(sec) = &tx.t.t else {
+                return Vec::new();
+            };
+            let Ok(section) = self.get_strtab_entry_by_id(tx.id) else {
+                return Vec::new();
+            };
+            sec.variables
+                .iter()
+                .filter_map(|info| {
+                    let id = self.ids.mint(info.get_type_id().ok()?);
+                    let t = self.get_type_by_id(id).ok()?;
+                    let btf_rs::Type::Var(var) = &t.t else {
+                        return None;
+                    };
+                    Some(SectionVariable {
+                        section: section.to_string(),
+                        offset: info.offset(),
+                        var: self.declaration(
+                            &TypeEx { t: t.clone(), id },
+                            var,
+                            Linkage::of_var(var),
+                        )?,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Returns the declaration `tx` of a variable or function, whose type is
+    /// referenced by `decl`.
+    fn declaration(
+        &self,
+        tx: &TypeEx,
+        decl: &dyn BtfType,
+        linkage: Linkage,
+    ) -> Option<Declaration> {
+        // Symbol names are mostly unique, interning them would not pay off.
+        let name = self.btf.resolve_name(tx.t.t.as_btf_type()?).ok()?;
         if name.is_empty() {
-            bail!(format!("Type {} has no name.", id))
+            return None;
+        }
+        let id = self.ids.mint(decl.get_type_id().ok()?);
+        let t = self.get_type_by_id(id).ok()?;
+        Some(Declaration {
+            name,
+            tx: TypeEx { t, id },
+            linkage,
+        })
+    }
+
+    /// Returns all types called `name` in ascending order of their IDs.
+    ///
+    /// Only the names in the string table are considered, not the names that
+    /// are derived from typedefs.
+    pub fn find_by_name(&self, name: &str) -> Vec<TypeEx> {
+        let mut ids = self.get_ids_by_name(name);
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|id| {
+                let t = self.get_type_by_id(id).ok()?;
+                Some(TypeEx { t, id })
+            })
+            .collect()
+    }
+
+    /// Returns the ID for the raw, absolute type ID `id`.
+    ///
+    /// Fails if there is no type with this ID.
+    pub fn id(&self, id: u32) -> Result<Id> {
+        let id = self.ids.mint(id);
+        self.get_type_by_id(id)?;
+        Ok(id)
+    }
+
+    /// Returns the encoding of the type of `m` if `m` is declared without kind
+    /// flag and its type is a narrow integer type.
+    fn member_int_bits(&self, m: &btf_rs::Member) -> Option<IntBits> {
+        if m.bitfield_size().is_some() {
+            return None;
+        }
+        self.narrow_ints.get(&m.get_type_id().ok()?).copied()
+    }
+
+    /// Returns the IDs of all types called `name`.
+    pub fn get_ids_by_name(&self, name: &str) -> Vec<Id> {
+        self.btf
+            .resolve_ids_by_name(name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| self.ids.mint(id))
+            .collect()
+    }
+
+    /// Prefixes the names of the structs, unions, and enums of the split BTF
+    /// that share their name with a definition in the base BTF with `module`,
+    /// see [`names::module_type`].
+    ///
+    /// BTF deduplication leaves such types only if their definitions differ,
+    /// so that the types of the module would otherwise replace or be replaced
+    /// by the ones of vmlinux. References to them are renamed alike.
+    fn namespace_module_types(&mut self, module: &str) {
+        let is_definition = |tx: &TypeEx| (tx.t.is_user() || tx.t.is_enum()) && !tx.t.is_fwd();
+        let (base, split): (Vec<_>, Vec<_>) = self
+            .types()
+            .filter(is_definition)
+            .partition(|tx| tx.id.origin() == IdOrigin::Base);
+        let base_names: HashSet<Name> = base
+            .iter()
+            .filter_map(|tx| self.get_strtab_entry_by_id(tx.id).ok())
+            .collect();
+        let mut namespaced = HashMap::new();
+        for tx in split {
+            let Ok(name) = self.get_strtab_entry_by_id(tx.id) else {
+                continue;
+            };
+            if !base_names.contains(&name) {
+                continue;
+            }
+            let is_taken = |name: &str| !self.get_ids_by_name(name).is_empty();
+            let new = names::module_type(module, &name, is_taken);
+            log::info!("[{}] module type `{}` renamed to `{}`", tx.id, name, new);
+            namespaced.insert(tx.id, self.intern(&new));
+        }
+        self.namespaced = namespaced;
+    }
+
+    /// Sets the byte order of the types to `endian`, see `--endian`, with a
+    /// warning if the magic of the BTF section tells otherwise.
+    pub fn override_endian(&mut self, endian: Endian) {
+        if endian == self.endian {
+            log::debug!("--endian {} agrees with the magic of {}", endian, self.name);
         } else {
-            Ok(name)
+            log::warn!(
+                target: diagnostics::INPUTS,
+                code = "endian_override",
+                endian:% = endian,
+                detected:% = self.endian;
+                "The BTF of {} is {} endian, but the types are {} endian as of --endian",
+                self.name,
+                self.endian,
+                endian
+            );
+        }
+        self.endian = endian;
+    }
+
+    /// Names anonymous types and members like dwarf2json does.
+    ///
+    /// Anonymous structs, unions, and enums are numbered from 1 in the order of
+    /// their IDs, anonymous members are named after their offset.
+    pub fn use_dwarf2json_names(&mut self) {
+        let unnamed = self
+            .types()
+            .filter(|tx| tx.t.is_user() || tx.t.is_enum())
+            .filter(|tx| self.get_strtab_entry_by_id(tx.id).is_err())
+            .enumerate()
+            .map(|(idx, tx)| (tx.id, idx + 1))
+            .collect();
+        self.dwarf2json_unnamed = Some(unnamed);
+    }
+
+    /// Names anonymous types after a hash of their content.
+    ///
+    /// Unlike IDs, the content of a type is stable across builds of the same
+    /// source. Anonymous types with the same hash get a numeric suffix in the
+    /// order of their IDs.
+    pub fn use_stable_anon_names(&mut self) {
+        // Looking up names one by one is too slow for this many placeholders.
+        let mut taken: HashSet<String> = self
+            .types()
+            .filter_map(|tx| self.get_strtab_entry_by_id(tx.id).ok())
+            .filter(|name| name.starts_with("unnamed_"))
+            .map(|name| name.to_string())
+            .collect();
+        let mut unnamed = HashMap::new();
+        let anonymous = self
+            .types()
+            .filter(|tx| tx.t.is_user() || tx.t.is_enum())
+            .filter(|tx| self.get_strtab_entry_by_id(tx.id).is_err());
+        for tx in anonymous {
+            let hash = format!("{:x}", Sha256::digest(self.describe_content(&tx, 0)));
+            let name = names::stable_unnamed_type(tx.t.t.name(), &hash[..6], |n| taken.contains(n));
+            taken.insert(name.clone());
+            unnamed.insert(tx.id, name);
         }
+        self.stable_unnamed = Some(unnamed);
+    }
+
+    /// Describes the content of the struct, union, or enum `tx` without
+    /// referring to any IDs.
+    ///
+    /// Anonymous types that are referenced without a typedef are described by
+    /// their content, up to a nesting `depth` of 8.
+    fn describe_content(&self, tx: &TypeEx, depth: usize) -> String {
+        let mut descr = format!("{} {}", tx.t.t.name(), tx.t.size().unwrap_or(0));
+        if let Some(e) = tx.t.as_enum() {
+            for (name, val) in e.variants(self, tx.id).unwrap_or_default() {
+                descr += &format!(" {}={}", name, i128::from(val));
+            }
+        }
+        for m in
+            tx.t.as_has_members()
+                .map(|t| t.members(self))
+                .unwrap_or_default()
+        {
+            let Ok(tx) = m.get_tx(self) else {
+                continue;
+            };
+            let rt = self.resolve_type_chain(tx);
+            descr += &format!(" {}@{}:", m.name(self), m.bit_offset());
+            for node in rt.path.0.iter() {
+                match node {
+                    ResolutionPathNode::Pointer => descr += "*",
+                    ResolutionPathNode::Array(len) => descr += &format!("[{}]", len),
+                    ResolutionPathNode::Typedef(_) => (),
+                }
+            }
+            let anonymous = (rt.tx.t.is_user() || rt.tx.t.is_enum())
+                && self.get_strtab_entry_by_id(rt.tx.id).is_err()
+                && rt.path.naming_typedef().is_none();
+            match anonymous && depth < 8 {
+                true => descr += &format!("{{{}}}", self.describe_content(&rt.tx, depth + 1)),
+                false => descr += &rt.name(self),
+            }
+        }
+        descr
+    }
+
+    /// Returns the placeholder name of the anonymous type `t` with `id`.
+    fn unnamed_type_name(&self, t: &btf_rs::Type, id: Id) -> Name {
+        if let Some(name) = self.unnamed.borrow().get(&id) {
+            return name.clone();
+        }
+        let is_taken = |name: &str| !self.get_ids_by_name(name).is_empty();
+        let name = if let Some(name) = self.stable_unnamed.as_ref().and_then(|n| n.get(&id)) {
+            self.intern(name)
+        } else {
+            self.intern(
+                &match self.dwarf2json_unnamed.as_ref().and_then(|n| n.get(&id)) {
+                    Some(n) => names::dwarf2json_unnamed_type(*n, is_taken),
+                    None => names::unnamed_type(t.name(), id, is_taken),
+                },
+            )
+        };
+        self.unnamed.borrow_mut().insert(id, name.clone());
+        name
     }
 
     /// Returns the type with the given `id`.
+    ///
+    /// Fails if `id` was obtained from a different BTF object.
     pub fn get_type_by_id(&self, id: Id) -> Result<Type> {
+        if id.owner != self.ids.owner {
+            bail!(
+                "Type ID {} ({:?}) belongs to a different BTF object",
+                id,
+                id.origin
+            );
+        }
         Ok(Type {
             t: self.btf.resolve_type_by_id(id.into())?,
         })
     }
 
+    /// Returns the prototype of the function prototype type `t`.
+    pub fn get_prototype(&self, t: &Type) -> Result<Prototype> {
+        let btf_rs::Type::FuncProto(proto) = &t.t else {
+            bail!("Type {:?} is not a function prototype", t)
+        };
+        let tx = |id: u32| -> Result<TypeEx> {
+            let id = self.ids.mint(id);
+            Ok(TypeEx {
+                t: self.get_type_by_id(id)?,
+                id,
+            })
+        };
+
+        Ok(Prototype {
+            return_type: tx(proto.return_type_id())?,
+            parameters: proto
+                .parameters
+                .iter()
+                .filter(|p| !p.is_variadic())
+                .map(|p| tx(p.get_type_id()?))
+                .collect::<Result<_>>()?,
+            variadic: proto.parameters.iter().any(|p| p.is_variadic()),
+        })
+    }
+
     /// Returns a partitioning of the types into the categories that Volatility
     /// distinguishes between as well as a processed view of all typedefs.
     ///
     /// Volatility distinguishes between user types, enum types, and base types.
     pub fn gen_vol_id_sets(&self) -> Result<VolIdSets> {
+        self.scan_types(&progress::Silent)
+    }
+
+    /// Same as [`Btf::gen_vol_id_sets`], reporting each type to `progress`.
+    pub(crate) fn scan_types(&self, progress: &dyn Progress) -> Result<VolIdSets> {
+        progress.start(Phase::ScanTypes, Some(self.nr_types() as usize));
         let mut basic_ids: BTreeSet<Id> = BTreeSet::new();
         let mut enum_ids: BTreeSet<Id> = BTreeSet::new();
         let mut user_ids: BTreeSet<Id> = BTreeSet::new();
+        let mut fwd_ids: BTreeSet<Id> = BTreeSet::new();
         let mut typedefs_bk: HashMap<Id, Vec<Id>> = HashMap::new();
-        let mut typedefs_fw: HashMap<Id, ResolvedType> = HashMap::new();
-
-        for id in Id::range(Id(0), Self::MAX_BTF_ID) {
-            match self.get_type_by_id(id) {
-                Ok(t) => {
-                    if t.is_base() {
-                        log::trace!("[{}] is base", id);
-                        basic_ids.insert(id);
-                    } else if t.is_enum() {
-                        log::trace!("[{}] is enum", id);
-                        enum_ids.insert(id);
-                    } else if t.is_user() {
-                        log::trace!("[{}] is user", id);
-                        user_ids.insert(id);
-                    } else if t.is_typedef() {
-                        let rt = self.resolve_type_chain(TypeEx { t, id });
-                        log::trace!(
-                            "[{}] is typedef: path {:?}, target {}",
-                            id,
-                            rt.path,
-                            rt.tx.id
-                        );
-                        if let std::collections::hash_map::Entry::Vacant(e) =
-                            typedefs_bk.entry(rt.tx.id)
-                        {
-                            e.insert(vec![id]);
-                        } else {
-                            let v = typedefs_bk.get_mut(&rt.tx.id).unwrap();
-                            v.push(id);
-                        }
-                        typedefs_fw.insert(id, rt);
-                    }
-                }
-                Err(_) => {
-                    log::debug!("Section defines {} types", u32::from(id) - 1);
-                    break;
+        let mut typedefs_fw: HashMap<Id, TypedefTarget> = HashMap::new();
+
+        for (n, TypeEx { t, id }) in self.types().enumerate() {
+            progress.advance(Phase::ScanTypes, n);
+            if self.skipped.contains(&id) {
+                log::trace!("[{}] is skipped", id);
+            } else if t.is_base() {
+                log::trace!("[{}] is base", id);
+                basic_ids.insert(id);
+            } else if t.is_enum() {
+                log::trace!("[{}] is enum", id);
+                enum_ids.insert(id);
+            } else if t.is_user() {
+                log::trace!("[{}] is user", id);
+                user_ids.insert(id);
+            } else if t.is_fwd() {
+                log::trace!("[{}] is fwd", id);
+                fwd_ids.insert(id);
+            } else if t.is_typedef() {
+                let target = self.resolve_typedef(TypeEx { t, id });
+                log::trace!(
+                    "[{}] is typedef: target {}, indirect {}",
+                    id,
+                    target.id,
+                    target.indirect
+                );
+                if let std::collections::hash_map::Entry::Vacant(e) = typedefs_bk.entry(target.id) {
+                    e.insert(vec![id]);
+                } else {
+                    let v = typedefs_bk.get_mut(&target.id).unwrap();
+                    v.push(id);
                 }
+                typedefs_fw.insert(id, target);
             }
         }
         log::debug!(
-            "ID sets: base {}, enum {}, user {}",
+            "ID sets: base {}, enum {}, user {}, fwd {}",
             basic_ids.len(),
             enum_ids.len(),
-            user_ids.len()
+            user_ids.len(),
+            fwd_ids.len()
         );
+        progress.finish(Phase::ScanTypes, self.nr_types() as usize);
 
         Ok((
             user_ids,
             enum_ids,
             basic_ids,
+            fwd_ids,
             Typedefs {
                 fw: typedefs_fw,
                 bk: typedefs_bk,
@@ -354,13 +1265,40 @@ impl Type {
     }
 }
 
+/// Return type and parameter types of a function prototype.
+pub struct Prototype {
+    pub return_type: TypeEx,
+    pub parameters: Vec<TypeEx>,
+    /// Whether the function takes a variable number of arguments.
+    pub variadic: bool,
+}
+
+/// Root node that a typedef resolves to.
+///
+/// The full [`ResolvedType`] is only computed, by [`Btf::resolve_type_chain`],
+/// for the typedefs that the types of the ISF file reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypedefTarget {
+    pub id: Id,
+    /// Whether there are pointers or arrays on the path to the root.
+    pub indirect: bool,
+}
+
+impl From<&ResolvedType> for TypedefTarget {
+    fn from(rt: &ResolvedType) -> Self {
+        Self {
+            id: rt.tx.id,
+            indirect: rt.path.has_indirections(),
+        }
+    }
+}
+
 /// Resolved typedefs.
 pub struct Typedefs {
-    /// Map from typedef nodes to the root nodes that they resolve to. Including
-    /// the resolution path.
-    pub fw: HashMap<Id, ResolvedType>,
-    /// Map from root nodes to the typedef nodes that resolve to them. Without
-    /// resolution path.
+    /// Map from typedef nodes to the root nodes that they resolve to.
+    pub fw: HashMap<Id, TypedefTarget>,
+    /// Map from root nodes to the typedef nodes that resolve to them, in
+    /// ascending order of their IDs. Without resolution path.
     pub bk: HashMap<Id, Vec<Id>>,
 }
 
@@ -376,116 +1314,242 @@ impl From<EnumValue> for i128 {
 /// Extension trait for uniform handling of enums of different sizes.
 pub trait Enum {
     /// Returns a map from the name of an enum variant to the corresponding
-    /// discriminant,
-    fn variants(&self, btf: &Btf) -> HashMap<String, EnumValue>;
+    /// discriminant.
+    ///
+    /// Variants without a name get a placeholder name based on `id`, the ID of
+    /// the enum.
+    fn variants(&self, btf: &Btf, id: Id) -> Result<BTreeMap<String, EnumValue>>;
 }
 
+// `btf_rs` returns the raw bits of the discriminants, the kind flag of the enum
+// determines whether they are sign-extended.
 impl Enum for btf_rs::Enum {
-    fn variants(&self, btf: &Btf) -> HashMap<String, EnumValue> {
-        self.members
-            .iter()
-            .map(|m| {
-                (
-                    btf.btf.resolve_name(m).expect("Unnamed enum member"),
-                    EnumValue(m.val() as i128),
-                )
-            })
-            .collect()
+    fn variants(&self, btf: &Btf, id: Id) -> Result<BTreeMap<String, EnumValue>> {
+        let signed = self.is_signed();
+        named_variants(
+            btf,
+            id,
+            self.members.iter().map(|m| match signed {
+                true => (m, m.val() as i32 as i128),
+                false => (m, m.val() as i128),
+            }),
+        )
     }
 }
 
 impl Enum for btf_rs::Enum64 {
-    fn variants(&self, btf: &Btf) -> HashMap<String, EnumValue> {
-        self.members
-            .iter()
-            .map(|m| {
-                (
-                    btf.btf.resolve_name(m).expect("Unnamed enum member"),
-                    EnumValue(m.val() as i128),
-                )
-            })
-            .collect()
+    fn variants(&self, btf: &Btf, id: Id) -> Result<BTreeMap<String, EnumValue>> {
+        let signed = self.is_signed();
+        named_variants(
+            btf,
+            id,
+            self.members.iter().map(|m| match signed {
+                true => (m, m.val() as i64 as i128),
+                false => (m, m.val() as i128),
+            }),
+        )
     }
 }
 
+/// Resolves the names of the enum variants `members` of the enum with `id`.
+fn named_variants<'a, M: BtfType + 'a>(
+    btf: &Btf,
+    id: Id,
+    members: impl Iterator<Item = (&'a M, i128)>,
+) -> Result<BTreeMap<String, EnumValue>> {
+    let members = members
+        .map(|(m, val)| Ok((btf.resolve_name(m)?, val)))
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("Unable to resolve names of enum constants of {}", id))?;
+
+    Ok(members
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, val))| {
+            let name = if name.is_empty() {
+                let placeholder = names::unnamed_constant(id, idx, |name| {
+                    members.iter().any(|(other, _)| **other == *name)
+                });
+                log::warn!(
+                    target: diagnostics::REPAIRED_TYPES,
+                    code = "unnamed_enum_constant",
+                    id:% = id,
+                    index = idx,
+                    placeholder:% = placeholder;
+                    "[{}] enum constant {} has no name, using {}",
+                    id,
+                    idx,
+                    placeholder
+                );
+                placeholder
+            } else {
+                name.to_string()
+            };
+            (name, EnumValue(*val))
+        })
+        .collect())
+}
+
 /// Extension trait for uniform handling of types that have members (structs and
 /// unions).
 pub trait HasMembers {
     /// Returns the members of this type.
-    fn members(&self) -> Vec<Member>;
+    fn members(&self, btf: &Btf) -> Vec<Member<'_>>;
 }
 
 impl HasMembers for btf_rs::Struct {
-    fn members(&self) -> Vec<Member> {
+    fn members(&self, btf: &Btf) -> Vec<Member<'_>> {
         self.members
             .iter()
             .enumerate()
-            .map(|(idx, m)| Member::from((idx as u64, m)))
+            .map(|(idx, m)| {
+                Member::from((idx as u64, m, &self.members[..], btf.member_int_bits(m)))
+            })
             .collect()
     }
 }
 
 /// Member of a struct or union type.
+///
+/// Bitfields are encoded in one of two ways, depending on the kind flag of the
+/// struct. With the flag, the offset of the member holds the size of the
+/// bitfield, which `btf_rs` decodes. Without the flag, the type of the member
+/// is an integer type that only uses some of its bits, see [`IntBits`].
 pub struct Member<'a> {
     m: &'a btf_rs::Member,
     idx: u64,
+    /// All members of the type, including this one.
+    siblings: &'a [btf_rs::Member],
+    /// Encoding of the type of this member if it is a narrow integer type and
+    /// the struct has no kind flag.
+    int_bits: Option<IntBits>,
 }
 
-impl<'a> From<(u64, &'a btf_rs::Member)> for Member<'a> {
-    fn from((idx, m): (u64, &'a btf_rs::Member)) -> Self {
-        Self { m, idx }
+type MemberParts<'a> = (
+    u64,
+    &'a btf_rs::Member,
+    &'a [btf_rs::Member],
+    Option<IntBits>,
+);
+
+impl<'a> From<MemberParts<'a>> for Member<'a> {
+    fn from((idx, m, siblings, int_bits): MemberParts<'a>) -> Self {
+        Self {
+            m,
+            idx,
+            siblings,
+            int_bits,
+        }
     }
 }
 
 impl Member<'_> {
     /// Returns the name of this member.
-    pub fn name(&self, btf: &Btf) -> String {
-        match btf.btf.resolve_name(self.m) {
-            Ok(name) if !name.is_empty() => name,
-            _ => format!("unnamed_member_{}", self.idx),
+    pub fn name(&self, btf: &Btf) -> Name {
+        let name = |m: &btf_rs::Member| btf.resolve_name(m).unwrap_or_else(|_| btf.intern(""));
+        let own = name(self.m);
+        if !own.is_empty() {
+            return own;
+        }
+        let is_taken = |candidate: &str| self.siblings.iter().any(|s| *name(s) == *candidate);
+        if btf.dwarf2json_unnamed.is_none() {
+            return btf.intern(&names::unnamed_member(self.idx, is_taken));
         }
+        let byte_offset = |m: &btf_rs::Member| m.bit_offset() >> 3;
+        let nth = self.siblings[..self.idx as usize]
+            .iter()
+            .filter(|s| name(s).is_empty() && byte_offset(s) == byte_offset(self.m))
+            .count();
+        btf.intern(&names::dwarf2json_unnamed_member(
+            self.byte_offset(),
+            nth,
+            is_taken,
+        ))
     }
 
     /// Returns true iff the member is a bitfield.
     pub fn is_bitfield(&self) -> bool {
-        self.m.bitfield_size().is_some_and(|s| s != 0)
+        match self.m.bitfield_size() {
+            Some(size) => size != 0,
+            None => self.int_bits.is_some(),
+        }
     }
 
-    /// Returns true iff the member is unnamed.
-    pub fn is_anon(&self) -> bool {
-        self.m
-            .get_name_offset()
-            .expect("BUG: member without name offset")
-            == 0
+    /// Returns the index of this member within its type.
+    pub fn index(&self) -> u64 {
+        self.idx
+    }
+
+    /// Returns true iff the member is unnamed, or `None` if it has no name
+    /// offset, which only happens with corrupted BTF.
+    pub fn is_anon(&self) -> Option<bool> {
+        self.m.get_name_offset().ok().map(|off| off == 0)
     }
 
     /// Returns the extended type of this member.
-    pub fn get_tx(&self, btf: &Btf) -> TypeEx {
-        let id = self
-            .m
-            .get_type_id()
-            .expect("BUG: member without type")
-            .into();
-        TypeEx {
-            t: btf.get_type_by_id(id).expect("BUG: member without type"),
-            id,
-        }
+    ///
+    /// Fails if the member has no type or its type ID is out of range, which
+    /// only happens with corrupted BTF.
+    pub fn get_tx(&self, btf: &Btf) -> Result<TypeEx> {
+        let id = self.m.get_type_id().context("Member without type")?;
+        let id = btf.ids.mint(id);
+        let t = btf
+            .get_type_by_id(id)
+            .with_context(|| format!("Member with invalid type ID {}", id))?;
+        Ok(TypeEx { t, id })
+    }
+
+    /// Describes the layout and the naming of the member, for `--trace-type`.
+    pub fn describe_layout(&self, btf: &Btf) -> String {
+        let naming = match self.is_anon() {
+            Some(false) => "own name",
+            _ if btf.dwarf2json_unnamed.is_some() => "unnamed, dwarf2json scheme",
+            _ => "unnamed, by index",
+        };
+        format!(
+            "raw bit offset {}, bitfield size {:?}, int bits {:?}, name `{}` ({})",
+            self.m.bit_offset(),
+            self.m.bitfield_size(),
+            self.int_bits,
+            self.name(btf),
+            naming
+        )
+    }
+
+    /// Returns the offset of the member in bits.
+    fn bit_offset(&self) -> u64 {
+        self.m.bit_offset() as u64 + self.int_bits.map_or(0, |int| int.offset as u64)
     }
 
     /// Returns the offset of the member in bytes.
     pub fn byte_offset(&self) -> u64 {
-        (self.m.bit_offset() >> 3) as u64
+        self.bit_offset() >> 3
     }
 
     /// Returns extra information about bitfield members.
     pub fn bitfield_info(&self) -> Option<BitfieldInfo> {
+        let length = match self.m.bitfield_size() {
+            Some(size) => size,
+            None => self.int_bits?.bits,
+        };
         Some(BitfieldInfo {
-            position: (self.m.bit_offset() & 0x07) as u8,
-            length: self.m.bitfield_size().map(|x| x as u8)?,
+            position: (self.bit_offset() & 0x07) as u8,
+            length: length as u8,
         })
     }
 }
 
+/// Encoding of an integer type that does not use all bits of its size.
+///
+/// Structs without kind flag declare bitfield members with such types.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IntBits {
+    /// Offset of the first used bit.
+    pub offset: u32,
+    /// Number of used bits.
+    pub bits: u32,
+}
+
 /// Information about a bitfield member.
 pub struct BitfieldInfo {
     /// Offset of the first bit belonging to this bitfield into the byte where
@@ -495,38 +1559,147 @@ pub struct BitfieldInfo {
     pub length: u8,
 }
 
-/// Representation of a BTF ID.
+impl BitfieldInfo {
+    /// Returns the position of the bitfield relative to the least significant
+    /// bit of a storage unit of `unit_size` bytes that begins at the byte where
+    /// the bitfield begins.
+    ///
+    /// BTF counts bit offsets from the least significant bit on little endian
+    /// targets and from the most significant bit on big endian targets.
+    pub fn lsb_position(&self, endian: Endian, unit_size: usize) -> u8 {
+        match endian {
+            Endian::Little => self.position,
+            Endian::Big => {
+                let unit_bits = 8 * unit_size as u32;
+                let end = self.position as u32 + self.length as u32;
+                if end > unit_bits {
+                    log::warn!(
+                        target: diagnostics::INVALID_BITFIELDS,
+                        code = "bitfield_exceeds_storage",
+                        bits = self.length,
+                        position = self.position,
+                        unit_bits = unit_bits;
+                        "Bitfield of {} bits at bit {} exceeds its {} bit storage unit",
+                        self.length,
+                        self.position,
+                        unit_bits
+                    );
+                }
+                unit_bits.saturating_sub(end) as u8
+            }
+        }
+    }
+}
+
+/// BTF object that a type ID originates from.
+///
+/// Split BTF (e.g. of kernel modules) continues the ID space of its base BTF.
+/// IDs below the number of base types refer to base types, all others to split
+/// types.
 #[derive(Copy, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Clone)]
-pub struct Id(u32);
+pub enum IdOrigin {
+    Base,
+    Split,
+}
 
-impl fmt::Display for Id {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+/// ID space of a [`Btf`].
+///
+/// Used to mint IDs that are tagged with the [`Btf`] they belong to.
+#[derive(Copy, Clone, Debug)]
+struct IdSpace {
+    /// Unique tag of the owning [`Btf`].
+    owner: u32,
+    /// First ID that belongs to the split BTF, if any.
+    split_start: Option<u32>,
+}
+
+impl IdSpace {
+    fn new(split_start: Option<u32>) -> Self {
+        static NEXT_OWNER: AtomicU32 = AtomicU32::new(0);
+
+        Self {
+            owner: NEXT_OWNER.fetch_add(1, AtomicOrdering::Relaxed),
+            split_start,
+        }
+    }
+
+    /// Tags the raw, absolute `id` with its owner and origin.
+    fn mint(&self, id: u32) -> Id {
+        let origin = match self.split_start {
+            Some(start) if id >= start => IdOrigin::Split,
+            _ => IdOrigin::Base,
+        };
+        Id {
+            owner: self.owner,
+            id,
+            origin,
+        }
     }
+
+    /// Returns the IDs in `range`.
+    fn range(&self, range: impl RangeBounds<u32>) -> IdRange {
+        let start = match range.start_bound() {
+            Bound::Included(start) => Some(*start),
+            Bound::Excluded(start) => start.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let last = match range.end_bound() {
+            Bound::Included(end) => Some(*end),
+            Bound::Excluded(end) => end.checked_sub(1),
+            Bound::Unbounded => Some(u32::MAX),
+        };
+        let (next, last) = match (start, last) {
+            (Some(start), Some(last)) if start <= last => (Some(start), last),
+            _ => (None, 0),
+        };
+        IdRange {
+            space: *self,
+            last,
+            next,
+        }
+    }
+}
+
+/// Representation of a BTF ID.
+///
+/// IDs are absolute, i.e., IDs of split BTF types continue after the IDs of the
+/// base types, and can only be used with the [`Btf`] that they were obtained
+/// from.
+#[derive(Copy, Ord, PartialOrd, Hash, Eq, PartialEq, Debug, Clone)]
+pub struct Id {
+    owner: u32,
+    id: u32,
+    origin: IdOrigin,
 }
 
-impl From<u32> for Id {
-    fn from(id: u32) -> Self {
-        Self(id)
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
     }
 }
 
 impl From<Id> for u32 {
     fn from(id: Id) -> u32 {
-        id.0
+        id.id
     }
 }
 
 impl Id {
-    fn range(start: Id, end: Id) -> IdRange {
-        let next = if start < end { Some(start) } else { None };
-        IdRange { end, next }
+    /// Returns the BTF object that this ID originates from.
+    pub fn origin(&self) -> IdOrigin {
+        self.origin
     }
 }
 
-struct IdRange {
-    end: Id,
-    next: Option<Id>,
+/// Iterator over a range of IDs of a [`Btf`], in ascending order.
+///
+/// Created by [`Btf::id_range`]. The range may include IDs that do not refer to
+/// a type.
+pub struct IdRange {
+    space: IdSpace,
+    /// Last ID in the range, inclusive.
+    last: u32,
+    next: Option<u32>,
 }
 
 impl Iterator for IdRange {
@@ -534,22 +1707,22 @@ impl Iterator for IdRange {
 
     fn next(&mut self) -> Option<Self::Item> {
         let next = self.next.take();
-        if let Some(id) = &next {
-            let next = Id(id.0 + 1);
-
-            self.next = if next < self.end { Some(next) } else { None };
+        if let Some(id) = next {
+            self.next = if id < self.last { Some(id + 1) } else { None };
         }
-        next
+        next.map(|id| self.space.mint(id))
     }
 }
 
 /// A _relevant_ node that may be encountered on the way to the base node.
 ///
-/// The qualifier nodes `restrict`, `volatile`, and `const` are not considered
-/// relevant for profile generation.
-#[derive(Debug)]
+/// The qualifier nodes `restrict`, `volatile`, and `const`, as well as type tags
+/// like `__user` or `__rcu`, are not considered relevant for profile generation.
+#[derive(Debug, Clone)]
 pub enum ResolutionPathNode {
     Pointer,
+    /// Array with the given number of elements, which is zero for flexible
+    /// array members (`arr[]`) and GNU zero-length arrays (`arr[0]`).
     Array(u64),
     Typedef(Id),
 }
@@ -564,7 +1737,7 @@ impl ResolutionPathNode {
 }
 
 /// A path through the type tree that ends at a root node.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResolutionPath(VecDeque<ResolutionPathNode>);
 
 impl ResolutionPath {
@@ -578,6 +1751,9 @@ impl ResolutionPath {
     }
 
     /// Adds a node to the end of the resolution path.
+    ///
+    /// Every array on the chain is a node of its own, i.e., the dimensions of
+    /// a multi-dimensional array are recorded from the outermost inwards.
     fn record_node(&mut self, tx: &TypeEx) {
         match &tx.t.t {
             btf_rs::Type::Array(arr) => self
@@ -585,6 +1761,10 @@ impl ResolutionPath {
                 .push_back(ResolutionPathNode::Array(arr.len() as u64)),
             btf_rs::Type::Ptr(_) => self.0.push_back(ResolutionPathNode::Pointer),
             btf_rs::Type::Typedef(_) => self.0.push_back(ResolutionPathNode::Typedef(tx.id)),
+            btf_rs::Type::Const(_)
+            | btf_rs::Type::Volatile(_)
+            | btf_rs::Type::Restrict(_)
+            | btf_rs::Type::TypeTag(_) => (),
             _ => (),
         }
     }
@@ -610,6 +1790,58 @@ impl ResolutionPath {
     }
 }
 
+/// Declaration of a variable, e.g., a per-CPU variable of the kernel, or of a
+/// function.
+pub struct Declaration {
+    pub name: String,
+    /// Type of the variable or function.
+    pub tx: TypeEx,
+    /// Linkage as recorded in BTF. pahole records all functions as static.
+    pub linkage: Linkage,
+}
+
+/// Linkage of a variable or function in BTF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Linkage {
+    /// Local to its compilation unit, i.e., `static`.
+    Static,
+    /// Defined and visible outside of its compilation unit.
+    Global,
+    /// Defined in another compilation unit, i.e., `extern`.
+    Extern,
+}
+
+impl Linkage {
+    fn of_var(var: &btf_rs::Var) -> Self {
+        if var.is_static() {
+            Linkage::Static
+        } else if var.is_global() {
+            Linkage::Global
+        } else {
+            Linkage::Extern
+        }
+    }
+
+    fn of_func(func: &btf_rs::Func) -> Self {
+        if func.is_static() {
+            Linkage::Static
+        } else if func.is_global() {
+            Linkage::Global
+        } else {
+            Linkage::Extern
+        }
+    }
+}
+
+/// A variable placed in a data section, e.g., `.data..percpu`.
+pub struct SectionVariable {
+    /// Name of the section.
+    pub section: String,
+    /// Offset of the variable in the section.
+    pub offset: u32,
+    pub var: Declaration,
+}
+
 /// A BTF type together with its ID.
 #[derive(Debug, Clone)]
 pub struct TypeEx {
@@ -618,7 +1850,7 @@ pub struct TypeEx {
 }
 
 /// An extended BTF type together with its path to the root.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResolvedType {
     pub path: ResolutionPath,
     pub tx: TypeEx,
@@ -629,36 +1861,657 @@ impl ResolvedType {
     ///
     /// For named types it returns the name. For unnamed types it tries to find
     /// a typedef and then falls back to a unique unnamed naming scheme.
-    pub fn name(&self, btf: &Btf) -> String {
+    pub fn name(&self, btf: &Btf) -> Name {
         if let btf_rs::Type::FuncProto(_) = self.tx.t.t {
-            String::from("function")
+            btf.intern("function")
         } else if let btf_rs::Type::Void = self.tx.t.t {
-            String::from("void")
+            btf.intern("void")
         } else if let Ok(name) = btf.get_strtab_entry_by_id(self.tx.id) {
             name
         } else if let Some(naming_typedef) = self.path.naming_typedef() {
             btf.get_strtab_entry_by_id(naming_typedef)
                 .expect("BUG: naming typedef without name")
         } else {
-            format!("unnamed_{}_{}", self.tx.t.t.name(), self.tx.id)
+            btf.unnamed_type_name(&self.tx.t.t, self.tx.id)
+        }
+    }
+
+    /// Describes where [`ResolvedType::name`] takes the name from, for
+    /// `--trace-type`.
+    pub fn name_source(&self, btf: &Btf) -> String {
+        match self.tx.t.t {
+            btf_rs::Type::FuncProto(_) => "function prototype".to_owned(),
+            btf_rs::Type::Void => "void".to_owned(),
+            _ if btf.get_strtab_entry_by_id(self.tx.id).is_ok() => "own name".to_owned(),
+            _ => match self.path.naming_typedef() {
+                Some(id) => format!("typedef [{}]", id),
+                None => "unnamed type".to_owned(),
+            },
         }
     }
 }
 
-/// Extracts BTF section from kernel binaries and determines endianness.
-fn get_btf_section(mmap: &Mmap) -> Result<(Endian, &[u8])> {
-    if mmap[0..2] == BTF_MAGIC_LE {
-        log::debug!("Got stand alone .BTF section, little endian");
-        Ok((Endian::Little, mmap))
-    } else if mmap[0..2] == BTF_MAGIC_BE {
-        log::debug!("Got stand alone .BTF section, big endian");
-        Ok((Endian::Big, mmap))
-    } else if let Ok(endian) = elf::is_elf(mmap) {
-        elf::extract_btfsec(mmap).map(|sec| (endian, sec))
+/// Finds the BTF section of the kernel in a memory image.
+///
+/// For ELF vmcores only the loadable segments are searched, for LiME images
+/// only the ranges, unless the image has the `format` raw. Of all candidates
+/// whose record lengths can be decoded, the one with the largest type section
+/// wins. Records that cannot be parsed are skipped later on, see
+/// [`Btf::from_section`].
+fn find_btf_in_image(raw: &[u8], format: ImageFormat) -> Result<(BtfHeader, Range<usize>)> {
+    let detect = format == ImageFormat::Auto;
+    let mut lime = None;
+    let candidates = if detect && (vmcore::is_compressed_kdump(raw) || elf::is_core(raw)) {
+        let vmcore = Vmcore::parse(raw)?;
+        log::info!("Memory image is an ELF vmcore, searching its loadable segments");
+        image::btf_candidates_in(raw, &vmcore.segments())
+    } else if detect && lime::is_lime(raw) {
+        log::info!("Memory image is a LiME image, searching its ranges");
+        image::btf_candidates_in(raw, &lime.insert(Lime::parse(raw)?).segments())
     } else {
-        bail!(
-            "Provided BTF file neither .BTF section nor ELF: {:x}",
-            mmap[0]
-        )
+        image::btf_candidates(raw)
+    };
+
+    let mut best: Option<(BtfHeader, Range<usize>)> = None;
+    for (hdr, range) in candidates {
+        if let Err(err) = hdr.count_types(&raw[range.clone()]) {
+            log::debug!(
+                "Candidate BTF section at {:#x} does not parse: {}",
+                range.start,
+                err
+            );
+            continue;
+        }
+        log::info!(
+            "Found BTF section at {:#x}: {} bytes of types",
+            range.start,
+            hdr.type_len
+        );
+        if best.as_ref().is_none_or(|(b, _)| hdr.type_len > b.type_len) {
+            best = Some((hdr, range));
+        }
+    }
+
+    let Some((hdr, range)) = best else {
+        bail!("No BTF section found")
+    };
+    if let Some(paddr) = lime.and_then(|lime| lime.phys_addr(range.start)) {
+        log::debug!("BTF section is at physical address {:#x}", paddr);
+    }
+    log::debug!(
+        "Using BTF section at {:#x}, {} endian",
+        range.start,
+        if hdr.endian == Endian::Big {
+            "big"
+        } else {
+            "little"
+        }
+    );
+    Ok((hdr, range))
+}
+
+/// Returns the name of the kernel module in the file called `file_name`, i.e.,
+/// the file name without extensions and with dashes replaced by underscores,
+/// like the kernel does.
+fn module_name(file_name: &str) -> String {
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    stem.replace('-', "_")
+}
+
+/// Returns the location of `sub` within `outer`.
+fn subslice_range(outer: &[u8], sub: &[u8]) -> Range<usize> {
+    let start = sub.as_ptr() as usize - outer.as_ptr() as usize;
+    start..start + sub.len()
+}
+
+/// Loads the BTF file at `path`.
+///
+/// Compressed kernel images are decompressed into memory.
+pub(crate) fn load_btf_file(path: &Path) -> Result<InputData> {
+    unpack_btf_file(InputData::load(path)?, &path.display().to_string())
+}
+
+/// Decompresses the BTF file `raw` named `name` if it is a compressed BTF
+/// section, ELF file, or kernel image.
+fn unpack_btf_file(raw: InputData, name: &str) -> Result<InputData> {
+    let is_btf_or_elf = |data: &[u8]| {
+        data.starts_with(&BTF_MAGIC_LE)
+            || data.starts_with(&BTF_MAGIC_BE)
+            || elf::is_elf(data).is_ok()
+            || decompress::is_arm64_image(data)
+    };
+    if is_btf_or_elf(&raw) {
+        return Ok(raw);
+    }
+    // The whole file is one stream, e.g., `.BTF.gz`, or `vmlinux.xz`.
+    if let Some(buf) = decompress::decompress_stream(&raw) {
+        let buf = buf.with_context(|| format!("failed to decompress --btf input {}", name))?;
+        if is_btf_or_elf(&buf) {
+            log::info!("Decompressed {}: {} bytes", name, buf.len());
+            return Ok(InputData::from(buf));
+        }
+        log::debug!("Decompressed {} is neither BTF nor ELF", name);
+    }
+    match decompress::decompress_kernel(&raw) {
+        Ok(buf) => Ok(InputData::from(buf)),
+        Err(err) if decompress::is_compressed(&raw) => {
+            Err(err.context(format!("Unable to decompress {}", name)))
+        }
+        Err(err) => {
+            log::debug!("{} is not a compressed kernel: {:#}", name, err);
+            Ok(raw)
+        }
+    }
+}
+
+/// Extracts BTF section from kernel binaries, called `name`, and parses its
+/// header.
+///
+/// If `scan` is true, other files are searched for a BTF section at any
+/// offset, like memory images.
+fn get_btf_section<'a>(
+    mmap: &'a [u8],
+    name: &str,
+    scan: bool,
+) -> error::Result<(BtfHeader, &'a [u8])> {
+    let parse_error = |source| error::Error::BtfParse {
+        name: name.to_owned(),
+        source,
+    };
+    if mmap.starts_with(&BTF_MAGIC_LE) || mmap.starts_with(&BTF_MAGIC_BE) {
+        let hdr = BtfHeader::parse(mmap)
+            .context("Invalid .BTF section")
+            .map_err(parse_error)?;
+        log::debug!("Got stand alone .BTF section, {} endian", hdr.endian);
+        Ok((hdr, mmap))
+    } else if elf::is_elf(mmap).is_ok() {
+        let btf_sec = elf::extract_btfsec(mmap).map_err(parse_error)?;
+        let hdr = BtfHeader::parse(btf_sec)
+            .context("Invalid .BTF section in ELF file")
+            .map_err(parse_error)?;
+        Ok((hdr, btf_sec))
+    } else if decompress::is_arm64_image(mmap) {
+        // The flat image has no sections, the BTF is found like in memory
+        // images.
+        log::debug!("Got arm64 Image, searching for BTF section");
+        let (hdr, range) = find_btf_in_image(mmap, ImageFormat::Raw).map_err(parse_error)?;
+        Ok((hdr, &mmap[range]))
+    } else if scan {
+        log::debug!(
+            "{} is neither .BTF section nor ELF, searching for BTF section",
+            name
+        );
+        let (hdr, range) = find_btf_in_image(mmap, ImageFormat::Auto).map_err(parse_error)?;
+        log::info!("Using BTF section at {:#x} of {}", range.start, name);
+        Ok((hdr, &mmap[range]))
+    } else {
+        Err(error::Error::NotBtfOrElf {
+            name: name.to_owned(),
+            len: mmap.len(),
+        })
+    }
+}
+
+/// Header of a BTF section.
+#[derive(Copy, Clone, Debug)]
+pub struct BtfHeader {
+    pub endian: Endian,
+    pub version: u8,
+    pub flags: u8,
+    pub hdr_len: u32,
+    pub type_off: u32,
+    pub type_len: u32,
+    pub str_off: u32,
+    pub str_len: u32,
+}
+
+/// Indexes and name offsets of the types whose name offset is not the one of a
+/// string, see [`BtfHeader::repair_records`].
+pub type InvalidNames = Vec<(u32, u32)>;
+
+/// Indexes and kinds of the types of kinds that are unknown, see
+/// [`BtfHeader::repair_records`].
+pub type UnknownKinds = Vec<(u32, u32)>;
+
+/// Layout of the records of a kind that is unknown, which is assumed to be the
+/// one of a known kind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum RecordLayout {
+    /// No data after the common part, like ptr.
+    Plain,
+    /// One word after the common part, like int.
+    Word,
+    /// Three words after the common part, like array.
+    Array,
+    /// Two words per `vlen`, like enum.
+    Vlen8,
+    /// Three words per `vlen`, like struct.
+    Vlen12,
+}
+
+impl RecordLayout {
+    /// Layouts in the order they are tried, see [`BtfHeader::walk_types`].
+    const ALL: [RecordLayout; 5] = [
+        RecordLayout::Plain,
+        RecordLayout::Word,
+        RecordLayout::Array,
+        RecordLayout::Vlen8,
+        RecordLayout::Vlen12,
+    ];
+
+    /// Returns the length of the data after the common part of a record.
+    fn extra(self, vlen: usize) -> usize {
+        match self {
+            RecordLayout::Plain => 0,
+            RecordLayout::Word => 4,
+            RecordLayout::Array => 12,
+            RecordLayout::Vlen8 => 8 * vlen,
+            RecordLayout::Vlen12 => 12 * vlen,
+        }
+    }
+
+    /// Returns the known kind of this layout whose anonymous record with all
+    /// other fields zeroed refers to nothing but `void`: const, decl tag,
+    /// array, func proto, or datasec.
+    fn placeholder_kind(self) -> u32 {
+        match self {
+            RecordLayout::Plain => 10,
+            RecordLayout::Word => 17,
+            RecordLayout::Array => 3,
+            RecordLayout::Vlen8 => 13,
+            RecordLayout::Vlen12 => 15,
+        }
+    }
+}
+
+impl BtfHeader {
+    /// Length of the header of BTF version 1.
+    pub const LEN: usize = 24;
+    /// Only BTF version that exists.
+    const VERSION: u8 = 1;
+    /// Upper bound for the header length that we accept.
+    const MAX_HDR_LEN: u32 = 4096;
+    /// Length of the common part of all type records.
+    const TYPE_LEN: usize = 12;
+    /// Largest kind that is known.
+    const MAX_KIND: u32 = 19;
+    /// Bits of the info of a type record that are unused by all kinds.
+    const INFO_UNUSED: u32 = 0x60ff_0000;
+
+    /// Parses the header of the BTF section at the start of `data` and checks
+    /// that it is sane and describes a section within `data`.
+    ///
+    /// The contents of the type and string sections are not checked.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < Self::LEN {
+            bail!("BTF header truncated: {} bytes", data.len());
+        }
+        let endian = if data[0..2] == BTF_MAGIC_LE {
+            Endian::Little
+        } else if data[0..2] == BTF_MAGIC_BE {
+            Endian::Big
+        } else {
+            bail!("Invalid BTF magic: {:02x}{:02x}", data[0], data[1]);
+        };
+        let u32_at = |off: usize| {
+            let bytes = data[off..off + 4].try_into().unwrap();
+            match endian {
+                Endian::Little => u32::from_le_bytes(bytes),
+                Endian::Big => u32::from_be_bytes(bytes),
+            }
+        };
+        let hdr = Self {
+            endian,
+            version: data[2],
+            flags: data[3],
+            hdr_len: u32_at(4),
+            type_off: u32_at(8),
+            type_len: u32_at(12),
+            str_off: u32_at(16),
+            str_len: u32_at(20),
+        };
+
+        if hdr.version != Self::VERSION {
+            bail!("Unsupported BTF version {}", hdr.version);
+        }
+        if (hdr.hdr_len as usize) < Self::LEN || hdr.hdr_len > Self::MAX_HDR_LEN {
+            bail!("Invalid BTF header length {}", hdr.hdr_len);
+        }
+        if !hdr.type_off.is_multiple_of(4) || !hdr.type_len.is_multiple_of(4) {
+            bail!("Misaligned BTF type section");
+        }
+        let type_end = hdr.type_off as u64 + hdr.type_len as u64;
+        let str_end = hdr.str_off as u64 + hdr.str_len as u64;
+        if type_end > hdr.str_off as u64 && str_end > hdr.type_off as u64 {
+            bail!("Overlapping BTF type and string sections");
+        }
+        if hdr.section_len() > data.len() as u64 {
+            bail!(
+                "BTF section of {} bytes exceeds available {} bytes",
+                hdr.section_len(),
+                data.len()
+            );
+        }
+
+        Ok(hdr)
+    }
+
+    /// Returns the length of the section described by this header.
+    pub fn section_len(&self) -> u64 {
+        let type_end = self.type_off as u64 + self.type_len as u64;
+        let str_end = self.str_off as u64 + self.str_len as u64;
+        self.hdr_len as u64 + type_end.max(str_end)
+    }
+
+    /// Returns true iff the section `data` that this header was parsed from is
+    /// split BTF.
+    ///
+    /// The string section of standalone BTF starts with the empty string, the
+    /// one of split BTF continues the strings of its base and usually does
+    /// not.
+    pub fn is_split(&self, data: &[u8]) -> bool {
+        let start = self.hdr_len as usize + self.str_off as usize;
+        self.str_len > 0 && data.get(start) != Some(&0)
+    }
+
+    /// Returns the number of types in the type section of `data`, the BTF
+    /// section that this header was parsed from.
+    ///
+    /// Only the record lengths are decoded, the records are not checked.
+    pub fn count_types(&self, data: &[u8]) -> Result<u32> {
+        self.walk_types(data, |_, _, _| {})
+    }
+
+    /// Returns the integer types in the type section of `data` that do not use
+    /// all bits of their size, by raw ID.
+    ///
+    /// `first_id` is the ID of the first type in the section.
+    pub fn narrow_ints(&self, data: &[u8], first_id: u32) -> Result<HashMap<u32, IntBits>> {
+        let mut ints = HashMap::new();
+        self.walk_types(data, |idx, kind, record| {
+            if kind != 1 {
+                return;
+            }
+            let size = self.u32_at(record, 8);
+            let encoding = self.u32_at(record, 12);
+            let int = IntBits {
+                offset: (encoding >> 16) & 0xff,
+                bits: encoding & 0xff,
+            };
+            if int.offset != 0 || u64::from(int.bits) != u64::from(size) * 8 {
+                ints.insert(first_id + idx, int);
+            }
+        })?;
+        log::debug!("Section defines {} narrow integer types", ints.len());
+        Ok(ints)
+    }
+
+    /// Returns `data` with the records repaired that `btf_rs` rejects the whole
+    /// section for, with the indexes and name offsets of the types whose name
+    /// was removed, and the indexes and kinds of the types that were replaced.
+    ///
+    /// Names are removed from the types whose name offset is not the one of a
+    /// string. Types of kinds that are unknown are replaced with an anonymous
+    /// type of a known kind with the same record length, see
+    /// [`BtfHeader::walk_types`]. `base` is the header and section of the base
+    /// BTF of split BTF, whose strings precede the ones of `data`.
+    pub fn repair_records<'a>(
+        &self,
+        data: &'a [u8],
+        base: Option<(&BtfHeader, &[u8])>,
+    ) -> Result<(Cow<'a, [u8]>, InvalidNames, UnknownKinds)> {
+        let (base_strings, base_len) = match base {
+            Some((hdr, data)) => (hdr.string_offsets(data), hdr.str_len),
+            None => (HashSet::new(), 0),
+        };
+        let strings = self.string_offsets(data);
+        let mut invalid = Vec::new();
+        let mut unknown = Vec::new();
+        let mut off = self.hdr_len as usize + self.type_off as usize;
+        self.walk_types(data, |idx, kind, record| {
+            let name_off = self.u32_at(record, 0);
+            let valid = name_off == 0
+                || match name_off.checked_sub(base_len) {
+                    Some(own) => strings.contains(&own),
+                    None => base_strings.contains(&name_off),
+                };
+            if kind > Self::MAX_KIND {
+                unknown.push((idx, off, kind, record.len()));
+            } else if !valid {
+                invalid.push((idx, off, name_off));
+            }
+            off += record.len();
+        })?;
+        if invalid.is_empty() && unknown.is_empty() {
+            return Ok((Cow::Borrowed(data), Vec::new(), Vec::new()));
+        }
+        let mut repaired = data.to_vec();
+        for (_, off, _) in &invalid {
+            repaired[*off..*off + 4].fill(0);
+        }
+        for (idx, off, kind, len) in &unknown {
+            let record = &mut repaired[*off..*off + len];
+            let (_, vlen) = self.kind_at(record, 0);
+            let layout = RecordLayout::ALL
+                .into_iter()
+                .find(|layout| layout.extra(vlen) == len - Self::TYPE_LEN)
+                .expect("BUG: record of unknown kind without layout");
+            log::trace!(
+                "Type {} of kind {} replaced with kind {}",
+                idx,
+                kind,
+                layout.placeholder_kind()
+            );
+            record.fill(0);
+            let vlen = match layout {
+                RecordLayout::Vlen8 | RecordLayout::Vlen12 => vlen as u32,
+                _ => 0,
+            };
+            let info = (layout.placeholder_kind() << 24) | vlen;
+            let info = match self.endian {
+                Endian::Little => info.to_le_bytes(),
+                Endian::Big => info.to_be_bytes(),
+            };
+            record[4..8].copy_from_slice(&info);
+        }
+        Ok((
+            Cow::Owned(repaired),
+            invalid
+                .into_iter()
+                .map(|(idx, _, name_off)| (idx, name_off))
+                .collect(),
+            unknown
+                .into_iter()
+                .map(|(idx, _, kind, _)| (idx, kind))
+                .collect(),
+        ))
+    }
+
+    /// Returns the offsets of the strings in the string section of `data`.
+    fn string_offsets(&self, data: &[u8]) -> HashSet<u32> {
+        let start = self.hdr_len as usize + self.str_off as usize;
+        let strings = &data[start..start + self.str_len as usize];
+        std::iter::once(0)
+            .chain(
+                strings
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| **b == 0)
+                    .map(|(i, _)| i as u32 + 1),
+            )
+            .filter(|off| *off < self.str_len)
+            .collect()
+    }
+
+    /// Calls `visit` with the index, kind, and record of each type in the type
+    /// section of `data`. Returns the number of types.
+    ///
+    /// The record length of kinds that are unknown, e.g., added by a newer
+    /// kernel, is derived: of the layouts of the known kinds, the first one is
+    /// taken with which the remaining records end exactly at the end of the
+    /// type section. Kind 0 is never valid, and neither are records that set
+    /// unused bits of their info.
+    fn walk_types(&self, data: &[u8], mut visit: impl FnMut(u32, u32, &[u8])) -> Result<u32> {
+        let start = self.hdr_len as usize + self.type_off as usize;
+        let types = &data[start..start + self.type_len as usize];
+
+        let mut layouts = HashMap::new();
+        let mut off = 0;
+        let mut count = 0u32;
+        while off < types.len() {
+            if types.len() - off < Self::TYPE_LEN {
+                bail!("BTF type {} truncated at {:#x}", count, off);
+            }
+            let (kind, vlen) = self.kind_at(types, off);
+            let extra = match self.record_extra(kind, vlen, &layouts) {
+                Some(extra) => extra,
+                None if self.is_unknown_kind(types, off) => {
+                    let Some(derived) = self.derive_layouts(types, off, layouts.clone()) else {
+                        bail!(
+                            "BTF type {} at {:#x} has unknown kind {}, whose record length \
+                             cannot be derived",
+                            count,
+                            off,
+                            kind
+                        );
+                    };
+                    layouts = derived;
+                    log::debug!("Kind {} has the layout {:?}", kind, layouts[&kind]);
+                    layouts[&kind].extra(vlen)
+                }
+                None => bail!("BTF type {} at {:#x} has unknown kind {}", count, off, kind),
+            };
+            let Some(record) = types.get(off..off + Self::TYPE_LEN + extra) else {
+                bail!("Last BTF type exceeds type section");
+            };
+            visit(count, kind, record);
+            off += record.len();
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Returns `layouts` with the layouts of the unknown kinds from the record
+    /// at `off` on, with which the records end exactly at the end of `types`,
+    /// if any.
+    fn derive_layouts(
+        &self,
+        types: &[u8],
+        mut off: usize,
+        layouts: HashMap<u32, RecordLayout>,
+    ) -> Option<HashMap<u32, RecordLayout>> {
+        while off < types.len() {
+            if types.len() - off < Self::TYPE_LEN {
+                return None;
+            }
+            let (kind, vlen) = self.kind_at(types, off);
+            let extra = match self.record_extra(kind, vlen, &layouts) {
+                Some(extra) => extra,
+                None if self.is_unknown_kind(types, off) => {
+                    return RecordLayout::ALL.into_iter().find_map(|layout| {
+                        let mut layouts = layouts.clone();
+                        layouts.insert(kind, layout);
+                        let next = off + Self::TYPE_LEN + layout.extra(vlen);
+                        self.derive_layouts(types, next, layouts)
+                    });
+                }
+                None => return None,
+            };
+            off += Self::TYPE_LEN + extra;
+        }
+        (off == types.len()).then_some(layouts)
+    }
+
+    /// Returns true iff the record at `off` in `types` is of a kind that is
+    /// unknown, and leaves the unused bits of its info clear.
+    fn is_unknown_kind(&self, types: &[u8], off: usize) -> bool {
+        let info = self.u32_at(types, off + 4);
+        let (kind, _) = self.kind_at(types, off);
+        kind > Self::MAX_KIND && info & Self::INFO_UNUSED == 0
+    }
+
+    /// Returns the kind and `vlen` of the record at `off` in `types`.
+    fn kind_at(&self, types: &[u8], off: usize) -> (u32, usize) {
+        let info = self.u32_at(types, off + 4);
+        ((info >> 24) & 0x1f, (info & 0xffff) as usize)
+    }
+
+    /// Returns the length of the data after the common part of a record of
+    /// `kind`, if the kind is known or its layout is in `layouts`.
+    fn record_extra(
+        &self,
+        kind: u32,
+        vlen: usize,
+        layouts: &HashMap<u32, RecordLayout>,
+    ) -> Option<usize> {
+        let layout = match kind {
+            // ptr, fwd, typedef, volatile, const, restrict, func, float,
+            // type tag
+            2 | 7..=12 | 16 | 18 => RecordLayout::Plain,
+            // int, var, decl tag
+            1 | 14 | 17 => RecordLayout::Word,
+            // array
+            3 => RecordLayout::Array,
+            // enum, func proto
+            6 | 13 => RecordLayout::Vlen8,
+            // struct, union, datasec, enum64
+            4 | 5 | 15 | 19 => RecordLayout::Vlen12,
+            _ => *layouts.get(&kind)?,
+        };
+        Some(layout.extra(vlen))
+    }
+
+    /// Returns the `u32` at `off` in `data`, which is in the byte order of the
+    /// section.
+    fn u32_at(&self, data: &[u8], off: usize) -> u32 {
+        let bytes = data[off..off + 4].try_into().unwrap();
+        match self.endian {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+    use std::io::Read;
+
+    use xz2::read::XzDecoder;
+
+    fn vmlinux() -> Btf {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/linux-6.18/vmlinux.btf.xz");
+        let mut data = Vec::new();
+        XzDecoder::new(File::open(path).unwrap())
+            .read_to_end(&mut data)
+            .unwrap();
+        Btf::from_bytes("vmlinux.btf", data).unwrap()
+    }
+
+    /// Memoized type chains and names are the same as the ones looked up
+    /// without memoization, on the first and any later lookup.
+    #[test]
+    fn memoization() {
+        let btf = vmlinux();
+        for tx in btf.types() {
+            let id = tx.id;
+            let walked = format!("{:?}", btf.walk_type_chain(tx.clone()).0);
+            for _ in 0..2 {
+                assert_eq!(format!("{:?}", btf.resolve_type_chain(tx.clone())), walked);
+            }
+
+            let name =
+                tx.t.t
+                    .as_btf_type()
+                    .and_then(|t| btf.resolve_name(t).ok())
+                    .filter(|name| !name.is_empty());
+            for _ in 0..2 {
+                assert_eq!(btf.get_strtab_entry_by_id(id).ok(), name, "{}", id);
+            }
+        }
     }
 }
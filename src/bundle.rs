@@ -0,0 +1,243 @@
+//! Profile bundles.
+//!
+//! A bundle is a directory that holds everything that belongs to a generated
+//! profile: the compressed ISF file, statistics, verification results, the
+//! inputs that were used, and a manifest that ties them together.
+//!
+//! Layout:
+//!
+//! - `profile.json.xz`: the ISF file,
+//! - `stats.json`: number of elements in the ISF file,
+//! - `warnings.json`: results of the verification passes,
+//! - `inputs/`: the BTF section and System.map the profile was generated from,
+//! - `manifest.json`: tool version, input and output hashes, and creation time.
+
+use crate::input::InputData;
+use crate::isf::Isf;
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "xz")]
+use xz2::write::XzEncoder;
+
+const PROFILE_NAME: &str = "profile.json.xz";
+const STATS_NAME: &str = "stats.json";
+const WARNINGS_NAME: &str = "warnings.json";
+const MANIFEST_NAME: &str = "manifest.json";
+const INPUTS_DIR: &str = "inputs";
+
+#[cfg(feature = "xz")]
+const XZ_LEVEL: u32 = 6;
+
+/// An input file, or part of it, that is included in the bundle.
+pub struct Artifact {
+    name: String,
+    data: Rc<InputData>,
+    range: Range<usize>,
+}
+
+impl Artifact {
+    pub fn new(name: impl Into<String>, data: Rc<InputData>, range: Range<usize>) -> Self {
+        Self {
+            name: name.into(),
+            data,
+            range,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data[self.range.clone()]
+    }
+}
+
+/// Results of the verification passes that ran on the ISF file.
+///
+/// Each entry is `None` if the pass succeeded or did not run.
+#[derive(Serialize, Default)]
+pub struct Verification {
+    /// Types that could not be constructed.
+    pub types: Option<String>,
+    pub symbol_types: Option<String>,
+    pub user_types: Option<String>,
+    /// Enums whose base types are undefined.
+    pub enums: Option<String>,
+    /// Malformed type descriptors.
+    pub descriptors: Option<String>,
+    /// Fields that do not fit into their user types.
+    pub layout: Option<String>,
+}
+
+/// Output file that is listed in the manifest.
+#[derive(Serialize)]
+struct Output {
+    path: String,
+    hash_type: &'static str,
+    hash_value: String,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    producer: Producer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<String>,
+    inputs: serde_json::Value,
+    outputs: &'a [Output],
+}
+
+#[derive(Serialize)]
+struct Producer {
+    name: &'static str,
+    version: &'static str,
+}
+
+/// Writer for profile bundles.
+pub struct Bundle {
+    dir: PathBuf,
+    reproducible: bool,
+}
+
+impl Bundle {
+    /// Creates a writer for a bundle at `dir`, which must not exist yet.
+    ///
+    /// If `reproducible` is set the manifest does not include a timestamp.
+    pub fn new(dir: &Path, reproducible: bool) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            reproducible,
+        }
+    }
+
+    /// Writes the bundle.
+    ///
+    /// All files are written to a temporary directory next to the bundle that
+    /// is only moved into place once everything succeeded.
+    pub fn write(
+        &self,
+        isf: &Isf,
+        artifacts: &[Artifact],
+        verification: &Verification,
+    ) -> Result<()> {
+        if self.dir.exists() {
+            bail!("Bundle directory {} already exists", self.dir.display());
+        }
+        let tmp_dir = self.tmp_dir()?;
+        if tmp_dir.exists() {
+            fs::remove_dir_all(&tmp_dir)
+                .with_context(|| format!("Unable to remove stale {}", tmp_dir.display()))?;
+        }
+        fs::create_dir_all(tmp_dir.join(INPUTS_DIR))
+            .with_context(|| format!("Unable to create {}", tmp_dir.display()))?;
+
+        match self.write_contents(&tmp_dir, isf, artifacts, verification) {
+            Ok(()) => fs::rename(&tmp_dir, &self.dir).with_context(|| {
+                format!(
+                    "Unable to move {} to {}",
+                    tmp_dir.display(),
+                    self.dir.display()
+                )
+            }),
+            Err(err) => {
+                let _ = fs::remove_dir_all(&tmp_dir);
+                Err(err)
+            }
+        }
+    }
+
+    fn tmp_dir(&self) -> Result<PathBuf> {
+        let name = self
+            .dir
+            .file_name()
+            .context("Bundle path has no directory name")?
+            .to_string_lossy();
+        Ok(self
+            .dir
+            .with_file_name(format!(".{}.tmp-{}", name, std::process::id())))
+    }
+
+    fn write_contents(
+        &self,
+        tmp_dir: &Path,
+        isf: &Isf,
+        artifacts: &[Artifact],
+        verification: &Verification,
+    ) -> Result<()> {
+        let mut outputs = Vec::new();
+
+        outputs.push(write_file(tmp_dir, PROFILE_NAME, &compress_profile(isf)?)?);
+        outputs.push(write_file(
+            tmp_dir,
+            STATS_NAME,
+            &serde_json::to_vec_pretty(&isf.stats())?,
+        )?);
+        outputs.push(write_file(
+            tmp_dir,
+            WARNINGS_NAME,
+            &serde_json::to_vec_pretty(verification)?,
+        )?);
+        for artifact in artifacts {
+            let path = Path::new(INPUTS_DIR).join(&artifact.name);
+            outputs.push(write_file(
+                tmp_dir,
+                &path.to_string_lossy(),
+                artifact.bytes(),
+            )?);
+        }
+
+        let manifest = Manifest {
+            producer: Producer {
+                name: env!("CARGO_CRATE_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            created: (!self.reproducible)
+                .then(|| humantime::format_rfc3339_seconds(SystemTime::now()).to_string()),
+            inputs: serde_json::to_value(isf.metadata())?["linux"].take(),
+            outputs: &outputs,
+        };
+        write_file(
+            tmp_dir,
+            MANIFEST_NAME,
+            &serde_json::to_vec_pretty(&manifest)?,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Writes `data` to `name` within `dir` and returns its manifest entry.
+fn write_file(dir: &Path, name: &str, data: &[u8]) -> Result<Output> {
+    let path = dir.join(name);
+    let mut writer = BufWriter::new(
+        File::create(&path).with_context(|| format!("Unable to create {}", path.display()))?,
+    );
+    writer
+        .write_all(data)
+        .and_then(|_| writer.flush())
+        .with_context(|| format!("Unable to write {}", path.display()))?;
+
+    Ok(Output {
+        path: String::from(name),
+        hash_type: "sha256",
+        hash_value: format!("{:x}", Sha256::digest(data)),
+    })
+}
+
+/// Returns the xz-compressed ISF JSON of `isf`.
+#[cfg(feature = "xz")]
+fn compress_profile(isf: &Isf) -> Result<Vec<u8>> {
+    let mut encoder = XzEncoder::new(Vec::new(), XZ_LEVEL);
+    serde_json::to_writer(&mut encoder, isf)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(not(feature = "xz"))]
+fn compress_profile(_isf: &Isf) -> Result<Vec<u8>> {
+    bail!("Bundles require the xz feature")
+}
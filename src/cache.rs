@@ -0,0 +1,322 @@
+//! Cache of the analysis of BTF files, see `--cache-dir`.
+//!
+//! Scanning the types of a kernel, i.e., sorting them into the sets that
+//! Volatility distinguishes between and resolving all typedefs, is repeated for
+//! every profile of the same BTF. The cache keeps its result together with the
+//! names in the string table, by the SHA-256 hash of the BTF section and that
+//! of its base BTF.
+//!
+//! An entry is a header, followed by the payload and the SHA-256 hash of the
+//! payload. All integers are little endian.
+//!
+//! | Field          | Size                                 |
+//! |----------------|--------------------------------------|
+//! | magic          | 8 bytes, `BTF2JSON`                  |
+//! | format version | u32                                  |
+//! | tool version   | u32 length, followed by the string   |
+//! | key            | 32 bytes, hash of the BTF            |
+//! | payload length | u64                                  |
+//! | payload        |                                      |
+//! | checksum       | 32 bytes, hash of the payload        |
+
+use crate::btf::{self, Btf, TypedefTarget, Typedefs};
+use crate::btf::{TypeEx, VolIdSets};
+use crate::progress::{Phase, Progress};
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+const MAGIC: &[u8; 8] = b"BTF2JSON";
+/// Version of the layout of the entries.
+const FORMAT_VERSION: u32 = 2;
+/// Version of btf2json, whose entries are the only ones that are used.
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+const HASH_LEN: usize = 32;
+
+/// Directory with the analysis of BTF files.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the path of the entry of `btf`.
+    pub fn entry_path(&self, btf: &Btf) -> PathBuf {
+        self.path(&key(btf))
+    }
+
+    fn path(&self, key: &[u8; HASH_LEN]) -> PathBuf {
+        self.dir.join(format!("{}.btfcache", hex(key)))
+    }
+
+    /// Returns the partitioning of the types of `btf`, see
+    /// [`Btf::gen_vol_id_sets`].
+    ///
+    /// The types are scanned if the cache has no usable entry for `btf`, which
+    /// is then written. Failing to write the entry is not an error.
+    pub(crate) fn scan_types(&self, btf: &Btf, progress: &dyn Progress) -> Result<VolIdSets> {
+        let key = key(btf);
+        let path = self.path(&key);
+        match self.load(btf, &key, &path) {
+            Ok(Some(sets)) => {
+                log::info!(
+                    "Using cached analysis of {} from {}",
+                    btf.name(),
+                    path.display()
+                );
+                progress.start(Phase::ScanTypes, None);
+                progress.finish(Phase::ScanTypes, btf.nr_types() as usize);
+                return Ok(sets);
+            }
+            Ok(None) => (),
+            Err(err) => log::warn!(
+                "Cache entry {} is corrupted, rescanning the types: {:#}",
+                path.display(),
+                err
+            ),
+        }
+
+        let sets = btf.scan_types(progress)?;
+        if let Err(err) = self.store(btf, &sets, &key, &path) {
+            log::warn!("Unable to write cache entry {}: {:#}", path.display(), err);
+        }
+        Ok(sets)
+    }
+
+    /// Loads the entry of `btf` with `key` at `path`.
+    ///
+    /// Returns `None` if there is no entry or it was written by another
+    /// version, and fails if it is corrupted.
+    fn load(&self, btf: &Btf, key: &[u8], path: &Path) -> Result<Option<VolIdSets>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                log::debug!("No cache entry {}", path.display());
+                return Ok(None);
+            }
+            Err(err) => return Err(err).context("Unable to read it"),
+        };
+
+        let mut reader = Reader::new(&data);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            bail!("Invalid magic");
+        }
+        let format_version = reader.u32()?;
+        let tool_version = reader.str()?;
+        if format_version != FORMAT_VERSION || tool_version != TOOL_VERSION {
+            log::info!(
+                "Ignoring cache entry {} of btf2json {}, format {}",
+                path.display(),
+                tool_version,
+                format_version
+            );
+            return Ok(None);
+        }
+        if reader.bytes(HASH_LEN)? != key {
+            log::info!("Ignoring cache entry {} of another BTF", path.display());
+            return Ok(None);
+        }
+        let len = usize::try_from(reader.u64()?).context("Invalid payload length")?;
+        let payload = reader.bytes(len)?;
+        if reader.bytes(HASH_LEN)? != Sha256::digest(payload).as_slice() {
+            bail!("Checksum mismatch");
+        }
+        if !reader.is_empty() {
+            bail!("Trailing data after the checksum");
+        }
+
+        decode(btf, &mut Reader::new(payload)).map(Some)
+    }
+
+    /// Writes the entry of `btf` with `key` and `sets` to `path`.
+    ///
+    /// The entry is written to a temporary file first, so that concurrent runs
+    /// never see partial entries.
+    fn store(&self, btf: &Btf, sets: &VolIdSets, key: &[u8], path: &Path) -> Result<()> {
+        let payload = encode(btf, sets);
+        let mut data = Vec::with_capacity(payload.len() + 128);
+        data.extend(MAGIC);
+        put_u32(&mut data, FORMAT_VERSION);
+        put_str(&mut data, TOOL_VERSION);
+        data.extend(key);
+        put_u64(&mut data, payload.len() as u64);
+        data.extend(&payload);
+        data.extend(Sha256::digest(&payload));
+
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Unable to create {}", self.dir.display()))?;
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, &data).with_context(|| format!("Unable to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })?;
+        log::info!(
+            "Cached analysis of {} in {}, {} bytes",
+            btf.name(),
+            path.display(),
+            data.len()
+        );
+        Ok(())
+    }
+}
+
+/// Returns the key of the entry of `btf`.
+fn key(btf: &Btf) -> [u8; HASH_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(btf.section());
+    if let Some(base) = btf.base() {
+        hasher.update(&base.raw()[base.section_range()]);
+    }
+    // The cached typedef chains are cut off at the limit.
+    if btf.max_type_depth() != btf::DEFAULT_MAX_TYPE_DEPTH {
+        hasher.update(btf.max_type_depth().to_le_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Encodes `sets` and the non-empty string table entries of the types of
+/// `btf`.
+fn encode(btf: &Btf, sets: &VolIdSets) -> Vec<u8> {
+    let (user_ids, enum_ids, basic_ids, fwd_ids, typedefs) = sets;
+    let mut out = Vec::new();
+    for ids in [user_ids, enum_ids, basic_ids, fwd_ids] {
+        put_u32(&mut out, ids.len() as u32);
+        for id in ids {
+            put_u32(&mut out, u32::from(*id));
+        }
+    }
+
+    // The backward map is derived from the forward map on loading.
+    let mut fw: Vec<_> = typedefs.fw.iter().collect();
+    fw.sort_unstable_by_key(|(id, _)| **id);
+    put_u32(&mut out, fw.len() as u32);
+    for (id, target) in fw {
+        put_u32(&mut out, u32::from(*id));
+        put_u32(&mut out, u32::from(target.id));
+        out.push(target.indirect as u8);
+    }
+
+    let names: Vec<(u32, btf::Name)> = btf
+        .types()
+        .filter_map(|TypeEx { t, id }| {
+            let name = btf.resolve_name(t.t.as_btf_type()?).ok()?;
+            (!name.is_empty()).then(|| (u32::from(id), name))
+        })
+        .collect();
+    put_u32(&mut out, names.len() as u32);
+    for (id, name) in names {
+        put_u32(&mut out, id);
+        put_str(&mut out, &name);
+    }
+    out
+}
+
+/// Decodes the sets encoded by [`encode`] and memoizes the names in `btf`.
+///
+/// Fails if the IDs do not refer to types of `btf`.
+fn decode(btf: &Btf, reader: &mut Reader) -> Result<VolIdSets> {
+    let mut sets: [BTreeSet<btf::Id>; 4] = Default::default();
+    for set in &mut sets {
+        for _ in 0..reader.u32()? {
+            set.insert(btf.id(reader.u32()?)?);
+        }
+    }
+
+    let mut fw = HashMap::new();
+    let mut bk: HashMap<btf::Id, Vec<btf::Id>> = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let id = btf.id(reader.u32()?)?;
+        let target = TypedefTarget {
+            id: btf.id(reader.u32()?)?,
+            indirect: match reader.u8()? {
+                0 => false,
+                1 => true,
+                flag => bail!("Invalid indirection flag {} of typedef {}", flag, id),
+            },
+        };
+        // The typedefs are in ascending order of their IDs, like the scan
+        // adds them.
+        bk.entry(target.id).or_default().push(id);
+        fw.insert(id, target);
+    }
+
+    for _ in 0..reader.u32()? {
+        let id = btf.id(reader.u32()?)?;
+        btf.memoize_strtab_entry(id, reader.str()?);
+    }
+    if !reader.is_empty() {
+        bail!("Trailing data in the payload");
+    }
+
+    let [user_ids, enum_ids, basic_ids, fwd_ids] = sets;
+    Ok((user_ids, enum_ids, basic_ids, fwd_ids, Typedefs { fw, bk }))
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend(value.to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_u32(out, s.len() as u32);
+    out.extend(s.as_bytes());
+}
+
+/// Bounds-checked reader of the little-endian values of an entry.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            bail!(
+                "Truncated, {} bytes left, but {} needed",
+                self.data.len(),
+                len
+            );
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into()?))
+    }
+
+    fn str(&mut self) -> Result<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.bytes(len)?).context("Invalid string")
+    }
+}
@@ -1,54 +1,1424 @@
 //! Command-line interface.
 
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::builder::PossibleValue;
 use clap::ValueEnum;
+use clap::{Arg, ArgGroup, Args, Parser, Subcommand};
+use regex::Regex;
 
-#[derive(Debug, Clone, ValueEnum)]
+use crate::btf::Endian;
+use crate::metadata::FormatVersion;
+use crate::symbols::{Banner, SymbolKind};
+use crate::version::KernelVersion;
+
+/// Architecture of a kernel, which `--arch` gives or is detected from the
+/// inputs, see [`ArchArg`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum Architecture {
     #[value(name = "x86_64")]
     X86_64,
     #[value(name = "arm64")]
     Arm64,
+    /// 32-bit x86, i.e., i386 to i686.
+    #[value(name = "x86")]
+    X86,
+    /// 32-bit ARM.
+    #[value(name = "arm")]
+    Arm,
+    #[value(name = "riscv64")]
+    Riscv64,
+    /// 64-bit little endian POWER.
+    #[value(name = "ppc64le")]
+    Ppc64le,
+    /// 64-bit IBM Z, which is big endian.
+    #[value(name = "s390x")]
+    S390x,
 }
 
-impl Default for Architecture {
-    fn default() -> Self {
-        Architecture::X86_64
+impl Architecture {
+    /// Returns the size of a pointer in bytes.
+    pub fn pointer_size(&self) -> u8 {
+        match self {
+            Architecture::X86_64
+            | Architecture::Arm64
+            | Architecture::Riscv64
+            | Architecture::Ppc64le
+            | Architecture::S390x => 8,
+            Architecture::X86 | Architecture::Arm => 4,
+        }
+    }
+
+    /// Returns the symbols at the start of the kernel text, by preference.
+    ///
+    /// Symbol addresses are rebased on the first one in the symbol sources.
+    /// The fallbacks for `_stext` are for symbol maps that lack it, and they
+    /// are assumed to be at the same address. On 32-bit ARM the address of
+    /// `_stext` depends on the configuration, so the start of the image is
+    /// preferred. On riscv64 `_stext` follows the head of the image, which
+    /// starts at `_start`.
+    pub fn text_anchors(&self) -> &'static [&'static str] {
+        match self {
+            Architecture::X86_64 => &["_stext", "_text", "__start_text", "startup_64"],
+            Architecture::Arm64 => &["_stext", "_text", "__start_text", "_head"],
+            Architecture::X86 => &["_stext", "_text", "__start_text", "startup_32"],
+            Architecture::Arm => &["_text", "stext", "_stext", "__start_text"],
+            Architecture::Riscv64 => &["_start", "_stext", "_text", "__start_text"],
+            Architecture::Ppc64le => &["_stext", "_text", "__start", "__start_text"],
+            Architecture::S390x => &["_stext", "_text", "startup_continue", "__start_text"],
+        }
+    }
+
+    /// Returns the virtual addresses of the kernel image and modules, or
+    /// `None` if they are not told apart from user addresses by their range.
+    ///
+    /// On arm64 the range depends on the virtual address bits `va_bits`, the
+    /// largest kernel address space is assumed without them. On s390x the
+    /// kernel has been moved to a virtual address only in 6.10.
+    pub fn kernel_address_range(&self, va_bits: Option<VaBits>) -> Option<RangeInclusive<u64>> {
+        match self {
+            // Up to the fixmap and the vsyscall page.
+            Architecture::X86_64 => Some(0xffffffff80000000..=0xfffffffffeffffff),
+            Architecture::Arm64 => {
+                let bits = va_bits.unwrap_or(VaBits::Va52).bits();
+                Some(u64::MAX << bits..=u64::MAX)
+            }
+            // With the default 3G/1G split.
+            Architecture::X86 => Some(0xc0000000..=0xffffffff),
+            // Modules precede the kernel by 16 MiB.
+            Architecture::Arm => Some(0xbf000000..=0xffffffff),
+            // Modules precede the kernel by 2 GiB.
+            Architecture::Riscv64 => Some(0xffffffff00000000..=u64::MAX),
+            Architecture::Ppc64le => Some(0xc000000000000000..=u64::MAX),
+            Architecture::S390x => None,
+        }
+    }
+
+    /// Returns the byte order of the kernels of the architecture.
+    pub fn endian(&self) -> Endian {
+        match self {
+            Architecture::S390x => Endian::Big,
+            _ => Endian::Little,
+        }
+    }
+
+    /// Returns whether plain `char` is signed in the kernel of `version`, or
+    /// `None` if that is unknown.
+    ///
+    /// The kernel is built with `-funsigned-char` since 6.2, before that
+    /// `char` follows the ABI of the architecture, where it is only signed on
+    /// x86.
+    pub fn char_signed(&self, version: Option<KernelVersion>) -> Option<bool> {
+        if version? >= KernelVersion::new(6, 2, 0) {
+            return Some(false);
+        }
+        match self {
+            Architecture::X86_64 | Architecture::X86 => Some(true),
+            Architecture::Arm64
+            | Architecture::Arm
+            | Architecture::Riscv64
+            | Architecture::Ppc64le
+            | Architecture::S390x => Some(false),
+        }
+    }
+
+    /// Returns common placements of the kernel image in physical memory, as
+    /// pairs of a virtual and the physical address that it maps to, see
+    /// `--kernel-virt-base`.
+    ///
+    /// They are guesses for kernels that were not relocated. The physical
+    /// load addresses on arm64, 32-bit ARM, and riscv64 are the ones of the
+    /// QEMU virt machine and of common boards.
+    pub fn image_placements(&self) -> Vec<(u64, u64)> {
+        match self {
+            // __START_KERNEL_map maps the physical address 0.
+            Architecture::X86_64 => vec![(0xffffffff80000000, 0)],
+            Architecture::Arm64 => vec![(0xffff800080000000, 0x40200000)],
+            // PAGE_OFFSET with the default 3G/1G split.
+            Architecture::X86 => vec![(0xc0000000, 0)],
+            Architecture::Arm => vec![
+                (0xc0000000, 0x40000000),
+                (0xc0000000, 0x80000000),
+                (0xc0000000, 0),
+            ],
+            Architecture::Riscv64 => vec![(0xffffffff80000000, 0x80200000)],
+            Architecture::Ppc64le => vec![(0xc000000000000000, 0)],
+            // Identity mapped before 6.10.
+            Architecture::S390x => vec![(0, 0)],
+        }
     }
 }
 
-#[derive(Parser, Debug)]
-#[clap(name = "btf2json", author = "Valentin Obst")]
-/// Generate Volatility 3 ISF files from BTF type information.
-pub struct Cli {
+/// Architecture of `--arch`, which is detected from the inputs unless given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchArg {
+    /// Detect the architecture from the input files.
+    #[default]
+    Auto,
+    Arch(Architecture),
+}
+
+impl ArchArg {
+    /// Returns the given architecture, or `None` for `auto`.
+    pub fn given(self) -> Option<Architecture> {
+        match self {
+            ArchArg::Auto => None,
+            ArchArg::Arch(arch) => Some(arch),
+        }
+    }
+
+    /// Returns the placements of [`Architecture::image_placements`], the ones
+    /// of all architectures for `auto`.
+    pub fn image_placements(self) -> Vec<(u64, u64)> {
+        match self {
+            ArchArg::Auto => Architecture::value_variants()
+                .iter()
+                .flat_map(|arch| arch.image_placements())
+                .collect(),
+            ArchArg::Arch(arch) => arch.image_placements(),
+        }
+    }
+}
+
+impl From<Architecture> for ArchArg {
+    fn from(arch: Architecture) -> Self {
+        ArchArg::Arch(arch)
+    }
+}
+
+impl ValueEnum for ArchArg {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            ArchArg::Auto,
+            ArchArg::Arch(Architecture::X86_64),
+            ArchArg::Arch(Architecture::Arm64),
+            ArchArg::Arch(Architecture::X86),
+            ArchArg::Arch(Architecture::Arm),
+            ArchArg::Arch(Architecture::Riscv64),
+            ArchArg::Arch(Architecture::Ppc64le),
+            ArchArg::Arch(Architecture::S390x),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            ArchArg::Auto => Some(
+                PossibleValue::new("auto").help("Detect the architecture from the input files"),
+            ),
+            ArchArg::Arch(arch) => arch.to_possible_value(),
+        }
+    }
+}
+
+/// Virtual address bits of an arm64 kernel, i.e., `CONFIG_ARM64_VA_BITS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VaBits {
+    #[value(name = "39")]
+    Va39,
+    #[value(name = "42")]
+    Va42,
+    #[value(name = "48")]
+    Va48,
+    /// 52 bits, whose kernel image is placed as with 48 bits.
+    #[value(name = "52")]
+    Va52,
+}
+
+impl VaBits {
+    pub fn bits(&self) -> u32 {
+        match self {
+            VaBits::Va39 => 39,
+            VaBits::Va42 => 42,
+            VaBits::Va48 => 48,
+            VaBits::Va52 => 52,
+        }
+    }
+}
+
+/// Layout of a memory image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ImageFormat {
+    /// Detect ELF vmcores and LiME images, other images are raw.
+    #[value(name = "auto")]
+    #[default]
+    Auto,
+    /// Flat dump of physical memory whose file offsets are physical
+    /// addresses, e.g., from /dev/mem or QEMU's `pmemsave`.
+    #[value(name = "raw")]
+    Raw,
+}
+
+/// What to do with symbol addresses that do not fit the pointer width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AddressOverflow {
+    /// Keep only the bits that fit the pointer width.
+    #[value(name = "mask")]
+    Mask,
+    /// Fail the generation.
+    #[value(name = "error")]
+    #[default]
+    Error,
+}
+
+/// Parses a hexadecimal address with an optional `0x` prefix.
+fn parse_address(s: &str) -> Result<u64, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u64::from_str_radix(digits, 16).map_err(|err| format!("not a hexadecimal address: {}", err))
+}
+
+/// Parses a symbol kind, the type letter of `nm` in either case.
+fn parse_symbol_kind(s: &str) -> Result<SymbolKind, String> {
+    let mut chars = s.trim().chars();
+    match (chars.next(), chars.next()) {
+        (Some(chr), None) => SymbolKind::try_from(&chr).map_err(|err| err.to_string()),
+        _ => Err(format!("{:?} is not a single type letter", s)),
+    }
+}
+
+/// Parses a section name and its hexadecimal address, separated by `=`.
+fn parse_section_address(s: &str) -> Result<(String, u64), String> {
+    let (section, addr) = s
+        .split_once('=')
+        .ok_or_else(|| String::from("expected SECTION=ADDRESS"))?;
+    Ok((section.to_owned(), parse_address(addr)?))
+}
+
+/// What to do with symbol names that appear more than once in the symbol
+/// source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DupSymbols {
+    /// Drop all symbols with the name.
+    #[value(name = "drop")]
+    #[default]
+    Drop,
+    /// Keep the first symbol with the name.
+    #[value(name = "first")]
+    First,
+    /// Keep the symbol with the name at the lowest address.
+    #[value(name = "lowest-addr")]
+    LowestAddr,
+    /// Keep the first symbol with the name, and the others as `<name>__<n>`.
+    #[value(name = "suffix")]
+    Suffix,
+}
+
+/// Compression of the ISF file written with `--output`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// Plain JSON.
+    #[value(name = "none")]
+    None,
+    /// gzip, as for `.json.gz` files.
+    #[value(name = "gz")]
+    Gz,
+    /// xz, as for `.json.xz` files.
+    #[value(name = "xz")]
+    Xz,
+}
+
+/// Output format of `symdb dump`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SymdbFormat {
+    /// One C-like declaration per entry, with its kernel versions.
+    #[default]
+    #[value(name = "text")]
+    Text,
+    #[value(name = "json")]
+    Json,
+}
+
+/// ISF file whose entry is kept if both files of `merge` define it
+/// differently.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Prefer {
+    #[default]
+    #[value(name = "base")]
+    Base,
+    #[value(name = "extra")]
+    Extra,
+}
+
+/// Output format of `stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    /// Human-readable table.
+    #[default]
+    #[value(name = "table")]
+    Table,
+    #[value(name = "json")]
+    Json,
+}
+
+/// Output format of `banners`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BannersFormat {
+    /// One paragraph per banner.
+    #[default]
+    #[value(name = "text")]
+    Text,
+    #[value(name = "json")]
+    Json,
+}
+
+/// Output format of `offsets`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OffsetsFormat {
+    /// One line per field, with a header.
+    #[default]
+    #[value(name = "csv")]
+    Csv,
+    #[value(name = "json")]
+    Json,
+}
+
+/// Output format of the summary of `batch`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BatchFormat {
+    /// Human-readable table.
+    #[default]
+    #[value(name = "table")]
+    Table,
+    #[value(name = "json")]
+    Json,
+}
+
+/// Level of the log messages that are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    #[value(name = "off")]
+    Off,
+    #[value(name = "error")]
+    Error,
+    #[value(name = "warn")]
+    Warn,
+    #[value(name = "info")]
+    Info,
+    #[value(name = "debug")]
+    Debug,
+    #[value(name = "trace")]
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Format of the log messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// One human-readable line per message.
+    #[default]
+    #[value(name = "text")]
+    Text,
+    /// One JSON object per message, with the code and parameters of the
+    /// warnings about the profile, see `btf2json::diagnostics`.
+    #[value(name = "json")]
+    Json,
+}
+
+/// Other tool whose conventions the ISF file follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compat {
+    /// Name anonymous types `__unnamed_<n>`, numbered in the order of their
+    /// IDs, and anonymous members `unnamed_field_<offset>`, with the byte
+    /// offset in hex.
+    #[value(name = "dwarf2json")]
+    Dwarf2json,
+}
+
+/// Adjustment of the ISF file to the expectations of Volatility, see
+/// `--quirks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Quirk {
+    /// Add the base type `pointer`, with the size of a pointer.
+    #[value(name = "pointer-base")]
+    PointerBase,
+    /// Add the standard spellings of the C base types that are missing, e.g.,
+    /// `unsigned long`, as aliases or synthesized.
+    #[value(name = "standard-base")]
+    StandardBase,
+    /// Set the type of symbols whose type is undefined to `void`.
+    #[value(name = "void-symbols")]
+    VoidSymbols,
+    /// Add empty stubs for the undefined structs, unions, and enums that
+    /// fields of user types reference, also disabled by `--no-fixups`.
+    #[value(name = "type-stubs")]
+    TypeStubs,
+}
+
+/// How much of the paths of the inputs `--record-invocation` records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RecordInvocation {
+    /// Only the file names, which do not reveal the local directories.
+    #[default]
+    Basenames,
+    /// The absolute paths.
+    Full,
+}
+
+/// Symbols whose contents are attached by `--constant-data` without a list.
+pub const DEFAULT_CONSTANT_DATA: [&str; 2] = ["init_uts_ns", "linux_proc_banner"];
+
+/// BTF of the running kernel, used with `--live`.
+pub const LIVE_BTF: &str = "/sys/kernel/btf/vmlinux";
+/// Symbols of the running kernel, used with `--live`.
+pub const LIVE_KALLSYMS: &str = "/proc/kallsyms";
+/// Banner of the running kernel, used with `--live`.
+pub const LIVE_BANNER: &str = "/proc/version";
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Generate an ISF file.
+    Generate(Box<GenerateArgs>),
+    /// Print a single type or symbol of the ISF file that `generate` would
+    /// write.
+    Query(Box<QueryArgs>),
+    /// Print statistics about the types in the BTF file and the symbols that
+    /// got types.
+    Stats(Box<StatsArgs>),
+    /// Write the dependency graph of the types in the Graphviz dot format.
+    Graph(Box<GraphArgs>),
+    /// Print the offsets, sizes, and types of members of structs and unions.
+    Offsets(Box<OffsetsArgs>),
+    /// Write a Volatility 2 Linux profile, a zip file with the vtypes of the
+    /// user types and the System.map.
+    Vol2(Box<Vol2Args>),
+    /// List the distinct Linux banners in a memory image, e.g., to tell which
+    /// kernel it is of.
+    Banners(Box<BannersArgs>),
+    /// Generate the ISF files of many kernels, e.g., of a distribution
+    /// archive, and print a summary.
+    Batch(Box<BatchArgs>),
+    /// Merge two ISF files, e.g., the profile of vmlinux and of a module, into
+    /// one.
+    Merge(Box<MergeArgs>),
+    /// Print the entries of the symdb or check that they are valid.
+    Symdb(Box<SymdbArgs>),
+    /// Check that all types referenced by symbols and fields of user types are
+    /// defined in an existing ISF file, and that it matches the ISF JSON
+    /// schema.
+    Check {
+        /// ISF file, optionally gzip or xz compressed.
+        ///
+        /// Use `-` to read from standard input.
+        file: PathBuf,
+    },
+    /// Check an existing ISF file against the ISF JSON schema.
+    Validate {
+        /// ISF file, optionally gzip or xz compressed.
+        ///
+        /// Use `-` to read from standard input.
+        file: PathBuf,
+    },
+}
+
+/// Options for generating an ISF file.
+#[derive(Args, Debug, Clone, Default)]
+pub struct GenerateArgs {
     #[clap(long = "btf")]
     /// BTF file for obtaining type information (can also be a kernel image).
+    ///
+    /// A debug vmlinux is sufficient on its own: the symbols are read from its
+    /// symbol table and the banner from `linux_banner`. Use `-` to read from
+    /// standard input.
     pub btf: Option<PathBuf>,
+    #[clap(long = "dwarf", conflicts_with_all = ["btf", "package", "live", "image"])]
+    /// Debug vmlinux to read type information from its DWARF debug information
+    /// instead of BTF, for kernels without BTF.
+    ///
+    /// Structs, unions, enums, typedefs, arrays, pointers, bitfields, and the
+    /// types of variables and functions are converted. As with `--btf`, the
+    /// symbols are read from its symbol table and the banner from
+    /// `linux_banner`.
+    pub dwarf: Option<PathBuf>,
+    #[clap(long = "base-btf", requires = "btf")]
+    /// Base BTF file if the BTF file is split BTF, e.g., vmlinux for kernel
+    /// modules.
+    pub base_btf: Option<PathBuf>,
+    #[clap(long = "scan", requires = "btf")]
+    /// Search a BTF file that is neither a `.BTF` section nor an ELF file,
+    /// e.g., a firmware dump, for a BTF section at any offset.
+    ///
+    /// Of the candidates with a valid header and string section, the one with
+    /// the most types is used.
+    pub scan: bool,
+    #[clap(long = "module", requires = "btf", conflicts_with_all = ["base_btf", "scan"])]
+    /// Kernel module, a `.ko` file, to generate a profile for, whose BTF is
+    /// split from the vmlinux given with `--btf`.
+    ///
+    /// The profile has the types of vmlinux and the module, and the symbols
+    /// of the symbol sources and of the symbol table of the module. Module
+    /// types that share their name with a different vmlinux type are prefixed
+    /// with the module name, e.g., `foo::file`. Module symbols are not rebased
+    /// and, without `--module-base`, their addresses are offsets into their
+    /// sections, which the metadata records.
+    pub module: Option<PathBuf>,
+    #[clap(long = "module-base", requires = "module", value_parser = parse_section_address)]
+    /// Load address of a section of the module, in hex, e.g.,
+    /// `.text=0xffffffffc0a00000`. Can be repeated.
+    ///
+    /// The load addresses are in /sys/module/<name>/sections/ of the kernel
+    /// that the module is loaded in. Module symbols are moved to the load
+    /// address of their section, symbols of other sections are dropped.
+    pub module_base: Vec<(String, u64)>,
     #[clap(long = "map")]
-    /// System.map file for obtaining symbol names and addresses.
-    pub map: Option<PathBuf>,
+    /// System.map file for obtaining symbol names and addresses. Can be
+    /// repeated.
+    ///
+    /// Use `-` to read from standard input.
+    ///
+    /// Several System.map files, e.g., of the base kernel and of partitions,
+    /// are merged into one source, where a name with different addresses in
+    /// two files is ambiguous like within one file, see `--dup-symbols`. One of
+    /// them must have `_stext`, or the symbol of `--anchor-symbol`.
+    ///
+    /// Symbols are also read from `--kallsyms` and from the symbol table of the
+    /// BTF file if it is vmlinux. All sources are merged, where the System.map
+    /// takes precedence over kallsyms, which takes precedence over the symbol
+    /// table. Addresses are rebased on `_stext` of the source with the highest
+    /// precedence that has it.
+    ///
+    /// Without any of these sources, e.g., if vmlinux is stripped, symbols are
+    /// synthesized from the variables in the BTF data sections instead. Their
+    /// addresses are the ELF section addresses plus the variable offsets,
+    /// anchored at the `.text` section. Only variables with BTF are found,
+    /// usually just the per-CPU variables, and the addresses are only as
+    /// accurate as the section layout of the ELF file. If there are no such
+    /// variables either, `--map` is required.
+    pub map: Vec<PathBuf>,
+    #[clap(long = "kallsyms")]
+    /// kallsyms file, e.g., a copy of /proc/kallsyms, for obtaining symbol
+    /// names and addresses.
+    ///
+    /// Merged with the other symbol sources, see `--map`. Module symbols are
+    /// ignored. Use `-` to read from standard input.
+    pub kallsyms: Option<PathBuf>,
     #[clap(long = "banner")]
     /// Linux banner.
     ///
     /// Mandatory if using a BTF file for type information. Takes precedence
     /// over all other possible sources of banner information.
     pub banner: Option<String>,
+    #[clap(long = "banner-file", value_name = "PATH")]
+    /// File with the Linux banner, e.g., a copy of /proc/version.
+    ///
+    /// Avoids quoting the parentheses and `#` of the banner on the command
+    /// line. The contents are the banner, normalized like the one of
+    /// `--banner`, i.e., a trailing newline is optional, or used byte for byte
+    /// with `--banner-raw`. Files that are empty or larger than 1 KiB are
+    /// rejected. `--banner` takes precedence, this file over the banner in
+    /// vmlinux or in a memory image.
+    pub banner_file: Option<PathBuf>,
+    #[clap(long = "banner-raw")]
+    /// Use the banner as is.
+    ///
+    /// By default, trailing NULs are removed and a trailing newline is added if
+    /// missing, as the banner in memory ends with exactly one newline.
+    pub banner_raw: bool,
+    #[clap(long = "kernel-version", value_name = "X.Y.Z")]
+    /// Kernel version, e.g., `6.1` or `5.15.0-91-generic`, for banners whose
+    /// version cannot be parsed.
+    ///
+    /// Takes precedence over the version in the banner for everything that
+    /// depends on it, i.e., the versioned entries of the symdb, the signedness
+    /// of `char`, and the detection of randomized layouts. Suffixes like
+    /// `-rc3` are ignored.
+    pub kernel_version: Option<KernelVersion>,
+    #[clap(long = "linux-banner-addr", value_parser = parse_address)]
+    /// Address of `linux_banner`, in hex, if the symbol sources lack it.
+    ///
+    /// Without the symbol, `linux_banner` is added with the known banner, at
+    /// its address in the symbol table of vmlinux, at this address, or at 0,
+    /// in this order. Volatility finds a banner at address 0 by its contents.
+    pub linux_banner_addr: Option<u64>,
+    #[clap(long = "base-offset", value_parser = parse_address)]
+    /// Address that `_stext`, or the symbol of `--anchor-symbol`, is moved to,
+    /// in hex.
+    ///
+    /// Symbol addresses are shifted by the same amount. Overrides the default
+    /// of the architecture, e.g., for kernels with a non-default
+    /// CONFIG_PHYSICAL_START.
+    pub base_offset: Option<u64>,
+    #[clap(long = "anchor-symbol", conflicts_with = "no_rebase")]
+    /// Symbol that is moved to the base offset instead of `_stext`.
+    ///
+    /// Its address in the symbol sources is pinned to the base offset, and
+    /// all other addresses are shifted by the same amount. The default base
+    /// offset is the address of `_stext` of the architecture, so give the
+    /// address of the anchor with `--base-offset` unless it is at the same
+    /// address, e.g., `--anchor-symbol _text --base-offset 0xffffffff81000000`.
+    /// On arm64, the default base offset is derived from the address of the
+    /// anchor.
+    pub anchor_symbol: Option<String>,
+    #[clap(long = "va-bits", value_enum, conflicts_with_all = ["base_offset", "no_rebase"])]
+    /// Virtual address bits of an arm64 kernel, which determine the default
+    /// base offset.
+    ///
+    /// By default, they are derived from the high bits of the address of
+    /// `_stext`, falling back to 48 bits. Other architectures ignore them.
+    pub va_bits: Option<VaBits>,
+    #[clap(long = "no-rebase", conflicts_with = "base_offset")]
+    /// Keep the symbol addresses as they are in the symbol source.
+    ///
+    /// By default, addresses are shifted such that `_stext` is at the base
+    /// offset, which removes the KASLR shift of kallsyms. Use this flag if the
+    /// profile is for the memory that the addresses were taken from.
+    pub no_rebase: bool,
+    #[clap(long = "constant-data", num_args = 0..=1, value_delimiter = ',')]
+    /// Attach the contents of these comma-separated data symbols as constant
+    /// data, read from the BTF file if it is vmlinux.
+    ///
+    /// Without a list, the contents of `init_uts_ns` and `linux_proc_banner`
+    /// are attached.
+    pub constant_data: Option<Vec<String>>,
+    #[clap(long = "symbol-filter")]
+    /// Only include symbols whose name matches this regular expression.
+    ///
+    /// `linux_banner` and the symbol that addresses are rebased on are always
+    /// included.
+    pub symbol_filter: Option<Regex>,
+    #[clap(long = "symbol-exclude")]
+    /// Exclude symbols whose name matches this regular expression, e.g.,
+    /// `^__(kstrtab|kcrctab|ksymtab)`.
+    ///
+    /// `linux_banner` and the symbol that addresses are rebased on are never
+    /// excluded.
+    pub symbol_exclude: Option<Regex>,
+    #[clap(long = "symbol-kinds", value_delimiter = ',', value_parser = parse_symbol_kind)]
+    /// Only include symbols of these comma-separated kinds, the type letters
+    /// of `nm`, e.g., `T,D,B,R`. The case of the letters does not matter.
+    ///
+    /// The kinds are `T` (text), `D` (data), `B` (bss), `R` (read-only data),
+    /// `W` and `V` (weak), and `A` (absolute). `linux_banner` and the symbol
+    /// that addresses are rebased on are always included.
+    pub symbol_kinds: Option<Vec<SymbolKind>>,
+    #[clap(long = "globals-only")]
+    /// Only include global symbols, i.e., drop the ones with a lowercase type
+    /// letter, like local labels and `static` variables.
+    ///
+    /// `linux_banner` and the symbol that addresses are rebased on are always
+    /// included.
+    pub globals_only: bool,
+    #[clap(long = "demangle-rust")]
+    /// Also add symbols with v0-mangled Rust names, of kernels with Rust
+    /// support, under their demangled names, e.g., `kernel::print::call_printk`.
+    ///
+    /// The mangled names are kept. A demangled name that another symbol has,
+    /// or that several symbols demangle to, is skipped with a warning. The
+    /// symbol filters apply to both names.
+    pub demangle_rust: bool,
+    #[clap(long = "symdb")]
+    /// Database of symbol types, used for symbols without type in the BTF file.
+    ///
+    /// Each line is a symbol name and its ISF type descriptor in JSON,
+    /// separated by a space. Optional requirements in between, e.g.,
+    /// `>=5.15 <6.2`, restrict the entry to these versions of the kernel in the
+    /// banner. Entries take precedence over the embedded database, or over the
+    /// installed one of builds without the `embedded-symdb` feature, which is
+    /// `$BTF2JSON_SYMDB` or `$XDG_DATA_HOME/btf2json/symdb`. Use `-` to read
+    /// from standard input.
+    ///
+    /// Lines that are not valid entries are skipped with a warning, `btf2json
+    /// symdb check` lists them.
+    pub symdb: Option<PathBuf>,
+    #[clap(long = "package", conflicts_with_all = ["btf", "base_btf", "image"])]
+    /// Kernel package to read vmlinux and System.map from, a `.deb` or `.rpm`
+    /// file, e.g., `linux-image-*-dbg.deb` or `kernel-debuginfo-*.rpm`.
+    ///
+    /// The files are found by their path in the package and extracted into
+    /// memory. `--map` takes precedence over the System.map in the package.
+    /// Use `-` to read from standard input.
+    pub package: Option<PathBuf>,
+    #[clap(
+        long = "search-dir",
+        value_name = "DIR",
+        conflicts_with_all = ["btf", "dwarf", "base_btf", "package", "live"]
+    )]
+    /// Directory with kernels, named as in `/boot`, to take the one with the
+    /// banner from, e.g., of `--banner` or of a memory image.
+    ///
+    /// Each `vmlinux-<release>` or `vmlinuz-<release>` is read for its banner,
+    /// which must match the banner exactly, apart from trailing newlines.
+    /// `System.map-<release>` is used unless `--map` is given. If no kernel
+    /// matches, the kernels of the same release are listed, e.g., of another
+    /// build. Several matches are an error. With `--cache-dir`, the banners are
+    /// kept there, so that later searches only read new or modified kernels.
+    pub search_dir: Option<PathBuf>,
+    #[clap(long = "live", conflicts_with_all = ["btf", "base_btf", "map", "kallsyms", "image", "package"])]
+    /// Generate a profile for the running kernel.
+    ///
+    /// Reads type information from /sys/kernel/btf/vmlinux, symbols from
+    /// /proc/kallsyms, and the banner from /proc/version.
+    pub live: bool,
+    #[clap(long = "types-only")]
+    /// Generate a profile without symbol information.
+    ///
+    /// No symbol source is required. If a banner is available it is attached to
+    /// a synthetic `linux_banner` symbol at address 0.
+    pub types_only: bool,
+    #[clap(long = "rich-functions")]
+    /// Include return and parameter types in function type descriptors.
+    ///
+    /// The additional keys are not part of the ISF schema and ignored by
+    /// Volatility.
+    pub rich_functions: bool,
+    #[clap(long = "max-type-depth", value_name = "N")]
+    /// Resolve chains of at most N typedefs, qualifiers, pointers, and arrays,
+    /// and nest type descriptors at most N levels deep [default: 128].
+    ///
+    /// Longer chains, e.g., of corrupted BTF or generated code, end in `void`
+    /// where they exceed the limit, with a warning naming the type and
+    /// member.
+    pub max_type_depth: Option<usize>,
+    #[clap(long = "emit-fwd-stubs")]
+    /// Emit empty user types for structs and unions that are only declared.
+    ///
+    /// Without stubs, fields that reference such types fail the type check and
+    /// make Volatility error out when they are accessed.
+    pub emit_fwd_stubs: bool,
+    #[clap(long = "compat", value_enum)]
+    /// Follow the naming conventions of another tool, so that the profiles of
+    /// both can be compared.
+    ///
+    /// By default, anonymous types are named `unnamed_<kind>_<id>` and anonymous
+    /// members `unnamed_member_<index>`.
+    pub compat: Option<Compat>,
+    #[clap(long = "stable-anon-names", conflicts_with = "compat")]
+    /// Name anonymous types after a hash of their content instead of their ID.
+    ///
+    /// BTF IDs change with every build, the content of a type only if its
+    /// definition changes. Types with the same hash get a numeric suffix.
+    pub stable_anon_names: bool,
+    #[clap(long = "keep-duplicate-types")]
+    /// Keep all types with conflicting definitions of the same name.
+    ///
+    /// By default only the most complete definition is kept. With this flag
+    /// the others are kept as `<name>__btf<id>`.
+    pub keep_duplicate_types: bool,
+    #[clap(long = "no-typedef-aliases")]
+    /// Emit user types and enums only under their primary name.
+    ///
+    /// By default they are also emitted under each of their typedef names. The
+    /// primary name is the tag name, or the first typedef of anonymous types.
+    /// References to the other names are rewritten to the primary name.
+    pub no_typedef_aliases: bool,
+    #[clap(long = "drop-colliding-aliases")]
+    /// Drop typedef aliases whose name is also used by a type of another
+    /// category.
+    ///
+    /// A real name beats a typedef alias, then user types beat enums, which
+    /// beat base types. By default the losing entries are kept. References to
+    /// dropped aliases are rewritten to another name of the same type.
+    pub drop_colliding_aliases: bool,
+    #[clap(long = "include-types")]
+    /// Only include types whose name matches this regular expression.
+    ///
+    /// Fields of included types that reference a dropped type are reported as
+    /// undefined. The base types of enums, `void`, and `pointer` are always
+    /// kept.
+    pub include_types: Option<Regex>,
+    #[clap(long = "exclude-types")]
+    /// Exclude types whose name matches this regular expression, e.g.,
+    /// `^trace_event_raw_`.
+    ///
+    /// Same caveats as for `--include-types`.
+    pub exclude_types: Option<Regex>,
+    #[clap(long = "prune")]
+    /// Only emit types that are reachable from the types of the symbols or
+    /// from the types given with `--roots`.
+    ///
+    /// Types are reached through struct and union fields, array, pointer, and
+    /// bitfield subtypes, and the base types of enums.
+    pub prune: bool,
+    #[clap(long = "roots", value_delimiter = ',', requires = "prune")]
+    /// Comma-separated names of types that are kept by `--prune` even if no
+    /// symbol references them, e.g., `task_struct,mm_struct`.
+    pub roots: Option<Vec<String>>,
+    #[clap(long = "exclude-anon-types")]
+    /// Drop anonymous structs and unions that no symbol and no field of
+    /// another type references.
+    ///
+    /// Anonymous members keep their types, and anonymous enums are always
+    /// kept.
+    pub exclude_anon_types: bool,
+    #[clap(long = "patch", value_name = "PATH")]
+    /// Merge the JSON overlay at PATH into the profile before it is verified,
+    /// e.g., to add a struct that the BTF lacks or to fix an offset.
+    ///
+    /// The overlay has the `user_types`, `enums`, `base_types`, and `symbols`
+    /// sections of an ISF file, each with a subset of the entries. An entry
+    /// replaces the one of the same name or is added, and `null` deletes the
+    /// entry. Malformed entries are rejected with their section and name.
+    pub patch: Option<PathBuf>,
+    #[clap(long = "trace-type", value_name = "NAME")]
+    /// Log how the user types named NAME are generated, whatever the log
+    /// level. Can be repeated.
+    ///
+    /// NAME matches the tag and the typedef names. For each matching type,
+    /// its BTF ID is logged and, for each member, its raw bit offset, the
+    /// resolution of its type, its name, its bitfield, and the resulting
+    /// field.
+    pub trace_type: Vec<String>,
+    #[clap(long = "output", short = 'o', conflicts_with_all = ["bundle", "output_dir"])]
+    /// Write the ISF to this file instead of printing it.
+    ///
+    /// The file is compressed according to its extension, i.e., `.gz` or
+    /// `.xz`, unless `--compress` is given. It only replaces an existing file
+    /// once it was written completely.
+    pub output: Option<PathBuf>,
+    #[clap(long = "compress", value_enum, requires = "output")]
+    /// Compression of the file written with `--output`, overriding the one
+    /// implied by the extension.
+    pub compress: Option<Compression>,
+    #[clap(long = "annotate-btf-ids", requires = "output")]
+    /// Write the BTF types that the user types, enums, and base types come
+    /// from to `<output>.provenance.json`.
+    ///
+    /// Each entry maps the ISF name to the ID of the BTF type, the name of
+    /// that type if the entry is named after one of its typedefs, and the BTF
+    /// file that defines the type. Types that are not in BTF, like stubs, are
+    /// missing.
+    pub annotate_btf_ids: bool,
+    #[clap(long = "output-dir", conflicts_with = "bundle")]
+    /// Write the xz-compressed ISF to this directory, named after the banner,
+    /// and print the path of the file.
+    ///
+    /// The name is the URL-safe base64 of the banner with the extension
+    /// `.json.xz`, so that a directory can hold the profiles of many kernels.
+    /// The directory is created if needed.
+    pub output_dir: Option<PathBuf>,
+    #[clap(long = "force", requires = "output_dir")]
+    /// Replace an existing file for the same banner in `--output-dir`.
+    pub force: bool,
+    #[clap(long = "banners-index", value_name = "PATH", requires = "output_dir")]
+    /// Add the file written to `--output-dir` to the banners index at PATH, as
+    /// read by Volatility 3 with `--remote-isf-url`.
+    ///
+    /// The index maps the base64 banners to the ISF files, by their path
+    /// relative to the directory of the index if they are below it. It is
+    /// created if needed, and updated under a lock, so that profiles can be
+    /// generated in parallel.
+    pub banners_index: Option<PathBuf>,
+    #[clap(long = "progress")]
+    /// Show the progress of the major phases on standard error, as periodic
+    /// lines if it is not a terminal.
+    pub progress: bool,
+    #[clap(long = "timings")]
+    /// Print the time spent in each phase of the generation on standard
+    /// error at the end, e.g., reading and scanning the BTF, gathering and
+    /// typing the symbols, constructing the types, verifying, and writing.
+    pub timings: bool,
+    #[clap(long = "cache-dir", value_name = "DIR")]
+    /// Keep the analysis of the BTF types in DIR, by the hash of the BTF
+    /// section, and reuse it on later runs with the same BTF.
+    ///
+    /// Entries of other versions of btf2json are ignored and replaced, corrupted
+    /// ones are rescanned with a warning.
+    pub cache_dir: Option<PathBuf>,
+    #[clap(long = "format-version", value_enum, default_value_t = FormatVersion::default())]
+    /// ISF format version of the generated ISF file.
+    ///
+    /// Older versions omit what their schema does not define. Only 6.2.0
+    /// profiles can be checked with `--validate`.
+    pub format_version: FormatVersion,
+    #[clap(long = "validate")]
+    /// Check the generated ISF against the ISF JSON schema before writing it,
+    /// and fail if it does not match.
+    pub validate: bool,
+    #[clap(long = "check")]
+    /// Verify that all types referenced by fields of user types, enums, and
+    /// symbols are defined, that the type descriptors are well-formed, and
+    /// that the fields fit into their user types.
+    ///
+    /// Symbols are checked before those of undefined types are set to `void`.
+    /// Bitfields must be of base types or enums, and the `pointer` base type
+    /// must be defined if there are pointers.
+    ///
+    /// Bitfields that exceed their type, as in some vendor BTF, are clamped to
+    /// its end with a warning, and fail the check.
+    ///
+    /// The check always runs in debug builds and with `--bundle` or
+    /// `--strict`. Problems are logged, but only fail with `--strict`.
+    pub check: bool,
+    #[clap(long = "check-report")]
+    /// Write the results of the checks as JSON to this file, implies
+    /// `--check`.
+    ///
+    /// Lists the fields of each user type that reference undefined types, with
+    /// the kind and name of the undefined type, the enums with undefined base
+    /// types, the symbols of undefined types, the malformed type descriptors,
+    /// and a summary.
+    pub check_report: Option<PathBuf>,
+    #[clap(long = "untyped-report", value_name = "PATH")]
+    /// Write the symbols that have no type, i.e., `void`, as JSON to PATH.
+    ///
+    /// The symbols are grouped by their kind, e.g., `T` or `D`, each with
+    /// whether no source has a type for it or its type is undefined. Totals
+    /// and the number of symbols typed by the symdb and by BTF variables and
+    /// functions are included.
+    pub untyped_report: Option<PathBuf>,
+    #[clap(long = "no-fixups")]
+    /// Leave references to undefined types in fields of user types as they
+    /// are.
+    ///
+    /// By default, empty stubs are added for undefined structs, unions, and
+    /// enums, so that Volatility does not fail when it follows such a field.
+    /// Same as leaving out the quirk `type-stubs`, see `--quirks`.
+    pub no_fixups: bool,
+    #[clap(long = "no-quirks")]
+    /// Translate the BTF as it is, without adjusting the ISF file to the
+    /// expectations of Volatility, which may then fail to load it.
+    ///
+    /// No `pointer` and standard base types are added, symbols keep their
+    /// undefined types, and no stubs are added for undefined types.
+    pub no_quirks: bool,
+    #[clap(
+        long = "quirks",
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with = "no_quirks"
+    )]
+    /// Apply only the listed quirks, i.e., adjustments of the ISF file to the
+    /// expectations of Volatility, instead of all of them.
+    pub quirks: Option<Vec<Quirk>>,
+    #[clap(long = "strict")]
+    /// Fail if BTF types cannot be parsed or constructed, or if symbols or
+    /// fields of user types reference undefined types, before writing any
+    /// output.
+    ///
+    /// The exit code is 1 if the generation failed, 2 if a verification failed,
+    /// and 3 if the arguments are invalid.
+    pub strict: bool,
+    #[clap(long = "warnings-as-errors")]
+    /// Fail with exit code 2, before writing any output, if the generation
+    /// logged warnings about the profile, e.g., about undefined types, as
+    /// counted in the summary at the end of the run.
+    pub warnings_as_errors: bool,
+    #[clap(long = "no-consistency-check")]
+    /// Skip comparing the release in the banner with the one in vmlinux, and
+    /// the addresses of landmark symbols in the System.map with the ones in
+    /// the symbol table of vmlinux.
+    ///
+    /// Mismatches are logged as warnings, and fail with `--strict`. Pass this
+    /// for intentionally mixed inputs.
+    pub no_consistency_check: bool,
+    #[clap(long = "bundle")]
+    /// Write a profile bundle to this directory instead of printing the ISF.
+    ///
+    /// The bundle contains the compressed ISF, statistics, verification
+    /// results, the inputs, and a manifest with hashes of all files.
+    pub bundle: Option<PathBuf>,
+    #[clap(long = "reproducible")]
+    /// Omit timestamps from generated files.
+    pub reproducible: bool,
+    #[clap(long = "timestamp", conflicts_with = "reproducible")]
+    /// Record the generation time in the metadata of the ISF file.
+    ///
+    /// Off by default, so that the same inputs produce identical ISF files.
+    pub timestamp: bool,
+    #[clap(long = "hostname", conflicts_with = "reproducible")]
+    /// Record the name of the generating host in the metadata of the ISF file.
+    pub hostname: bool,
+    #[clap(
+        long = "record-invocation",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "basenames"
+    )]
+    /// Record the effective configuration in `metadata.producer.invocation`,
+    /// e.g., the architecture, the base offset used for rebasing, the filters,
+    /// and the input files.
+    ///
+    /// The inputs are recorded by their file names, or by their absolute paths
+    /// with `--record-invocation=full`.
+    pub record_invocation: Option<RecordInvocation>,
+    #[clap(long = "comment")]
+    /// Attach a comment, e.g., a ticket ID, to the metadata of the ISF file.
+    ///
+    /// The comment is emitted verbatim as `metadata.producer.comment`, as the
+    /// ISF schema does not allow additional keys directly in `metadata`. An
+    /// empty comment is omitted.
+    pub comment: Option<String>,
+    /// Define the architecture of the system for which the ISF is generated.
+    ///
+    /// By default, it is detected from the ELF header of the BTF source, the
+    /// banner, or the addresses in the System.map. The addresses of 32-bit
+    /// kernels do not tell x86 and ARM apart.
+    #[clap(long = "arch", value_enum, default_value_t = ArchArg::default())]
+    pub arch: ArchArg,
+    /// Byte order of the types, by default the one of the BTF magic.
+    ///
+    /// For BTF carved from memory or flat images whose byte order is in doubt.
+    /// It is the endianness of the base types and decides the bit positions of
+    /// bitfields, and is rejected if `--arch` has the other one.
+    #[clap(long = "endian", value_enum)]
+    pub endian: Option<Endian>,
+    /// What to do with symbol names that appear more than once in the symbol
+    /// source.
+    #[clap(long = "dup-symbols", value_enum, default_value_t = DupSymbols::default())]
+    pub dup_symbols: DupSymbols,
+    /// Generate a profile even if the addresses in the System.map or kallsyms
+    /// file are zero.
+    ///
+    /// The kernel hides the addresses in `/proc/kallsyms` from users other
+    /// than root if `kernel.kptr_restrict` is set, which is detected and
+    /// rejected by default, as none of the symbols of the profile would be
+    /// found.
+    #[clap(long = "allow-zero-addresses")]
+    pub allow_zero_addresses: bool,
+    /// Bring the addresses of the System.map and kallsyms files into the
+    /// canonical form of kernel addresses of the architecture before rebasing
+    /// them.
+    ///
+    /// For maps of tools that drop the upper bits, x86_64 and riscv64
+    /// addresses are sign-extended from 32, 48, or 57 bits, e.g., `81000000`
+    /// to `ffffffff81000000`, riscv64 also from 39 bits. The tags in the top
+    /// byte of arm64 addresses are replaced with the sign of bit 55. Addresses
+    /// below 2^31, e.g., of per-CPU variables, are kept. A warning tells how
+    /// many addresses were fixed.
+    #[clap(long = "canonicalize-addresses")]
+    pub canonicalize_addresses: bool,
+    /// What to do with symbol addresses that do not fit the pointer width of the
+    /// architecture.
+    #[clap(long = "address-overflow", value_enum, default_value_t = AddressOverflow::default())]
+    pub address_overflow: AddressOverflow,
+    /// Memory image to extract type and/or symbol information from.
+    ///
+    /// Without another symbol source, the symbols are recovered from the
+    /// kallsyms tables in the image. For ELF vmcores, e.g., from kdump, the
+    /// banner is read from the address of `linux_banner` in the System.map
+    /// given with `--map`, or else in the kallsyms tables. Other images are
+    /// searched for the banner, and need `--arch` unless the banner tells it.
+    /// LiME images are only searched in their ranges of physical memory.
+    #[clap(long = "image")]
+    pub image: Option<PathBuf>,
+    #[clap(long = "image-format", value_enum, default_value_t = ImageFormat::default(), requires = "image")]
+    /// Layout of the memory image.
+    pub image_format: ImageFormat,
+    #[clap(
+        long = "phys-base",
+        value_parser = parse_address,
+        requires_all = ["image", "kernel_virt_base"]
+    )]
+    /// Physical address, in hex, that the kernel virtual address of
+    /// `--kernel-virt-base` maps to in a raw or LiME memory image.
+    ///
+    /// Virtual addresses of the kernel image are translated to physical ones
+    /// by `phys = virt - kernel_virt_base + phys_base`, to read the banner at
+    /// the address of `linux_banner` in the System.map. Without these options,
+    /// common placements of the kernel of the architecture are tried, and the
+    /// one that was assumed is logged. If none holds the banner, the image is
+    /// searched for it.
+    pub phys_base: Option<u64>,
+    #[clap(long = "kernel-virt-base", value_parser = parse_address, requires = "phys_base")]
+    /// Kernel virtual address, in hex, that maps to `--phys-base`, e.g.,
+    /// `0xffffffff80000000` on x86_64.
+    pub kernel_virt_base: Option<u64>,
+}
+
+/// Options for printing a single type or symbol.
+#[derive(Args, Debug)]
+#[clap(group(ArgGroup::new("query").required(true).args(["type_name", "symbol"])))]
+pub struct QueryArgs {
+    #[clap(long = "type")]
+    /// Print the type with this name, which can also be a typedef alias or the
+    /// placeholder name of an anonymous type.
+    pub type_name: Option<String>,
+    #[clap(long = "symbol")]
+    /// Print the address and type of the symbol with this name.
+    ///
+    /// Requires a symbol source, e.g., `--map`, and a banner, as for
+    /// `generate`.
+    pub symbol: Option<String>,
+    #[clap(long = "c-style")]
+    /// Print a C-like declaration with offsets and sizes instead of the ISF
+    /// JSON fragment.
+    pub c_style: bool,
+    /// Inputs as for `generate`, options about the output are ignored.
+    #[clap(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options for printing statistics.
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[clap(long = "format", value_enum, default_value_t = StatsFormat::default())]
+    /// Output format.
+    pub format: StatsFormat,
+    /// Inputs as for `generate`, the banner is optional and options about the
+    /// output are ignored.
+    ///
+    /// Symbols are only counted if a symbol source is given.
+    #[clap(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options for listing the banners in a memory image.
+#[derive(Args, Debug)]
+pub struct BannersArgs {
+    #[clap(long = "image")]
+    /// Memory image to search for banners.
+    ///
+    /// ELF vmcores are only searched in their loadable segments and LiME
+    /// images in their ranges, see `--image-format`.
+    pub image: PathBuf,
+    #[clap(long = "image-format", value_enum, default_value_t = ImageFormat::default())]
+    /// Layout of the memory image.
+    pub image_format: ImageFormat,
+    #[clap(long = "format", value_enum, default_value_t = BannersFormat::default())]
+    /// Output format.
+    pub format: BannersFormat,
+}
+
+/// Options for generating the ISF files of many kernels.
+#[derive(Args, Debug)]
+#[clap(group(ArgGroup::new("kernels").required(true).args(["input_dir", "manifest"])))]
+pub struct BatchArgs {
+    #[clap(long = "input-dir", value_name = "DIR")]
+    /// Directory with the kernels, as in `/boot`.
+    ///
+    /// Each `vmlinux-<release>` or, if there is none, `vmlinuz-<release>` is
+    /// generated with `System.map-<release>` if there is one. System.map files
+    /// without a kernel are reported as failures.
+    pub input_dir: Option<PathBuf>,
+    #[clap(long = "manifest", value_name = "PATH")]
+    /// JSON file with the kernels, for layouts that do not follow the naming
+    /// of `/boot`.
+    ///
+    /// It is a list of objects with the kernel or BTF file `btf`, and the
+    /// optional System.map `map` and `banner`, e.g.,
+    /// `[{"btf": "6.1/vmlinux", "map": "6.1/System.map"}]`. Relative paths are
+    /// relative to the directory of the manifest.
+    pub manifest: Option<PathBuf>,
+    #[clap(long = "jobs", short = 'j', value_name = "N", default_value_t = 1)]
+    /// Generate N kernels in parallel, one per thread.
+    pub jobs: usize,
+    #[clap(long = "format", value_enum, default_value_t = BatchFormat::default())]
+    /// Format of the summary.
+    pub format: BatchFormat,
+    /// Options as for `generate`, which apply to every kernel.
+    ///
+    /// `--output-dir` is required, the files are named after the banners. The
+    /// inputs of the kernels, like `--btf`, `--map`, or `--banner`, cannot be
+    /// given.
+    #[clap(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options for writing the dependency graph of the types.
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    #[clap(long = "root", value_name = "NAME")]
+    /// Only include the types that are reachable from the type NAME. Can be
+    /// repeated.
+    ///
+    /// Without roots, the graph has all types, which is large for a kernel.
+    pub root: Vec<String>,
+    #[clap(long = "depth", requires = "root")]
+    /// Only follow this many dependencies from the roots.
+    pub depth: Option<usize>,
+    /// Inputs as for `generate`, the graph is written to `--output` or printed.
+    ///
+    /// Types depend on each other as for `--prune`: user types on the types of
+    /// their fields, also through pointers, arrays, and functions, and enums on
+    /// their base type.
+    #[clap(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options for printing the offsets of members.
+#[derive(Args, Debug)]
+pub struct OffsetsArgs {
+    #[clap(
+        long = "fields",
+        value_name = "TYPE.MEMBER",
+        value_delimiter = ',',
+        required = true
+    )]
+    /// Members to look up, e.g., `task_struct.comm,mm_struct.pgd`. Can be
+    /// repeated.
+    ///
+    /// The type is a struct, a union, or a typedef of one. Members of
+    /// anonymous members are found as members of their parent, and members of
+    /// members are separated by dots, e.g., `task_struct.se.vruntime`.
+    pub fields: Vec<String>,
+    #[clap(long = "format", value_enum, default_value_t = OffsetsFormat::default())]
+    /// Output format.
+    pub format: OffsetsFormat,
+    /// Inputs as for `generate`, only the BTF is read. The architecture gives
+    /// the size of pointers.
+    #[clap(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options for writing a Volatility 2 profile.
+#[derive(Args, Debug)]
+pub struct Vol2Args {
+    /// Inputs as for `generate`, the profile is written to `--output` or
+    /// printed.
+    ///
+    /// Only the types, and the addresses and kinds of the symbols are in the
+    /// profile, options about the ISF file are ignored.
+    #[clap(flatten)]
+    pub generate: GenerateArgs,
+}
+
+/// Options for merging two ISF files.
+#[derive(Args, Debug)]
+pub struct MergeArgs {
+    /// ISF file to merge into, optionally gzip or xz compressed.
+    pub base: PathBuf,
+    /// ISF file whose entries are added, optionally gzip or xz compressed.
+    pub extra: PathBuf,
+    #[clap(long = "output", short = 'o')]
+    /// Write the merged ISF to this file instead of printing it.
+    ///
+    /// The file is compressed according to its extension, i.e., `.gz` or
+    /// `.xz`, unless `--compress` is given.
+    pub output: Option<PathBuf>,
+    #[clap(long = "compress", value_enum, requires = "output")]
+    /// Compression of the file written with `--output`, overriding the one
+    /// implied by the extension.
+    pub compress: Option<Compression>,
+    #[clap(long = "prefer", value_enum, default_value_t = Prefer::default())]
+    /// File whose entry is kept if both define a type or symbol differently.
+    ///
+    /// Such conflicts are reported with the differences of the definitions.
+    pub prefer: Prefer,
+    #[clap(long = "force")]
+    /// Merge files whose `linux_banner` differ, i.e., of different kernels.
+    pub force: bool,
+}
+
+/// Options for printing and checking a symdb.
+#[derive(Args, Debug)]
+pub struct SymdbArgs {
+    #[clap(subcommand)]
+    pub command: SymdbCommand,
+    #[clap(long = "symdb", global = true)]
+    /// Symdb to read instead of the one that `generate` uses by default, i.e.,
+    /// the embedded one, or the installed one of builds without the
+    /// `embedded-symdb` feature.
+    pub symdb: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SymdbCommand {
+    /// Print the entries of the symdb.
+    Dump {
+        #[clap(long = "format", value_enum, default_value_t = SymdbFormat::default())]
+        /// Output format.
+        format: SymdbFormat,
+    },
+    /// Check that each line of the symdb is an entry with a valid type
+    /// descriptor and version requirements, and list the ones that are not.
+    Check,
+}
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "btf2json",
+    author = "Valentin Obst",
+    args_conflicts_with_subcommands = true,
+    arg_required_else_help = true,
+    mut_args = hide_legacy
+)]
+/// Generate Volatility 3 ISF files from BTF type information.
+pub struct Cli {
+    #[clap(flatten)]
+    /// Options of `generate` given without the subcommand.
+    ///
+    /// Deprecated, only accepted so that existing scripts keep working.
+    pub legacy: GenerateArgs,
     #[clap(long = "version")]
     /// Print btf2json version.
     pub version: bool,
-    #[clap(long = "verbose")]
+    #[clap(long = "verbose", global = true)]
     /// Display debug output.
     pub verbose: bool,
-    #[clap(long = "debug")]
+    #[clap(long = "debug", global = true)]
     /// Display more debug output.
     pub debug: bool,
-    /// Define the architecture of the system for which the ISF is generated.
-    #[clap(long = "arch", value_enum, default_value_t = Architecture::default())]
-    pub arch: Architecture,
-    /// Memory image to extract type and/or symbol information from (not
-    /// implemented).
-    #[clap(long = "image")]
-    pub image: Option<PathBuf>,
+    #[clap(long = "log-level", value_name = "LEVEL", global = true)]
+    /// Level of the log messages, overrides --verbose and --debug.
+    pub log_level: Option<LogLevel>,
+    #[clap(long = "log-file", value_name = "PATH", global = true)]
+    /// Write the log messages to PATH, which is truncated, instead of
+    /// standard error, where only warnings and errors remain.
+    pub log_file: Option<PathBuf>,
+    #[clap(long = "log-format", value_enum, default_value_t = LogFormat::default(), global = true)]
+    /// Format of the log messages and of the summary of the warnings.
+    pub log_format: LogFormat,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl GenerateArgs {
+    /// Returns the kernel version of `--kernel-version`, or else the one in
+    /// `banner`.
+    pub fn kernel_version(&self, banner: Option<&Banner>) -> Option<KernelVersion> {
+        self.kernel_version
+            .or_else(|| banner.and_then(Banner::kernel_version))
+    }
+
+    /// Returns the quirks to apply, all of them by default.
+    pub fn enabled_quirks(&self) -> Vec<Quirk> {
+        if self.no_quirks {
+            return Vec::new();
+        }
+        let mut quirks = match &self.quirks {
+            Some(quirks) => quirks.clone(),
+            None => Quirk::value_variants().to_vec(),
+        };
+        if self.no_fixups {
+            quirks.retain(|quirk| *quirk != Quirk::TypeStubs);
+        }
+        quirks
+    }
+}
+
+impl Cli {
+    /// Returns the options for generating an ISF file, given to `generate` or,
+    /// deprecated, without a subcommand.
+    pub fn generate_args(&self) -> &GenerateArgs {
+        match &self.command {
+            Some(Command::Generate(args)) => args,
+            _ => &self.legacy,
+        }
+    }
+
+    /// Returns the level of the log messages, errors only by default.
+    pub fn log_level(&self) -> log::LevelFilter {
+        match self.log_level {
+            Some(level) => level.into(),
+            None if self.debug => log::LevelFilter::Trace,
+            None if self.verbose => log::LevelFilter::Debug,
+            None => log::LevelFilter::Error,
+        }
+    }
+
+    /// Returns true iff an ISF file is generated without the `generate`
+    /// subcommand.
+    pub fn is_legacy(&self) -> bool {
+        self.command.is_none() && !self.version
+    }
+}
+
+/// Hides the legacy options of `generate` from the top-level help.
+fn hide_legacy(arg: Arg) -> Arg {
+    let hide = !arg.is_global_set() && arg.get_id() != "version";
+    arg.hide(hide)
 }
@@ -0,0 +1,253 @@
+//! Cross-checks of the banner, the BTF file, and the symbol sources.
+//!
+//! Inputs of different builds of the same kernel, e.g., a System.map of
+//! another package, produce a profile with wrong addresses without any error.
+//! The checks compare what the inputs tell about each other where they
+//! overlap:
+//!
+//! - the release in the banner and the one in `linux_banner` and
+//!   `init_uts_ns` of vmlinux, and
+//! - the addresses of landmark symbols in each System.map or kallsyms and in
+//!   the symbol table of vmlinux, which may only differ by a uniform shift,
+//!   e.g., due to KASLR.
+//!
+//! Independently, the rebased symbol addresses are checked against the kernel
+//! address range of the architecture.
+
+use crate::btf::Btf;
+use crate::cli::{Architecture, VaBits};
+use crate::symbols::{MapFormat, SymbolKind};
+use crate::{elf, randstruct, version, GenerationContext};
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use clap::ValueEnum;
+
+/// Symbols whose addresses are compared between the symbol sources.
+pub const LANDMARKS: &[&str] = &[
+    "_stext",
+    "_text",
+    "_etext",
+    "_sdata",
+    "_edata",
+    "__bss_start",
+    "_end",
+    "linux_banner",
+    "init_task",
+    "init_uts_ns",
+    "jiffies",
+];
+
+/// Maximum length of the release in `struct new_utsname`, including the NUL.
+const UTS_LEN: usize = 65;
+
+/// Maximum share of symbols outside the kernel address range that is not
+/// reported, e.g., symbols of the vsyscall page.
+const MAX_OUTSIDE_KERNEL_RANGE: f64 = 0.01;
+
+/// A value that two inputs disagree on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// What was compared, e.g., `Kernel release`.
+    pub what: String,
+    /// The names of the two inputs.
+    pub inputs: [String; 2],
+    /// The values the two inputs have.
+    pub values: [String; 2],
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} differs: {} has {}, {} has {}",
+            self.what, self.inputs[0], self.values[0], self.inputs[1], self.values[1]
+        )
+    }
+}
+
+/// Symbols whose addresses fall outside the kernel address range of the
+/// architecture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfRange {
+    pub arch: Architecture,
+    /// Expected range of kernel addresses.
+    pub range: RangeInclusive<u64>,
+    /// Number of symbols outside the range.
+    pub outside: usize,
+    /// Number of symbols that were checked.
+    pub checked: usize,
+    /// Lowest and highest address of the checked symbols.
+    pub addresses: RangeInclusive<u64>,
+}
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}% of symbols fall outside the expected kernel address range {:#x}-{:#x} for {}, \
+             they are at {:#x}-{:#x}; check --arch/--base-offset",
+            self.outside * 100 / self.checked,
+            self.range.start(),
+            self.range.end(),
+            self.arch
+                .to_possible_value()
+                .map(|value| value.get_name().to_owned())
+                .unwrap_or_default(),
+            self.addresses.start(),
+            self.addresses.end()
+        )
+    }
+}
+
+impl GenerationContext {
+    /// Checks that the symbol addresses fall into the kernel address range of
+    /// the architecture, as they do not if the architecture, base offset, or
+    /// anchor symbol do not match the symbol sources.
+    ///
+    /// Per-CPU and absolute symbols, which keep their addresses on rebasing,
+    /// zero addresses, and section offsets of module symbols are not checked.
+    /// A few symbols outside the range are tolerated.
+    pub fn check_address_range(&self) -> Option<OutOfRange> {
+        let va_bits = self
+            .symbols
+            .base_offset()
+            .and_then(VaBits::from_kernel_address);
+        let range = self.arch.kernel_address_range(va_bits)?;
+        let relative_module = self.symbols.module().is_some_and(|m| m.bases.is_empty());
+        let is_text = |kind| matches!(kind, SymbolKind::T | SymbolKind::W);
+        let text_start = self
+            .symbols
+            .iter()
+            .filter(|(_, sym)| is_text(sym.kind()) && sym.address() != 0)
+            .filter(|(_, sym)| sym.section().is_none())
+            .map(|(_, sym)| sym.address())
+            .min()?;
+        let addrs: Vec<u64> = self
+            .symbols
+            .iter()
+            .map(|(_, sym)| sym)
+            .filter(|sym| sym.kind() != SymbolKind::A && sym.address() >= text_start)
+            .filter(|sym| !(relative_module && sym.section().is_some()))
+            .map(|sym| sym.address())
+            .collect();
+        let outside = addrs.iter().filter(|addr| !range.contains(addr)).count();
+        if outside == 0 {
+            return None;
+        }
+        if (outside as f64) / (addrs.len() as f64) <= MAX_OUTSIDE_KERNEL_RANGE {
+            log::debug!(
+                "{} of {} symbols are outside the kernel address range",
+                outside,
+                addrs.len()
+            );
+            return None;
+        }
+        Some(OutOfRange {
+            arch: self.arch,
+            range,
+            outside,
+            checked: addrs.len(),
+            addresses: *addrs.iter().min()?..=*addrs.iter().max()?,
+        })
+    }
+
+    /// Compares the banner, the BTF file, and the symbol sources where they
+    /// overlap, and returns the values they disagree on.
+    ///
+    /// Checks whose inputs are missing, e.g., without an ELF file, are skipped.
+    pub fn check_consistency(&self) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        let raw = self.btf.kernel_raw();
+        if elf::is_elf(&raw).is_err() {
+            return mismatches;
+        }
+        let vmlinux = self.btf.kernel_name().clone();
+
+        if let Some(release) = self
+            .banner
+            .as_ref()
+            .map(|banner| banner.to_string())
+            .as_deref()
+            .and_then(version::release)
+        {
+            let elf_banner = elf::get_banner(&raw).ok();
+            let elf_releases = [
+                (
+                    "linux_banner",
+                    elf_banner.as_deref().and_then(version::release),
+                ),
+                ("init_uts_ns", uts_release(&self.btf, &raw)),
+            ];
+            for (symbol, elf_release) in elf_releases {
+                match elf_release {
+                    Some(elf_release) if elf_release != release => mismatches.push(Mismatch {
+                        what: String::from("Kernel release"),
+                        inputs: [String::from("banner"), format!("{} {}", vmlinux, symbol)],
+                        values: [release.to_owned(), elf_release.to_owned()],
+                    }),
+                    _ => (),
+                }
+            }
+        }
+
+        let Ok(elf_symbols) = elf::symbols(&raw) else {
+            return mismatches;
+        };
+        let elf_addr = |name: &str| {
+            elf_symbols
+                .iter()
+                .find(|(sym, _, _)| sym == name)
+                .map(|(_, addr, _)| *addr)
+        };
+        for source in self.symbols.sources() {
+            if source.format == MapFormat::Symtab {
+                continue;
+            }
+            let Ok(addrs) = source.addresses(LANDMARKS) else {
+                continue;
+            };
+            let mut shift = None;
+            for &name in LANDMARKS {
+                let (Some(&addr), Some(elf_addr)) = (addrs.get(name), elf_addr(name)) else {
+                    continue;
+                };
+                let &mut (first, first_shift) =
+                    shift.get_or_insert((name, addr.wrapping_sub(elf_addr)));
+                if addr.wrapping_sub(elf_addr) != first_shift {
+                    let what = if first_shift == 0 {
+                        format!("Address of {}", name)
+                    } else {
+                        format!(
+                            "Address of {}, shifted by {:#x} like {},",
+                            name, first_shift, first
+                        )
+                    };
+                    mismatches.push(Mismatch {
+                        what,
+                        inputs: [source.name.clone(), vmlinux.clone()],
+                        values: [format!("{:#x}", addr), format!("{:#x}", elf_addr)],
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+}
+
+/// Returns the release in `init_uts_ns` of the ELF file `raw`, whose layout
+/// is read from `btf`.
+fn uts_release<'a>(btf: &Btf, raw: &'a [u8]) -> Option<&'a str> {
+    let ns = randstruct::find_struct(btf, "uts_namespace")?;
+    let name = randstruct::find_member_offset(btf, &ns, "name")?;
+    let utsname = randstruct::find_struct(btf, "new_utsname")?;
+    let offset = (name + randstruct::find_member_offset(btf, &utsname, "release")?) as usize;
+    let bytes = elf::read_symbol_bytes(raw, "init_uts_ns").ok()??;
+    let bytes = bytes.get(offset..)?;
+    let bytes = &bytes[..bytes.len().min(UTS_LEN)];
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    std::str::from_utf8(&bytes[..len])
+        .ok()
+        .filter(|release| !release.is_empty())
+}
@@ -0,0 +1,254 @@
+//! Decompression of compressed kernel images (vmlinuz, bzImage) and ELF
+//! sections.
+
+use crate::elf;
+
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use ruzstd::decoding::StreamingDecoder;
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+
+/// Upper bound for the size of a decompressed kernel.
+const MAX_DECOMPRESSED: u64 = 1 << 30;
+/// Upper bound for the zstd window size.
+const MAX_ZSTD_WINDOW: u64 = 1 << 30;
+/// Size of the uncompressed blocks in the legacy lz4 format.
+const LZ4_LEGACY_BLOCK: usize = 8 << 20;
+
+/// Offset of the number of setup sectors in the bzImage setup header.
+const BZ_SETUP_SECTS: usize = 0x1f1;
+/// Offset of the setup header signature.
+const BZ_HDR_MAGIC_OFF: usize = 0x202;
+const BZ_HDR_MAGIC: &[u8] = b"HdrS";
+/// Offset of the boot protocol version.
+const BZ_VERSION: usize = 0x206;
+/// Offset of the payload offset, relative to the protected-mode code.
+const BZ_PAYLOAD_OFFSET: usize = 0x248;
+/// Offset of the payload length.
+const BZ_PAYLOAD_LENGTH: usize = 0x24c;
+/// First boot protocol version that describes the payload.
+const BZ_MIN_VERSION: u16 = 0x0208;
+
+/// Offset of the magic in the header of an arm64 `Image`.
+const ARM64_MAGIC_OFF: usize = 0x38;
+const ARM64_MAGIC: &[u8] = b"ARM\x64";
+
+/// Compression formats used for kernel images.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Zstd,
+    Lz4,
+    Lzo,
+}
+
+impl Compression {
+    const ALL: [Self; 5] = [Self::Gzip, Self::Xz, Self::Zstd, Self::Lz4, Self::Lzo];
+
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Gzip => &[0x1f, 0x8b, 0x08],
+            Self::Xz => &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00],
+            Self::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            // Legacy frame format, which is what the kernel uses.
+            Self::Lz4 => &[0x02, 0x21, 0x4c, 0x18],
+            Self::Lzo => &[0x89, 0x4c, 0x5a, 0x4f, 0x00, 0x0d, 0x0a, 0x1a, 0x0a],
+        }
+    }
+
+    /// Returns the format whose magic is at the start of `data`, if any.
+    fn detect(data: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| data.starts_with(c.magic()))
+    }
+
+    /// Decompresses the stream at the start of `data`.
+    ///
+    /// Data after the end of the stream is ignored.
+    fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self {
+            Self::Gzip => read_all(GzDecoder::new(data), &mut buf)?,
+            #[cfg(feature = "xz")]
+            Self::Xz => read_all(XzDecoder::new(data), &mut buf)?,
+            #[cfg(not(feature = "xz"))]
+            Self::Xz => bail!("xz-compressed kernel images require the xz feature"),
+            Self::Zstd => read_all(
+                StreamingDecoder::new_with_max_window_size(data, MAX_ZSTD_WINDOW)
+                    .map_err(|err| anyhow::anyhow!("{}", err))?,
+                &mut buf,
+            )?,
+            Self::Lz4 => decompress_lz4_legacy(data, &mut buf)?,
+            Self::Lzo => bail!("LZO-compressed kernel images are not supported"),
+        }
+        Ok(buf)
+    }
+}
+
+/// Compression of ELF sections with zlib, see `SHF_COMPRESSED`.
+const ELFCOMPRESS_ZLIB: u32 = 1;
+/// Compression of ELF sections with zstd.
+const ELFCOMPRESS_ZSTD: u32 = 2;
+
+/// Decompresses the data of a compressed ELF section, which follows the
+/// compression header with the type `ch_type`.
+pub fn decompress_section(ch_type: u32, data: &[u8]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match ch_type {
+        ELFCOMPRESS_ZLIB => read_all(ZlibDecoder::new(data), &mut buf)?,
+        ELFCOMPRESS_ZSTD => read_all(
+            StreamingDecoder::new_with_max_window_size(data, MAX_ZSTD_WINDOW)
+                .map_err(|err| anyhow::anyhow!("{}", err))?,
+            &mut buf,
+        )?,
+        _ => bail!("Unknown compression type {}", ch_type),
+    }
+    Ok(buf)
+}
+
+/// Reads `reader` to the end, but at most [`MAX_DECOMPRESSED`] bytes.
+fn read_all(reader: impl Read, buf: &mut Vec<u8>) -> Result<()> {
+    reader
+        .take(MAX_DECOMPRESSED + 1)
+        .read_to_end(buf)
+        .context("Corrupt or truncated stream")?;
+    if buf.len() as u64 > MAX_DECOMPRESSED {
+        bail!("Decompressed data exceeds {} bytes", MAX_DECOMPRESSED);
+    }
+    Ok(())
+}
+
+/// Decompresses data in the legacy lz4 frame format.
+///
+/// The stream consists of the magic, followed by blocks that are prefixed with
+/// their compressed length. It ends at the end of the data or at the first
+/// length that does not fit the remaining data, e.g., the decompressed size
+/// that the kernel build appends.
+fn decompress_lz4_legacy(data: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+    let magic = Compression::Lz4.magic();
+    let mut rest = &data[magic.len()..];
+    let mut block = vec![0; LZ4_LEGACY_BLOCK];
+    while rest.len() >= 4 {
+        if rest.starts_with(magic) {
+            rest = &rest[magic.len()..];
+            continue;
+        }
+        let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        if len > rest.len() - 4 {
+            break;
+        }
+        let n = lz4_flex::block::decompress_into(&rest[4..4 + len], &mut block)
+            .context("Corrupt lz4 block")?;
+        buf.extend_from_slice(&block[..n]);
+        if buf.len() as u64 > MAX_DECOMPRESSED {
+            bail!("Decompressed data exceeds {} bytes", MAX_DECOMPRESSED);
+        }
+        rest = &rest[4 + len..];
+    }
+    if buf.is_empty() {
+        bail!("Truncated lz4 stream");
+    }
+    Ok(())
+}
+
+/// Returns the location of the compressed kernel in a bzImage, if `raw` is one.
+fn bzimage_payload(raw: &[u8]) -> Option<&[u8]> {
+    let u16_at = |off: usize| Some(u16::from_le_bytes(raw.get(off..off + 2)?.try_into().ok()?));
+    let u32_at = |off: usize| Some(u32::from_le_bytes(raw.get(off..off + 4)?.try_into().ok()?));
+
+    if raw.get(BZ_HDR_MAGIC_OFF..BZ_HDR_MAGIC_OFF + 4)? != BZ_HDR_MAGIC
+        || u16_at(BZ_VERSION)? < BZ_MIN_VERSION
+    {
+        return None;
+    }
+    let setup_sects = match *raw.get(BZ_SETUP_SECTS)? {
+        0 => 4,
+        n => n as usize,
+    };
+    let start = (setup_sects + 1) * 512 + u32_at(BZ_PAYLOAD_OFFSET)? as usize;
+    let len = u32_at(BZ_PAYLOAD_LENGTH)? as usize;
+    log::debug!("bzImage payload at {:#x}, {} bytes", start, len);
+
+    raw.get(start..start.checked_add(len)?)
+}
+
+/// Returns the offsets of all compression magics in `raw`.
+fn find_streams(raw: &[u8]) -> Vec<(Compression, usize)> {
+    let mut streams: Vec<(Compression, usize)> = Compression::ALL
+        .into_iter()
+        .flat_map(|c| memchr::memmem::find_iter(raw, c.magic()).map(move |off| (c, off)))
+        .collect();
+    streams.sort_unstable_by_key(|(_, off)| *off);
+    streams
+}
+
+/// Returns true iff `raw` is a flat arm64 kernel `Image`, which is not an ELF
+/// file.
+pub fn is_arm64_image(raw: &[u8]) -> bool {
+    raw.get(ARM64_MAGIC_OFF..ARM64_MAGIC_OFF + ARM64_MAGIC.len()) == Some(ARM64_MAGIC)
+}
+
+/// Returns true iff `raw` is a bzImage or starts with the magic of a known
+/// compression format.
+pub fn is_compressed(raw: &[u8]) -> bool {
+    Compression::detect(raw).is_some() || bzimage_payload(raw).is_some()
+}
+
+/// Decompresses `raw` if it is a gzip, xz, or zstd stream, e.g., a compressed
+/// `.BTF` section.
+///
+/// Returns `None` if `raw` does not start with the magic of either format.
+pub fn decompress_stream(raw: &[u8]) -> Option<Result<Vec<u8>>> {
+    let compression = Compression::detect(raw)
+        .filter(|c| matches!(c, Compression::Gzip | Compression::Xz | Compression::Zstd))?;
+    log::debug!("Decompressing {:?} stream", compression);
+    Some(compression.decompress(raw))
+}
+
+/// Decompresses a compressed kernel image.
+///
+/// The payload of a bzImage is tried first. Afterwards, all streams in the file
+/// are tried in order until one decompresses to an ELF file or an arm64
+/// `Image`, e.g., of `Image.gz`.
+pub fn decompress_kernel(raw: &[u8]) -> Result<Vec<u8>> {
+    let payload = bzimage_payload(raw).and_then(|p| {
+        Compression::detect(p).map(|c| (c, p.as_ptr() as usize - raw.as_ptr() as usize))
+    });
+    let mut last_err = None;
+
+    let streams = find_streams(raw)
+        .into_iter()
+        .filter(|stream| Some(*stream) != payload);
+
+    for (compression, off) in payload.into_iter().chain(streams) {
+        log::debug!("Trying {:?} stream at {:#x}", compression, off);
+        match compression.decompress(&raw[off..]) {
+            Ok(buf) if elf::is_elf(&buf).is_ok() || is_arm64_image(&buf) => {
+                log::info!(
+                    "Decompressed {:?} kernel at {:#x}: {} bytes",
+                    compression,
+                    off,
+                    buf.len()
+                );
+                return Ok(buf);
+            }
+            Ok(_) => log::debug!(
+                "{:?} stream at {:#x} is not an ELF file or arm64 Image",
+                compression,
+                off
+            ),
+            Err(err) => {
+                log::debug!("{:?} stream at {:#x}: {:#}", compression, off, err);
+                last_err = Some(err.context(format!("{:?} stream at {:#x}", compression, off)));
+            }
+        }
+    }
+
+    match last_err {
+        Some(err) => Err(err.context("Unable to decompress kernel image")),
+        None => bail!("No compressed kernel found"),
+    }
+}
@@ -0,0 +1,338 @@
+//! Warnings of the generation by category, see `--warnings-as-errors`.
+//!
+//! Warnings about problems of the generated profile are logged with the log
+//! target of their category, like the diagnostics of `--trace-type` with
+//! [`crate::TRACE_TARGET`]. [`Collector`] wraps the logger of the application
+//! and counts them, whether or not they are logged at the configured level, so
+//! that [`summary`] can tell how many warnings of which kind a run had.
+//! Warnings about the options, e.g., that `--types-only` ignores the symbol
+//! sources, are not counted.
+//!
+//! The warnings also carry a stable `code`, like `undefined_field_type`, and
+//! their parameters as key-values of the log record. [`write_json`] formats
+//! them for `--log-format json`, with the phase of the code from [`CODES`].
+
+use crate::progress::Phase;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, Log, Metadata, Record};
+use serde_json::{json, Map};
+
+/// Fields of user types whose type is not defined.
+pub const UNDEFINED_FIELDS: &str = "btf2json::warnings::undefined_fields";
+/// Undefined types that fixups replace with stubs, or fail to.
+pub const UNDEFINED_TYPES: &str = "btf2json::warnings::undefined_types";
+/// Symbols whose type is not defined, which become `void`.
+pub const VOIDED_SYMBOLS: &str = "btf2json::warnings::voided_symbols";
+/// BTF types whose record could not be parsed.
+pub const SKIPPED_RECORDS: &str = "btf2json::warnings::skipped_records";
+/// Types that could not be converted.
+pub const SKIPPED_TYPES: &str = "btf2json::warnings::skipped_types";
+/// Members that are left out of their user type.
+pub const SKIPPED_MEMBERS: &str = "btf2json::warnings::skipped_members";
+/// Bitfields that exceed their type or storage unit.
+pub const INVALID_BITFIELDS: &str = "btf2json::warnings::invalid_bitfields";
+/// Types that are repaired, e.g., enums without a base type of their size.
+pub const REPAIRED_TYPES: &str = "btf2json::warnings::repaired_types";
+/// Names of several types or symbols, of which only one is kept.
+pub const NAMING_CONFLICTS: &str = "btf2json::warnings::naming_conflicts";
+/// Symbols that are invalid, dropped, or lose their type or data.
+pub const SYMBOLS: &str = "btf2json::warnings::symbols";
+/// Inputs that do not match each other or what they are expected to be.
+pub const INPUTS: &str = "btf2json::warnings::inputs";
+/// Verifications of the profile that failed.
+pub const VERIFICATION: &str = "btf2json::warnings::verification";
+
+/// Log targets of the categories and their descriptions in the summary.
+const CATEGORIES: &[(&str, &str)] = &[
+    (UNDEFINED_FIELDS, "fields of undefined types"),
+    (UNDEFINED_TYPES, "undefined types"),
+    (VOIDED_SYMBOLS, "symbols of undefined types"),
+    (SKIPPED_RECORDS, "skipped BTF records"),
+    (SKIPPED_TYPES, "skipped types"),
+    (SKIPPED_MEMBERS, "skipped members"),
+    (INVALID_BITFIELDS, "invalid bitfields"),
+    (REPAIRED_TYPES, "repaired types"),
+    (NAMING_CONFLICTS, "naming conflicts"),
+    (SYMBOLS, "problematic symbols"),
+    (INPUTS, "problematic inputs"),
+    (VERIFICATION, "failed verifications"),
+];
+
+/// Codes of the warnings and the phases of the generation they are about.
+///
+/// The codes are stable, new ones may be added.
+pub const CODES: &[(&str, Phase)] = &[
+    // BTF type whose record cannot be parsed, which is skipped.
+    ("unparsable_btf_record", Phase::ScanTypes),
+    // BTF type of a kind that is unknown, which is skipped.
+    ("unknown_btf_kind", Phase::ScanTypes),
+    // Cycle in a chain of typedefs and qualifiers, resolved to void.
+    ("type_chain_cycle", Phase::ScanTypes),
+    // Type chain longer than --max-type-depth, resolved to void.
+    ("type_chain_too_deep", Phase::ScanTypes),
+    // Type descriptor nested deeper than --max-type-depth, cut off with void.
+    ("type_descriptor_too_deep", Phase::UserTypes),
+    // Enum constant without a name, which gets a placeholder.
+    ("unnamed_enum_constant", Phase::ScanTypes),
+    // Byte order of --endian that is not the one of the BTF magic.
+    ("endian_override", Phase::ScanTypes),
+    // LiME image that ends in the header of a range, which is left out.
+    ("truncated_lime_header", Phase::ScanTypes),
+    // Range of a LiME image that ends with the image.
+    ("truncated_lime_range", Phase::ScanTypes),
+    // Bitfield that exceeds its storage unit.
+    ("bitfield_exceeds_storage", Phase::ScanTypes),
+    // Line of a System.map that cannot be parsed.
+    ("invalid_map_line", Phase::Symbols),
+    // Section of `--module-base` without symbols of the module.
+    ("empty_module_section", Phase::Symbols),
+    // Symbols of the module in sections without `--module-base`.
+    ("unplaced_module_symbols", Phase::Symbols),
+    // Symbols of the module that are also kernel symbols.
+    ("shadowed_module_symbols", Phase::Symbols),
+    // Symbols with another address in a previous source.
+    ("conflicting_symbol_addresses", Phase::Symbols),
+    // Demangled Rust symbol name of several symbols.
+    ("ambiguous_rust_symbol", Phase::Symbols),
+    // Symbols that are not rebased since their addresses would overflow.
+    ("rebase_overflow", Phase::Symbols),
+    // Name of a variable and a function in BTF.
+    ("variable_and_function", Phase::Symbols),
+    // Symbol whose BTF type cannot be converted.
+    ("untyped_symbol", Phase::Symbols),
+    // Constant data of a symbol that is not in the symbol table.
+    ("missing_constant_data_symbol", Phase::Symbols),
+    // Constant data of a symbol that cannot be read.
+    ("unreadable_constant_data", Phase::Symbols),
+    // Text anchor of arm64 in a layout other than the one of `--va-bits`.
+    ("arm64_layout_mismatch", Phase::Symbols),
+    // Text anchor of arm64 in no known layout.
+    ("unknown_arm64_layout", Phase::Symbols),
+    // Banner that does not start with "Linux version ".
+    ("nonstandard_banner", Phase::Symbols),
+    // Banner of a memory image that is not at the address of linux_banner.
+    ("relocated_banner", Phase::Symbols),
+    // Symbol whose type is not defined, which becomes void.
+    ("voided_symbol", Phase::Symbols),
+    // Architecture given on the command line that the inputs are not of.
+    ("arch_mismatch", Phase::Symbols),
+    // Architecture of another byte order than the types.
+    ("arch_endian_mismatch", Phase::Symbols),
+    // vmcore without VMCOREINFO, whose kernel is assumed not to be relocated.
+    ("missing_vmcoreinfo", Phase::Symbols),
+    // Mismatch of the inputs, e.g., of the release in the banner and vmlinux.
+    ("inconsistent_inputs", Phase::Symbols),
+    // Symbol addresses outside of the address space of the architecture.
+    ("symbols_out_of_range", Phase::Symbols),
+    // No symdb for the types of symbols without type in the BTF file.
+    ("missing_symdb", Phase::Symbols),
+    // linux_banner that is in no symbol source and gets address 0.
+    ("synthetic_linux_banner", Phase::Symbols),
+    // Line of a symdb that is not a valid entry, which is skipped.
+    ("invalid_symdb_entry", Phase::Symbols),
+    // Layout of a structure that appears to be randomized by RANDSTRUCT.
+    ("randstruct_suspected", Phase::ScanTypes),
+    // Types with conflicting definitions under the same name.
+    ("conflicting_type_definition", Phase::UserTypes),
+    // Type that could not be converted.
+    ("skipped_type", Phase::UserTypes),
+    // Name of several types, or of a type and an alias.
+    ("name_collision", Phase::UserTypes),
+    // Field of a user type whose type is not defined.
+    ("undefined_field_type", Phase::UserTypes),
+    // Undefined type that is replaced with a stub.
+    ("undefined_type_stub", Phase::UserTypes),
+    // Undefined type that cannot be replaced with a stub.
+    ("unstubbable_undefined_type", Phase::UserTypes),
+    // Bitfield that exceeds its type, which is clamped.
+    ("clamped_bitfield", Phase::UserTypes),
+    // Member that is left out of its user type.
+    ("skipped_member", Phase::UserTypes),
+    // Enum that uses an integer base type of another signedness.
+    ("substituted_enum_base_type", Phase::UserTypes),
+    // Enum whose base type is added.
+    ("added_enum_base_type", Phase::UserTypes),
+    // Verification of the profile that failed.
+    ("failed_verification", Phase::Serialize),
+];
+
+/// Returns the phase of the warnings with `code`.
+pub fn phase(code: &str) -> Option<Phase> {
+    CODES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, phase)| *phase)
+}
+
+/// Number of warnings by log target.
+static COUNTS: Mutex<BTreeMap<&str, usize>> = Mutex::new(BTreeMap::new());
+
+/// Counts a warning of the category with the log `target`, for warnings that
+/// are not logged, e.g., because they are printed.
+pub fn count(target: &str) {
+    if let Some((target, _)) = CATEGORIES.iter().find(|(t, _)| *t == target) {
+        *COUNTS.lock().unwrap().entry(target).or_default() += 1;
+    }
+}
+
+/// Returns the number of warnings by category so far.
+pub fn summary() -> Summary {
+    let counts = COUNTS.lock().unwrap();
+    Summary(
+        CATEGORIES
+            .iter()
+            .filter_map(|(target, _)| Some((*target, *counts.get(target)?)))
+            .collect(),
+    )
+}
+
+/// Writes `record` as a single-line JSON object with its `level`, the
+/// `phase` and `code` of warnings with a code, its `target`, its `message`,
+/// and its key-values, e.g., the names of the type and field.
+pub fn write_json(w: &mut dyn Write, record: &Record) -> io::Result<()> {
+    let mut params = Params(Map::new());
+    // Visiting the parameters cannot fail.
+    let _ = record.key_values().visit(&mut params);
+    let code = params.0.remove("code");
+    let mut event = event(
+        record.level(),
+        code.as_ref().and_then(|code| code.as_str()),
+        record.target(),
+        &record.args().to_string(),
+    );
+    event.as_object_mut().unwrap().append(&mut params.0);
+    writeln!(w, "{}", event)
+}
+
+/// Returns the JSON object of [`write_json`] without parameters, e.g., for
+/// warnings that are printed instead of logged.
+pub fn event(level: Level, code: Option<&str>, target: &str, message: &str) -> serde_json::Value {
+    json!({
+        "level": level.as_str().to_lowercase(),
+        "phase": code.and_then(phase).map(|phase| phase.code()),
+        "code": code,
+        "target": target,
+        "message": message,
+    })
+}
+
+/// Parameters of a log record as JSON values.
+struct Params(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for Params {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Logger that counts the warnings of the categories and passes all log
+/// messages on to another logger.
+///
+/// The maximum log level has to be at least [`log::LevelFilter::Warn`] for the
+/// warnings to be counted.
+pub struct Collector {
+    inner: Box<dyn Log>,
+}
+
+impl Collector {
+    pub fn new(inner: Box<dyn Log>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Log for Collector {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() == Level::Warn {
+            count(record.target());
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Number of warnings by the log target of their category, in a fixed order.
+pub struct Summary(Vec<(&'static str, usize)>);
+
+impl Summary {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of warnings.
+    pub fn total(&self) -> usize {
+        self.0.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Returns the number of warnings by the description of their category.
+    pub fn counts(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.0
+            .iter()
+            .map(|(target, count)| (description(target), *count))
+    }
+
+    /// Returns the summary as a JSON object like the log records of
+    /// [`write_json`], with the code `warning_summary`, the `total`, and the
+    /// `counts` by category, named like `undefined_fields` after its log
+    /// target.
+    pub fn to_json(&self) -> serde_json::Value {
+        let counts: Map<String, serde_json::Value> = self
+            .0
+            .iter()
+            .map(|(target, count)| {
+                let name = target.rsplit("::").next().unwrap_or(target);
+                (name.to_owned(), (*count).into())
+            })
+            .collect();
+        let mut event = event(
+            Level::Warn,
+            Some("warning_summary"),
+            "btf2json::warnings",
+            &format!("{} warnings", self.total()),
+        );
+        let object = event.as_object_mut().unwrap();
+        object.insert("total".to_owned(), self.total().into());
+        object.insert("counts".to_owned(), counts.into());
+        event
+    }
+}
+
+/// Returns the description of the category with the log `target`.
+fn description(target: &str) -> &'static str {
+    CATEGORIES
+        .iter()
+        .find(|(t, _)| *t == target)
+        .map_or("", |(_, description)| description)
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} warnings", self.total())?;
+        for (description, count) in self.counts() {
+            write!(f, "\n{:>8}  {}", count, description)?;
+        }
+        Ok(())
+    }
+}
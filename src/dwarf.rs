@@ -0,0 +1,952 @@
+//! Type information from DWARF, for kernels without BTF, see `--dwarf`.
+//!
+//! The types of the debug information are converted into a BTF section, which
+//! the rest of the crate handles like the BTF of any other kernel. Named
+//! structs, unions, enums, and typedefs are merged by name across compilation
+//! units, the first definition wins. Other types are merged if they are
+//! identical. Global variables and functions become BTF variables and
+//! functions, such that symbols get their types.
+
+use crate::elf;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use gimli::{
+    constants, AttributeValue, DebuggingInformationEntry, DwAt, EndianSlice, Reader, RunTimeEndian,
+    Unit,
+};
+
+type R<'a> = EndianSlice<'a, RunTimeEndian>;
+
+/// Offset of a DIE in `.debug_info`.
+type DieOffset = usize;
+
+const BTF_MAGIC: u16 = 0xeb9f;
+const BTF_HEADER_LEN: u32 = 24;
+
+const BTF_KIND_INT: u32 = 1;
+const BTF_KIND_PTR: u32 = 2;
+const BTF_KIND_ARRAY: u32 = 3;
+const BTF_KIND_STRUCT: u32 = 4;
+const BTF_KIND_UNION: u32 = 5;
+const BTF_KIND_ENUM: u32 = 6;
+const BTF_KIND_FWD: u32 = 7;
+const BTF_KIND_TYPEDEF: u32 = 8;
+const BTF_KIND_VOLATILE: u32 = 9;
+const BTF_KIND_CONST: u32 = 10;
+const BTF_KIND_RESTRICT: u32 = 11;
+const BTF_KIND_FUNC: u32 = 12;
+const BTF_KIND_FUNC_PROTO: u32 = 13;
+const BTF_KIND_VAR: u32 = 14;
+const BTF_KIND_FLOAT: u32 = 16;
+const BTF_KIND_ENUM64: u32 = 19;
+
+const BTF_INT_SIGNED: u32 = 1;
+const BTF_INT_BOOL: u32 = 4;
+
+const BTF_LINKAGE_STATIC: u32 = 0;
+const BTF_LINKAGE_GLOBAL: u32 = 1;
+
+/// Name of the integer type that indexes arrays, which BTF requires. It is the
+/// same type as `unsigned int` of the DWARF, such that it adds no base type.
+const ARRAY_INDEX_NAME: &str = "unsigned int";
+
+/// Member of a struct or union, with its location as given in DWARF.
+struct Member {
+    name: Option<String>,
+    t: Option<DieOffset>,
+    /// Offset in bytes, `DW_AT_data_member_location`.
+    location: u64,
+    /// Offset in bits, `DW_AT_data_bit_offset` of DWARF 4 and later.
+    data_bit_offset: Option<u64>,
+    /// Offset in bits from the most significant bit of the storage unit,
+    /// `DW_AT_bit_offset` of DWARF 2 and 3.
+    bit_offset: Option<u64>,
+    bit_size: Option<u64>,
+    /// Size of the storage unit of bitfields in DWARF 2 and 3.
+    byte_size: Option<u64>,
+}
+
+/// Function prototype.
+struct Proto {
+    ret: Option<DieOffset>,
+    params: Vec<Option<DieOffset>>,
+    variadic: bool,
+}
+
+/// Declaration of a variable or function.
+struct Decl {
+    name: Option<String>,
+    t: Option<DieOffset>,
+    external: bool,
+    declaration: bool,
+    /// Declaration that this one completes, `DW_AT_specification` or
+    /// `DW_AT_abstract_origin`.
+    origin: Option<DieOffset>,
+}
+
+/// DIE that is relevant to the types.
+enum Die {
+    Base {
+        name: String,
+        size: u32,
+        encoding: constants::DwAte,
+    },
+    /// Pointer or type qualifier of BTF kind `kind`.
+    Modifier {
+        kind: u32,
+        t: Option<DieOffset>,
+    },
+    /// Type qualifier that BTF does not have, e.g., `_Atomic`.
+    Alias(Option<DieOffset>),
+    Typedef {
+        name: String,
+        t: Option<DieOffset>,
+    },
+    Array {
+        t: Option<DieOffset>,
+        /// Number of elements in each dimension, the outermost first.
+        counts: Vec<u32>,
+    },
+    Record {
+        union: bool,
+        name: Option<String>,
+        size: u32,
+        declaration: bool,
+        members: Vec<Member>,
+    },
+    Enum {
+        name: Option<String>,
+        size: u32,
+        t: Option<DieOffset>,
+        declaration: bool,
+        /// Values as signed and unsigned numbers, if they can be read as such.
+        values: Vec<(String, Option<i64>, Option<u64>)>,
+    },
+    Proto(Proto),
+    Func(Decl, Proto),
+    Var(Decl),
+}
+
+/// Returns the BTF section with the types, variables, and functions of the DWARF
+/// debug information in the ELF file `raw`.
+///
+/// The BTF section has the byte order of the ELF file.
+pub fn to_btf(raw: &[u8]) -> Result<Vec<u8>> {
+    let (sections, little_endian) = elf::debug_sections(raw)?;
+    if !sections.contains_key(".debug_info") {
+        bail!("No DWARF debug information (.debug_info section)");
+    }
+    let endian = if little_endian {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+    let loaded = gimli::DwarfSections::load(|id| -> Result<&[u8]> {
+        Ok(sections
+            .get(id.name())
+            .map_or(&[][..], |data| data.as_ref()))
+    })?;
+    let dwarf = loaded.borrow(|data| EndianSlice::new(data, endian));
+
+    let mut collector = Collector::default();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next().context("Invalid DWARF unit header")? {
+        let unit = dwarf.unit(header).context("Invalid DWARF unit")?;
+        collector
+            .address_size
+            .get_or_insert(unit.header.address_size());
+        let mut tree = unit.entries_tree(None)?;
+        collector.walk(&dwarf, &unit, tree.root()?, true)?;
+    }
+    log::debug!("Read {} DWARF types and declarations", collector.dies.len());
+
+    let mut encoder = Encoder::new(
+        &collector.dies,
+        collector.address_size.unwrap_or(8),
+        little_endian,
+    );
+    for (off, die) in &collector.dies {
+        match die {
+            Die::Func(decl, proto) => encoder.func(decl, proto)?,
+            Die::Var(decl) => encoder.var(decl)?,
+            _ => {
+                encoder.type_id(Some(*off))?;
+            }
+        }
+    }
+    log::debug!("Converted DWARF into {} BTF types", encoder.types.len());
+    Ok(encoder.finish())
+}
+
+#[derive(Default)]
+struct Collector {
+    dies: BTreeMap<DieOffset, Die>,
+    address_size: Option<u8>,
+}
+
+impl Collector {
+    /// Collects the DIEs below `node`, which is the root of a unit if `top` is
+    /// true.
+    fn walk(
+        &mut self,
+        dwarf: &gimli::Dwarf<R>,
+        unit: &Unit<R>,
+        node: gimli::EntriesTreeNode<R>,
+        top: bool,
+    ) -> Result<()> {
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            self.visit(dwarf, unit, child, top)?;
+        }
+        Ok(())
+    }
+
+    /// Collects `node` and the DIEs below it. Variables are only collected at
+    /// the top level of a unit, i.e., if `top` is true.
+    fn visit(
+        &mut self,
+        dwarf: &gimli::Dwarf<R>,
+        unit: &Unit<R>,
+        child: gimli::EntriesTreeNode<R>,
+        top: bool,
+    ) -> Result<()> {
+        let entry = child.entry();
+        let off = die_offset(unit, entry);
+        let tag = entry.tag();
+        let die = match tag {
+            constants::DW_TAG_base_type => Some(Die::Base {
+                name: name(dwarf, unit, entry)?.unwrap_or_default(),
+                size: udata(entry, constants::DW_AT_byte_size)?.unwrap_or(0) as u32,
+                encoding: match entry.attr_value(constants::DW_AT_encoding)? {
+                    Some(AttributeValue::Encoding(encoding)) => encoding,
+                    _ => constants::DW_ATE_unsigned,
+                },
+            }),
+            constants::DW_TAG_pointer_type
+            | constants::DW_TAG_reference_type
+            | constants::DW_TAG_rvalue_reference_type => Some(Die::Modifier {
+                kind: BTF_KIND_PTR,
+                t: type_ref(unit, entry)?,
+            }),
+            constants::DW_TAG_const_type => Some(Die::Modifier {
+                kind: BTF_KIND_CONST,
+                t: type_ref(unit, entry)?,
+            }),
+            constants::DW_TAG_volatile_type => Some(Die::Modifier {
+                kind: BTF_KIND_VOLATILE,
+                t: type_ref(unit, entry)?,
+            }),
+            constants::DW_TAG_restrict_type => Some(Die::Modifier {
+                kind: BTF_KIND_RESTRICT,
+                t: type_ref(unit, entry)?,
+            }),
+            constants::DW_TAG_atomic_type
+            | constants::DW_TAG_immutable_type
+            | constants::DW_TAG_packed_type
+            | constants::DW_TAG_shared_type
+            | constants::DW_TAG_unspecified_type => Some(Die::Alias(type_ref(unit, entry)?)),
+            constants::DW_TAG_typedef => Some(Die::Typedef {
+                name: name(dwarf, unit, entry)?.unwrap_or_default(),
+                t: type_ref(unit, entry)?,
+            }),
+            constants::DW_TAG_variable if top => Some(Die::Var(decl(dwarf, unit, entry)?)),
+            _ => None,
+        };
+        if let Some(die) = die {
+            self.dies.insert(off, die);
+            return Ok(());
+        }
+
+        // The remaining DIEs are described by their children.
+        let die = match tag {
+            constants::DW_TAG_array_type => {
+                let t = type_ref(unit, entry)?;
+                let mut counts = Vec::new();
+                let mut children = child.children();
+                while let Some(dim) = children.next()? {
+                    if dim.entry().tag() == constants::DW_TAG_subrange_type {
+                        counts.push(array_count(dim.entry())?);
+                    }
+                }
+                Die::Array { t, counts }
+            }
+            constants::DW_TAG_structure_type
+            | constants::DW_TAG_class_type
+            | constants::DW_TAG_union_type => {
+                let union = tag == constants::DW_TAG_union_type;
+                let name = name(dwarf, unit, entry)?;
+                let size = udata(entry, constants::DW_AT_byte_size)?.unwrap_or(0) as u32;
+                let declaration = flag(entry, constants::DW_AT_declaration)?;
+                let mut members = Vec::new();
+                let mut children = child.children();
+                while let Some(node) = children.next()? {
+                    let entry = node.entry();
+                    if entry.tag() == constants::DW_TAG_member {
+                        members.push(member(dwarf, unit, entry)?);
+                    } else {
+                        // Types can be defined within structs.
+                        self.visit(dwarf, unit, node, false)?;
+                    }
+                }
+                Die::Record {
+                    union,
+                    name,
+                    size,
+                    declaration,
+                    members,
+                }
+            }
+            constants::DW_TAG_enumeration_type => {
+                let name = name(dwarf, unit, entry)?;
+                let size = udata(entry, constants::DW_AT_byte_size)?.unwrap_or(4) as u32;
+                let t = type_ref(unit, entry)?;
+                let declaration = flag(entry, constants::DW_AT_declaration)?;
+                let mut values = Vec::new();
+                let mut children = child.children();
+                while let Some(node) = children.next()? {
+                    let entry = node.entry();
+                    if entry.tag() != constants::DW_TAG_enumerator {
+                        continue;
+                    }
+                    let value = entry.attr_value_raw(constants::DW_AT_const_value)?;
+                    values.push((
+                        self::name(dwarf, unit, entry)?.unwrap_or_default(),
+                        value.as_ref().and_then(AttributeValue::sdata_value),
+                        value.as_ref().and_then(AttributeValue::udata_value),
+                    ));
+                }
+                Die::Enum {
+                    name,
+                    size,
+                    t,
+                    declaration,
+                    values,
+                }
+            }
+            constants::DW_TAG_subroutine_type => {
+                let ret = type_ref(unit, entry)?;
+                Die::Proto(self.proto(dwarf, unit, child, ret)?)
+            }
+            constants::DW_TAG_subprogram => {
+                let decl = decl(dwarf, unit, entry)?;
+                let proto = self.proto(dwarf, unit, child, decl.t)?;
+                Die::Func(decl, proto)
+            }
+            _ => return self.walk(dwarf, unit, child, false),
+        };
+        self.dies.insert(off, die);
+        Ok(())
+    }
+
+    /// Returns the prototype of the function or function type `node` with the
+    /// return type `ret`, collecting the types defined in its body.
+    fn proto(
+        &mut self,
+        dwarf: &gimli::Dwarf<R>,
+        unit: &Unit<R>,
+        node: gimli::EntriesTreeNode<R>,
+        ret: Option<DieOffset>,
+    ) -> Result<Proto> {
+        let mut proto = Proto {
+            ret,
+            params: Vec::new(),
+            variadic: false,
+        };
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            match child.entry().tag() {
+                constants::DW_TAG_formal_parameter => {
+                    proto.params.push(type_ref(unit, child.entry())?);
+                }
+                constants::DW_TAG_unspecified_parameters => proto.variadic = true,
+                _ => self.visit(dwarf, unit, child, false)?,
+            }
+        }
+        Ok(proto)
+    }
+}
+
+fn die_offset(unit: &Unit<R>, entry: &DebuggingInformationEntry<R>) -> DieOffset {
+    entry
+        .offset()
+        .to_debug_info_offset(&unit.header)
+        .map_or(0, |off| off.0)
+}
+
+fn name(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    entry: &DebuggingInformationEntry<R>,
+) -> Result<Option<String>> {
+    let Some(value) = entry.attr_value(constants::DW_AT_name)? else {
+        return Ok(None);
+    };
+    let name = dwarf.attr_string(unit, value)?;
+    Ok(Some(name.to_string_lossy().into_owned()))
+}
+
+/// Returns the DIE that the reference attribute `at` points to.
+fn reference(
+    unit: &Unit<R>,
+    entry: &DebuggingInformationEntry<R>,
+    at: DwAt,
+) -> Result<Option<DieOffset>> {
+    Ok(match entry.attr_value(at)? {
+        Some(AttributeValue::UnitRef(off)) => {
+            off.to_debug_info_offset(&unit.header).map(|off| off.0)
+        }
+        Some(AttributeValue::DebugInfoRef(off)) => Some(off.0),
+        _ => None,
+    })
+}
+
+/// Returns the type of `entry`, `None` for `void`.
+fn type_ref(unit: &Unit<R>, entry: &DebuggingInformationEntry<R>) -> Result<Option<DieOffset>> {
+    reference(unit, entry, constants::DW_AT_type)
+}
+
+fn udata(entry: &DebuggingInformationEntry<R>, at: DwAt) -> Result<Option<u64>> {
+    Ok(entry
+        .attr_value_raw(at)?
+        .and_then(|value| value.udata_value()))
+}
+
+fn flag(entry: &DebuggingInformationEntry<R>, at: DwAt) -> Result<bool> {
+    Ok(matches!(
+        entry.attr_value(at)?,
+        Some(AttributeValue::Flag(true))
+    ))
+}
+
+/// Returns the number of elements of the array dimension `entry`, 0 if it is
+/// unknown, e.g., for flexible arrays.
+fn array_count(entry: &DebuggingInformationEntry<R>) -> Result<u32> {
+    if let Some(count) = udata(entry, constants::DW_AT_count)? {
+        return Ok(count as u32);
+    }
+    let lower = udata(entry, constants::DW_AT_lower_bound)?.unwrap_or(0);
+    Ok(match entry.attr_value_raw(constants::DW_AT_upper_bound)? {
+        // Empty arrays have the upper bound -1.
+        Some(AttributeValue::Sdata(-1)) => 0,
+        Some(value) => value.udata_value().map_or(0, |upper| {
+            upper.saturating_add(1).saturating_sub(lower) as u32
+        }),
+        None => 0,
+    })
+}
+
+fn member(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    entry: &DebuggingInformationEntry<R>,
+) -> Result<Member> {
+    let location = match entry.attr_value_raw(constants::DW_AT_data_member_location)? {
+        Some(value) => match value.udata_value() {
+            Some(location) => location,
+            // DWARF 2 describes the location with `DW_OP_plus_uconst`.
+            None => value
+                .exprloc_value()
+                .and_then(|expr| {
+                    let mut r = expr.0;
+                    match r.read_u8().ok()? {
+                        op if op == constants::DW_OP_plus_uconst.0
+                            || op == constants::DW_OP_constu.0 =>
+                        {
+                            r.read_uleb128().ok()
+                        }
+                        _ => None,
+                    }
+                })
+                .unwrap_or(0),
+        },
+        None => 0,
+    };
+    Ok(Member {
+        name: name(dwarf, unit, entry)?,
+        t: type_ref(unit, entry)?,
+        location,
+        data_bit_offset: udata(entry, constants::DW_AT_data_bit_offset)?,
+        bit_offset: udata(entry, constants::DW_AT_bit_offset)?,
+        bit_size: udata(entry, constants::DW_AT_bit_size)?,
+        byte_size: udata(entry, constants::DW_AT_byte_size)?,
+    })
+}
+
+fn decl(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    entry: &DebuggingInformationEntry<R>,
+) -> Result<Decl> {
+    Ok(Decl {
+        name: name(dwarf, unit, entry)?,
+        t: type_ref(unit, entry)?,
+        external: flag(entry, constants::DW_AT_external)?,
+        declaration: flag(entry, constants::DW_AT_declaration)?,
+        origin: match reference(unit, entry, constants::DW_AT_specification)? {
+            Some(origin) => Some(origin),
+            None => reference(unit, entry, constants::DW_AT_abstract_origin)?,
+        },
+    })
+}
+
+/// Builder of the BTF section, which assigns the BTF IDs of DIEs.
+struct Encoder<'a> {
+    dies: &'a BTreeMap<DieOffset, Die>,
+    address_size: u8,
+    little_endian: bool,
+    /// First definitions of named structs, unions, and enums, by BTF kind and
+    /// name.
+    definitions: HashMap<(u32, &'a str), DieOffset>,
+    /// Types, the type with ID `n` at index `n - 1`.
+    types: Vec<Vec<u32>>,
+    strings: Vec<u8>,
+    string_offsets: HashMap<String, u32>,
+    ids: HashMap<DieOffset, u32>,
+    /// Typedefs by name.
+    typedefs: HashMap<&'a str, u32>,
+    /// Types without name or with a name that is not merged, by their encoding.
+    identical: HashMap<Vec<u32>, u32>,
+    /// DIEs whose types are being built, to detect cycles.
+    pending: HashSet<DieOffset>,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(dies: &'a BTreeMap<DieOffset, Die>, address_size: u8, little_endian: bool) -> Self {
+        let mut definitions = HashMap::new();
+        for (off, die) in dies {
+            let key = match die {
+                Die::Record {
+                    union,
+                    name: Some(name),
+                    declaration: false,
+                    ..
+                } => (record_kind(*union), name.as_str()),
+                Die::Enum {
+                    name: Some(name),
+                    declaration: false,
+                    ..
+                } => (BTF_KIND_ENUM, name.as_str()),
+                _ => continue,
+            };
+            definitions.entry(key).or_insert(*off);
+        }
+        Self {
+            dies,
+            address_size,
+            little_endian,
+            definitions,
+            types: Vec::new(),
+            strings: vec![0],
+            string_offsets: HashMap::new(),
+            ids: HashMap::new(),
+            typedefs: HashMap::new(),
+            identical: HashMap::new(),
+            pending: HashSet::new(),
+        }
+    }
+
+    /// Returns the string table offset of `name`, adding it if needed.
+    fn string(&mut self, name: &str) -> u32 {
+        if name.is_empty() {
+            return 0;
+        }
+        if let Some(off) = self.string_offsets.get(name) {
+            return *off;
+        }
+        let off = self.strings.len() as u32;
+        self.strings.extend(name.as_bytes());
+        self.strings.push(0);
+        self.string_offsets.insert(name.to_owned(), off);
+        off
+    }
+
+    /// Adds the type `words`, unless an identical type exists, and returns its
+    /// ID.
+    fn add(&mut self, words: Vec<u32>) -> u32 {
+        if let Some(id) = self.identical.get(&words) {
+            return *id;
+        }
+        self.types.push(words.clone());
+        let id = self.types.len() as u32;
+        self.identical.insert(words, id);
+        id
+    }
+
+    /// Reserves the ID of a type that is referenced before it is built.
+    fn reserve(&mut self) -> u32 {
+        self.types.push(Vec::new());
+        self.types.len() as u32
+    }
+
+    /// Returns the BTF ID of the type `off`, 0 for `void`.
+    fn type_id(&mut self, off: Option<DieOffset>) -> Result<u32> {
+        let Some(off) = off else {
+            return Ok(0);
+        };
+        if let Some(id) = self.ids.get(&off) {
+            return Ok(*id);
+        }
+        let dies = self.dies;
+        let Some(die) = dies.get(&off) else {
+            log::debug!("Unsupported DWARF type at {:#x}, using void", off);
+            return Ok(0);
+        };
+        if !self.pending.insert(off) {
+            bail!("DWARF type at {:#x} contains itself", off);
+        }
+        let id = self.encode(off, die)?;
+        self.pending.remove(&off);
+        self.ids.insert(off, id);
+        Ok(id)
+    }
+
+    fn encode(&mut self, off: DieOffset, die: &'a Die) -> Result<u32> {
+        Ok(match die {
+            Die::Base {
+                name,
+                size,
+                encoding,
+            } => {
+                let name = self.string(name);
+                match *encoding {
+                    constants::DW_ATE_float => self.add(vec![name, BTF_KIND_FLOAT << 24, *size]),
+                    encoding => {
+                        let flags = match encoding {
+                            constants::DW_ATE_boolean => BTF_INT_BOOL,
+                            constants::DW_ATE_signed | constants::DW_ATE_signed_char => {
+                                BTF_INT_SIGNED
+                            }
+                            _ => 0,
+                        };
+                        let bits = (size * 8).min(128);
+                        self.add(vec![name, BTF_KIND_INT << 24, *size, (flags << 24) | bits])
+                    }
+                }
+            }
+            Die::Modifier { kind, t } => {
+                let t = self.type_id(*t)?;
+                self.add(vec![0, kind << 24, t])
+            }
+            Die::Alias(t) => self.type_id(*t)?,
+            Die::Typedef { name, t } => {
+                if let Some(id) = self.typedefs.get(name.as_str()) {
+                    return Ok(*id);
+                }
+                let id = self.reserve();
+                self.typedefs.insert(name, id);
+                self.ids.insert(off, id);
+                let t = self.type_id(*t)?;
+                self.types[id as usize - 1] = vec![self.string(name), BTF_KIND_TYPEDEF << 24, t];
+                id
+            }
+            Die::Array { t, counts } => {
+                let mut id = self.type_id(*t)?;
+                let index = self.array_index();
+                let counts = if counts.is_empty() { &[0][..] } else { counts };
+                for count in counts.iter().rev() {
+                    id = self.add(vec![0, BTF_KIND_ARRAY << 24, 0, id, index, *count]);
+                }
+                id
+            }
+            Die::Record {
+                union,
+                name,
+                size,
+                declaration,
+                members,
+            } => {
+                let kind = record_kind(*union);
+                if let Some(name) = name {
+                    match self.definitions.get(&(kind, name.as_str())) {
+                        Some(def) if *def != off => return self.type_id(Some(*def)),
+                        Some(_) => {}
+                        None => {
+                            let name = self.string(name);
+                            return Ok(self.add(vec![
+                                name,
+                                ((*union as u32) << 31) | (BTF_KIND_FWD << 24),
+                                0,
+                            ]));
+                        }
+                    }
+                    let id = self.reserve();
+                    self.ids.insert(off, id);
+                    let words = self.record(kind, name, *size, members)?;
+                    self.types[id as usize - 1] = words;
+                    id
+                } else {
+                    debug_assert!(!declaration);
+                    let words = self.record(kind, "", *size, members)?;
+                    self.add(words)
+                }
+            }
+            Die::Enum {
+                name,
+                size,
+                t,
+                declaration: _,
+                values,
+            } => {
+                if let Some(name) = name {
+                    match self.definitions.get(&(BTF_KIND_ENUM, name.as_str())) {
+                        Some(def) if *def != off => return self.type_id(Some(*def)),
+                        _ => {}
+                    }
+                }
+                let signed = match t {
+                    Some(t) => self.is_signed(*t),
+                    None => values.iter().any(|(_, _, unsigned)| unsigned.is_none()),
+                };
+                let values: Vec<_> = values
+                    .iter()
+                    .map(|(name, s, u)| {
+                        let value = if signed {
+                            s.or(u.map(|u| u as i64))
+                        } else {
+                            u.map(|u| u as i64).or(*s)
+                        };
+                        (name, value.unwrap_or_default())
+                    })
+                    .collect();
+                let wide = values.iter().any(|(_, v)| {
+                    if signed {
+                        i32::try_from(*v).is_err()
+                    } else {
+                        u32::try_from(*v as u64).is_err()
+                    }
+                });
+                let kind = if wide { BTF_KIND_ENUM64 } else { BTF_KIND_ENUM };
+                let name = self.string(name.as_deref().unwrap_or_default());
+                let info = ((signed as u32) << 31) | (kind << 24) | values.len() as u32;
+                let mut words = vec![name, info, *size];
+                for (name, value) in values {
+                    words.push(self.string(name));
+                    words.push(value as u32);
+                    if wide {
+                        words.push((value as u64 >> 32) as u32);
+                    }
+                }
+                self.add(words)
+            }
+            Die::Proto(proto) => self.proto(proto)?,
+            Die::Func(..) | Die::Var(..) => 0,
+        })
+    }
+
+    /// Returns the encoding of a struct or union.
+    fn record(&mut self, kind: u32, name: &str, size: u32, members: &[Member]) -> Result<Vec<u32>> {
+        let mut encoded = Vec::new();
+        let mut kind_flag = false;
+        for member in members {
+            let t = self.type_id(member.t)?;
+            let base = member.location * 8;
+            let (offset, bits) = match (member.bit_size, member.data_bit_offset, member.bit_offset)
+            {
+                (Some(bits), Some(offset), _) => (offset, bits),
+                (Some(bits), None, Some(from_msb)) => {
+                    let storage = match member.byte_size {
+                        Some(size) => size,
+                        None => self.size_of(member.t) as u64,
+                    };
+                    let offset = if self.little_endian {
+                        (base + storage * 8).saturating_sub(from_msb + bits)
+                    } else {
+                        base + from_msb
+                    };
+                    (offset, bits)
+                }
+                (Some(bits), None, None) => (base, bits),
+                (None, offset, _) => (offset.unwrap_or(base), 0),
+            };
+            kind_flag |= bits != 0;
+            let name = self.string(member.name.as_deref().unwrap_or_default());
+            encoded.push((name, t, offset as u32, bits as u32));
+        }
+
+        let info = ((kind_flag as u32) << 31) | (kind << 24) | members.len() as u32;
+        let mut words = vec![self.string(name), info, size];
+        for (name, t, offset, bits) in encoded {
+            let offset = if kind_flag {
+                (bits << 24) | offset
+            } else {
+                offset
+            };
+            words.extend([name, t, offset]);
+        }
+        Ok(words)
+    }
+
+    fn proto(&mut self, proto: &Proto) -> Result<u32> {
+        let ret = self.type_id(proto.ret)?;
+        let vlen = proto.params.len() + proto.variadic as usize;
+        let mut words = vec![0, (BTF_KIND_FUNC_PROTO << 24) | vlen as u32, ret];
+        for param in &proto.params {
+            words.extend([0, self.type_id(*param)?]);
+        }
+        // Variadic functions end with a `void` parameter.
+        if proto.variadic {
+            words.extend([0, 0]);
+        }
+        Ok(self.add(words))
+    }
+
+    /// Returns the ID of the integer that indexes arrays.
+    fn array_index(&mut self) -> u32 {
+        let name = self.string(ARRAY_INDEX_NAME);
+        self.add(vec![name, BTF_KIND_INT << 24, 4, 32])
+    }
+
+    /// Returns `decl` with the name and type of the declarations it completes.
+    fn resolve(&self, decl: &'a Decl) -> (Option<&'a str>, Option<DieOffset>, bool) {
+        let mut name = decl.name.as_deref();
+        let mut t = decl.t;
+        let mut external = decl.external;
+        let mut origin = decl.origin;
+        // Bounded, origins may form cycles in broken debug information.
+        for _ in 0..8 {
+            let Some(Die::Var(decl) | Die::Func(decl, _)) = origin.and_then(|o| self.dies.get(&o))
+            else {
+                break;
+            };
+            name = name.or(decl.name.as_deref());
+            t = t.or(decl.t);
+            external |= decl.external;
+            origin = decl.origin;
+        }
+        (name, t, external)
+    }
+
+    /// Adds the function `decl` if it is a definition.
+    fn func(&mut self, decl: &'a Decl, proto: &Proto) -> Result<()> {
+        if decl.declaration {
+            return Ok(());
+        }
+        let (Some(name), ret, external) = self.resolve(decl) else {
+            return Ok(());
+        };
+        // Out-of-line copies of inline functions describe their parameters in
+        // the abstract instance.
+        let proto = match decl.origin.and_then(|o| self.dies.get(&o)) {
+            Some(Die::Func(_, origin)) if proto.params.is_empty() => origin,
+            _ => proto,
+        };
+        let proto = Proto {
+            ret,
+            params: proto.params.clone(),
+            variadic: proto.variadic,
+        };
+        let proto = self.proto(&proto)?;
+        let name = self.string(name);
+        let linkage = if external {
+            BTF_LINKAGE_GLOBAL
+        } else {
+            BTF_LINKAGE_STATIC
+        };
+        self.add(vec![name, (BTF_KIND_FUNC << 24) | linkage, proto]);
+        Ok(())
+    }
+
+    /// Adds the variable `decl` if it is a definition.
+    fn var(&mut self, decl: &'a Decl) -> Result<()> {
+        if decl.declaration {
+            return Ok(());
+        }
+        let (Some(name), t, external) = self.resolve(decl) else {
+            return Ok(());
+        };
+        let t = self.type_id(t)?;
+        let name = self.string(name);
+        let linkage = if external {
+            BTF_LINKAGE_GLOBAL
+        } else {
+            BTF_LINKAGE_STATIC
+        };
+        self.add(vec![name, BTF_KIND_VAR << 24, t, linkage]);
+        Ok(())
+    }
+
+    /// Returns whether the type `off` is a signed integer.
+    fn is_signed(&self, mut off: DieOffset) -> bool {
+        for _ in 0..16 {
+            match self.dies.get(&off) {
+                Some(Die::Base { encoding, .. }) => {
+                    return matches!(
+                        *encoding,
+                        constants::DW_ATE_signed | constants::DW_ATE_signed_char
+                    )
+                }
+                Some(
+                    Die::Typedef { t: Some(t), .. }
+                    | Die::Alias(Some(t))
+                    | Die::Modifier { t: Some(t), .. },
+                ) => off = *t,
+                _ => break,
+            }
+        }
+        false
+    }
+
+    /// Returns the size of the type `off`, as needed for bitfields.
+    fn size_of(&self, mut off: Option<DieOffset>) -> u32 {
+        for _ in 0..16 {
+            let Some(die) = off.and_then(|o| self.dies.get(&o)) else {
+                break;
+            };
+            match die {
+                Die::Base { size, .. } | Die::Record { size, .. } | Die::Enum { size, .. } => {
+                    return *size
+                }
+                Die::Modifier {
+                    kind: BTF_KIND_PTR, ..
+                } => return self.address_size as u32,
+                Die::Modifier { t, .. } | Die::Alias(t) | Die::Typedef { t, .. } => off = *t,
+                _ => break,
+            }
+        }
+        0
+    }
+
+    /// Returns the BTF section, with the byte order of the ELF file.
+    fn finish(self) -> Vec<u8> {
+        let little_endian = self.little_endian;
+        let word = |v: u32| {
+            if little_endian {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            }
+        };
+        let types: Vec<u8> = self.types.iter().flatten().flat_map(|v| word(*v)).collect();
+        let mut btf = Vec::new();
+        btf.extend(if little_endian {
+            BTF_MAGIC.to_le_bytes()
+        } else {
+            BTF_MAGIC.to_be_bytes()
+        });
+        // Version 1, no flags.
+        btf.extend([1, 0]);
+        let types_len = types.len() as u32;
+        for v in [
+            BTF_HEADER_LEN,
+            0,
+            types_len,
+            types_len,
+            self.strings.len() as u32,
+        ] {
+            btf.extend(word(v));
+        }
+        btf.extend(types);
+        btf.extend(&self.strings);
+        btf
+    }
+}
+
+fn record_kind(union: bool) -> u32 {
+    if union {
+        BTF_KIND_UNION
+    } else {
+        BTF_KIND_STRUCT
+    }
+}
@@ -1,30 +1,69 @@
 //! Utilities for working with ELF files.
 
 use crate::btf::Endian;
+use crate::decompress;
+use crate::image;
+use crate::version::BANNER_PREFIX;
 
-use anyhow::{bail, Context, Result};
-use goblin::elf::Elf;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
 
-const ELF_MAGIC_LE: [u8; 4] = [0x7f, 0x45, 0x4c, 0x46];
-const ELF_MAGIC_BE: [u8; 4] = [0x46, 0x4c, 0x45, 0x7f];
+use anyhow::{anyhow, bail, Context, Result};
+use goblin::container::Ctx;
+use goblin::elf::compression_header::CompressionHeader;
+use goblin::elf::header::{EI_DATA, ELFDATA2LSB, ELFDATA2MSB, ELFMAG, ET_CORE, ET_EXEC, ET_REL};
+use goblin::elf::program_header::{ProgramHeader, PT_LOAD};
+use goblin::elf::section_header::{
+    SHF_ALLOC, SHF_COMPRESSED, SHF_EXECINSTR, SHF_WRITE, SHN_ABS, SHN_COMMON, SHN_LORESERVE,
+    SHN_UNDEF, SHT_NOBITS, SHT_NULL,
+};
+use goblin::elf::sym::{Sym, STB_LOCAL, STB_WEAK, STT_FILE, STT_OBJECT, STT_SECTION};
+use goblin::elf::Elf;
 
 const BTF_SEC_NAME: &str = ".BTF";
 
+/// Sections that are searched for the banner if `linux_banner` cannot be
+/// read.
+const BANNER_SECTIONS: [&str; 2] = [".init.data", ".rodata"];
+
 /// Determines whether buffer is an ELF file, and, if yes, its endianness.
+///
+/// The magic is the same for both byte orders, which is given by the
+/// `EI_DATA` byte of the identification instead.
 // TODO: Wrong use of Result type?
 pub fn is_elf(raw: &[u8]) -> Result<Endian> {
-    if raw[..4] == ELF_MAGIC_LE {
-        Ok(Endian::Little)
-    } else if raw[..4] == ELF_MAGIC_BE {
-        Ok(Endian::Big)
-    } else {
+    if !raw.starts_with(ELFMAG) {
         bail!("Not an ELF file.")
     }
+    match raw.get(EI_DATA) {
+        Some(&ELFDATA2LSB) => Ok(Endian::Little),
+        Some(&ELFDATA2MSB) => Ok(Endian::Big),
+        Some(data) => bail!("ELF file has invalid data encoding {}", data),
+        None => bail!("ELF file is truncated"),
+    }
 }
 
 /// Returns the `.BTF` section of the ELF file.
+///
+/// If the section headers are missing or broken, the loadable segments are
+/// scanned for the BTF section instead.
 pub fn extract_btfsec(raw: &[u8]) -> Result<&[u8]> {
-    let elf = Elf::parse(raw)?;
+    match btfsec_from_section_headers(raw) {
+        Ok(btf_sec) => {
+            log::debug!("Found {} section via section headers", BTF_SEC_NAME);
+            Ok(btf_sec)
+        }
+        Err(err) => {
+            log::info!("{:#}, scanning loadable segments for BTF", err);
+            btfsec_from_segments(raw).context(err)
+        }
+    }
+}
+
+/// Returns the `.BTF` section of the ELF file using the section headers.
+fn btfsec_from_section_headers(raw: &[u8]) -> Result<&[u8]> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
     for shdr in elf.section_headers.iter() {
         let Some(sec_name) = elf.shdr_strtab.get_at(shdr.sh_name) else {
             log::debug!("Unable to get name for section: {}", shdr.sh_name);
@@ -34,41 +73,368 @@ pub fn extract_btfsec(raw: &[u8]) -> Result<&[u8]> {
         if sec_name != BTF_SEC_NAME {
             continue;
         }
-        return Ok(&raw[shdr.sh_offset as usize..(shdr.sh_offset + shdr.sh_size) as usize]);
+        return section_data(raw, sec_name, shdr.sh_offset, shdr.sh_size);
     }
     bail!("No {} section in ELF file", BTF_SEC_NAME)
 }
 
-/// Returns the Linux banner of the ELF file.
+/// Returns the contents of the section `name` at `offset` with `size` bytes.
+///
+/// Fails if the section is not within the ELF file.
+fn section_data<'a>(raw: &'a [u8], name: &str, offset: u64, size: u64) -> Result<&'a [u8]> {
+    offset
+        .checked_add(size)
+        .filter(|end| *end <= raw.len() as u64)
+        .map(|end| &raw[offset as usize..end as usize])
+        .with_context(|| {
+            format!(
+                "Section {} at {:#x} with {:#x} bytes exceeds ELF file of {:#x} bytes",
+                name,
+                offset,
+                size,
+                raw.len()
+            )
+        })
+}
+
+/// Returns the BTF section found in the loadable segments of the ELF file.
+///
+/// Of all candidates, the one with the largest type section wins.
+fn btfsec_from_segments(raw: &[u8]) -> Result<&[u8]> {
+    let segments = load_segments(raw)?
+        .iter()
+        .filter_map(|phdr| segment_range(raw, phdr))
+        .collect::<Vec<_>>();
+
+    let Some((hdr, range)) = image::btf_candidates_in(raw, &segments)
+        .into_iter()
+        .max_by_key(|(hdr, _)| hdr.type_len)
+    else {
+        bail!("No BTF section in loadable segments of ELF file")
+    };
+    log::info!(
+        "Found BTF section at {:#x} in loadable segments of ELF file: {} bytes of types",
+        range.start,
+        hdr.type_len
+    );
+    Ok(&raw[range])
+}
+
+/// Returns the Linux banner of the ELF file, without trailing NULs.
+///
+/// If `linux_banner` cannot be read, e.g., as its section header is broken,
+/// the sections that usually contain the banner are searched for it instead.
 pub fn get_banner(raw: &[u8]) -> Result<String> {
-    let elf = Elf::parse(raw)?;
-    for sym in elf.syms.iter() {
-        let Some(sym_name) = elf.strtab.get_at(sym.st_name) else {
-            log::debug!("Unable to get name for symbol: {}", sym.st_name);
+    let banner = match read_symbol_bytes(raw, "linux_banner") {
+        Ok(Some(banner)) => banner.to_vec(),
+        result => {
+            let err = result
+                .err()
+                .unwrap_or_else(|| anyhow!("Banner is in a section without contents."));
+            log::debug!(
+                "{:#} Searching {} for the banner",
+                err,
+                BANNER_SECTIONS.join(" and ")
+            );
+            banner_from_sections(raw)
+                .context(err)
+                .context("Unable to find Linux banner.")?
+                .into_bytes()
+        }
+    };
+    let banner = String::from_utf8(banner).context("Linux banner is not UTF-8.")?;
+    Ok(banner.trim_end_matches('\0').to_owned())
+}
+
+/// Returns the first Linux banner in the sections that usually contain it,
+/// see [`image::find_banner`].
+fn banner_from_sections(raw: &[u8]) -> Result<String> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
+    for name in BANNER_SECTIONS {
+        let Some(shdr) = elf
+            .section_headers
+            .iter()
+            .find(|shdr| elf.shdr_strtab.get_at(shdr.sh_name) == Some(name))
+        else {
             continue;
         };
-
-        if sym_name != "linux_banner" {
+        if shdr.sh_type == SHT_NOBITS {
             continue;
         }
+        let data = match section_data(raw, name, shdr.sh_offset, shdr.sh_size) {
+            Ok(data) => data,
+            Err(err) => {
+                log::debug!("{:#}", err);
+                continue;
+            }
+        };
+        if let Some(banner) = image::find_banner(data) {
+            log::info!("Found Linux banner in section {}", name);
+            return Ok(format!("{}\n", banner));
+        }
+    }
+    bail!(
+        "No {:?} string in {}",
+        BANNER_PREFIX,
+        BANNER_SECTIONS.join(" or ")
+    )
+}
+
+/// Returns the bytes backing the symbol `name` of the ELF file.
+///
+/// Returns `None` if the section of the symbol has no contents in the file,
+/// e.g., `.bss`.
+pub fn read_symbol_bytes<'a>(raw: &'a [u8], name: &str) -> Result<Option<&'a [u8]>> {
+    let elf = Elf::parse(raw)?;
+    let Some(sym) = find_symbol(&elf, name) else {
+        bail!("No symbol {} in ELF file.", name)
+    };
+
+    match sym.st_shndx as u32 {
+        SHN_UNDEF => bail!("Symbol {} is undefined.", name),
+        SHN_ABS => bail!("Symbol {} is absolute and in no section.", name),
+        SHN_COMMON => bail!("Symbol {} is common and in no section.", name),
+        shndx if shndx >= SHN_LORESERVE => {
+            bail!("Symbol {} is in reserved section index {:#x}.", name, shndx)
+        }
+        _ => (),
+    }
+    let sh_hdr = elf
+        .section_headers
+        .get(sym.st_shndx)
+        .with_context(|| format!("Symbol {} is in non-existent section.", name))?;
+    if sh_hdr.sh_type == SHT_NULL {
+        bail!(
+            "Symbol {} is in section {}, whose header is empty.",
+            name,
+            sym.st_shndx
+        );
+    }
+    let sec_name = elf.shdr_strtab.get_at(sh_hdr.sh_name).unwrap_or("?");
+    if sh_hdr.sh_type == SHT_NOBITS {
+        return Ok(None);
+    }
+    // Symbol values of relocatable files are offsets into their section.
+    let offset = match elf.header.e_type {
+        ET_REL => Some(sym.st_value),
+        _ => sym.st_value.checked_sub(sh_hdr.sh_addr),
+    }
+    .filter(|off| off.checked_add(sym.st_size) <= Some(sh_hdr.sh_size))
+    .with_context(|| format!("Symbol {} is outside of its section {}.", name, sec_name))?;
+
+    log::debug!(
+        "Found symbol {}: sec {}, off {}, size {}",
+        name,
+        sym.st_shndx,
+        offset,
+        sym.st_size
+    );
+
+    let section = section_data(raw, sec_name, sh_hdr.sh_offset, sh_hdr.sh_size)?;
+    Ok(Some(
+        &section[offset as usize..(offset + sym.st_size) as usize],
+    ))
+}
 
-        let sh_hdr = elf
+/// Returns the address of the defined symbol `name` in the symbol table of
+/// the ELF file, or `None` if there is no such symbol.
+pub fn symbol_address(raw: &[u8], name: &str) -> Result<Option<u64>> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
+    Ok(find_symbol(&elf, name)
+        .filter(|sym| sym.st_shndx as u32 != SHN_UNDEF)
+        .map(|sym| sym.st_value))
+}
+
+/// Returns the first symbol called `name` in the symbol table.
+fn find_symbol(elf: &Elf, name: &str) -> Option<Sym> {
+    elf.syms
+        .iter()
+        .find(|sym| elf.strtab.get_at(sym.st_name) == Some(name))
+}
+
+/// Returns the addresses of the allocated sections of the executable ELF file,
+/// e.g., vmlinux, by name.
+pub fn section_addresses(raw: &[u8]) -> Result<HashMap<String, u64>> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
+    if elf.header.e_type != ET_EXEC {
+        bail!("Not an executable ELF file")
+    }
+    Ok(elf
+        .section_headers
+        .iter()
+        .filter(|shdr| shdr.sh_flags & u64::from(SHF_ALLOC) != 0)
+        .filter_map(|shdr| {
+            let name = elf.shdr_strtab.get_at(shdr.sh_name)?;
+            Some((name.to_owned(), shdr.sh_addr))
+        })
+        .collect())
+}
+
+/// Returns the symbols in the symbol table of the ELF file as name, address,
+/// and type, where the type is the letter that `nm` would print.
+///
+/// Undefined symbols, symbols of files and sections, and symbols in sections
+/// that are not allocated are skipped.
+pub fn symbols(raw: &[u8]) -> Result<Vec<(String, u64, char)>> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
+    Ok(elf
+        .syms
+        .iter()
+        .filter_map(|sym| {
+            let (name, chr) = classify(&elf, &sym)?;
+            Some((name.to_owned(), sym.st_value, chr))
+        })
+        .collect())
+}
+
+/// Returns the symbols of the kernel module, i.e., relocatable ELF file, as
+/// name, section, offset into the section, and type like [`symbols`].
+///
+/// Absolute symbols are skipped, as they are not part of the module image.
+pub fn module_symbols(raw: &[u8]) -> Result<Vec<(String, String, u64, char)>> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
+    if elf.header.e_type != ET_REL {
+        bail!("Not a kernel module, i.e., a relocatable ELF file")
+    }
+    Ok(elf
+        .syms
+        .iter()
+        .filter(|sym| sym.st_shndx != SHN_ABS as usize)
+        .filter_map(|sym| {
+            let (name, chr) = classify(&elf, &sym)?;
+            let shdr = elf.section_headers.get(sym.st_shndx)?;
+            let section = elf.shdr_strtab.get_at(shdr.sh_name)?;
+            Some((name.to_owned(), section.to_owned(), sym.st_value, chr))
+        })
+        .collect())
+}
+
+/// Returns the name of `sym` and the letter that `nm` would print for it, or
+/// `None` if `nm` would not list it, see [`symbols`].
+fn classify<'a>(elf: &Elf<'a>, sym: &Sym) -> Option<(&'a str, char)> {
+    if matches!(sym.st_type(), STT_FILE | STT_SECTION) {
+        return None;
+    }
+    let name = elf
+        .strtab
+        .get_at(sym.st_name)
+        .filter(|name| !name.is_empty())?;
+    let chr = if sym.st_shndx == SHN_ABS as usize {
+        'A'
+    } else {
+        let shdr = elf
             .section_headers
             .get(sym.st_shndx)
-            .context("Banner is in non-existent section.")?;
-        let offset = sym.st_value - sh_hdr.sh_addr;
-        let name_start = (sh_hdr.sh_offset + offset) as usize;
-        let name_end = name_start + sym.st_size as usize;
-
-        log::debug!(
-            "Found Linux banner: sec {}, off {}, size {}",
-            sym.st_shndx,
-            offset,
-            sym.st_size
-        );
+            .filter(|_| sym.st_shndx != 0)?;
+        if sym.st_bind() == STB_WEAK {
+            if sym.st_type() == STT_OBJECT {
+                'V'
+            } else {
+                'W'
+            }
+        } else if shdr.sh_flags & u64::from(SHF_EXECINSTR) != 0 {
+            'T'
+        } else if shdr.sh_type == SHT_NOBITS {
+            'B'
+        } else if shdr.sh_flags & u64::from(SHF_WRITE) != 0 {
+            'D'
+        } else if shdr.sh_flags & u64::from(SHF_ALLOC) != 0 {
+            'R'
+        } else {
+            return None;
+        }
+    };
+    let chr = match sym.st_bind() {
+        STB_LOCAL => chr.to_ascii_lowercase(),
+        _ => chr,
+    };
+    Some((name, chr))
+}
+
+/// Returns true iff the ELF file has a section called `name`.
+pub fn has_section(raw: &[u8], name: &str) -> bool {
+    Elf::parse(raw).is_ok_and(|elf| {
+        elf.section_headers
+            .iter()
+            .any(|shdr| elf.shdr_strtab.get_at(shdr.sh_name) == Some(name))
+    })
+}
 
-        return Ok(String::from_utf8(raw[name_start..name_end].to_vec())?);
+/// Contents of sections by name.
+pub type Sections<'a> = HashMap<String, Cow<'a, [u8]>>;
+
+/// Returns the DWARF sections of the ELF file, i.e., the `.debug_*` sections,
+/// and whether the file is little endian.
+///
+/// Sections that are compressed (`SHF_COMPRESSED`) are decompressed.
+pub fn debug_sections(raw: &[u8]) -> Result<(Sections<'_>, bool)> {
+    let elf = Elf::parse(raw).context("Unable to parse ELF file")?;
+    let ctx = Ctx::new(elf.header.container()?, elf.header.endianness()?);
+    let mut sections = HashMap::new();
+    for shdr in elf.section_headers.iter() {
+        let Some(name) = elf.shdr_strtab.get_at(shdr.sh_name) else {
+            continue;
+        };
+        if !name.starts_with(".debug_") || shdr.sh_type == SHT_NOBITS {
+            continue;
+        }
+        let data = section_data(raw, name, shdr.sh_offset, shdr.sh_size)?;
+        let data = if shdr.sh_flags & SHF_COMPRESSED as u64 != 0 {
+            let chdr = CompressionHeader::parse(data, 0, ctx)
+                .with_context(|| format!("Invalid compression header of section {}", name))?;
+            let compressed = &data[CompressionHeader::size(ctx).min(data.len())..];
+            Cow::Owned(
+                decompress::decompress_section(chdr.ch_type, compressed)
+                    .with_context(|| format!("Unable to decompress section {}", name))?,
+            )
+        } else {
+            Cow::Borrowed(data)
+        };
+        sections.insert(name.to_owned(), data);
     }
+    Ok((sections, elf.little_endian))
+}
+
+/// Returns true iff the ELF file has a symbol table.
+pub fn has_symtab(raw: &[u8]) -> bool {
+    is_elf(raw).is_ok() && Elf::parse(raw).is_ok_and(|elf| !elf.syms.is_empty())
+}
 
-    bail!("Unable to find Linux banner.")
+/// Returns the machine of the ELF file.
+pub fn get_machine(raw: &[u8]) -> Option<u16> {
+    is_elf(raw).ok()?;
+    Elf::parse_header(raw).ok().map(|hdr| hdr.e_machine)
+}
+
+/// Returns true iff `raw` is an ELF core file, e.g., a vmcore.
+pub fn is_core(raw: &[u8]) -> bool {
+    is_elf(raw).is_ok() && Elf::parse_header(raw).is_ok_and(|hdr| hdr.e_type == ET_CORE)
+}
+
+/// Returns the program headers of the ELF file.
+pub fn program_headers(raw: &[u8]) -> Result<Vec<ProgramHeader>> {
+    let header = Elf::parse_header(raw).context("Unable to parse ELF header")?;
+    let ctx = Ctx::new(header.container()?, header.endianness()?);
+    ProgramHeader::parse(raw, header.e_phoff as usize, header.e_phnum as usize, ctx)
+        .context("Unable to parse ELF program headers")
+}
+
+/// Returns the program headers of the loadable segments of the ELF file.
+pub fn load_segments(raw: &[u8]) -> Result<Vec<ProgramHeader>> {
+    Ok(program_headers(raw)?
+        .into_iter()
+        .filter(|phdr| phdr.p_type == PT_LOAD)
+        .collect())
+}
+
+/// Returns the location of the contents of the segment `phdr` in `raw`.
+///
+/// Returns `None` if the segment is not within the file.
+pub fn segment_range(raw: &[u8], phdr: &ProgramHeader) -> Option<Range<usize>> {
+    let end = phdr.p_offset.checked_add(phdr.p_filesz)?;
+    if end > raw.len() as u64 {
+        log::debug!("Segment at {:#x} exceeds ELF file", phdr.p_offset);
+        return None;
+    }
+    Some(phdr.p_offset as usize..end as usize)
 }
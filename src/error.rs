@@ -0,0 +1,99 @@
+//! Errors of the public API.
+//!
+//! The failures that users of the crate can act on get their own variants,
+//! everything else is passed on as [`Error::Other`]. Internally, errors are
+//! `anyhow` errors, which are converted at the API boundary.
+
+use crate::btf::BtfHeader;
+
+use std::fmt;
+
+/// Check of an ISF file that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationKind {
+    /// Types of symbols are undefined.
+    SymbolTypes,
+    /// Types of fields of user types are undefined.
+    UserTypes,
+    /// Fields do not fit into their user types.
+    Layout,
+    /// Base types of enums are undefined.
+    Enums,
+    /// Type descriptors are malformed, e.g., bitfields of structs.
+    Descriptors,
+}
+
+impl fmt::Display for VerificationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            VerificationKind::SymbolTypes => "Symbol type",
+            VerificationKind::UserTypes => "User type",
+            VerificationKind::Layout => "Layout",
+            VerificationKind::Enums => "Enum",
+            VerificationKind::Descriptors => "Type descriptor",
+        })
+    }
+}
+
+/// Error of the public API.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The BTF file is neither a raw `.BTF` section nor an ELF file.
+    #[error(
+        "Provided BTF file {name} is neither .BTF section nor ELF: {len} bytes{}",
+        if *len < BtfHeader::LEN { ", too small" } else { "" }
+    )]
+    NotBtfOrElf { name: String, len: usize },
+    /// The BTF section of the BTF file is malformed.
+    #[error("Unable to parse BTF of {name}")]
+    BtfParse {
+        name: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// Too many lines of a symbol map are invalid. `line` is the first invalid
+    /// one, whose error is the source.
+    #[error("{invalid} of {lines} lines of {name} are invalid, the first is line {line}")]
+    MapParse {
+        name: String,
+        line: usize,
+        invalid: usize,
+        lines: usize,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// None of the symbols that the symbols are rebased on is in the symbol
+    /// sources.
+    #[error("No text anchor found in symbol sources, tried {}.", .tried.join(", "))]
+    MissingAnchorSymbol { tried: &'static [&'static str] },
+    /// The symbol given with `--anchor-symbol` is not in the symbol sources.
+    #[error("Anchor symbol {name} not found in symbol sources.")]
+    AnchorSymbolNotFound { name: String },
+    /// The package has no vmlinux at any of the paths that are tried.
+    #[error("No vmlinux found in {name}, tried {}.", .tried.join(", "))]
+    PackageLayout {
+        name: String,
+        tried: &'static [&'static str],
+    },
+    /// No banner was given and none could be read from the sources.
+    #[error("Unable to find Linux banner.")]
+    BannerNotFound,
+    /// The ISF file references undefined types.
+    #[error("{kind} verification failed: {details}")]
+    IsfVerification {
+        kind: VerificationKind,
+        details: String,
+    },
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Error {
+    /// Recovers the errors of the API that were passed on as `anyhow` errors,
+    /// dropping the context that was added to them.
+    fn from(err: anyhow::Error) -> Self {
+        err.downcast::<Error>().unwrap_or_else(Error::Other)
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,173 @@
+//! C interface for generating ISF files, enabled by the `ffi` feature.
+//!
+//! The crate is built as `cdylib` with `cargo rustc --crate-type cdylib`,
+//! `include/btf2json.h` declares the functions. Strings that are returned to C
+//! are owned by the library and released with [`btf2json_free`]. Panics do not
+//! cross the boundary, they are returned as [`BTF2JSON_ERR_PANIC`].
+
+use crate::GenerationOptions;
+
+use std::any::Any;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// The ISF file was generated.
+pub const BTF2JSON_OK: c_int = 0;
+/// The ISF file could not be generated, like exit code 1 of the command line.
+pub const BTF2JSON_ERR_GENERATION: c_int = 1;
+/// The arguments are invalid, like exit code 3 of the command line.
+pub const BTF2JSON_ERR_ARGUMENTS: c_int = 3;
+/// The generation panicked.
+pub const BTF2JSON_ERR_PANIC: c_int = 4;
+
+/// Failed call, with the code that is returned and the message.
+struct FfiError(c_int, String);
+
+/// Generates the ISF file from the BTF file at `btf_path`.
+///
+/// `map_path` is an optional System.map, `banner` an optional Linux banner,
+/// both may be `NULL`. On success, the ISF JSON is stored in `*out_json` and
+/// [`BTF2JSON_OK`] is returned. Otherwise, `*out_json` is `NULL`, an error code
+/// is returned, and the message is stored in `*out_err` unless `out_err` is
+/// `NULL`.
+///
+/// Like the command line without `--strict`, failed verifications are only
+/// logged and undefined types are stubbed.
+///
+/// # Safety
+///
+/// The paths and the banner must be `NULL` or NUL-terminated strings,
+/// `out_json` and `out_err` must be `NULL` or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn btf2json_generate(
+    btf_path: *const c_char,
+    map_path: *const c_char,
+    banner: *const c_char,
+    out_json: *mut *mut c_char,
+    out_err: *mut *mut c_char,
+) -> c_int {
+    if !out_json.is_null() {
+        *out_json = ptr::null_mut();
+    }
+    if !out_err.is_null() {
+        *out_err = ptr::null_mut();
+    }
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        if out_json.is_null() {
+            return Err(FfiError(
+                BTF2JSON_ERR_ARGUMENTS,
+                String::from("out_json is NULL"),
+            ));
+        }
+        let Some(btf_path) = c_str(btf_path, "btf_path")? else {
+            return Err(FfiError(
+                BTF2JSON_ERR_ARGUMENTS,
+                String::from("btf_path is NULL"),
+            ));
+        };
+        generate(
+            btf_path,
+            c_str(map_path, "map_path")?,
+            c_str(banner, "banner")?,
+        )
+    }))
+    .unwrap_or_else(|payload| {
+        Err(FfiError(
+            BTF2JSON_ERR_PANIC,
+            format!("btf2json panicked: {}", panic_message(&*payload)),
+        ))
+    });
+
+    match result {
+        Ok(json) => {
+            *out_json = into_c_string(json);
+            BTF2JSON_OK
+        }
+        Err(FfiError(code, msg)) => {
+            if !out_err.is_null() {
+                *out_err = into_c_string(msg);
+            }
+            code
+        }
+    }
+}
+
+/// Releases a string returned by [`btf2json_generate`]. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a string returned by this library that was not
+/// released yet.
+#[no_mangle]
+pub unsafe extern "C" fn btf2json_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Generates the ISF JSON through the library API.
+fn generate(
+    btf_path: &str,
+    map_path: Option<&str>,
+    banner: Option<&str>,
+) -> Result<String, FfiError> {
+    let mut options = GenerationOptions::new().btf_file(btf_path);
+    if let Some(map_path) = map_path {
+        options = options.map_file(map_path);
+    }
+    if let Some(banner) = banner {
+        options = options.banner(banner);
+    }
+    let mut isf = options.generate().map_err(|err| {
+        FfiError(
+            BTF2JSON_ERR_GENERATION,
+            format!("{:#}", anyhow::Error::from(err)),
+        )
+    })?;
+    for err in isf.apply_quirks() {
+        log::warn!("{}", err);
+    }
+    isf.to_json_string()
+        .map_err(|err| FfiError(BTF2JSON_ERR_GENERATION, format!("{:#}", err)))
+}
+
+/// Returns the string `s` that is passed as argument `name`, `None` if it is
+/// `NULL`.
+///
+/// # Safety
+///
+/// `s` must be `NULL` or a NUL-terminated string.
+unsafe fn c_str<'a>(s: *const c_char, name: &str) -> Result<Option<&'a str>, FfiError> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(Some(s)),
+        Err(_) => Err(FfiError(
+            BTF2JSON_ERR_ARGUMENTS,
+            format!("{} is not UTF-8", name),
+        )),
+    }
+}
+
+/// Hands `s` over to C, dropping NUL bytes, e.g., of quoted input lines.
+fn into_c_string(s: String) -> *mut c_char {
+    let mut bytes = s.into_bytes();
+    bytes.retain(|&b| b != 0);
+    CString::new(bytes)
+        .expect("BUG: NUL bytes were dropped")
+        .into_raw()
+}
+
+/// Returns the message of the panic with `payload`.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "unknown panic"
+    }
+}
@@ -0,0 +1,246 @@
+//! Dependencies between the types of an ISF file, as followed by `--prune` and
+//! exported by `btf2json graph`.
+
+use crate::cli::GraphArgs;
+use crate::isf::Isf;
+use crate::v_types::{TypeDescr, UserKind};
+use crate::GenerationContext;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::io::{self, Write};
+
+use anyhow::{bail, Error, Result};
+
+/// Kind of a type that other types depend on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    Struct,
+    Union,
+    Enum,
+    Base,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Struct => "struct",
+            Self::Union => "union",
+            Self::Enum => "enum",
+            Self::Base => "base",
+        }
+    }
+}
+
+/// Type of an ISF file, by kind and name.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeRef {
+    pub kind: Kind,
+    pub name: String,
+}
+
+impl TypeRef {
+    fn new(kind: Kind, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    /// Returns the type named `name`, which is looked up in the user types,
+    /// the enums, and the base types, in that order.
+    pub fn lookup(isf: &Isf, name: &str) -> Option<Self> {
+        if let Some(ut) = isf.get_user_type(name) {
+            let kind = match ut.kind() {
+                UserKind::Union => Kind::Union,
+                UserKind::Struct | UserKind::Class => Kind::Struct,
+            };
+            Some(Self::new(kind, name))
+        } else if isf.get_enum(name).is_some() {
+            Some(Self::new(Kind::Enum, name))
+        } else if isf.get_base_type(name).is_some() {
+            Some(Self::new(Kind::Base, name))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the size of the type in bytes, or `None` if it is undefined.
+    pub fn size(&self, isf: &Isf) -> Option<u64> {
+        match self.kind {
+            Kind::Struct | Kind::Union => isf.get_user_type(&self.name).map(|ut| ut.size()),
+            Kind::Enum => isf.get_enum(&self.name).map(|e| e.size() as u64),
+            Kind::Base => isf.get_base_type(&self.name).map(|b| b.size() as u64),
+        }
+    }
+}
+
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind.as_str(), self.name)
+    }
+}
+
+/// Adds the types that `t` refers to to `types`.
+///
+/// Types are referred to through the subtypes of arrays, pointers, bitfields,
+/// and functions.
+pub fn referenced_types(t: &TypeDescr, types: &mut Vec<TypeRef>) {
+    match t {
+        TypeDescr::Base { name } => types.push(TypeRef::new(Kind::Base, name)),
+        TypeDescr::Enum { name } => types.push(TypeRef::new(Kind::Enum, name)),
+        TypeDescr::Struct { name } => types.push(TypeRef::new(Kind::Struct, name)),
+        TypeDescr::Union { name } => types.push(TypeRef::new(Kind::Union, name)),
+        TypeDescr::Array { subtype, .. } | TypeDescr::Pointer { subtype } => {
+            referenced_types(subtype, types)
+        }
+        TypeDescr::Bitfield { type_, .. } => referenced_types(type_, types),
+        TypeDescr::Function {
+            return_type,
+            parameters,
+            ..
+        } => {
+            for t in return_type
+                .iter()
+                .map(Box::as_ref)
+                .chain(parameters.iter().flatten())
+            {
+                referenced_types(t, types);
+            }
+        }
+    }
+}
+
+/// Returns the types that `t` directly depends on, with the name of the field
+/// that refers to them.
+///
+/// User types depend on the types of their fields, enums on their base type.
+/// Undefined types have no dependencies.
+pub fn dependencies(isf: &Isf, t: &TypeRef) -> Vec<(Option<String>, TypeRef)> {
+    let mut deps = Vec::new();
+    match t.kind {
+        Kind::Struct | Kind::Union => {
+            let Some(ut) = isf.get_user_type(&t.name) else {
+                return deps;
+            };
+            let mut types = Vec::new();
+            for (name, field) in ut.fields() {
+                referenced_types(field.type_descr(), &mut types);
+                deps.extend(types.drain(..).map(|t| (Some(name.to_string()), t)));
+            }
+        }
+        Kind::Enum => {
+            if let Some(e) = isf.get_enum(&t.name) {
+                deps.push((None, TypeRef::new(Kind::Base, e.base())));
+            }
+        }
+        Kind::Base => (),
+    }
+    deps
+}
+
+/// Dependency graph of the types of an ISF file.
+pub struct TypeGraph {
+    /// Types with their size in bytes, `None` for undefined types.
+    nodes: BTreeMap<TypeRef, Option<u64>>,
+    /// Dependencies with the name of the field, if any.
+    edges: BTreeSet<(TypeRef, TypeRef, Option<String>)>,
+}
+
+impl TryFrom<&GraphArgs> for TypeGraph {
+    type Error = Error;
+
+    fn try_from(args: &GraphArgs) -> Result<TypeGraph> {
+        let mut generate = args.generate.clone();
+        generate.types_only = true;
+        let isf = Isf::try_from(GenerationContext::try_from(&generate)?)?;
+        TypeGraph::new(&isf, &args.root, args.depth)
+    }
+}
+
+impl TypeGraph {
+    /// Returns the graph of the types that are reachable from the types named
+    /// in `roots` within `depth` dependencies, or of all types if there are no
+    /// roots.
+    pub fn new(isf: &Isf, roots: &[String], depth: Option<usize>) -> Result<Self> {
+        let mut pending = VecDeque::new();
+        for root in roots {
+            let Some(t) = TypeRef::lookup(isf, root) else {
+                bail!("Root type `{}` is not defined", root);
+            };
+            pending.push_back((t, 0));
+        }
+        if roots.is_empty() {
+            let names = isf
+                .user_types()
+                .map(|(name, _)| name)
+                .chain(isf.enums().map(|(name, _)| name))
+                .chain(isf.base_types().map(|(name, _)| name));
+            pending.extend(names.filter_map(|name| Some((TypeRef::lookup(isf, name)?, 0))));
+        }
+
+        let mut graph = TypeGraph {
+            nodes: BTreeMap::new(),
+            edges: BTreeSet::new(),
+        };
+        while let Some((t, level)) = pending.pop_front() {
+            if graph.nodes.contains_key(&t) {
+                continue;
+            }
+            graph.nodes.insert(t.clone(), t.size(isf));
+            if depth.is_some_and(|depth| level >= depth) {
+                continue;
+            }
+            for (field, dep) in dependencies(isf, &t) {
+                graph.edges.insert((t.clone(), dep.clone(), field));
+                pending.push_back((dep, level + 1));
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Returns the number of types in the graph.
+    pub fn nr_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of dependencies in the graph.
+    pub fn nr_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Writes the graph in the Graphviz dot format to `writer`.
+    ///
+    /// Nodes are labeled with the kind, name, and size of the type, edges with
+    /// the name of the field.
+    pub fn write_dot(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "digraph types {{")?;
+        for (t, size) in &self.nodes {
+            let size = size.map_or_else(|| "undefined".to_string(), |size| size.to_string());
+            writeln!(
+                writer,
+                "  {} [label={}];",
+                quote(&t.to_string()),
+                quote(&format!("{} ({})", t, size))
+            )?;
+        }
+        for (from, to, field) in &self.edges {
+            write!(
+                writer,
+                "  {} -> {}",
+                quote(&from.to_string()),
+                quote(&to.to_string())
+            )?;
+            match field {
+                Some(field) => writeln!(writer, " [label={}];", quote(field))?,
+                None => writeln!(writer, ";")?,
+            }
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+/// Returns `s` as a quoted dot ID.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,504 @@
+//! Utilities for working with memory images.
+
+use crate::btf::{BtfHeader, Endian};
+
+use std::ops::Range;
+
+use memchr::memmem;
+
+/// Start of the header of a little endian BTF section: magic and version.
+const BTF_PREFIX_LE: [u8; 3] = [0x9f, 0xeb, 0x01];
+/// Start of the header of a big endian BTF section: magic and version.
+const BTF_PREFIX_BE: [u8; 3] = [0xeb, 0x9f, 0x01];
+/// Start of the Linux banner.
+const BANNER_PREFIX: &[u8] = b"Linux version ";
+/// Maximum length of the Linux banner.
+const MAX_BANNER_LEN: usize = 1024;
+/// Number of entries of `kallsyms_token_table` and `kallsyms_token_index`.
+const KALLSYMS_TOKENS: usize = 256;
+/// Number of symbols between two entries of `kallsyms_markers`.
+const KALLSYMS_MARKER_STRIDE: usize = 256;
+/// Maximum length of a token, which is far longer than any the kernel builds.
+const KALLSYMS_MAX_TOKEN_LEN: usize = 128;
+/// Maximum number of bytes of the compressed names after the last marker.
+const KALLSYMS_MAX_TAIL: usize = KALLSYMS_MARKER_STRIDE * (2 + 0xff);
+
+/// Returns all locations in `raw` that hold a sane BTF header, together with
+/// the range of the section it describes.
+///
+/// Besides the header, only the start of the string section is checked, which
+/// must be the empty string.
+pub fn btf_candidates(raw: &[u8]) -> Vec<(BtfHeader, Range<usize>)> {
+    let mut offsets: Vec<usize> = memmem::find_iter(raw, &BTF_PREFIX_LE)
+        .chain(memmem::find_iter(raw, &BTF_PREFIX_BE))
+        .collect();
+    offsets.sort_unstable();
+    log::debug!("Found {} BTF magic values in image", offsets.len());
+
+    offsets
+        .into_iter()
+        .filter_map(|off| match BtfHeader::parse(&raw[off..]) {
+            Ok(hdr) if starts_with_empty_string(raw, off, &hdr) => {
+                Some((hdr, off..off + hdr.section_len() as usize))
+            }
+            Ok(_) => {
+                log::trace!("No BTF string section at {:#x}", off);
+                None
+            }
+            Err(err) => {
+                log::trace!("No BTF header at {:#x}: {}", off, err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns all BTF sections found by [`btf_candidates`] that lie within one of
+/// the `segments` of `raw`.
+///
+/// The returned ranges are relative to `raw`.
+pub fn btf_candidates_in(raw: &[u8], segments: &[Range<usize>]) -> Vec<(BtfHeader, Range<usize>)> {
+    segments
+        .iter()
+        .flat_map(|segment| {
+            btf_candidates(&raw[segment.clone()])
+                .into_iter()
+                .map(|(hdr, range)| (hdr, segment.start + range.start..segment.start + range.end))
+        })
+        .collect()
+}
+
+/// Returns true iff the string section of the BTF section at `off` starts with
+/// the empty string, as it does for all base BTF.
+fn starts_with_empty_string(raw: &[u8], off: usize, hdr: &BtfHeader) -> bool {
+    hdr.str_len > 0 && raw[off + hdr.hdr_len as usize + hdr.str_off as usize] == 0
+}
+
+/// Returns the first Linux banner in `raw`.
+///
+/// Besides the prefix, a banner must be NUL-terminated, printable, and end
+/// with a newline, which rules out, e.g., the format string of
+/// `linux_proc_banner`.
+pub fn find_banner(raw: &[u8]) -> Option<&str> {
+    memmem::find_iter(raw, BANNER_PREFIX).find_map(|off| {
+        let banner = banner_at(&raw[off..])?;
+        log::debug!("Found Linux banner at {:#x} in memory image", off);
+        Some(banner)
+    })
+}
+
+/// Returns the Linux banner at the start of `data`, without its newline, if
+/// there is one, see [`find_banner`].
+pub fn banner_at(data: &[u8]) -> Option<&str> {
+    if !data.starts_with(BANNER_PREFIX) {
+        return None;
+    }
+    let data = &data[..data.len().min(MAX_BANNER_LEN)];
+    let banner = &data[..data.iter().position(|&b| b == 0)?];
+    let text = banner.strip_suffix(b"\n")?;
+    if !text.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        return None;
+    }
+    std::str::from_utf8(text).ok()
+}
+
+/// Linear translation between the virtual addresses of the kernel image and
+/// physical addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysTranslation {
+    /// Virtual address that maps to `phys_base`.
+    virt_base: u64,
+    phys_base: u64,
+}
+
+impl PhysTranslation {
+    /// Returns the translation that maps `virt_base` to `phys_base`.
+    pub fn new(virt_base: u64, phys_base: u64) -> Self {
+        Self {
+            virt_base,
+            phys_base,
+        }
+    }
+
+    /// Returns the physical address of the virtual address `vaddr`, if it is
+    /// not below the virtual base.
+    pub fn to_phys(&self, vaddr: u64) -> Option<u64> {
+        vaddr
+            .checked_sub(self.virt_base)?
+            .checked_add(self.phys_base)
+    }
+
+    /// Returns the virtual address of the physical address `paddr`, if it is
+    /// not below the physical base.
+    pub fn to_virt(&self, paddr: u64) -> Option<u64> {
+        paddr
+            .checked_sub(self.phys_base)?
+            .checked_add(self.virt_base)
+    }
+}
+
+impl std::fmt::Display for PhysTranslation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "virtual {:#x} at physical {:#x}",
+            self.virt_base, self.phys_base
+        )
+    }
+}
+
+/// Returns all Linux banners in `raw` as found by [`find_banner`], with their
+/// offsets.
+pub fn find_banners(raw: &[u8]) -> Vec<(usize, &str)> {
+    memmem::find_iter(raw, BANNER_PREFIX)
+        .filter_map(|off| Some((off, banner_at(&raw[off..])?)))
+        .collect()
+}
+
+/// Returns the first Linux banner found by [`find_banner`] in the `segments`
+/// of `raw`.
+pub fn find_banner_in<'a>(raw: &'a [u8], segments: &[Range<usize>]) -> Option<&'a str> {
+    segments
+        .iter()
+        .find_map(|segment| find_banner(&raw[segment.clone()]))
+}
+
+/// A symbol recovered from the kallsyms tables of a memory image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KallsymsEntry {
+    pub name: String,
+    /// Type of the symbol as in System.map, e.g., `T`.
+    pub kind: char,
+    pub addr: u64,
+}
+
+/// Reads integers of the kernel from a memory image.
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    raw: &'a [u8],
+    endian: Endian,
+    pointer_size: usize,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, off: usize) -> Option<[u8; N]> {
+        self.raw.get(off..off.checked_add(N)?)?.try_into().ok()
+    }
+
+    fn u16(&self, off: usize) -> Option<u16> {
+        let bytes = self.bytes(off)?;
+        Some(match self.endian {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, off: usize) -> Option<u32> {
+        let bytes = self.bytes(off)?;
+        Some(match self.endian {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        })
+    }
+
+    /// Reads a pointer, i.e., a `long` of the kernel.
+    fn ptr(&self, off: usize) -> Option<u64> {
+        if self.pointer_size == 4 {
+            return self.u32(off).map(u64::from);
+        }
+        let bytes = self.bytes(off)?;
+        Some(match self.endian {
+            Endian::Little => u64::from_le_bytes(bytes),
+            Endian::Big => u64::from_be_bytes(bytes),
+        })
+    }
+
+    /// Returns `off` aligned like the kallsyms tables, to the pointer size.
+    fn align(&self, off: usize) -> usize {
+        off.next_multiple_of(self.pointer_size)
+    }
+}
+
+/// Recovers the symbols from the kallsyms tables in the memory image `raw` of
+/// a kernel with the given byte order and pointer size.
+///
+/// The tables are located by the structure of `kallsyms_token_index`, which is
+/// followed by `kallsyms_token_table`, `kallsyms_markers`, `kallsyms_names`,
+/// and `kallsyms_num_syms` when going backwards. The addresses are in
+/// `kallsyms_offsets`, relative to `kallsyms_relative_base`, or in
+/// `kallsyms_addresses`. Since Linux 6.4, they follow the token index, before
+/// they precede the number of symbols. The symbols are sorted by address,
+/// which tells the layouts apart.
+pub fn kallsyms(raw: &[u8], endian: Endian, pointer_size: usize) -> Option<Vec<KallsymsEntry>> {
+    let reader = Reader {
+        raw,
+        endian,
+        pointer_size,
+    };
+    (0..raw.len().saturating_sub(2 * KALLSYMS_TOKENS))
+        .step_by(4)
+        .filter_map(|off| token_index(reader, off))
+        .find_map(|(off, index)| {
+            log::debug!("Found kallsyms_token_index candidate at {:#x}", off);
+            let (table, tokens) = token_table(reader, off, &index)?;
+            let (names_off, names) = names(reader, table, &tokens)?;
+            let symbols = addresses(reader, off, names_off, names)?;
+            log::info!(
+                "Found {} kallsyms entries at {:#x} in memory image",
+                symbols.len(),
+                off
+            );
+            Some(symbols)
+        })
+}
+
+/// Returns the symbols of [`kallsyms`] in the first of the `segments` of
+/// `raw` that has kallsyms tables.
+pub fn kallsyms_in(
+    raw: &[u8],
+    segments: &[Range<usize>],
+    endian: Endian,
+    pointer_size: usize,
+) -> Option<Vec<KallsymsEntry>> {
+    segments
+        .iter()
+        .find_map(|segment| kallsyms(&raw[segment.clone()], endian, pointer_size))
+}
+
+/// Returns the offsets in `kallsyms_token_index` if there is one at `off`.
+///
+/// The offsets start at zero and increase by at least two, for the shortest
+/// token and its NUL.
+fn token_index(reader: Reader, off: usize) -> Option<(usize, Vec<usize>)> {
+    let valid_step = |prev: u16, next: u16| {
+        next.checked_sub(prev)
+            .is_some_and(|step| (2..=KALLSYMS_MAX_TOKEN_LEN + 1).contains(&usize::from(step)))
+    };
+    // Cheap checks first, most of the image is rejected here.
+    if reader.u16(off)? != 0 || !valid_step(0, reader.u16(off + 2)?) {
+        return None;
+    }
+    let mut index = Vec::with_capacity(KALLSYMS_TOKENS);
+    let mut prev = None;
+    for i in 0..KALLSYMS_TOKENS {
+        let next = reader.u16(off + 2 * i)?;
+        if prev.is_some_and(|prev| !valid_step(prev, next)) {
+            return None;
+        }
+        index.push(usize::from(next));
+        prev = Some(next);
+    }
+    Some((off, index))
+}
+
+/// Returns the start of `kallsyms_token_table` and its tokens, which precede
+/// the token index at `index_off` with `index`.
+fn token_table(reader: Reader, index_off: usize, index: &[usize]) -> Option<(usize, Vec<String>)> {
+    let last = *index.last()?;
+    // The table is followed by padding to the alignment of the index.
+    (0..reader.pointer_size).find_map(|pad| {
+        let end = index_off.checked_sub(pad)?;
+        if reader.raw.get(end.checked_sub(1)?) != Some(&0) {
+            return None;
+        }
+        let last_start = reader.raw[..end - 1]
+            .iter()
+            .rposition(|&b| b == 0)
+            .map_or(0, |nul| nul + 1);
+        let table = last_start.checked_sub(last)?;
+        let tokens = index
+            .iter()
+            .zip(index.iter().skip(1).chain([&(end - table)]))
+            .map(|(&start, &next)| {
+                let nul = next - 1;
+                let token = reader.raw.get(table + start..table + nul)?;
+                (reader.raw[table + nul] == 0
+                    && !token.is_empty()
+                    && token.iter().all(|b| b.is_ascii_graphic()))
+                .then(|| String::from_utf8_lossy(token).into_owned())
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((table, tokens))
+    })
+}
+
+/// Returns the start of `kallsyms_names` and the decompressed names, with the
+/// type as first character.
+///
+/// The names are followed by `kallsyms_markers`, which precede the token table
+/// at `table`, and preceded by `kallsyms_num_syms`.
+fn names(reader: Reader, table: usize, tokens: &[String]) -> Option<(usize, Vec<String>)> {
+    let ptr = reader.pointer_size;
+    // The markers are followed by padding to the alignment of the table.
+    let padded = table
+        .checked_sub(4)
+        .filter(|&end| reader.u32(end) == Some(0));
+    [Some(table), padded].into_iter().flatten().find_map(|end| {
+        let markers = markers(reader, end)?;
+        let markers_off = end - 4 * markers.len();
+        let count = KALLSYMS_MARKER_STRIDE * (markers.len() - 1);
+        // `kallsyms_num_syms` is a `long` before Linux 6.1 and an `int` since,
+        // either one aligned pointer before the names.
+        let num_syms = |start: usize| {
+            [ptr, 4].into_iter().find_map(|back| {
+                let n = reader.u32(start.checked_sub(back)?)? as usize;
+                (n > count && n <= count + KALLSYMS_MARKER_STRIDE).then_some(n)
+            })
+        };
+        // The names of the symbols after the last marker take at most
+        // `KALLSYMS_MAX_TAIL` bytes.
+        let latest = markers_off.checked_sub(*markers.last()?)?;
+        let latest = latest - latest % ptr;
+        (0..=KALLSYMS_MAX_TAIL / ptr)
+            .map_while(|i| latest.checked_sub(i * ptr))
+            .find_map(|start| {
+                let (names, end) = decode_names(reader, start, num_syms(start)?, &markers, tokens)?;
+                (end <= markers_off && markers_off - end < ptr).then_some((start, names))
+            })
+    })
+}
+
+/// Returns `kallsyms_markers`, which ends at `end`, by reading backwards to
+/// its first entry, which is zero.
+fn markers(reader: Reader, end: usize) -> Option<Vec<usize>> {
+    let mut markers = Vec::new();
+    let mut off = end;
+    loop {
+        off = off.checked_sub(4)?;
+        let marker = reader.u32(off)? as usize;
+        if markers.last().is_some_and(|&next| marker >= next) {
+            return None;
+        }
+        markers.push(marker);
+        if marker == 0 {
+            break;
+        }
+    }
+    markers.reverse();
+    Some(markers)
+}
+
+/// Decompresses the `n` names at `start`, and returns them and their end.
+///
+/// Every name starts with its length in tokens, followed by the indices of the
+/// tokens. Fails unless every 256th name is at its marker.
+fn decode_names(
+    reader: Reader,
+    start: usize,
+    n: usize,
+    markers: &[usize],
+    tokens: &[String],
+) -> Option<(Vec<String>, usize)> {
+    let mut off = start;
+    let mut names = Vec::with_capacity(n);
+    for i in 0..n {
+        if i % KALLSYMS_MARKER_STRIDE == 0
+            && markers.get(i / KALLSYMS_MARKER_STRIDE) != Some(&(off - start))
+        {
+            return None;
+        }
+        let mut len = usize::from(*reader.raw.get(off)?);
+        off += 1;
+        // Since Linux 6.1, lengths of 128 tokens and more take two bytes.
+        if len & 0x80 != 0 {
+            len = (len & 0x7f) | usize::from(*reader.raw.get(off)?) << 7;
+            off += 1;
+        }
+        let name: String = reader
+            .raw
+            .get(off..off + len)?
+            .iter()
+            .map(|&token| tokens[usize::from(token)].as_str())
+            .collect();
+        off += len;
+        // The type and at least one character of the name.
+        if name.len() < 2 {
+            return None;
+        }
+        names.push(name);
+    }
+    Some((names, off))
+}
+
+/// Returns the symbols with the addresses for the `names` at `names_off`,
+/// whose token index is at `index_off`.
+fn addresses(
+    reader: Reader,
+    index_off: usize,
+    names_off: usize,
+    names: Vec<String>,
+) -> Option<Vec<KallsymsEntry>> {
+    let (ptr, n) = (reader.pointer_size, names.len());
+    // Since Linux 6.4, the addresses follow the token index.
+    let after = reader.align(index_off + 2 * KALLSYMS_TOKENS);
+    // Before, they precede `kallsyms_num_syms`, which is a `long` then.
+    let num_syms = names_off.checked_sub(ptr);
+    let base_before = num_syms.and_then(|off| off.checked_sub(ptr));
+    let offsets_before = base_before.and_then(|off| off.checked_sub(reader.align(4 * n)));
+    let addresses_before = num_syms.and_then(|off| off.checked_sub(ptr * n));
+
+    let addrs = [
+        Some(after).zip(Some(reader.align(after + 4 * n))),
+        offsets_before.zip(base_before),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(offsets, base)| relative_addresses(reader, offsets, base, n))
+    .chain(
+        [Some(after), addresses_before]
+            .into_iter()
+            .flatten()
+            .map(|off| absolute_addresses(reader, off, n)),
+    )
+    .flatten()
+    .find(|addrs| addrs.windows(2).all(|w| w[0] <= w[1]) && addrs.first() < addrs.last())?;
+
+    names
+        .into_iter()
+        .zip(addrs)
+        .map(|(name, addr)| {
+            let mut chars = name.chars();
+            let kind = chars.next()?;
+            Some(KallsymsEntry {
+                name: chars.as_str().to_owned(),
+                kind,
+                addr,
+            })
+        })
+        .collect()
+}
+
+/// Returns the addresses in `kallsyms_offsets` at `off`, relative to
+/// `kallsyms_relative_base` at `base` (CONFIG_KALLSYMS_BASE_RELATIVE).
+fn relative_addresses(reader: Reader, off: usize, base: usize, n: usize) -> Option<Vec<u64>> {
+    let base = reader.ptr(base)?;
+    let offsets = (0..n)
+        .map(|i| reader.u32(off + 4 * i).map(|offset| offset as i32))
+        .collect::<Option<Vec<_>>>()?;
+    // With CONFIG_KALLSYMS_ABSOLUTE_PERCPU, the offsets of per-CPU symbols are
+    // their addresses, and the other ones are negative, as `-1 - offset`.
+    let absolute_percpu = offsets.iter().any(|&offset| offset < 0);
+    let mask = if reader.pointer_size == 4 {
+        u64::from(u32::MAX)
+    } else {
+        u64::MAX
+    };
+    Some(
+        offsets
+            .into_iter()
+            .map(|offset| match (absolute_percpu, offset) {
+                (true, 0..) => offset as u64,
+                (true, _) => base.wrapping_add(u64::from(!offset as u32)),
+                (false, _) => base.wrapping_add(u64::from(offset as u32)),
+            })
+            .map(|addr| addr & mask)
+            .collect(),
+    )
+}
+
+/// Returns the addresses in `kallsyms_addresses` at `off`.
+fn absolute_addresses(reader: Reader, off: usize, n: usize) -> Option<Vec<u64>> {
+    (0..n)
+        .map(|i| reader.ptr(off + reader.pointer_size * i))
+        .collect()
+}
@@ -0,0 +1,151 @@
+//! Raw input data that profiles are generated from.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "mmap")]
+use memmap::Mmap;
+
+/// Name under which inputs that are read from standard input are recorded.
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// Returns true iff `path` refers to standard input, i.e., is `-` or
+/// `/dev/stdin`.
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-") || path == Path::new("/dev/stdin")
+}
+
+/// Returns the name under which the input at `path` is recorded, i.e., its file
+/// name.
+pub fn file_name(path: &Path) -> Result<String> {
+    if is_stdin(path) {
+        return Ok(String::from(STDIN_NAME));
+    }
+    Ok(path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Fails if more than one of the inputs at `paths` refers to standard input.
+pub fn ensure_single_stdin<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Result<()> {
+    if paths.into_iter().filter(|path| is_stdin(path)).count() > 1 {
+        bail!("Only one input can be read from standard input");
+    }
+    Ok(())
+}
+
+/// Raw bytes of an input to the profile generation.
+///
+/// Inputs can come from files on disk, be produced at runtime, e.g., by
+/// decompressing a file or by reading a stream, or be embedded into the
+/// executable.
+pub enum InputData {
+    /// Memory-mapped file.
+    #[cfg(feature = "mmap")]
+    Mapped(Mmap),
+    /// Buffer that was produced at runtime.
+    Owned(Vec<u8>),
+    /// Buffer that is embedded into the executable.
+    Static(&'static [u8]),
+}
+
+impl InputData {
+    /// Memory-maps the file at `path`.
+    #[cfg(feature = "mmap")]
+    pub fn map_file(path: &Path) -> Result<Self> {
+        let file = fs::File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        Ok(Self::Mapped(mmap))
+    }
+
+    /// Reads the file at `path`, memory-mapping is not built in.
+    #[cfg(not(feature = "mmap"))]
+    pub fn map_file(path: &Path) -> Result<Self> {
+        Self::read_file(path)
+    }
+
+    /// Reads the file at `path` into memory.
+    ///
+    /// Unlike [`InputData::map_file`] this works for files whose size is not
+    /// known in advance, e.g., in procfs.
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let buf = fs::read(path).with_context(|| format!("Unable to read {}", path.display()))?;
+
+        Ok(Self::Owned(buf))
+    }
+
+    /// Reads standard input to the end.
+    pub fn read_stdin() -> Result<Self> {
+        let mut buf = Vec::new();
+        io::stdin()
+            .lock()
+            .read_to_end(&mut buf)
+            .context("Unable to read standard input")?;
+
+        Ok(Self::Owned(buf))
+    }
+
+    /// Loads the input at `path`.
+    ///
+    /// Standard input and files that cannot be memory-mapped, e.g., in sysfs or
+    /// procfs, are read into memory. All other files are memory-mapped.
+    pub fn load(path: &Path) -> Result<Self> {
+        if is_stdin(path) {
+            return Self::read_stdin();
+        }
+        Self::map_file(path).or_else(|err| {
+            log::debug!("Unable to map {}, reading it: {}", path.display(), err);
+            Self::read_file(path)
+        })
+    }
+}
+
+impl AsRef<[u8]> for InputData {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(buf) => buf,
+            Self::Static(buf) => buf,
+        }
+    }
+}
+
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_ref()
+    }
+}
+
+impl fmt::Debug for InputData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            #[cfg(feature = "mmap")]
+            Self::Mapped(_) => "Mapped",
+            Self::Owned(_) => "Owned",
+            Self::Static(_) => "Static",
+        };
+        write!(f, "{}({} bytes)", kind, self.len())
+    }
+}
+
+impl From<Vec<u8>> for InputData {
+    fn from(buf: Vec<u8>) -> Self {
+        Self::Owned(buf)
+    }
+}
+
+impl From<&'static [u8]> for InputData {
+    fn from(buf: &'static [u8]) -> Self {
+        Self::Static(buf)
+    }
+}
@@ -1,33 +1,182 @@
 //! Internal representation of ISF files.
 
+use crate::cli::Quirk;
+use crate::error::VerificationKind;
 use crate::metadata::Metadata;
-use crate::GenerationContext;
-use crate::{btf, v_symbols, v_types};
+use crate::patch::{self, Patch};
+use crate::progress::Phase;
+use crate::schema;
+use crate::timings::Timings;
+use crate::v_types::Definition;
+use crate::{btf, diagnostics, error, graph, names, symbols, v_symbols, v_types};
+use crate::{GenerationContext, TRACE_TARGET};
 
+use std::cell::{Cell, RefCell};
+use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::iter;
+use std::mem;
+use std::path::Path;
+use std::time::Instant;
 
-use anyhow::{bail, Error, Result};
-use serde::Serialize;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-mod quirks {
+pub(crate) mod quirks {
     //! Manual adjustments that we have to make to the ISF file in order to meet
     //! Volatility's expectations.
 
     use super::*;
-    use std::collections::btree_map::Entry;
 
     /// Adds a base type named "pointer" with the appropriate size and
     /// endianness.
-    pub fn fixup_base(base_types: &mut BTreeMap<String, v_types::Base>, endian: &btf::Endian) {
+    pub fn fixup_base(
+        base_types: &mut BTreeMap<String, v_types::Base>,
+        width: v_symbols::AddressWidth,
+        endian: v_types::Endian,
+    ) {
         if let Entry::Vacant(ent) = base_types.entry(String::from("pointer")) {
-            ent.insert(v_types::Base::new_pointer(endian.into()));
+            ent.insert(v_types::Base::new_pointer(width.bytes(), endian));
         }
     }
+
+    /// Size of a standard base type.
+    enum Size {
+        Bytes(u8),
+        /// Size of `long`, i.e., of a pointer.
+        Long,
+        /// 16 bytes, on 64-bit architectures only.
+        Int128,
+    }
+
+    /// Base types that Volatility expects by these names, with their size,
+    /// signedness, and kind.
+    ///
+    /// `char` is unsigned as the kernel is built with `-funsigned-char` since
+    /// 6.2. GCC and Clang spell the unsigned `__int128` differently.
+    const STANDARD_BASE: [(&str, Size, bool, v_types::BaseKind); 21] = {
+        use v_types::BaseKind::{Bool, Int, Void};
+        use Size::{Bytes, Int128, Long};
+        [
+            ("void", Bytes(0), false, Void),
+            ("_Bool", Bytes(1), false, Bool),
+            ("char", Bytes(1), false, Int),
+            ("signed char", Bytes(1), true, Int),
+            ("unsigned char", Bytes(1), false, Int),
+            ("short", Bytes(2), true, Int),
+            ("short int", Bytes(2), true, Int),
+            ("unsigned short", Bytes(2), false, Int),
+            ("short unsigned int", Bytes(2), false, Int),
+            ("int", Bytes(4), true, Int),
+            ("unsigned int", Bytes(4), false, Int),
+            ("long", Long, true, Int),
+            ("long int", Long, true, Int),
+            ("unsigned long", Long, false, Int),
+            ("long unsigned int", Long, false, Int),
+            ("long long", Bytes(8), true, Int),
+            ("long long int", Bytes(8), true, Int),
+            ("long long unsigned int", Bytes(8), false, Int),
+            ("__int128", Int128, true, Int),
+            ("__int128 unsigned", Int128, false, Int),
+            ("unsigned __int128", Int128, false, Int),
+        ]
+    };
+
+    /// Adds the standard base types that are missing.
+    ///
+    /// A missing type is an alias of another spelling with the same
+    /// definition, or synthesized if there is none.
+    pub fn fixup_standard_base(
+        base_types: &mut BTreeMap<String, v_types::Base>,
+        width: v_symbols::AddressWidth,
+        endian: v_types::Endian,
+    ) {
+        for (name, size, signed, kind) in STANDARD_BASE {
+            if base_types.contains_key(name) {
+                continue;
+            }
+            let size = match size {
+                Size::Bytes(bytes) => bytes,
+                Size::Long => width.bytes(),
+                Size::Int128 if width.bytes() < 8 => continue,
+                Size::Int128 => 16,
+            };
+            let base = v_types::Base::new(size, signed, kind, endian);
+            match base_types.iter().find(|(_, other)| **other == base) {
+                Some((other, _)) => {
+                    log::info!("Adding base type `{}` as alias of `{}`", name, other)
+                }
+                None => log::info!("Synthesizing base type `{}` of size {}", name, size),
+            }
+            base_types.insert(String::from(name), base);
+        }
+    }
+
+    /// Point of the generation at which a quirk is applied.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub enum Stage {
+        /// Once the types and symbols are constructed, see
+        /// [`Isf::try_from`].
+        Construction,
+        /// Once the ISF file is verified, see [`Isf::apply_quirks`].
+        Verification,
+    }
+
+    /// Adjustment of a quirk, which returns the problem it fixed, if any.
+    type Fixup = fn(&mut Isf) -> error::Result<()>;
+
+    /// The quirks in the order of their application.
+    const QUIRKS: [(Quirk, Stage, Fixup); 4] = [
+        (Quirk::PointerBase, Stage::Construction, |isf| {
+            if let Some((width, endian)) = isf.target {
+                fixup_base(&mut isf.base_types, width, endian);
+            }
+            Ok(())
+        }),
+        (Quirk::StandardBase, Stage::Construction, |isf| {
+            if let Some((width, endian)) = isf.target {
+                fixup_standard_base(&mut isf.base_types, width, endian);
+            }
+            Ok(())
+        }),
+        (
+            Quirk::VoidSymbols,
+            Stage::Verification,
+            Isf::fix_symbol_types,
+        ),
+        (Quirk::TypeStubs, Stage::Verification, Isf::fix_user_types),
+    ];
+
+    /// Applies the quirks of `stage` that are enabled for `isf`, and returns
+    /// the problems they fixed.
+    pub fn apply(isf: &mut Isf, stage: Stage) -> Vec<error::Error> {
+        let mut fixed = Vec::new();
+        for (quirk, _, fixup) in QUIRKS.iter().filter(|(_, at, _)| *at == stage) {
+            if !isf.quirks.contains(quirk) {
+                continue;
+            }
+            if let Some(name) = quirk.to_possible_value() {
+                log::info!("Applying quirk `{}`", name.get_name());
+            }
+            if let Err(err) = fixup(isf) {
+                fixed.push(err);
+            }
+        }
+        fixed
+    }
 }
 
 /// Representation of an ISF file.
-#[derive(Serialize)]
+///
+/// Unknown keys are ignored when reading an ISF file, so profiles generated by
+/// other tools, e.g., dwarf2json, can be loaded as well.
+#[derive(Serialize, Deserialize)]
 pub struct Isf {
     metadata: Metadata,
     user_types: BTreeMap<String, v_types::User>,
@@ -36,179 +185,1836 @@ pub struct Isf {
     // determining the name of the base type of an enum.
     base_types: BTreeMap<String, v_types::Base>,
     symbols: BTreeMap<String, v_symbols::Symbol>,
+    /// Number of types that were skipped as they could not be constructed.
+    #[serde(skip)]
+    skipped_types: usize,
+    /// Number of bitfields that exceeded their type and were fixed.
+    #[serde(skip)]
+    invalid_bitfields: usize,
+    /// BTF types of the entries, with `--annotate-btf-ids`.
+    #[serde(skip)]
+    provenance: Option<Provenance>,
+    /// Sources of the types of the symbols, if generated.
+    #[serde(skip)]
+    type_sources: Option<symbols::TypeSources>,
+    /// Quirks to apply, all of them for ISF files that were read.
+    #[serde(skip, default = "all_quirks")]
+    quirks: Vec<Quirk>,
+    /// Width of addresses and byte order of the target, if generated.
+    #[serde(skip)]
+    target: Option<(v_symbols::AddressWidth, v_types::Endian)>,
+    /// Durations of the phases of the generation, if generated.
+    #[serde(skip)]
+    timings: Timings,
+}
+
+fn all_quirks() -> Vec<Quirk> {
+    Quirk::value_variants().to_vec()
+}
+
+/// BTF type that an entry of an ISF file comes from.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct TypeOrigin {
+    pub btf_id: u32,
+    /// Primary name of the type if the entry is one of its aliases, see
+    /// [`Isf::split_names`] and, for base types, their C name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<String>,
+    /// Name of the BTF file that defines the type.
+    pub source: String,
+}
+
+/// BTF types that the entries of an ISF file come from, by name, see
+/// `--annotate-btf-ids`.
+///
+/// Entries that are not in BTF, like stubs and `pointer`, are missing.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Provenance {
+    pub user_types: BTreeMap<String, TypeOrigin>,
+    pub enums: BTreeMap<String, TypeOrigin>,
+    pub base_types: BTreeMap<String, TypeOrigin>,
+}
+
+impl Provenance {
+    /// Returns the BTF types of the entries of `isf`, of which `origins`
+    /// records the types.
+    fn new(
+        ctx: &GenerationContext,
+        isf: &Isf,
+        origins: &HashMap<Category, HashMap<String, btf::Id>>,
+    ) -> Self {
+        let collect = |names: Vec<&String>, category| {
+            names
+                .into_iter()
+                .filter_map(|name| {
+                    let id = *origins.get(&category)?.get(name)?;
+                    let primary = match category {
+                        Category::Base => ctx.btf.get_names_by_id(id, None).ok()?.remove(0),
+                        _ => Isf::split_names(ctx, id).0,
+                    };
+                    let origin = TypeOrigin {
+                        btf_id: id.into(),
+                        alias_of: (primary != *name).then_some(primary),
+                        source: ctx.btf.source_of(id).clone(),
+                    };
+                    Some((name.clone(), origin))
+                })
+                .collect()
+        };
+        Provenance {
+            user_types: collect(isf.user_types.keys().collect(), Category::User),
+            enums: collect(isf.enums.keys().collect(), Category::Enum),
+            base_types: collect(isf.base_types.keys().collect(), Category::Base),
+        }
+    }
+
+    /// Reads a provenance file from `reader`.
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(reader))?)
+    }
+
+    /// Writes the provenance as JSON to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Unable to write {}", path.display()))
+    }
+}
+
+/// Resolution of distinct types that end up with the same name.
+///
+/// Identical definitions are merged. Of conflicting definitions a real name
+/// beats a typedef alias, see [`is_alias`]. Otherwise the more complete one is
+/// kept, i.e., the one with more fields or constants and then the larger one;
+/// on ties the type with the lower ID wins. Optionally, the losing definition
+/// is kept under a suffixed name, see [`names::duplicate_type`].
+struct Duplicates<'a> {
+    btf: &'a btf::Btf,
+    keep: bool,
+    identical: usize,
+    conflicting: usize,
+}
+
+impl<'a> Duplicates<'a> {
+    fn new(btf: &'a btf::Btf, keep: bool) -> Self {
+        Self {
+            btf,
+            keep,
+            identical: 0,
+            conflicting: 0,
+        }
+    }
+
+    /// Inserts the element `elem` of the type with `id` into `elems` under
+    /// `name`.
+    ///
+    /// `origins` records the type that each element in `elems` belongs to.
+    fn insert<T: Definition>(
+        &mut self,
+        elems: &mut BTreeMap<String, T>,
+        origins: &mut HashMap<String, btf::Id>,
+        name: String,
+        id: btf::Id,
+        elem: T,
+    ) {
+        let Some(&other_id) = origins.get(&name) else {
+            origins.insert(name.clone(), id);
+            elems.insert(name, elem);
+            return;
+        };
+        if other_id == id {
+            return;
+        }
+        let other = &elems[&name];
+        if *other == elem {
+            log::debug!("[{}] identical to [{}] `{}`", id, other_id, name);
+            self.identical += 1;
+            return;
+        }
+
+        self.conflicting += 1;
+        let replace = match (
+            is_alias(self.btf, &name, other_id),
+            is_alias(self.btf, &name, id),
+        ) {
+            (true, false) => true,
+            (false, true) => false,
+            _ => elem.completeness() > other.completeness(),
+        };
+        let (loser_id, loser) = if replace {
+            origins.insert(name.clone(), id);
+            (other_id, elems.insert(name.clone(), elem).unwrap())
+        } else {
+            (id, elem)
+        };
+        log::warn!(
+            target: diagnostics::NAMING_CONFLICTS,
+            code = "conflicting_type_definition",
+            type_name:% = name,
+            id:% = id,
+            other_id:% = other_id,
+            kept_id:% = origins[&name];
+            "Types {} and {} have conflicting definitions of `{}`, keeping {}",
+            other_id,
+            id,
+            name,
+            origins[&name]
+        );
+        if self.keep {
+            let name = names::duplicate_type(&name, loser_id, |name| elems.contains_key(name));
+            origins.insert(name.clone(), loser_id);
+            elems.insert(name, loser);
+        }
+    }
+
+    fn log(&self) {
+        if self.identical > 0 {
+            log::info!("Merged {} identical duplicate types", self.identical);
+        }
+        if self.conflicting > 0 {
+            log::warn!(
+                "{} types have conflicting definitions, {}",
+                self.conflicting,
+                if self.keep {
+                    "kept all of them"
+                } else {
+                    "dropped the less complete ones"
+                }
+            );
+        }
+    }
+}
+
+/// Returns true iff `name` is not the name of the type with `id` in the string
+/// table, i.e., a typedef alias or the placeholder of an anonymous type.
+fn is_alias(btf: &btf::Btf, name: &str, id: btf::Id) -> bool {
+    btf.get_strtab_entry_by_id(id).ok().as_deref() != Some(name)
+}
+
+/// Category of a type in the ISF file.
+///
+/// On name collisions across categories, earlier categories take precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Category {
+    User,
+    Enum,
+    Base,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::User => "user type",
+            Self::Enum => "enum",
+            Self::Base => "base type",
+        })
+    }
+}
+
+/// References to rename, per category, from the old to the new name.
+#[derive(Default)]
+struct Renames {
+    user: HashMap<String, String>,
+    enums: HashMap<String, String>,
+    base: HashMap<String, String>,
+}
+
+/// Selection of types by name, see `--include-types` and `--exclude-types`.
+struct TypeFilter<'a> {
+    include: Option<&'a Regex>,
+    exclude: Option<&'a Regex>,
+    /// Number of names that did not pass the filter.
+    dropped: usize,
+}
+
+impl<'a> TypeFilter<'a> {
+    fn new(include: Option<&'a Regex>, exclude: Option<&'a Regex>) -> Self {
+        Self {
+            include,
+            exclude,
+            dropped: 0,
+        }
+    }
+
+    /// Returns a filter that passes all names.
+    fn none() -> Self {
+        Self::new(None, None)
+    }
+
+    /// Returns true iff the type `name` passes the filter.
+    fn matches(&self, name: &str) -> bool {
+        self.include.is_none_or(|re| re.is_match(name))
+            && !self.exclude.is_some_and(|re| re.is_match(name))
+    }
+
+    /// Returns true iff the type `name` passes the filter, counting the names
+    /// that do not.
+    fn keep(&mut self, name: &str) -> bool {
+        let keep = self.matches(name);
+        if !keep {
+            log::trace!("`{}` dropped by the type filters", name);
+            self.dropped += 1;
+        }
+        keep
+    }
 }
 
 impl Isf {
+    /// Constructs the elements for the types with `ids`, under each of their
+    /// names.
+    ///
+    /// Elements that cannot be constructed are skipped and counted in
+    /// `skipped`. `origins` records the type that each element belongs to.
     fn map_from_ids<F, G, T>(
         ids: &BTreeSet<btf::Id>,
         id_to_names: F,
-        name_to_elem: G,
+        mut name_to_elem: G,
+        filter: &mut TypeFilter,
+        duplicates: &mut Duplicates,
+        origins: &mut HashMap<String, btf::Id>,
+        skipped: &mut usize,
     ) -> BTreeMap<String, T>
     where
         F: Fn(btf::Id) -> Vec<String>,
-        G: Fn(String, btf::Id) -> (String, T),
+        G: FnMut(String, btf::Id) -> Result<(String, T)>,
+        T: Definition,
     {
-        ids.iter()
-            .flat_map(|id| {
-                id_to_names(*id)
-                    .into_iter()
-                    .map(|name| name_to_elem(name, *id))
+        let mut elems = BTreeMap::new();
+        for id in ids.iter() {
+            for name in id_to_names(*id) {
+                if !filter.keep(&name) {
+                    continue;
+                }
+                match name_to_elem(name.clone(), *id) {
+                    Ok((name, elem)) => duplicates.insert(&mut elems, origins, name, *id, elem),
+                    Err(err) => {
+                        log::warn!(
+                            target: diagnostics::SKIPPED_TYPES,
+                            code = "skipped_type",
+                            id:% = id,
+                            type_name:% = name,
+                            error:% = format!("{:#}", err);
+                            "[{}] skipping `{}`: {:#}",
+                            id,
+                            name,
+                            err
+                        );
+                        *skipped += 1;
+                    }
+                }
+            }
+        }
+        elems
+    }
+
+    /// Splits the names of the user type or enum `id` into its primary name
+    /// and its aliases.
+    ///
+    /// The primary name is the tag name, or the first typedef of anonymous
+    /// types, so that they stay reachable by a meaningful name. The placeholder
+    /// of anonymous types is then the last alias.
+    fn split_names(ctx: &GenerationContext, id: btf::Id) -> (String, Vec<String>) {
+        let mut names = ctx.btf.get_names_by_id(id, Some(&ctx.typedefs)).unwrap();
+        if ctx.btf.get_strtab_entry_by_id(id).is_err() {
+            names.rotate_left(1);
+        }
+        let primary = names.remove(0);
+        (primary, names)
+    }
+
+    /// Returns the names that the user type or enum `id` is emitted under.
+    fn names_of(ctx: &GenerationContext, id: btf::Id) -> Vec<String> {
+        if ctx.typedef_aliases {
+            return ctx.btf.get_names_by_id(id, Some(&ctx.typedefs)).unwrap();
+        }
+        vec![Isf::split_names(ctx, id).0]
+    }
+
+    /// Returns the IDs of the user types traced with `--trace-type`, logging
+    /// the names that they have and are emitted under.
+    fn traced_ids(ctx: &GenerationContext) -> HashSet<btf::Id> {
+        let mut traced = HashSet::new();
+        let mut found = HashSet::new();
+        for id in ctx.user_ids.iter().chain(&ctx.fwd_ids) {
+            if ctx.trace_types.is_empty() {
+                break;
+            }
+            let names = ctx.btf.get_names_by_id(*id, Some(&ctx.typedefs)).unwrap();
+            let matching: Vec<_> = names
+                .iter()
+                .filter(|name| ctx.trace_types.contains(*name))
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+            found.extend(matching.into_iter().cloned());
+            log::info!(
+                target: TRACE_TARGET,
+                "[{}] {} named {:?}, emitted as {:?}",
+                id,
+                if ctx.fwd_ids.contains(id) {
+                    "declaration"
+                } else {
+                    "definition"
+                },
+                names,
+                Isf::names_of(ctx, *id)
+            );
+            traced.insert(*id);
+        }
+        for name in ctx.trace_types.difference(&found) {
+            log::info!(target: TRACE_TARGET, "No user type is named `{}`", name);
+        }
+        traced
+    }
+
+    /// Maps the aliases of the types with `ids` to their primary names.
+    fn aliases_of<'a>(
+        ctx: &GenerationContext,
+        ids: impl Iterator<Item = &'a btf::Id>,
+    ) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        for id in ids {
+            let (primary, names) = Isf::split_names(ctx, *id);
+            for name in names {
+                aliases.entry(name).or_insert_with(|| primary.clone());
+            }
+        }
+        aliases
+    }
+
+    /// Rewrites references to the aliases of user types and enums to their
+    /// primary names.
+    ///
+    /// Aliases that are the name of another type are kept.
+    fn rewrite_aliases(
+        &mut self,
+        mut user_aliases: HashMap<String, String>,
+        mut enum_aliases: HashMap<String, String>,
+    ) -> Result<()> {
+        user_aliases.retain(|alias, _| !self.user_types.contains_key(alias));
+        enum_aliases.retain(|alias, _| !self.enums.contains_key(alias));
+        let renames = Renames {
+            user: user_aliases,
+            enums: enum_aliases,
+            base: HashMap::new(),
+        };
+        let rewritten = self.rename_references(&renames);
+        log::debug!("Rewrote {} references to typedef aliases", rewritten);
+
+        // The dropped entries would have been copies of the primary ones.
+        if log::log_enabled!(log::Level::Info) {
+            let (mut entries, mut bytes) = (0, 0);
+            let entry_len = |alias: &String, len| alias.len() + len + 4;
+            for (alias, primary) in renames.user.iter() {
+                if let Some(user) = self.user_types.get(primary) {
+                    entries += 1;
+                    bytes += entry_len(alias, serde_json::to_vec(user)?.len());
+                }
+            }
+            for (alias, primary) in renames.enums.iter() {
+                if let Some(e) = self.enums.get(primary) {
+                    entries += 1;
+                    bytes += entry_len(alias, serde_json::to_vec(e)?.len());
+                }
+            }
+            log::info!(
+                "Dropped {} typedef aliases of user types and enums ({} bytes)",
+                entries,
+                bytes
+            );
+        }
+        Ok(())
+    }
+
+    /// Renames the references in fields and symbols according to `renames`.
+    ///
+    /// Returns the number of renamed references.
+    fn rename_references(&mut self, renames: &Renames) -> usize {
+        let mut renamed = 0;
+        let mut rename = |t: &mut v_types::TypeDescr| {
+            t.visit_mut(&mut |t| {
+                let (renames, name) = match t {
+                    v_types::TypeDescr::Struct { name } | v_types::TypeDescr::Union { name } => {
+                        (&renames.user, name)
+                    }
+                    v_types::TypeDescr::Enum { name } => (&renames.enums, name),
+                    v_types::TypeDescr::Base { name } => (&renames.base, name),
+                    _ => return,
+                };
+                if let Some(new) = renames.get(&**name) {
+                    *name = new.as_str().into();
+                    renamed += 1;
+                }
             })
-            .collect()
+        };
+        for user in self.user_types.values_mut() {
+            for (_, field) in user.fields.iter_mut() {
+                rename(&mut field.t);
+            }
+        }
+        for symbol in self.symbols.values_mut() {
+            rename(&mut symbol.t);
+        }
+        renamed
+    }
+
+    /// Resolves names that are used in more than one category.
+    ///
+    /// A real name beats a typedef alias, see [`is_alias`], otherwise the
+    /// precedence of [`Category`] applies. By default, all entries are kept.
+    /// With `--drop-colliding-aliases`, losing aliases are dropped if their
+    /// type has another name, and references to them are to be renamed to
+    /// that name.
+    fn resolve_collisions(
+        ctx: &GenerationContext,
+        user_types: &mut BTreeMap<String, v_types::User>,
+        enums: &mut BTreeMap<String, v_types::Enum>,
+        base_types: &mut BTreeMap<String, v_types::Base>,
+        origins: &HashMap<Category, HashMap<String, btf::Id>>,
+    ) -> Renames {
+        let mut categories: BTreeMap<String, Vec<Category>> = BTreeMap::new();
+        for (names, category) in [
+            (user_types.keys().collect::<Vec<_>>(), Category::User),
+            (enums.keys().collect(), Category::Enum),
+            (base_types.keys().collect(), Category::Base),
+        ] {
+            for name in names {
+                categories.entry(name.clone()).or_default().push(category);
+            }
+        }
+
+        let mut renames = Renames::default();
+        for (name, categories) in categories.into_iter().filter(|(_, c)| c.len() > 1) {
+            // Synthesized types have no ID and count as real.
+            let mut claims: Vec<_> = categories
+                .into_iter()
+                .map(|category| {
+                    let id = origins[&category].get(&name).copied();
+                    let alias = id.is_some_and(|id| is_alias(&ctx.btf, &name, id));
+                    (alias, category, id)
+                })
+                .collect();
+            claims.sort();
+            let describe = |(alias, category, id): (bool, Category, Option<btf::Id>)| {
+                let alias = if alias { " (typedef alias)" } else { "" };
+                match id {
+                    Some(id) => format!("{} {}{}", category, id, alias),
+                    None => format!("{}{}", category, alias),
+                }
+            };
+
+            for &(alias, category, id) in claims[1..].iter() {
+                let drop = ctx.drop_colliding_aliases && alias;
+                log::warn!(
+                    target: diagnostics::NAMING_CONFLICTS,
+                    code = "name_collision",
+                    name:% = name,
+                    kept:% = describe(claims[0]),
+                    other:% = describe((alias, category, id)),
+                    dropped = drop;
+                    "`{}` names {} and {}, {} the latter",
+                    name,
+                    describe(claims[0]),
+                    describe((alias, category, id)),
+                    if drop { "dropping" } else { "keeping" }
+                );
+                let Some(id) = id.filter(|_| drop) else {
+                    continue;
+                };
+                let (primary, aliases) = Isf::split_names(ctx, id);
+                let Some(other) = iter::once(primary)
+                    .chain(aliases)
+                    .find(|other| *other != name && origins[&category].get(other) == Some(&id))
+                else {
+                    log::warn!("Keeping `{}`, it is the only name of {}", name, id);
+                    continue;
+                };
+                let renames = match category {
+                    Category::User => {
+                        user_types.remove(&name);
+                        &mut renames.user
+                    }
+                    Category::Enum => {
+                        enums.remove(&name);
+                        &mut renames.enums
+                    }
+                    Category::Base => {
+                        base_types.remove(&name);
+                        &mut renames.base
+                    }
+                };
+                renames.insert(name.clone(), other);
+            }
+        }
+        renames
+    }
+
+    /// Adds empty user types for forward declarations without a definition.
+    ///
+    /// Stubs never replace a definition of the same name, and are subject to
+    /// the type filters.
+    fn add_fwd_stubs(
+        user_types: &mut BTreeMap<String, v_types::User>,
+        ctx: &GenerationContext,
+        filter: &mut TypeFilter,
+    ) {
+        let mut nr_stubs = 0;
+        for id in ctx.fwd_ids.iter() {
+            let t = ctx.btf.get_type_by_id(*id).unwrap();
+            let kind = if t.is_fwd_struct() {
+                v_types::UserKind::Struct
+            } else {
+                v_types::UserKind::Union
+            };
+            for name in Isf::names_of(ctx, *id) {
+                if user_types.contains_key(&name) || !filter.keep(&name) {
+                    continue;
+                }
+                log::debug!("[{}] adding stub for `{} {}`", id, kind.as_str(), name);
+                user_types.insert(name, v_types::User::new_stub(kind));
+                nr_stubs += 1;
+            }
+        }
+        log::info!("Added {} stubs for fwd declared types", nr_stubs);
     }
 }
 
 impl TryFrom<GenerationContext> for Isf {
-    type Error = Error;
+    type Error = error::Error;
 
     /// Try to construct ISF file from gathered information.
-    fn try_from(mut ctx: GenerationContext) -> Result<Isf> {
+    fn try_from(mut ctx: GenerationContext) -> error::Result<Isf> {
+        let mut duplicates = Duplicates::new(&ctx.btf, ctx.keep_duplicate_types);
+        let mut origins: HashMap<Category, HashMap<String, btf::Id>> = HashMap::new();
+        let mut skipped = 0;
+        let skipped_members = RefCell::default();
+        let invalid_bitfields = RefCell::default();
+        let mut filter = TypeFilter::new(ctx.include_types.as_ref(), ctx.exclude_types.as_ref());
+        let base_from_id = |name, id| {
+            Ok((
+                name,
+                v_types::Base::try_from(v_types::BaseConstructionCtx {
+                    btf: &ctx.btf,
+                    tx: btf::TypeEx {
+                        t: ctx.btf.get_type_by_id(id).unwrap(),
+                        id,
+                    },
+                    char_signed: ctx.char_signed,
+                })?,
+            ))
+        };
+        // Base types are only filtered once the enums that need them are known.
+        // Standard spellings and typedef aliases are added after the enums, so
+        // that enums are based on the C names of the base types in BTF.
+        let start = Instant::now();
         let mut base_types = Isf::map_from_ids(
             &ctx.basic_ids,
             |id| ctx.btf.get_names_by_id(id, None).unwrap(),
+            base_from_id,
+            &mut TypeFilter::none(),
+            &mut duplicates,
+            origins.entry(Category::Base).or_default(),
+            &mut skipped,
+        );
+        ctx.timings.record("base-types", start.elapsed());
+
+        let start = Instant::now();
+        let (user_aliases, enum_aliases) = if ctx.typedef_aliases {
+            Default::default()
+        } else {
+            (
+                Isf::aliases_of(&ctx, ctx.user_ids.iter().chain(&ctx.fwd_ids)),
+                Isf::aliases_of(&ctx, ctx.enum_ids.iter()),
+            )
+        };
+        let traced = Isf::traced_ids(&ctx);
+        ctx.progress
+            .start(Phase::UserTypes, Some(ctx.user_ids.len()));
+        let done = Cell::new(0);
+        let mut user_types = Isf::map_from_ids(
+            &ctx.user_ids,
+            |id| {
+                ctx.progress.advance(Phase::UserTypes, done.get());
+                done.set(done.get() + 1);
+                Isf::names_of(&ctx, id)
+            },
             |name, id| {
-                (
+                Ok((
                     name,
-                    v_types::Base::from(v_types::BaseConstructionCtx {
-                        btf: &ctx.btf,
-                        tx: btf::TypeEx {
-                            t: ctx.btf.get_type_by_id(id).unwrap(),
-                            id,
+                    v_types::User::try_from(v_types::UserConstructionCtx {
+                        basic_ctx: v_types::BaseConstructionCtx {
+                            btf: &ctx.btf,
+                            tx: btf::TypeEx {
+                                t: ctx.btf.get_type_by_id(id).unwrap(),
+                                id,
+                            },
+                            char_signed: ctx.char_signed,
                         },
-                    }),
-                )
+                        typedefs: &ctx.typedefs,
+                        rich_functions: ctx.rich_functions,
+                        skipped_members: &skipped_members,
+                        invalid_bitfields: &invalid_bitfields,
+                        traced: &traced,
+                    })?,
+                ))
             },
+            &mut filter,
+            &mut duplicates,
+            origins.entry(Category::User).or_default(),
+            &mut skipped,
         );
-        quirks::fixup_base(&mut base_types, &ctx.btf.endian);
+        ctx.progress.finish(Phase::UserTypes, done.get());
+        if ctx.emit_fwd_stubs {
+            Isf::add_fwd_stubs(&mut user_types, &ctx, &mut filter);
+        }
+        ctx.timings.record("user-types", start.elapsed());
 
-        Ok(Isf {
-            metadata: ctx.mbuilder.take().unwrap().into(),
-            user_types: Isf::map_from_ids(
-                &ctx.user_ids,
-                |id| ctx.btf.get_names_by_id(id, Some(&ctx.typedefs)).unwrap(),
-                |name, id| {
-                    (
-                        name,
-                        v_types::User::from(v_types::UserConstructionCtx {
-                            basic_ctx: v_types::BaseConstructionCtx {
-                                btf: &ctx.btf,
-                                tx: btf::TypeEx {
-                                    t: ctx.btf.get_type_by_id(id).unwrap(),
-                                    id,
-                                },
-                            },
-                            typedefs: &ctx.typedefs,
-                        }),
-                    )
-                },
-            ),
-            enums: Isf::map_from_ids(
-                &ctx.enum_ids,
-                |id| ctx.btf.get_names_by_id(id, Some(&ctx.typedefs)).unwrap(),
-                |name, id| {
-                    (
-                        name,
-                        v_types::Enum::from(v_types::EnumConstructionCtx {
-                            basic_ctx: v_types::BaseConstructionCtx {
-                                btf: &ctx.btf,
-                                tx: btf::TypeEx {
-                                    t: ctx.btf.get_type_by_id(id).unwrap(),
-                                    id,
-                                },
+        let start = Instant::now();
+        let mut enums = Isf::map_from_ids(
+            &ctx.enum_ids,
+            |id| Isf::names_of(&ctx, id),
+            |name, id| {
+                Ok((
+                    name,
+                    v_types::Enum::try_from(v_types::EnumConstructionCtx {
+                        basic_ctx: v_types::BaseConstructionCtx {
+                            btf: &ctx.btf,
+                            tx: btf::TypeEx {
+                                t: ctx.btf.get_type_by_id(id).unwrap(),
+                                id,
                             },
-                            base_types: &base_types,
-                        }),
-                    )
-                },
-            ),
+                            char_signed: ctx.char_signed,
+                        },
+                        base_types: &mut base_types,
+                    })?,
+                ))
+            },
+            &mut filter,
+            &mut duplicates,
+            origins.entry(Category::Enum).or_default(),
+            &mut skipped,
+        );
+        ctx.timings.record("enums", start.elapsed());
+
+        let start = Instant::now();
+        let mut alias_origins = HashMap::new();
+        let aliases = Isf::map_from_ids(
+            &ctx.basic_ids,
+            |id| {
+                let mut names = ctx.btf.get_names_by_id(id, Some(&ctx.typedefs)).unwrap();
+                // The first name is the C name of the base type.
+                names.remove(0);
+                names
+            },
+            base_from_id,
+            &mut TypeFilter::none(),
+            &mut duplicates,
+            &mut alias_origins,
+            &mut skipped,
+        );
+        for (name, base) in aliases {
+            match base_types.entry(name) {
+                Entry::Vacant(ent) => {
+                    origins
+                        .entry(Category::Base)
+                        .or_default()
+                        .insert(ent.key().clone(), alias_origins[ent.key()]);
+                    ent.insert(base);
+                }
+                Entry::Occupied(ent) => {
+                    log::debug!("Base type alias `{}` shadowed by a base type", ent.key())
+                }
+            }
+        }
+        ctx.timings.record("base-types", start.elapsed());
+        let renames =
+            Isf::resolve_collisions(&ctx, &mut user_types, &mut enums, &mut base_types, &origins);
+        duplicates.log();
+        if skipped > 0 {
+            log::error!("Skipped {} types that could not be constructed", skipped);
+        }
+        if !skipped_members.borrow().is_empty() {
+            log::error!(
+                "Skipped {} members of user types that could not be constructed",
+                skipped_members.borrow().len()
+            );
+        }
+        let invalid_bitfields = invalid_bitfields.into_inner().len();
+        if invalid_bitfields > 0 {
+            log::error!(
+                "Fixed {} bitfields of user types that exceed their type",
+                invalid_bitfields
+            );
+        }
+
+        let mut isf = Isf {
+            metadata: ctx.mbuilder.take().unwrap().into(),
+            user_types,
+            enums,
             base_types,
-            symbols: ctx
-                .symbols
-                .into_iter()
-                .map(|(name, sym)| (name, sym.into()))
-                .collect(),
+            symbols: BTreeMap::new(),
+            skipped_types: skipped,
+            invalid_bitfields,
+            provenance: None,
+            type_sources: None,
+            quirks: ctx.quirks.clone(),
+            target: Some((ctx.address_width, (&ctx.btf.endian).into())),
+            timings: Timings::default(),
+        };
+        // The base types added by the quirks are subject to the type filters.
+        quirks::apply(&mut isf, quirks::Stage::Construction);
+
+        let nr_base_types = isf.base_types.len();
+        let enum_bases: HashSet<&String> = isf.enums.values().map(v_types::Enum::base).collect();
+        // `void` is the fallback type of symbols, `pointer` is required by
+        // Volatility.
+        isf.base_types.retain(|name, _| {
+            name == "void" || name == "pointer" || enum_bases.contains(name) || filter.matches(name)
+        });
+        if filter.include.is_some() || filter.exclude.is_some() {
+            log::info!(
+                "Dropped {} user types and enums and {} base types by the type filters",
+                filter.dropped,
+                nr_base_types - isf.base_types.len()
+            );
+        }
+
+        let start = Instant::now();
+        let type_sources = ctx.symbols.type_sources();
+        let mut symbols = v_symbols::from_symbols(
+            mem::take(&mut ctx.symbols),
+            ctx.address_width,
+            ctx.address_overflow,
+        )?;
+        if !ctx.format_version.has_linkage() {
+            symbols
+                .values_mut()
+                .for_each(v_symbols::Symbol::clear_linkage);
+        }
+        isf.symbols = symbols;
+        isf.type_sources = Some(type_sources);
+        ctx.timings.record("symbol-entries", start.elapsed());
+
+        let start = Instant::now();
+        isf.rename_references(&renames);
+        if !ctx.typedef_aliases {
+            isf.rewrite_aliases(user_aliases, enum_aliases)?;
+        }
+        if let Some(patch) = ctx.patch.take() {
+            // Patched entries do not come from the BTF.
+            for (category, names) in [
+                (Category::User, patch.user_types.keys().collect::<Vec<_>>()),
+                (Category::Enum, patch.enums.keys().collect()),
+                (Category::Base, patch.base_types.keys().collect()),
+            ] {
+                let origins = origins.entry(category).or_default();
+                names.into_iter().for_each(|name| _ = origins.remove(name));
+            }
+            isf.patch(patch);
+        }
+        if let Some(roots) = &ctx.prune_roots {
+            isf.prune(roots)?;
+        }
+        if ctx.exclude_anon_types {
+            isf.exclude_anon_types()?;
+        }
+        if ctx.annotate_btf_ids {
+            isf.provenance = Some(Provenance::new(&ctx, &isf, &origins));
+        }
+        ctx.timings.record("rewrite", start.elapsed());
+        isf.timings = mem::take(&mut ctx.timings);
+        Ok(isf)
+    }
+}
+
+/// Replaces, adds, or removes the `patched` entries of `entries`, which are of
+/// `kind`.
+fn merge_entries<T>(kind: &str, entries: &mut BTreeMap<String, T>, patched: patch::Entries<T>) {
+    for (name, entry) in patched {
+        match entry {
+            Some(entry) => match entries.insert(name.clone(), entry) {
+                Some(_) => log::debug!("Patch replaces {} {}", kind, name),
+                None => log::debug!("Patch adds {} {}", kind, name),
+            },
+            None => match entries.remove(&name) {
+                Some(_) => log::debug!("Patch deletes {} {}", kind, name),
+                None => log::warn!("Patch deletes {} {}, which does not exist", kind, name),
+            },
+        }
+    }
+}
+
+/// Returns what `t` is, for messages, e.g., `struct file` or `pointer`.
+fn describe(t: &v_types::TypeDescr) -> String {
+    match t {
+        v_types::TypeDescr::Array { .. } => String::from("array"),
+        v_types::TypeDescr::Pointer { .. } => String::from("pointer"),
+        v_types::TypeDescr::Bitfield { .. } => String::from("bitfield"),
+        v_types::TypeDescr::Function { .. } => String::from("function"),
+        v_types::TypeDescr::Base { name } => format!("base type {}", name),
+        _ => format!("{} {}", t.kind().unwrap(), t.name().unwrap()),
+    }
+}
+
+/// Names of the types that are reachable from the symbols and root types of an
+/// ISF file.
+#[derive(Default)]
+struct Reachable {
+    base_types: BTreeSet<String>,
+    enums: BTreeSet<String>,
+    user_types: BTreeSet<String>,
+}
+
+impl Reachable {
+    fn len(&self) -> usize {
+        self.base_types.len() + self.enums.len() + self.user_types.len()
+    }
+
+    /// Marks `t` as reachable, returns true iff it was not before.
+    fn insert(&mut self, t: &graph::TypeRef) -> bool {
+        let names = match t.kind {
+            graph::Kind::Struct | graph::Kind::Union => &mut self.user_types,
+            graph::Kind::Enum => &mut self.enums,
+            graph::Kind::Base => &mut self.base_types,
+        };
+        names.insert(t.name.clone())
+    }
+}
+
+/// Writer that only counts the bytes written to it.
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Type that is referenced by a field, but not defined in the ISF file.
+#[derive(Serialize)]
+pub struct UndefinedType {
+    /// `struct`, `union`, `enum`, or empty for base types.
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl UndefinedType {
+    /// Returns the type that `t` resolves to, which is undefined.
+    fn of(t: &v_types::TypeDescr) -> Self {
+        let rt = t.resolve();
+        Self {
+            kind: rt.kind().unwrap(),
+            name: rt.name().unwrap().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UndefinedType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.name)
+    }
+}
+
+/// Field of a user type that references an undefined type.
+#[derive(Serialize)]
+pub struct UndefinedField {
+    pub name: String,
+    pub undefined: UndefinedType,
+}
+
+/// User type with fields that reference undefined types.
+#[derive(Serialize)]
+pub struct UserTypeProblem {
+    pub kind: &'static str,
+    pub name: String,
+    pub fields: Vec<UndefinedField>,
+}
+
+/// Numbers of problems found by the checks of [`Isf::check_report`].
+#[derive(Serialize)]
+pub struct CheckSummary {
+    /// User types with fields that reference undefined types.
+    pub user_types: usize,
+    /// Unique undefined types.
+    pub undefined_types: usize,
+    /// Fields that reference undefined types.
+    pub fields: usize,
+    /// Enums whose base type is undefined.
+    pub enums: usize,
+    /// Symbols whose type is undefined.
+    pub symbols: usize,
+    /// Malformed type descriptors.
+    pub descriptors: usize,
+}
+
+/// Enum whose base type is undefined.
+#[derive(Serialize)]
+pub struct EnumProblem {
+    pub name: String,
+    pub base: String,
+}
+
+/// Symbol whose type is undefined.
+#[derive(Serialize)]
+pub struct SymbolProblem {
+    pub name: String,
+    pub undefined: UndefinedType,
+}
+
+/// Malformed type descriptor of a field or symbol.
+#[derive(Serialize)]
+pub struct DescriptorProblem {
+    /// Field or symbol of the descriptor, e.g., `struct file::f_mode` or
+    /// `symbol jiffies`.
+    pub location: String,
+    /// What is malformed.
+    pub details: String,
+}
+
+impl fmt::Display for DescriptorProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.location, self.details)
+    }
+}
+
+/// Results of checking that all types referenced by user types, enums, and
+/// symbols are defined, and that the type descriptors are well-formed.
+#[derive(Serialize)]
+pub struct CheckReport {
+    pub summary: CheckSummary,
+    pub user_types: Vec<UserTypeProblem>,
+    pub enums: Vec<EnumProblem>,
+    pub symbols: Vec<SymbolProblem>,
+    pub descriptors: Vec<DescriptorProblem>,
+}
+
+impl CheckReport {
+    /// Logs the problems and returns an error for each check that failed.
+    pub fn check(&self) -> Vec<error::Error> {
+        [
+            self.check_user_types(),
+            self.check_enums(),
+            self.check_symbols(),
+            self.check_descriptors(),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+    }
+
+    /// Logs the enums with undefined base types and fails if there are any.
+    fn check_enums(&self) -> error::Result<()> {
+        if self.enums.is_empty() {
+            log::debug!("The base types of all enums are present");
+            return Ok(());
+        }
+        for e in self.enums.iter() {
+            log::error!("[enum {}] has undefined base type `{}`", e.name, e.base);
+        }
+        Err(error::Error::IsfVerification {
+            kind: VerificationKind::Enums,
+            details: format!("{} enums have undefined base types", self.enums.len()),
+        })
+    }
+
+    /// Fails if symbols have undefined types, which are logged when they are
+    /// set to void.
+    fn check_symbols(&self) -> error::Result<()> {
+        if self.symbols.is_empty() {
+            return Ok(());
+        }
+        Err(error::Error::IsfVerification {
+            kind: VerificationKind::SymbolTypes,
+            details: format!("{} symbols reference undefined types", self.symbols.len()),
+        })
+    }
+
+    /// Logs the malformed type descriptors and fails if there are any.
+    fn check_descriptors(&self) -> error::Result<()> {
+        if self.descriptors.is_empty() {
+            log::debug!("All type descriptors are well-formed");
+            return Ok(());
+        }
+        // Unlike undefined types these are never expected, so they are shown
+        // by default.
+        for problem in self.descriptors.iter() {
+            log::error!("{}", problem);
+        }
+        Err(error::Error::IsfVerification {
+            kind: VerificationKind::Descriptors,
+            details: format!("{} type descriptors are malformed", self.descriptors.len()),
         })
     }
+
+    /// Logs the fields of user types that reference undefined types and fails
+    /// if there are any.
+    fn check_user_types(&self) -> error::Result<()> {
+        if self.user_types.is_empty() {
+            log::debug!("All types referenced by user types are present");
+            return Ok(());
+        }
+        for ut in self.user_types.iter() {
+            for field in ut.fields.iter() {
+                log::warn!(
+                    target: diagnostics::UNDEFINED_FIELDS,
+                    code = "undefined_field_type",
+                    kind:% = ut.kind,
+                    type_name:% = ut.name,
+                    field:% = field.name,
+                    field_type:% = field.undefined;
+                    "[{} {}::{}] has undefined type `{}`",
+                    ut.kind,
+                    ut.name,
+                    field.name,
+                    field.undefined
+                );
+            }
+        }
+        log::error!(
+            "{} user types have fields that reference undefined types, {} unique types undefined, {} unique fields affected",
+            self.summary.user_types,
+            self.summary.undefined_types,
+            self.summary.fields,
+        );
+        Err(error::Error::IsfVerification {
+            kind: VerificationKind::UserTypes,
+            details: format!("{} fields reference undefined types", self.summary.fields),
+        })
+    }
+
+    /// Writes the report as JSON to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Unable to write {}", path.display()))
+    }
+}
+
+/// Symbol that has the `void` fallback type.
+#[derive(Serialize)]
+pub struct UntypedSymbol {
+    pub name: String,
+    /// `no_type` if no source has a type for the symbol, `undefined_type` if
+    /// its type is undefined.
+    pub reason: &'static str,
+    /// Undefined type of the symbol.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub undefined: Option<String>,
+}
+
+/// Numbers of symbols with and without types.
+#[derive(Serialize)]
+pub struct UntypedSummary {
+    pub symbols: usize,
+    pub typed: usize,
+    pub untyped: usize,
+    /// Untyped symbols that no source has a type for.
+    pub no_type: usize,
+    /// Untyped symbols whose type is undefined.
+    pub undefined_type: usize,
+}
+
+/// Symbols that have the `void` fallback type, see `--untyped-report`.
+#[derive(Serialize)]
+pub struct UntypedReport {
+    pub summary: UntypedSummary,
+    pub sources: symbols::TypeSources,
+    /// Untyped symbols by their kind, e.g., `T`.
+    pub untyped: BTreeMap<String, Vec<UntypedSymbol>>,
+}
+
+impl UntypedReport {
+    /// Writes the report as JSON to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Unable to write {}", path.display()))
+    }
+}
+
+/// Field that does not fit into its user type.
+#[derive(Serialize)]
+pub struct LayoutProblem {
+    pub kind: &'static str,
+    pub name: String,
+    pub field: String,
+    /// What does not fit, with the numbers involved.
+    pub details: String,
+}
+
+impl fmt::Display for LayoutProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{} {}::{}] {}",
+            self.kind, self.name, self.field, self.details
+        )
+    }
+}
+
+/// Number of elements in an ISF file.
+#[derive(Serialize)]
+pub struct IsfStats {
+    pub base_types: usize,
+    pub enums: usize,
+    pub user_types: usize,
+    pub symbols: usize,
+}
+
+impl fmt::Display for Isf {
+    /// Formats the ISF file as JSON.
+    ///
+    /// Serialization errors become [`fmt::Error`], on which `to_string`
+    /// panics. [`Isf::to_json_string`] returns them instead.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        f.write_str(&json)
+    }
 }
 
 impl Isf {
+    /// Reads an ISF file from `reader`.
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(reader))?)
+    }
+
+    /// Reads the ISF file at `path`, which may be gzip or xz compressed.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        serde_json::from_value(schema::read_isf(path)?)
+            .with_context(|| format!("{} is not a valid ISF file", path.display()))
+    }
+
+    /// Returns the metadata of the ISF file.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the user types by name, in the order of their names.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use btf2json::v_types::UserKind;
+    /// use btf2json::GenerationOptions;
+    ///
+    /// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_le.btf");
+    /// let isf = GenerationOptions::new()
+    ///     .btf_file(path)
+    ///     .types_only(true)
+    ///     .generate()?;
+    ///
+    /// let structs = isf
+    ///     .user_types()
+    ///     .filter(|(_, user)| user.kind() == UserKind::Struct)
+    ///     .count();
+    /// assert_eq!(structs, isf.user_types().len());
+    ///
+    /// let task = isf.get_user_type("task").unwrap();
+    /// for (name, field) in task.fields() {
+    ///     println!("{} at offset {}", name, field.offset());
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn user_types(&self) -> impl ExactSizeIterator<Item = (&str, &v_types::User)> {
+        self.user_types.iter().map(|(name, t)| (name.as_str(), t))
+    }
+
+    /// Returns the user type named `name`.
+    pub fn get_user_type(&self, name: &str) -> Option<&v_types::User> {
+        self.user_types.get(name)
+    }
+
+    /// Returns the enums by name, in the order of their names.
+    pub fn enums(&self) -> impl ExactSizeIterator<Item = (&str, &v_types::Enum)> {
+        self.enums.iter().map(|(name, t)| (name.as_str(), t))
+    }
+
+    /// Returns the enum named `name`.
+    pub fn get_enum(&self, name: &str) -> Option<&v_types::Enum> {
+        self.enums.get(name)
+    }
+
+    /// Returns the base types by name, in the order of their names.
+    pub fn base_types(&self) -> impl ExactSizeIterator<Item = (&str, &v_types::Base)> {
+        self.base_types.iter().map(|(name, t)| (name.as_str(), t))
+    }
+
+    /// Returns the base type named `name`.
+    pub fn get_base_type(&self, name: &str) -> Option<&v_types::Base> {
+        self.base_types.get(name)
+    }
+
+    /// Returns the symbols by name, in the order of their names.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use btf2json::cli::Architecture;
+    /// use btf2json::v_types::TypeDescr;
+    /// use btf2json::GenerationOptions;
+    ///
+    /// # let vars = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars");
+    /// let isf = GenerationOptions::new()
+    ///     .btf_file(format!("{}/vars.btf", vars))
+    ///     .map_file(format!("{}/System.map", vars))
+    ///     .banner("Linux version 6.18.0")
+    ///     .arch(Architecture::X86_64)
+    ///     .generate()?;
+    ///
+    /// let jiffies = isf.get_symbol("jiffies").unwrap();
+    /// assert_eq!(jiffies.address(), 0xffffffff82000000);
+    /// assert!(matches!(
+    ///     jiffies.type_descr(),
+    ///     TypeDescr::Base { name } if &**name == "long long unsigned int"
+    /// ));
+    /// assert!(isf.symbols().len() > 1);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn symbols(&self) -> impl ExactSizeIterator<Item = (&str, &v_symbols::Symbol)> {
+        self.symbols.iter().map(|(name, sym)| (name.as_str(), sym))
+    }
+
+    /// Returns the symbol named `name`.
+    pub fn get_symbol(&self, name: &str) -> Option<&v_symbols::Symbol> {
+        self.symbols.get(name)
+    }
+
+    /// Returns the BTF types that the entries come from, if they were
+    /// recorded, see `--annotate-btf-ids`.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Returns the durations of the phases of the generation, which the
+    /// caller can add to, e.g., for the verification and writing.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Returns the number of types that were skipped during the generation
+    /// as they could not be constructed.
+    pub fn skipped_types(&self) -> usize {
+        self.skipped_types
+    }
+
+    /// Returns the number of elements in the ISF file.
+    pub fn stats(&self) -> IsfStats {
+        IsfStats {
+            base_types: self.base_types.len(),
+            enums: self.enums.len(),
+            user_types: self.user_types.len(),
+            symbols: self.symbols.len(),
+        }
+    }
+
+    /// Returns the number of elements in the ISF file as text, e.g., for
+    /// logging.
+    pub fn summary(&self) -> String {
+        let stats = self.stats();
+        format!(
+            "base {}, enum {}, user {}, symbol {}",
+            stats.base_types, stats.enums, stats.user_types, stats.symbols
+        )
+    }
+
+    /// Writes the ISF file as JSON to `writer`.
+    ///
+    /// The JSON is also returned by [`Isf::to_vec`] and
+    /// [`Isf::to_json_string`], and formatted with [`Display`](fmt::Display).
+    pub fn to_writer(&self, writer: impl Write) -> Result<()> {
+        Ok(serde_json::to_writer(writer, self)?)
+    }
+
+    /// Returns the ISF file as JSON.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Returns the ISF file as a JSON string.
+    pub fn to_json_string(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
     /// Writes a valid ISF file to stdout.
-    pub fn dump_stdout(&self) {
-        log::debug!(
-            "ISF elements: base {}, enum {}, user {}, symbol {}",
-            &self.base_types.len(),
-            &self.enums.len(),
-            &self.user_types.len(),
-            &self.symbols.len()
+    ///
+    /// Errors are returned, e.g., if stdout is a pipe whose reader is gone.
+    pub fn dump_stdout(&self) -> Result<()> {
+        self.dump_to(BufWriter::new(io::stdout().lock()))
+    }
+
+    /// Writes a valid ISF file, followed by a newline, to `writer`.
+    pub fn dump_to(&self, mut writer: impl Write) -> Result<()> {
+        self.to_writer(&mut writer)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Verifies that the ISF file matches the schema of its format version.
+    pub fn validate(&self) -> Result<()> {
+        schema::validate(&serde_json::to_value(self)?)
+    }
+
+    /// Returns the number of types in the ISF file.
+    fn nr_types(&self) -> usize {
+        self.base_types.len() + self.enums.len() + self.user_types.len()
+    }
+
+    /// Returns the size of the serialized ISF file in bytes.
+    fn serialized_len(&self) -> Result<usize> {
+        let mut counter = ByteCounter::default();
+        serde_json::to_writer(&mut counter, self)?;
+        Ok(counter.0)
+    }
+
+    /// Returns the types that are reachable from the symbols and the types
+    /// named in `roots`, see [`graph::dependencies`].
+    fn reachable(&self, roots: &[String]) -> Reachable {
+        let mut pending = Vec::new();
+        for root in roots {
+            match graph::TypeRef::lookup(self, root) {
+                Some(t) => pending.push(t),
+                None => log::warn!("Root type `{}` is not defined", root),
+            }
+        }
+        self.reachable_from(pending)
+    }
+
+    /// Returns the types that are reachable from the symbols and the types in
+    /// `pending`.
+    fn reachable_from(&self, mut pending: Vec<graph::TypeRef>) -> Reachable {
+        let mut reachable = Reachable::default();
+
+        // Volatility requires the base type of pointers.
+        reachable.base_types.insert(String::from("pointer"));
+        for sym in self.symbols.values() {
+            graph::referenced_types(&sym.t, &mut pending);
+        }
+        while let Some(t) = pending.pop() {
+            if reachable.insert(&t) {
+                pending.extend(graph::dependencies(self, &t).into_iter().map(|(_, t)| t));
+            }
+        }
+        reachable
+    }
+
+    /// Merges the overlay `patch` into the ISF file.
+    ///
+    /// Entries of the overlay replace the ones of the same name or are added,
+    /// deleted entries are removed.
+    pub fn patch(&mut self, patch: Patch) {
+        log::info!("Patching {} entries of the ISF file", patch.len());
+        merge_entries("user type", &mut self.user_types, patch.user_types);
+        merge_entries("enum", &mut self.enums, patch.enums);
+        merge_entries("base type", &mut self.base_types, patch.base_types);
+        merge_entries("symbol", &mut self.symbols, patch.symbols);
+    }
+
+    /// Drops all types that are neither reachable from the symbols nor from
+    /// the types named in `roots`.
+    ///
+    /// Types are reached through the type descriptors of symbols and fields of
+    /// user types, including the subtypes of arrays, pointers, bitfields, and
+    /// functions, and through the base types of enums. The `pointer` base type
+    /// is always kept.
+    pub fn prune(&mut self, roots: &[String]) -> Result<()> {
+        let measure = log::log_enabled!(log::Level::Info);
+        let (nr_before, len_before) = (
+            self.nr_types(),
+            if measure { self.serialized_len()? } else { 0 },
         );
-        println!("{}", serde_json::to_string(&self).unwrap());
+
+        let reachable = self.reachable(roots);
+        log::debug!("{} types are reachable", reachable.len());
+        self.base_types
+            .retain(|name, _| reachable.base_types.contains(name));
+        self.enums.retain(|name, _| reachable.enums.contains(name));
+        self.user_types
+            .retain(|name, _| reachable.user_types.contains(name));
+
+        if measure {
+            log::info!(
+                "Pruned {} of {} types, ISF size {} -> {} bytes",
+                nr_before - self.nr_types(),
+                nr_before,
+                len_before,
+                self.serialized_len()?
+            );
+        }
+        Ok(())
     }
 
-    /// Verifies that all types referenced by fields of user types are defined.
-    pub fn check_user_types(&self) -> Result<()> {
-        let mut problematic_types: HashMap<String, Vec<&String>> = HashMap::new();
-        let mut undefined_types: HashSet<String> = HashSet::new();
+    /// Drops the anonymous structs and unions that no symbol and no field of
+    /// another type references, e.g., of anonymous members of dropped types.
+    ///
+    /// The named types and all enums, including anonymous ones, are kept, so
+    /// that every type that is referenced stays defined. Anonymous types are
+    /// recognized by their placeholder names.
+    pub fn exclude_anon_types(&mut self) -> Result<()> {
+        let measure = log::log_enabled!(log::Level::Info);
+        let (nr_before, len_before) = (
+            self.user_types.len(),
+            if measure { self.serialized_len()? } else { 0 },
+        );
+
+        let named = self
+            .user_types
+            .keys()
+            .filter(|name| !names::is_unnamed_user_type(name))
+            .filter_map(|name| graph::TypeRef::lookup(self, name));
+        let enums = self.enums.keys().map(|name| graph::TypeRef {
+            kind: graph::Kind::Enum,
+            name: name.clone(),
+        });
+        let reachable = self.reachable_from(named.chain(enums).collect());
+        self.user_types.retain(|name, _| {
+            !names::is_unnamed_user_type(name) || reachable.user_types.contains(name)
+        });
+
+        if measure {
+            log::info!(
+                "Excluded {} unreferenced anonymous types of {} user types, ISF size {} -> {} bytes",
+                nr_before - self.user_types.len(),
+                nr_before,
+                len_before,
+                self.serialized_len()?
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the problems found by checking that the types referenced by
+    /// user types, enums, and symbols are defined, and that the type
+    /// descriptors are well-formed.
+    ///
+    /// The symbols are checked as they are, i.e., before
+    /// [`Isf::fix_symbol_types`] sets the ones of undefined types to void.
+    pub fn check_report(&self) -> CheckReport {
+        let (user_types, undefined_types) = self.user_type_problems();
+        let enums = self.enum_problems();
+        let symbols = self.symbol_problems();
+        let descriptors = self.descriptor_problems();
+        CheckReport {
+            summary: CheckSummary {
+                user_types: user_types.len(),
+                undefined_types,
+                fields: user_types.iter().map(|ut| ut.fields.len()).sum(),
+                enums: enums.len(),
+                symbols: symbols.len(),
+                descriptors: descriptors.len(),
+            },
+            user_types,
+            enums,
+            symbols,
+            descriptors,
+        }
+    }
+
+    /// Returns the user types with fields that reference undefined types, and
+    /// the number of unique undefined types.
+    fn user_type_problems(&self) -> (Vec<UserTypeProblem>, usize) {
+        let mut user_types = Vec::new();
+        let mut undefined_types = BTreeSet::new();
 
         for (name, ut) in self.user_types.iter() {
-            let problematic_fields: Vec<&String> = ut
+            let fields: Vec<UndefinedField> = ut
                 .fields
                 .iter()
-                .filter_map(|(field_name, field)| {
-                    if self.is_defined(&field.t) {
-                        return None;
+                .filter(|(_, field)| !self.is_defined(&field.t))
+                .map(|(field_name, field)| {
+                    let undefined = UndefinedType::of(&field.t);
+                    undefined_types.insert(undefined.to_string());
+                    UndefinedField {
+                        name: field_name.to_string(),
+                        undefined,
                     }
-                    let rt = field.t.resolve();
-                    let field_type_kind = rt.kind().unwrap();
-                    let field_type_name = rt.name().unwrap();
-                    log::warn!(
-                        "[{} {}::{}] has undefined type `{} {}`",
-                        ut.kind.as_str(),
-                        name,
-                        field_name,
-                        field_type_kind,
-                        field_type_name,
-                    );
-                    undefined_types.insert(format!("{} {}", field_type_kind, field_type_name));
-                    Some(field_name)
                 })
                 .collect();
-            if !problematic_fields.is_empty() {
-                problematic_types.insert(
-                    format!("{} {}", ut.kind.as_str(), name.as_str()),
-                    problematic_fields,
-                );
+            if !fields.is_empty() {
+                user_types.push(UserTypeProblem {
+                    kind: ut.kind.as_str(),
+                    name: name.clone(),
+                    fields,
+                });
             }
         }
+        (user_types, undefined_types.len())
+    }
 
-        if problematic_types.is_empty() {
-            log::debug!("All types referenced by user types are present");
-            Ok(())
-        } else {
-            log::error!(
-                "{} user types have fields that reference undefined types, {} unique types undefined, {} unique fields affected",
-                problematic_types.len(),
-                undefined_types.len(),
-                problematic_types.iter().flat_map(|(_, v)| v.iter()).count(),
-            );
-            bail!("User type verification failed.")
+    /// Returns the enums whose base type is undefined.
+    fn enum_problems(&self) -> Vec<EnumProblem> {
+        self.enums
+            .iter()
+            .filter(|(_, e)| !self.base_types.contains_key(e.base()))
+            .map(|(name, e)| EnumProblem {
+                name: name.clone(),
+                base: e.base().clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the symbols whose type is undefined.
+    fn symbol_problems(&self) -> Vec<SymbolProblem> {
+        self.symbols
+            .iter()
+            .filter(|(_, sym)| !self.is_defined(&sym.t))
+            .map(|(name, sym)| SymbolProblem {
+                name: name.clone(),
+                undefined: UndefinedType::of(&sym.t),
+            })
+            .collect()
+    }
+
+    /// Returns the malformed type descriptors of fields and symbols.
+    ///
+    /// Bitfields must be of base types or enums, and the `pointer` base type
+    /// must be defined if any descriptor is a pointer.
+    pub fn descriptor_problems(&self) -> Vec<DescriptorProblem> {
+        let mut problems = Vec::new();
+        let mut pointers = 0;
+        let mut check = |location: &dyn Fn() -> String, t: &v_types::TypeDescr| {
+            t.visit(&mut |t| match t {
+                v_types::TypeDescr::Bitfield { type_, .. }
+                    if !matches!(
+                        **type_,
+                        v_types::TypeDescr::Base { .. } | v_types::TypeDescr::Enum { .. }
+                    ) =>
+                {
+                    problems.push(DescriptorProblem {
+                        location: location(),
+                        details: format!("bitfield of {}", describe(type_)),
+                    })
+                }
+                v_types::TypeDescr::Pointer { .. } => pointers += 1,
+                _ => (),
+            });
+        };
+        for (name, ut) in self.user_types.iter() {
+            for (field_name, field) in ut.fields.iter() {
+                let location = || format!("{} {}::{}", ut.kind.as_str(), name, field_name);
+                check(&location, &field.t);
+            }
+        }
+        for (name, sym) in self.symbols.iter() {
+            check(&|| format!("symbol {}", name), &sym.t);
+        }
+
+        if pointers > 0 && !self.base_types.contains_key("pointer") {
+            problems.push(DescriptorProblem {
+                location: String::from("base_types"),
+                details: format!(
+                    "base type `pointer` is undefined, but {} descriptors are pointers",
+                    pointers
+                ),
+            });
         }
+        problems
+    }
+
+    /// Returns the symbols that have the `void` fallback type, as far as they
+    /// are known after [`Isf::fix_symbol_types`].
+    pub fn untyped_report(&self) -> UntypedReport {
+        let mut untyped: BTreeMap<String, Vec<UntypedSymbol>> = BTreeMap::new();
+        let (mut no_type, mut undefined_type) = (0, 0);
+        for (name, sym) in self.symbols.iter() {
+            let (reason, undefined) = match sym.untyped() {
+                None => continue,
+                Some(v_symbols::Untyped::NoType) => {
+                    no_type += 1;
+                    ("no_type", None)
+                }
+                Some(v_symbols::Untyped::UndefinedType(t)) => {
+                    undefined_type += 1;
+                    ("undefined_type", Some(t.clone()))
+                }
+            };
+            let kind = sym
+                .kind()
+                .map_or_else(|| "unknown".to_string(), |kind| kind.to_string());
+            untyped.entry(kind).or_default().push(UntypedSymbol {
+                name: name.clone(),
+                reason,
+                undefined,
+            });
+        }
+
+        UntypedReport {
+            summary: UntypedSummary {
+                symbols: self.symbols.len(),
+                typed: self.symbols.len() - no_type - undefined_type,
+                untyped: no_type + undefined_type,
+                no_type,
+                undefined_type,
+            },
+            sources: self.type_sources.clone().unwrap_or_default(),
+            untyped,
+        }
+    }
+
+    /// Verifies that all types referenced by fields of user types are defined.
+    pub fn check_user_types(&self) -> error::Result<()> {
+        self.check_report().check_user_types()
+    }
+
+    /// Verifies that the base types of all enums are defined.
+    pub fn check_enums(&self) -> error::Result<()> {
+        self.check_report().check_enums()
+    }
+
+    /// Verifies that all type descriptors are well-formed, see
+    /// [`Isf::descriptor_problems`].
+    pub fn check_descriptors(&self) -> error::Result<()> {
+        self.check_report().check_descriptors()
+    }
+
+    /// Verifies that the types of all symbols are defined.
+    pub fn check_symbol_types(&self) -> error::Result<()> {
+        self.check_report().check_symbols()
+    }
+
+    /// Applies the quirks that fix undefined types, i.e., `void-symbols` with
+    /// [`Isf::fix_symbol_types`] and `type-stubs` with
+    /// [`Isf::fix_user_types`], unless they are disabled.
+    ///
+    /// Returns the problems that were fixed.
+    pub fn apply_quirks(&mut self) -> Vec<error::Error> {
+        quirks::apply(self, quirks::Stage::Verification)
+    }
+
+    /// Returns the size of `t` in bytes, or `None` if it is undefined or a
+    /// function.
+    fn size_of(&self, t: &v_types::TypeDescr) -> Option<u64> {
+        match t {
+            v_types::TypeDescr::Base { name } => {
+                self.base_types.get(&**name).map(|b| b.size() as u64)
+            }
+            v_types::TypeDescr::Enum { name } => self.enums.get(&**name).map(|e| e.size() as u64),
+            v_types::TypeDescr::Struct { name } | v_types::TypeDescr::Union { name } => {
+                self.user_types.get(&**name).map(v_types::User::size)
+            }
+            v_types::TypeDescr::Pointer { .. } => match self.target {
+                Some((width, _)) => Some(width.bytes() as u64),
+                None => self.base_types.get("pointer").map(|b| b.size() as u64),
+            },
+            v_types::TypeDescr::Array { count, subtype } => {
+                self.size_of(subtype)?.checked_mul(*count)
+            }
+            v_types::TypeDescr::Bitfield { type_, .. } => self.size_of(type_),
+            v_types::TypeDescr::Function { .. } => None,
+        }
+    }
+
+    /// Returns the fields of user types that do not fit into their user type.
+    ///
+    /// Fields must start within the user type, members of unions at offset 0,
+    /// bitfields must fit into their type, and arrays must end within the user
+    /// type. Fields of undefined types are only checked for their offset.
+    pub fn layout_problems(&self) -> Vec<LayoutProblem> {
+        let mut problems = Vec::new();
+        for (name, ut) in self.user_types.iter() {
+            for (field_name, field) in ut.fields.iter() {
+                let (offset, size) = (field.offset(), ut.size());
+                let mut problem = |details| {
+                    problems.push(LayoutProblem {
+                        kind: ut.kind.as_str(),
+                        name: name.clone(),
+                        field: field_name.to_string(),
+                        details,
+                    })
+                };
+                if offset > size {
+                    problem(format!("offset {} exceeds size {}", offset, size));
+                } else if ut.kind == v_types::UserKind::Union && offset != 0 {
+                    problem(format!("union member at offset {}", offset));
+                } else if let v_types::TypeDescr::Bitfield {
+                    bit_position,
+                    bit_length,
+                    type_,
+                } = &field.t
+                {
+                    let bits = self.size_of(type_).map(|size| size * 8);
+                    let end = *bit_position as u64 + *bit_length as u64;
+                    if bits.is_some_and(|bits| end > bits) {
+                        problem(format!(
+                            "bits {}..{} exceed the {} bits of its type",
+                            bit_position,
+                            end,
+                            bits.unwrap()
+                        ));
+                    }
+                } else if let v_types::TypeDescr::Array { count, subtype } = &field.t {
+                    let elem_size = self.size_of(subtype).unwrap_or(0);
+                    let end = count
+                        .checked_mul(elem_size)
+                        .and_then(|len| len.checked_add(offset));
+                    if end.is_none_or(|end| end > size) {
+                        problem(format!(
+                            "array of {} elements of {} bytes at offset {} exceeds size {}",
+                            count, elem_size, offset, size
+                        ));
+                    }
+                }
+            }
+        }
+        problems
+    }
+
+    /// Verifies that all fields fit into their user types, see
+    /// [`Isf::layout_problems`].
+    ///
+    /// Bitfields that exceeded their type fail the check too, although they
+    /// were fixed when the user types were constructed.
+    pub fn check_layout(&self) -> error::Result<()> {
+        let problems = self.layout_problems();
+        if problems.is_empty() && self.invalid_bitfields == 0 {
+            log::debug!("All fields fit into their user types");
+            return Ok(());
+        }
+        // Unlike undefined types these are never expected, so they are shown
+        // by default.
+        for problem in problems.iter() {
+            log::error!("{}", problem);
+        }
+        let mut details = Vec::new();
+        if !problems.is_empty() {
+            details.push(format!(
+                "{} fields do not fit into their user types",
+                problems.len()
+            ));
+        }
+        if self.invalid_bitfields > 0 {
+            details.push(format!(
+                "{} bitfields exceeded their type and were fixed",
+                self.invalid_bitfields
+            ));
+        }
+        log::error!("{}", details.join(", "));
+        Err(error::Error::IsfVerification {
+            kind: VerificationKind::Layout,
+            details: details.join(", "),
+        })
     }
 
     /// Tests if a type is defined in the ISF file.
     fn is_defined(&self, t: &v_types::TypeDescr) -> bool {
         let rt = t.resolve();
         match rt {
-            v_types::TypeDescr::Base { name } => self.base_types.contains_key(name),
-            v_types::TypeDescr::Enum { name } => self.enums.contains_key(name),
+            v_types::TypeDescr::Base { name } => self.base_types.contains_key(&**name),
+            v_types::TypeDescr::Enum { name } => self.enums.contains_key(&**name),
             v_types::TypeDescr::Union { name } => self
                 .user_types
-                .get(name)
+                .get(&**name)
                 .is_some_and(|t| t.kind == v_types::UserKind::Union),
             v_types::TypeDescr::Struct { name } => self
                 .user_types
-                .get(name)
+                .get(&**name)
                 .is_some_and(|t| t.kind == v_types::UserKind::Struct),
-            v_types::TypeDescr::Function => true,
+            // The types of parameters are not required to be defined.
+            v_types::TypeDescr::Function { .. } => true,
             _ => panic!("BUG: type descriptor resolution failed: {:?}", rt),
         }
     }
@@ -218,7 +2024,7 @@ impl Isf {
     /// As we rely on a database to determine the type of symbols there will be
     /// some cases where the referenced types are not defined in the BTF
     /// section. In that case we simply set the type of the symbol to "void".
-    pub fn fix_symbol_types(&mut self) -> Result<()> {
+    pub fn fix_symbol_types(&mut self) -> error::Result<()> {
         let mut problematic_symbols: HashSet<String> = HashSet::new();
         let mut missing_types: HashSet<&v_types::TypeDescr> = HashSet::new();
 
@@ -227,7 +2033,15 @@ impl Isf {
                 continue;
             }
             let rt = sym.t.resolve();
-            log::warn!("Symbol {} references non-present type {:?}", name, rt);
+            log::warn!(
+                target: diagnostics::VOIDED_SYMBOLS,
+                code = "voided_symbol",
+                symbol:% = name,
+                type_name:? = rt;
+                "Symbol {} references non-present type {:?}",
+                name,
+                rt
+            );
             missing_types.insert(rt);
             problematic_symbols.insert(name.to_owned());
         }
@@ -244,9 +2058,97 @@ impl Isf {
             // fixup symbols
             for sym_name in problematic_symbols.iter() {
                 let sym = self.symbols.get_mut(sym_name).unwrap();
-                sym.t = v_types::TypeDescr::new_void();
+                let rt = sym.t.resolve();
+                let name = rt.name().map_or("", |name| &**name);
+                let undefined = match rt.kind() {
+                    Some(kind) if !kind.is_empty() => format!("{} {}", kind, name),
+                    _ => name.to_string(),
+                };
+                sym.set_void(undefined);
             }
-            bail!("Symbol type verification failed.")
+            Err(error::Error::IsfVerification {
+                kind: VerificationKind::SymbolTypes,
+                details: format!("{} symbols were set to void", problematic_symbols.len()),
+            })
         }
     }
+
+    /// Adds stubs for undefined types that are referenced by fields of user
+    /// types.
+    ///
+    /// Structs and unions are stubbed by empty user types, enums by enums
+    /// without constants. Afterwards, all types referenced by fields of user
+    /// types are defined, unless a type of another kind already has the name.
+    pub fn fix_user_types(&mut self) -> error::Result<()> {
+        let mut undefined: BTreeSet<(&'static str, String)> = BTreeSet::new();
+        for ut in self.user_types.values() {
+            for (_, field) in ut.fields.iter() {
+                if self.is_defined(&field.t) {
+                    continue;
+                }
+                let rt = field.t.resolve();
+                undefined.insert((rt.kind().unwrap(), rt.name().unwrap().to_string()));
+            }
+        }
+
+        if undefined.is_empty() {
+            log::debug!("No stubs needed for types referenced by user types");
+            return Ok(());
+        }
+        let mut nr_stubs = 0;
+        for (kind, name) in undefined.iter() {
+            let taken = self.user_types.contains_key(name)
+                || self.enums.contains_key(name)
+                || self.base_types.contains_key(name);
+            let enum_stub = match *kind {
+                "struct" | "union" => None,
+                _ => v_types::Enum::new_stub(&self.base_types),
+            };
+            let stubbable = matches!(*kind, "struct" | "union") || enum_stub.is_some();
+            if taken || kind.is_empty() || !stubbable {
+                log::warn!(
+                    target: diagnostics::UNDEFINED_TYPES,
+                    code = "unstubbable_undefined_type",
+                    kind:% = kind,
+                    type_name:% = name;
+                    "Unable to add stub for undefined type `{} {}`",
+                    kind,
+                    name
+                );
+                continue;
+            }
+            log::warn!(
+                target: diagnostics::UNDEFINED_TYPES,
+                code = "undefined_type_stub",
+                kind:% = kind,
+                type_name:% = name;
+                "Adding stub for undefined type `{} {}`",
+                kind,
+                name
+            );
+            match *kind {
+                "struct" | "union" => {
+                    let kind = if *kind == "struct" {
+                        v_types::UserKind::Struct
+                    } else {
+                        v_types::UserKind::Union
+                    };
+                    self.user_types
+                        .insert(name.clone(), v_types::User::new_stub(kind));
+                }
+                _ => {
+                    self.enums.insert(name.clone(), enum_stub.unwrap());
+                }
+            }
+            nr_stubs += 1;
+        }
+        Err(error::Error::IsfVerification {
+            kind: VerificationKind::UserTypes,
+            details: format!(
+                "{} undefined types referenced, {} stubs were added",
+                undefined.len(),
+                nr_stubs
+            ),
+        })
+    }
 }
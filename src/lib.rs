@@ -1,23 +1,76 @@
 //! Generating ISF files using BTF information.
 
-use crate::btf::{Btf, Typedefs};
-use crate::cli::Cli;
-use crate::metadata::MetadataBuilder;
-use crate::symbols::SymbolsBuilder;
+use crate::btf::{Btf, Endian, Typedefs, VolIdSets};
+use crate::bundle::Artifact;
+use crate::cache::Cache;
+use crate::cli::{AddressOverflow, ArchArg, Architecture, Cli, Compat, GenerateArgs, Quirk};
+use crate::input::InputData;
+use crate::isf::Isf;
+use crate::metadata::{FormatVersion, Invocation, MetadataBuilder};
+use crate::patch::Patch;
+use crate::progress::{Phase, Progress, Silent, StderrProgress};
+use crate::symbols::{Banner, MapFormat, Symbols, SymbolsBuilder};
+use crate::timings::Timings;
+use crate::v_symbols::AddressWidth;
+use crate::version::KernelVersion;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
 use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 
-use anyhow::{Error, Result};
+use regex::Regex;
 
+pub use crate::error::Error;
+
+pub mod arch;
+pub mod banners;
+pub mod batch;
 pub mod btf;
+pub mod bundle;
+pub mod cache;
 pub mod cli;
+pub mod consistency;
+pub mod decompress;
+pub mod diagnostics;
+pub mod dwarf;
 pub mod elf;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod graph;
+pub mod image;
+pub mod input;
 pub mod isf;
+pub mod lime;
+pub mod merge;
 pub mod metadata;
+pub mod names;
+pub mod offsets;
+pub mod output;
+pub mod package;
+pub mod patch;
+pub mod progress;
+pub mod query;
+pub mod randstruct;
+pub mod schema;
+pub mod search;
+pub mod stats;
 pub mod symbols;
+pub mod symdb;
+#[cfg(feature = "test-util")]
+pub mod testutil;
+pub mod timings;
 pub mod v_symbols;
 pub mod v_types;
+pub mod version;
+pub mod vmcore;
+pub mod vol2;
+
+/// Log target of the diagnostics of `--trace-type`, which are logged at info
+/// level.
+pub const TRACE_TARGET: &str = "btf2json::trace_type";
 
 /// Information required to generate an ISF file.
 pub struct GenerationContext {
@@ -27,34 +80,705 @@ pub struct GenerationContext {
     // In the end, the underlying types are going to be disambiguated by name.
     // There can be types of the same kind and name, but with different ids.
     // If we use hash sets here the iteration order will not be deterministic,
-    // which will lead to randomness in the types that are included in the final
-    // ISF file.
+    // which will lead to randomness in how conflicts between those types are
+    // resolved.
     user_ids: BTreeSet<btf::Id>,
     enum_ids: BTreeSet<btf::Id>,
     basic_ids: BTreeSet<btf::Id>,
+    fwd_ids: BTreeSet<btf::Id>,
     symbols: symbols::Symbols,
     typedefs: Typedefs,
+    arch: Architecture,
+    address_width: AddressWidth,
+    address_overflow: AddressOverflow,
+    rich_functions: bool,
+    emit_fwd_stubs: bool,
+    keep_duplicate_types: bool,
+    /// Emit user types and enums under all of their typedef names.
+    typedef_aliases: bool,
+    /// Drop typedef aliases that collide with types of other categories.
+    drop_colliding_aliases: bool,
+    format_version: FormatVersion,
+    include_types: Option<Regex>,
+    exclude_types: Option<Regex>,
+    /// Types to keep besides those reachable from the symbols, if unreachable
+    /// types are dropped.
+    prune_roots: Option<Vec<String>>,
+    /// Drop anonymous user types that nothing references.
+    exclude_anon_types: bool,
+    /// Record the BTF types that the entries come from.
+    annotate_btf_ids: bool,
+    /// Names of the user types whose generation is traced.
+    trace_types: HashSet<String>,
+    /// Adjustments to the expectations of Volatility that are applied.
+    quirks: Vec<Quirk>,
+    /// Banner of the profile, if one was found.
+    banner: Option<Banner>,
+    /// Signedness of plain `char` in the kernel, if known.
+    char_signed: Option<bool>,
+    /// Overlay that is merged into the ISF file, see `--patch`.
+    patch: Option<Patch>,
+    progress: Rc<dyn Progress>,
+    /// Durations of the phases so far, which the ISF file takes over.
+    timings: Timings,
+}
+
+/// Options for generating an ISF file in-process.
+///
+/// The defaults are those of `btf2json generate`, and each option corresponds
+/// to the command line option of the same name. Inputs are files or, for the
+/// BTF and the System.map, data in memory.
+///
+/// # Example
+///
+/// ```
+/// use btf2json::cli::Architecture;
+/// use btf2json::GenerationOptions;
+///
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_le.btf");
+/// let btf = std::fs::read(path)?;
+/// let isf = GenerationOptions::new()
+///     .btf_bytes("vmlinux.btf", btf)
+///     .banner("Linux version 6.18.0 (gcc) #1 SMP")
+///     .arch(Architecture::X86_64)
+///     .types_only(true)
+///     .generate()?;
+///
+/// let mut json = Vec::new();
+/// isf.to_writer(&mut json)?;
+/// assert!(json.starts_with(b"{\"metadata\""));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Default)]
+pub struct GenerationOptions {
+    args: GenerateArgs,
+    /// Name and contents of the BTF file, instead of a path.
+    btf_data: Option<(String, Vec<u8>)>,
+    /// Name and contents of the System.map, instead of a path.
+    map_data: Option<(String, Vec<u8>)>,
+    progress: Option<Rc<dyn Progress>>,
+}
+
+impl From<GenerateArgs> for GenerationOptions {
+    fn from(args: GenerateArgs) -> Self {
+        Self {
+            args,
+            ..Default::default()
+        }
+    }
+}
+
+impl GenerationOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the BTF from the file at `path`, which can also be a kernel image.
+    pub fn btf_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.btf = Some(path.as_ref().to_owned());
+        self.args.dwarf = None;
+        self.args.package = None;
+        self.btf_data = None;
+        self
+    }
+
+    /// Reads the types from the DWARF debug information of the vmlinux at
+    /// `path`, for kernels without BTF.
+    pub fn dwarf_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.dwarf = Some(path.as_ref().to_owned());
+        self.args.btf = None;
+        self.args.package = None;
+        self.btf_data = None;
+        self
+    }
+
+    /// Reads vmlinux and, unless a System.map is given, System.map from the
+    /// `.deb` or `.rpm` package at `path`.
+    pub fn package_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.package = Some(path.as_ref().to_owned());
+        self.args.btf = None;
+        self.args.dwarf = None;
+        self.btf_data = None;
+        self
+    }
+
+    /// Reads the BTF from `data`, referred to as `name` in the metadata.
+    ///
+    /// The banner and the architecture are read from `data` if it is vmlinux
+    /// and they are not given.
+    pub fn btf_bytes(mut self, name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.args.btf = None;
+        self.args.dwarf = None;
+        self.args.package = None;
+        self.btf_data = Some((name.into(), data));
+        self
+    }
+
+    /// Reads the base BTF of split BTF from the file at `path`.
+    pub fn base_btf_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.base_btf = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Reads symbols from the System.map at `path`.
+    ///
+    /// Can be called more than once, to merge several System.map files.
+    pub fn map_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.map.push(path.as_ref().to_owned());
+        self.map_data = None;
+        self
+    }
+
+    /// Reads symbols from the System.map `data`, referred to as `name` in the
+    /// metadata.
+    pub fn map_bytes(mut self, name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.args.map.clear();
+        self.map_data = Some((name.into(), data));
+        self
+    }
+
+    /// Reads symbols from the kallsyms file at `path`.
+    pub fn kallsyms_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.kallsyms = Some(path.as_ref().to_owned());
+        self
+    }
+
+    pub fn banner(mut self, banner: impl Into<String>) -> Self {
+        self.args.banner = Some(banner.into());
+        self
+    }
+
+    pub fn arch(mut self, arch: Architecture) -> Self {
+        self.args.arch = arch.into();
+        self
+    }
+
+    /// Sets the byte order of the types, which takes precedence over the one
+    /// of the BTF magic.
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.args.endian = Some(endian);
+        self
+    }
+
+    /// Sets the kernel version, which takes precedence over the one in the
+    /// banner.
+    pub fn kernel_version(mut self, version: KernelVersion) -> Self {
+        self.args.kernel_version = Some(version);
+        self
+    }
+
+    pub fn base_offset(mut self, base_offset: u64) -> Self {
+        self.args.base_offset = Some(base_offset);
+        self
+    }
+
+    pub fn no_rebase(mut self, no_rebase: bool) -> Self {
+        self.args.no_rebase = no_rebase;
+        self
+    }
+
+    pub fn types_only(mut self, types_only: bool) -> Self {
+        self.args.types_only = types_only;
+        self
+    }
+
+    pub fn symbol_filter(mut self, filter: Regex) -> Self {
+        self.args.symbol_filter = Some(filter);
+        self
+    }
+
+    pub fn rich_functions(mut self, rich_functions: bool) -> Self {
+        self.args.rich_functions = rich_functions;
+        self
+    }
+
+    /// Limits type chains and the nesting of type descriptors, see
+    /// [`Btf::set_max_type_depth`].
+    pub fn max_type_depth(mut self, depth: usize) -> Self {
+        self.args.max_type_depth = Some(depth);
+        self
+    }
+
+    pub fn emit_fwd_stubs(mut self, emit_fwd_stubs: bool) -> Self {
+        self.args.emit_fwd_stubs = emit_fwd_stubs;
+        self
+    }
+
+    /// Applies only the given quirks, none if empty, instead of all of them.
+    pub fn quirks(mut self, quirks: Vec<Quirk>) -> Self {
+        self.args.quirks = Some(quirks);
+        self
+    }
+
+    pub fn compat(mut self, compat: Compat) -> Self {
+        self.args.compat = Some(compat);
+        self
+    }
+
+    pub fn stable_anon_names(mut self, stable_anon_names: bool) -> Self {
+        self.args.stable_anon_names = stable_anon_names;
+        self
+    }
+
+    pub fn keep_duplicate_types(mut self, keep: bool) -> Self {
+        self.args.keep_duplicate_types = keep;
+        self
+    }
+
+    /// Emits user types and enums under their typedef names too, the default.
+    pub fn typedef_aliases(mut self, typedef_aliases: bool) -> Self {
+        self.args.no_typedef_aliases = !typedef_aliases;
+        self
+    }
+
+    pub fn include_types(mut self, include: Regex) -> Self {
+        self.args.include_types = Some(include);
+        self
+    }
+
+    pub fn exclude_types(mut self, exclude: Regex) -> Self {
+        self.args.exclude_types = Some(exclude);
+        self
+    }
+
+    /// Only emits the types reachable from the symbols and `roots`.
+    pub fn prune(mut self, roots: Vec<String>) -> Self {
+        self.args.prune = true;
+        self.args.roots = Some(roots);
+        self
+    }
+
+    /// Drops the anonymous structs and unions that nothing references, see
+    /// [`Isf::exclude_anon_types`].
+    pub fn exclude_anon_types(mut self, exclude_anon_types: bool) -> Self {
+        self.args.exclude_anon_types = exclude_anon_types;
+        self
+    }
+
+    /// Merges the overlay at `path` into the profile, see `--patch`.
+    pub fn patch_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.args.patch = Some(path.as_ref().to_owned());
+        self
+    }
+
+    pub fn format_version(mut self, format_version: FormatVersion) -> Self {
+        self.args.format_version = format_version;
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.args.comment = Some(comment.into());
+        self
+    }
+
+    /// Keeps the analysis of the BTF types in `dir`, see `--cache-dir`.
+    pub fn cache_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.args.cache_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Reports the progress of the generation to `progress`, instead of to
+    /// standard error if `--progress` is given.
+    pub fn progress(mut self, progress: Rc<dyn Progress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Generates the ISF file.
+    ///
+    /// Unlike `btf2json generate`, undefined types are neither checked nor
+    /// fixed, see [`Isf::fix_symbol_types`] and [`Isf::fix_user_types`].
+    pub fn generate(self) -> error::Result<Isf> {
+        Isf::try_from(GenerationContext::try_from(self)?)
+    }
 }
 
 impl TryFrom<&Cli> for GenerationContext {
     type Error = Error;
 
+    /// Try to gather the required information from the options of `generate`.
+    fn try_from(cli: &Cli) -> error::Result<GenerationContext> {
+        GenerationContext::try_from(cli.generate_args())
+    }
+}
+
+impl TryFrom<&GenerateArgs> for GenerationContext {
+    type Error = Error;
+
     /// Try to gather the required information from the sources given on the
     /// CLI.
-    fn try_from(cli: &Cli) -> Result<GenerationContext> {
-        let btf = Btf::try_from(cli)?;
-        let (user_ids, enum_ids, basic_ids, typedefs) = btf.gen_vol_id_sets()?;
-        let symbols = SymbolsBuilder::try_from(cli)?
-            .add_types_from_btf(&btf)
-            .build();
-        Ok(GenerationContext {
-            mbuilder: Some(MetadataBuilder::new(&btf, &symbols)),
+    fn try_from(cli: &GenerateArgs) -> error::Result<GenerationContext> {
+        GenerationContext::try_from(GenerationOptions::from(cli.clone()))
+    }
+}
+
+impl TryFrom<GenerationOptions> for GenerationContext {
+    type Error = Error;
+
+    /// Try to gather the required information from the sources in `options`.
+    fn try_from(options: GenerationOptions) -> error::Result<GenerationContext> {
+        let GenerationOptions {
+            args: mut cli,
+            mut btf_data,
+            mut map_data,
+            progress,
+        } = options;
+        let progress = progress.unwrap_or_else(|| {
+            if cli.progress {
+                Rc::new(StderrProgress::new())
+            } else {
+                Rc::new(Silent)
+            }
+        });
+        if let (Some(arch), Some(endian)) = (cli.arch.given(), cli.endian) {
+            if endian != arch.endian() {
+                return Err(anyhow::anyhow!(
+                    "Architecture {:?} is {} endian, but --endian {} was given",
+                    arch,
+                    arch.endian(),
+                    endian
+                )
+                .into());
+            }
+        }
+        input::ensure_single_stdin(
+            [
+                &cli.btf,
+                &cli.dwarf,
+                &cli.base_btf,
+                &cli.module,
+                &cli.kallsyms,
+                &cli.symdb,
+                &cli.package,
+            ]
+            .into_iter()
+            .flatten()
+            .chain(&cli.map)
+            .map(PathBuf::as_path),
+        )?;
+        if let Some(dir) = cli.search_dir.as_ref().filter(|_| btf_data.is_none()) {
+            let banner = Banner::try_from(&cli).map_err(|err| {
+                err.context("--search-dir needs a banner, e.g., of --banner or --image")
+            })?;
+            let kernel = search::select(dir, &banner, cli.cache_dir.as_deref())?;
+            cli.btf = kernel.btf;
+            if cli.map.is_empty() {
+                cli.map.extend(kernel.map);
+            }
+        }
+        if let Some(path) = cli.package.as_ref().filter(|_| btf_data.is_none()) {
+            let package = package::extract(path)?;
+            btf_data = Some(package.vmlinux);
+            if map_data.is_none() && cli.map.is_empty() {
+                map_data = package.system_map;
+            }
+        }
+        let timings = Timings::default();
+        let start = Instant::now();
+        let mut btf = match btf_data {
+            Some((name, data)) => {
+                let btf = Btf::from_bytes(name, data)?;
+                // The banner and architecture are otherwise read from the file.
+                if cli.banner.is_none() && cli.banner_file.is_none() {
+                    cli.banner = elf::get_banner(&btf.raw()).ok();
+                }
+                if let (ArchArg::Auto, Some(arch)) = (cli.arch, arch::from_elf_data(&btf.raw())) {
+                    cli.arch = arch.into();
+                }
+                btf
+            }
+            None => Btf::try_from(&cli)?,
+        };
+        timings.record("btf-load", start.elapsed());
+        let map = map_data.map(|(name, data)| (name, InputData::from(data)));
+        let cli = &cli;
+        if let Some(endian) = cli.endian {
+            btf.override_endian(endian);
+        }
+        if let Some(depth) = cli.max_type_depth {
+            btf.set_max_type_depth(depth);
+        }
+        if cli.compat == Some(Compat::Dwarf2json) {
+            btf.use_dwarf2json_names();
+        }
+        if cli.stable_anon_names {
+            btf.use_stable_anon_names();
+        }
+        let id_sets = timings.time("btf-scan", || match &cli.cache_dir {
+            Some(dir) => Cache::new(dir).scan_types(&btf, &*progress),
+            None => btf.scan_types(&*progress),
+        })?;
+        let banner = Banner::try_from(cli).ok();
+        let version = cli.kernel_version(banner.as_ref());
+        match (version, cli.kernel_version) {
+            (Some(version), Some(_)) => {
+                log::debug!("Kernel version {} of --kernel-version", version)
+            }
+            (Some(version), None) => log::debug!("Kernel version {} of the banner", version),
+            (None, _) => log::debug!("Unknown kernel version, use --kernel-version"),
+        }
+        randstruct::detect(&btf, version)
+            .log(map.is_some() || !cli.map.is_empty() || cli.kallsyms.is_some());
+        let arch = arch::detect(cli)?;
+        if arch.endian() != btf.endian {
+            log::warn!(
+                target: diagnostics::INPUTS,
+                code = "arch_endian_mismatch",
+                arch:? = arch,
+                endian:% = arch.endian(),
+                detected:% = btf.endian;
+                "{:?} is {} endian, but the types are {} endian like the BTF or --endian",
+                arch,
+                arch.endian(),
+                btf.endian
+            );
+        }
+        progress.start(Phase::Symbols, None);
+        let symbols = SymbolsBuilder::from_cli(cli, map, arch, &btf, true, &timings)?;
+        let symbols = timings.time("symbol-types", || {
+            symbols.add_types_from_btf(&btf, cli.rich_functions)
+        });
+        let symbols = symbols.build();
+        progress.finish(Phase::Symbols, symbols.len());
+        let mut options = ContextOptions::new()
+            .args(cli.clone())
+            .arch(arch)
+            .progress(progress);
+        options.banner = banner;
+        options.timings = timings;
+        GenerationContext::with_id_sets(btf, symbols, id_sets, options)
+    }
+}
+
+/// Options of a [`GenerationContext`] of a `Btf` and `Symbols` that the caller
+/// already has, see [`GenerationContext::new`].
+pub struct ContextOptions {
+    args: GenerateArgs,
+    arch: Option<Architecture>,
+    banner: Option<Banner>,
+    progress: Rc<dyn Progress>,
+    /// Timings of reading the inputs, if the context is created from them.
+    timings: Timings,
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self {
+            args: GenerateArgs::default(),
+            arch: None,
+            banner: None,
+            progress: Rc::new(Silent),
+            timings: Timings::default(),
+        }
+    }
+}
+
+impl ContextOptions {
+    /// Returns the defaults of `btf2json generate`, with the architecture read
+    /// from the BTF and no banner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the options of `btf2json generate` in `args` that apply to the
+    /// generation, i.e., not the inputs, the architecture, and the banner.
+    pub fn args(mut self, args: GenerateArgs) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Sets the architecture, which is required unless the BTF is read from a
+    /// vmlinux.
+    pub fn arch(mut self, arch: Architecture) -> Self {
+        self.arch = Some(arch);
+        self
+    }
+
+    pub fn banner(mut self, banner: Banner) -> Self {
+        self.banner = Some(banner);
+        self
+    }
+
+    /// Sets the receiver of the progress, none by default.
+    pub fn progress(mut self, progress: Rc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+}
+
+impl GenerationContext {
+    /// Creates the context for generating an ISF file of the types in `btf`
+    /// and of `symbols`, with `options`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use btf2json::btf::Btf;
+    /// use btf2json::cli::Architecture;
+    /// use btf2json::isf::Isf;
+    /// use btf2json::symbols::{Banner, Symbols};
+    /// use btf2json::{ContextOptions, GenerationContext};
+    ///
+    /// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_le.btf");
+    /// let btf = Btf::from_bytes("vmlinux.btf", std::fs::read(path)?)?;
+    /// let options = ContextOptions::new()
+    ///     .arch(Architecture::X86_64)
+    ///     .banner(Banner::new("Linux version 6.18.0"));
+    /// let isf = Isf::try_from(GenerationContext::new(btf, Symbols::default(), options)?)?;
+    /// assert_eq!(isf.symbols().len(), 0);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn new(btf: Btf, symbols: Symbols, options: ContextOptions) -> error::Result<Self> {
+        let id_sets = btf.gen_vol_id_sets()?;
+        Self::with_id_sets(btf, symbols, id_sets, options)
+    }
+
+    /// Same as [`GenerationContext::new`] with the partitioning of the types
+    /// of `btf` from [`Btf::gen_vol_id_sets`], e.g., of an earlier context of
+    /// the same BTF.
+    pub fn with_id_sets(
+        btf: Btf,
+        symbols: Symbols,
+        id_sets: VolIdSets,
+        options: ContextOptions,
+    ) -> error::Result<Self> {
+        let ContextOptions {
+            args,
+            arch,
+            banner,
+            progress,
+            timings,
+        } = options;
+        let arch = match arch {
+            Some(arch) => arch,
+            None => arch::from_elf_data(&btf.raw()).ok_or_else(|| {
+                anyhow::anyhow!("Unable to detect the architecture of the BTF, please set it")
+            })?,
+        };
+        let patch = args.patch.as_deref().map(Patch::from_path).transpose()?;
+        Ok(Self {
+            patch,
+            timings,
+            ..Self::from_parts(btf, symbols, id_sets, &args, arch, banner, progress)
+        })
+    }
+
+    /// Creates the context from its parts and the options `cli`.
+    fn from_parts(
+        btf: Btf,
+        symbols: Symbols,
+        id_sets: VolIdSets,
+        cli: &GenerateArgs,
+        arch: Architecture,
+        banner: Option<Banner>,
+        progress: Rc<dyn Progress>,
+    ) -> Self {
+        let (user_ids, enum_ids, basic_ids, fwd_ids, typedefs) = id_sets;
+        let invocation = Invocation::new(
+            cli,
+            arch,
+            &symbols,
+            cli.record_invocation.unwrap_or_default(),
+        );
+        log::info!("Effective configuration: {}", invocation);
+        GenerationContext {
+            mbuilder: Some(
+                MetadataBuilder::new(&btf, &symbols, banner.as_ref())
+                    .generation(cli.timestamp, cli.hostname)
+                    .comment(cli.comment.as_deref().unwrap_or_default())
+                    .invocation(cli.record_invocation.is_some().then_some(invocation))
+                    .format(cli.format_version),
+            ),
             btf,
             user_ids,
             enum_ids,
             basic_ids,
+            fwd_ids,
             symbols,
             typedefs,
-        })
+            arch,
+            address_width: AddressWidth::new(arch.pointer_size()),
+            address_overflow: cli.address_overflow,
+            rich_functions: cli.rich_functions,
+            emit_fwd_stubs: cli.emit_fwd_stubs,
+            keep_duplicate_types: cli.keep_duplicate_types,
+            typedef_aliases: !cli.no_typedef_aliases,
+            drop_colliding_aliases: cli.drop_colliding_aliases,
+            format_version: cli.format_version,
+            include_types: cli.include_types.clone(),
+            exclude_types: cli.exclude_types.clone(),
+            prune_roots: cli.prune.then(|| cli.roots.clone().unwrap_or_default()),
+            exclude_anon_types: cli.exclude_anon_types,
+            annotate_btf_ids: cli.annotate_btf_ids,
+            trace_types: cli.trace_type.iter().cloned().collect(),
+            quirks: cli.enabled_quirks(),
+            char_signed: arch.char_signed(cli.kernel_version(banner.as_ref())),
+            banner,
+            patch: None,
+            progress,
+            timings: Timings::default(),
+        }
+    }
+
+    /// Returns the receiver of the progress, which also gets the progress of
+    /// writing the ISF file.
+    pub fn progress(&self) -> Rc<dyn Progress> {
+        self.progress.clone()
+    }
+
+    /// Returns the durations of the phases that created the context.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Returns the banner that the profile is generated for, if one was found.
+    pub fn banner(&self) -> Option<&Banner> {
+        self.banner.as_ref()
+    }
+
+    /// Returns the number of BTF types whose record could not be parsed, see
+    /// [`Btf::skipped_records`].
+    pub fn skipped_records(&self) -> usize {
+        self.btf.skipped_records()
+    }
+
+    /// Returns true iff symbols were gathered without a symdb, which happens if
+    /// it is not embedded, no symdb is installed, and none is given, see
+    /// [`symbols::installed_symdb_path`].
+    pub fn missing_symdb(&self) -> bool {
+        !self.symbols.is_empty() && self.symbols.symdb_name().is_none()
+    }
+
+    /// Returns the inputs that should be preserved alongside the profile.
+    ///
+    /// These are the BTF section, its base BTF section, and the System.map, if
+    /// used.
+    pub fn artifacts(&self) -> Vec<Artifact> {
+        let mut artifacts = vec![Artifact::new(
+            format!("{}.BTF", self.btf.name()),
+            self.btf.raw(),
+            self.btf.section_range(),
+        )];
+        if let Some(base) = self.btf.base() {
+            artifacts.push(Artifact::new(
+                format!("{}.BTF", base.name()),
+                base.raw(),
+                base.section_range(),
+            ));
+        }
+        for source in self.symbols.sources() {
+            // An ELF symbol table is part of the BTF file, which is too large
+            // to be preserved as a whole.
+            if source.format == MapFormat::Symtab {
+                continue;
+            }
+            let len = source.raw.len();
+            artifacts.push(Artifact::new(
+                source.name.clone(),
+                source.raw.clone(),
+                0..len,
+            ));
+        }
+        artifacts
     }
 }
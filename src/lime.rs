@@ -0,0 +1,141 @@
+//! Utilities for working with LiME memory images.
+//!
+//! The `lime` format of LiME consists of the captured ranges of physical
+//! memory, each preceded by a 32 byte header:
+//!
+//! | Offset | Size | Field                                       |
+//! |--------|------|---------------------------------------------|
+//! | 0      | 4    | magic, `0x4C694D45`                         |
+//! | 4      | 4    | version, 1                                  |
+//! | 8      | 8    | first physical address of the range         |
+//! | 16     | 8    | last physical address of the range          |
+//! | 24     | 8    | reserved                                    |
+//!
+//! All fields are little endian. The `padded` format has no headers, it fills
+//! the gaps between the ranges with zeros, so that file offsets are physical
+//! addresses, like in raw images.
+
+use crate::diagnostics;
+
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+
+/// Magic of the header of a range.
+const LIME_MAGIC: u32 = 0x4C694D45;
+/// Version of the header of a range.
+const LIME_VERSION: u32 = 1;
+/// Size of the header of a range.
+const HEADER_LEN: usize = 32;
+
+/// A range of physical memory in a LiME image.
+struct Segment {
+    /// Physical address of the range.
+    paddr: u64,
+    /// Location of the contents of the range in the image.
+    file: Range<usize>,
+}
+
+/// Layout of a LiME image.
+pub struct Lime {
+    segments: Vec<Segment>,
+}
+
+impl Lime {
+    /// Parses the headers of the ranges of the LiME image `raw`.
+    ///
+    /// The last range may be truncated, e.g., as the capture was aborted, in
+    /// which case it ends with the image.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        if !is_lime(raw) {
+            bail!("Not a LiME image")
+        }
+
+        let mut segments = Vec::new();
+        let mut off = 0;
+        while off < raw.len() {
+            let Some(header) = raw.get(off..off + HEADER_LEN) else {
+                log::warn!(
+                    target: diagnostics::INPUTS,
+                    code = "truncated_lime_header",
+                    offset = off;
+                    "LiME image ends in the header of a range at {:#x}",
+                    off
+                );
+                break;
+            };
+            let u32_at = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+            let u64_at = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap());
+            if u32_at(0) != LIME_MAGIC {
+                bail!("Invalid LiME header at {:#x}", off);
+            }
+            if u32_at(4) != LIME_VERSION {
+                bail!("Unsupported LiME version {} at {:#x}", u32_at(4), off);
+            }
+            let (start, end) = (u64_at(8), u64_at(16));
+            let Some(len) = end
+                .checked_sub(start)
+                .and_then(|len| usize::try_from(len).ok()?.checked_add(1))
+            else {
+                bail!("Invalid LiME range {:#x}-{:#x} at {:#x}", start, end, off);
+            };
+
+            let data = off + HEADER_LEN;
+            let data_end = data.saturating_add(len).min(raw.len());
+            if data_end - data < len {
+                log::warn!(
+                    target: diagnostics::INPUTS,
+                    code = "truncated_lime_range",
+                    start = start,
+                    end = end,
+                    len = data_end - data;
+                    "LiME range {:#x}-{:#x} is truncated to {:#x} bytes",
+                    start,
+                    end,
+                    data_end - data
+                );
+            }
+            segments.push(Segment {
+                paddr: start,
+                file: data..data_end,
+            });
+            off = data_end;
+        }
+        log::debug!("LiME image has {} ranges", segments.len());
+
+        Ok(Self { segments })
+    }
+
+    /// Returns the locations of the contents of all ranges.
+    pub fn segments(&self) -> Vec<Range<usize>> {
+        self.segments.iter().map(|seg| seg.file.clone()).collect()
+    }
+
+    /// Returns the physical address of the contents at offset `off` of the
+    /// image, if it is in a range.
+    pub fn phys_addr(&self, off: usize) -> Option<u64> {
+        let seg = self.segments.iter().find(|seg| seg.file.contains(&off))?;
+        Some(seg.paddr + (off - seg.file.start) as u64)
+    }
+
+    /// Returns up to `len` bytes at the physical address `paddr`.
+    ///
+    /// Fewer bytes are returned if the range ends before. Returns `None` if
+    /// the address is not in any range.
+    pub fn read_phys<'a>(&self, raw: &'a [u8], paddr: u64, len: usize) -> Option<&'a [u8]> {
+        let seg = self
+            .segments
+            .iter()
+            .find(|seg| paddr >= seg.paddr && paddr - seg.paddr < seg.file.len() as u64)?;
+        let start = seg.file.start + (paddr - seg.paddr) as usize;
+        Some(&raw[start..seg.file.end.min(start.saturating_add(len))])
+    }
+}
+
+/// Returns true iff `raw` is in the `lime` format of LiME, i.e., starts with
+/// the header of a range.
+///
+/// Images in the `padded` format are flat and not detected.
+pub fn is_lime(raw: &[u8]) -> bool {
+    raw.get(..4) == Some(&LIME_MAGIC.to_le_bytes())
+}
@@ -0,0 +1,267 @@
+//! Merging of two ISF files, as done by `btf2json merge`, e.g., of the profile
+//! of vmlinux and of a module, or of a profile by dwarf2json for the same
+//! kernel.
+//!
+//! The merge works on the JSON of the files, so that entries which this crate
+//! would write differently are compared as they are. The result is then read
+//! as an ISF file and verified like a generated one.
+
+use crate::cli::{MergeArgs, Prefer};
+use crate::isf::Isf;
+use crate::schema;
+
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use base64::prelude::*;
+use serde_json::{Map, Value};
+
+/// Sections of entries that are merged by name.
+const SECTIONS: [&str; 4] = ["user_types", "enums", "base_types", "symbols"];
+
+/// Source arrays of the metadata that are concatenated.
+const SOURCES: [&str; 2] = ["types", "symbols"];
+
+/// Maximal number of differences shown for a conflict.
+const MAX_DIFF_LINES: usize = 8;
+
+/// An entry that is defined differently in both files.
+#[derive(Debug)]
+pub struct Conflict {
+    /// Section of the entry, e.g., `user_types`.
+    pub section: &'static str,
+    pub name: String,
+    /// Differences of the definitions, one per JSON path, at most
+    /// [`MAX_DIFF_LINES`] and a note about the rest.
+    pub diff: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} differs", self.section, self.name)?;
+        for line in &self.diff {
+            write!(f, "\n  {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of merging two ISF files.
+pub struct Merged {
+    pub isf: Isf,
+    /// Entries that are defined differently, of which the preferred one was
+    /// kept.
+    pub conflicts: Vec<Conflict>,
+    /// Failed verifications of the merged file, after which symbols of
+    /// undefined types were set to void.
+    pub problems: Vec<String>,
+}
+
+impl TryFrom<&MergeArgs> for Merged {
+    type Error = anyhow::Error;
+
+    /// Merges the ISF files of `args`.
+    fn try_from(args: &MergeArgs) -> Result<Self> {
+        let base = schema::read_isf(&args.base)?;
+        let extra = schema::read_isf(&args.extra)?;
+        Self::merge(base, extra, args.prefer, args.force)
+    }
+}
+
+impl Merged {
+    /// Merges the ISF files `base` and `extra`, keeping the entry of `prefer`
+    /// if both define one differently.
+    ///
+    /// Files of different kernels, as told by their `linux_banner`, are only
+    /// merged with `force`.
+    pub fn merge(mut base: Value, extra: Value, prefer: Prefer, force: bool) -> Result<Self> {
+        let Value::Object(mut extra) = extra else {
+            bail!("The extra ISF file is not a JSON object");
+        };
+        let Some(base_obj) = base.as_object_mut() else {
+            bail!("The base ISF file is not a JSON object");
+        };
+        check_banners(base_obj, &extra, force)?;
+
+        let mut conflicts = Vec::new();
+        for section in SECTIONS {
+            let Some(Value::Object(entries)) = extra.remove(section) else {
+                continue;
+            };
+            let merged = base_obj
+                .entry(section)
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .with_context(|| format!("{} of the base ISF file is not an object", section))?;
+            for (name, entry) in entries {
+                match merged.get_mut(&name) {
+                    None => {
+                        merged.insert(name, entry);
+                    }
+                    Some(kept) if *kept == entry => {}
+                    Some(kept) => {
+                        let mut diff = Vec::new();
+                        diff_values("", kept, &entry, &mut diff);
+                        if diff.len() > MAX_DIFF_LINES {
+                            let more = diff.len() - MAX_DIFF_LINES;
+                            diff.truncate(MAX_DIFF_LINES);
+                            diff.push(format!("... and {} more", more));
+                        }
+                        log::debug!("Conflicting definitions of {}.{}", section, name);
+                        if prefer == Prefer::Extra {
+                            *kept = entry;
+                        }
+                        conflicts.push(Conflict {
+                            section,
+                            name,
+                            diff,
+                        });
+                    }
+                }
+            }
+        }
+        merge_sources(base_obj, extra.remove("metadata"));
+        log::info!(
+            "Merged the ISF files with {} conflicts, kept the {} entries",
+            conflicts.len(),
+            match prefer {
+                Prefer::Base => "base",
+                Prefer::Extra => "extra",
+            }
+        );
+
+        let mut isf: Isf =
+            serde_json::from_value(base).context("The merged ISF file is invalid")?;
+        let mut problems = Vec::new();
+        if let Err(err) = isf.check_user_types() {
+            problems.push(err.to_string());
+        }
+        if let Err(err) = isf.fix_symbol_types() {
+            problems.push(err.to_string());
+        }
+        Ok(Self {
+            isf,
+            conflicts,
+            problems,
+        })
+    }
+}
+
+/// Returns the contents of `linux_banner` of the ISF file `isf`, without the
+/// trailing newline.
+fn banner(isf: &Map<String, Value>) -> Option<String> {
+    let data = isf
+        .get("symbols")?
+        .get("linux_banner")?
+        .get("constant_data")?
+        .as_str()?;
+    let banner = BASE64_STANDARD.decode(data).ok()?;
+    Some(
+        String::from_utf8_lossy(&banner)
+            .trim_end_matches(['\n', '\0'])
+            .to_owned(),
+    )
+}
+
+/// Fails if the banners of `base` and `extra` differ, or only warns with
+/// `force`.
+///
+/// Files without a banner, e.g., of modules, are merged with any file.
+fn check_banners(base: &Map<String, Value>, extra: &Map<String, Value>, force: bool) -> Result<()> {
+    let (Some(base), Some(extra)) = (banner(base), banner(extra)) else {
+        log::info!("Not comparing the banners, as a file has none");
+        return Ok(());
+    };
+    if base == extra {
+        return Ok(());
+    }
+    if !force {
+        bail!(
+            "The ISF files are of different kernels, pass --force to merge them anyway:\n  base:  {}\n  extra: {}",
+            base,
+            extra
+        );
+    }
+    log::warn!(
+        "Merging ISF files of different kernels: base {:?}, extra {:?}",
+        base,
+        extra
+    );
+    Ok(())
+}
+
+/// Appends the sources in `linux.types` and `linux.symbols` of the metadata
+/// `extra` to the ones of `base`, except for the ones that are already there.
+fn merge_sources(base: &mut Map<String, Value>, extra: Option<Value>) {
+    let Some(Value::Object(mut extra_linux)) =
+        extra.and_then(|mut meta| meta.get_mut("linux").map(Value::take))
+    else {
+        return;
+    };
+    let metadata = base
+        .entry("metadata")
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Some(metadata) = metadata.as_object_mut() else {
+        return;
+    };
+    let Some(Value::Object(linux)) = metadata.get_mut("linux") else {
+        metadata.insert("linux".to_owned(), Value::Object(extra_linux));
+        return;
+    };
+    for kind in SOURCES {
+        let Some(Value::Array(added)) = extra_linux.remove(kind) else {
+            continue;
+        };
+        let Value::Array(sources) = linux
+            .entry(kind)
+            .or_insert_with(|| Value::Array(Vec::new()))
+        else {
+            continue;
+        };
+        for source in added {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+    }
+}
+
+/// Appends the differences of `base` and `extra` below the JSON path `path`
+/// to `diff`.
+fn diff_values(path: &str, base: &Value, extra: &Value, diff: &mut Vec<String>) {
+    match (base, extra) {
+        (Value::Object(base), Value::Object(extra)) => {
+            for (key, value) in base {
+                let path = format!("{}.{}", path, key);
+                match extra.get(key) {
+                    Some(other) => diff_values(&path, value, other, diff),
+                    None => diff.push(format!("{}: only in base, {}", path, short(value))),
+                }
+            }
+            for (key, value) in extra {
+                if !base.contains_key(key) {
+                    diff.push(format!("{}.{}: only in extra, {}", path, key, short(value)));
+                }
+            }
+        }
+        (base, extra) if base != extra => {
+            let path = if path.is_empty() { "." } else { path };
+            diff.push(format!(
+                "{}: base {}, extra {}",
+                path,
+                short(base),
+                short(extra)
+            ));
+        }
+        _ => {}
+    }
+}
+
+/// Returns the JSON of `value`, shortened to about 60 characters.
+fn short(value: &Value) -> String {
+    let json = value.to_string();
+    match json.char_indices().nth(60) {
+        Some((end, _)) => format!("{}...", &json[..end]),
+        None => json,
+    }
+}
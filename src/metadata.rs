@@ -1,68 +1,105 @@
 //! ISF Metadata.
 
 use crate::btf::Btf;
-use crate::symbols::Symbols;
+use crate::cli::{Architecture, GenerateArgs, RecordInvocation};
+use crate::input::InputData;
+use crate::symbols::{self, Banner, MapFormat, SymbolKind, Symbols};
+use crate::version::BANNER_PREFIX;
 
+use std::collections::BTreeMap;
 use std::convert::From;
+use std::fmt;
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
+use std::sync::LazyLock;
+use std::time::SystemTime;
 
-use crypto::digest::Digest;
-use crypto::sha2::Sha256;
-use memmap::Mmap;
-use serde::Serialize;
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// Representation of the ISF metadata object.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Metadata {
-    producer: &'static Producer,
-    format: &'static Format,
-    linux: Linux,
+    producer: Producer,
+    format: Format,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    linux: Option<Linux>,
 }
 
 impl From<MetadataBuilder> for Metadata {
-    fn from(builder: MetadataBuilder) -> Self {
+    fn from(mut builder: MetadataBuilder) -> Self {
         Self {
-            producer: &PRODUCER,
-            format: &FORMAT,
-            linux: Linux::from(builder),
+            producer: Producer {
+                datetime: builder.datetime.take(),
+                host: builder.host.take(),
+                comment: std::mem::take(&mut builder.comment),
+                invocation: builder.invocation.take(),
+                ..Producer::default()
+            },
+            format: Format(String::from(builder.format.as_str())),
+            linux: builder.format.has_sources().then(|| Linux::from(builder)),
         }
     }
 }
 
 /// Metadata for a component of the ISF file.
 ///
-/// We provide metadata for the BTF file, System.map, and symdb used to generate
-/// the profile.
+/// We provide metadata for the BTF file, System.map or ELF symbol table, and
+/// symdb used to generate the profile. The contents are hashed when the
+/// metadata is created, so that the inputs need not be kept for it.
 #[derive(Debug)]
-struct CompMeta<T, U, const K: u32> {
-    raw: T,
-    name: U,
+struct CompMeta {
+    kind: SourceKind,
+    name: String,
+    /// SHA-256 hash of the contents, in hex.
+    hash_value: String,
 }
 
-impl<T, U, const K: u32> CompMeta<T, U, K> {
-    fn new(raw: T, name: U) -> Self {
-        Self { raw, name }
+impl CompMeta {
+    fn new(kind: SourceKind, raw: &[u8], name: impl ToString) -> Self {
+        Self::with_hash(kind, name, format!("{:x}", Sha256::digest(raw)))
     }
 
-    fn try_new(raw: Option<T>, name: Option<U>) -> Option<Self> {
+    fn try_new(
+        kind: SourceKind,
+        raw: Option<Rc<InputData>>,
+        name: Option<impl ToString>,
+    ) -> Option<Self> {
         if let (Some(raw), Some(name)) = (raw, name) {
-            Some(Self { raw, name })
+            Some(Self::new(kind, &raw, name))
         } else {
             None
         }
     }
-}
 
-type BtfMeta = CompMeta<Rc<Mmap>, String, { SourceKind::Btf as u32 }>;
-type MapMeta = CompMeta<Rc<Mmap>, String, { SourceKind::SystemMap as u32 }>;
-type SymDbMeta = CompMeta<&'static [u8], &'static str, { SourceKind::Symdb as u32 }>;
+    /// Creates the metadata for a component whose SHA-256 hash is already
+    /// known, e.g., for inputs that were streamed.
+    fn with_hash(kind: SourceKind, name: impl ToString, hash_value: String) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            hash_value,
+        }
+    }
+}
 
 /// Builder for [`Metadata`].
 #[derive(Debug)]
 pub struct MetadataBuilder {
-    btf: BtfMeta,
-    map: Option<MapMeta>,
-    symdb: Option<SymDbMeta>,
+    btf: CompMeta,
+    base_btf: Option<CompMeta>,
+    maps: Vec<CompMeta>,
+    symdb: Option<CompMeta>,
+    module: Option<Module>,
+    build: Build,
+    datetime: Option<String>,
+    host: Option<String>,
+    comment: String,
+    invocation: Option<Invocation>,
+    format: FormatVersion,
 }
 
 impl MetadataBuilder {
@@ -70,59 +107,517 @@ impl MetadataBuilder {
         self.into()
     }
 
-    pub fn new(btf: &Btf, syms: &Symbols) -> Self {
+    pub fn new(btf: &Btf, syms: &Symbols, banner: Option<&Banner>) -> Self {
         Self {
-            btf: BtfMeta::new(btf.raw().clone(), btf.name().clone()),
-            map: MapMeta::try_new(syms.raw_map(), syms.map_name()),
-            symdb: SymDbMeta::try_new(syms.raw_symdb(), syms.symdb_name()),
+            btf: CompMeta::new(SourceKind::Btf, &btf.raw(), btf.name()),
+            base_btf: btf
+                .base()
+                .map(|base| CompMeta::new(SourceKind::Btf, &base.raw(), base.name())),
+            maps: syms
+                .sources()
+                .iter()
+                .map(|source| {
+                    let kind = match source.format {
+                        MapFormat::Symtab => SourceKind::Symtab,
+                        _ => SourceKind::SystemMap,
+                    };
+                    CompMeta::new(kind, &source.raw, &source.name)
+                })
+                .collect(),
+            symdb: CompMeta::try_new(SourceKind::Symdb, syms.raw_symdb(), syms.symdb_name()),
+            module: syms.module().map(|module| Module::new(module, syms)),
+            build: banner
+                .map(|banner| Build::from_banner(&banner.to_string()))
+                .unwrap_or_default(),
+            datetime: None,
+            host: None,
+            comment: String::new(),
+            invocation: None,
+            format: FormatVersion::default(),
         }
     }
+
+    /// Sets the format version of the ISF file.
+    pub fn format(mut self, format: FormatVersion) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Attaches `comment` to the metadata, an empty comment is omitted.
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = comment.to_owned();
+        self
+    }
+
+    /// Records the effective configuration, see `--record-invocation`.
+    pub fn invocation(mut self, invocation: Option<Invocation>) -> Self {
+        self.invocation = invocation;
+        self
+    }
+
+    /// Records when the ISF file is generated if `timestamp` is set, and on
+    /// which host if `host` is set.
+    ///
+    /// Both are off by default, so that the same inputs produce identical ISF
+    /// files.
+    pub fn generation(mut self, timestamp: bool, host: bool) -> Self {
+        if timestamp {
+            self.datetime = Some(humantime::format_rfc3339_seconds(SystemTime::now()).to_string());
+        }
+        if host {
+            self.host = match fs::read_to_string(HOSTNAME) {
+                Ok(name) => Some(name.trim_end().to_owned()),
+                Err(err) => {
+                    log::warn!("Unable to read the hostname from {}: {}", HOSTNAME, err);
+                    None
+                }
+            };
+        }
+        self
+    }
 }
 
-/// Metadata about the tool that produced the ISF file.
-#[derive(Serialize)]
+const HOSTNAME: &str = "/proc/sys/kernel/hostname";
+
+/// Metadata about the tool that produced the ISF file, and optionally when and
+/// where it was produced.
+#[derive(Serialize, Deserialize)]
 struct Producer {
-    name: &'static str,
-    version: &'static str,
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datetime: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    /// Free-form comment of the user, see `--comment`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    comment: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invocation: Option<Invocation>,
 }
 
 const PRODUCER_NAME: &str = env!("CARGO_CRATE_NAME");
 const PRODUCER_VERSION: &str = env!("CARGO_PKG_VERSION");
-const PRODUCER: Producer = Producer {
-    name: PRODUCER_NAME,
-    version: PRODUCER_VERSION,
-};
+
+impl Default for Producer {
+    /// Returns the metadata about this tool.
+    fn default() -> Self {
+        Self {
+            name: String::from(PRODUCER_NAME),
+            version: String::from(PRODUCER_VERSION),
+            datetime: None,
+            host: None,
+            comment: String::new(),
+            invocation: None,
+        }
+    }
+}
+
+/// Effective configuration of a run, which is logged and, with
+/// `--record-invocation`, recorded in the metadata.
+///
+/// Unlike the command line, it has the values that were resolved, e.g., the
+/// detected architecture and the base offset that was used.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// Input files by option, e.g., `map`.
+    pub inputs: BTreeMap<String, String>,
+    pub arch: String,
+    /// Symbol that the addresses were rebased on, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// Address that the anchor was moved to, if the symbols were rebased.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_offset: Option<u64>,
+    /// Filters of symbols and types by option, e.g., `symbol-filter`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub filters: BTreeMap<String, String>,
+    /// Other options that differ from their default, e.g., `rich-functions`
+    /// or `compat=dwarf2json`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+impl Invocation {
+    /// Returns the effective configuration of generating an ISF file with the
+    /// options `cli` for `arch`, which resulted in `symbols`.
+    ///
+    /// Inputs are recorded by their file names unless `paths` is
+    /// [`RecordInvocation::Full`].
+    pub fn new(
+        cli: &GenerateArgs,
+        arch: Architecture,
+        symbols: &Symbols,
+        paths: RecordInvocation,
+    ) -> Self {
+        let path = |path: &Path| {
+            if path == Path::new("-") {
+                return String::from("<stdin>");
+            }
+            let path = match paths {
+                RecordInvocation::Basenames => path.file_name().map_or(path, Path::new),
+                RecordInvocation::Full => {
+                    &std::path::absolute(path).unwrap_or_else(|_| path.to_owned())
+                }
+            };
+            path.display().to_string()
+        };
+        let inputs = [
+            ("btf", &cli.btf),
+            ("base-btf", &cli.base_btf),
+            ("module", &cli.module),
+            ("package", &cli.package),
+            ("image", &cli.image),
+            ("kallsyms", &cli.kallsyms),
+            ("symdb", &cli.symdb),
+            ("banner-file", &cli.banner_file),
+        ]
+        .into_iter()
+        .filter_map(|(option, input)| Some((option.to_owned(), path(input.as_deref()?))))
+        .chain((!cli.map.is_empty()).then(|| {
+            let maps: Vec<String> = cli.map.iter().map(|map| path(map)).collect();
+            (String::from("map"), maps.join(","))
+        }))
+        .collect();
+        let filters = [
+            ("symbol-filter", &cli.symbol_filter),
+            ("symbol-exclude", &cli.symbol_exclude),
+            ("include-types", &cli.include_types),
+            ("exclude-types", &cli.exclude_types),
+        ]
+        .into_iter()
+        .filter_map(|(option, re)| Some((option.to_owned(), re.as_ref()?.to_string())))
+        .chain(cli.symbol_kinds.as_ref().map(|kinds| {
+            let kinds: Vec<String> = kinds.iter().map(SymbolKind::to_string).collect();
+            (String::from("symbol-kinds"), kinds.join(","))
+        }))
+        .chain(
+            cli.roots
+                .as_ref()
+                .filter(|_| cli.prune)
+                .map(|roots| (String::from("roots"), roots.join(","))),
+        )
+        .collect();
+        let flags = [
+            ("live", cli.live),
+            ("scan", cli.scan),
+            ("types-only", cli.types_only),
+            ("rich-functions", cli.rich_functions),
+            ("emit-fwd-stubs", cli.emit_fwd_stubs),
+            ("stable-anon-names", cli.stable_anon_names),
+            ("keep-duplicate-types", cli.keep_duplicate_types),
+            ("no-typedef-aliases", cli.no_typedef_aliases),
+            ("drop-colliding-aliases", cli.drop_colliding_aliases),
+            ("prune", cli.prune && cli.roots.is_none()),
+            ("exclude-anon-types", cli.exclude_anon_types),
+            ("demangle-rust", cli.demangle_rust),
+            ("allow-zero-addresses", cli.allow_zero_addresses),
+            ("globals-only", cli.globals_only),
+            ("canonicalize-addresses", cli.canonicalize_addresses),
+            ("no-fixups", cli.no_fixups),
+            ("no-quirks", cli.no_quirks),
+            ("banner-raw", cli.banner_raw),
+        ];
+        let mut options: Vec<String> = flags
+            .into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(option, _)| option.to_owned())
+            .collect();
+        if let Some(compat) = cli.compat {
+            options.push(format!("compat={}", value_name(compat)));
+        }
+        if let Some(endian) = cli.endian {
+            options.push(format!("endian={}", endian));
+        }
+        if let Some(depth) = cli.max_type_depth {
+            options.push(format!("max-type-depth={}", depth));
+        }
+        if cli.dup_symbols != Default::default() {
+            options.push(format!("dup-symbols={}", value_name(cli.dup_symbols)));
+        }
+        if cli.address_overflow != Default::default() {
+            options.push(format!(
+                "address-overflow={}",
+                value_name(cli.address_overflow)
+            ));
+        }
+        if let Some(quirks) = &cli.quirks {
+            let quirks: Vec<_> = quirks.iter().copied().map(value_name).collect();
+            options.push(format!("quirks={}", quirks.join(",")));
+        }
+        if cli.image_format != Default::default() {
+            options.push(format!("image-format={}", value_name(cli.image_format)));
+        }
+        if let (Some(phys_base), Some(virt_base)) = (cli.phys_base, cli.kernel_virt_base) {
+            options.push(format!("phys-base={:#x}", phys_base));
+            options.push(format!("kernel-virt-base={:#x}", virt_base));
+        }
+        for (section, addr) in &cli.module_base {
+            options.push(format!("module-base={}={:#x}", section, addr));
+        }
+        if let Some(version) = cli.kernel_version {
+            options.push(format!("kernel-version={}", version));
+        }
+        if let Some(constant_data) = &cli.constant_data {
+            options.push(format!("constant-data={}", constant_data.join(",")));
+        }
+
+        Self {
+            inputs,
+            arch: value_name(arch),
+            anchor: symbols.anchor().map(str::to_owned),
+            base_offset: symbols.base_offset(),
+            filters,
+            options,
+        }
+    }
+}
+
+/// Returns the name of `value` on the command line.
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_owned())
+        .unwrap_or_default()
+}
+
+impl fmt::Display for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "arch {}", self.arch)?;
+        match (&self.anchor, self.base_offset) {
+            (Some(anchor), Some(base_offset)) => {
+                write!(f, ", rebased {} to {:#x}", anchor, base_offset)?
+            }
+            (None, Some(base_offset)) => write!(f, ", rebased to {:#x}", base_offset)?,
+            _ => write!(f, ", not rebased")?,
+        }
+        for (option, value) in self.inputs.iter().chain(&self.filters) {
+            write!(f, ", --{} {}", option, value)?;
+        }
+        for option in &self.options {
+            write!(f, ", --{}", option)?;
+        }
+        Ok(())
+    }
+}
 
 /// ISF file format version.
-#[derive(Serialize)]
-struct Format(&'static str);
+#[derive(Serialize, Deserialize)]
+struct Format(String);
 
-const FORMAT_VERSION: &str = "6.2.0";
-const FORMAT: Format = Format(FORMAT_VERSION);
+/// ISF format versions that can be generated.
+///
+/// Only the differences between the versions that matter for Linux profiles
+/// are implemented, and only 6.2.0 profiles can be validated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum FormatVersion {
+    /// Format of older Volatility 3 releases, without the linkage of symbols
+    /// and the sources in the metadata.
+    #[value(name = "4.1.0")]
+    V4_1_0,
+    #[default]
+    #[value(name = "6.2.0")]
+    V6_2_0,
+}
+
+impl FormatVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V4_1_0 => "4.1.0",
+            Self::V6_2_0 => "6.2.0",
+        }
+    }
+
+    /// Returns true iff symbols have a `linkage`.
+    pub fn has_linkage(self) -> bool {
+        self >= Self::V6_2_0
+    }
+
+    /// Returns true iff the metadata lists the sources of the ISF file, and
+    /// the information about the kernel build.
+    pub fn has_sources(self) -> bool {
+        self >= Self::V6_2_0
+    }
+}
 
 /// Metadata for Linux profiles.
 ///
-/// Sources used to generate the contained type and symbol information.
-#[derive(Serialize)]
+/// Sources used to generate the contained type and symbol information, and
+/// information about the kernel build.
+#[derive(Serialize, Deserialize)]
 struct Linux {
     symbols: Vec<Symbol>,
     types: Vec<Type>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<Module>,
+    #[serde(flatten)]
+    build: Build,
 }
 
 impl From<MetadataBuilder> for Linux {
     fn from(ctx: MetadataBuilder) -> Self {
-        let types = vec![ctx.btf.into()];
-        let mut symbols = Vec::new();
-
-        if let Some(map) = ctx.map {
-            symbols.push(map.into());
+        let mut types = vec![ctx.btf.into()];
+        if let Some(base_btf) = ctx.base_btf {
+            types.push(base_btf.into());
         }
+        let mut symbols: Vec<Symbol> = ctx.maps.into_iter().map(Source::from).collect();
 
         if let Some(symdb) = ctx.symdb {
             symbols.push(symdb.into());
         }
 
-        Self { types, symbols }
+        Self {
+            types,
+            symbols,
+            module: ctx.module,
+            build: ctx.build,
+        }
+    }
+}
+
+/// Kernel module that the profile is generated for, see `--module`.
+#[derive(Serialize, Deserialize, Debug)]
+struct Module {
+    name: String,
+    addresses: ModuleAddresses,
+    /// Sections of the module symbols, by name.
+    sections: BTreeMap<String, ModuleSection>,
+}
+
+impl Module {
+    fn new(module: &symbols::Module, syms: &Symbols) -> Self {
+        let mut sections: BTreeMap<String, ModuleSection> = module
+            .bases
+            .iter()
+            .map(|(name, addr)| {
+                let section = ModuleSection {
+                    address: Some(*addr),
+                    symbols: Vec::new(),
+                };
+                (name.clone(), section)
+            })
+            .collect();
+        for (name, sym) in syms.iter() {
+            if let Some(section) = sym.section() {
+                let section = sections.entry(section.to_owned()).or_default();
+                section.symbols.push(name.to_owned());
+            }
+        }
+        sections
+            .values_mut()
+            .for_each(|section| section.symbols.sort_unstable());
+        Self {
+            name: module.name.clone(),
+            addresses: if module.bases.is_empty() {
+                ModuleAddresses::SectionRelative
+            } else {
+                ModuleAddresses::Absolute
+            },
+            sections,
+        }
+    }
+}
+
+/// What the addresses of the symbols of a kernel module are.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum ModuleAddresses {
+    /// Load addresses, from `--module-base`.
+    Absolute,
+    /// Offsets into the sections of the symbols.
+    SectionRelative,
+}
+
+/// Section of a kernel module.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct ModuleSection {
+    /// Load address, if given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<u64>,
+    /// Names of the symbols in the section.
+    symbols: Vec<String>,
+}
+
+/// Date in the version string of the banner, which is `date` by default, but
+/// can be anything with `KBUILD_BUILD_TIMESTAMP`. Usual values are matched.
+static BUILD_DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(Mon|Tue|Wed|Thu|Fri|Sat|Sun) (Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec) +",
+        r"\d{1,2} \d{2}:\d{2}:\d{2}( \S+)? \d{4}",
+        r"|\d{4}-\d{2}-\d{2}",
+        r"|@\d+",
+    ))
+    .unwrap()
+});
+
+/// Information about the kernel build, parsed from the banner.
+///
+/// The banner has the form `Linux version <release> (<user>@<host>)
+/// (<compiler>, <linker>) #<build> [flags] <date>`, but distributions and
+/// older kernels vary it. Parts that cannot be parsed are absent.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct Build {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_date: Option<String>,
+}
+
+impl Build {
+    pub fn from_banner(banner: &str) -> Self {
+        let Some(rest) = banner.trim_end().strip_prefix(BANNER_PREFIX) else {
+            return Self::default();
+        };
+        let (release, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        // The groups in parentheses before the build number, the first is
+        // usually the builder.
+        let (mut groups, mut depth, mut start) = (Vec::new(), 0, 0);
+        let mut version = "";
+        for (idx, c) in rest.char_indices() {
+            match c {
+                '(' if depth == 0 => (depth, start) = (1, idx + 1),
+                '(' => depth += 1,
+                ')' if depth == 1 => {
+                    groups.push(&rest[start..idx]);
+                    depth = 0;
+                }
+                ')' if depth > 1 => depth -= 1,
+                '#' if depth == 0 => {
+                    version = &rest[idx..];
+                    break;
+                }
+                _ => (),
+            }
+        }
+        let is_builder = |group: &&str| group.contains('@') && !group.contains(' ');
+        let compiler = groups
+            .into_iter()
+            .find(|group| !is_builder(group))
+            .map(Build::first_of_list);
+
+        Self {
+            kernel_version: Some(release.to_owned()).filter(|r| !r.is_empty()),
+            compiler: compiler.map(str::to_owned).filter(|c| !c.is_empty()),
+            build_date: BUILD_DATE.find(version).map(|m| m.as_str().to_owned()),
+        }
+    }
+
+    /// Returns the first element of the comma separated `list`, ignoring commas
+    /// in parentheses.
+    fn first_of_list(list: &str) -> &str {
+        let mut depth = 0;
+        for (idx, c) in list.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => return &list[..idx],
+                _ => (),
+            }
+        }
+        list
     }
 }
 
@@ -130,64 +625,40 @@ type Symbol = Source;
 type Type = Source;
 
 /// Sources that can be used to generate (parts of) components of an ISF file.
-#[derive(Serialize)]
+///
+/// `Dwarf` is never produced by this tool, but occurs in profiles generated by
+/// dwarf2json.
+#[derive(Serialize, Deserialize, Debug)]
 enum SourceKind {
+    #[serde(rename = "dwarf")]
+    Dwarf,
     #[serde(rename = "symdb")]
     Symdb,
     #[serde(rename = "btf")]
     Btf,
     #[serde(rename = "system-map")]
     SystemMap,
-}
-
-impl SourceKind {
-    // Hack to use const generics to encode enum variants.
-    const fn from_u32(desc: u32) -> Self {
-        if desc == Self::Symdb as u32 {
-            Self::Symdb
-        } else if desc == Self::Btf as u32 {
-            Self::Btf
-        } else if desc == Self::SystemMap as u32 {
-            Self::SystemMap
-        } else {
-            panic!("BUG")
-        }
-    }
+    #[serde(rename = "symtab")]
+    Symtab,
 }
 
 /// Metadata for a concrete source used to generate (part of) a component of an
 /// ISF file.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct Source {
     kind: SourceKind,
     name: String,
-    hash_type: &'static str,
+    hash_type: String,
     hash_value: String,
 }
 
-// TODO: Remove duplication.
-impl<T: AsRef<[u8]>, U: ToString, const K: u32> From<CompMeta<Rc<T>, U, K>> for Source {
-    fn from(meta: CompMeta<Rc<T>, U, K>) -> Self {
-        let mut hash = Sha256::new();
-        hash.input(meta.raw.as_ref().as_ref());
-        Self {
-            kind: SourceKind::from_u32(K),
-            name: meta.name.to_string(),
-            hash_type: "sha256",
-            hash_value: hash.result_str(),
-        }
-    }
-}
-
-impl<U: ToString, const K: u32> From<CompMeta<&[u8], U, K>> for Source {
-    fn from(meta: CompMeta<&[u8], U, K>) -> Self {
-        let mut hash = Sha256::new();
-        hash.input(meta.raw);
+impl From<CompMeta> for Source {
+    fn from(meta: CompMeta) -> Self {
         Self {
-            kind: SourceKind::from_u32(K),
-            name: meta.name.to_string(),
-            hash_type: "sha256",
-            hash_value: hash.result_str(),
+            kind: meta.kind,
+            name: meta.name,
+            hash_type: String::from("sha256"),
+            hash_value: meta.hash_value,
         }
     }
 }
@@ -0,0 +1,186 @@
+//! Generation of placeholder names.
+//!
+//! Entities without a name, or whose name is ambiguous, get a generated
+//! placeholder name. All placeholders are generated here so that they never
+//! shadow a real name: if a placeholder is already taken, a numeric suffix is
+//! appended until it is free. Real names thus always win over placeholders.
+
+use std::fmt::Display;
+
+/// Returns a placeholder name for an anonymous type of `kind` with `id`.
+///
+/// `is_taken` reports whether a real type already uses a name.
+pub fn unnamed_type(kind: &str, id: impl Display, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("unnamed_{}_{}", kind, id), is_taken)
+}
+
+/// Returns the placeholder name that dwarf2json uses for the `n`-th anonymous
+/// type.
+///
+/// `is_taken` reports whether a real type already uses a name.
+pub fn dwarf2json_unnamed_type(n: usize, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("__unnamed_{}", n), is_taken)
+}
+
+/// Returns a placeholder name for an anonymous type of `kind` whose content
+/// hashes to `hash`.
+///
+/// `is_taken` reports whether a real type or another placeholder already uses
+/// a name.
+pub fn stable_unnamed_type(kind: &str, hash: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("unnamed_{}_{}", kind, hash), is_taken)
+}
+
+/// Returns true iff the user type `name` is the placeholder of an anonymous
+/// struct or union, as returned by [`unnamed_type`],
+/// [`dwarf2json_unnamed_type`], or [`stable_unnamed_type`].
+pub fn is_unnamed_user_type(name: &str) -> bool {
+    name.starts_with("unnamed_struct_")
+        || name.starts_with("unnamed_union_")
+        || name.starts_with("__unnamed_")
+}
+
+/// Returns the name under which the type with `id` is kept if its definition
+/// conflicts with another type of the same `name`.
+///
+/// `is_taken` reports whether a real type already uses a name.
+pub fn duplicate_type(name: &str, id: impl Display, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("{}__btf{}", name, id), is_taken)
+}
+
+/// Returns the name of the type `name` of the kernel module `module` if the
+/// kernel has a different type of the same name.
+///
+/// `is_taken` reports whether a real type already uses a name.
+pub fn module_type(module: &str, name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("{}::{}", module, name), is_taken)
+}
+
+/// Returns a placeholder name for the anonymous member at index `idx`.
+///
+/// `is_taken` reports whether a real member of the same type already uses a
+/// name.
+pub fn unnamed_member(idx: u64, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("unnamed_member_{}", idx), is_taken)
+}
+
+/// Returns the placeholder name that dwarf2json uses for an anonymous member at
+/// byte `offset`.
+///
+/// `nth` counts the preceding anonymous members at the same offset, and
+/// `is_taken` reports whether a real member of the same type already uses a
+/// name.
+pub fn dwarf2json_unnamed_member(
+    offset: u64,
+    nth: usize,
+    is_taken: impl Fn(&str) -> bool,
+) -> String {
+    let candidate = match nth {
+        0 => format!("unnamed_field_{:x}", offset),
+        _ => format!("unnamed_field_{:x}_{}", offset, nth),
+    };
+    disambiguate(candidate, is_taken)
+}
+
+/// Returns a placeholder name for the anonymous constant at index `idx` of the
+/// enum with `id`.
+///
+/// `is_taken` reports whether a real constant of the same enum already uses a
+/// name.
+pub fn unnamed_constant(id: impl Display, idx: usize, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("unnamed_constant_{}_{}", id, idx), is_taken)
+}
+
+/// Returns the name of the `n`-th duplicate of the symbol `name`.
+///
+/// `is_taken` reports whether a real symbol already uses a name.
+pub fn suffixed_symbol(name: &str, n: usize, is_taken: impl Fn(&str) -> bool) -> String {
+    disambiguate(format!("{}__{}", name, n), is_taken)
+}
+
+/// Returns `candidate` if it is not taken, and otherwise `candidate` with the
+/// first free numeric suffix.
+fn disambiguate(candidate: String, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(&candidate) {
+        return candidate;
+    }
+    let name = (1..)
+        .map(|n| format!("{}_{}", candidate, n))
+        .find(|name| !is_taken(name))
+        .expect("BUG: ran out of suffixes");
+    log::info!(
+        "Placeholder {} collides with a real name, using {}",
+        candidate,
+        name
+    );
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a predicate that reports `taken` as taken.
+    fn taken<'a>(taken: &'a [&str]) -> impl Fn(&str) -> bool + 'a {
+        move |name| taken.contains(&name)
+    }
+
+    #[test]
+    fn free_placeholders() {
+        assert_eq!(unnamed_type("struct", 7, taken(&[])), "unnamed_struct_7");
+        assert_eq!(unnamed_member(3, taken(&[])), "unnamed_member_3");
+        assert_eq!(duplicate_type("file", 42, taken(&[])), "file__btf42");
+        assert_eq!(module_type("foo", "file", taken(&[])), "foo::file");
+        assert_eq!(dwarf2json_unnamed_type(5, taken(&[])), "__unnamed_5");
+        assert_eq!(
+            dwarf2json_unnamed_member(0x18, 0, taken(&[])),
+            "unnamed_field_18"
+        );
+        assert_eq!(
+            dwarf2json_unnamed_member(0x18, 2, taken(&[])),
+            "unnamed_field_18_2"
+        );
+    }
+
+    #[test]
+    fn taken_placeholders() {
+        assert_eq!(
+            unnamed_type("struct", 7, taken(&["unnamed_struct_7"])),
+            "unnamed_struct_7_1"
+        );
+        assert_eq!(
+            unnamed_member(3, taken(&["unnamed_member_3", "unnamed_member_3_1"])),
+            "unnamed_member_3_2"
+        );
+        assert_eq!(
+            duplicate_type("file", 42, taken(&["file__btf42"])),
+            "file__btf42_1"
+        );
+        assert_eq!(
+            module_type("foo", "file", taken(&["foo::file"])),
+            "foo::file_1"
+        );
+        assert_eq!(
+            dwarf2json_unnamed_type(5, taken(&["__unnamed_5"])),
+            "__unnamed_5_1"
+        );
+        assert_eq!(
+            dwarf2json_unnamed_member(0x18, 0, taken(&["unnamed_field_18"])),
+            "unnamed_field_18_1"
+        );
+        // Placeholders with a suffix of their own get another one.
+        assert_eq!(
+            dwarf2json_unnamed_member(0x18, 1, taken(&["unnamed_field_18_1"])),
+            "unnamed_field_18_1_1"
+        );
+    }
+
+    #[test]
+    fn unnamed_user_types() {
+        assert!(is_unnamed_user_type("unnamed_struct_7"));
+        assert!(is_unnamed_user_type("unnamed_union_7_1"));
+        assert!(is_unnamed_user_type("__unnamed_5"));
+        assert!(!is_unnamed_user_type("unnamed_enum_7"));
+        assert!(!is_unnamed_user_type("task_struct"));
+    }
+}
@@ -0,0 +1,283 @@
+//! Offsets of selected members of structs and unions, as printed by
+//! `btf2json offsets`, which are looked up in the BTF without generating an
+//! ISF file.
+
+use crate::arch;
+use crate::btf::{Btf, TypeEx};
+use crate::cli::{OffsetsArgs, OffsetsFormat};
+
+use std::fmt::Write as _;
+
+use anyhow::{bail, Context, Error, Result};
+use serde::Serialize;
+
+/// Result of looking up one member.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The member as given, e.g., `task_struct.comm`.
+    pub field: String,
+    /// Offset in bytes from the start of the outermost type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    /// Size in bytes, unknown for bitfields and for pointers if the
+    /// architecture is unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// C-like name of the type of the member, e.g., `char[16]`.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_name: Option<String>,
+    /// Why the lookup failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Results of the lookups, in the order of the fields.
+pub struct Offsets(Vec<Entry>);
+
+impl TryFrom<&OffsetsArgs> for Offsets {
+    type Error = Error;
+
+    /// Looks up the fields of `args` in the BTF of its inputs.
+    fn try_from(args: &OffsetsArgs) -> Result<Self> {
+        let mut btf = Btf::try_from(&args.generate)?;
+        if let Some(depth) = args.generate.max_type_depth {
+            btf.set_max_type_depth(depth);
+        }
+        let pointer_size = match arch::detect(&args.generate) {
+            Ok(arch) => Some(arch.pointer_size() as u64),
+            Err(err) => {
+                log::warn!("{:#}, sizes of pointers are unknown", err);
+                None
+            }
+        };
+        Ok(Self::lookup(&btf, &args.fields, pointer_size))
+    }
+}
+
+impl Offsets {
+    /// Looks up each of `fields`, which are a type name followed by member
+    /// names, separated by dots.
+    ///
+    /// Pointers have `pointer_size` bytes. A failed lookup does not stop the
+    /// others.
+    pub fn lookup(btf: &Btf, fields: &[String], pointer_size: Option<u64>) -> Self {
+        Self(
+            fields
+                .iter()
+                .map(|field| match lookup(btf, field, pointer_size) {
+                    Ok(entry) => entry,
+                    Err(err) => Entry {
+                        field: field.clone(),
+                        offset: None,
+                        size: None,
+                        type_name: None,
+                        error: Some(format!("{:#}", err)),
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.0
+    }
+
+    /// Returns the number of failed lookups.
+    pub fn failures(&self) -> usize {
+        self.0.iter().filter(|entry| entry.error.is_some()).count()
+    }
+
+    pub fn format(&self, format: OffsetsFormat) -> Result<String> {
+        match format {
+            OffsetsFormat::Json => Ok(serde_json::to_string_pretty(&self.0)? + "\n"),
+            OffsetsFormat::Csv => Ok(self.csv()),
+        }
+    }
+
+    fn csv(&self) -> String {
+        let mut csv = String::from("field,offset,size,type,error\n");
+        let opt = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        for entry in &self.0 {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                csv_field(&entry.field),
+                opt(entry.offset),
+                opt(entry.size),
+                csv_field(entry.type_name.as_deref().unwrap_or_default()),
+                csv_field(entry.error.as_deref().unwrap_or_default())
+            );
+        }
+        csv
+    }
+}
+
+/// Quotes `s` if it has a comma, a quote, or a line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn lookup(btf: &Btf, field: &str, pointer_size: Option<u64>) -> Result<Entry> {
+    let mut path = field.split('.');
+    let type_name = path.next().unwrap_or_default();
+    let members: Vec<&str> = path.collect();
+    if type_name.is_empty() || members.is_empty() || members.iter().any(|m| m.is_empty()) {
+        bail!("expected <type>.<member>");
+    }
+    let mut tx =
+        find_type(btf, type_name).with_context(|| format!("no struct or union `{}`", type_name))?;
+    let mut offset = 0;
+    let mut bits = None;
+    let mut parent = type_name.to_string();
+    for name in members {
+        let outer = btf.resolve_type_chain(tx).tx;
+        if outer.t.as_has_members().is_none() {
+            bail!("`{}` is no struct or union", parent);
+        }
+        let (member_offset, member_tx, member_bits) = find_member(btf, &outer, name, 0)?
+            .with_context(|| format!("no member `{}` in `{}`", name, parent))?;
+        offset += member_offset;
+        tx = member_tx;
+        bits = member_bits;
+        parent = format!("{}.{}", parent, name);
+    }
+    let type_name = c_name(btf, &tx, 0)?;
+    Ok(Entry {
+        field: field.to_string(),
+        offset: Some(offset),
+        size: match bits {
+            Some(_) => None,
+            None => size_of(btf, &tx, pointer_size, 0)?,
+        },
+        type_name: Some(match bits {
+            Some(bits) => format!("{}:{}", type_name, bits),
+            None => type_name,
+        }),
+        error: None,
+    })
+}
+
+/// Returns the struct or union called `name`, or the one that the typedef
+/// `name` is of.
+fn find_type(btf: &Btf, name: &str) -> Option<TypeEx> {
+    let candidates = btf.find_by_name(name);
+    candidates
+        .iter()
+        .find(|tx| tx.t.as_has_members().is_some())
+        .or_else(|| {
+            candidates.iter().find(|tx| {
+                let root = btf.resolve_type_chain((*tx).clone()).tx;
+                root.t.as_has_members().is_some()
+            })
+        })
+        .cloned()
+}
+
+/// Returns the offset in bytes, the type, and the bitfield length of the
+/// member `name` of `tx`, searching anonymous members recursively.
+///
+/// Like the other recursions of this module, `depth` is limited by
+/// [`Btf::max_type_depth`], which only corrupted BTF exceeds with a cycle.
+fn find_member(
+    btf: &Btf,
+    tx: &TypeEx,
+    name: &str,
+    depth: usize,
+) -> Result<Option<(u64, TypeEx, Option<u8>)>> {
+    if depth > btf.max_type_depth() {
+        bail!("[{}] type nested too deeply", tx.id);
+    }
+    let Some(members) = tx.t.as_has_members().map(|t| t.members(btf)) else {
+        return Ok(None);
+    };
+    for m in &members {
+        if m.is_anon() == Some(true) {
+            let inner = btf.resolve_type_chain(m.get_tx(btf)?).tx;
+            if let Some((offset, tx, bits)) = find_member(btf, &inner, name, depth + 1)? {
+                return Ok(Some((m.byte_offset() + offset, tx, bits)));
+            }
+        } else if *m.name(btf) == *name {
+            let bits = m
+                .is_bitfield()
+                .then(|| m.bitfield_info().map(|info| info.length))
+                .flatten();
+            return Ok(Some((m.byte_offset(), m.get_tx(btf)?, bits)));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the type that `tx` refers to, e.g., the pointee of a pointer.
+fn referenced(btf: &Btf, tx: &TypeEx) -> Result<TypeEx> {
+    let raw =
+        tx.t.t
+            .as_btf_type()
+            .and_then(|t| t.get_type_id().ok())
+            .with_context(|| format!("[{}] type without referenced type", tx.id))?;
+    let id = btf.id(raw)?;
+    Ok(TypeEx {
+        t: btf.get_type_by_id(id)?,
+        id,
+    })
+}
+
+/// Returns the C-like name of `tx`, without qualifiers.
+fn c_name(btf: &Btf, tx: &TypeEx, depth: usize) -> Result<String> {
+    if depth > btf.max_type_depth() {
+        bail!("[{}] type nested too deeply", tx.id);
+    }
+    let own = || {
+        btf.get_strtab_entry_by_id(tx.id)
+            .map(|name| name.to_string())
+    };
+    Ok(match &tx.t.t {
+        btf_rs::Type::Void => String::from("void"),
+        btf_rs::Type::Ptr(_) => format!("{} *", c_name(btf, &referenced(btf, tx)?, depth + 1)?),
+        btf_rs::Type::Array(arr) => format!(
+            "{}[{}]",
+            c_name(btf, &referenced(btf, tx)?, depth + 1)?,
+            arr.len()
+        ),
+        btf_rs::Type::Const(_)
+        | btf_rs::Type::Volatile(_)
+        | btf_rs::Type::Restrict(_)
+        | btf_rs::Type::TypeTag(_) => c_name(btf, &referenced(btf, tx)?, depth + 1)?,
+        btf_rs::Type::FuncProto(_) => String::from("function"),
+        btf_rs::Type::Struct(_) => format!("struct {}", own().unwrap_or_default()),
+        btf_rs::Type::Union(_) => format!("union {}", own().unwrap_or_default()),
+        btf_rs::Type::Enum(_) | btf_rs::Type::Enum64(_) => {
+            format!("enum {}", own().unwrap_or_default())
+        }
+        btf_rs::Type::Fwd(_) if tx.t.is_fwd_union() => format!("union {}", own()?),
+        btf_rs::Type::Fwd(_) => format!("struct {}", own()?),
+        _ => own()?,
+    }
+    .trim_end()
+    .to_string())
+}
+
+/// Returns the size of `tx` in bytes, or `None` if it is unknown.
+fn size_of(btf: &Btf, tx: &TypeEx, pointer_size: Option<u64>, depth: usize) -> Result<Option<u64>> {
+    if depth > btf.max_type_depth() {
+        bail!("[{}] type nested too deeply", tx.id);
+    }
+    Ok(match &tx.t.t {
+        btf_rs::Type::Ptr(_) => pointer_size,
+        btf_rs::Type::Array(arr) => size_of(btf, &referenced(btf, tx)?, pointer_size, depth + 1)?
+            .map(|size| {
+                size.checked_mul(arr.len() as u64)
+                    .with_context(|| format!("[{}] array size overflows", tx.id))
+            })
+            .transpose()?,
+        btf_rs::Type::Typedef(_)
+        | btf_rs::Type::Const(_)
+        | btf_rs::Type::Volatile(_)
+        | btf_rs::Type::Restrict(_)
+        | btf_rs::Type::TypeTag(_) => size_of(btf, &referenced(btf, tx)?, pointer_size, depth + 1)?,
+        _ => tx.t.size().map(|size| size as u64),
+    })
+}
@@ -0,0 +1,208 @@
+//! Writing ISF files to disk.
+
+use crate::cli::Compression;
+use crate::isf::Isf;
+use crate::symbols::Banner;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use flate2::write::GzEncoder;
+use serde_json::{json, Map, Value};
+#[cfg(feature = "xz")]
+use xz2::write::XzEncoder;
+
+#[cfg(feature = "xz")]
+const XZ_LEVEL: u32 = 6;
+/// Maximum length of a file name on common file systems.
+const NAME_MAX: usize = 255;
+/// Version of the banners index format that Volatility 3 reads.
+const BANNERS_INDEX_VERSION: u64 = 1;
+
+impl Compression {
+    /// Returns the compression implied by the extension of `path`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gz,
+            Some("xz") => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Writes `isf` to the file at `path`, compressed with `compression` or, if
+/// `None`, the compression implied by the extension.
+///
+/// The ISF file is written to a temporary file next to `path` that is only
+/// moved into place once everything succeeded.
+pub fn write(isf: &Isf, path: &Path, compression: Option<Compression>) -> Result<()> {
+    let compression = compression.unwrap_or_else(|| Compression::from_path(path));
+    log::debug!("Writing ISF file to {} ({:?})", path.display(), compression);
+
+    let tmp_path = tmp_path(path)?;
+    match write_compressed(isf, &tmp_path, compression) {
+        Ok(()) => fs::rename(&tmp_path, path).with_context(|| {
+            format!(
+                "Unable to move {} to {}",
+                tmp_path.display(),
+                path.display()
+            )
+        }),
+        Err(err) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(err)
+        }
+    }
+}
+
+/// Returns the path of the provenance file of the ISF file at `path`, see
+/// `--annotate-btf-ids`.
+pub fn provenance_path(path: &Path) -> PathBuf {
+    let mut provenance = path.as_os_str().to_owned();
+    provenance.push(".provenance.json");
+    PathBuf::from(provenance)
+}
+
+/// Returns the path of the ISF file for `banner` in the directory `dir`, which
+/// is created if needed.
+///
+/// Fails if the file exists, unless `force` is true.
+pub fn banner_path(dir: &Path, banner: &Banner, force: bool) -> Result<PathBuf> {
+    let name = banner.isf_file_name();
+    if name.len() > NAME_MAX {
+        bail!(
+            "File name for banner {:?} exceeds {} bytes",
+            banner.to_string(),
+            NAME_MAX
+        );
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Unable to create {}", dir.display()))?;
+    let path = dir.join(name);
+    if !force && path.exists() {
+        bail!(
+            "{} already exists for this banner, use --force to replace it",
+            path.display()
+        );
+    }
+    Ok(path)
+}
+
+/// Adds the ISF file at `path` to the banners index at `index`, which maps the
+/// base64 banners to the ISF files of the kernels, as Volatility 3 reads them
+/// with `--remote-isf-url`:
+///
+/// ```json
+/// {"version": 1, "linux": {"TGludXggdmVyc2lvbiA2LjE4LjAK": ["<name>.json.xz"]}}
+/// ```
+///
+/// The banner is the `constant_data` of `linux_banner` in `isf`, so that it
+/// matches the banner that Volatility looks up. Files in the directory of the
+/// index, or below, are added by their relative path, others as given. The
+/// index is created if needed, entries that it already has are kept, and it is
+/// replaced as a whole under a lock, so that concurrent updates are merged.
+pub fn update_banners_index(index: &Path, isf: &Isf, path: &Path) -> Result<()> {
+    let banner = isf
+        .get_symbol("linux_banner")
+        .and_then(|sym| sym.constant_data())
+        .context("ISF file has no linux_banner constant data for the banners index")?;
+    let dir = index.parent().unwrap_or(Path::new(""));
+    let entry = path
+        .strip_prefix(dir)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned();
+
+    let lock_path = index.with_file_name(format!(
+        ".{}.lock",
+        index
+            .file_name()
+            .with_context(|| format!("Index path {} has no file name", index.display()))?
+            .to_string_lossy()
+    ));
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Unable to create {}", lock_path.display()))?;
+    lock.lock()
+        .with_context(|| format!("Unable to lock {}", lock_path.display()))?;
+
+    let mut contents = match fs::read(index) {
+        Ok(raw) => serde_json::from_slice::<Value>(&raw)
+            .ok()
+            .filter(|contents| contents["version"] == BANNERS_INDEX_VERSION)
+            .with_context(|| format!("Invalid banners index {}", index.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            json!({"version": BANNERS_INDEX_VERSION})
+        }
+        Err(err) => return Err(err).with_context(|| format!("Unable to read {}", index.display())),
+    };
+    let Some(Value::Object(linux)) = contents
+        .as_object_mut()
+        .map(|contents| contents.entry("linux").or_insert(Value::Object(Map::new())))
+    else {
+        bail!("Invalid banners index {}", index.display());
+    };
+    let Value::Array(files) = linux.entry(banner).or_insert(Value::Array(Vec::new())) else {
+        bail!("Invalid banners index {}", index.display());
+    };
+    if files.iter().any(|file| file.as_str() == Some(&entry)) {
+        return Ok(());
+    }
+    files.push(Value::String(entry));
+
+    let tmp_path = tmp_path(index)?;
+    let written = serde_json::to_vec_pretty(&contents)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| Ok(fs::write(&tmp_path, raw)?))
+        .with_context(|| format!("Unable to write {}", tmp_path.display()))
+        .and_then(|_| {
+            fs::rename(&tmp_path, index).with_context(|| {
+                format!(
+                    "Unable to move {} to {}",
+                    tmp_path.display(),
+                    index.display()
+                )
+            })
+        });
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    written
+}
+
+fn tmp_path(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("Output path {} has no file name", path.display()))?
+        .to_string_lossy();
+    Ok(path.with_file_name(format!(".{}.tmp-{}", name, std::process::id())))
+}
+
+fn write_compressed(isf: &Isf, path: &Path, compression: Compression) -> Result<()> {
+    let file = BufWriter::new(
+        File::create(path).with_context(|| format!("Unable to create {}", path.display()))?,
+    );
+    let written = match compression {
+        Compression::None => isf.dump_to(file),
+        Compression::Gz => {
+            let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+            isf.dump_to(&mut encoder)
+                .and_then(|_| Ok(encoder.finish()?.flush()?))
+        }
+        #[cfg(feature = "xz")]
+        Compression::Xz => {
+            let mut encoder = XzEncoder::new(file, XZ_LEVEL);
+            isf.dump_to(&mut encoder)
+                .and_then(|_| Ok(encoder.finish()?.flush()?))
+        }
+        #[cfg(not(feature = "xz"))]
+        Compression::Xz => Err(anyhow::anyhow!("xz compression requires the xz feature")),
+    };
+    written.with_context(|| format!("Unable to write {}", path.display()))
+}
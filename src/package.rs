@@ -0,0 +1,405 @@
+//! Extraction of vmlinux and System.map from kernel packages.
+//!
+//! Debian packages are `ar` archives with a `data.tar*` member, RPM packages
+//! have a lead and two headers followed by a cpio payload. Both payloads are
+//! decompressed and scanned as streams, only the files that are looked for are
+//! kept in memory.
+
+use crate::error;
+use crate::input::{self, InputData};
+
+use std::io::{self, Read};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use flate2::read::MultiGzDecoder;
+use ruzstd::decoding::StreamingDecoder;
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+
+/// Paths of vmlinux in packages, by decreasing preference. `*` matches a single
+/// path component or a part of it.
+pub const VMLINUX_PATHS: &[&str] = &[
+    "usr/lib/debug/boot/vmlinux-*",
+    "usr/lib/debug/lib/modules/*/vmlinux",
+    "usr/lib/debug/usr/lib/modules/*/vmlinux",
+    "boot/vmlinux-*",
+    "lib/modules/*/vmlinux",
+    "usr/lib/modules/*/vmlinux",
+    "boot/vmlinuz-*",
+    "lib/modules/*/vmlinuz",
+    "usr/lib/modules/*/vmlinuz",
+];
+
+/// Paths of System.map in packages, by decreasing preference.
+pub const SYSTEM_MAP_PATHS: &[&str] = &[
+    "boot/System.map-*",
+    "lib/modules/*/System.map",
+    "usr/lib/modules/*/System.map",
+];
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const AR_HEADER_LEN: usize = 60;
+const RPM_LEAD_MAGIC: &[u8] = &[0xed, 0xab, 0xee, 0xdb];
+const RPM_LEAD_LEN: usize = 96;
+const RPM_HEADER_MAGIC: &[u8] = &[0x8e, 0xad, 0xe8, 0x01];
+const RPM_HEADER_LEN: usize = 16;
+const TAR_BLOCK: usize = 512;
+const CPIO_NEWC_MAGIC: &[u8] = b"070701";
+const CPIO_CRC_MAGIC: &[u8] = b"070702";
+const CPIO_HEADER_LEN: usize = 110;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+/// Upper bound for the size of a file that is extracted.
+const MAX_FILE: u64 = 1 << 31;
+
+/// Files that were extracted from a package.
+#[derive(Debug)]
+pub struct Package {
+    /// File name and contents of vmlinux, which can also be compressed.
+    pub vmlinux: (String, Vec<u8>),
+    /// File name and contents of System.map, if the package has one.
+    pub system_map: Option<(String, Vec<u8>)>,
+}
+
+/// Index of the path pattern that a file matched, its name, and its contents.
+type Match = (usize, String, Vec<u8>);
+
+/// Best match of the files that are looked for so far.
+#[derive(Default)]
+struct Found {
+    vmlinux: Option<Match>,
+    system_map: Option<Match>,
+}
+
+impl Found {
+    /// Returns the slot and the pattern index if the file at `path` is better
+    /// than the one found so far.
+    fn slot(&mut self, path: &str) -> Option<(&mut Option<Match>, usize)> {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        [
+            (&mut self.vmlinux, VMLINUX_PATHS),
+            (&mut self.system_map, SYSTEM_MAP_PATHS),
+        ]
+        .into_iter()
+        .find_map(|(slot, patterns)| {
+            let idx = patterns.iter().position(|p| path_matches(p, path))?;
+            match slot {
+                Some((best, _, _)) if *best <= idx => None,
+                _ => Some((slot, idx)),
+            }
+        })
+    }
+
+    /// Reads the file at `path` with `size` bytes from `reader` if it is looked
+    /// for, skips it otherwise.
+    fn visit(&mut self, path: &str, size: u64, reader: &mut impl Read) -> Result<()> {
+        let Some((slot, idx)) = self.slot(path) else {
+            return skip(reader, size);
+        };
+        if size > MAX_FILE {
+            bail!("{} exceeds {} bytes", path, MAX_FILE);
+        }
+        let mut buf = Vec::new();
+        reader
+            .take(size)
+            .read_to_end(&mut buf)
+            .with_context(|| format!("Unable to read {}", path))?;
+        if buf.len() as u64 != size {
+            bail!("{} is truncated", path);
+        }
+        log::info!("Found {} in package, {} bytes", path, size);
+        let name = path.rsplit('/').next().unwrap_or(path).to_owned();
+        *slot = Some((idx, name, buf));
+        Ok(())
+    }
+}
+
+/// Extracts vmlinux and System.map from the `.deb` or `.rpm` package at
+/// `path`.
+pub fn extract(path: &Path) -> Result<Package> {
+    let raw = InputData::load(path)?;
+    let name = input::file_name(path)?;
+    let mut found = Found::default();
+
+    if raw.starts_with(AR_MAGIC) {
+        log::debug!("{} is a Debian package", name);
+        let data = deb_data(&raw).with_context(|| format!("Invalid Debian package {}", name))?;
+        read_tar(decoder(data)?, &mut found)
+    } else if raw.starts_with(RPM_LEAD_MAGIC) {
+        log::debug!("{} is an RPM package", name);
+        let payload = rpm_payload(&raw).with_context(|| format!("Invalid RPM package {}", name))?;
+        read_cpio(decoder(payload)?, &mut found)
+    } else {
+        bail!("{} is neither a Debian nor an RPM package", name)
+    }
+    .with_context(|| format!("Unable to read the files in {}", name))?;
+
+    let Some((_, vmlinux, data)) = found.vmlinux else {
+        return Err(error::Error::PackageLayout {
+            name,
+            tried: VMLINUX_PATHS,
+        }
+        .into());
+    };
+    if found.system_map.is_none() {
+        log::info!(
+            "No System.map in {}, tried {}",
+            name,
+            SYSTEM_MAP_PATHS.join(", ")
+        );
+    }
+    Ok(Package {
+        vmlinux: (vmlinux, data),
+        system_map: found.system_map.map(|(_, name, data)| (name, data)),
+    })
+}
+
+/// Returns true iff `path` matches `pattern`, component by component.
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.split('/');
+    let mut path = path.split('/');
+    loop {
+        match (pattern.next(), path.next()) {
+            (Some(p), Some(c)) if component_matches(p, c) => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Returns true iff the path component `name` matches `pattern`, in which `*`
+/// matches any sequence of characters.
+fn component_matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(name) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| component_matches(rest, &name[i..]))
+        }
+    }
+}
+
+/// Returns a reader for the decompressed `data`, whose format is detected by
+/// its magic.
+fn decoder<'a>(data: &'a [u8]) -> Result<Box<dyn Read + 'a>> {
+    Ok(if data.starts_with(&[0x1f, 0x8b]) {
+        Box::new(MultiGzDecoder::new(data))
+    } else if data.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]) {
+        xz_decoder(data)?
+    } else if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(StreamingDecoder::new(data).map_err(|err| anyhow::anyhow!("{}", err))?)
+    } else if data.starts_with(b"BZh") {
+        bail!("bzip2-compressed packages are not supported")
+    } else if data.starts_with(&[0x5d, 0x00, 0x00]) {
+        bail!("lzma-compressed packages are not supported")
+    } else {
+        Box::new(data)
+    })
+}
+
+#[cfg(feature = "xz")]
+fn xz_decoder(data: &[u8]) -> Result<Box<dyn Read + '_>> {
+    Ok(Box::new(XzDecoder::new_multi_decoder(data)))
+}
+
+#[cfg(not(feature = "xz"))]
+fn xz_decoder(_data: &[u8]) -> Result<Box<dyn Read + '_>> {
+    bail!("xz-compressed packages require the xz feature")
+}
+
+/// Skips `size` bytes of `reader`.
+fn skip(reader: &mut impl Read, size: u64) -> Result<()> {
+    let skipped = io::copy(&mut reader.take(size), &mut io::sink())?;
+    if skipped != size {
+        bail!("Truncated archive");
+    }
+    Ok(())
+}
+
+/// Returns the `data.tar*` member of the Debian package `raw`.
+fn deb_data(raw: &[u8]) -> Result<&[u8]> {
+    let mut off = AR_MAGIC.len();
+    while off + AR_HEADER_LEN <= raw.len() {
+        let hdr = &raw[off..off + AR_HEADER_LEN];
+        if &hdr[58..] != b"`\n" {
+            bail!("Invalid ar header at {:#x}", off);
+        }
+        let name = String::from_utf8_lossy(&hdr[..16]);
+        let name = name.trim_end().trim_end_matches('/');
+        let size: usize = std::str::from_utf8(&hdr[48..58])?
+            .trim_end()
+            .parse()
+            .with_context(|| format!("Invalid size of ar member {}", name))?;
+        let start = off + AR_HEADER_LEN;
+        let data = raw
+            .get(start..start.saturating_add(size))
+            .with_context(|| format!("ar member {} exceeds the file", name))?;
+        log::debug!("ar member {}, {} bytes", name, size);
+        if name.starts_with("data.tar") {
+            return Ok(data);
+        }
+        // Members are aligned to 2 bytes.
+        off = start + size + size % 2;
+    }
+    bail!("No data.tar member")
+}
+
+/// Returns the payload of the RPM package `raw`.
+fn rpm_payload(raw: &[u8]) -> Result<&[u8]> {
+    // The signature header is padded to 8 bytes, the main header is not.
+    let sig_len = rpm_header_len(raw, RPM_LEAD_LEN).context("Invalid signature header")?;
+    let main = RPM_LEAD_LEN + sig_len.next_multiple_of(8);
+    let main_len = rpm_header_len(raw, main).context("Invalid main header")?;
+    raw.get(main + main_len..)
+        .context("Main header exceeds the file")
+}
+
+/// Returns the length of the RPM header at `off` in `raw`.
+fn rpm_header_len(raw: &[u8], off: usize) -> Result<usize> {
+    let hdr = raw
+        .get(off..off + RPM_HEADER_LEN)
+        .context("Truncated header")?;
+    if &hdr[..4] != RPM_HEADER_MAGIC {
+        bail!("Invalid header magic at {:#x}", off);
+    }
+    let nindex = u32::from_be_bytes(hdr[8..12].try_into().unwrap()) as usize;
+    let hsize = u32::from_be_bytes(hdr[12..16].try_into().unwrap()) as usize;
+    Ok(RPM_HEADER_LEN + 16 * nindex + hsize)
+}
+
+/// Parses the octal or base-256 number in the tar header field `field`.
+fn tar_number(field: &[u8]) -> Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        let mut n = u64::from(field[0] & 0x7f);
+        for &b in &field[1..] {
+            n = n
+                .checked_mul(256)
+                .and_then(|n| n.checked_add(u64::from(b)))
+                .context("Tar number overflows")?;
+        }
+        return Ok(n);
+    }
+    let s = std::str::from_utf8(field)?.trim_matches(|c| c == ' ' || c == '\0');
+    if s.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(s, 8).with_context(|| format!("Invalid tar number {:?}", s))
+}
+
+/// Returns the NUL-terminated string in the tar header field `field`.
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Returns the `path` record of the pax extended header `data`, if any.
+fn pax_path(data: &[u8]) -> Option<String> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest.iter().position(|&b| b == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        let record = rest.get(space + 1..len)?.strip_suffix(b"\n")?;
+        if let Some(path) = record.strip_prefix(b"path=") {
+            return Some(String::from_utf8_lossy(path).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+/// Scans the tar archive `reader` for the files in `found`.
+fn read_tar(mut reader: impl Read, found: &mut Found) -> Result<()> {
+    let mut hdr = [0; TAR_BLOCK];
+    // Name of the next entry from a GNU long name or pax header.
+    let mut long_name = None;
+    loop {
+        reader
+            .read_exact(&mut hdr)
+            .context("Truncated tar archive")?;
+        if hdr.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        let size = tar_number(&hdr[124..136])?;
+        let padding = size.next_multiple_of(TAR_BLOCK as u64) - size;
+        let typeflag = hdr[156];
+        match typeflag {
+            b'L' | b'x' => {
+                if size > TAR_BLOCK as u64 * 64 {
+                    bail!("Tar extended header exceeds {} bytes", TAR_BLOCK * 64);
+                }
+                let mut data = vec![0; size as usize];
+                reader
+                    .read_exact(&mut data)
+                    .context("Truncated tar archive")?;
+                long_name = if typeflag == b'L' {
+                    Some(tar_string(&data))
+                } else {
+                    pax_path(&data)
+                };
+            }
+            b'0' | b'\0' | b'7' => {
+                let name = long_name.take().unwrap_or_else(|| {
+                    let name = tar_string(&hdr[..100]);
+                    // Only POSIX ustar has the prefix, GNU uses the field.
+                    if &hdr[257..265] != b"ustar\x0000" || hdr[345] == 0 {
+                        return name;
+                    }
+                    format!("{}/{}", tar_string(&hdr[345..500]), name)
+                });
+                found.visit(&name, size, &mut reader)?;
+            }
+            _ => {
+                long_name = None;
+                skip(&mut reader, size)?;
+            }
+        }
+        skip(&mut reader, padding)?;
+    }
+}
+
+/// Parses the hexadecimal field at `idx` of the cpio header `hdr`.
+fn cpio_field(hdr: &[u8], idx: usize) -> Result<u64> {
+    let field = &hdr[6 + 8 * idx..6 + 8 * (idx + 1)];
+    u64::from_str_radix(std::str::from_utf8(field)?, 16)
+        .with_context(|| format!("Invalid cpio header field {:?}", field))
+}
+
+/// Scans the cpio archive in the "new ASCII" format `reader` for the files in
+/// `found`.
+fn read_cpio(mut reader: impl Read, found: &mut Found) -> Result<()> {
+    let mut hdr = [0; CPIO_HEADER_LEN];
+    loop {
+        reader
+            .read_exact(&mut hdr)
+            .context("Truncated cpio archive")?;
+        if !hdr.starts_with(CPIO_NEWC_MAGIC) && !hdr.starts_with(CPIO_CRC_MAGIC) {
+            bail!("Unsupported cpio format, only the new ASCII format is supported");
+        }
+        let mode = cpio_field(&hdr, 1)?;
+        let size = cpio_field(&hdr, 6)?;
+        let namesize = cpio_field(&hdr, 11)? as usize;
+        if namesize > 4096 {
+            bail!("cpio file name exceeds 4096 bytes");
+        }
+        // The header and the name, as well as the data, are aligned to 4 bytes.
+        let mut name = vec![0; (CPIO_HEADER_LEN + namesize).next_multiple_of(4) - CPIO_HEADER_LEN];
+        reader
+            .read_exact(&mut name)
+            .context("Truncated cpio archive")?;
+        let name = tar_string(&name);
+        if name == CPIO_TRAILER {
+            return Ok(());
+        }
+        // Only regular files, which excludes symbolic links.
+        if mode & 0o170000 == 0o100000 {
+            found.visit(&name, size, &mut reader)?;
+        } else {
+            skip(&mut reader, size)?;
+        }
+        skip(&mut reader, size.next_multiple_of(4) - size)?;
+    }
+}
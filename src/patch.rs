@@ -0,0 +1,96 @@
+//! Overlays of `--patch`, which correct or extend a generated ISF file.
+//!
+//! An overlay has the shape of an ISF file with a subset of its sections,
+//! e.g.,
+//!
+//! ```json
+//! {
+//!   "user_types": {"vendor_ctx": {"kind": "struct", "size": 8, "fields": {}}},
+//!   "symbols": {"stale_symbol": null}
+//! }
+//! ```
+//!
+//! Its entries replace the ones of the same name, or are added, and `null`
+//! deletes an entry.
+
+use crate::v_symbols::Symbol;
+use crate::v_types::{Base, Enum, User};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Entries of a section, `None` for the ones that are deleted.
+pub type Entries<T> = BTreeMap<String, Option<T>>;
+
+/// Overlay that is merged into an ISF file, see [`crate::isf::Isf::patch`].
+#[derive(Default)]
+pub struct Patch {
+    pub user_types: Entries<User>,
+    pub enums: Entries<Enum>,
+    pub base_types: Entries<Base>,
+    pub symbols: Entries<Symbol>,
+}
+
+impl Patch {
+    /// Reads the overlay at `path`, which may be gzip or xz compressed like an
+    /// ISF file.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let value = crate::schema::read_isf(path)?;
+        Self::from_value(value).with_context(|| format!("Invalid patch {}", path.display()))
+    }
+
+    /// Checks the entries of the overlay `value` against the ISF type model.
+    ///
+    /// Errors name the offending entry, e.g., `user_types.task_struct`.
+    pub fn from_value(value: Value) -> Result<Self> {
+        let Value::Object(sections) = value else {
+            bail!("expected an object of ISF sections");
+        };
+        let mut patch = Patch::default();
+        for (section, entries) in sections {
+            match section.as_str() {
+                "user_types" => patch.user_types = entries_of(&section, entries)?,
+                "enums" => patch.enums = entries_of(&section, entries)?,
+                "base_types" => patch.base_types = entries_of(&section, entries)?,
+                "symbols" => patch.symbols = entries_of(&section, entries)?,
+                _ => bail!(
+                    "{}: unknown section, expected user_types, enums, base_types, or symbols",
+                    section
+                ),
+            }
+        }
+        Ok(patch)
+    }
+
+    /// Returns the number of entries that are added, replaced, or deleted.
+    pub fn len(&self) -> usize {
+        self.user_types.len() + self.enums.len() + self.base_types.len() + self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn entries_of<T: DeserializeOwned>(section: &str, entries: Value) -> Result<Entries<T>> {
+    let Value::Object(entries) = entries else {
+        bail!("{}: expected an object of entries", section);
+    };
+    entries
+        .into_iter()
+        .map(|(name, entry)| {
+            let entry = match entry {
+                Value::Null => None,
+                entry => Some(
+                    serde_json::from_value(entry)
+                        .with_context(|| format!("{}.{}", section, name))?,
+                ),
+            };
+            Ok((name, entry))
+        })
+        .collect()
+}
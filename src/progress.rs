@@ -0,0 +1,166 @@
+//! Progress of the major phases of the generation.
+//!
+//! Users of the library subscribe to the progress by implementing [`Progress`],
+//! `--progress` reports it on standard error with [`StderrProgress`].
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Time between two updates of the progress on a terminal.
+const TTY_INTERVAL: Duration = Duration::from_millis(100);
+/// Time between two progress lines if standard error is not a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Major phase of the generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Sorting the BTF types by kind, with one step per type.
+    ScanTypes,
+    /// Reading the symbol sources and typing the symbols.
+    Symbols,
+    /// Constructing the user types, with one step per BTF type of a user type.
+    UserTypes,
+    /// Writing the ISF file.
+    Serialize,
+}
+
+impl Phase {
+    /// Returns the stable identifier of the phase, e.g., `btf-scan`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Phase::ScanTypes => "btf-scan",
+            Phase::Symbols => "symbols",
+            Phase::UserTypes => "types",
+            Phase::Serialize => "serialize",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Phase::ScanTypes => "scanning BTF types",
+            Phase::Symbols => "building symbols",
+            Phase::UserTypes => "constructing user types",
+            Phase::Serialize => "serializing",
+        })
+    }
+}
+
+/// Receiver of the progress of the generation.
+///
+/// Phases are started and finished in order, steps are only reported for
+/// phases with a known number of steps. All methods do nothing by default.
+pub trait Progress {
+    /// `phase` started, with `total` steps if known.
+    fn start(&self, _phase: Phase, _total: Option<usize>) {}
+
+    /// `done` steps of `phase` are done.
+    fn advance(&self, _phase: Phase, _done: usize) {}
+
+    /// `phase` finished, `done` is the number of steps or elements it
+    /// produced.
+    fn finish(&self, _phase: Phase, _done: usize) {}
+}
+
+/// Ignores the progress.
+pub struct Silent;
+
+impl Progress for Silent {}
+
+/// Phase that is shown by [`StderrProgress`].
+struct Current {
+    total: Option<usize>,
+    start: Instant,
+    last: Instant,
+}
+
+/// Shows the progress on standard error, on a single updated line if it is a
+/// terminal and as periodic lines otherwise.
+pub struct StderrProgress {
+    start: Instant,
+    tty: bool,
+    current: RefCell<Option<Current>>,
+}
+
+impl Default for StderrProgress {
+    fn default() -> Self {
+        StderrProgress::new()
+    }
+}
+
+impl StderrProgress {
+    pub fn new() -> Self {
+        StderrProgress {
+            start: Instant::now(),
+            tty: io::stderr().is_terminal(),
+            current: RefCell::new(None),
+        }
+    }
+
+    /// Writes `line` for `phase`, replacing the previous one on a terminal.
+    fn show(&self, phase: Phase, line: fmt::Arguments, end: bool) {
+        let mut stderr = io::stderr().lock();
+        let elapsed = self.start.elapsed().as_secs_f64();
+        // Progress is best effort, errors writing it are ignored.
+        let _ = if self.tty {
+            write!(stderr, "\r\x1b[K[{:6.1}s] {}: {}", elapsed, phase, line).and_then(|_| {
+                if end {
+                    writeln!(stderr)
+                } else {
+                    stderr.flush()
+                }
+            })
+        } else {
+            writeln!(stderr, "[{:6.1}s] {}: {}", elapsed, phase, line)
+        };
+    }
+}
+
+impl Progress for StderrProgress {
+    fn start(&self, phase: Phase, total: Option<usize>) {
+        let now = Instant::now();
+        *self.current.borrow_mut() = Some(Current {
+            total,
+            start: now,
+            last: now,
+        });
+        match total {
+            Some(total) => self.show(phase, format_args!("0/{}", total), false),
+            None => self.show(phase, format_args!("started"), false),
+        }
+    }
+
+    fn advance(&self, phase: Phase, done: usize) {
+        let mut current = self.current.borrow_mut();
+        let Some(current) = current.as_mut() else {
+            return;
+        };
+        let interval = if self.tty { TTY_INTERVAL } else { LOG_INTERVAL };
+        if current.last.elapsed() < interval {
+            return;
+        }
+        current.last = Instant::now();
+        match current.total {
+            Some(total) => self.show(phase, format_args!("{}/{}", done, total), false),
+            None => self.show(phase, format_args!("{}", done), false),
+        }
+    }
+
+    fn finish(&self, phase: Phase, done: usize) {
+        let Some(current) = self.current.borrow_mut().take() else {
+            return;
+        };
+        self.show(
+            phase,
+            format_args!(
+                "{} done in {:.1}s",
+                done,
+                current.start.elapsed().as_secs_f64()
+            ),
+            true,
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Printing a single type or symbol without generating an ISF file.
+
+use crate::btf;
+use crate::cli::QueryArgs;
+use crate::isf::quirks;
+use crate::v_symbols;
+use crate::v_types::{self, Definition, TypeDescr};
+use crate::GenerationContext;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem;
+
+use anyhow::{bail, Error, Result};
+use serde_json::{json, Value};
+
+/// Maximum number of close matches listed for an unknown name.
+const MAX_CLOSE_MATCHES: usize = 5;
+
+/// ISF element found by a query.
+pub enum Element {
+    User(v_types::User),
+    Enum(v_types::Enum),
+    Base(v_types::Base),
+    Symbol(v_symbols::Symbol),
+}
+
+impl Element {
+    /// Ranks elements of the same name, user types beat enums, which beat base
+    /// types, then the more complete definition wins.
+    fn rank(&self) -> (u8, (usize, u64)) {
+        match self {
+            Self::User(user) => (3, user.completeness()),
+            Self::Enum(enum_) => (2, enum_.completeness()),
+            Self::Base(base) => (1, base.completeness()),
+            Self::Symbol(_) => (0, (0, 0)),
+        }
+    }
+
+    /// Returns the ISF JSON fragment with the element under `name`.
+    pub fn to_json(&self, name: &str) -> Result<Value> {
+        let (key, elem) = match self {
+            Self::User(user) => ("user_types", serde_json::to_value(user)?),
+            Self::Enum(enum_) => ("enums", serde_json::to_value(enum_)?),
+            Self::Base(base) => ("base_types", serde_json::to_value(base)?),
+            Self::Symbol(sym) => ("symbols", serde_json::to_value(sym)?),
+        };
+        Ok(json!({ key: { name: elem } }))
+    }
+}
+
+/// Looks up types and symbols by name.
+pub struct Query {
+    ctx: GenerationContext,
+    /// IDs of the user types, enums, and base types under each of their
+    /// names, including typedef aliases.
+    ids: HashMap<String, Vec<btf::Id>>,
+    symbols: BTreeMap<String, v_symbols::Symbol>,
+}
+
+impl TryFrom<&QueryArgs> for Query {
+    type Error = Error;
+
+    fn try_from(args: &QueryArgs) -> Result<Query> {
+        let mut generate = args.generate.clone();
+        // Types can be looked up without a symbol source.
+        generate.types_only |= args.symbol.is_none();
+        Query::new(GenerationContext::try_from(&generate)?)
+    }
+}
+
+impl Query {
+    pub fn new(mut ctx: GenerationContext) -> Result<Self> {
+        let mut ids: HashMap<String, Vec<btf::Id>> = HashMap::new();
+        for id in ctx
+            .user_ids
+            .iter()
+            .chain(&ctx.enum_ids)
+            .chain(&ctx.basic_ids)
+        {
+            for name in ctx
+                .btf
+                .get_names_by_id(*id, Some(&ctx.typedefs))
+                .unwrap_or_default()
+            {
+                ids.entry(name).or_default().push(*id);
+            }
+        }
+        let mut symbols = v_symbols::from_symbols(
+            mem::take(&mut ctx.symbols),
+            ctx.address_width,
+            ctx.address_overflow,
+        )?;
+        if !ctx.format_version.has_linkage() {
+            symbols
+                .values_mut()
+                .for_each(v_symbols::Symbol::clear_linkage);
+        }
+        Ok(Self { ctx, ids, symbols })
+    }
+
+    /// Returns the output for the query in `args`.
+    pub fn run(mut self, args: &QueryArgs) -> Result<String> {
+        let (name, elem) = match (&args.type_name, &args.symbol) {
+            (Some(name), _) => (name, self.type_by_name(name)?),
+            (None, Some(name)) => (name, self.symbol_by_name(name)?),
+            (None, None) => bail!("Nothing to query, use --type or --symbol"),
+        };
+        if args.c_style {
+            Ok(self.c_style(name, &elem))
+        } else {
+            Ok(serde_json::to_string_pretty(&elem.to_json(name)?)?)
+        }
+    }
+
+    /// Constructs the type `name`, which may also be a typedef alias.
+    ///
+    /// If several types have the name, the one that would be emitted into the
+    /// ISF file is returned.
+    pub fn type_by_name(&self, name: &str) -> Result<Element> {
+        let Some(ids) = self.ids.get(name) else {
+            bail!(unknown("type", name, self.ids.keys()))
+        };
+        ids.iter()
+            .filter_map(|id| {
+                self.construct(*id)
+                    .inspect_err(|err| log::warn!("[{}] skipping `{}`: {:#}", id, name, err))
+                    .ok()
+            })
+            .max_by_key(Element::rank)
+            .ok_or_else(|| anyhow::anyhow!("Unable to construct type `{}`", name))
+    }
+
+    /// Takes the symbol `name` as it would be emitted into the ISF file.
+    pub fn symbol_by_name(&mut self, name: &str) -> Result<Element> {
+        match self.symbols.remove(name) {
+            Some(sym) => Ok(Element::Symbol(sym)),
+            None => bail!(unknown("symbol", name, self.symbols.keys())),
+        }
+    }
+
+    fn construct(&self, id: btf::Id) -> Result<Element> {
+        let basic_ctx = v_types::BaseConstructionCtx {
+            btf: &self.ctx.btf,
+            tx: btf::TypeEx {
+                t: self.ctx.btf.get_type_by_id(id)?,
+                id,
+            },
+            char_signed: self.ctx.char_signed,
+        };
+        Ok(if basic_ctx.tx.t.is_enum() {
+            Element::Enum(v_types::Enum::try_from(v_types::EnumConstructionCtx {
+                basic_ctx,
+                base_types: &mut self.base_types(),
+            })?)
+        } else if basic_ctx.tx.t.is_struct() || basic_ctx.tx.t.is_union() {
+            Element::User(v_types::User::try_from(v_types::UserConstructionCtx {
+                basic_ctx,
+                typedefs: &self.ctx.typedefs,
+                rich_functions: self.ctx.rich_functions,
+                skipped_members: &RefCell::default(),
+                invalid_bitfields: &RefCell::default(),
+                traced: &HashSet::new(),
+            })?)
+        } else {
+            Element::Base(v_types::Base::try_from(basic_ctx)?)
+        })
+    }
+
+    /// Returns the base types under their C names, which enums choose their
+    /// base type from.
+    fn base_types(&self) -> BTreeMap<String, v_types::Base> {
+        let mut base_types = BTreeMap::new();
+        for id in &self.ctx.basic_ids {
+            let (Ok(names), Ok(t)) = (
+                self.ctx.btf.get_names_by_id(*id, None),
+                self.ctx.btf.get_type_by_id(*id),
+            ) else {
+                continue;
+            };
+            let basic_ctx = v_types::BaseConstructionCtx {
+                btf: &self.ctx.btf,
+                tx: btf::TypeEx { t, id: *id },
+                char_signed: self.ctx.char_signed,
+            };
+            if let (Some(name), Ok(base)) = (names.into_iter().next(), basic_ctx.construct()) {
+                base_types.insert(name, base);
+            }
+        }
+        quirks::fixup_base(
+            &mut base_types,
+            self.ctx.address_width,
+            (&self.ctx.btf.endian).into(),
+        );
+        base_types
+    }
+
+    /// Returns the size of `t` in bytes, if known.
+    fn size_of(&self, t: &TypeDescr) -> Option<u64> {
+        match t {
+            TypeDescr::Pointer { .. } => Some(self.ctx.address_width.bytes() as u64),
+            TypeDescr::Array { count, subtype } => Some(count * self.size_of(subtype)?),
+            TypeDescr::Base { name } if &**name == "pointer" => {
+                Some(self.ctx.address_width.bytes() as u64)
+            }
+            TypeDescr::Base { name }
+            | TypeDescr::Enum { name }
+            | TypeDescr::Struct { name }
+            | TypeDescr::Union { name } => {
+                let id = *self.ids.get(&**name)?.first()?;
+                Some(self.ctx.btf.get_type_by_id(id).ok()?.size()? as u64)
+            }
+            TypeDescr::Bitfield { .. } | TypeDescr::Function { .. } => None,
+        }
+    }
+
+    /// Returns a C-like declaration of `elem` with the offsets and sizes
+    /// annotated.
+    fn c_style(&self, name: &str, elem: &Element) -> String {
+        match elem {
+            Element::User(user) => {
+                let mut fields: Vec<(&btf::Name, &v_types::UserField)> =
+                    user.fields.iter().collect();
+                fields.sort_by_key(|(name, field)| {
+                    let bit_position = match &field.t {
+                        TypeDescr::Bitfield { bit_position, .. } => *bit_position,
+                        _ => 0,
+                    };
+                    (field.offset(), bit_position, *name)
+                });
+                let lines = fields
+                    .into_iter()
+                    .map(|(name, field)| {
+                        let mut notes = vec![format!("offset: {}", field.offset())];
+                        match &field.t {
+                            TypeDescr::Bitfield {
+                                bit_position,
+                                bit_length,
+                                ..
+                            } => notes.push(format!(
+                                "bits: {}..{}",
+                                bit_position,
+                                bit_position + bit_length
+                            )),
+                            t => {
+                                notes.extend(self.size_of(t).map(|size| format!("size: {}", size)))
+                            }
+                        }
+                        (declaration(&field.t, name), notes.join(", "))
+                    })
+                    .collect();
+                format!(
+                    "{} {} {{ /* size: {} */\n{}}};",
+                    user.kind.as_str(),
+                    name,
+                    user.size(),
+                    aligned(lines)
+                )
+            }
+            Element::Enum(enum_) => {
+                let mut constants: Vec<(&String, &i128)> = enum_.constants().iter().collect();
+                constants.sort_by_key(|(name, value)| (**value, *name));
+                let lines = constants
+                    .into_iter()
+                    .map(|(name, value)| format!("\t{} = {},\n", name, value))
+                    .collect::<String>();
+                format!(
+                    "enum {} {{ /* size: {}, base: {} */\n{}}};",
+                    name,
+                    enum_.size(),
+                    enum_.base(),
+                    lines
+                )
+            }
+            Element::Base(base) => format!(
+                "{} /* size: {}, {} */",
+                name,
+                base.size(),
+                if base.is_signed() {
+                    "signed"
+                } else {
+                    "unsigned"
+                }
+            ),
+            Element::Symbol(sym) => {
+                let mut notes = vec![format!(
+                    "address: {}",
+                    self.ctx.address_width.format(sym.address)
+                )];
+                notes.extend(self.size_of(&sym.t).map(|size| format!("size: {}", size)));
+                format!("{}; /* {} */", declaration(&sym.t, name), notes.join(", "))
+            }
+        }
+    }
+}
+
+/// Returns the C declaration of `declarator` with type `t`.
+pub(crate) fn declaration(t: &TypeDescr, declarator: &str) -> String {
+    let decl = match t {
+        TypeDescr::Base { name } => format!("{} {}", name, declarator),
+        TypeDescr::Enum { name } => format!("enum {} {}", name, declarator),
+        TypeDescr::Struct { name } => format!("struct {} {}", name, declarator),
+        TypeDescr::Union { name } => format!("union {} {}", name, declarator),
+        TypeDescr::Pointer { subtype } => match **subtype {
+            TypeDescr::Array { .. } | TypeDescr::Function { .. } => {
+                declaration(subtype, &format!("(*{})", declarator))
+            }
+            _ => declaration(subtype, &format!("*{}", declarator)),
+        },
+        TypeDescr::Array { count, subtype } => {
+            declaration(subtype, &format!("{}[{}]", declarator, count))
+        }
+        TypeDescr::Bitfield {
+            type_, bit_length, ..
+        } => format!("{} : {}", declaration(type_, declarator), bit_length),
+        TypeDescr::Function {
+            return_type,
+            parameters,
+            variadic,
+        } => {
+            let mut params: Vec<String> = parameters
+                .iter()
+                .flatten()
+                .map(|param| declaration(param, ""))
+                .collect();
+            if *variadic {
+                params.push(String::from("..."));
+            }
+            let declarator = format!("{}({})", declarator, params.join(", "));
+            match return_type {
+                Some(t) => declaration(t, &declarator),
+                None => declaration(&TypeDescr::new_void(), &declarator),
+            }
+        }
+    };
+    decl.trim_end().to_string()
+}
+
+/// Formats member declarations and their notes, with the notes aligned.
+fn aligned(lines: Vec<(String, String)>) -> String {
+    let width = lines
+        .iter()
+        .map(|(decl, _)| decl.len() + 1)
+        .max()
+        .unwrap_or(0);
+    lines
+        .into_iter()
+        .map(|(decl, notes)| format!("\t{:<width$} /* {} */\n", decl + ";", notes))
+        .collect()
+}
+
+/// Returns the error message for an unknown name of `what`, with the closest
+/// of the `known` names.
+fn unknown<'a>(what: &str, name: &str, known: impl Iterator<Item = &'a String>) -> String {
+    let mut matches: Vec<(usize, &String)> = known
+        .filter_map(|candidate| {
+            let distance = edit_distance(name, candidate);
+            (distance <= name.len() / 3 + 1 || candidate.contains(name))
+                .then_some((distance, candidate))
+        })
+        .collect();
+    matches.sort();
+    let mut msg = format!("No {} named `{}`", what, name);
+    if !matches.is_empty() {
+        msg.push_str(", close matches: ");
+        msg.push_str(
+            &matches
+                .iter()
+                .take(MAX_CLOSE_MATCHES)
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+    msg
+}
+
+/// Returns the Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,281 @@
+//! Detection of structure layout randomization (RANDSTRUCT).
+//!
+//! Kernels built with `CONFIG_RANDSTRUCT` shuffle the members of sensitive
+//! structures using a per-build seed. Profiles of such kernels are only valid
+//! for the exact build they were generated from, and mixing symbol and type
+//! information from different builds silently produces garbage.
+//!
+//! If the BTF comes from a vmlinux file, the build tells us directly:
+//!
+//! - The kernel config that is embedded with `CONFIG_IKCONFIG` lists the
+//!   RANDSTRUCT options.
+//! - The vermagic string, i.e., the release of the banner followed by the
+//!   build flags that modules must match, ends with the hash of the seed.
+//!
+//! Otherwise, there is no reliable marker in BTF, so we use heuristics:
+//!
+//! - The randomized part of `task_struct` is wrapped in an anonymous struct
+//!   (`randomized_struct_fields_start`) only if RANDSTRUCT is enabled.
+//! - Pairs of members that are declared in a fixed order must appear in this
+//!   order in an unrandomized layout.
+
+use crate::btf::{Btf, TypeEx};
+use crate::decompress;
+use crate::diagnostics;
+use crate::version::KernelVersion;
+
+use memchr::memmem;
+
+/// First kernel version with RANDSTRUCT, as a GCC plugin.
+const RANDSTRUCT_SINCE: KernelVersion = KernelVersion::new(4, 13, 0);
+/// Kernel version that replaced `pids` of `task_struct` by `thread_pid` and
+/// `pid_links`.
+const PID_LINKS_SINCE: KernelVersion = KernelVersion::new(4, 19, 0);
+
+/// Start of the kernel config that is embedded with `CONFIG_IKCONFIG`, which
+/// is followed by the gzip-compressed config.
+const IKCONFIG_START: &[u8] = b"IKCFG_ST";
+/// Options that enable RANDSTRUCT, before and since Linux 5.19.
+const RANDSTRUCT_OPTIONS: &[&str] = &[
+    "CONFIG_GCC_PLUGIN_RANDSTRUCT=y",
+    "CONFIG_RANDSTRUCT_FULL=y",
+    "CONFIG_RANDSTRUCT_PERFORMANCE=y",
+];
+/// Prefix of the seed hash in the vermagic string, e.g., `RANDSTRUCT_PLUGIN_`
+/// before and `RANDSTRUCT_` since Linux 5.19.
+const VERMAGIC_RANDSTRUCT: &[u8] = b" RANDSTRUCT_";
+
+/// Two members of a struct, where `first` is declared before `second` in all
+/// kernel versions the hint applies to.
+struct OrderHint {
+    type_name: &'static str,
+    first: &'static str,
+    second: &'static str,
+    /// First kernel version the hint applies to.
+    since: KernelVersion,
+    /// First kernel version the hint no longer applies to, `None` if unbounded.
+    until: Option<KernelVersion>,
+}
+
+impl OrderHint {
+    /// Returns a hint for all kernel versions with RANDSTRUCT.
+    const fn new(type_name: &'static str, first: &'static str, second: &'static str) -> Self {
+        Self {
+            type_name,
+            first,
+            second,
+            since: RANDSTRUCT_SINCE,
+            until: None,
+        }
+    }
+
+    /// Restricts the hint to kernel versions since `version`.
+    const fn since(mut self, version: KernelVersion) -> Self {
+        self.since = version;
+        self
+    }
+
+    /// Restricts the hint to kernel versions before `version`.
+    const fn until(mut self, version: KernelVersion) -> Self {
+        self.until = Some(version);
+        self
+    }
+
+    /// Returns true iff the hint applies to kernels of the given `version`.
+    ///
+    /// If the version is unknown, all hints apply.
+    fn applies_to(&self, version: Option<KernelVersion>) -> bool {
+        let Some(version) = version else {
+            return true;
+        };
+        version >= self.since && self.until.is_none_or(|until| version < until)
+    }
+}
+
+/// Member orderings of structures that are marked `__randomize_layout`.
+///
+/// Extend this table when new structures or kernel versions need coverage.
+const ORDER_HINTS: &[OrderHint] = &[
+    OrderHint::new("task_struct", "pid", "tgid"),
+    OrderHint::new("task_struct", "real_parent", "parent"),
+    OrderHint::new("task_struct", "children", "sibling"),
+    OrderHint::new("task_struct", "group_leader", "pids").until(PID_LINKS_SINCE),
+    OrderHint::new("task_struct", "thread_pid", "pid_links").since(PID_LINKS_SINCE),
+    OrderHint::new("task_struct", "utime", "stime"),
+    OrderHint::new("cred", "uid", "gid"),
+    OrderHint::new("cred", "suid", "sgid"),
+    OrderHint::new("cred", "euid", "egid"),
+    OrderHint::new("fs_struct", "root", "pwd"),
+];
+
+/// Evidence of the kernel build on whether RANDSTRUCT is enabled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuildHint {
+    /// The embedded kernel config enables (`true`) or disables RANDSTRUCT.
+    Config(bool),
+    /// The vermagic string contains the hash of the randomization seed.
+    Vermagic,
+}
+
+/// Returns the evidence on RANDSTRUCT in the `raw` vmlinux file, if any.
+///
+/// The embedded kernel config takes precedence over the vermagic string.
+pub fn build_hint(raw: &[u8]) -> Option<BuildHint> {
+    let config = memmem::find_iter(raw, IKCONFIG_START).find_map(|off| {
+        let config = decompress::decompress_stream(&raw[off + IKCONFIG_START.len()..])?.ok()?;
+        log::debug!("Found the kernel config at {:#x}", off);
+        Some(String::from_utf8_lossy(&config).into_owned())
+    });
+    if let Some(config) = config {
+        let enabled = config
+            .lines()
+            .any(|line| RANDSTRUCT_OPTIONS.contains(&line.trim()));
+        return Some(BuildHint::Config(enabled));
+    }
+    memmem::find(raw, VERMAGIC_RANDSTRUCT).map(|_| BuildHint::Vermagic)
+}
+
+/// Result of the RANDSTRUCT heuristics.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Evidence of the kernel build, if the BTF comes with it.
+    pub build_hint: Option<BuildHint>,
+    /// `task_struct` contains the anonymous randomization wrapper.
+    pub wrapper_found: bool,
+    /// Number of order hints that could be checked.
+    pub checked: usize,
+    /// Order hints that were violated, as human readable descriptions.
+    pub violations: Vec<String>,
+}
+
+impl Report {
+    /// Returns true iff the kernel is suspected to use RANDSTRUCT.
+    ///
+    /// The embedded kernel config is authoritative, the other findings are
+    /// not.
+    pub fn is_suspected(&self) -> bool {
+        match self.build_hint {
+            Some(BuildHint::Config(enabled)) => enabled,
+            Some(BuildHint::Vermagic) => true,
+            None => self.wrapper_found || !self.violations.is_empty(),
+        }
+    }
+
+    /// Logs the result of the heuristics.
+    ///
+    /// `separate_symbols` indicates that symbol information comes from a
+    /// different source than the type information, which is dangerous for
+    /// randomized kernels.
+    pub fn log(&self, separate_symbols: bool) {
+        if self.build_hint == Some(BuildHint::Config(false)) {
+            log::debug!(
+                "The kernel config disables structure layout randomization ({} of {} hints violated)",
+                self.violations.len(),
+                self.checked
+            );
+            return;
+        }
+        if !self.is_suspected() {
+            log::debug!(
+                "No structure layout randomization detected ({} hints checked)",
+                self.checked
+            );
+            return;
+        }
+
+        let advice = if separate_symbols {
+            "the profile is only valid for this exact kernel build, make sure the \
+             System.map comes from the same build as the BTF"
+        } else {
+            "the profile is only valid for this exact kernel build"
+        };
+        let build = self.build_hint.map(|hint| match hint {
+            BuildHint::Config(_) => String::from("the kernel config enables RANDSTRUCT"),
+            BuildHint::Vermagic => String::from("the vermagic string contains a RANDSTRUCT seed"),
+        });
+        let wrapper = self
+            .wrapper_found
+            .then(|| String::from("task_struct contains the randomized fields wrapper"));
+        for finding in build.iter().chain(&wrapper).chain(&self.violations) {
+            log::warn!(
+                target: diagnostics::INPUTS,
+                code = "randstruct_suspected",
+                finding:% = finding;
+                "Kernel appears to use structure layout randomization ({}), {}",
+                finding,
+                advice
+            );
+        }
+    }
+}
+
+/// Runs the RANDSTRUCT heuristics on `btf`.
+///
+/// The `version` of the kernel is used to select the applicable hints, and
+/// the file of `btf` is searched for a [`BuildHint`].
+pub fn detect(btf: &Btf, version: Option<KernelVersion>) -> Report {
+    let mut report = Report {
+        build_hint: build_hint(&btf.raw()),
+        wrapper_found: has_randomization_wrapper(btf),
+        ..Default::default()
+    };
+
+    for hint in ORDER_HINTS.iter().filter(|h| h.applies_to(version)) {
+        let Some(tx) = find_struct(btf, hint.type_name) else {
+            continue;
+        };
+        let (Some(first), Some(second)) = (
+            find_member_offset(btf, &tx, hint.first),
+            find_member_offset(btf, &tx, hint.second),
+        ) else {
+            continue;
+        };
+        report.checked += 1;
+        if first > second {
+            report.violations.push(format!(
+                "{}.{} (offset {}) follows {}.{} (offset {})",
+                hint.type_name, hint.first, first, hint.type_name, hint.second, second
+            ));
+        }
+    }
+
+    report
+}
+
+/// Returns true iff the `pid` member of `task_struct` is nested in an anonymous
+/// struct.
+fn has_randomization_wrapper(btf: &Btf) -> bool {
+    let Some(tx) = find_struct(btf, "task_struct") else {
+        return false;
+    };
+    let Some(members) = tx.t.as_has_members().map(|m| m.members(btf)) else {
+        return false;
+    };
+    members
+        .iter()
+        .filter(|m| m.is_anon() == Some(true))
+        .filter_map(|m| m.get_tx(btf).ok())
+        .any(|tx| find_member_offset(btf, &tx, "pid").is_some())
+}
+
+/// Returns the first struct called `name`.
+pub(crate) fn find_struct(btf: &Btf, name: &str) -> Option<TypeEx> {
+    btf.find_by_name(name)
+        .into_iter()
+        .find(|tx| tx.t.as_has_members().is_some())
+}
+
+/// Returns the offset in bytes of the member `name` within `tx`, descending
+/// into anonymous members.
+pub(crate) fn find_member_offset(btf: &Btf, tx: &TypeEx, name: &str) -> Option<u64> {
+    let members = tx.t.as_has_members()?.members(btf);
+    members.iter().find_map(|m| {
+        if m.is_anon() == Some(true) {
+            find_member_offset(btf, &m.get_tx(btf).ok()?, name).map(|off| m.byte_offset() + off)
+        } else if *m.name(btf) == *name {
+            Some(m.byte_offset())
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,132 @@
+//! Validation of ISF files against the ISF JSON schema.
+//!
+//! The schemas in `schemas/` are the ones that Volatility 3 ships, patched to
+//! accept the `btf` and `symdb` source kinds as described in the README. The
+//! schema is selected by the format version in the metadata of an ISF file.
+
+use crate::input;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use rust_embed::RustEmbed;
+use serde_json::Value;
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+
+#[derive(RustEmbed)]
+#[folder = "schemas/"]
+struct Schemas;
+
+/// Maximum number of violations that are listed in an error.
+const MAX_REPORTED: usize = 50;
+
+const GZ_MAGIC: &[u8] = b"\x1f\x8b";
+const XZ_MAGIC: &[u8] = b"\xfd7zXZ\x00";
+
+/// Part of an ISF file that does not match the schema.
+#[derive(Debug)]
+pub struct Violation {
+    /// JSON pointer to the offending value.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// Returns the schema for ISF files of `format`.
+fn schema(format: &str) -> Result<Value> {
+    let name = format!("schema-{}.json", format);
+    let file = Schemas::get(&name)
+        .ok_or_else(|| anyhow!("There is no schema for ISF format {}", format))?;
+    Ok(serde_json::from_slice(&file.data)
+        .unwrap_or_else(|err| panic!("BUG: invalid {}: {}", name, err)))
+}
+
+/// Returns all parts of the ISF file `isf` that do not match the schema of its
+/// format version.
+pub fn violations(isf: &Value) -> Result<Vec<Violation>> {
+    let format = isf
+        .pointer("/metadata/format")
+        .and_then(Value::as_str)
+        .context("ISF file has no format version")?;
+    let validator = jsonschema::validator_for(&schema(format)?).map_err(|err| {
+        anyhow!(
+            "Unable to compile schema for ISF format {}: {}",
+            format,
+            err
+        )
+    })?;
+
+    Ok(validator
+        .iter_errors(isf)
+        .map(|err| Violation {
+            pointer: err.instance_path().to_string(),
+            message: err.to_string(),
+        })
+        .collect())
+}
+
+/// Fails if the ISF file `isf` does not match the schema of its format
+/// version, listing the violations.
+pub fn validate(isf: &Value) -> Result<()> {
+    let violations = violations(isf)?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    for violation in &violations {
+        log::debug!("Schema violation at {}", violation);
+    }
+
+    let mut msg = format!("{} schema violations", violations.len());
+    for violation in violations.iter().take(MAX_REPORTED) {
+        msg.push_str(&format!("\n  {}", violation));
+    }
+    if violations.len() > MAX_REPORTED {
+        msg.push_str(&format!("\n  and {} more", violations.len() - MAX_REPORTED));
+    }
+    bail!(msg)
+}
+
+/// Reads the ISF file at `path`, which may be gzip or xz compressed.
+pub fn read_isf(path: &Path) -> Result<Value> {
+    let mut raw = Vec::new();
+    if input::is_stdin(path) {
+        io::stdin()
+            .lock()
+            .read_to_end(&mut raw)
+            .context("Unable to read standard input")?;
+    } else {
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut raw))
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+    }
+
+    let isf = if raw.starts_with(GZ_MAGIC) {
+        serde_json::from_reader(GzDecoder::new(raw.as_slice()))
+    } else if raw.starts_with(XZ_MAGIC) {
+        #[cfg(not(feature = "xz"))]
+        bail!(
+            "{} is xz-compressed, which requires the xz feature",
+            path.display()
+        );
+        #[cfg(feature = "xz")]
+        serde_json::from_reader(XzDecoder::new(raw.as_slice()))
+    } else {
+        serde_json::from_slice(&raw)
+    };
+    isf.with_context(|| format!("{} is not a JSON file", path.display()))
+}
@@ -0,0 +1,193 @@
+//! Selection of the kernel in a directory whose banner is the one of the
+//! profile, see `--search-dir`.
+//!
+//! The kernels are found by the names that `/boot` uses, like with `btf2json
+//! batch`. Reading the banner of each is the slow part, so that with
+//! `--cache-dir` the banners are kept in `search-banners.json` by the path,
+//! size, and modification time of the kernel.
+
+use crate::batch::{self, Kernel};
+use crate::btf;
+use crate::symbols::Banner;
+use crate::version;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Name of the file with the banners in the cache directory.
+const CACHE_FILE: &str = "search-banners.json";
+
+/// Banner of a kernel, valid as long as the kernel is not modified.
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+struct CacheEntry {
+    len: u64,
+    /// Modification time, in nanoseconds since the epoch.
+    modified: u64,
+    /// `None` if the kernel has no banner.
+    banner: Option<String>,
+}
+
+impl CacheEntry {
+    fn stat(path: &Path) -> Result<Self> {
+        let meta =
+            fs::metadata(path).with_context(|| format!("Unable to stat {}", path.display()))?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |time| time.as_nanos() as u64);
+        Ok(Self {
+            len: meta.len(),
+            modified,
+            banner: None,
+        })
+    }
+
+    fn is_current(&self, stat: &CacheEntry) -> bool {
+        self.len == stat.len && self.modified == stat.modified
+    }
+}
+
+/// Banners of kernels by their canonical path.
+#[derive(Default)]
+struct BannerCache {
+    path: Option<PathBuf>,
+    entries: BTreeMap<PathBuf, CacheEntry>,
+    modified: bool,
+}
+
+impl BannerCache {
+    /// Reads the cache in `dir`, or returns an empty one if it cannot be read.
+    fn load(dir: Option<&Path>) -> Self {
+        let Some(dir) = dir else {
+            return Self::default();
+        };
+        let path = dir.join(CACHE_FILE);
+        let entries = match fs::read(&path) {
+            Ok(raw) => serde_json::from_slice(&raw).unwrap_or_else(|err| {
+                log::warn!("Ignoring corrupted cache {}: {}", path.display(), err);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path: Some(path),
+            entries,
+            modified: false,
+        }
+    }
+
+    /// Returns the normalized banner of the kernel at `path`, `None` if it has
+    /// none.
+    fn banner(&mut self, path: &Path) -> Result<Option<String>> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let mut stat = CacheEntry::stat(&path)?;
+        if let Some(entry) = self
+            .entries
+            .get(&path)
+            .filter(|entry| entry.is_current(&stat))
+        {
+            log::debug!("Using the cached banner of {}", path.display());
+            return Ok(entry.banner.clone());
+        }
+        stat.banner = match btf::load_btf_file(&path).and_then(|raw| Banner::from_btfsec(&raw)) {
+            Ok(banner) => Some(normalize(&banner.to_string())),
+            Err(err) => {
+                log::debug!("No banner in {}: {:#}", path.display(), err);
+                None
+            }
+        };
+        let banner = stat.banner.clone();
+        self.entries.insert(path, stat);
+        self.modified = true;
+        Ok(banner)
+    }
+
+    /// Writes the cache back if it has new entries.
+    fn store(&self) -> Result<()> {
+        let Some(path) = self.path.as_ref().filter(|_| self.modified) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create {}", dir.display()))?;
+        }
+        fs::write(path, serde_json::to_vec(&self.entries)?)
+            .with_context(|| format!("Unable to write {}", path.display()))
+    }
+}
+
+/// Returns `banner` without trailing NULs and newlines, which are not part of
+/// the match.
+fn normalize(banner: &str) -> String {
+    banner.trim_end_matches(['\0', '\n']).to_owned()
+}
+
+/// Returns the kernel in `dir` whose banner is `banner`, with the System.map
+/// of the same release if there is one.
+///
+/// Fails if no kernel or several kernels have the banner. If none has it, the
+/// error lists the kernels of the same release, e.g., with another build date.
+pub fn select(dir: &Path, banner: &Banner, cache_dir: Option<&Path>) -> Result<Kernel> {
+    let wanted = normalize(&banner.to_string());
+    let mut cache = BannerCache::load(cache_dir);
+    let mut matches = Vec::new();
+    let mut near = Vec::new();
+    for kernel in batch::discover(dir)? {
+        let Some(path) = &kernel.btf else {
+            continue;
+        };
+        let Some(found) = cache.banner(path)? else {
+            continue;
+        };
+        if found == wanted {
+            matches.push(kernel);
+        } else if version::release(&found)
+            .is_some_and(|found| Some(found) == version::release(&wanted))
+        {
+            near.push(format!("{}: {}", path.display(), found));
+        }
+    }
+    if let Err(err) = cache.store() {
+        log::warn!("Unable to cache the banners: {:#}", err);
+    }
+
+    match matches.len() {
+        1 => {
+            let kernel = matches.remove(0);
+            log::info!(
+                "Using {} and {} for banner {:?}",
+                kernel.btf.as_ref().unwrap().display(),
+                kernel
+                    .map
+                    .as_ref()
+                    .map_or_else(|| "no System.map".into(), |map| map.display().to_string()),
+                wanted
+            );
+            Ok(kernel)
+        }
+        0 if near.is_empty() => bail!("No kernel in {} has the banner {:?}", dir.display(), wanted),
+        0 => bail!(
+            "No kernel in {} has the banner {:?}, but these have the same release:\n  {}",
+            dir.display(),
+            wanted,
+            near.join("\n  ")
+        ),
+        _ => bail!(
+            "Several kernels in {} have the banner {:?}:\n  {}",
+            dir.display(),
+            wanted,
+            matches
+                .iter()
+                .filter_map(|kernel| kernel.btf.as_ref())
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n  ")
+        ),
+    }
+}
@@ -0,0 +1,159 @@
+//! Statistics about the types of a BTF file and the symbols that got types,
+//! without generating an ISF file.
+
+use crate::arch;
+use crate::btf::{Btf, TypeEx};
+use crate::cli::{GenerateArgs, StatsFormat};
+use crate::symbols::SymbolsBuilder;
+
+use anyhow::{Error, Result};
+use serde::Serialize;
+
+/// Number of structs listed by size.
+const NR_LARGEST: usize = 10;
+
+/// Struct listed by size.
+#[derive(Serialize)]
+pub struct SizedType {
+    pub name: String,
+    pub size: usize,
+}
+
+/// Number of symbols, if a symbol source was given.
+#[derive(Serialize)]
+pub struct SymbolStats {
+    pub total: usize,
+    pub with_types: u64,
+}
+
+/// Statistics as reported by `stats`.
+///
+/// The counts of base types, enums, and forward declarations, and of user
+/// types as structs plus unions, are the ID sets that the generation logs.
+#[derive(Serialize)]
+pub struct Stats {
+    pub structs: usize,
+    pub unions: usize,
+    pub enums: usize,
+    pub base_types: usize,
+    pub typedefs: usize,
+    pub fwd_decls: usize,
+    /// Structs, unions, and enums without a name.
+    pub anonymous: usize,
+    pub largest_structs: Vec<SizedType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<SymbolStats>,
+}
+
+impl TryFrom<&GenerateArgs> for Stats {
+    type Error = Error;
+
+    /// Gathers the statistics of the inputs given on the CLI.
+    ///
+    /// Unlike for the generation, no banner is required.
+    fn try_from(cli: &GenerateArgs) -> Result<Stats> {
+        let btf = Btf::try_from(cli)?;
+        let (user_ids, enum_ids, basic_ids, fwd_ids, typedefs) = btf.gen_vol_id_sets()?;
+
+        let mut stats = Stats {
+            structs: 0,
+            unions: 0,
+            enums: enum_ids.len(),
+            base_types: basic_ids.len(),
+            typedefs: typedefs.fw.len(),
+            fwd_decls: fwd_ids.len(),
+            anonymous: 0,
+            largest_structs: Vec::new(),
+            symbols: None,
+        };
+        let mut structs = Vec::new();
+        for id in user_ids.iter().chain(&enum_ids) {
+            let t = btf.get_type_by_id(*id)?;
+            if btf.get_strtab_entry_by_id(*id).is_err() {
+                stats.anonymous += 1;
+            }
+            if t.is_struct() {
+                stats.structs += 1;
+                structs.push(TypeEx { t, id: *id });
+            } else if t.is_union() {
+                stats.unions += 1;
+            }
+        }
+        structs.sort_by_key(|tx| std::cmp::Reverse(tx.t.size().unwrap_or(0)));
+        stats.largest_structs = structs
+            .into_iter()
+            .take(NR_LARGEST)
+            .map(|tx| {
+                Ok(SizedType {
+                    name: btf.get_names_by_id(tx.id, None)?.remove(0),
+                    size: tx.t.size().unwrap_or(0),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        if !cli.types_only && (!cli.map.is_empty() || cli.kallsyms.is_some() || cli.live) {
+            let arch = arch::detect(cli)?;
+            let symbols = SymbolsBuilder::without_banner(cli, arch, &btf)?
+                .add_types_from_btf(&btf, cli.rich_functions)
+                .build();
+            stats.symbols = Some(SymbolStats {
+                total: symbols.len(),
+                with_types: symbols.with_types(),
+            });
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Stats {
+    /// Returns the statistics in `format`.
+    pub fn format(&self, format: StatsFormat) -> Result<String> {
+        match format {
+            StatsFormat::Json => Ok(serde_json::to_string_pretty(self)? + "\n"),
+            StatsFormat::Table => Ok(self.table()),
+        }
+    }
+
+    fn table(&self) -> String {
+        let mut rows = vec![
+            ("structs", self.structs.to_string()),
+            ("unions", self.unions.to_string()),
+            ("enums", self.enums.to_string()),
+            ("base types", self.base_types.to_string()),
+            ("typedefs", self.typedefs.to_string()),
+            ("forward declarations", self.fwd_decls.to_string()),
+            ("anonymous types", self.anonymous.to_string()),
+        ];
+        if let Some(symbols) = &self.symbols {
+            rows.push(("symbols", symbols.total.to_string()));
+            rows.push(("symbols with types", symbols.with_types.to_string()));
+        }
+        let mut table = aligned(rows.iter().map(|(k, v)| (*k, v.as_str())), "");
+
+        if !self.largest_structs.is_empty() {
+            table.push_str("\nlargest structs:\n");
+            let sizes: Vec<String> = self
+                .largest_structs
+                .iter()
+                .map(|t| t.size.to_string())
+                .collect();
+            table.push_str(&aligned(
+                self.largest_structs
+                    .iter()
+                    .zip(&sizes)
+                    .map(|(t, size)| (t.name.as_str(), size.as_str())),
+                "  ",
+            ));
+        }
+        table
+    }
+}
+
+/// Formats rows of labels and right-aligned values as lines with `indent`.
+fn aligned<'a>(rows: impl Iterator<Item = (&'a str, &'a str)> + Clone, indent: &str) -> String {
+    let label_width = rows.clone().map(|(k, _)| k.len()).max().unwrap_or(0);
+    let value_width = rows.clone().map(|(_, v)| v.len()).max().unwrap_or(0);
+    rows.map(|(k, v)| format!("{}{:<label_width$}  {:>value_width$}\n", indent, k, v))
+        .collect()
+}
@@ -1,40 +1,114 @@
 //! Generation of symbol information.
 
-use crate::btf::Btf;
-use crate::cli::Cli;
+use crate::btf::{self, Btf, Declaration, Endian};
+use crate::cli::{
+    Architecture, DupSymbols, GenerateArgs, ImageFormat, VaBits, DEFAULT_CONSTANT_DATA,
+    LIVE_BANNER, LIVE_KALLSYMS,
+};
+use crate::decompress;
+use crate::diagnostics;
 use crate::elf;
-
-use std::collections::{HashMap, HashSet};
+use crate::error;
+use crate::image::{self, KallsymsEntry, PhysTranslation};
+use crate::input::{self, InputData};
+use crate::lime::{self, Lime};
+use crate::names;
+use crate::timings::Timings;
+use crate::v_symbols::AddressWidth;
+use crate::v_types::TypeDescr;
+use crate::version::{KernelVersion, VersionReq};
+use crate::vmcore::{self, Vmcore};
+
+use std::cell::OnceCell;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::env;
+use std::fmt;
+use std::fs;
 use std::iter::{IntoIterator, Iterator};
-use std::path::Path;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str;
+use std::time::Instant;
 
 use anyhow::{bail, Context, Error, Result};
 use base64::prelude::*;
-use memmap::Mmap;
+#[cfg(feature = "embedded-symdb")]
 use rust_embed::RustEmbed;
+use serde::Serialize;
+
+/// Environment variable with the path of the installed symdb, which is used if
+/// the symdb is not embedded.
+pub const SYMDB_ENV: &str = "BTF2JSON_SYMDB";
 
 /// The embedded symdb.
+#[cfg(feature = "embedded-symdb")]
 #[derive(RustEmbed)]
 #[folder = "symdb/"]
 struct SymDbAssets;
 
+#[cfg(feature = "embedded-symdb")]
 impl SymDbAssets {
     const SYMDB_NAME: &'static str = "dummy.symdb";
 }
 
+/// Entry of a symdb.
+pub struct SymDbEntry {
+    /// Line of the entry, starting at 1.
+    line: usize,
+    name: String,
+    /// Kernel versions the entry applies to, all versions if empty.
+    versions: Vec<VersionReq>,
+    t: TypeDescr,
+}
+
+impl SymDbEntry {
+    /// Returns true iff the entry applies to kernels of the given `version`.
+    ///
+    /// If the version is unknown, only entries for all versions apply.
+    fn applies_to(&self, version: Option<KernelVersion>) -> bool {
+        match version {
+            Some(version) => self.versions.iter().all(|req| req.matches(version)),
+            None => self.versions.is_empty(),
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn versions(&self) -> &[VersionReq] {
+        &self.versions
+    }
+
+    pub fn type_descr(&self) -> &TypeDescr {
+        &self.t
+    }
+}
+
+/// Line of a symdb that is not a valid entry.
+pub struct InvalidSymDbLine {
+    /// Line number, starting at 1.
+    pub line: usize,
+    pub text: String,
+    pub error: Error,
+}
+
 /// Mapping from symbol names to types.
-struct SymDb {
-    lines: Vec<(&'static str, &'static str)>,
+pub struct SymDb {
+    name: String,
+    lines: Vec<SymDbEntry>,
+    invalid: Vec<InvalidSymDbLine>,
 }
 
 impl SymDb {
     /// Get a reference to the embedded object.
+    #[cfg(feature = "embedded-symdb")]
     fn get_raw() -> &'static [u8] {
         let Some(symdb) = SymDbAssets::get(SymDbAssets::SYMDB_NAME) else {
             panic!("BUG: symdb not found.");
@@ -46,24 +120,149 @@ impl SymDb {
         data
     }
 
-    fn new() -> Self {
-        let Ok(symdb) = str::from_utf8(Self::get_raw()) else {
-            panic!("BUG: invalid data in symdb.");
+    /// Parses the embedded symdb.
+    #[cfg(feature = "embedded-symdb")]
+    fn embedded() -> Self {
+        match Self::parse(Self::get_raw(), SymDbAssets::SYMDB_NAME) {
+            Ok(symdb) => symdb,
+            Err(e) => panic!("BUG: invalid embedded symdb: {:#}", e),
+        }
+    }
+
+    /// Reads the symdb at `path` or, if `None`, the one that the generation
+    /// uses by default, i.e., the embedded or the installed one.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Self::default_symdb();
         };
-        Self {
-            lines: symdb
-                .lines()
-                .map(|l| match l.split_once(' ') {
-                    Some((name, t)) => (name, t),
-                    _ => panic!("BUG: invalid entry in symdb: {}", l),
-                })
-                .collect(),
+        let raw = InputData::load(path)
+            .with_context(|| format!("Unable to read symdb {}", path.display()))?;
+        Self::parse(&raw, &input::file_name(path)?)
+    }
+
+    #[cfg(feature = "embedded-symdb")]
+    fn default_symdb() -> Result<Self> {
+        Ok(Self::embedded())
+    }
+
+    #[cfg(not(feature = "embedded-symdb"))]
+    fn default_symdb() -> Result<Self> {
+        match Self::installed_path() {
+            Some((path, from_env)) if from_env || path.exists() => Self::load(Some(&path)),
+            _ => bail!("No symdb is installed, use --symdb or ${}", SYMDB_ENV),
         }
     }
+
+    /// Parses the symdb `raw` with one `<name> [<version req>...] <type
+    /// descriptor>` entry per line, where the type descriptor is in JSON and
+    /// the optional version requirements are like `>=5.15 <6.2`. Empty lines
+    /// are skipped.
+    ///
+    /// Lines that are not valid entries are kept apart, see
+    /// [`SymDb::invalid`], so that one of them does not fail the generation.
+    pub fn parse(raw: &[u8], name: &str) -> Result<Self> {
+        let symdb = str::from_utf8(raw).with_context(|| format!("{}: not UTF-8", name))?;
+        let mut lines = Vec::new();
+        let mut invalid = Vec::new();
+        for (i, text) in symdb.lines().enumerate() {
+            if text.trim().is_empty() {
+                continue;
+            }
+            match Self::parse_entry(i + 1, text) {
+                Ok(entry) => lines.push(entry),
+                Err(error) => invalid.push(InvalidSymDbLine {
+                    line: i + 1,
+                    text: text.to_owned(),
+                    error,
+                }),
+            }
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            lines,
+            invalid,
+        })
+    }
+
+    /// Returns the name of the symdb, e.g., its file name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the valid entries, in the order of their lines.
+    pub fn entries(&self) -> &[SymDbEntry] {
+        &self.lines
+    }
+
+    /// Returns the lines that are not valid entries.
+    pub fn invalid(&self) -> &[InvalidSymDbLine] {
+        &self.invalid
+    }
+
+    /// Warns about each line that is not a valid entry, whose symbol is left
+    /// to the other sources of types.
+    fn warn_invalid(&self) {
+        for invalid in &self.invalid {
+            log::warn!(
+                target: diagnostics::SYMBOLS,
+                code = "invalid_symdb_entry",
+                symdb:% = self.name,
+                line = invalid.line;
+                "{}:{}: {:#}, skipping the entry",
+                self.name,
+                invalid.line,
+                invalid.error
+            );
+        }
+    }
+
+    /// Returns the path of the installed symdb, see [`installed_symdb_path`],
+    /// and whether it is given by the environment.
+    fn installed_path() -> Option<(PathBuf, bool)> {
+        if let Some(path) = env::var_os(SYMDB_ENV).filter(|path| !path.is_empty()) {
+            return Some((path.into(), true));
+        }
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".local/share")))?;
+        Some((data_home.join("btf2json").join("symdb"), false))
+    }
+
+    fn parse_entry(line: usize, text: &str) -> Result<SymDbEntry> {
+        let Some((name, mut rest)) = text.split_once(' ') else {
+            bail!("Expected `<name> <type>`")
+        };
+        let mut versions = Vec::new();
+        while let Some((req, tail)) = rest
+            .trim_start()
+            .split_once(' ')
+            .filter(|(req, _)| req.starts_with(['<', '=', '>']))
+        {
+            versions.push(req.parse()?);
+            rest = tail;
+        }
+        let t = serde_json::from_str::<TypeDescr>(rest)
+            .with_context(|| format!("Invalid type of {}", name))?;
+
+        Ok(SymDbEntry {
+            line,
+            name: name.to_owned(),
+            versions,
+            t,
+        })
+    }
+}
+
+/// Returns the path of the symdb that is loaded if it is not embedded, which
+/// is `$BTF2JSON_SYMDB` if set, else `btf2json/symdb` in `$XDG_DATA_HOME` or
+/// `~/.local/share`.
+pub fn installed_symdb_path() -> Option<PathBuf> {
+    SymDb::installed_path().map(|(path, _)| path)
 }
 
 impl IntoIterator for SymDb {
-    type Item = (&'static str, &'static str);
+    type Item = SymDbEntry;
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -74,12 +273,33 @@ impl IntoIterator for SymDb {
 /// Symbol information that we have about the kernel.
 #[derive(Default)]
 pub struct Symbols {
-    raw_map: Option<Rc<Mmap>>,
-    name_map: Option<String>,
-    raw_symdb: Option<&'static [u8]>,
-    name_symdb: Option<&'static str>,
+    /// Sources of symbol names and addresses, by decreasing precedence.
+    sources: Vec<SymbolSource>,
+    raw_symdb: Option<Rc<InputData>>,
+    name_symdb: Option<String>,
     symbols: HashMap<String, Symbol>,
-    base_offset: u64, // value of _stext in System.map, used to remove KASLR shift
+    base_offset: Option<u64>, // value of _stext in System.map, used to remove KASLR shift
+    /// Layout of the arm64 kernel address space if the base offset is its
+    /// default, which depends on the anchor.
+    arm64_layout: Option<Arm64Layout>,
+    /// Width of addresses, at which rebased addresses wrap around.
+    address_width: Option<AddressWidth>,
+    /// Whether the symbols were synthesized from the BTF file instead of read
+    /// from a symbol map.
+    synthesized: bool,
+    /// Symbol that the addresses were rebased on, if any.
+    anchor: Option<String>,
+    /// What to do with symbol names that appear more than once.
+    dup_symbols: DupSymbols,
+    /// Whether symbol maps whose addresses are hidden are accepted.
+    allow_zero_addresses: bool,
+    /// Architecture whose canonical form the addresses of symbol maps are
+    /// brought into, with `--canonicalize-addresses`.
+    canonical_arch: Option<Architecture>,
+    /// Kernel module whose symbols were added, if any.
+    module: Option<Module>,
+    /// Names of the symbols by address, built on first use.
+    by_addr: OnceCell<HashMap<u64, String>>,
 }
 
 impl IntoIterator for Symbols {
@@ -93,39 +313,115 @@ impl IntoIterator for Symbols {
 
 impl Symbols {
     /// Returns number of symbols that have associated type information.
-    fn with_types(&self) -> u64 {
+    pub fn with_types(&self) -> u64 {
         self.symbols.iter().filter(|(_, s)| s.t.is_some()).count() as u64
     }
 
-    /// Get memory mapping of the System.map that was used to construct these
-    /// `Symbols`.
-    pub fn raw_map(&self) -> Option<Rc<Mmap>> {
-        self.raw_map.as_ref().map(|p| p.clone())
+    /// Returns how many symbols got their type from each source.
+    pub fn type_sources(&self) -> TypeSources {
+        let mut sources = TypeSources {
+            symdb: self.name_symdb.clone(),
+            ..Default::default()
+        };
+        for sym in self.symbols.values() {
+            match sym.source {
+                Some(TypeSource::SymDb) => sources.symdb_types += 1,
+                Some(TypeSource::BtfVariable) => sources.btf_variables += 1,
+                Some(TypeSource::BtfFunction) => sources.btf_functions += 1,
+                None => (),
+            }
+        }
+        sources
     }
 
-    /// Get name of the System.map that was used to construct these `Symbols`.
-    pub fn map_name(&self) -> Option<String> {
-        self.name_map.clone()
+    /// Returns the symbol maps that were used to construct these `Symbols`, by
+    /// decreasing precedence.
+    pub fn sources(&self) -> &[SymbolSource] {
+        &self.sources
     }
 
-    /// Returns pointer to the embedded symdb.
-    pub fn raw_symdb(&self) -> Option<&'static [u8]> {
-        self.raw_symdb
+    /// Returns the address that the symbols were rebased to, if they were
+    /// rebased.
+    pub fn base_offset(&self) -> Option<u64> {
+        self.base_offset
     }
 
-    /// Returns pointer to the name of the embedded symdb.
-    pub fn symdb_name(&self) -> Option<&'static str> {
-        self.name_symdb
+    /// Returns the symbol that the addresses were rebased on, if any.
+    pub fn anchor(&self) -> Option<&str> {
+        self.anchor.as_deref()
+    }
+
+    /// Returns the kernel module whose symbols were added, if any.
+    pub fn module(&self) -> Option<&Module> {
+        self.module.as_ref()
+    }
+
+    /// Returns the symdb, i.e., the external one if given, else the embedded
+    /// or installed one.
+    pub fn raw_symdb(&self) -> Option<Rc<InputData>> {
+        self.raw_symdb.clone()
+    }
+
+    /// Returns the name of the symdb.
+    pub fn symdb_name(&self) -> Option<String> {
+        self.name_symdb.clone()
     }
 
     pub fn sym_addr_from_name(&self, sym_name: &str) -> Option<u64> {
         self.symbols.get(sym_name).map(|s| s.addr)
     }
+
+    /// Returns the name of the symbol at `addr`.
+    ///
+    /// Of several symbols at the same address, the one with the smallest name
+    /// is returned.
+    pub fn sym_name_from_addr(&self, addr: u64) -> Option<&str> {
+        let by_addr = self.by_addr.get_or_init(|| {
+            let mut by_addr: HashMap<u64, String> = HashMap::new();
+            for (name, sym) in self.symbols.iter() {
+                match by_addr.entry(sym.addr) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(name.clone());
+                    }
+                    Entry::Occupied(mut entry) if name < entry.get() => {
+                        entry.insert(name.clone());
+                    }
+                    Entry::Occupied(_) => {}
+                }
+            }
+            by_addr
+        });
+        by_addr.get(&addr).map(String::as_str)
+    }
+
+    /// Returns the symbol called `name`.
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+
+    /// Returns an iterator over the symbols and their names, in arbitrary
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Symbol)> {
+        self.symbols.iter().map(|(name, sym)| (name.as_str(), sym))
+    }
+
+    /// Returns the number of symbols.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns true iff there are no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
 }
 
-#[derive(Copy, Clone)]
-enum SymbolScope {
+/// Visibility of a symbol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymbolScope {
+    /// Visible outside of its compilation unit.
     Global,
+    /// Only visible in its compilation unit, i.e., `static`.
     Local,
 }
 
@@ -139,22 +435,37 @@ impl From<&char> for SymbolScope {
     }
 }
 
-#[allow(non_camel_case_types, dead_code)]
-#[derive(Copy, Clone)]
-enum SymbolKind {
+/// Kind of a symbol, as the type letter that `nm` prints, regardless of the
+/// scope.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
     V, // weak object
-    v,
     A, // absolute
     R, // .rodata
-    r,
     W, // weak
-    w,
     B, // .bss
-    b,
     D, // .data
-    d,
     T, // .text
-    t,
+}
+
+impl SymbolKind {
+    /// Returns the kind of symbols in the section `name`.
+    fn from_section(name: &str) -> Self {
+        if name.starts_with(".bss") {
+            SymbolKind::B
+        } else if name.starts_with(".rodata") {
+            SymbolKind::R
+        } else {
+            SymbolKind::D
+        }
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    /// Formats the kind as its type letter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
 }
 
 impl TryFrom<&char> for SymbolKind {
@@ -169,36 +480,423 @@ impl TryFrom<&char> for SymbolKind {
             'B' | 'b' => SymbolKind::B,
             'D' | 'd' => SymbolKind::D,
             'T' | 't' => SymbolKind::T,
-            _ => {
-                log::warn!("{} is not a valid symbol kind.", chr);
-                bail!("{} is not a valid symbol kind.", chr)
-            }
+            _ => bail!("{} is not a valid symbol kind.", chr),
         })
     }
 }
 
+/// Source of the type of a symbol.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TypeSource {
+    SymDb,
+    BtfVariable,
+    BtfFunction,
+}
+
+/// Numbers of symbols that got their type from each source, see
+/// [`Symbols::type_sources`].
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TypeSources {
+    /// Name of the symdb, if types were looked up in one.
+    pub symdb: Option<String>,
+    pub symdb_types: usize,
+    pub btf_variables: usize,
+    pub btf_functions: usize,
+}
+
 /// Information about a single symbol.
-#[allow(dead_code)]
+#[derive(Clone)]
 pub struct Symbol {
     addr: u64,
-    t: Option<String>,
+    t: Option<TypeDescr>,
+    /// Where the type comes from, if there is one.
+    source: Option<TypeSource>,
     kind: SymbolKind,
     scope: SymbolScope,
+    /// Scope of the function in BTF, if the symbol is one, see
+    /// [`Symbol::linkage`].
+    linkage: Option<SymbolScope>,
     constant_data: Option<String>,
+    /// Section of a kernel module symbol, see [`Symbols::module`].
+    section: Option<Rc<str>>,
 }
 
 impl Symbol {
-    pub fn r#type(&self) -> Option<&String> {
-        self.t.as_ref()
+    /// Returns true iff the symbol is in a text section, i.e., a function.
+    fn is_text(&self) -> bool {
+        matches!(self.kind, SymbolKind::T | SymbolKind::W)
+    }
+
+    pub fn r#type(&mut self) -> Option<TypeDescr> {
+        self.t.take()
+    }
+
+    /// Returns where the type of the symbol comes from, if it has one.
+    pub fn type_source(&self) -> Option<TypeSource> {
+        self.source
+    }
+
+    /// Returns the kind of the symbol.
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    /// Returns the scope of the symbol.
+    pub fn scope(&self) -> SymbolScope {
+        self.scope
     }
 
     pub fn address(&self) -> u64 {
         self.addr
     }
 
+    /// Returns true iff the symbol is visible outside of its compilation unit.
+    pub fn is_global(&self) -> bool {
+        matches!(self.scope, SymbolScope::Global)
+    }
+
+    /// Returns the linkage of the symbol, the one of its function in BTF if
+    /// there is one, and its scope in the symbol source otherwise.
+    pub fn linkage(&self) -> SymbolScope {
+        self.linkage.unwrap_or(self.scope)
+    }
+
     pub fn constant_data(&mut self) -> Option<String> {
         self.constant_data.take()
     }
+
+    /// Returns the section of the symbol if it is a symbol of the kernel
+    /// module.
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+}
+
+/// Kernel module whose symbols were added, see `--module`.
+#[derive(Clone, Debug)]
+pub struct Module {
+    pub name: String,
+    /// Load addresses of the sections, by name. Empty if the addresses of the
+    /// module symbols are offsets into their sections.
+    pub bases: BTreeMap<String, u64>,
+}
+
+/// Symbols read from a symbol source, whose names can appear more than once.
+#[derive(Default)]
+struct SymbolCandidates {
+    by_name: HashMap<String, Vec<Symbol>>,
+}
+
+impl SymbolCandidates {
+    /// Adds the symbol `sym` called `name`.
+    fn insert(&mut self, name: &str, sym: Symbol) {
+        self.by_name
+            .entry(String::from(name))
+            .or_default()
+            .push(sym);
+    }
+
+    /// Returns the symbols by name, where names that appear more than once are
+    /// resolved according to `policy`.
+    ///
+    /// Names are not suitable to disambiguate symbols. ISF nevertheless does
+    /// just that.
+    fn resolve(self, policy: DupSymbols) -> HashMap<String, Symbol> {
+        let mut symbols: HashMap<String, Symbol> = HashMap::new();
+        let mut extras: Vec<(String, Vec<Symbol>)> = Vec::new();
+        let real_names: HashSet<String> = self.by_name.keys().cloned().collect();
+
+        for (name, mut syms) in self.by_name {
+            if syms.len() == 1 {
+                symbols.insert(name, syms.remove(0));
+                continue;
+            }
+            log::trace!("Symbol name {} is ambiguous.", name);
+            match policy {
+                DupSymbols::Drop => {}
+                DupSymbols::First => {
+                    symbols.insert(name, syms.remove(0));
+                }
+                DupSymbols::LowestAddr => {
+                    let lowest = (0..syms.len())
+                        .min_by_key(|idx| syms[*idx].addr)
+                        .expect("BUG: no symbols for name");
+                    symbols.insert(name, syms.swap_remove(lowest));
+                }
+                DupSymbols::Suffix => {
+                    let first = syms.remove(0);
+                    symbols.insert(name.clone(), first);
+                    extras.push((name, syms));
+                }
+            }
+        }
+
+        // Suffixes are assigned in a deterministic order.
+        extras.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, syms) in extras {
+            for (n, sym) in syms.into_iter().enumerate() {
+                let suffixed = names::suffixed_symbol(&name, n + 1, |candidate| {
+                    real_names.contains(candidate) || symbols.contains_key(candidate)
+                });
+                symbols.insert(suffixed, sym);
+            }
+        }
+
+        symbols
+    }
+
+    /// Adds the symbols of `other`, which were read from another symbol
+    /// source, except those that are already here at the same address.
+    fn extend(&mut self, other: SymbolCandidates) {
+        for (name, syms) in other.by_name {
+            let known = self.by_name.entry(name).or_default();
+            let nr_known = known.len();
+            for sym in syms {
+                if !known[..nr_known].iter().any(|k| k.addr == sym.addr) {
+                    known.push(sym);
+                }
+            }
+        }
+    }
+
+    /// Returns the number of names that appear more than once.
+    fn nr_ambiguous(&self) -> usize {
+        self.by_name.values().filter(|syms| syms.len() > 1).count()
+    }
+}
+
+/// Upper bound for the length of the Linux banner.
+const MAX_BANNER_LEN: usize = 1024;
+
+/// Maximum fraction of the lines of a symbol map that may be invalid.
+const MAX_INVALID_MAP_LINES: f64 = 0.01;
+
+/// Fraction of the addresses of a symbol map above which they are considered
+/// hidden by `kernel.kptr_restrict`. Some per-CPU symbols are at zero anyway.
+const MAX_ZERO_ADDRESSES: f64 = 0.9;
+
+/// Returns the text of the symbol map `raw`, without byte order mark.
+fn map_text(raw: &[u8]) -> Result<&str> {
+    let text = str::from_utf8(raw).context("Error while reading system map: invalid UTF-8")?;
+    Ok(text.strip_prefix('\u{feff}').unwrap_or(text))
+}
+
+/// Returns the symbols of the symbol map `raw` called `name` in `format`.
+///
+/// Fails if too many lines are invalid, see [`MAX_INVALID_MAP_LINES`].
+fn parse_map_data(raw: &[u8], name: &str, format: MapFormat) -> Result<SymbolCandidates> {
+    let mut candidates = SymbolCandidates::default();
+
+    let (mut nr_lines, mut invalid, mut first_error) = (0, Vec::new(), None);
+    let mut extra_fields = false;
+    for (idx, line) in map_text(raw)?.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Module symbols are followed by the module name in brackets.
+        if matches!(format, MapFormat::Kallsyms)
+            && line
+                .split_whitespace()
+                .nth(3)
+                .is_some_and(|field| field.starts_with('['))
+        {
+            continue;
+        }
+        nr_lines += 1;
+        let (sym_name, sym, rest) = match parse_map_line(line) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::warn!(
+                    target: diagnostics::SYMBOLS,
+                    code = "invalid_map_line",
+                    file:% = name,
+                    line = idx + 1,
+                    error:% = format!("{:#}", e);
+                    "{}:{}: {:#}: {}",
+                    name,
+                    idx + 1,
+                    e,
+                    line
+                );
+                invalid.push(idx + 1);
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        if !rest.is_empty() && !extra_fields {
+            log::info!(
+                "{}:{}: ignoring fields after the symbol name: {}",
+                name,
+                idx + 1,
+                rest.join(" ")
+            );
+            extra_fields = true;
+        }
+        candidates.insert(sym_name, sym);
+    }
+    if let (Some(first), Some(source)) = (invalid.first(), first_error) {
+        if invalid.len() as f64 > nr_lines as f64 * MAX_INVALID_MAP_LINES {
+            return Err(error::Error::MapParse {
+                name: name.to_owned(),
+                line: *first,
+                invalid: invalid.len(),
+                lines: nr_lines,
+                source,
+            }
+            .into());
+        }
+        log::warn!("Skipped {} invalid lines of {}", invalid.len(), name);
+    }
+
+    Ok(candidates)
+}
+
+/// Returns the name and the symbol on the `line` of a symbol map, followed by
+/// the fields after the name.
+///
+/// Fields can be separated by any whitespace, e.g., the padding of `nm`.
+fn parse_map_line(line: &str) -> Result<(&str, Symbol, Vec<&str>)> {
+    let mut fields = line.split_whitespace();
+    let (Some(addr), Some(chr), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+        bail!("Expected `<address> <type> <name>`")
+    };
+    let addr =
+        u64::from_str_radix(addr, 16).with_context(|| format!("Invalid address {}", addr))?;
+    let mut chars = chr.chars();
+    let (Some(chr), None) = (chars.next(), chars.next()) else {
+        bail!("Invalid symbol type {}", chr)
+    };
+    let sym = Symbol {
+        addr,
+        t: None,
+        source: None,
+        kind: SymbolKind::try_from(&chr)?,
+        scope: SymbolScope::from(&chr),
+        linkage: None,
+        constant_data: None,
+        section: None,
+    };
+
+    Ok((name, sym, fields.collect()))
+}
+
+/// Recovers the symbols from the kallsyms tables in the memory image `raw`.
+///
+/// For ELF vmcores, only the loadable segments are searched, for LiME images
+/// only the ranges, unless the image has the `format` raw.
+fn image_kallsyms(
+    raw: &[u8],
+    format: ImageFormat,
+    endian: Endian,
+    pointer_size: usize,
+) -> Result<Vec<KallsymsEntry>> {
+    let detect = format == ImageFormat::Auto;
+    let entries = if detect && (vmcore::is_compressed_kdump(raw) || elf::is_core(raw)) {
+        let vmcore = Vmcore::parse(raw)?;
+        image::kallsyms_in(raw, &vmcore.segments(), endian, pointer_size)
+    } else if detect && lime::is_lime(raw) {
+        let lime = Lime::parse(raw)?;
+        image::kallsyms_in(raw, &lime.segments(), endian, pointer_size)
+    } else {
+        image::kallsyms(raw, endian, pointer_size)
+    };
+    entries.context("No kallsyms tables found in memory image")
+}
+
+/// Returns the address of the symbol `name` in the first of the System.map
+/// files `maps` that has it.
+pub fn map_address(maps: &[&Path], name: &str) -> Result<u64> {
+    for map in maps {
+        let raw = InputData::map_file(map)?;
+        let addr = map_text(&raw)?
+            .lines()
+            .filter_map(|line| parse_map_line(line).ok())
+            .find_map(|(sym_name, sym, _)| (sym_name == name).then_some(sym.addr));
+        if let Some(addr) = addr {
+            return Ok(addr);
+        }
+    }
+    let maps: Vec<String> = maps.iter().map(|map| map.display().to_string()).collect();
+    bail!("No symbol {} in {}", name, maps.join(", "))
+}
+
+/// Returns `addr` in the canonical form of kernel addresses of `arch`.
+///
+/// Addresses that lack the upper bits, as some tools drop them, are
+/// sign-extended from the narrowest of the widths of `arch` that they fit in,
+/// 32, 48, or 57 bits on x86_64, and also 39 bits on riscv64. Tags in the top
+/// byte of arm64 addresses, e.g., of KASAN, are replaced with the sign of
+/// bit 55. Other addresses, and those of other architectures, are returned as
+/// they are.
+pub fn canonical_address(arch: Architecture, addr: u64) -> u64 {
+    let widths: &[u32] = match arch {
+        Architecture::X86_64 => &[32, 48, 57],
+        Architecture::Riscv64 => &[32, 39, 48, 57],
+        Architecture::Arm64 => {
+            return if addr & (1 << 55) != 0 {
+                addr | 0xff << 56
+            } else {
+                addr & !(0xff << 56)
+            };
+        }
+        _ => return addr,
+    };
+    let Some(&width) = widths.iter().find(|&&width| addr >> width == 0) else {
+        return addr;
+    };
+    if addr & (1 << (width - 1)) == 0 {
+        return addr;
+    }
+    addr | (u64::MAX << width)
+}
+
+/// Format of a symbol map.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MapFormat {
+    /// System.map file generated by the kernel build.
+    SystemMap,
+    /// kallsyms file of a running kernel, which also lists module symbols.
+    Kallsyms,
+    /// Symbol table of an ELF file, i.e., vmlinux.
+    Symtab,
+}
+
+/// Symbol map that contributed symbols.
+pub struct SymbolSource {
+    pub raw: Rc<InputData>,
+    pub name: String,
+    pub format: MapFormat,
+}
+
+impl SymbolSource {
+    /// Returns the addresses of the symbols in this source that are called one
+    /// of `names`.
+    ///
+    /// The first symbol with a name counts, module symbols in kallsyms are
+    /// skipped.
+    pub fn addresses(&self, names: &[&str]) -> Result<HashMap<String, u64>> {
+        let mut addrs = HashMap::new();
+        match self.format {
+            MapFormat::Symtab => {
+                for (name, addr, _) in elf::symbols(&self.raw)? {
+                    if names.contains(&name.as_str()) {
+                        addrs.entry(name).or_insert(addr);
+                    }
+                }
+            }
+            MapFormat::SystemMap | MapFormat::Kallsyms => {
+                for line in map_text(&self.raw)?.lines() {
+                    let Ok((name, sym, rest)) = parse_map_line(line) else {
+                        continue;
+                    };
+                    if rest.is_empty() && names.contains(&name) {
+                        addrs.entry(name.to_owned()).or_insert(sym.addr);
+                    }
+                }
+            }
+        }
+        Ok(addrs)
+    }
 }
 
 /// Used to build up symbol information by combining different sources.
@@ -213,158 +911,1357 @@ impl SymbolsBuilder {
         self.0
     }
 
-    /// Add symbol information from a System.map file.
-    fn add_from_system_map(mut self, map: &PathBuf) -> Result<Self> {
-        let mut system_map_symbols: HashMap<String, Symbol> = HashMap::new();
-        // Names are not suitable to disambiguate symbols. ISF nevertheless does
-        // just that. If a symbol name appears more than once we ignore it all
-        // together.
-        let mut ambiguous_names: HashSet<String> = HashSet::new();
-
-        for line in BufReader::new(File::open(map)?).lines() {
-            let Ok(line) = line else {
-                bail!("Error while reading system map: {}", line.unwrap_err())
-            };
-            match line.split(' ').collect::<Vec<&str>>()[..] {
-                [addr, scope, name] => {
-                    if ambiguous_names.contains(name) {
-                        continue;
-                    }
-                    if system_map_symbols.contains_key(name) {
-                        system_map_symbols.remove(name);
-                        ambiguous_names.insert(String::from(name));
-                        log::trace!("Symbol name {} is ambiguous, dropping.", name);
-                        continue;
-                    }
-                    let Some(scope) = scope.chars().next() else {
-                        bail!("Invalid scope in system map: {}", scope)
-                    };
-                    let Ok(addr) = u64::from_str_radix(addr, 16) else {
-                        bail!("Invalid address in system map: {}", addr)
-                    };
-                    system_map_symbols.insert(
-                        String::from(name),
-                        Symbol {
-                            addr,
-                            t: None,
-                            kind: SymbolKind::try_from(&scope)?,
-                            scope: SymbolScope::from(&scope),
-                            constant_data: None,
-                        },
-                    )
-                }
-                _ => bail!("Invalid format of system map: {}", line),
-            };
+    /// Add symbol information from System.map files.
+    ///
+    /// Each System.map is read only once, from a file or standard input, and
+    /// the same data is hashed for the metadata. The symbols of all files are
+    /// resolved together, i.e., a name at different addresses in two files is
+    /// ambiguous, while the same symbol in two files is not. If there is more
+    /// than one file, one of them must have `anchor`, or else `_stext`.
+    fn add_from_system_maps(mut self, maps: &[PathBuf], anchor: Option<&str>) -> Result<Self> {
+        let mut candidates = SymbolCandidates::default();
+        let mut sources = Vec::new();
+        for map in maps {
+            let raw = InputData::load(map)?;
+            let name = input::file_name(map)?;
+            let mut map_candidates = parse_map_data(&raw, &name, MapFormat::SystemMap)?;
+            self.canonicalize(&mut map_candidates, &name);
+            candidates.extend(map_candidates);
+            sources.push(SymbolSource {
+                raw: Rc::new(raw),
+                name,
+                format: MapFormat::SystemMap,
+            });
+        }
+        let names: Vec<&str> = sources.iter().map(|source| source.name.as_str()).collect();
+        let names = names.join(", ");
+        let anchor = anchor.unwrap_or("_stext");
+        if sources.len() > 1 && !candidates.by_name.contains_key(anchor) {
+            bail!("None of the System.map files {} has {}", names, anchor);
         }
 
-        let stext_addr: u64 = match system_map_symbols.get("_stext") {
-            Some(sym) => sym.addr,
-            _ => bail!("No _stext symbol found in system map."),
-        };
+        let system_map_symbols = self.resolve(candidates);
+        self.check_zero_addresses(&system_map_symbols, &names, MapFormat::SystemMap)?;
+        self.merge(system_map_symbols, sources);
+
+        Ok(self)
+    }
+
+    /// Add symbol information from a kallsyms file.
+    fn add_from_kallsyms(self, path: &Path) -> Result<Self> {
+        // Files in procfs can not be mapped.
+        let raw = InputData::load(path)?;
+        self.add_from_map_data(raw, input::file_name(path)?, MapFormat::Kallsyms)
+    }
 
-        self.0.symbols = system_map_symbols
+    /// Add symbol information recovered from the kallsyms tables in the memory
+    /// image at `path` with the layout `format`, of a kernel for `arch` with
+    /// the byte order `endian`.
+    ///
+    /// The symbols are added like a kallsyms file called `<image>.kallsyms`.
+    fn add_from_image(
+        self,
+        path: &Path,
+        format: ImageFormat,
+        arch: Architecture,
+        endian: Endian,
+    ) -> Result<Self> {
+        let raw = InputData::map_file(path)?;
+        let pointer_size = usize::from(arch.pointer_size());
+        let map: String = image_kallsyms(&raw, format, endian, pointer_size)?
             .iter()
-            .map(|(name, sym)| {
-                let addr = sym.addr - (stext_addr - self.0.base_offset);
-                (
-                    String::from(name),
-                    Symbol {
-                        addr,
-                        t: sym.t.clone(),
-                        kind: sym.kind,
-                        scope: sym.scope,
-                        constant_data: None,
-                    },
+            .map(|entry| {
+                format!(
+                    "{:0width$x} {} {}\n",
+                    entry.addr,
+                    entry.kind,
+                    entry.name,
+                    width = 2 * pointer_size
                 )
             })
             .collect();
+        self.add_from_map_data(
+            map.into_bytes().into(),
+            format!("{}.kallsyms", input::file_name(path)?),
+            MapFormat::Kallsyms,
+        )
+    }
+
+    /// Add symbol information from the symbol map `raw` called `name`.
+    fn add_from_map_data(
+        mut self,
+        raw: InputData,
+        name: String,
+        format: MapFormat,
+    ) -> Result<Self> {
+        let mut candidates = parse_map_data(&raw, &name, format)?;
+        self.canonicalize(&mut candidates, &name);
+        let system_map_symbols = self.resolve(candidates);
+        self.check_zero_addresses(&system_map_symbols, &name, format)?;
+
+        self.merge(
+            system_map_symbols,
+            vec![SymbolSource {
+                raw: Rc::new(raw),
+                name,
+                format,
+            }],
+        );
+
+        Ok(self)
+    }
+
+    /// Brings the addresses of the `candidates` of the symbol map `name` into
+    /// canonical form with `--canonicalize-addresses`, see
+    /// [`canonical_address`].
+    fn canonicalize(&self, candidates: &mut SymbolCandidates, name: &str) {
+        let Some(arch) = self.0.canonical_arch else {
+            return;
+        };
+        let (mut fixed, mut first) = (0, None);
+        for sym in candidates.by_name.values_mut().flatten() {
+            let addr = canonical_address(arch, sym.addr);
+            if addr != sym.addr {
+                first.get_or_insert((sym.addr, addr));
+                sym.addr = addr;
+                fixed += 1;
+            }
+        }
+        if let Some((from, to)) = first {
+            log::warn!(
+                "Canonicalized {} addresses of {}, e.g., {:#x} to {:#x}",
+                fixed,
+                name,
+                from,
+                to
+            );
+        } else {
+            log::debug!("All addresses of {} are canonical", name);
+        }
+    }
+
+    /// Fails if the addresses of the `symbols` of the symbol map `name` are
+    /// hidden, i.e., zero, like in `/proc/kallsyms` read without root while
+    /// `kernel.kptr_restrict` is set, unless `--allow-zero-addresses` is given.
+    ///
+    /// The addresses count as hidden if `_stext` or nearly all symbols are at
+    /// zero.
+    fn check_zero_addresses(
+        &self,
+        symbols: &HashMap<String, Symbol>,
+        name: &str,
+        format: MapFormat,
+    ) -> Result<()> {
+        let zero = symbols.values().filter(|sym| sym.addr == 0).count();
+        let what = if zero > 0 && zero == symbols.len() {
+            String::from("All addresses in")
+        } else if zero as f64 > symbols.len() as f64 * MAX_ZERO_ADDRESSES {
+            format!("{} of {} addresses in", zero, symbols.len())
+        } else if symbols.get("_stext").is_some_and(|sym| sym.addr == 0) {
+            String::from("The address of _stext and likely all others in")
+        } else {
+            return Ok(());
+        };
+        let source = match format {
+            MapFormat::Kallsyms => "kallsyms",
+            _ => name,
+        };
+        if self.0.allow_zero_addresses {
+            // Logged as an error to be shown by default, the profile is
+            // unlikely to work.
+            log::error!(
+                "{} {} are zero, the symbol addresses of the profile are wrong.",
+                what,
+                source
+            );
+            return Ok(());
+        }
+        bail!(
+            "{} {} are zero, the kernel hides them with kernel.kptr_restrict. \
+             Read {} as root, or after `sysctl kernel.kptr_restrict=0`, \
+             or pass --allow-zero-addresses to generate a profile anyway.",
+            what,
+            source,
+            name
+        )
+    }
+
+    /// Add symbol information from the symbol table of the ELF file `raw`
+    /// called `name`, i.e., vmlinux.
+    ///
+    /// The symbols are the same that `nm` lists, which is what System.map is
+    /// generated from.
+    fn add_from_elf(mut self, raw: Rc<InputData>, name: String) -> Result<Self> {
+        let mut candidates = SymbolCandidates::default();
+
+        for (sym_name, addr, chr) in elf::symbols(&raw)? {
+            candidates.insert(
+                &sym_name,
+                Symbol {
+                    addr,
+                    t: None,
+                    source: None,
+                    kind: SymbolKind::try_from(&chr)?,
+                    scope: SymbolScope::from(&chr),
+                    linkage: None,
+                    constant_data: None,
+                    section: None,
+                },
+            );
+        }
+        let elf_symbols = self.resolve(candidates);
+
+        self.merge(
+            elf_symbols,
+            vec![SymbolSource {
+                raw,
+                name,
+                format: MapFormat::Symtab,
+            }],
+        );
+
+        Ok(self)
+    }
+
+    /// Add the symbols in the symbol table of the kernel module of the BTF
+    /// file, see `--module`.
+    ///
+    /// The addresses are offsets into the sections of the symbols, unless
+    /// load addresses of sections are given with `--module-base`. Then the
+    /// symbols are moved to the load address of their section, and symbols of
+    /// other sections are dropped. Symbols that the kernel has as well are
+    /// skipped, as the profile is still one of the kernel.
+    fn add_module_from_cli(mut self, cli: &GenerateArgs, btf: &Btf) -> Result<Self> {
+        let Some(module) = btf.module() else {
+            return Ok(self);
+        };
+        let bases: BTreeMap<String, u64> = cli.module_base.iter().cloned().collect();
+        let mut sections: HashMap<String, Rc<str>> = HashMap::new();
+        let mut candidates = SymbolCandidates::default();
+        let mut unplaced = 0;
+
+        let raw = btf.raw();
+        let module_symbols = elf::module_symbols(&raw)
+            .with_context(|| format!("Unable to read the symbols of {}", btf.name()))?;
+        for (sym_name, section, offset, chr) in module_symbols {
+            let addr = if bases.is_empty() {
+                offset
+            } else if let Some(base) = bases.get(&section) {
+                base.wrapping_add(offset)
+            } else {
+                log::trace!(
+                    "Module symbol {} is in section {} without base",
+                    sym_name,
+                    section
+                );
+                unplaced += 1;
+                continue;
+            };
+            let section = sections
+                .entry(section)
+                .or_insert_with_key(|section| section.as_str().into());
+            candidates.insert(
+                &sym_name,
+                Symbol {
+                    addr,
+                    t: None,
+                    source: None,
+                    kind: SymbolKind::try_from(&chr)?,
+                    scope: SymbolScope::from(&chr),
+                    linkage: None,
+                    constant_data: None,
+                    section: Some(section.clone()),
+                },
+            );
+        }
+        for section in bases
+            .keys()
+            .filter(|section| !sections.contains_key(*section))
+        {
+            log::warn!(
+                target: diagnostics::SYMBOLS,
+                code = "empty_module_section",
+                module:% = module,
+                section:% = section;
+                "Module {} has no symbols in section {}",
+                module,
+                section
+            );
+        }
+        if unplaced > 0 {
+            log::warn!(
+                target: diagnostics::SYMBOLS,
+                code = "unplaced_module_symbols",
+                module:% = module,
+                count = unplaced;
+                "Dropped {} symbols of module {} in sections without --module-base",
+                unplaced,
+                module
+            );
+        }
+
+        let module_symbols = self.resolve(candidates);
+        let (total, mut shadowed) = (module_symbols.len(), 0);
+        for (name, sym) in module_symbols {
+            match self.0.symbols.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(sym);
+                }
+                Entry::Occupied(entry) => {
+                    log::debug!(
+                        "Module symbol {} at {:#x} is also a kernel symbol at {:#x}",
+                        entry.key(),
+                        sym.addr,
+                        entry.get().addr
+                    );
+                    shadowed += 1;
+                }
+            }
+        }
+        log::info!(
+            "Got {} symbols from module {}, {}",
+            total,
+            module,
+            if bases.is_empty() {
+                "at offsets into their sections"
+            } else {
+                "at the load addresses of their sections"
+            }
+        );
+        if shadowed > 0 {
+            log::warn!(
+                target: diagnostics::SYMBOLS,
+                code = "shadowed_module_symbols",
+                module:% = module,
+                count = shadowed;
+                "{} symbols of module {} are also kernel symbols, keeping the kernel ones",
+                shadowed,
+                module
+            );
+        }
+        self.0.module = Some(Module {
+            name: module.to_owned(),
+            bases,
+        });
+
+        Ok(self)
+    }
+
+    /// Merges the `symbols` read from `sources` into the symbols of the sources
+    /// that were added before, which take precedence.
+    ///
+    /// Symbols at different addresses in different sources are reported. A
+    /// zero address, e.g., of a local symbol in kallsyms read without
+    /// privileges, is replaced by the address in a later source.
+    fn merge(&mut self, symbols: HashMap<String, Symbol>, sources: Vec<SymbolSource>) {
+        let names: Vec<&str> = sources.iter().map(|source| source.name.as_str()).collect();
+        let source_name = names.join(", ");
+        let total = symbols.len();
+        let (mut added, mut conflicts) = (0, 0);
+        for (name, sym) in symbols {
+            let Some(known) = self.0.symbols.get_mut(&name) else {
+                self.0.symbols.insert(name, sym);
+                added += 1;
+                continue;
+            };
+            if known.addr == sym.addr || sym.addr == 0 {
+                continue;
+            }
+            if known.addr == 0 {
+                log::debug!(
+                    "Zero address of {} replaced by {:#x} from {}",
+                    name,
+                    sym.addr,
+                    source_name
+                );
+                known.addr = sym.addr;
+                continue;
+            }
+            log::debug!(
+                "Symbol {} at {:#x}, but at {:#x} in {}",
+                name,
+                known.addr,
+                sym.addr,
+                source_name
+            );
+            conflicts += 1;
+        }
+
+        log::info!(
+            "Got {} symbols from {} ({:?}), {} not in previous sources",
+            total,
+            source_name,
+            sources[0].format,
+            added
+        );
+        if conflicts > 0 {
+            log::warn!(
+                target: diagnostics::SYMBOLS,
+                code = "conflicting_symbol_addresses",
+                source:% = source_name,
+                count = conflicts;
+                "{} symbols of {} have a different address in a previous source, keeping the previous ones",
+                conflicts,
+                source_name
+            );
+        }
+        self.0.sources.extend(sources);
+    }
 
-        // record metadata
-        let name_map = String::from(
-            map.file_name()
-                .context("Path to System.map is invalid")?
-                .to_str()
-                .context("Unicode error")?,
+    /// Add symbols for the variables in the data sections of the BTF file,
+    /// which must be an executable ELF file without symbol table, i.e., a
+    /// stripped vmlinux.
+    ///
+    /// The address of a variable is the address of its ELF section plus its
+    /// offset in the section. Only variables with BTF are found, which are just
+    /// the per-CPU variables unless the kernel was built with BTF for all
+    /// global variables. In place of `_stext` the `.text` section is used.
+    fn add_from_btf_sections(mut self, btf: &Btf) -> Result<Self> {
+        let raw = btf.kernel_raw();
+        let sections = elf::section_addresses(&raw)
+            .context("Synthesizing symbols requires the BTF file to be vmlinux")?;
+        let mut candidates = SymbolCandidates::default();
+
+        for sec_var in btf.section_variables() {
+            let Some(sec_addr) = sections.get(&sec_var.section) else {
+                log::debug!(
+                    "Section {} of variable {} is not in ELF file",
+                    sec_var.section,
+                    sec_var.var.name
+                );
+                continue;
+            };
+            let kind = SymbolKind::from_section(&sec_var.section);
+            candidates.insert(
+                &sec_var.var.name,
+                Symbol {
+                    addr: sec_addr + u64::from(sec_var.offset),
+                    t: None,
+                    source: None,
+                    kind,
+                    scope: if sec_var.var.linkage == btf::Linkage::Static {
+                        SymbolScope::Local
+                    } else {
+                        SymbolScope::Global
+                    },
+                    linkage: None,
+                    constant_data: None,
+                    section: None,
+                },
+            );
+        }
+        let btf_symbols = self.resolve(candidates);
+        log::info!(
+            "Synthesized {} symbols from BTF variables",
+            btf_symbols.len()
         );
-        let file = File::open(map)?;
-        let mmap = unsafe { Mmap::map(&file) }?;
-        self.0.raw_map = Some(Rc::new(mmap));
-        self.0.name_map = Some(name_map);
 
+        self.0.symbols = btf_symbols;
+        self.rebase(|_| {
+            sections
+                .get(".text")
+                .copied()
+                .context("No .text section in ELF file.")
+        })?;
+        self.0.synthesized = true;
+
+        Ok(self)
+    }
+
+    /// Rebases the symbols of all sources on `_stext`, which is taken from the
+    /// source with the highest precedence that has it.
+    ///
+    /// Without `_stext`, the first of the other text anchors of `arch` is used.
+    /// The `anchor` from `--anchor-symbol` replaces them if given.
+    fn rebase_on_stext(mut self, arch: Architecture, anchor: Option<&str>) -> Result<Self> {
+        let mut used = None;
+        self.rebase(|symbols| {
+            if let Some(anchor) = anchor {
+                let sym =
+                    symbols
+                        .get(anchor)
+                        .ok_or_else(|| error::Error::AnchorSymbolNotFound {
+                            name: anchor.to_owned(),
+                        })?;
+                log::info!(
+                    "Rebasing on {} at {:#x} (--anchor-symbol)",
+                    anchor,
+                    sym.addr
+                );
+                used = Some(anchor.to_owned());
+                return Ok(sym.addr);
+            }
+            let anchors = arch.text_anchors();
+            let Some((anchor, sym)) = anchors
+                .iter()
+                .find_map(|anchor| Some((anchor, symbols.get(*anchor)?)))
+            else {
+                return Err(error::Error::MissingAnchorSymbol { tried: anchors }.into());
+            };
+            log::info!("Rebasing on {} at {:#x}", anchor, sym.addr);
+            used = Some((*anchor).to_owned());
+            Ok(sym.addr)
+        })?;
+        self.0.anchor = used;
         Ok(self)
     }
 
-    /// Add type information from embedded database.
-    fn add_types_from_symdb(mut self) -> Self {
-        for (name, t) in SymDb::new().into_iter() {
-            if let Some(s) = self.0.symbols.get_mut(name) {
-                log::trace!("[symdb] name {}, type {}", name, t);
-                s.t = Some(String::from(t));
+    /// Adds the symbols with v0-mangled Rust names, e.g.,
+    /// `_RNvNtCs1EKtwoKEMO2_6kernel5print11call_printk`, under their demangled
+    /// names too, e.g., `kernel::print::call_printk`, if `--demangle-rust` is
+    /// given.
+    ///
+    /// The mangled names are kept, which makes the mangled original of a
+    /// demangled name available under the same address. Demangled names that
+    /// are taken by another symbol, or that more than one symbol demangles to,
+    /// are skipped.
+    fn demangle_rust_from_cli(mut self, cli: &GenerateArgs) -> Self {
+        if !cli.demangle_rust {
+            return self;
+        }
+        let mut demangled: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+        for name in self.0.symbols.keys().filter(|name| name.starts_with("_R")) {
+            if let Ok(symbol) = rustc_demangle::try_demangle(name) {
+                // The alternate format omits the hashes of the crates.
+                demangled
+                    .entry(format!("{:#}", symbol))
+                    .or_default()
+                    .push(name);
             }
         }
 
-        // Record metadata
-        self.0.name_symdb = Some(SymDbAssets::SYMDB_NAME);
-        self.0.raw_symdb = Some(SymDb::get_raw());
+        let mut added = Vec::new();
+        for (name, mut mangled) in demangled {
+            if mangled.len() > 1 || self.0.symbols.contains_key(&name) {
+                mangled.sort();
+                log::warn!(
+                    target: diagnostics::NAMING_CONFLICTS,
+                    code = "ambiguous_rust_symbol",
+                    symbol:% = name,
+                    count = mangled.len();
+                    "Demangled Rust symbol {} is not unique, keeping only the mangled name of {}",
+                    name,
+                    mangled
+                        .iter()
+                        .map(|name| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                continue;
+            }
+            added.push((name, self.0.symbols[mangled[0]].clone()));
+        }
+        log::info!("Added {} demangled Rust symbols", added.len());
+        self.0.symbols.extend(added);
 
         self
     }
 
-    /// Add type information from BTF section.
-    pub fn add_types_from_btf(self, _btf: &Btf) -> Self {
-        log::info!("Types from BTF not implemented.");
+    /// Removes the symbols that do not match `--symbol-filter`, that match
+    /// `--symbol-exclude`, that are not of the `--symbol-kinds`, or that are
+    /// local with `--globals-only`.
+    ///
+    /// The banner and the rebasing anchor are kept, as they are needed to
+    /// use the profile.
+    fn filter_from_cli(mut self, cli: &GenerateArgs) -> Self {
+        if cli.symbol_filter.is_none()
+            && cli.symbol_exclude.is_none()
+            && cli.symbol_kinds.is_none()
+            && !cli.globals_only
+        {
+            return self;
+        }
+        let total = self.0.symbols.len();
+        let anchor = self.0.anchor.clone();
+        // Dropped symbols count for the first filter that drops them.
+        let (mut by_name, mut by_kind, mut by_scope) = (0, 0, 0);
+        self.0.symbols.retain(|name, sym| {
+            let name_matches = cli
+                .symbol_filter
+                .as_ref()
+                .is_none_or(|re| re.is_match(name))
+                && !cli
+                    .symbol_exclude
+                    .as_ref()
+                    .is_some_and(|re| re.is_match(name));
+            let kind_matches = cli
+                .symbol_kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&sym.kind));
+            let scope_matches = !cli.globals_only || sym.is_global();
+            if name_matches && kind_matches && scope_matches {
+                return true;
+            }
+            if name == "linux_banner" || Some(name) == anchor.as_ref() {
+                log::debug!("Keeping {} despite the symbol filters", name);
+                return true;
+            }
+            if !name_matches {
+                by_name += 1;
+            } else if !kind_matches {
+                by_kind += 1;
+            } else {
+                by_scope += 1;
+            }
+            false
+        });
+        log::info!(
+            "Dropped {} of {} symbols by the symbol filters",
+            total - self.0.symbols.len(),
+            total
+        );
+        log::debug!(
+            "Dropped {} symbols by name, {} by kind, and {} local symbols",
+            by_name,
+            by_kind,
+            by_scope
+        );
+
         self
     }
 
+    /// Returns the symbols in `candidates`, where names that appear more than
+    /// once are resolved according to the policy for duplicate symbols.
+    fn resolve(&self, candidates: SymbolCandidates) -> HashMap<String, Symbol> {
+        let nr_ambiguous = candidates.nr_ambiguous();
+        if nr_ambiguous > 0 {
+            log::info!(
+                "{} symbol names are ambiguous, resolving with policy {:?}",
+                nr_ambiguous,
+                self.0.dup_symbols
+            );
+        }
+        candidates.resolve(self.0.dup_symbols)
+    }
+
+    /// Shifts the symbols such that `_stext` is at the base offset.
+    ///
+    /// `stext_addr` returns the address of `_stext` in the symbols, which is
+    /// only required when rebasing.
+    ///
+    /// Symbols below the kernel image, i.e., below `_stext` and all text
+    /// symbols, are not relocated and keep their addresses. These are per-CPU
+    /// variables on x86_64 and SMP kernels, whose addresses are offsets into
+    /// the per-CPU area close to zero, and absolute symbols, e.g.,
+    /// `__kcfi_typeid_*`. Symbols that would be shifted below zero are also
+    /// kept, with a warning.
+    fn rebase(
+        &mut self,
+        stext_addr: impl FnOnce(&HashMap<String, Symbol>) -> Result<u64>,
+    ) -> Result<()> {
+        let Some(base_offset) = self.0.base_offset else {
+            return Ok(());
+        };
+        let stext = stext_addr(&self.0.symbols)?;
+        let base_offset = match self.0.arm64_layout {
+            Some(layout) => layout.base_offset(stext),
+            None => base_offset,
+        };
+        self.0.base_offset = Some(base_offset);
+        let image_base = self
+            .0
+            .symbols
+            .values()
+            .filter(|sym| sym.is_text() && sym.addr != 0)
+            .map(|sym| sym.addr)
+            .fold(stext, u64::min);
+        let max = self.0.address_width.map_or(u64::MAX, |width| width.max());
+        let (mut absolute, mut below_image, mut underflow) = (0, 0, Vec::new());
+        for (name, sym) in self.0.symbols.iter_mut() {
+            // Absolute symbols are not relocated wherever they are.
+            if sym.kind == SymbolKind::A {
+                absolute += 1;
+                continue;
+            }
+            if sym.addr < image_base {
+                below_image += 1;
+                continue;
+            }
+            let addr = if stext >= base_offset {
+                sym.addr.checked_sub(stext - base_offset)
+            } else {
+                sym.addr.checked_add(base_offset - stext)
+            };
+            match addr {
+                // The shift wraps around within the address width. Addresses
+                // beyond it are left to `--address-overflow`.
+                Some(addr) if sym.addr <= max => sym.addr = addr & max,
+                Some(addr) => sym.addr = addr,
+                None => underflow.push(name.as_str()),
+            }
+        }
+        if absolute > 0 {
+            log::info!("Not rebasing {} absolute symbols", absolute);
+        }
+        if below_image > 0 {
+            log::info!(
+                "Not rebasing {} per-CPU symbols below {:#x}",
+                below_image,
+                image_base
+            );
+        }
+        if !underflow.is_empty() {
+            underflow.sort_unstable();
+            log::warn!(
+                target: diagnostics::SYMBOLS,
+                code = "rebase_overflow",
+                count = underflow.len(),
+                symbol:% = underflow[0];
+                "Not rebasing {} symbols whose addresses would overflow, e.g., {}",
+                underflow.len(),
+                underflow[0]
+            );
+        }
+        Ok(())
+    }
+
+    /// Add type information from the embedded or installed database and, if
+    /// given, the external one, which wins on conflicts.
+    ///
+    /// Entries for a range of kernel versions only apply if the `version` of
+    /// the kernel is known and in the range.
+    fn add_types_from_symdb(
+        mut self,
+        symdb: Option<&Path>,
+        version: Option<KernelVersion>,
+    ) -> Result<Self> {
+        if version.is_none() {
+            log::debug!("Unknown kernel version, skipping versioned symdb entries");
+        }
+        self.add_types_from_default_symdb(version)?;
+
+        if let Some(path) = symdb {
+            let name = input::file_name(path)?;
+            self.add_types_from_symdb_file(path, name, version)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Add type information from the embedded database.
+    #[cfg(feature = "embedded-symdb")]
+    fn add_types_from_default_symdb(&mut self, version: Option<KernelVersion>) -> Result<()> {
+        let symdb = SymDb::embedded();
+        symdb.warn_invalid();
+        self.add_types(symdb, version);
+        self.0.name_symdb = Some(SymDbAssets::SYMDB_NAME.to_owned());
+        self.0.raw_symdb = Some(Rc::new(SymDb::get_raw().into()));
+        Ok(())
+    }
+
+    /// Add type information from the installed database, if any, see
+    /// [`installed_symdb_path`], which must exist if its path is given by the
+    /// environment.
+    #[cfg(not(feature = "embedded-symdb"))]
+    fn add_types_from_default_symdb(&mut self, version: Option<KernelVersion>) -> Result<()> {
+        match SymDb::installed_path() {
+            Some((path, from_env)) if from_env || path.exists() => {
+                // Record the name of the file that a link, e.g., of a package,
+                // is to.
+                let real_path = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                let name = input::file_name(&real_path)?;
+                self.add_types_from_symdb_file(&path, name, version)
+                    .with_context(|| format!("Invalid installed symdb, see ${}", SYMDB_ENV))
+            }
+            Some((path, _)) => {
+                log::debug!("No symdb installed at {}", path.display());
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Add type information from the database at `path`, which is referred to
+    /// as `name`.
+    fn add_types_from_symdb_file(
+        &mut self,
+        path: &Path,
+        name: String,
+        version: Option<KernelVersion>,
+    ) -> Result<()> {
+        let raw = InputData::load(path)
+            .with_context(|| format!("Unable to read symdb {}", path.display()))?;
+        let symdb = SymDb::parse(&raw, &name)?;
+        log::debug!("Read {} entries from symdb {}", symdb.lines.len(), name);
+        symdb.warn_invalid();
+        self.add_types(symdb, version);
+        self.0.name_symdb = Some(name);
+        self.0.raw_symdb = Some(Rc::new(raw));
+        Ok(())
+    }
+
+    fn add_types(&mut self, symdb: SymDb, version: Option<KernelVersion>) {
+        for entry in symdb {
+            if !entry.applies_to(version) {
+                continue;
+            }
+            if let Some(s) = self.0.symbols.get_mut(&entry.name) {
+                log::trace!("[symdb] name {}, type {:?}", entry.name, entry.t);
+                s.t = Some(entry.t);
+                s.source = Some(TypeSource::SymDb);
+            }
+        }
+    }
+
+    /// Add type information from the variables and functions in the BTF
+    /// section.
+    ///
+    /// Overrides the types from the symdb. Functions only type text symbols,
+    /// whose linkage they also give unless all functions are static, which
+    /// means that the BTF does not record it. `rich_functions` includes the
+    /// prototype in function descriptors.
+    pub fn add_types_from_btf(mut self, btf: &Btf, rich_functions: bool) -> Self {
+        let variables = self.declared_types(btf, btf.variables(), rich_functions);
+        let functions = self.declared_types(btf, btf.functions(), rich_functions);
+
+        // pahole records all functions of vmlinux as static, i.e., without
+        // their linkage.
+        let has_linkage = functions
+            .values()
+            .any(|(_, linkage)| *linkage == Some(SymbolScope::Global));
+        if !has_linkage {
+            log::debug!("[btf] all functions are static, using the scope of the symbols");
+        }
+
+        let mut typed = 0;
+        for (name, (t, linkage)) in functions {
+            if variables.contains_key(&name) {
+                log::warn!(
+                    target: diagnostics::NAMING_CONFLICTS,
+                    code = "variable_and_function",
+                    symbol:% = name;
+                    "{} is declared as variable and function in BTF, using the variable",
+                    name
+                );
+                continue;
+            }
+            let Some(s) = self.0.symbols.get_mut(&name) else {
+                continue;
+            };
+            if !s.is_text() {
+                log::trace!("[btf] function {} is not a text symbol", name);
+                continue;
+            }
+            log::trace!("[btf] function {}, type {:?}", name, t);
+            s.t = Some(t);
+            s.source = Some(TypeSource::BtfFunction);
+            if let Some(linkage) = linkage.filter(|_| has_linkage) {
+                if linkage != s.scope {
+                    log::debug!(
+                        "[btf] function {} is {:?} in BTF, but {:?} in the symbols, using the BTF linkage",
+                        name,
+                        linkage,
+                        s.scope
+                    );
+                }
+                s.linkage = Some(linkage);
+            }
+            typed += 1;
+        }
+        for (name, (t, _)) in variables {
+            if let Some(s) = self.0.symbols.get_mut(&name) {
+                log::trace!("[btf] variable {}, type {:?}", name, t);
+                s.t = Some(t);
+                s.source = Some(TypeSource::BtfVariable);
+                typed += 1;
+            }
+        }
+        log::debug!(
+            "Got types of {} symbols from BTF, {} symbols with types",
+            typed,
+            self.0.with_types()
+        );
+
+        self
+    }
+
+    /// Returns the types of the declarations `decls` that have a symbol, and
+    /// their scopes if their linkage is known, by name.
+    ///
+    /// Names that are declared more than once with different types are
+    /// ambiguous and dropped, like ambiguous symbols. Names that are declared
+    /// with different linkages, e.g., as `static` function in several
+    /// compilation units, have no known scope.
+    fn declared_types(
+        &self,
+        btf: &Btf,
+        decls: impl Iterator<Item = Declaration>,
+        rich_functions: bool,
+    ) -> HashMap<String, (TypeDescr, Option<SymbolScope>)> {
+        type Declared = Option<(TypeDescr, Option<SymbolScope>)>;
+        let mut types: HashMap<String, Declared> = HashMap::new();
+        for decl in decls {
+            if !self.0.symbols.contains_key(&decl.name) {
+                log::trace!("[btf] {} has no symbol", decl.name);
+                continue;
+            }
+            let t = match TypeDescr::new_symbol(btf, &decl.name, decl.tx, rich_functions) {
+                Ok(t) => t,
+                Err(err) => {
+                    log::warn!(
+                        target: diagnostics::SYMBOLS,
+                        code = "untyped_symbol",
+                        symbol:% = decl.name,
+                        error:% = format!("{:#}", err);
+                        "[btf] skipping type of {}: {:#}",
+                        decl.name,
+                        err
+                    );
+                    continue;
+                }
+            };
+            let scope = match decl.linkage {
+                btf::Linkage::Static => Some(SymbolScope::Local),
+                btf::Linkage::Global => Some(SymbolScope::Global),
+                btf::Linkage::Extern => None,
+            };
+            match types.entry(decl.name) {
+                Entry::Vacant(ent) => {
+                    ent.insert(Some((t, scope)));
+                }
+                Entry::Occupied(mut ent) => match ent.get_mut() {
+                    Some((prev, _)) if *prev != t => {
+                        log::trace!("[btf] declaration {} is ambiguous, dropping", ent.key());
+                        ent.insert(None);
+                    }
+                    Some((_, prev)) if *prev != scope => *prev = None,
+                    _ => (),
+                },
+            }
+        }
+        types
+            .into_iter()
+            .filter_map(|(name, t)| Some((name, t?)))
+            .collect()
+    }
+
     /// Add the base64 encoded banner as payload to the corresponding symbol.
     ///
     /// This is how Volatility expects it.
-    fn add_banner_from_cli(mut self, cli: &Cli) -> Result<Self> {
+    ///
+    /// Synthesized symbols may lack `linux_banner`, in which case a synthetic
+    /// symbol at address 0 is added. So may stripped System.maps, in which
+    /// case the symbol is added at the address of [`linux_banner_addr`].
+    fn add_banner_from_cli(mut self, cli: &GenerateArgs, btf: &Btf) -> Result<Self> {
+        if self.0.synthesized && !self.0.symbols.contains_key("linux_banner") {
+            return Ok(self.add_synthetic_banner_from_cli(cli));
+        }
+
         let banner = Banner::try_from(cli)?;
 
         log::info!("Found banner: {}", banner);
 
-        let Some(sym) = self.0.symbols.get_mut("linux_banner") else {
-            bail!("No symbol entry for Linux banner.")
-        };
+        let constant_data = Some(BASE64_STANDARD.encode(banner));
+        if let Some(sym) = self.0.symbols.get_mut("linux_banner") {
+            sym.constant_data = constant_data;
+            return Ok(self);
+        }
 
-        sym.constant_data = Some(BASE64_STANDARD.encode(banner));
+        self.0.symbols.insert(
+            String::from("linux_banner"),
+            Symbol {
+                addr: linux_banner_addr(cli, btf),
+                t: None,
+                source: None,
+                kind: SymbolKind::R,
+                scope: SymbolScope::Global,
+                linkage: None,
+                constant_data,
+                section: None,
+            },
+        );
 
         Ok(self)
     }
 
-    fn add_base_offset_from_cli(mut self, cli: &Cli) -> Self {
-        match cli.arch {
-            // Default offset value for x86_64
-            crate::cli::Architecture::X86_64 => self.0.base_offset = 0xffffffff81000000,
-            // Default offset value for arm64
-            crate::cli::Architecture::Arm64 => self.0.base_offset = 0xffff800080010000,
+    /// Add the base64 encoded contents of the symbols selected on the CLI as
+    /// payload to the symbols.
+    ///
+    /// The contents are read from the sections of the BTF file, which must be
+    /// vmlinux. Symbols in sections without contents, e.g., `.bss`, are
+    /// skipped.
+    fn add_constant_data_from_cli(mut self, cli: &GenerateArgs, btf: &Btf) -> Self {
+        let Some(names) = &cli.constant_data else {
+            return self;
+        };
+        let raw = btf.kernel_raw();
+        if elf::is_elf(&raw).is_err() {
+            log::warn!("Constant data can only be read from vmlinux, ignoring --constant-data.");
+            return self;
         }
+        let names: Vec<&str> = if names.is_empty() {
+            DEFAULT_CONSTANT_DATA.to_vec()
+        } else {
+            names.iter().map(String::as_str).collect()
+        };
 
-        log::debug!("Base offset set to {:#x}", self.0.base_offset);
+        for name in names {
+            let Some(sym) = self.0.symbols.get_mut(name) else {
+                log::warn!(
+                    target: diagnostics::SYMBOLS,
+                    code = "missing_constant_data_symbol",
+                    symbol:% = name;
+                    "No symbol entry for constant data of {}.",
+                    name
+                );
+                continue;
+            };
+            match elf::read_symbol_bytes(&raw, name) {
+                Ok(Some(data)) => {
+                    log::debug!(
+                        "Attaching {} bytes of constant data to {}",
+                        data.len(),
+                        name
+                    );
+                    sym.constant_data = Some(BASE64_STANDARD.encode(data));
+                }
+                Ok(None) => {
+                    log::debug!(
+                        "Symbol {} is in a section without contents, skipping.",
+                        name
+                    )
+                }
+                Err(err) => {
+                    log::warn!(
+                        target: diagnostics::SYMBOLS,
+                        code = "unreadable_constant_data",
+                        symbol:% = name,
+                        error:% = format!("{:#}", err);
+                        "Unable to read constant data of {}: {:#}",
+                        name,
+                        err
+                    )
+                }
+            }
+        }
+
+        self
+    }
+
+    /// Add a synthetic `linux_banner` symbol at address 0 that carries the
+    /// banner.
+    ///
+    /// Used for profiles without a symbol source, where the banner is optional.
+    fn add_synthetic_banner_from_cli(mut self, cli: &GenerateArgs) -> Self {
+        let Ok(banner) = Banner::try_from(cli) else {
+            log::warn!("No banner available, profile will not contain a Linux banner.");
+            return self;
+        };
+
+        log::warn!("Attaching banner to synthetic linux_banner symbol at address 0.");
+
+        self.0.symbols.insert(
+            String::from("linux_banner"),
+            Symbol {
+                addr: 0,
+                t: None,
+                source: None,
+                kind: SymbolKind::R,
+                scope: SymbolScope::Global,
+                linkage: None,
+                constant_data: Some(BASE64_STANDARD.encode(banner)),
+                section: None,
+            },
+        );
+
+        self
+    }
+
+    /// Set the policy for symbol names that appear more than once.
+    fn add_dup_symbols(mut self, policy: DupSymbols) -> Self {
+        self.0.dup_symbols = policy;
+        self
+    }
+
+    fn add_allow_zero_addresses(mut self, allow: bool) -> Self {
+        self.0.allow_zero_addresses = allow;
+        self
+    }
+
+    fn add_canonicalize_addresses(mut self, cli: &GenerateArgs, arch: Architecture) -> Self {
+        self.0.canonical_arch = cli.canonicalize_addresses.then_some(arch);
+        self
+    }
+
+    /// Set the address that `_stext` is moved to, which is the one given on
+    /// the CLI, if any, and the default of the architecture `arch` otherwise.
+    ///
+    /// With `--no-rebase` the addresses are not moved at all.
+    fn add_base_offset(mut self, cli: &GenerateArgs, arch: Architecture) -> Self {
+        self.0.address_width = Some(AddressWidth::new(arch.pointer_size()));
+        if cli.no_rebase {
+            log::info!("Not rebasing symbol addresses (--no-rebase)");
+            return self;
+        }
+        if let Some(base_offset) = cli.base_offset {
+            self.0.base_offset = Some(base_offset);
+            log::info!("Base offset set to {:#x} (--base-offset)", base_offset);
+            return self;
+        }
+
+        if cli.va_bits.is_some() && arch != Architecture::Arm64 {
+            log::warn!("Ignoring --va-bits, which only applies to arm64");
+        }
+        let base_offset = match arch {
+            // Default offset value for x86_64
+            Architecture::X86_64 => 0xffffffff81000000,
+            Architecture::Arm64 => {
+                let layout = match cli.va_bits {
+                    Some(va_bits) => Arm64Layout::Given(va_bits),
+                    None => Arm64Layout::Detect,
+                };
+                self.0.arm64_layout = Some(layout);
+                cli.va_bits.unwrap_or(VaBits::Va48).base_offset()
+            }
+            // Start of the kernel with the default 3G/1G split on 32-bit
+            Architecture::X86 => 0xc1000000,
+            Architecture::Arm => 0xc0008000,
+            // Start of the kernel mapping, at `_start`
+            Architecture::Riscv64 => 0xffffffff80000000,
+            // KERNELBASE
+            Architecture::Ppc64le => 0xc000000000000000,
+            // Link address of the text before the kernel moved to a virtual
+            // address in 6.10, which needs --base-offset
+            Architecture::S390x => 0x100000,
+        };
+        self.0.base_offset = Some(base_offset);
+
+        log::info!(
+            "Base offset set to {:#x} (default for {:?})",
+            base_offset,
+            arch
+        );
 
         self
     }
 }
 
-impl TryFrom<&Cli> for SymbolsBuilder {
-    type Error = Error;
+impl TryFrom<(&GenerateArgs, Architecture, &Btf)> for SymbolsBuilder {
+    type Error = error::Error;
+
+    fn try_from(
+        (cli, arch, btf): (&GenerateArgs, Architecture, &Btf),
+    ) -> error::Result<SymbolsBuilder> {
+        Ok(SymbolsBuilder::from_cli(
+            cli,
+            None,
+            arch,
+            btf,
+            true,
+            &Timings::default(),
+        )?)
+    }
+}
+
+/// Size of the arm64 module region, which precedes the kernel image in current
+/// kernels.
+const ARM64_MODULES_VSIZE: u64 = 2 << 30;
+/// Size of the arm64 image header, which precedes `_stext`.
+const ARM64_HEAD_SIZE: u64 = 0x10000;
+
+impl VaBits {
+    /// Returns the default address of `_stext` of arm64 kernels with these
+    /// virtual address bits.
+    ///
+    /// The kernel image follows the module region, which starts at the end of
+    /// the linear map. The linear map covers the lower half of the kernel
+    /// address space, of at most 48 bits for the image placement.
+    pub fn base_offset(&self) -> u64 {
+        let bits = self.bits().min(48);
+        let linear_map_end = u64::MAX << (bits - 1);
+        linear_map_end + ARM64_MODULES_VSIZE + ARM64_HEAD_SIZE
+    }
+
+    /// Returns the virtual address bits whose layout has the kernel text at
+    /// `addr`, e.g., `_stext` from a System.map or kallsyms.
+    ///
+    /// The image, also with KASLR, is in the quarter of the kernel address
+    /// space above the linear map, so the number of leading one bits gives
+    /// away the size of the address space. 52 bits are indistinguishable from
+    /// 48 bits.
+    pub fn from_kernel_address(addr: u64) -> Option<Self> {
+        match 65 - addr.leading_ones() {
+            39 => Some(VaBits::Va39),
+            42 => Some(VaBits::Va42),
+            48 => Some(VaBits::Va48),
+            _ => None,
+        }
+    }
+}
+
+/// Layout of the arm64 kernel address space that the default base offset is
+/// taken from.
+#[derive(Debug, Clone, Copy)]
+enum Arm64Layout {
+    /// Given with `--va-bits`, checked against the anchor.
+    Given(VaBits),
+    /// Derived from the address of the anchor.
+    Detect,
+}
+
+impl Arm64Layout {
+    /// Returns the base offset for symbols with the text anchor at `anchor`.
+    fn base_offset(self, anchor: u64) -> u64 {
+        let detected = VaBits::from_kernel_address(anchor);
+        match (self, detected) {
+            (Arm64Layout::Given(va_bits), Some(detected))
+                if detected.base_offset() != va_bits.base_offset() =>
+            {
+                log::warn!(
+                    target: diagnostics::INPUTS,
+                    code = "arm64_layout_mismatch",
+                    anchor = anchor,
+                    detected_va_bits = detected.bits(),
+                    va_bits = va_bits.bits();
+                    "The text anchor at {:#x} is in the layout of {} virtual address bits, not of {} from --va-bits",
+                    anchor,
+                    detected.bits(),
+                    va_bits.bits()
+                );
+            }
+            (Arm64Layout::Given(va_bits), None) => log::warn!(
+                target: diagnostics::INPUTS,
+                code = "unknown_arm64_layout",
+                anchor = anchor,
+                va_bits = va_bits.bits();
+                "The text anchor at {:#x} is in no known arm64 layout, using {} virtual address bits from --va-bits",
+                anchor,
+                va_bits.bits()
+            ),
+            (Arm64Layout::Given(_), Some(_)) => (),
+            (Arm64Layout::Detect, Some(detected)) => {
+                log::info!(
+                    "Detected {} virtual address bits from the text anchor at {:#x}",
+                    detected.bits(),
+                    anchor
+                );
+                return detected.base_offset();
+            }
+            (Arm64Layout::Detect, None) => log::warn!(
+                target: diagnostics::INPUTS,
+                code = "unknown_arm64_layout",
+                anchor = anchor,
+                va_bits = 48;
+                "The text anchor at {:#x} is in no known arm64 layout, assuming 48 virtual address bits (see --va-bits)",
+                anchor
+            ),
+        }
+        let va_bits = match self {
+            Arm64Layout::Given(va_bits) => va_bits,
+            Arm64Layout::Detect => VaBits::Va48,
+        };
+        va_bits.base_offset()
+    }
+}
+
+impl SymbolsBuilder {
+    /// Gathers the symbols from the sources on the CLI without attaching the
+    /// banner, which is then not required.
+    ///
+    /// For looking at the symbols without generating an ISF file.
+    pub fn without_banner(
+        cli: &GenerateArgs,
+        arch: Architecture,
+        btf: &Btf,
+    ) -> error::Result<Self> {
+        Ok(SymbolsBuilder::from_cli(
+            cli,
+            None,
+            arch,
+            btf,
+            false,
+            &Timings::default(),
+        )?)
+    }
 
-    fn try_from(cli: &Cli) -> Result<SymbolsBuilder> {
-        let sym_builder = if cli.map.is_some() {
-            log::debug!("Got System.map file for symbol addresses.");
-            SymbolsBuilder::new()
-                .add_base_offset_from_cli(cli)
-                .add_from_system_map(cli.map.as_ref().unwrap())
-        } else if cli.image.is_some() {
-            log::debug!("Got memory image, extracting symbol information.");
-            bail!("Extraction of symbols from memory image is not implemented.")
+    /// Gathers the symbols from the sources on the CLI, where `map` is the
+    /// name and contents of a System.map that is used instead of `--map`.
+    ///
+    /// Reading the sources is timed as `symbol-sources`, typing the symbols by
+    /// the symdb as `symbol-types`.
+    pub(crate) fn from_cli(
+        cli: &GenerateArgs,
+        map: Option<(String, InputData)>,
+        arch: Architecture,
+        btf: &Btf,
+        banner: bool,
+        timings: &Timings,
+    ) -> Result<Self> {
+        if cli.types_only {
+            log::warn!("Generating types-only profile, symbol sources are ignored.");
+            return Ok(SymbolsBuilder::new().add_synthetic_banner_from_cli(cli));
+        }
+
+        let start = Instant::now();
+        // Sources are merged by decreasing precedence.
+        let mut sym_builder = SymbolsBuilder::new()
+            .add_base_offset(cli, arch)
+            .add_dup_symbols(cli.dup_symbols)
+            .add_allow_zero_addresses(cli.allow_zero_addresses)
+            .add_canonicalize_addresses(cli, arch);
+        if let Some((name, raw)) = map {
+            log::debug!("Got System.map data for symbol addresses.");
+            sym_builder = sym_builder.add_from_map_data(raw, name, MapFormat::SystemMap)?;
+        } else if !cli.map.is_empty() {
+            log::debug!("Got System.map files for symbol addresses.");
+            sym_builder =
+                sym_builder.add_from_system_maps(&cli.map, cli.anchor_symbol.as_deref())?;
+        }
+        if let Some(kallsyms) = &cli.kallsyms {
+            log::debug!("Got kallsyms file for symbol addresses.");
+            sym_builder = sym_builder.add_from_kallsyms(kallsyms)?;
+        }
+        if cli.live {
+            log::debug!("Live mode, reading symbols from {}.", LIVE_KALLSYMS);
+            sym_builder = sym_builder.add_from_kallsyms(Path::new(LIVE_KALLSYMS))?;
+        }
+        // The vmlinux of a package is the BTF file.
+        let btf_file = cli.btf.is_some() || cli.dwarf.is_some() || cli.package.is_some();
+        if btf_file && elf::has_symtab(&btf.kernel_raw()) {
+            log::debug!("Reading symbols from ELF symbol table.");
+            sym_builder = sym_builder.add_from_elf(btf.kernel_raw(), btf.kernel_name().clone())?;
+        }
+
+        let sym_builder = if !sym_builder.0.sources.is_empty() {
+            sym_builder.rebase_on_stext(arch, cli.anchor_symbol.as_deref())
+        } else if let Some(image) = &cli.image {
+            log::debug!("Got memory image, recovering symbols from kallsyms.");
+            sym_builder
+                .add_from_image(image, cli.image_format, arch, btf.endian)?
+                .rebase_on_stext(arch, cli.anchor_symbol.as_deref())
+        } else if btf_file && elf::is_elf(&btf.kernel_raw()).is_ok() {
+            // Without section variables, nothing but the banner would be left.
+            if btf.section_variables().next().is_none() {
+                bail!(
+                    "{} has no symbol table and no BTF variables, pass its System.map with --map",
+                    btf.kernel_name()
+                );
+            }
+            log::warn!(
+                "No System.map, synthesizing symbols from BTF variables, see --help for caveats."
+            );
+            sym_builder.add_from_btf_sections(btf)
         } else {
-            bail!("No source for symbol information provided.")
+            bail!("No source for symbol information provided, use --map or --kallsyms (or --types-only to generate a profile without symbols).")
         }?;
-        let sym_builder = sym_builder
-            .add_types_from_symdb()
-            .add_banner_from_cli(cli)?;
+        // Module symbols are not relocated with the kernel.
+        let sym_builder = sym_builder.add_module_from_cli(cli, btf)?;
+        timings.record("symbol-sources", start.elapsed());
+        let sym_builder = timings
+            .time("symbol-types", || {
+                sym_builder.add_types_from_symdb(
+                    cli.symdb.as_deref(),
+                    cli.kernel_version(Banner::try_from(cli).ok().as_ref()),
+                )
+            })?
+            .add_constant_data_from_cli(cli, btf);
+        let sym_builder = if banner {
+            sym_builder.add_banner_from_cli(cli, btf)?
+        } else {
+            sym_builder
+        }
+        .demangle_rust_from_cli(cli)
+        .filter_from_cli(cli);
         log::debug!(
             "Got {} symbols ({} with types)",
             sym_builder.0.symbols.len(),
@@ -375,6 +2272,41 @@ impl TryFrom<&Cli> for SymbolsBuilder {
     }
 }
 
+/// Returns the address of a `linux_banner` that is in no symbol source.
+///
+/// The address is taken from the symbol table of the BTF file if it is
+/// vmlinux, from `--linux-banner-addr`, or is 0.
+fn linux_banner_addr(cli: &GenerateArgs, btf: &Btf) -> u64 {
+    let raw = btf.kernel_raw();
+    if elf::is_elf(&raw).is_ok() {
+        match elf::symbol_address(&raw, "linux_banner") {
+            Ok(Some(addr)) => {
+                log::info!(
+                    "No linux_banner in the symbol sources, using {:#x} from the symbol table of {}",
+                    addr,
+                    btf.kernel_name()
+                );
+                return addr;
+            }
+            Ok(None) => (),
+            Err(err) => log::debug!("{:#}", err),
+        }
+    }
+    if let Some(addr) = cli.linux_banner_addr {
+        log::info!(
+            "No linux_banner in the symbol sources, using {:#x} from --linux-banner-addr",
+            addr
+        );
+        return addr;
+    }
+    log::warn!(
+        target: diagnostics::SYMBOLS,
+        code = "synthetic_linux_banner";
+        "No linux_banner in the symbol sources, adding it at address 0, pass its address with --linux-banner-addr"
+    );
+    0
+}
+
 /// Linux banner.
 pub struct Banner(String);
 
@@ -391,7 +2323,211 @@ impl AsRef<[u8]> for Banner {
 }
 
 impl Banner {
-    fn from_btfsec(raw: &[u8]) -> Result<Self> {
+    /// Returns the banner `banner`, normalized like those found in the inputs.
+    pub fn new(banner: impl Into<String>) -> Self {
+        Banner(banner.into()).normalized()
+    }
+
+    /// Returns the banner as it is in memory, i.e., without trailing NULs and
+    /// with exactly one trailing newline.
+    ///
+    /// Volatility compares the banner with the one in memory byte by byte.
+    fn normalized(self) -> Self {
+        if !self.0.starts_with("Linux version ") {
+            log::warn!(
+                target: diagnostics::INPUTS,
+                code = "nonstandard_banner",
+                banner:% = self.0;
+                "Banner {:?} does not start with \"Linux version \", the profile will likely not be matched",
+                self.0
+            );
+        }
+        let banner = self.0.trim_end_matches(['\0', '\n']);
+        Banner(format!("{}\n", banner))
+    }
+
+    /// Returns the name of the ISF file for this banner in a directory of
+    /// profiles for many kernels, the URL-safe base64 of the banner with the
+    /// extension `.json.xz`.
+    pub fn isf_file_name(&self) -> String {
+        format!("{}.json.xz", BASE64_URL_SAFE.encode(&self.0))
+    }
+
+    /// Returns the kernel version in the banner, if it can be parsed.
+    pub fn kernel_version(&self) -> Option<KernelVersion> {
+        KernelVersion::from_banner(&self.0)
+    }
+
+    /// Reads the banner from the memory image at `path`, as given with the
+    /// options `cli`.
+    ///
+    /// Raw and LiME images are read at the physical address of `linux_banner`
+    /// in the System.map, see `--phys-base`, or else searched for the banner,
+    /// LiME images only in their ranges.
+    fn from_image(path: &Path, cli: &GenerateArgs) -> Result<Self> {
+        let raw = InputData::map_file(path)?;
+        let maps: Vec<&Path> = cli
+            .map
+            .iter()
+            .map(PathBuf::as_path)
+            .filter(|map| !input::is_stdin(map))
+            .collect();
+        let detect = cli.image_format == ImageFormat::Auto;
+        if detect && (vmcore::is_compressed_kdump(&raw) || elf::is_core(&raw)) {
+            return Banner::from_vmcore(&raw, &maps);
+        }
+        let lime = if detect && lime::is_lime(&raw) {
+            Some(Lime::parse(&raw)?)
+        } else {
+            None
+        };
+        let read_phys = |paddr: u64| match &lime {
+            Some(lime) => lime.read_phys(&raw, paddr, MAX_BANNER_LEN),
+            None => raw.get(usize::try_from(paddr).ok()?..),
+        };
+
+        let explicit = cli
+            .phys_base
+            .zip(cli.kernel_virt_base)
+            .map(|(phys_base, virt_base)| PhysTranslation::new(virt_base, phys_base));
+        let addr = map_address(&maps, "linux_banner").ok();
+        if let Some(addr) = addr {
+            let translations = match explicit {
+                Some(translation) => vec![translation],
+                None => cli
+                    .arch
+                    .image_placements()
+                    .into_iter()
+                    .map(|(virt_base, phys_base)| PhysTranslation::new(virt_base, phys_base))
+                    .collect(),
+            };
+            for translation in translations {
+                let Some(paddr) = translation.to_phys(addr) else {
+                    continue;
+                };
+                if let Some(banner) = read_phys(paddr).and_then(image::banner_at) {
+                    if explicit.is_none() {
+                        log::info!(
+                            "Assuming the kernel image at {}, which has the Linux banner at \
+                             the address of linux_banner; use --phys-base and \
+                             --kernel-virt-base otherwise",
+                            translation
+                        );
+                    }
+                    log::debug!("Found Linux banner at physical address {:#x}", paddr);
+                    return Ok(Banner(banner.to_owned()));
+                }
+            }
+            if let Some(translation) = explicit {
+                bail!(
+                    "No Linux banner at the address {:#x} of linux_banner with the kernel image at {}",
+                    addr,
+                    translation
+                );
+            }
+        } else if explicit.is_some() {
+            log::warn!(
+                "--phys-base needs the address of linux_banner from --map, searching the image for the banner"
+            );
+        }
+
+        let banner = match &lime {
+            Some(lime) => image::find_banner_in(&raw, &lime.segments())
+                .context("No Linux banner in LiME image")?,
+            None => image::find_banner(&raw).context("No Linux banner in memory image")?,
+        };
+        let off = banner.as_ptr() as usize - raw.as_ptr() as usize;
+        let paddr = match &lime {
+            Some(lime) => lime.phys_addr(off),
+            None => Some(off as u64),
+        };
+        if let (Some(addr), Some(paddr)) = (addr, paddr) {
+            log::warn!(
+                target: diagnostics::INPUTS,
+                code = "relocated_banner",
+                paddr = paddr;
+                "No Linux banner at the address of linux_banner in the known placements of the \
+                 kernel image, using the one at physical address {:#x}, i.e., assuming the \
+                 kernel image at {}",
+                paddr,
+                PhysTranslation::new(addr, paddr)
+            );
+        } else if let Some(paddr) = paddr {
+            log::debug!("Linux banner is at physical address {:#x}", paddr);
+        }
+        Ok(Banner(banner.to_owned()))
+    }
+
+    /// Reads the banner from the file at `path`, as given with
+    /// `--banner-file`.
+    ///
+    /// The contents are kept as they are, for `--banner-raw`.
+    fn from_file(path: &Path) -> Result<Self> {
+        let raw = fs::read(path)
+            .with_context(|| format!("Unable to read banner file {}", path.display()))?;
+        if raw.is_empty() {
+            bail!("Banner file {} is empty", path.display());
+        }
+        if raw.len() > MAX_BANNER_LEN {
+            bail!(
+                "Banner file {} has {} bytes, more than the {} bytes of a Linux banner",
+                path.display(),
+                raw.len(),
+                MAX_BANNER_LEN
+            );
+        }
+        let banner = String::from_utf8(raw)
+            .with_context(|| format!("Banner file {} is invalid UTF-8", path.display()))?;
+        Ok(Banner(banner))
+    }
+
+    /// Reads the banner from the ELF vmcore `raw`.
+    ///
+    /// The address of `linux_banner` is taken from the System.map files `maps`
+    /// and relocated by the kernel offset, or else from the kallsyms tables in
+    /// the vmcore.
+    fn from_vmcore(raw: &[u8], maps: &[&Path]) -> Result<Self> {
+        let vmcore = Vmcore::parse(raw)?;
+        let addr = match maps {
+            [_, ..] => map_address(maps, "linux_banner")?.wrapping_add(vmcore.kernel_offset()),
+            [] => {
+                image_kallsyms(
+                    raw,
+                    ImageFormat::Auto,
+                    vmcore.endian(),
+                    vmcore.pointer_size(),
+                )
+                .context("Extraction of Linux banner from vmcore requires --map with a file")?
+                .into_iter()
+                .find(|entry| entry.name == "linux_banner")
+                .context("No linux_banner in the kallsyms of the vmcore")?
+                .addr
+            }
+        };
+
+        let data = vmcore
+            .read_virt(raw, addr, MAX_BANNER_LEN)
+            .with_context(|| format!("Linux banner at {:#x} is not in the vmcore", addr))?;
+        let len = data
+            .iter()
+            .position(|b| *b == 0)
+            .with_context(|| format!("Linux banner at {:#x} is not terminated", addr))?;
+        let banner = str::from_utf8(&data[..len]).context("Linux banner is invalid UTF-8")?;
+        if !banner.starts_with("Linux version ") {
+            bail!("No Linux banner at {:#x}: {:?}", addr, banner);
+        }
+        log::debug!("Found Linux banner in vmcore at {:#x}", addr);
+
+        Ok(Banner(banner.to_owned()))
+    }
+
+    /// Reads the banner from vmlinux, or an arm64 `Image`, which has no symbol
+    /// table and is searched for the banner instead.
+    pub(crate) fn from_btfsec(raw: &[u8]) -> Result<Self> {
+        if decompress::is_arm64_image(raw) {
+            let banner = image::find_banner(raw).context("No Linux banner in arm64 Image")?;
+            return Ok(Banner(banner.to_owned()));
+        }
         elf::is_elf(raw)?;
         let banner = elf::get_banner(raw)?;
 
@@ -399,30 +2535,56 @@ impl Banner {
     }
 }
 
-impl TryFrom<&Cli> for Banner {
+impl TryFrom<&GenerateArgs> for Banner {
     type Error = Error;
 
-    fn try_from(cli: &Cli) -> Result<Banner> {
-        if cli.banner.is_some() {
-            return Ok(Banner(cli.banner.as_ref().unwrap().to_owned()));
+    /// Finds the banner in the sources given on the CLI and normalizes it,
+    /// unless `--banner-raw` is given.
+    fn try_from(cli: &GenerateArgs) -> Result<Banner> {
+        let banner = Banner::from_sources(cli)?;
+        Ok(if cli.banner_raw {
+            banner
+        } else {
+            banner.normalized()
+        })
+    }
+}
+
+impl Banner {
+    fn from_sources(cli: &GenerateArgs) -> Result<Banner> {
+        if let Some(banner) = &cli.banner {
+            if cli.banner_file.is_some() {
+                log::info!("Using the banner of --banner instead of --banner-file");
+            }
+            return Ok(Banner(banner.to_owned()));
         };
 
-        if cli.btf.is_some() {
-            let file_path: &Path = Path::new(cli.btf.as_ref().unwrap());
-            let file = File::open(file_path)?;
-            let mmap = unsafe { Mmap::map(&file)? };
+        if let Some(path) = &cli.banner_file {
+            return Banner::from_file(path);
+        }
+
+        if cli.live {
+            let banner = fs::read_to_string(LIVE_BANNER)
+                .with_context(|| format!("Unable to read {}", LIVE_BANNER))?;
+            return Ok(Banner(banner.trim_end_matches('\n').to_owned()));
+        }
+
+        // Standard input was already consumed for the type information.
+        let vmlinux = cli.btf.as_ref().or(cli.dwarf.as_ref());
+        if let Some(btf) = vmlinux.filter(|btf| !input::is_stdin(btf)) {
+            let raw = btf::load_btf_file(btf)?;
 
-            let banner = Banner::from_btfsec(&mmap);
+            let banner = Banner::from_btfsec(&raw);
 
             if banner.is_ok() {
                 return banner;
             }
         };
 
-        if cli.image.is_some() {
-            bail!("Extraction of Linux banner from memory image is not implemented.")
+        if let Some(image) = &cli.image {
+            return Banner::from_image(image, cli);
         }
 
-        bail!("Unable to find Linux banner.")
+        Err(error::Error::BannerNotFound.into())
     }
 }
@@ -0,0 +1,70 @@
+//! Contents of a symdb, as printed by `btf2json symdb dump`, and the lines
+//! that are not valid entries, as listed by `btf2json symdb check`.
+
+use crate::cli::SymdbFormat;
+use crate::query;
+use crate::symbols::SymDb;
+use crate::v_types::TypeDescr;
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// Entry of a symdb as printed with `--format json`.
+#[derive(Serialize)]
+struct Entry<'a> {
+    line: usize,
+    name: &'a str,
+    /// Requirements on the kernel version, e.g., `>=5.15.0`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    versions: Vec<String>,
+    #[serde(rename = "type")]
+    t: &'a TypeDescr,
+}
+
+/// Formats the valid entries of `symdb` in `format`.
+pub fn dump(symdb: &SymDb, format: SymdbFormat) -> Result<String> {
+    let entries = symdb.entries().iter().map(|entry| Entry {
+        line: entry.line(),
+        name: entry.name(),
+        versions: entry.versions().iter().map(ToString::to_string).collect(),
+        t: entry.type_descr(),
+    });
+    match format {
+        SymdbFormat::Text => {
+            let mut out = String::new();
+            for entry in entries {
+                write!(out, "{};", query::declaration(entry.t, entry.name))?;
+                if !entry.versions.is_empty() {
+                    write!(out, " /* {} */", entry.versions.join(" "))?;
+                }
+                writeln!(out)?;
+            }
+            Ok(out)
+        }
+        SymdbFormat::Json => Ok(format!(
+            "{}\n",
+            serde_json::to_string_pretty(&entries.collect::<Vec<_>>())?
+        )),
+    }
+}
+
+/// Returns a message for each line of `symdb` that is not a valid entry, with
+/// its line number, e.g., `custom.symdb:2: datafn {}: Invalid type of datafn:
+/// ...`.
+pub fn check(symdb: &SymDb) -> Vec<String> {
+    symdb
+        .invalid()
+        .iter()
+        .map(|invalid| {
+            format!(
+                "{}:{}: {}: {:#}",
+                symdb.name(),
+                invalid.line,
+                invalid.text,
+                invalid.error
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,208 @@
+//! Building synthetic BTF for tests, enabled by the `test-util` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use btf2json::btf::Btf;
+//! use btf2json::testutil::BtfBuilder;
+//!
+//! let mut b = BtfBuilder::new();
+//! let int = b.int("int", 4, true);
+//! let s = b.struct_("point", 8, &[("x", int, 0), ("y", int, 32)]);
+//! b.typedef_("point_t", s);
+//!
+//! let btf = Btf::from_bytes("point.btf", b.bytes())?;
+//! assert_eq!(btf.nr_types(), 4);
+//! # Ok::<(), btf2json::error::Error>(())
+//! ```
+
+use crate::btf::Btf;
+use crate::error;
+
+pub const BTF_KIND_INT: u32 = 1;
+pub const BTF_KIND_PTR: u32 = 2;
+pub const BTF_KIND_ARRAY: u32 = 3;
+pub const BTF_KIND_STRUCT: u32 = 4;
+pub const BTF_KIND_UNION: u32 = 5;
+pub const BTF_KIND_ENUM: u32 = 6;
+pub const BTF_KIND_FWD: u32 = 7;
+pub const BTF_KIND_TYPEDEF: u32 = 8;
+pub const BTF_KIND_FUNC: u32 = 12;
+pub const BTF_KIND_FUNC_PROTO: u32 = 13;
+
+/// ID of `void`, which is not a type of the section.
+pub const VOID: u32 = 0;
+
+/// Little endian BTF section, built one type at a time.
+///
+/// The methods adding a type return its ID. IDs are not checked, so types can
+/// refer to types that are added later, or that do not exist.
+#[derive(Default)]
+pub struct BtfBuilder {
+    types: Vec<u32>,
+    strings: Vec<u8>,
+    nr_types: u32,
+}
+
+impl BtfBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the string table offset of `name`, adding it if needed.
+    fn name(&mut self, name: &str) -> u32 {
+        if name.is_empty() {
+            return 0;
+        }
+        if self.strings.is_empty() {
+            self.strings.push(0);
+        }
+        let off = self.strings.len() as u32;
+        self.strings.extend(name.as_bytes());
+        self.strings.push(0);
+        off
+    }
+
+    /// Adds a type of `kind` with `extra` data following the common part.
+    ///
+    /// The kind flag is set if `kind_flag` is true.
+    pub fn add(
+        &mut self,
+        name: &str,
+        kind: u32,
+        kind_flag: bool,
+        vlen: u32,
+        size_or_type: u32,
+        extra: &[u32],
+    ) -> u32 {
+        let name = self.name(name);
+        self.add_with_name_off(name, kind, kind_flag, vlen, size_or_type, extra)
+    }
+
+    /// Same as [`BtfBuilder::add`], with the string table offset `name_off`
+    /// instead of a name, e.g., to add a type whose name is not a string.
+    pub fn add_with_name_off(
+        &mut self,
+        name_off: u32,
+        kind: u32,
+        kind_flag: bool,
+        vlen: u32,
+        size_or_type: u32,
+        extra: &[u32],
+    ) -> u32 {
+        let info = ((kind_flag as u32) << 31) | (kind << 24) | vlen;
+        self.types.extend([name_off, info, size_or_type]);
+        self.types.extend(extra);
+        self.nr_types += 1;
+        self.nr_types
+    }
+
+    /// Adds an integer of `size` bytes.
+    pub fn int(&mut self, name: &str, size: u32, signed: bool) -> u32 {
+        let encoding = if signed { 1 } else { 0 };
+        self.add(
+            name,
+            BTF_KIND_INT,
+            false,
+            0,
+            size,
+            &[(encoding << 24) | (size * 8)],
+        )
+    }
+
+    /// Adds a pointer to `t`.
+    pub fn ptr(&mut self, t: u32) -> u32 {
+        self.add("", BTF_KIND_PTR, false, 0, t, &[])
+    }
+
+    /// Adds an array of `nelems` elements of type `t`, indexed by `index`.
+    pub fn array(&mut self, t: u32, index: u32, nelems: u32) -> u32 {
+        self.add("", BTF_KIND_ARRAY, false, 0, 0, &[t, index, nelems])
+    }
+
+    /// Adds a struct of `size` bytes with `members` of name, type ID, and bit
+    /// offset.
+    pub fn struct_(&mut self, name: &str, size: u32, members: &[(&str, u32, u32)]) -> u32 {
+        self.members(name, BTF_KIND_STRUCT, size, members)
+    }
+
+    /// Adds a union of `size` bytes, see [`BtfBuilder::struct_`].
+    pub fn union_(&mut self, name: &str, size: u32, members: &[(&str, u32, u32)]) -> u32 {
+        self.members(name, BTF_KIND_UNION, size, members)
+    }
+
+    /// Adds a struct of `size` bytes with `members` of name, type ID, bit
+    /// offset, and bitfield size, which is 0 for members that are not
+    /// bitfields.
+    pub fn bitfields(&mut self, name: &str, size: u32, members: &[(&str, u32, u32, u32)]) -> u32 {
+        let mut extra = Vec::new();
+        for (name, t, off, bits) in members {
+            extra.extend([self.name(name), *t, (bits << 24) | off]);
+        }
+        let vlen = members.len() as u32;
+        self.add(name, BTF_KIND_STRUCT, true, vlen, size, &extra)
+    }
+
+    fn members(&mut self, name: &str, kind: u32, size: u32, members: &[(&str, u32, u32)]) -> u32 {
+        let mut extra = Vec::new();
+        for (name, t, off) in members {
+            extra.extend([self.name(name), *t, *off]);
+        }
+        self.add(name, kind, false, members.len() as u32, size, &extra)
+    }
+
+    /// Adds an enum of `size` bytes with `variants` of name and value.
+    pub fn enum_(&mut self, name: &str, size: u32, variants: &[(&str, i32)]) -> u32 {
+        let mut extra = Vec::new();
+        for (name, value) in variants {
+            extra.extend([self.name(name), *value as u32]);
+        }
+        let vlen = variants.len() as u32;
+        self.add(name, BTF_KIND_ENUM, true, vlen, size, &extra)
+    }
+
+    /// Adds a forward declaration of a struct, or of a union if `union` is
+    /// true.
+    pub fn fwd(&mut self, name: &str, union: bool) -> u32 {
+        self.add(name, BTF_KIND_FWD, union, 0, 0, &[])
+    }
+
+    /// Adds a typedef of `t`.
+    pub fn typedef_(&mut self, name: &str, t: u32) -> u32 {
+        self.add(name, BTF_KIND_TYPEDEF, false, 0, t, &[])
+    }
+
+    /// Adds a function prototype returning `ret` with `params` of name and
+    /// type ID.
+    pub fn func_proto(&mut self, ret: u32, params: &[(&str, u32)]) -> u32 {
+        let mut extra = Vec::new();
+        for (name, t) in params {
+            extra.extend([self.name(name), *t]);
+        }
+        let vlen = params.len() as u32;
+        self.add("", BTF_KIND_FUNC_PROTO, false, vlen, ret, &extra)
+    }
+
+    /// Adds a function with the prototype `proto`, which is global if `global`
+    /// is true, and static otherwise.
+    pub fn func(&mut self, name: &str, proto: u32, global: bool) -> u32 {
+        self.add(name, BTF_KIND_FUNC, false, global as u32, proto, &[])
+    }
+
+    /// Returns the BTF section.
+    pub fn bytes(&self) -> Vec<u8> {
+        let types: Vec<u8> = self.types.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut btf = vec![0x9f, 0xeb, 0x01, 0x00];
+        for v in [24, 0, types.len(), types.len(), self.strings.len()] {
+            btf.extend((v as u32).to_le_bytes());
+        }
+        btf.extend(types);
+        btf.extend(&self.strings);
+        btf
+    }
+
+    /// Loads the BTF section, which is referred to as `name`.
+    pub fn build(&self, name: &str) -> error::Result<Btf> {
+        Btf::from_bytes(name, self.bytes())
+    }
+}
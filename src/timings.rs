@@ -0,0 +1,102 @@
+//! Durations of the phases of the generation, as printed by `--timings`.
+//!
+//! Unlike the [`Progress`](crate::progress::Progress), which reports the major
+//! phases while they run, the timings are collected for every step of the
+//! pipeline and read once the ISF file is written, see [`Isf::timings`].
+//!
+//! [`Isf::timings`]: crate::isf::Isf::timings
+
+use std::cell::RefCell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Time spent in a phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// Stable identifier of the phase, e.g., `btf-scan`.
+    pub label: &'static str,
+    pub duration: Duration,
+}
+
+/// Durations of the phases in the order they first ran.
+///
+/// Phases that run several times, e.g., `base-types` before and after the
+/// enums, are summed up.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    phases: RefCell<Vec<Timing>>,
+}
+
+impl Timings {
+    /// Runs `f` as the phase `label`.
+    pub fn time<T>(&self, label: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(label, start.elapsed());
+        result
+    }
+
+    /// Adds `duration` to the phase `label`.
+    pub fn record(&self, label: &'static str, duration: Duration) {
+        let mut phases = self.phases.borrow_mut();
+        match phases.iter_mut().find(|timing| timing.label == label) {
+            Some(timing) => timing.duration += duration,
+            None => phases.push(Timing { label, duration }),
+        }
+    }
+
+    /// Returns the phases that ran.
+    pub fn phases(&self) -> Vec<Timing> {
+        self.phases.borrow().clone()
+    }
+
+    /// Returns the duration of `label`, if it ran.
+    pub fn get(&self, label: &str) -> Option<Duration> {
+        self.phases
+            .borrow()
+            .iter()
+            .find(|timing| timing.label == label)
+            .map(|timing| timing.duration)
+    }
+
+    /// Returns the time spent in all phases.
+    pub fn total(&self) -> Duration {
+        self.phases
+            .borrow()
+            .iter()
+            .map(|timing| timing.duration)
+            .sum()
+    }
+}
+
+impl fmt::Display for Timings {
+    /// Formats a table of the phases with their durations and shares of the
+    /// total.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        let share = |duration: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                100.0 * duration.as_secs_f64() / total.as_secs_f64()
+            }
+        };
+        writeln!(f, "{:<20} {:>12} {:>7}", "phase", "duration", "share")?;
+        for timing in self.phases.borrow().iter() {
+            writeln!(
+                f,
+                "{:<20} {:>9.1} ms {:>6.1}%",
+                timing.label,
+                timing.duration.as_secs_f64() * 1000.0,
+                share(timing.duration)
+            )?;
+        }
+        write!(
+            f,
+            "{:<20} {:>9.1} ms {:>6.1}%",
+            "total",
+            total.as_secs_f64() * 1000.0,
+            share(total)
+        )
+    }
+}
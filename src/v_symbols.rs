@@ -2,46 +2,185 @@
 //!
 //! Only code that should be affected by a change in the ISF spec for symbols.
 
+use crate::cli::AddressOverflow;
 use crate::symbols;
 use crate::v_types;
 
-use serde::Serialize;
+use std::collections::BTreeMap;
 
-#[derive(Serialize)]
-#[allow(dead_code)]
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Width of addresses on the target architecture.
+#[derive(Copy, Clone, Debug)]
+pub struct AddressWidth {
+    bytes: u8,
+}
+
+impl AddressWidth {
+    pub fn new(bytes: u8) -> Self {
+        Self { bytes }
+    }
+
+    /// Returns the number of bytes of an address.
+    pub fn bytes(&self) -> u8 {
+        self.bytes
+    }
+
+    /// Returns the largest representable address.
+    pub fn max(&self) -> u64 {
+        u64::MAX >> (64 - 8 * self.bytes as u32)
+    }
+
+    /// Returns true iff `addr` is representable.
+    pub fn fits(&self, addr: u64) -> bool {
+        addr <= self.max()
+    }
+
+    /// Formats `addr` as hex number with all digits of the address width.
+    pub fn format(&self, addr: u64) -> String {
+        format!("{:#0width$x}", addr, width = 2 + 2 * self.bytes as usize)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum Linkage {
     Global,
     Static,
 }
 
 /// Represents an ISF symbol.
-#[derive(Serialize)]
+///
+/// Symbols without a type, as emitted by dwarf2json for symbols that are only
+/// in the System.map, are read as `void`.
+#[derive(Serialize, Deserialize)]
 pub struct Symbol {
     pub address: u64,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default = "v_types::TypeDescr::new_void")]
     pub t: v_types::TypeDescr,
     #[serde(skip_serializing_if = "Option::is_none")]
     linkage: Option<Linkage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     constant_data: Option<String>,
+    /// Kind of the symbol, if it comes from a symbol source.
+    #[serde(skip)]
+    kind: Option<symbols::SymbolKind>,
+    /// Why the symbol has the `void` fallback type, if it has.
+    #[serde(skip)]
+    untyped: Option<Untyped>,
+}
+
+/// Why a symbol has the `void` fallback type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Untyped {
+    /// No source has a type for the symbol.
+    NoType,
+    /// The type of the symbol is undefined, it is named here.
+    UndefinedType(String),
+}
+
+/// Converts symbols into ISF symbols whose addresses fit into `width`.
+///
+/// Addresses that do not fit are either masked or cause an error, depending on
+/// `overflow`.
+pub fn from_symbols(
+    symbols: symbols::Symbols,
+    width: AddressWidth,
+    overflow: AddressOverflow,
+) -> Result<BTreeMap<String, Symbol>> {
+    let mut masked = 0;
+    let symbols = symbols
+        .into_iter()
+        .map(|(name, sym)| {
+            let mut sym = Symbol::from(sym);
+            if !width.fits(sym.address) {
+                match overflow {
+                    AddressOverflow::Error => bail!(
+                        "Address {:#x} of symbol {} does not fit into {} bits",
+                        sym.address,
+                        name,
+                        8 * width.bytes
+                    ),
+                    AddressOverflow::Mask => {
+                        let address = sym.address & width.max();
+                        log::debug!(
+                            "Masking address of symbol {}: {:#x} -> {}",
+                            name,
+                            sym.address,
+                            width.format(address)
+                        );
+                        sym.address = address;
+                        masked += 1;
+                    }
+                }
+            }
+            Ok((name, sym))
+        })
+        .collect::<Result<_>>()?;
+
+    if masked > 0 {
+        log::warn!(
+            "Masked {} symbol addresses to {} bits",
+            masked,
+            8 * width.bytes
+        );
+    }
+
+    Ok(symbols)
+}
+
+impl Symbol {
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Returns the type of the symbol, `void` if it has none.
+    pub fn type_descr(&self) -> &v_types::TypeDescr {
+        &self.t
+    }
+
+    /// Drops the linkage, which older ISF formats do not have.
+    pub fn clear_linkage(&mut self) {
+        self.linkage = None;
+    }
+
+    /// Returns the kind of the symbol, if it comes from a symbol source.
+    pub fn kind(&self) -> Option<symbols::SymbolKind> {
+        self.kind
+    }
+
+    /// Returns the base64 encoded contents of the symbol, if they are in the
+    /// ISF file.
+    pub fn constant_data(&self) -> Option<&str> {
+        self.constant_data.as_deref()
+    }
+
+    /// Returns why the symbol has the `void` fallback type, if it has.
+    pub fn untyped(&self) -> Option<&Untyped> {
+        self.untyped.as_ref()
+    }
+
+    /// Sets the type of the symbol to `void` as its type `undefined` is
+    /// undefined.
+    pub fn set_void(&mut self, undefined: String) {
+        self.t = v_types::TypeDescr::new_void();
+        self.untyped = Some(Untyped::UndefinedType(undefined));
+    }
 }
 
 impl From<symbols::Symbol> for Symbol {
     fn from(mut sym: symbols::Symbol) -> Self {
+        let t = sym.r#type();
         Symbol {
             address: sym.address(),
-            t: sym
-                .r#type()
-                .map(|t| match serde_json::from_str::<v_types::TypeDescr>(t) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        panic!("Symbol type had invalid format: {}: {}", e, t)
-                    }
-                })
-                .unwrap_or(v_types::TypeDescr::Base {
-                    name: String::from("void"),
-                }),
-            linkage: None,
+            untyped: t.is_none().then_some(Untyped::NoType),
+            t: t.unwrap_or_else(v_types::TypeDescr::new_void),
+            kind: Some(sym.kind()),
+            linkage: Some(match sym.linkage() {
+                symbols::SymbolScope::Global => Linkage::Global,
+                symbols::SymbolScope::Local => Linkage::Static,
+            }),
             constant_data: sym.constant_data(),
         }
     }
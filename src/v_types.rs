@@ -21,21 +21,29 @@
 //! - conversion code.
 
 use crate::btf;
+use crate::diagnostics;
+use crate::TRACE_TARGET;
 
-use std::collections::{BTreeMap, HashMap};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
 use std::convert::From;
+use std::fmt;
 
 use anyhow::{bail, Context, Error, Result};
+use serde::de::{self, Deserializer, Visitor};
 use serde::{Deserialize, Serialize};
 
 /// ISF `type_descriptor`.
-#[derive(Serialize, Deserialize, Eq, PartialEq, Hash, Debug)]
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash, Debug)]
 #[serde(tag = "kind")]
 pub enum TypeDescr {
     #[serde(rename = "array")]
     Array { count: u64, subtype: Box<TypeDescr> },
     #[serde(rename = "base")]
-    Base { name: String },
+    Base {
+        #[serde(deserialize_with = "deserialize_name")]
+        name: btf::Name,
+    },
     #[serde(rename = "bitfield")]
     Bitfield {
         bit_position: u8,
@@ -44,25 +52,63 @@ pub enum TypeDescr {
         type_: Box<TypeDescr>,
     },
     #[serde(rename = "enum")]
-    Enum { name: String },
+    Enum {
+        #[serde(deserialize_with = "deserialize_name")]
+        name: btf::Name,
+    },
+    /// Function, with its prototype only if rich function descriptors are
+    /// requested.
     #[serde(rename = "function")]
-    Function,
+    Function {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        return_type: Option<Box<TypeDescr>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parameters: Option<Vec<TypeDescr>>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        variadic: bool,
+    },
     #[serde(rename = "pointer")]
     Pointer { subtype: Box<TypeDescr> },
     #[serde(rename = "struct")]
-    Struct { name: String },
+    Struct {
+        #[serde(deserialize_with = "deserialize_name")]
+        name: btf::Name,
+    },
     #[serde(rename = "union")]
-    Union { name: String },
+    Union {
+        #[serde(deserialize_with = "deserialize_name")]
+        name: btf::Name,
+    },
+}
+
+/// Deserializes a name straight into a [`btf::Name`], without the intermediate
+/// `String` of the default implementation.
+fn deserialize_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<btf::Name, D::Error> {
+    struct NameVisitor;
+
+    impl Visitor<'_> for NameVisitor {
+        type Value = btf::Name;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a type name")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(v.into())
+        }
+    }
+
+    deserializer.deserialize_str(NameVisitor)
 }
 
 impl TypeDescr {
     pub fn new_void() -> Self {
         Self::Base {
-            name: String::from("void"),
+            name: "void".into(),
         }
     }
 
-    pub fn name(&self) -> Option<&String> {
+    pub fn name(&self) -> Option<&btf::Name> {
         match self {
             Self::Base { name } => Some(name),
             Self::Enum { name } => Some(name),
@@ -72,7 +118,7 @@ impl TypeDescr {
         }
     }
 
-    pub fn kind(&self) -> Option<&str> {
+    pub fn kind(&self) -> Option<&'static str> {
         match self {
             Self::Base { name: _ } => Some(""),
             Self::Enum { name: _ } => Some("enum"),
@@ -81,6 +127,52 @@ impl TypeDescr {
             _ => None,
         }
     }
+    /// Calls `f` for this descriptor and all descriptors nested in it.
+    pub fn visit(&self, f: &mut impl FnMut(&Self)) {
+        f(self);
+        match self {
+            Self::Array { subtype, .. } | Self::Pointer { subtype } => subtype.visit(f),
+            Self::Bitfield { type_, .. } => type_.visit(f),
+            Self::Function {
+                return_type,
+                parameters,
+                ..
+            } => {
+                for t in return_type
+                    .iter()
+                    .map(Box::as_ref)
+                    .chain(parameters.iter().flatten())
+                {
+                    t.visit(f);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Calls `f` for this descriptor and all descriptors nested in it.
+    pub fn visit_mut(&mut self, f: &mut impl FnMut(&mut Self)) {
+        f(self);
+        match self {
+            Self::Array { subtype, .. } | Self::Pointer { subtype } => subtype.visit_mut(f),
+            Self::Bitfield { type_, .. } => type_.visit_mut(f),
+            Self::Function {
+                return_type,
+                parameters,
+                ..
+            } => {
+                for t in return_type
+                    .iter_mut()
+                    .map(Box::as_mut)
+                    .chain(parameters.iter_mut().flatten())
+                {
+                    t.visit_mut(f);
+                }
+            }
+            _ => (),
+        }
+    }
+
     pub fn resolve(&self) -> &Self {
         let mut tmp = self;
         loop {
@@ -88,7 +180,7 @@ impl TypeDescr {
                 Self::Base { name: _ }
                 | Self::Enum { name: _ }
                 | Self::Union { name: _ }
-                | Self::Function
+                | Self::Function { .. }
                 | Self::Struct { name: _ } => return tmp,
                 Self::Array { count: _, subtype } => tmp = subtype,
                 Self::Pointer { subtype } => tmp = subtype,
@@ -102,80 +194,247 @@ impl TypeDescr {
     }
 }
 
+/// What a type descriptor is constructed for.
+#[derive(Copy, Clone)]
+enum Origin<'a> {
+    /// Field of a user type.
+    Field(&'a UserFieldConstructionCtx<'a, 'a>),
+    /// Symbol with the given name.
+    Symbol(&'a str),
+}
+
+impl fmt::Display for Origin<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(ufctx) => write!(
+                f,
+                "{}::{}",
+                ufctx.uctx.basic_ctx.tx.id,
+                ufctx.m.name(ufctx.uctx.basic_ctx.btf)
+            ),
+            Self::Symbol(name) => write!(f, "{}", name),
+        }
+    }
+}
+
 struct TypeDescrConstructionCtx<'a> {
-    ufctx: &'a UserFieldConstructionCtx<'a, 'a>,
+    btf: &'a btf::Btf,
+    origin: Origin<'a>,
+    /// Include the prototype in function descriptors.
+    rich_functions: bool,
     rt: btf::ResolvedType,
-    name: String,
+    name: btf::Name,
     handle_bitfield: bool,
+    /// Number of descriptors this one is nested in, e.g., of the function
+    /// whose parameter it is.
+    depth: usize,
 }
 
-impl From<TypeDescrConstructionCtx<'_>> for TypeDescr {
-    fn from(mut ctx: TypeDescrConstructionCtx<'_>) -> Self {
-        log::trace!(
-            "[{}::{}] path {:?},",
-            ctx.ufctx.uctx.basic_ctx.tx.id,
-            &ctx.ufctx.m.name(ctx.ufctx.uctx.basic_ctx.btf),
-            ctx.rt.path
-        );
-        match ctx.rt.path.pop_node() {
-            Some(btf::ResolutionPathNode::Pointer) => Self::Pointer {
-                subtype: Box::new(TypeDescr::from(ctx)),
-            },
-            Some(btf::ResolutionPathNode::Array(nelem)) => Self::Array {
-                count: nelem,
-                subtype: Box::new(TypeDescr::from(ctx)),
-            },
-            Some(btf::ResolutionPathNode::Typedef(_)) => TypeDescr::from(ctx),
-            None => {
-                if ctx.ufctx.m.is_bitfield() && ctx.handle_bitfield {
-                    let bfinfo = ctx.ufctx.m.bitfield_info().unwrap();
-                    ctx.handle_bitfield = false;
-
-                    Self::Bitfield {
-                        bit_position: bfinfo.position,
-                        bit_length: bfinfo.length,
-                        type_: Box::new(TypeDescr::from(ctx)),
+impl TryFrom<TypeDescrConstructionCtx<'_>> for TypeDescr {
+    type Error = Error;
+
+    /// Constructs the descriptor of the root of the path, wrapped in the
+    /// pointers and arrays on the path in a loop, so that long paths do not
+    /// recurse.
+    fn try_from(mut ctx: TypeDescrConstructionCtx<'_>) -> Result<Self> {
+        log::trace!("[{}] path {:?},", ctx.origin, ctx.rt.path);
+        let mut wrappers = Vec::new();
+        while let Some(node) = ctx.rt.path.pop_node() {
+            match node {
+                btf::ResolutionPathNode::Pointer => wrappers.push(node),
+                btf::ResolutionPathNode::Array(nelem) => {
+                    if nelem == 0 {
+                        // The element type is still emitted, so that it is
+                        // checked like any other referenced type. The size of
+                        // the parent is taken from BTF and thus unaffected.
+                        log::trace!("[{}] flexible array", ctx.origin);
                     }
-                } else if ctx.rt.tx.t.is_union() {
-                    Self::Union { name: ctx.name }
-                } else if ctx.rt.tx.t.is_struct() {
-                    Self::Struct { name: ctx.name }
-                } else if ctx.rt.tx.t.is_fwd() {
-                    let kind = if ctx.rt.tx.t.is_fwd_struct() {
-                        "struct"
-                    } else {
-                        "union"
-                    };
+                    wrappers.push(node);
+                }
+                btf::ResolutionPathNode::Typedef(_) => (),
+            }
+        }
+
+        let max_depth = ctx.btf.max_type_depth();
+        let depth = ctx.depth + wrappers.len() + 1;
+        let mut descr = if depth > max_depth {
+            log::warn!(
+                target: diagnostics::REPAIRED_TYPES,
+                code = "type_descriptor_too_deep",
+                origin:% = ctx.origin,
+                id:% = ctx.rt.tx.id,
+                max_depth = max_depth;
+                "[{}] descriptor of [{}] nests {} levels deep, cutting it off with void at --max-type-depth {}",
+                ctx.origin,
+                ctx.rt.tx.id,
+                depth,
+                max_depth
+            );
+            wrappers.truncate(max_depth.saturating_sub(ctx.depth + 1));
+            Self::new_void()
+        } else {
+            ctx.depth = depth - 1;
+            Self::new_root(ctx)?
+        };
+        for node in wrappers.into_iter().rev() {
+            descr = match node {
+                btf::ResolutionPathNode::Pointer => Self::Pointer {
+                    subtype: Box::new(descr),
+                },
+                btf::ResolutionPathNode::Array(count) => Self::Array {
+                    count,
+                    subtype: Box::new(descr),
+                },
+                btf::ResolutionPathNode::Typedef(_) => unreachable!(),
+            };
+        }
+        Ok(descr)
+    }
+}
+
+impl TypeDescr {
+    /// Returns the descriptor of the root of the resolved type of `ctx`, i.e.,
+    /// without the pointers and arrays on the path to it.
+    fn new_root(mut ctx: TypeDescrConstructionCtx<'_>) -> Result<Self> {
+        let bitfield = match ctx.origin {
+            Origin::Field(ufctx) if ctx.handle_bitfield && ufctx.m.is_bitfield() => {
+                ufctx.m.bitfield_info()
+            }
+            _ => None,
+        };
+        Ok(if let Some(mut bfinfo) = bitfield {
+            ctx.handle_bitfield = false;
+            let unit_size = ctx.rt.tx.t.size().unwrap_or(0);
+            if let Origin::Field(ufctx) = ctx.origin {
+                ufctx.fix_bitfield(&mut bfinfo, unit_size);
+            }
+            if let Origin::Field(ufctx) = ctx.origin {
+                if ufctx.uctx.is_traced() {
                     log::info!(
-                        "[{}::{}] `{} {}` from fwd declaration will likely not be present",
-                        ctx.ufctx.uctx.basic_ctx.tx.id,
-                        &ctx.ufctx.m.name(ctx.ufctx.uctx.basic_ctx.btf),
-                        kind,
-                        &ctx.name
+                        target: TRACE_TARGET,
+                        "[{}] bitfield of {} bits at bit {} of its first byte, bit {} of its {} byte unit ({:?} endian)",
+                        ctx.origin,
+                        bfinfo.length,
+                        bfinfo.position,
+                        bfinfo.lsb_position(ctx.btf.endian, unit_size),
+                        unit_size,
+                        ctx.btf.endian
                     );
-                    if ctx.rt.tx.t.is_fwd_struct() {
-                        Self::Struct { name: ctx.name }
-                    } else {
-                        Self::Union { name: ctx.name }
-                    }
-                } else if ctx.rt.tx.t.is_enum() {
-                    Self::Enum { name: ctx.name }
-                } else if ctx.rt.tx.t.is_base() {
-                    Self::Base { name: ctx.name }
-                } else if ctx.rt.tx.t.is_func() {
-                    Self::Function
-                } else {
-                    panic!(
-                        "Unable to construct type descriptor: res type {:?}",
-                        ctx.rt.tx
-                    )
                 }
             }
-        }
+
+            Self::Bitfield {
+                bit_position: bfinfo.lsb_position(ctx.btf.endian, unit_size),
+                bit_length: bfinfo.length,
+                type_: Box::new(Self::new_root(ctx)?),
+            }
+        } else if ctx.rt.tx.t.is_union() {
+            Self::Union { name: ctx.name }
+        } else if ctx.rt.tx.t.is_struct() {
+            Self::Struct { name: ctx.name }
+        } else if ctx.rt.tx.t.is_fwd() {
+            let kind = if ctx.rt.tx.t.is_fwd_struct() {
+                "struct"
+            } else {
+                "union"
+            };
+            log::info!(
+                "[{}] `{} {}` from fwd declaration will likely not be present",
+                ctx.origin,
+                kind,
+                &ctx.name
+            );
+            if ctx.rt.tx.t.is_fwd_struct() {
+                Self::Struct { name: ctx.name }
+            } else {
+                Self::Union { name: ctx.name }
+            }
+        } else if ctx.rt.tx.t.is_enum() {
+            Self::Enum { name: ctx.name }
+        } else if ctx.rt.tx.t.is_base() {
+            Self::Base { name: ctx.name }
+        } else if ctx.rt.tx.t.is_func() {
+            if ctx.rich_functions {
+                Self::new_rich_function(&ctx)?
+            } else {
+                Self::Function {
+                    return_type: None,
+                    parameters: None,
+                    variadic: false,
+                }
+            }
+        } else {
+            bail!(
+                "[{}] unable to construct type descriptor for [{}] {:?}",
+                ctx.origin,
+                ctx.rt.tx.id,
+                ctx.rt.tx.t
+            )
+        })
+    }
+
+    /// Returns the descriptor of the type `tx` of the symbol `name`, a variable
+    /// or function.
+    ///
+    /// `rich_functions` includes the prototype in function descriptors.
+    pub fn new_symbol(
+        btf: &btf::Btf,
+        name: &str,
+        tx: btf::TypeEx,
+        rich_functions: bool,
+    ) -> Result<Self> {
+        let rt = btf.resolve_type_chain(tx);
+        let type_name = rt.name(btf);
+        TypeDescr::try_from(TypeDescrConstructionCtx {
+            btf,
+            origin: Origin::Symbol(name),
+            rich_functions,
+            rt,
+            name: type_name,
+            handle_bitfield: false,
+            depth: 0,
+        })
+    }
+
+    /// Returns a function descriptor that includes the return type and the
+    /// parameter types of the prototype.
+    fn new_rich_function(ctx: &TypeDescrConstructionCtx<'_>) -> Result<Self> {
+        let btf = ctx.btf;
+        let proto = btf.get_prototype(&ctx.rt.tx.t).with_context(|| {
+            format!(
+                "[{}] failed to resolve function prototype [{}]",
+                ctx.origin, ctx.rt.tx.id
+            )
+        })?;
+        let descr = |tx: btf::TypeEx| {
+            let rt = btf.resolve_type_chain(tx);
+            let name = rt.name(btf);
+            TypeDescr::try_from(TypeDescrConstructionCtx {
+                btf,
+                origin: ctx.origin,
+                rich_functions: ctx.rich_functions,
+                rt,
+                name,
+                handle_bitfield: false,
+                depth: ctx.depth + 1,
+            })
+        };
+
+        Ok(Self::Function {
+            return_type: Some(Box::new(descr(proto.return_type)?)),
+            parameters: Some(
+                proto
+                    .parameters
+                    .into_iter()
+                    .map(descr)
+                    .collect::<Result<_>>()?,
+            ),
+            variadic: proto.variadic,
+        })
     }
 }
 
-#[derive(Debug, Serialize, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
 pub enum BaseKind {
     #[serde(rename = "void")]
     Void,
@@ -197,7 +456,9 @@ impl TryFrom<&btf::Type> for BaseKind {
             btf_rs::Type::Void => Ok(Self::Void),
             btf_rs::Type::Float(_) => Ok(Self::Float),
             btf_rs::Type::Int(i) => {
-                if i.is_char() {
+                // Wider ints with the char encoding, e.g., of wide characters,
+                // are no `char` to Volatility.
+                if i.is_char() && i.size() == 1 {
                     Ok(Self::Char)
                 } else if i.is_bool() {
                     Ok(Self::Bool)
@@ -210,7 +471,7 @@ impl TryFrom<&btf::Type> for BaseKind {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum Endian {
     #[serde(rename = "big")]
     Big,
@@ -227,8 +488,33 @@ impl From<&btf::Endian> for Endian {
     }
 }
 
+/// ISF element that can be ranked against a conflicting element of the same
+/// name.
+pub trait Definition: PartialEq {
+    /// Returns how complete the definition is, larger is more complete.
+    fn completeness(&self) -> (usize, u64);
+}
+
+impl Definition for Base {
+    fn completeness(&self) -> (usize, u64) {
+        (0, self.size as u64)
+    }
+}
+
+impl Definition for User {
+    fn completeness(&self) -> (usize, u64) {
+        (self.fields.0.len(), self.size)
+    }
+}
+
+impl Definition for Enum {
+    fn completeness(&self) -> (usize, u64) {
+        (self.constants.len(), self.size as u64)
+    }
+}
+
 /// ISF `element_base_type`.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Base {
     size: u8,
     signed: bool,
@@ -237,43 +523,98 @@ pub struct Base {
 }
 
 impl Base {
-    pub fn new_pointer(endian: Endian) -> Self {
+    /// Returns a base type that does not correspond to a BTF type.
+    pub fn new(size: u8, signed: bool, kind: BaseKind, endian: Endian) -> Self {
         Self {
-            // TODO: distinguish between 64 and 32 bit
-            size: 8,
-            signed: false,
-            kind: BaseKind::Int,
+            size,
+            signed,
+            kind,
             endian,
         }
     }
+
+    /// Returns the base type of pointers with `size` bytes.
+    pub fn new_pointer(size: u8, endian: Endian) -> Self {
+        Self::new(size, false, BaseKind::Int, endian)
+    }
+
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    pub fn kind(&self) -> BaseKind {
+        self.kind
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
 }
 
 /// Argument for constructing an ISF base type.
 pub struct BaseConstructionCtx<'a> {
     pub btf: &'a btf::Btf,
     pub tx: btf::TypeEx,
+    /// Signedness of plain `char` in the kernel, if known, which overrides
+    /// the one in the BTF, see [`Architecture::char_signed`].
+    ///
+    /// [`Architecture::char_signed`]: crate::cli::Architecture::char_signed
+    pub char_signed: Option<bool>,
 }
 
 impl BaseConstructionCtx<'_> {
-    pub fn construct(self) -> Base {
-        self.into()
+    pub fn construct(self) -> Result<Base> {
+        self.try_into()
     }
 }
 
-impl From<BaseConstructionCtx<'_>> for Base {
-    fn from(ctx: BaseConstructionCtx) -> Self {
-        Base {
-            size: ctx.tx.t.size().unwrap_or(0) as u8,
-            signed: ctx.tx.t.signed().unwrap(),
-            kind: (&ctx.tx.t)
-                .try_into()
-                .expect("Attempt to construct Volatility base type from invalid BTF type"),
-            endian: (&ctx.btf.endian).into(),
+impl TryFrom<BaseConstructionCtx<'_>> for Base {
+    type Error = Error;
+
+    fn try_from(ctx: BaseConstructionCtx) -> Result<Self> {
+        let kind = (&ctx.tx.t).try_into()?;
+        let mut signed = ctx
+            .tx
+            .t
+            .signed()
+            .with_context(|| format!("[{}] base type without signedness", ctx.tx.id))?;
+        match kind {
+            BaseKind::Bool => signed = false,
+            BaseKind::Char | BaseKind::Int => {
+                // BTF does not tell plain `char` without a signedness from
+                // `unsigned char`, so the one of the kernel build wins.
+                if let Some(char_signed) = ctx.char_signed.filter(|s| *s != signed) {
+                    let name = ctx.tx.t.t.as_btf_type();
+                    let name = name.and_then(|t| ctx.btf.resolve_name(t).ok());
+                    if name.is_some_and(|name| &*name == "char") {
+                        log::info!(
+                            "[{}] char is {} in BTF, using the {} char of the kernel",
+                            ctx.tx.id,
+                            if signed { "signed" } else { "unsigned" },
+                            if char_signed { "signed" } else { "unsigned" },
+                        );
+                        signed = char_signed;
+                    }
+                }
+            }
+            BaseKind::Void | BaseKind::Float => (),
         }
+        let size = ctx.tx.t.size().unwrap_or(0);
+        Ok(Base {
+            size: u8::try_from(size)
+                .with_context(|| format!("[{}] base type of {} bytes", ctx.tx.id, size))?,
+            signed,
+            kind,
+            endian: (&ctx.btf.endian).into(),
+        })
     }
 }
 
-#[derive(Serialize, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum UserKind {
     #[serde(rename = "struct")]
     Struct,
@@ -308,49 +649,145 @@ impl TryFrom<&btf::Type> for UserKind {
 }
 
 /// ISF `field`.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
 pub struct UserField {
     #[serde(rename = "type")]
     pub t: TypeDescr,
     offset: u64,
-    #[serde(rename = "anonymous")]
+    #[serde(rename = "anonymous", default)]
     anon: bool,
 }
 
+impl UserField {
+    /// Returns the offset of the field in bytes.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the type of the field.
+    pub fn type_descr(&self) -> &TypeDescr {
+        &self.t
+    }
+
+    /// Returns true iff the field is an anonymous struct or union whose
+    /// fields are accessed as if they were fields of the containing type.
+    pub fn is_anonymous(&self) -> bool {
+        self.anon
+    }
+}
+
 struct UserFieldConstructionCtx<'a, 'b> {
     uctx: &'a UserConstructionCtx<'b>,
     m: btf::Member<'b>,
 }
 
-impl From<UserFieldConstructionCtx<'_, '_>> for UserField {
-    fn from(ctx: UserFieldConstructionCtx) -> Self {
+impl UserFieldConstructionCtx<'_, '_> {
+    /// Clamps the bitfield `bfinfo` of this field to the end of its storage
+    /// unit of `unit_size` bytes if it exceeds it, as in corrupted or vendor
+    /// BTF.
+    ///
+    /// The unit begins at the byte where the bitfield begins, so the bitfield
+    /// always starts within it. Clamped bitfields are recorded, so that they
+    /// fail the layout check.
+    fn fix_bitfield(&self, bfinfo: &mut btf::BitfieldInfo, unit_size: usize) {
+        let unit_bits = 8 * unit_size as u32;
+        let end = u32::from(bfinfo.position) + u32::from(bfinfo.length);
+        if unit_bits == 0 || end <= unit_bits {
+            return;
+        }
+        let length = unit_bits.saturating_sub(u32::from(bfinfo.position));
+        let id = self.uctx.basic_ctx.tx.id;
+        let btf = self.uctx.basic_ctx.btf;
+        if self
+            .uctx
+            .invalid_bitfields
+            .borrow_mut()
+            .insert((id, self.m.index()))
+        {
+            let parent = btf.get_names_by_id(id, None).unwrap_or_default();
+            log::warn!(
+                target: diagnostics::INVALID_BITFIELDS,
+                code = "clamped_bitfield",
+                id:% = id,
+                type_name:% = parent.first().map_or("", String::as_str),
+                field:% = self.m.name(btf),
+                bits = bfinfo.length,
+                position = bfinfo.position,
+                type_bits = unit_bits,
+                clamped_bits = length;
+                "[{}] bitfield `{}` of `{}` of {} bits at bit {} exceeds its {} bit type, \
+                 clamping it to {} bits",
+                id,
+                self.m.name(btf),
+                parent.first().map_or("", String::as_str),
+                bfinfo.length,
+                bfinfo.position,
+                unit_bits,
+                length
+            );
+        }
+        bfinfo.length = length as u8;
+    }
+}
+
+impl TryFrom<UserFieldConstructionCtx<'_, '_>> for UserField {
+    type Error = Error;
+
+    fn try_from(ctx: UserFieldConstructionCtx) -> Result<Self> {
         let rt = ctx
             .uctx
             .basic_ctx
             .btf
-            .resolve_type_chain(ctx.m.get_tx(ctx.uctx.basic_ctx.btf));
+            .resolve_type_chain(ctx.m.get_tx(ctx.uctx.basic_ctx.btf)?);
         let name = rt.name(ctx.uctx.basic_ctx.btf);
+        if ctx.uctx.is_traced() {
+            log::info!(
+                target: TRACE_TARGET,
+                "[{}] resolves along {:?} to [{}], named `{}` ({})",
+                Origin::Field(&ctx),
+                rt.path,
+                rt.tx.id,
+                name,
+                rt.name_source(ctx.uctx.basic_ctx.btf)
+            );
+        }
 
-        UserField {
-            t: TypeDescr::from(TypeDescrConstructionCtx {
-                ufctx: &ctx,
+        let field = UserField {
+            t: TypeDescr::try_from(TypeDescrConstructionCtx {
+                btf: ctx.uctx.basic_ctx.btf,
+                origin: Origin::Field(&ctx),
+                rich_functions: ctx.uctx.rich_functions,
                 rt,
                 name,
                 handle_bitfield: true,
-            }),
+                depth: 0,
+            })?,
             offset: ctx.m.byte_offset(),
-            anon: ctx.m.is_anon(),
+            anon: ctx.m.is_anon().context("Member without name offset")?,
+        };
+        if ctx.uctx.is_traced() {
+            log::info!(
+                target: TRACE_TARGET,
+                "[{}] field {}",
+                Origin::Field(&ctx),
+                serde_json::to_string(&field)?
+            );
         }
+        Ok(field)
     }
 }
 
-#[derive(Serialize)]
-pub struct UserFields(HashMap<String, UserField>);
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserFields(BTreeMap<btf::Name, UserField>);
 
 impl UserFields {
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &UserField)> {
+    pub fn iter(&self) -> impl Iterator<Item = (&btf::Name, &UserField)> {
         self.0.iter()
     }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&btf::Name, &mut UserField)> {
+        self.0.iter_mut()
+    }
 }
 
 impl TryFrom<&UserConstructionCtx<'_>> for UserFields {
@@ -363,24 +800,56 @@ impl TryFrom<&UserConstructionCtx<'_>> for UserFields {
             .t
             .as_has_members()
             .context("Cannot construct ISF user type from BTF type without members.")?
-            .members();
+            .members(ctx.basic_ctx.btf);
 
-        Ok(Self(
-            members
-                .into_iter()
-                .map(|m| {
-                    (
-                        m.name(ctx.basic_ctx.btf),
-                        UserField::from(UserFieldConstructionCtx { uctx: ctx, m }),
-                    )
-                })
-                .collect(),
-        ))
+        let btf = ctx.basic_ctx.btf;
+        let mut fields = BTreeMap::new();
+        for m in members {
+            if ctx.is_traced() {
+                log::info!(
+                    target: TRACE_TARGET,
+                    "[{}] member {}: {}",
+                    ctx.basic_ctx.tx.id,
+                    m.index(),
+                    m.describe_layout(btf)
+                );
+            }
+            // Members of corrupted BTF are skipped, the rest of the type is
+            // still usable.
+            if let Err(err) = m
+                .get_tx(btf)
+                .and_then(|_| m.is_anon().context("Member without name offset"))
+            {
+                let id = ctx.basic_ctx.tx.id;
+                if ctx.skipped_members.borrow_mut().insert((id, m.index())) {
+                    let parent = btf.get_names_by_id(id, None).unwrap_or_default();
+                    log::warn!(
+                        target: diagnostics::SKIPPED_MEMBERS,
+                        code = "skipped_member",
+                        id:% = id,
+                        type_name:% = parent.first().map_or("", String::as_str),
+                        index = m.index(),
+                        error:% = format!("{:#}", err);
+                        "[{}] skipping member {} of `{}`: {:#}",
+                        id,
+                        m.index(),
+                        parent.first().map_or("", String::as_str),
+                        err
+                    );
+                }
+                continue;
+            }
+            fields.insert(
+                m.name(btf),
+                UserField::try_from(UserFieldConstructionCtx { uctx: ctx, m })?,
+            );
+        }
+        Ok(Self(fields))
     }
 }
 
 /// ISF `element_user_type`.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
 pub struct User {
     pub kind: UserKind,
     size: u64,
@@ -390,61 +859,205 @@ pub struct User {
 pub struct UserConstructionCtx<'a> {
     pub basic_ctx: BaseConstructionCtx<'a>,
     pub typedefs: &'a btf::Typedefs,
+    /// Include the prototype in function descriptors.
+    pub rich_functions: bool,
+    /// Members of corrupted BTF that were skipped, by type and index.
+    pub skipped_members: &'a RefCell<HashSet<(btf::Id, u64)>>,
+    /// Bitfields that exceed their type and were fixed, by type and index,
+    /// see `UserFieldConstructionCtx::fix_bitfield`.
+    pub invalid_bitfields: &'a RefCell<HashSet<(btf::Id, u64)>>,
+    /// User types whose construction is logged, see `--trace-type`.
+    pub traced: &'a HashSet<btf::Id>,
+}
+
+impl UserConstructionCtx<'_> {
+    fn is_traced(&self) -> bool {
+        self.traced.contains(&self.basic_ctx.tx.id)
+    }
 }
 
-impl From<UserConstructionCtx<'_>> for User {
-    fn from(ctx: UserConstructionCtx) -> Self {
+impl User {
+    /// Returns an empty user type of `kind` that stands in for a type that is
+    /// only declared.
+    pub fn new_stub(kind: UserKind) -> Self {
         User {
-            kind: UserKind::try_from(&ctx.basic_ctx.tx.t)
-                .expect("Attempt to construct ISF user type from invalid BTF type."),
-            size: ctx.basic_ctx.tx.t.size().unwrap() as u64,
-            fields: UserFields::try_from(&ctx).expect("Failed to construct user type."),
+            kind,
+            size: 0,
+            fields: UserFields(BTreeMap::new()),
         }
     }
+
+    pub fn kind(&self) -> UserKind {
+        self.kind
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the fields by name, in the order of their names.
+    pub fn fields(&self) -> impl ExactSizeIterator<Item = (&str, &UserField)> {
+        self.fields.0.iter().map(|(name, field)| (&**name, field))
+    }
+
+    /// Returns the field named `name`.
+    pub fn get_field(&self, name: &str) -> Option<&UserField> {
+        self.fields.0.get(name)
+    }
+}
+
+impl TryFrom<UserConstructionCtx<'_>> for User {
+    type Error = Error;
+
+    fn try_from(ctx: UserConstructionCtx) -> Result<Self> {
+        Ok(User {
+            kind: UserKind::try_from(&ctx.basic_ctx.tx.t)?,
+            size: ctx
+                .basic_ctx
+                .tx
+                .t
+                .size()
+                .context("user type without size")? as u64,
+            fields: UserFields::try_from(&ctx).context("failed to construct fields")?,
+        })
+    }
 }
 
 /// ISF `element_enum`.
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
 pub struct Enum {
     size: u8,
     base: String,
-    constants: HashMap<String, i128>,
+    constants: BTreeMap<String, i128>,
+}
+
+impl Enum {
+    /// Returns the name of the base type of the enum.
+    pub fn base(&self) -> &String {
+        &self.base
+    }
+
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    pub fn constants(&self) -> &BTreeMap<String, i128> {
+        &self.constants
+    }
+
+    /// Returns the value of the constant named `name`.
+    pub fn get_constant(&self, name: &str) -> Option<i128> {
+        self.constants.get(name).copied()
+    }
+
+    /// Returns an enum without constants that stands in for an undefined enum.
+    ///
+    /// Its base type is a signed 4 byte integer from `base_types`, the size of
+    /// `int` on all supported architectures, or else `pointer`. Returns `None`
+    /// if there is neither, e.g., with `--no-quirks`.
+    pub fn new_stub(base_types: &BTreeMap<String, Base>) -> Option<Self> {
+        let (base, basic_type) = base_types
+            .iter()
+            .find(|(_, basic_type)| {
+                basic_type.size == 4 && basic_type.signed && basic_type.kind == BaseKind::Int
+            })
+            .or_else(|| base_types.get_key_value("pointer"))?;
+        Some(Enum {
+            size: basic_type.size,
+            base: base.clone(),
+            constants: BTreeMap::new(),
+        })
+    }
 }
 
 pub struct EnumConstructionCtx<'a> {
     pub basic_ctx: BaseConstructionCtx<'a>,
-    pub base_types: &'a BTreeMap<String, Base>,
+    /// Base types to choose the base type of the enum from. A base type is
+    /// added if none fits.
+    pub base_types: &'a mut BTreeMap<String, Base>,
 }
 
-impl From<EnumConstructionCtx<'_>> for Enum {
-    fn from(ctx: EnumConstructionCtx) -> Self {
-        let size = ctx.basic_ctx.tx.t.size().unwrap() as u8;
-        let signed = ctx.basic_ctx.tx.t.signed().unwrap();
-
-        let base = ctx
+impl EnumConstructionCtx<'_> {
+    /// Returns the name of the base type for an enum of `size` bytes.
+    ///
+    /// Prefers an integer of the same size and signedness, then one of the
+    /// same size. Otherwise, adds an integer named
+    /// `__enum_base_<size>_<signed|unsigned>`.
+    fn base(&mut self, size: u8, signed: bool) -> Result<String> {
+        let id = self.basic_ctx.tx.id;
+        let is_int = |base: &Base| base.size == size && base.kind == BaseKind::Int;
+        if let Some((name, _)) = self
             .base_types
             .iter()
-            .find(|(_, basic_type)| {
-                basic_type.size == size
-                    && basic_type.signed == signed
-                    && basic_type.kind == BaseKind::Int
-            })
-            .map(|(name, _)| name.clone())
-            .expect("Cannot find name for base type of enum.");
+            .find(|(_, base)| is_int(base) && base.signed == signed)
+        {
+            return Ok(name.clone());
+        }
+        if let Some((name, _)) = self.base_types.iter().find(|(_, base)| is_int(base)) {
+            log::warn!(
+                target: diagnostics::REPAIRED_TYPES,
+                code = "substituted_enum_base_type",
+                id:% = id,
+                signed = signed,
+                size = size,
+                base_type:% = name;
+                "[{}] no {} base type of {} bytes for enum, using `{}`",
+                id,
+                if signed { "signed" } else { "unsigned" },
+                size,
+                name
+            );
+            return Ok(name.clone());
+        }
 
-        Enum {
-            size: ctx.basic_ctx.tx.t.size().unwrap() as u8,
-            base,
-            constants: ctx
-                .basic_ctx
-                .tx
-                .t
-                .as_enum()
-                .unwrap()
-                .variants(ctx.basic_ctx.btf)
-                .into_iter()
-                .map(|(k, v)| (k, v.into()))
-                .collect(),
+        let name = format!(
+            "__enum_base_{}_{}",
+            size,
+            if signed { "signed" } else { "unsigned" }
+        );
+        if self.base_types.contains_key(&name) {
+            bail!("no base type of {} bytes, and `{}` is taken", size, name);
         }
+        log::warn!(
+            target: diagnostics::REPAIRED_TYPES,
+            code = "added_enum_base_type",
+            id:% = id,
+            signed = signed,
+            size = size,
+            base_type:% = name;
+            "[{}] no base type of {} bytes for enum, adding `{}`",
+            id,
+            size,
+            name
+        );
+        let endian = (&self.basic_ctx.btf.endian).into();
+        self.base_types
+            .insert(name.clone(), Base::new(size, signed, BaseKind::Int, endian));
+        Ok(name)
+    }
+}
+
+impl TryFrom<EnumConstructionCtx<'_>> for Enum {
+    type Error = Error;
+
+    fn try_from(mut ctx: EnumConstructionCtx) -> Result<Self> {
+        let t = &ctx.basic_ctx.tx.t;
+        let size = t.size().context("enum without size")?;
+        let size = u8::try_from(size).with_context(|| format!("enum of {} bytes", size))?;
+        let signed = t.signed().context("enum without signedness")?;
+        let constants = t
+            .as_enum()
+            .context("not an enum")?
+            .variants(ctx.basic_ctx.btf, ctx.basic_ctx.tx.id)
+            .context("Failed to construct enum.")?
+            .into_iter()
+            .map(|(k, v)| (k, v.into()))
+            .collect();
+
+        Ok(Enum {
+            size,
+            base: ctx.base(size, signed)?,
+            constants,
+        })
     }
 }
@@ -0,0 +1,144 @@
+//! Linux kernel versions.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Error, Result};
+
+/// Prefix of the Linux banner that precedes the kernel release.
+pub(crate) const BANNER_PREFIX: &str = "Linux version ";
+
+/// Returns the kernel release in the Linux banner `banner`, e.g.,
+/// `6.1.0-18-amd64`.
+pub fn release(banner: &str) -> Option<&str> {
+    banner
+        .strip_prefix(BANNER_PREFIX)?
+        .split_whitespace()
+        .next()
+}
+
+/// Version of a Linux kernel, e.g., `6.1.0`.
+///
+/// Suffixes like `-91-generic` or `-rc3` are not part of the version.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KernelVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl KernelVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses the kernel version from a Linux banner.
+    pub fn from_banner(banner: &str) -> Option<Self> {
+        release(banner)?.parse().ok()
+    }
+}
+
+impl FromStr for KernelVersion {
+    type Err = Error;
+
+    /// Parses versions of the form `x.y` or `x.y.z`, followed by an optional
+    /// suffix that does not start with a digit.
+    fn from_str(s: &str) -> Result<Self> {
+        let end = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let mut parts = s[..end].split('.').filter(|p| !p.is_empty());
+        let mut next = |what: &str| -> Result<Option<u32>> {
+            parts
+                .next()
+                .map(|p| {
+                    p.parse::<u32>()
+                        .with_context(|| format!("Invalid {} version in {}", what, s))
+                })
+                .transpose()
+        };
+        let (Some(major), Some(minor)) = (next("major")?, next("minor")?) else {
+            bail!("Invalid kernel version: {}", s)
+        };
+        let patch = next("patch")?.unwrap_or(0);
+
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for KernelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Comparison operator of a [`VersionReq`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+/// Requirement on the kernel version, e.g., `>=5.15` or `<6.2`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VersionReq {
+    op: Op,
+    version: KernelVersion,
+}
+
+impl VersionReq {
+    /// Returns true iff `version` satisfies the requirement.
+    pub fn matches(&self, version: KernelVersion) -> bool {
+        match self.op {
+            Op::Lt => version < self.version,
+            Op::Le => version <= self.version,
+            Op::Eq => version == self.version,
+            Op::Ge => version >= self.version,
+            Op::Gt => version > self.version,
+        }
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Eq => "=",
+            Op::Ge => ">=",
+            Op::Gt => ">",
+        };
+        write!(f, "{}{}", op, self.version)
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = Error;
+
+    /// Parses requirements of the form `<op><version>`, where the operator is
+    /// one of `<`, `<=`, `=`, `>=`, and `>`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (op, version) = [
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+            ("=", Op::Eq),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| Some((op, s.strip_prefix(prefix)?)))
+        .with_context(|| format!("Invalid version requirement: {}", s))?;
+
+        Ok(Self {
+            op,
+            version: version.parse()?,
+        })
+    }
+}
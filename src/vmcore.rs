@@ -0,0 +1,170 @@
+//! Utilities for working with ELF vmcore dumps, e.g., from kdump.
+
+use crate::btf::Endian;
+use crate::diagnostics;
+use crate::elf;
+
+use std::ops::Range;
+use std::str;
+
+use anyhow::{bail, Context, Result};
+use goblin::elf::header::{EI_CLASS, ELFCLASS32};
+use goblin::elf::program_header::{PT_LOAD, PT_NOTE};
+
+/// Signatures of the compressed kdump format written by makedumpfile.
+const KDUMP_SIGNATURES: [&[u8]; 2] = [b"KDUMP   ", b"DISKDUMP"];
+/// Name of the note that holds the VMCOREINFO.
+const VMCOREINFO_NAME: &[u8] = b"VMCOREINFO";
+
+/// A loadable segment of a vmcore.
+struct Segment {
+    /// Virtual address of the segment, zero if the segment is not mapped.
+    vaddr: u64,
+    /// Location of the contents of the segment in the vmcore.
+    file: Range<usize>,
+}
+
+/// Layout of an ELF vmcore.
+pub struct Vmcore {
+    segments: Vec<Segment>,
+    /// Offset of the kernel from its link-time address (KASLR).
+    kernel_offset: u64,
+    endian: Endian,
+    /// Size of a pointer of the kernel, from the ELF class.
+    pointer_size: usize,
+}
+
+impl Vmcore {
+    /// Parses the program headers and the VMCOREINFO of the vmcore `raw`.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        if is_compressed_kdump(raw) {
+            bail!("Compressed kdump files are not supported, convert to ELF with `makedumpfile -E`")
+        }
+        if !elf::is_core(raw) {
+            bail!("Not an ELF vmcore")
+        }
+        let endian = elf::is_elf(raw)?;
+
+        let phdrs = elf::program_headers(raw)?;
+        let segments: Vec<Segment> = phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .filter_map(|phdr| {
+                Some(Segment {
+                    vaddr: phdr.p_vaddr,
+                    file: elf::segment_range(raw, phdr)?,
+                })
+            })
+            .collect();
+        log::debug!("vmcore has {} loadable segments", segments.len());
+
+        let vmcoreinfo = phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_NOTE)
+            .filter_map(|phdr| elf::segment_range(raw, phdr))
+            .find_map(|range| find_vmcoreinfo(&raw[range], endian));
+        let kernel_offset = match vmcoreinfo {
+            Some(info) => parse_kernel_offset(info)?,
+            None => {
+                log::warn!(
+                    target: diagnostics::INPUTS,
+                    code = "missing_vmcoreinfo";
+                    "vmcore has no VMCOREINFO, assuming the kernel is not relocated"
+                );
+                0
+            }
+        };
+        log::debug!("Kernel offset {:#x}", kernel_offset);
+
+        Ok(Self {
+            segments,
+            kernel_offset,
+            endian,
+            pointer_size: if raw[EI_CLASS] == ELFCLASS32 { 4 } else { 8 },
+        })
+    }
+
+    /// Returns the locations of the contents of all loadable segments.
+    pub fn segments(&self) -> Vec<Range<usize>> {
+        self.segments.iter().map(|seg| seg.file.clone()).collect()
+    }
+
+    /// Returns the offset of the kernel from its link-time address.
+    pub fn kernel_offset(&self) -> u64 {
+        self.kernel_offset
+    }
+
+    /// Returns the byte order of the kernel.
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// Returns the size of a pointer of the kernel.
+    pub fn pointer_size(&self) -> usize {
+        self.pointer_size
+    }
+
+    /// Returns the virtual address of the contents at offset `off` of the
+    /// vmcore, if it is in a mapped segment.
+    pub fn virt_addr(&self, off: usize) -> Option<u64> {
+        let seg = self
+            .segments
+            .iter()
+            .find(|seg| seg.vaddr != 0 && seg.file.contains(&off))?;
+        Some(seg.vaddr + (off - seg.file.start) as u64)
+    }
+
+    /// Returns up to `len` bytes at the virtual address `vaddr`.
+    ///
+    /// Fewer bytes are returned if the segment ends before. Returns `None` if
+    /// the address is not in any segment.
+    pub fn read_virt<'a>(&self, raw: &'a [u8], vaddr: u64, len: usize) -> Option<&'a [u8]> {
+        let seg = self.segments.iter().find(|seg| {
+            seg.vaddr != 0 && vaddr >= seg.vaddr && vaddr - seg.vaddr < seg.file.len() as u64
+        })?;
+        let start = seg.file.start + (vaddr - seg.vaddr) as usize;
+        Some(&raw[start..seg.file.end.min(start.saturating_add(len))])
+    }
+}
+
+/// Returns true iff `raw` is in the compressed kdump format.
+pub fn is_compressed_kdump(raw: &[u8]) -> bool {
+    KDUMP_SIGNATURES.iter().any(|sig| raw.starts_with(sig))
+}
+
+/// Returns the contents of the VMCOREINFO note in the note segment `notes`.
+fn find_vmcoreinfo(mut notes: &[u8], endian: Endian) -> Option<&str> {
+    let u32_at = |data: &[u8], off: usize| -> Option<usize> {
+        let bytes = data.get(off..off + 4)?.try_into().ok()?;
+        Some(match endian {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        } as usize)
+    };
+    let aligned = |len: usize| len.checked_next_multiple_of(4);
+
+    while notes.len() >= 12 {
+        let (namesz, descsz) = (u32_at(notes, 0)?, u32_at(notes, 4)?);
+        let name_end = 12 + aligned(namesz)?;
+        let desc_end = name_end.checked_add(aligned(descsz)?)?;
+        let name = notes.get(12..12 + namesz)?;
+        let desc = notes.get(name_end..name_end + descsz)?;
+        if name.strip_suffix(b"\0").unwrap_or(name) == VMCOREINFO_NAME {
+            return str::from_utf8(desc).ok();
+        }
+        notes = notes.get(desc_end..)?;
+    }
+    None
+}
+
+/// Returns the KASLR offset from the VMCOREINFO `info`.
+fn parse_kernel_offset(info: &str) -> Result<u64> {
+    let Some(offset) = info
+        .lines()
+        .find_map(|line| line.strip_prefix("KERNELOFFSET="))
+    else {
+        return Ok(0);
+    };
+    u64::from_str_radix(offset, 16)
+        .with_context(|| format!("Invalid KERNELOFFSET in VMCOREINFO: {}", offset))
+}
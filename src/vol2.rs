@@ -0,0 +1,313 @@
+//! Volatility 2 Linux profiles, as written by `btf2json vol2`.
+//!
+//! A profile is a zip file with a Python module that defines the vtypes of the
+//! user types in `linux_types`, and a System.map. Types are described as in
+//! the vtypes that Volatility 2 derives from DWARF:
+//!
+//! ```python
+//! linux_types = {
+//!     'list_head': [16, {
+//!         'next': [0, ['pointer', ['list_head']]],
+//!         'prev': [8, ['pointer', ['list_head']]],
+//!     }],
+//! }
+//! ```
+
+use crate::cli::Vol2Args;
+use crate::isf::Isf;
+use crate::v_types::{Base, BaseKind, TypeDescr, User};
+use crate::GenerationContext;
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use anyhow::{Error, Result};
+use flate2::write::DeflateEncoder;
+use flate2::Crc;
+
+/// Name of the vtypes module in the profile.
+pub const VTYPES_NAME: &str = "linux.vtypes";
+/// Name of the System.map in the profile.
+pub const SYSTEM_MAP_NAME: &str = "System.map";
+
+/// Volatility 2 Linux profile.
+pub struct Profile {
+    vtypes: String,
+    system_map: String,
+}
+
+impl TryFrom<&Vol2Args> for Profile {
+    type Error = Error;
+
+    fn try_from(args: &Vol2Args) -> Result<Profile> {
+        let ctx = GenerationContext::try_from(&args.generate)?;
+        let banner = ctx.banner().map(ToString::to_string);
+        // Only the addresses of symbols are in the profile, so their types
+        // need no fixing.
+        let isf = Isf::try_from(ctx)?;
+        Ok(Profile::new(&isf, banner.as_deref()))
+    }
+}
+
+impl Profile {
+    /// Returns the profile of the types and symbols of `isf`, generated for
+    /// the kernel with `banner`.
+    pub fn new(isf: &Isf, banner: Option<&str>) -> Self {
+        let mut vtypes = format!(
+            "# Volatility 2 vtypes generated by btf2json {}\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        if let Some(banner) = banner {
+            let _ = writeln!(vtypes, "# {}", banner.trim_end());
+        }
+        vtypes.push_str("linux_types = {\n");
+        for (name, user) in isf.user_types() {
+            let _ = writeln!(vtypes, "    {}: [{}, {{", py_str(name), user.size());
+            for (field, (offset, t)) in vtype_fields(isf, user) {
+                let _ = writeln!(
+                    vtypes,
+                    "        {}: [{}, {}],",
+                    py_str(field),
+                    offset,
+                    type_spec(isf, t)
+                );
+            }
+            vtypes.push_str("    }],\n");
+        }
+        vtypes.push_str("}\n");
+
+        // Addresses are as wide as pointers, which tells the memory model.
+        let width = 2 * isf
+            .get_base_type("pointer")
+            .map_or(8, |p| p.size() as usize);
+        let mut symbols: Vec<_> = isf.symbols().collect();
+        symbols.sort_by_key(|(name, sym)| (sym.address(), *name));
+        let mut system_map = String::new();
+        for (name, sym) in symbols {
+            let kind = sym
+                .kind()
+                .map_or_else(|| "D".to_string(), |k| k.to_string());
+            let _ = writeln!(
+                system_map,
+                "{:0width$x} {} {}",
+                sym.address(),
+                kind,
+                name,
+                width = width
+            );
+        }
+
+        Self { vtypes, system_map }
+    }
+
+    /// Returns the Python module with the vtypes.
+    pub fn vtypes(&self) -> &str {
+        &self.vtypes
+    }
+
+    /// Returns the System.map with the symbols.
+    pub fn system_map(&self) -> &str {
+        &self.system_map
+    }
+
+    /// Writes the profile as zip file to `writer`.
+    pub fn write_zip(&self, writer: impl Write) -> io::Result<()> {
+        write_zip(
+            writer,
+            &[
+                (VTYPES_NAME, self.vtypes.as_bytes()),
+                (SYSTEM_MAP_NAME, self.system_map.as_bytes()),
+            ],
+        )
+    }
+}
+
+/// Returns the fields of `user` by name, with their offsets and types.
+///
+/// The fields of anonymous structs and unions are also added as fields of
+/// `user`, as Volatility 2 does not look them up in anonymous members, unless
+/// `user` has fields of the same names.
+fn vtype_fields<'a>(isf: &'a Isf, user: &'a User) -> BTreeMap<&'a str, (u64, &'a TypeDescr)> {
+    let mut fields = BTreeMap::new();
+    let mut inlined = Vec::new();
+    add_fields(isf, user, 0, &mut fields, &mut inlined);
+    for (name, field) in inlined {
+        fields.entry(name).or_insert(field);
+    }
+    fields
+}
+
+fn add_fields<'a>(
+    isf: &'a Isf,
+    user: &'a User,
+    base: u64,
+    fields: &mut BTreeMap<&'a str, (u64, &'a TypeDescr)>,
+    inlined: &mut Vec<(&'a str, (u64, &'a TypeDescr))>,
+) {
+    for (name, field) in user.fields() {
+        let offset = base + field.offset();
+        fields.insert(name, (offset, field.type_descr()));
+        let anon = match field.type_descr() {
+            TypeDescr::Struct { name } | TypeDescr::Union { name } if field.is_anonymous() => {
+                isf.get_user_type(name)
+            }
+            _ => None,
+        };
+        if let Some(anon) = anon {
+            let mut nested = BTreeMap::new();
+            add_fields(isf, anon, offset, &mut nested, inlined);
+            inlined.extend(nested);
+        }
+    }
+}
+
+/// Returns the Volatility 2 type spec of `t`.
+fn type_spec(isf: &Isf, t: &TypeDescr) -> String {
+    match t {
+        TypeDescr::Base { name } => format!("[{}]", py_str(&base_name(isf, name))),
+        TypeDescr::Struct { name } | TypeDescr::Union { name } => format!("[{}]", py_str(name)),
+        TypeDescr::Enum { name } => {
+            let Some(e) = isf.get_enum(name) else {
+                return "['int']".to_string();
+            };
+            let choices = e
+                .constants()
+                .iter()
+                .map(|(name, value)| format!("{}: {}", value, py_str(name)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "['Enumeration', {{'target': {}, 'choices': {{{}}}}}]",
+                py_str(&base_name(isf, e.base())),
+                choices
+            )
+        }
+        TypeDescr::Pointer { subtype } => format!("['pointer', {}]", type_spec(isf, subtype)),
+        TypeDescr::Array { count, subtype } => {
+            format!("['array', {}, {}]", count, type_spec(isf, subtype))
+        }
+        TypeDescr::Bitfield {
+            bit_position,
+            bit_length,
+            type_,
+        } => {
+            let native = match type_.as_ref() {
+                TypeDescr::Base { name } => base_name(isf, name),
+                TypeDescr::Enum { name } => isf
+                    .get_enum(name)
+                    .map_or_else(|| "int".to_string(), |e| base_name(isf, e.base())),
+                _ => "unsigned int".to_string(),
+            };
+            format!(
+                "['BitField', {{'start_bit': {}, 'end_bit': {}, 'native_type': {}}}]",
+                bit_position,
+                *bit_position as u16 + *bit_length as u16,
+                py_str(&native)
+            )
+        }
+        // Volatility 2 has no function types, they are only pointed to.
+        TypeDescr::Function { .. } => "['void']".to_string(),
+    }
+}
+
+/// Returns the name of the Volatility 2 native type of the base type `name`.
+fn base_name(isf: &Isf, name: &str) -> String {
+    let native = match name {
+        "_Bool" => "unsigned char",
+        "char" | "signed char" | "unsigned char" | "int" | "unsigned int" | "float" | "double"
+        | "void" => name,
+        "short int" | "short" => "short",
+        "short unsigned int" | "unsigned short" => "unsigned short",
+        "long int" | "long" => "long",
+        "long unsigned int" | "unsigned long" => "unsigned long",
+        "long long int" | "long long" => "long long",
+        "long long unsigned int" | "unsigned long long" => "unsigned long long",
+        "long double" => "double",
+        "pointer" => "address",
+        _ => match isf.get_base_type(name) {
+            Some(base) => native_by_layout(base),
+            None => "int",
+        },
+    };
+    native.to_string()
+}
+
+/// Returns the name of the Volatility 2 native type with the layout of `base`.
+fn native_by_layout(base: &Base) -> &'static str {
+    match (base.kind(), base.size(), base.is_signed()) {
+        (BaseKind::Void, ..) => "void",
+        (BaseKind::Float, 4, _) => "float",
+        (BaseKind::Float, ..) => "double",
+        (BaseKind::Bool, ..) => "unsigned char",
+        (_, 1, true) => "signed char",
+        (_, 1, false) => "unsigned char",
+        (_, 2, true) => "short",
+        (_, 2, false) => "unsigned short",
+        (_, 4, true) => "int",
+        (_, 4, false) => "unsigned int",
+        (_, _, true) => "long long",
+        (_, _, false) => "unsigned long long",
+    }
+}
+
+/// Returns `s` as a Python string literal.
+fn py_str(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Date of the files in zip files, 1980-01-01 in the MS-DOS format, which
+/// keeps profiles reproducible.
+const ZIP_DATE: u16 = (1 << 5) | 1;
+
+/// Writes a zip file of the deflated `files` with name and contents to
+/// `writer`.
+fn write_zip(mut writer: impl Write, files: &[(&str, &[u8])]) -> io::Result<()> {
+    let mut offset = 0u32;
+    let mut central = Vec::new();
+    for (name, data) in files {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        // Common part of the local and central headers, from the version
+        // needed to extract to the file name length.
+        let mut common = Vec::new();
+        for v in [20u16, 0, 8, 0, ZIP_DATE] {
+            common.extend(v.to_le_bytes());
+        }
+        for v in [crc.sum(), compressed.len() as u32, data.len() as u32] {
+            common.extend(v.to_le_bytes());
+        }
+        common.extend((name.len() as u16).to_le_bytes());
+
+        let mut local = 0x04034b50u32.to_le_bytes().to_vec();
+        local.extend(&common);
+        local.extend(0u16.to_le_bytes());
+        local.extend(name.as_bytes());
+        writer.write_all(&local)?;
+        writer.write_all(&compressed)?;
+
+        central.extend(0x02014b50u32.to_le_bytes());
+        central.extend(20u16.to_le_bytes());
+        central.extend(&common);
+        // Extra field, comment, disk, and attributes.
+        central.extend([0; 12]);
+        central.extend(offset.to_le_bytes());
+        central.extend(name.as_bytes());
+        offset += (local.len() + compressed.len()) as u32;
+    }
+    writer.write_all(&central)?;
+
+    let mut end = 0x06054b50u32.to_le_bytes().to_vec();
+    end.extend([0; 4]);
+    end.extend((files.len() as u16).to_le_bytes());
+    end.extend((files.len() as u16).to_le_bytes());
+    end.extend((central.len() as u32).to_le_bytes());
+    end.extend(offset.to_le_bytes());
+    end.extend(0u16.to_le_bytes());
+    writer.write_all(&end)?;
+    writer.flush()
+}
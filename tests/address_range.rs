@@ -0,0 +1,114 @@
+//! Tests for the check of the symbol addresses against the kernel address
+//! range of the architecture.
+//!
+//! The System.map is a slice of the one of an x86_64 kernel with a KASLR
+//! shift of `0x2e000000`, with per-CPU and absolute symbols, see
+//! `tests/percpu.rs`. The types are from `tests/data/funcs/funcs.btf`.
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use btf2json::cli::Architecture;
+use btf2json::consistency::OutOfRange;
+
+const MAP: &str = "\
+0000000000000000 D __per_cpu_start
+0000000000000000 D fixed_percpu_data
+00000000000001e0 A __kcfi_typeid_vfs_read
+0000000000014ec0 D runqueues
+000000000002e000 D __per_cpu_end
+00000000bb4a6ac1 A __kcfi_typeid_do_init
+ffffffffaf000000 T _text
+ffffffffaf000000 T _stext
+ffffffffaf000100 T vfs_read
+ffffffffb0800000 D linux_banner
+ffffffffb0a00010 D jiffies
+";
+
+const BTF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs/funcs.btf");
+
+fn write_map(name: &str) -> String {
+    let map = common::write_temp(&format!("range-{}.map", name), MAP);
+    map.to_str().unwrap().to_owned()
+}
+
+fn check(name: &str, args: &[&str]) -> Option<OutOfRange> {
+    let map = write_map(name);
+    let cli = [
+        "--btf",
+        BTF,
+        "--map",
+        &map,
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    let ctx = common::context(&[&cli, args].concat()).unwrap();
+    fs::remove_file(&map).unwrap();
+    ctx.check_address_range()
+}
+
+#[test]
+fn rebased() {
+    assert_eq!(check("rebased", &[]), None);
+    // KASLR addresses are still kernel addresses.
+    assert_eq!(check("unrebased", &["--no-rebase"]), None);
+}
+
+#[test]
+fn mis_based() {
+    let out_of_range = check("mis-based", &["--base-offset", "0x1000000"]).unwrap();
+    assert_eq!(out_of_range.arch, Architecture::X86_64);
+    // The per-CPU and absolute symbols are not counted.
+    assert_eq!((out_of_range.outside, out_of_range.checked), (5, 5));
+    assert_eq!(out_of_range.addresses, 0x1000000..=0x2a00010);
+    assert_eq!(
+        out_of_range.to_string(),
+        "100% of symbols fall outside the expected kernel address range \
+         0xffffffff80000000-0xfffffffffeffffff for x86_64, they are at \
+         0x1000000-0x2a00010; check --arch/--base-offset"
+    );
+}
+
+#[test]
+fn arm64() {
+    assert_eq!(check("arm64", &["--arch", "arm64"]), None);
+    assert_eq!(
+        check("arm64-va39", &["--arch", "arm64", "--va-bits", "39"]),
+        None
+    );
+    // Without known virtual address bits the largest range is expected.
+    let out_of_range = check(
+        "arm64-mis-based",
+        &["--arch", "arm64", "--base-offset", "0x80010000"],
+    )
+    .unwrap();
+    assert_eq!(out_of_range.range, 0xfff0000000000000..=u64::MAX);
+}
+
+#[test]
+fn strict() {
+    let map = write_map("strict");
+    let output = common::run(&[
+        "generate",
+        "--strict",
+        "--base-offset",
+        "0x1000000",
+        "--btf",
+        BTF,
+        "--map",
+        &map,
+        "--banner",
+        "Linux version 6.18.0",
+    ]);
+    fs::remove_file(&map).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("100% of symbols fall outside"),
+        "{}",
+        stderr
+    );
+}
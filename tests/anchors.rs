@@ -0,0 +1,96 @@
+//! Tests for rebasing on symbols other than `_stext`.
+//!
+//! The System.map files in `tests/data/anchors` list `do_init`, `jiffies`, and
+//! `linux_banner`, `stext.map` with `_stext`, `text.map` with `_text` at the
+//! same address, and `none.map` without any text anchor. `padded.map` has
+//! `_text` 2 MiB before `_stext`, with a KASLR shift of `0x9000000`.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(map: &str) -> anyhow::Result<Value> {
+    generate_with(map, &[])
+}
+
+fn generate_with(map: &str, args: &[&str]) -> anyhow::Result<Value> {
+    let btf = common::data("funcs/funcs.btf");
+    let map = common::data("anchors").join(map);
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    Ok(common::try_generate(&[&cli, args].concat())?)
+}
+
+#[test]
+fn text_is_fallback_for_stext() {
+    let stext = generate("stext.map").unwrap();
+    let text = generate("text.map").unwrap();
+
+    for name in ["do_init", "jiffies"] {
+        assert_eq!(
+            text["symbols"][name]["address"],
+            stext["symbols"][name]["address"]
+        );
+    }
+    assert_eq!(
+        text["symbols"]["jiffies"]["address"],
+        json!(0xffffffff82000010u64)
+    );
+    assert_eq!(
+        text["symbols"]["_text"]["address"],
+        json!(0xffffffff81000000u64)
+    );
+}
+
+#[test]
+fn missing_anchor_lists_tried_symbols() {
+    let err = generate("none.map").unwrap_err();
+
+    assert!(
+        format!("{:#}", err).contains("_stext, _text, __start_text, startup_64"),
+        "{:#}",
+        err
+    );
+}
+
+#[test]
+fn anchor_symbol() {
+    let address = |isf: &Value, name: &str| isf["symbols"][name]["address"].clone();
+
+    let stext = generate("padded.map").unwrap();
+    assert_eq!(address(&stext, "_stext"), json!(0xffffffff81000000u64));
+    assert_eq!(address(&stext, "_text"), json!(0xffffffff80e00000u64));
+
+    let text = generate_with(
+        "padded.map",
+        &[
+            "--anchor-symbol",
+            "_text",
+            "--base-offset",
+            "0xffffffff81000000",
+        ],
+    )
+    .unwrap();
+    assert_eq!(address(&text, "_text"), json!(0xffffffff81000000u64));
+    assert_eq!(address(&text, "_stext"), json!(0xffffffff81200000u64));
+    assert_eq!(address(&text, "jiffies"), json!(0xffffffff82200010u64));
+}
+
+#[test]
+fn missing_anchor_symbol_is_named() {
+    let err = generate_with("stext.map", &["--anchor-symbol", "_text"]).unwrap_err();
+
+    assert!(
+        format!("{:#}", err).contains("Anchor symbol _text not found"),
+        "{:#}",
+        err
+    );
+}
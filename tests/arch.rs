@@ -0,0 +1,179 @@
+//! Tests for the detection of the architecture.
+
+mod common;
+
+use std::fs;
+
+use btf2json::arch;
+use btf2json::cli::{ArchArg, Architecture, Cli};
+use clap::Parser;
+
+/// Returns the architecture detected for a System.map with `_stext` at `stext`.
+fn detect_from_map(name: &str, stext: u64, args: &[&str]) -> anyhow::Result<Architecture> {
+    let map = common::write_temp(
+        &format!("{}.map", name),
+        format!("{:016x} T _stext\n", stext),
+    );
+    let cli = Cli::parse_from(
+        [
+            "btf2json",
+            "--map",
+            map.to_str().unwrap(),
+            "--banner",
+            "Linux version 6.18.0",
+        ]
+        .iter()
+        .chain(args),
+    );
+    let arch = arch::detect(cli.generate_args());
+    fs::remove_file(&map).unwrap();
+    arch
+}
+
+#[test]
+fn system_map() {
+    let cases = [
+        (0xffffffff81000000, Architecture::X86_64),
+        (0xffff800080010000, Architecture::Arm64),
+        (0xffffffc008010000, Architecture::Arm64),
+        (0xc000000000000000, Architecture::Ppc64le),
+    ];
+    for (stext, arch) in cases {
+        assert_eq!(detect_from_map("arch", stext, &[]).unwrap(), arch);
+    }
+}
+
+#[test]
+fn explicit_architecture_wins() {
+    let arch = detect_from_map("arch-explicit", 0xffffffff81000000, &["--arch", "arm64"]);
+    assert_eq!(arch.unwrap(), Architecture::Arm64);
+}
+
+#[test]
+fn arch_values() {
+    let arch = |args: &[&str]| {
+        let cli = Cli::parse_from(
+            [
+                "btf2json",
+                "--types-only",
+                "--banner",
+                "Linux version 6.18.0",
+            ]
+            .iter()
+            .chain(args),
+        );
+        cli.generate_args().arch
+    };
+    assert_eq!(arch(&[]), ArchArg::Auto);
+    assert_eq!(arch(&["--arch", "auto"]), ArchArg::Auto);
+    assert_eq!(
+        arch(&["--arch", "s390x"]),
+        ArchArg::Arch(Architecture::S390x)
+    );
+    assert_eq!(ArchArg::Auto.given(), None);
+}
+
+#[test]
+fn undetectable() {
+    assert!(detect_from_map("arch-unknown", 0x1000, &[]).is_err());
+    let arch = detect_from_map("arch-types-only", 0x1000, &["--types-only"]);
+    assert_eq!(arch.unwrap(), Architecture::X86_64);
+}
+
+#[test]
+fn banner_of_32_bit_kernels() {
+    let cases = [
+        (
+            "Linux version 6.1.0-686-pae (gcc-12 (Debian 12.2.0-14) i686-linux-gnu)",
+            Architecture::X86,
+        ),
+        (
+            "Linux version 5.10.0 (gcc version 10.2.1 (arm-linux-gnueabihf))",
+            Architecture::Arm,
+        ),
+        (
+            "Linux version 6.6.0 (armv7l-unknown-linux-gnueabihf-gcc)",
+            Architecture::Arm,
+        ),
+        (
+            "Linux version 6.6.0 (aarch64-linux-gnu-gcc)",
+            Architecture::Arm64,
+        ),
+    ];
+    for (banner, arch) in cases {
+        let cli = Cli::parse_from(["btf2json", "--types-only", "--banner", banner]);
+        assert_eq!(
+            arch::detect(cli.generate_args()).unwrap(),
+            arch,
+            "{}",
+            banner
+        );
+    }
+}
+
+#[test]
+fn banner_of_other_64_bit_kernels() {
+    let cases = [
+        (
+            "Linux version 6.8.0-31-generic (riscv64-linux-gnu-gcc-13 (Ubuntu 13.2.0-23ubuntu4) 13.2.0)",
+            Architecture::Riscv64,
+        ),
+        (
+            "Linux version 6.8.0-31-generic (powerpc64le-linux-gnu-gcc-13 (Ubuntu 13.2.0-23ubuntu4) 13.2.0)",
+            Architecture::Ppc64le,
+        ),
+        (
+            "Linux version 5.14.0-427.el9.ppc64le (gcc version 11.4.1)",
+            Architecture::Ppc64le,
+        ),
+        (
+            "Linux version 5.14.0-427.el9.s390x (gcc version 11.4.1)",
+            Architecture::S390x,
+        ),
+    ];
+    for (banner, arch) in cases {
+        let cli = Cli::parse_from(["btf2json", "--types-only", "--banner", banner]);
+        assert_eq!(
+            arch::detect(cli.generate_args()).unwrap(),
+            arch,
+            "{}",
+            banner
+        );
+    }
+}
+
+/// Returns an ELF64 header of `machine`, in big endian iff `big_endian`.
+fn elf_header(machine: u16, big_endian: bool) -> Vec<u8> {
+    let u16_bytes = |n: u16| {
+        if big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        }
+    };
+    let mut elf = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+    elf[5] = if big_endian { 2 } else { 1 }; // EI_DATA
+    elf.extend(u16_bytes(2)); // e_type: EXEC
+    elf.extend(u16_bytes(machine));
+    elf.resize(64, 0);
+    elf[52..54].copy_from_slice(&u16_bytes(64)); // e_ehsize
+    elf
+}
+
+#[test]
+fn elf_machine() {
+    // EM_PPC64 is only supported in little endian.
+    assert_eq!(
+        arch::from_elf_data(&elf_header(21, false)),
+        Some(Architecture::Ppc64le)
+    );
+    assert_eq!(arch::from_elf_data(&elf_header(21, true)), None);
+    assert_eq!(
+        arch::from_elf_data(&elf_header(22, true)),
+        Some(Architecture::S390x)
+    );
+    assert_eq!(
+        arch::from_elf_data(&elf_header(62, false)),
+        Some(Architecture::X86_64)
+    );
+}
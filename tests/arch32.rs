@@ -0,0 +1,90 @@
+//! Tests for profiles of 32-bit kernels.
+//!
+//! The types are from `tests/data/funcs/funcs.btf`. The symbols are from
+//! `tests/data/arch32/x86.map`, with `_stext` at `0xc1200000`, and
+//! `tests/data/arch32/arm.map`, with `_text` at `0xc0108000`.
+
+mod common;
+
+use std::fs;
+
+use serde_json::{json, Value};
+
+fn try_generate(arch: &str, args: &[&str]) -> btf2json::error::Result<Value> {
+    let btf = common::data("funcs/funcs.btf");
+    let map = common::data(&format!("arch32/{}.map", arch));
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.1.0",
+        "--arch",
+        arch,
+    ];
+    common::try_generate(&[&cli, args].concat())
+}
+
+fn generate(arch: &str, args: &[&str]) -> Value {
+    try_generate(arch, args).unwrap()
+}
+
+#[test]
+fn pointer_size() {
+    for arch in ["x86", "arm"] {
+        let isf = generate(arch, &[]);
+        assert_eq!(isf["base_types"]["pointer"]["size"], json!(4), "{}", arch);
+    }
+}
+
+#[test]
+fn x86_rebased() {
+    let isf = generate("x86", &[]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["_stext"]["address"], json!(0xc1000000u32));
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xc1000100u32));
+    // Per-CPU offsets are not rebased.
+    assert_eq!(symbols["runqueues"]["address"], json!(0x40));
+}
+
+#[test]
+fn arm_rebased_on_text() {
+    let isf = generate("arm", &[]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["_text"]["address"], json!(0xc0008000u32));
+    assert_eq!(symbols["_stext"]["address"], json!(0xc0100000u32));
+    assert_eq!(symbols["linux_banner"]["address"], json!(0xc1000100u32));
+}
+
+#[test]
+fn base_offset_override() {
+    let isf = generate("arm", &["--base-offset", "c0108000"]);
+
+    assert_eq!(isf["symbols"]["vfs_read"]["address"], json!(0xc0200100u32));
+}
+
+/// Symbols beyond 4 GiB are masked to 32 bits or fail the generation.
+#[test]
+fn address_overflow() {
+    let map = common::write_temp("arch32.map", "1c1200200 T too_high\n");
+    let args = ["--map", map.to_str().unwrap(), "--address-overflow"];
+
+    let isf = generate("x86", &[&args[..], &["mask"]].concat());
+    assert_eq!(isf["symbols"]["too_high"]["address"], json!(0xc1000200u32));
+    // Addresses that fit are kept.
+    assert_eq!(isf["symbols"]["vfs_read"]["address"], json!(0xc1000100u32));
+
+    let err = try_generate("x86", &[&args[..], &["error"]].concat()).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Address 0x1c1000200 of symbol too_high does not fit into 32 bits"),
+        "{}",
+        err
+    );
+    // The default is to fail.
+    assert!(try_generate("x86", &args[..2]).is_err());
+    fs::remove_file(&map).unwrap();
+}
@@ -0,0 +1,85 @@
+//! Tests for profiles of riscv64, ppc64le, and s390x kernels.
+//!
+//! The types are from `tests/data/funcs/funcs.btf`, or the big endian
+//! `tests/data/endian/tiny_be.btf`. The symbols are from
+//! `tests/data/arch64/<arch>.map`, with KASLR-shifted addresses:
+//!
+//! - `riscv64.map` has `_start` at `0xffffffff8a000000` and `_stext` one page
+//!   of head code later.
+//! - `ppc64le.map` has `_stext` at `0xc000000002000000`.
+//! - `s390x.map` has no `_stext`, but `startup_continue` at `0xa00000`.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(arch: &str, btf: &str, args: &[&str]) -> Value {
+    let btf = common::data(btf);
+    let map = common::data(&format!("arch64/{}.map", arch));
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.8.0",
+        "--arch",
+        arch,
+    ];
+    common::generate(&[&cli, args].concat())
+}
+
+#[test]
+fn riscv64_rebased_on_start() {
+    let isf = generate("riscv64", "funcs/funcs.btf", &[]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["_start"]["address"], json!(0xffffffff80000000u64));
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff80002000u64));
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xffffffff80002100u64));
+    assert_eq!(
+        symbols["linux_banner"]["address"],
+        json!(0xffffffff81000100u64)
+    );
+    assert_eq!(isf["base_types"]["pointer"]["size"], json!(8));
+}
+
+#[test]
+fn ppc64le_rebased() {
+    let isf = generate("ppc64le", "funcs/funcs.btf", &[]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["_stext"]["address"], json!(0xc000000000000000u64));
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xc000000000000100u64));
+    assert_eq!(
+        symbols["linux_banner"]["address"],
+        json!(0xc000000001000100u64)
+    );
+    assert_eq!(isf["base_types"]["pointer"]["endian"], json!("little"));
+}
+
+#[test]
+fn s390x_rebased_on_fallback() {
+    let isf = generate("s390x", "endian/tiny_be.btf", &[]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["startup_continue"]["address"], json!(0x100000));
+    assert_eq!(symbols["vfs_read"]["address"], json!(0x100100));
+    assert_eq!(symbols["linux_banner"]["address"], json!(0x1100100));
+    assert_eq!(isf["base_types"]["pointer"]["endian"], json!("big"));
+    assert_eq!(isf["base_types"]["pointer"]["size"], json!(8));
+}
+
+#[test]
+fn base_offset_override() {
+    let isf = generate(
+        "s390x",
+        "endian/tiny_be.btf",
+        &["--base-offset", "3ffe0000000"],
+    );
+
+    assert_eq!(
+        isf["symbols"]["vfs_read"]["address"],
+        json!(0x3ffe0000100u64)
+    );
+}
@@ -0,0 +1,79 @@
+//! Tests for flat arm64 kernel `Image` files, which are not ELF files.
+//!
+//! The images are built with the header of an arm64 `Image`, the BTF of
+//! `tests/data/funcs/funcs.btf` at a page boundary, see `tests/functions.rs`,
+//! and a banner after it.
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use flate2::write::GzEncoder;
+use serde_json::{json, Value};
+use std::io::Write;
+
+const BANNER: &[u8] =
+    b"Linux version 6.18.0-arm64 (builder@host) (clang 19.1.0) #1 SMP PREEMPT\n\0";
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+
+/// Returns an arm64 `Image` with the BTF and banner.
+fn image() -> Vec<u8> {
+    let mut image = vec![0; 64];
+    // code0: b primary_entry
+    image[0..4].copy_from_slice(&0x14000010u32.to_le_bytes());
+    image[0x38..0x3c].copy_from_slice(b"ARM\x64");
+    // Code, with something that looks like a BTF magic but is not one.
+    image.extend([0x9f, 0xeb, 0x01, 0x00, 0xff, 0xff, 0xff, 0xff]);
+    image.resize(0x1000, 0xd5);
+    image.extend(fs::read(format!("{}/funcs.btf", FUNCS)).unwrap());
+    image.resize(image.len().next_multiple_of(0x1000), 0);
+    image.extend(BANNER);
+    image.resize(image.len() + 0x100, 0);
+    image
+}
+
+fn generate(image: &[u8], name: &str) -> Value {
+    let path = common::write_temp(name, image);
+    let map = format!("{}/System.map", FUNCS);
+    let isf = common::try_generate(&["--btf", path.to_str().unwrap(), "--map", &map]);
+    fs::remove_file(&path).unwrap();
+    isf.unwrap()
+}
+
+fn assert_profile(isf: &Value) {
+    assert_eq!(
+        isf["user_types"]["file"]["fields"]["f_pos"]["type"],
+        json!({"kind": "base", "name": "long int"})
+    );
+    assert_eq!(isf["symbols"]["vfs_read"]["type"]["kind"], "function");
+    // The banner is found by its string.
+    assert_eq!(isf["metadata"]["linux"]["kernel_version"], "6.18.0-arm64");
+}
+
+#[test]
+fn image_file() {
+    assert_profile(&generate(&image(), "Image"));
+}
+
+#[test]
+fn image_gz() {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&image()).unwrap();
+    assert_profile(&generate(&encoder.finish().unwrap(), "Image.gz"));
+}
+
+#[test]
+fn no_btf() {
+    let mut image = image();
+    image.truncate(0x1000);
+    let path = common::write_temp("Image-nobtf", &image);
+    let err = common::context(&["--btf", path.to_str().unwrap(), "--types-only"])
+        .err()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(
+        format!("{:#}", anyhow::Error::from(err)).contains("No BTF section found"),
+        "unexpected error"
+    );
+}
@@ -0,0 +1,116 @@
+//! Tests for array members.
+//!
+//! `tests/data/arrays/arrays.btf` is a hand-crafted BTF section that defines:
+//!
+//! ```c
+//! typedef unsigned char row_t[16];
+//!
+//! struct arrays {
+//!     unsigned char buf[4][16];
+//!     int cube[5][2][3];
+//!     int *ptrs[2][3];
+//!     unsigned char (*prow)[16];
+//!     row_t rows[4];
+//! };
+//! ```
+//!
+//! `tests/data/arrays/flex.btf` defines structures with trailing arrays:
+//!
+//! ```c
+//! struct foo { int a; int b; };
+//! struct missing;
+//!
+//! struct flex { int len; struct foo arr[]; };          // size 8
+//! struct gnu { int len; char name[0]; };               // size 4
+//! struct anon_flex { int len; struct { int x; int y; } items[]; };
+//! struct fwd_flex { int len; struct missing items[]; };
+//! ```
+
+mod common;
+
+use btf2json::isf::Isf;
+use serde_json::{json, Value};
+
+fn generate_isf(name: &str) -> Isf {
+    let path = common::data("arrays").join(name);
+    common::generate_isf(&["--btf", path.to_str().unwrap(), "--types-only"])
+}
+
+fn generate(name: &str) -> Value {
+    serde_json::to_value(generate_isf(name)).unwrap()
+}
+
+fn array(count: u64, subtype: Value) -> Value {
+    json!({"kind": "array", "count": count, "subtype": subtype})
+}
+
+fn base(name: &str) -> Value {
+    json!({"kind": "base", "name": name})
+}
+
+#[test]
+fn multi_dimensional_arrays() {
+    let isf = generate("arrays.btf");
+    let fields = &isf["user_types"]["arrays"]["fields"];
+
+    assert_eq!(
+        fields["buf"]["type"],
+        array(4, array(16, base("unsigned char")))
+    );
+    assert_eq!(
+        fields["cube"]["type"],
+        array(5, array(2, array(3, base("int"))))
+    );
+    assert_eq!(
+        fields["ptrs"]["type"],
+        array(
+            2,
+            array(3, json!({"kind": "pointer", "subtype": base("int")}))
+        )
+    );
+    assert_eq!(
+        fields["prow"]["type"],
+        json!({"kind": "pointer", "subtype": array(16, base("unsigned char"))})
+    );
+    assert_eq!(
+        fields["rows"]["type"],
+        array(4, array(16, base("unsigned char")))
+    );
+    assert_eq!(fields["cube"]["offset"], json!(64));
+    assert_eq!(fields["ptrs"]["offset"], json!(184));
+}
+
+#[test]
+fn flexible_arrays() {
+    let isf = generate("flex.btf");
+    let user_types = &isf["user_types"];
+
+    assert_eq!(user_types["flex"]["size"], json!(8));
+    assert_eq!(
+        user_types["flex"]["fields"]["arr"],
+        json!({"type": array(0, json!({"kind": "struct", "name": "foo"})), "offset": 8, "anonymous": false})
+    );
+    assert_eq!(user_types["gnu"]["size"], json!(4));
+    assert_eq!(
+        user_types["gnu"]["fields"]["name"]["type"],
+        array(0, base("char"))
+    );
+
+    let items = &user_types["anon_flex"]["fields"]["items"]["type"];
+    assert_eq!(items["count"], json!(0));
+    let name = items["subtype"]["name"].as_str().unwrap();
+    assert!(name.starts_with("unnamed_struct_"), "{}", name);
+    assert_eq!(user_types[name]["size"], json!(8));
+}
+
+#[test]
+fn flexible_array_element_types_are_checked() {
+    // `struct fwd_flex` references an undefined type, which must be reported
+    // independent of the iteration order of the fields.
+    for _ in 0..4 {
+        let isf = generate_isf("flex.btf");
+        assert!(isf.check_user_types().is_err());
+    }
+    let isf = generate("flex.btf");
+    assert!(isf["user_types"]["missing"].is_null());
+}
@@ -0,0 +1,100 @@
+//! Tests for the normalization of the banner.
+//!
+//! `tests/data/constdata/vmlinux` has a `linux_banner` of
+//! `"Linux version 6.18.0-const\n\0"`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use base64::prelude::*;
+
+fn try_banner(args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let btf = Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/constdata/vmlinux"
+    ));
+    let isf = common::try_generate(&[&["--btf", btf.to_str().unwrap()], args].concat())?;
+    let data = isf["symbols"]["linux_banner"]["constant_data"]
+        .as_str()
+        .unwrap();
+    Ok(BASE64_STANDARD.decode(data)?)
+}
+
+fn banner(args: &[&str]) -> Vec<u8> {
+    try_banner(args).unwrap()
+}
+
+#[test]
+fn newline_is_added() {
+    assert_eq!(
+        banner(&["--banner", "Linux version 6.18.0"]),
+        b"Linux version 6.18.0\n"
+    );
+}
+
+#[test]
+fn banner_with_and_without_newline_match() {
+    assert_eq!(
+        banner(&["--banner", "Linux version 6.18.0"]),
+        banner(&["--banner", "Linux version 6.18.0\n"])
+    );
+    assert_eq!(
+        banner(&["--banner", "Linux version 6.18.0"]),
+        banner(&["--banner", "Linux version 6.18.0\n\n\0"])
+    );
+}
+
+#[test]
+fn elf_banner_is_normalized() {
+    assert_eq!(banner(&[]), b"Linux version 6.18.0-const\n");
+}
+
+#[test]
+fn raw_banner_is_kept() {
+    assert_eq!(
+        banner(&["--banner-raw", "--banner", "Linux version 6.18.0"]),
+        b"Linux version 6.18.0"
+    );
+    // Trailing NUL bytes are not part of the banner.
+    assert_eq!(banner(&["--banner-raw"]), b"Linux version 6.18.0-const\n");
+}
+
+#[test]
+fn banner_file() {
+    let contents = b"Linux version 6.18.0 (builder@host) (gcc 15.3.0) #1 SMP";
+    let path = common::write_temp("file", contents);
+    let path = path.to_str().unwrap();
+
+    // The file takes precedence over the banner in vmlinux, --banner over the
+    // file.
+    let mut expected = contents.to_vec();
+    expected.push(b'\n');
+    assert_eq!(banner(&["--banner-file", path]), expected);
+    assert_eq!(banner(&["--banner-file", path, "--banner-raw"]), contents);
+    assert_eq!(
+        banner(&["--banner-file", path, "--banner", "Linux version 6.18.0"]),
+        b"Linux version 6.18.0\n"
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn invalid_banner_file() {
+    let empty = common::write_temp("empty", b"");
+    let err = try_banner(&["--banner-file", empty.to_str().unwrap()]).unwrap_err();
+    fs::remove_file(&empty).unwrap();
+    assert!(err.to_string().ends_with("is empty"), "{}", err);
+
+    let large = common::write_temp("large", [b'x'; 2048]);
+    let err = try_banner(&["--banner-file", large.to_str().unwrap()]).unwrap_err();
+    fs::remove_file(&large).unwrap();
+    assert!(
+        err.to_string()
+            .ends_with("has 2048 bytes, more than the 1024 bytes of a Linux banner"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,109 @@
+//! Tests for listing the banners of memory images with `btf2json banners`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+const BANNER: &str = "Linux version 6.18.0-1-amd64 (debian-kernel@lists.debian.org) \
+                      (x86_64-linux-gnu-gcc-15 (Debian 15.2.0-4) 15.2.0, GNU ld (GNU Binutils \
+                      for Debian) 2.45) #1 SMP PREEMPT_DYNAMIC Debian 6.18.3-1 \
+                      (2026-01-10)\n";
+const KEXEC_BANNER: &str =
+    "Linux version 6.12.0 (builder@host) (gcc 14.2.0) #1 SMP Mon Mar  3 10:00:00 UTC 2025\n";
+const STALE_BANNER: &str = "Linux version 6.1.0 (builder@host) #1 SMP\n";
+
+fn banners(image: &Path, args: &[&str]) -> Output {
+    common::run(&[&["banners", "--image", image.to_str().unwrap()], args].concat())
+}
+
+/// Returns a raw image with `BANNER` twice, the banner of a kernel that was
+/// replaced with kexec, a stale banner without the build date, and a message
+/// that starts like a banner but does not end with a newline.
+fn raw_image() -> Vec<u8> {
+    let mut raw = Vec::new();
+    for (off, data) in [
+        (0x100, b"Linux version 6.18.0 is affected".as_slice()),
+        (0x200, BANNER.as_bytes()),
+        (0x1000, KEXEC_BANNER.as_bytes()),
+        (0x2000, STALE_BANNER.as_bytes()),
+        (0x3000, BANNER.as_bytes()),
+    ] {
+        raw.resize(off, 0);
+        raw.extend(data);
+        raw.push(0);
+    }
+    raw
+}
+
+#[test]
+fn all_banners_are_listed() {
+    let image = common::write_temp("raw", raw_image());
+    let output = banners(&image, &["--format", "json"]);
+    fs::remove_file(&image).unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let listed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        listed[0]["occurrences"],
+        json!([
+            {"offset": 0x200, "physical_address": 0x200},
+            {"offset": 0x3000, "physical_address": 0x3000},
+        ])
+    );
+    assert_eq!(listed[0]["banner"], json!(BANNER));
+    assert_eq!(
+        listed[0]["confidence"],
+        json!("high"),
+        "{}",
+        listed[0]["note"]
+    );
+    assert_eq!(listed[1]["banner"], json!(KEXEC_BANNER));
+    assert_eq!(listed[1]["confidence"], json!("high"));
+    assert_eq!(
+        listed[2],
+        json!({
+            "banner": STALE_BANNER,
+            "base64": "TGludXggdmVyc2lvbiA2LjEuMCAoYnVpbGRlckBob3N0KSAjMSBTTVAK",
+            "confidence": "low",
+            "note": "no compiler, no build date",
+            "occurrences": [{"offset": 0x2000, "physical_address": 0x2000}],
+        })
+    );
+    assert_eq!(listed.as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn text_format() {
+    let lime = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/lime/two_ranges.lime"
+    );
+    let output = banners(Path::new(lime), &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Linux version 6.18.0 (btf2json@test) #1 SMP\n  \
+         confidence: low (no compiler, no build date)\n  \
+         offsets: 0xa0 (physical 0x1080)\n  \
+         base64: TGludXggdmVyc2lvbiA2LjE4LjAgKGJ0ZjJqc29uQHRlc3QpICMxIFNNUAo=\n"
+    );
+}
+
+#[test]
+fn no_banner() {
+    let image = common::write_temp("empty", [0; 0x1000]);
+    let output = banners(&image, &[]);
+    fs::remove_file(&image).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No Linux banner in"), "{}", stderr);
+}
@@ -0,0 +1,41 @@
+//! Tests for the typedef aliases of base types.
+//!
+//! The BTF file `tests/data/base_aliases/base_aliases.btf` defines
+//!
+//! ```c
+//! typedef long long unsigned int __u64;
+//! typedef __u64 u64;
+//! typedef int __s32;
+//! typedef unsigned int __u32;
+//!
+//! enum pid_type { PIDTYPE_PID, PIDTYPE_TGID };   // 4 bytes
+//! enum hrtimer_base_type { HRTIMER_BASE_MONOTONIC };
+//!
+//! struct timer { u64 expires; __s32 flags; __u32 cpu; };
+//! ```
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate() -> Value {
+    let btf = common::data("base_aliases/base_aliases.btf");
+    common::generate(&["--btf", btf.to_str().unwrap(), "--types-only"])
+}
+
+#[test]
+fn typedef_aliases() {
+    let isf = generate();
+    let base_types = &isf["base_types"];
+    let u64_ = json!({"size": 8, "signed": false, "kind": "int", "endian": "little"});
+    assert_eq!(base_types["long long unsigned int"], u64_);
+    assert_eq!(base_types["u64"], u64_);
+    assert_eq!(base_types["__u64"], u64_);
+    assert_eq!(base_types["__s32"]["size"], json!(4));
+    assert_eq!(base_types["__s32"]["signed"], json!(true));
+
+    // Enums keep the C names of their base types.
+    for e in isf["enums"].as_object().unwrap().values() {
+        assert!(!e["base"].as_str().unwrap().starts_with("__"), "{}", e);
+    }
+}
@@ -0,0 +1,77 @@
+//! Tests for overriding the address that `_stext` is moved to.
+//!
+//! Uses `tests/data/vars`, whose System.map has `_stext` at
+//! `0xffffffff81000000` and `jiffies` at `0xffffffff82000000`.
+
+mod common;
+
+use btf2json::cli::Cli;
+use clap::Parser;
+use serde_json::{json, Value};
+
+fn args(base_offset: Option<&str>) -> Vec<String> {
+    let data = common::data("vars");
+    let mut args: Vec<String> = [
+        "btf2json",
+        "--btf",
+        data.join("vars.btf").to_str().unwrap(),
+        "--map",
+        data.join("System.map").to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ]
+    .map(String::from)
+    .into();
+    if let Some(base_offset) = base_offset {
+        args.extend(["--base-offset".into(), base_offset.into()]);
+    }
+    args
+}
+
+fn generate(base_offset: Option<&str>) -> Value {
+    let args = args(base_offset);
+    common::generate(&args[1..].iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+#[test]
+fn default_base_offset() {
+    let isf = generate(None);
+
+    assert_eq!(
+        isf["symbols"]["_stext"]["address"],
+        json!(0xffffffff81000000u64)
+    );
+    assert_eq!(
+        isf["symbols"]["jiffies"]["address"],
+        json!(0xffffffff82000000u64)
+    );
+}
+
+#[test]
+fn base_offset_overrides_default() {
+    for base_offset in [
+        "0xffffffff80000000",
+        "ffffffff80000000",
+        "0XFFFFFFFF80000000",
+    ] {
+        let isf = generate(Some(base_offset));
+
+        assert_eq!(
+            isf["symbols"]["_stext"]["address"],
+            json!(0xffffffff80000000u64)
+        );
+        assert_eq!(
+            isf["symbols"]["jiffies"]["address"],
+            json!(0xffffffff81000000u64)
+        );
+    }
+}
+
+#[test]
+fn invalid_base_offset_is_rejected() {
+    let err = Cli::try_parse_from(args(Some("0xkernel"))).unwrap_err();
+
+    assert!(err.to_string().contains("0xkernel"));
+}
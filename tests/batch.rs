@@ -0,0 +1,199 @@
+//! Tests for generating the ISF files of many kernels with `batch`.
+//!
+//! The kernels are arm64 `Image` files built like in `tests/arm64_image.rs`,
+//! which have a banner, and the BTF files of `tests/data/funcs` and
+//! `tests/data/vars` with banners from a manifest.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+const BANNER: &[u8] =
+    b"Linux version 6.18.0-arm64 (builder@host) (clang 19.1.0) #1 SMP PREEMPT\n\0";
+
+/// Returns an arm64 `Image` with the BTF of `tests/data/funcs` and a banner.
+fn image() -> Vec<u8> {
+    let mut image = vec![0; 64];
+    image[0..4].copy_from_slice(&0x14000010u32.to_le_bytes());
+    image[0x38..0x3c].copy_from_slice(b"ARM\x64");
+    image.resize(0x1000, 0xd5);
+    image.extend(fs::read(format!("{}/funcs/funcs.btf", DATA)).unwrap());
+    image.resize(image.len().next_multiple_of(0x1000), 0);
+    image.extend(BANNER);
+    image.resize(image.len() + 0x100, 0);
+    image
+}
+
+/// Returns a new, empty directory for the test `name`.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("btf2json-batch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn batch(args: &[&str], dir: &Path) -> Output {
+    let out = dir.join("out");
+    common::run(&[&["batch"], args, &["--output-dir", out.to_str().unwrap()]].concat())
+}
+
+fn summary(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn input_dir() {
+    let dir = temp_dir("input-dir");
+    let input = dir.join("boot");
+    fs::create_dir(&input).unwrap();
+    fs::write(input.join("vmlinuz-6.18.0-arm64"), image()).unwrap();
+    fs::copy(
+        format!("{}/funcs/System.map", DATA),
+        input.join("System.map-6.18.0-arm64"),
+    )
+    .unwrap();
+    fs::write(input.join("vmlinux-broken"), b"not a kernel").unwrap();
+    fs::write(input.join("System.map-orphan"), b"").unwrap();
+    fs::write(input.join("config-6.18.0-arm64"), b"").unwrap();
+
+    let output = batch(
+        &[
+            "--input-dir",
+            input.to_str().unwrap(),
+            "--jobs",
+            "2",
+            "--format",
+            "json",
+        ],
+        &dir,
+    );
+    // One failure fails the batch, but not the other kernels.
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let summary = summary(&output);
+    let reports = summary.as_array().unwrap();
+    assert_eq!(reports.len(), 3);
+
+    assert_eq!(reports[0]["kernel"], "vmlinuz-6.18.0-arm64");
+    assert_eq!(reports[0]["status"], "ok");
+    let symbols = reports[0]["symbols"].as_u64().unwrap();
+    let typed = reports[0]["typed_symbols"].as_u64().unwrap();
+    assert!(typed > 0 && typed <= symbols, "{} of {}", typed, symbols);
+    let isf = Path::new(reports[0]["output"].as_str().unwrap());
+    assert!(isf.starts_with(dir.join("out")));
+    assert!(isf.to_str().unwrap().ends_with(".json.xz"));
+    assert!(isf.exists());
+
+    assert_eq!(reports[1]["kernel"], "vmlinux-broken");
+    assert_eq!(reports[1]["status"], "failed");
+    assert!(reports[1]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("vmlinux-broken"));
+    assert_eq!(
+        reports[2],
+        json!({
+            "kernel": "System.map-orphan",
+            "status": "failed",
+            "reason": "No kernel for this System.map",
+        })
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn manifest() {
+    let dir = temp_dir("manifest");
+    let manifest = dir.join("kernels.json");
+    fs::write(
+        &manifest,
+        json!([
+            {
+                "btf": format!("{}/funcs/funcs.btf", DATA),
+                "map": format!("{}/funcs/System.map", DATA),
+                "banner": "Linux version 6.18.0-funcs",
+            },
+            {
+                "btf": format!("{}/vars/vars.btf", DATA),
+                "map": format!("{}/vars/System.map", DATA),
+                "banner": "Linux version 6.18.0-vars",
+            },
+        ])
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = batch(
+        &["--manifest", manifest.to_str().unwrap(), "--jobs", "2"],
+        &dir,
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let table = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert!(lines[0].starts_with("kernel"), "{}", table);
+    assert!(lines[1].starts_with("funcs.btf") && lines[1].contains(" ok "));
+    assert!(lines[2].starts_with("vars.btf") && lines[2].contains(" ok "));
+    assert!(table.ends_with("\n2 of 2 kernels succeeded\n"), "{}", table);
+    assert_eq!(fs::read_dir(dir.join("out")).unwrap().count(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn same_banner() {
+    let dir = temp_dir("same-banner");
+    let manifest = dir.join("kernels.json");
+    fs::copy(format!("{}/funcs/funcs.btf", DATA), dir.join("funcs.btf")).unwrap();
+    // Relative to the manifest.
+    let kernel = json!({"btf": "funcs.btf", "banner": "Linux version 6.18.0"});
+    fs::write(&manifest, json!([kernel, kernel]).to_string()).unwrap();
+
+    let output = batch(
+        &[
+            "--manifest",
+            manifest.to_str().unwrap(),
+            "--format",
+            "json",
+            "--types-only",
+        ],
+        &dir,
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let summary = summary(&output);
+    assert_eq!(summary[0]["status"], "ok");
+    assert_eq!(summary[1]["status"], "failed");
+    assert_eq!(summary[1]["reason"], "Same banner as funcs.btf");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_arguments() {
+    let dir = temp_dir("arguments");
+    let output = batch(
+        &["--input-dir", dir.to_str().unwrap(), "--btf", "vmlinux"],
+        &dir,
+    );
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("--btf cannot be given to batch"),
+        "{}",
+        stderr
+    );
+
+    let output = common::run(&["batch", "--input-dir", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+
+    // Nothing to generate.
+    let output = batch(&["--input-dir", dir.to_str().unwrap()], &dir);
+    assert_eq!(output.status.code(), Some(1));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,79 @@
+//! Tests for big endian BTF.
+//!
+//! `tests/data/endian/tiny_{le,be}.btf` are hand-crafted BTF sections with the
+//! same types in both byte orders: base types, an enum, an array, a pointer, a
+//! struct with bitfields (`kind_flag` set), and a typedef of a struct.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(name: &str) -> Value {
+    let path = common::data("endian").join(name);
+    let mut isf = common::generate(&["--btf", path.to_str().unwrap(), "--types-only"]);
+    isf.as_object_mut().unwrap().remove("metadata");
+    isf
+}
+
+#[test]
+fn big_endian_matches_little_endian() {
+    let mut le = generate("tiny_le.btf");
+    let mut be = generate("tiny_be.btf");
+
+    for (isf, endian) in [(&mut le, "little"), (&mut be, "big")] {
+        for base in isf["base_types"].as_object_mut().unwrap().values_mut() {
+            assert_eq!(base["endian"], json!(endian));
+            base.as_object_mut().unwrap().remove("endian");
+        }
+        // Bit positions depend on the byte order, see `bitfield_positions`.
+        for field in isf["user_types"]["bits"]["fields"]
+            .as_object_mut()
+            .unwrap()
+            .values_mut()
+        {
+            field["type"]["bit_position"] = Value::Null;
+        }
+    }
+    assert_eq!(le, be);
+
+    let task = &be["user_types"]["task"];
+    assert_eq!(task["size"], json!(40));
+    assert_eq!(task["fields"]["flags"]["offset"], json!(24));
+    assert_eq!(be["enums"]["mode"]["constants"]["BIG"], json!(0x12345678));
+    assert_eq!(
+        be["user_types"]["bits"]["fields"]["c"]["type"]["bit_length"],
+        json!(24)
+    );
+}
+
+/// Returns the offset, bit position, and bit length of the bitfields of `bits`.
+fn bitfields(isf: &Value) -> Vec<(u64, u64, u64)> {
+    isf["user_types"]["bits"]["fields"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|field| {
+            let t = &field["type"];
+            (
+                field["offset"].as_u64().unwrap(),
+                t["bit_position"].as_u64().unwrap(),
+                t["bit_length"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn bitfield_positions() {
+    // `a:3`, `b:5`, and `c:24` of `int`, at bit offsets 0, 3, and 8. Positions
+    // are relative to the least significant bit of the `int` read at the
+    // offset, which holds the last bits of a big endian bitfield.
+    assert_eq!(
+        bitfields(&generate("tiny_le.btf")),
+        [(0, 0, 3), (0, 3, 5), (1, 0, 24)]
+    );
+    assert_eq!(
+        bitfields(&generate("tiny_be.btf")),
+        [(0, 32 - 3, 3), (0, 32 - 3 - 5, 5), (1, 32 - 24, 24)]
+    );
+}
@@ -0,0 +1,139 @@
+//! Tests for bitfield members.
+//!
+//! `tests/data/bitfields/bitfields.btf` is a hand-crafted BTF section that
+//! defines the same struct twice:
+//!
+//! ```c
+//! struct bits {
+//!     unsigned int a:3;
+//!     unsigned int b:5;
+//!     int c;
+//!     unsigned short d:4;
+//!     unsigned int e:20;
+//!     unsigned int :4, f:2;
+//! };
+//! ```
+//!
+//! `struct bits_kf1` has the kind flag set, i.e., the member offsets hold the
+//! bitfield sizes. `struct bits_kf0` has no kind flag, its bitfields are
+//! members of narrow integer types instead. The type of `f` also encodes an
+//! offset of 4 bits, relative to the member offset 96.
+
+mod common;
+
+use std::fs;
+
+use btf2json::testutil::BtfBuilder;
+use serde_json::{json, Value};
+
+fn generate() -> Value {
+    let path = common::data("bitfields/bitfields.btf");
+    common::generate(&["--btf", path.to_str().unwrap(), "--types-only"])
+}
+
+fn bitfield(offset: u64, position: u64, length: u64, base: &str) -> Value {
+    json!({
+        "type": {
+            "kind": "bitfield",
+            "bit_position": position,
+            "bit_length": length,
+            "type": {"kind": "base", "name": base},
+        },
+        "offset": offset,
+        "anonymous": false,
+    })
+}
+
+#[test]
+fn bitfields_with_kind_flag() {
+    let isf = generate();
+    let fields = &isf["user_types"]["bits_kf1"]["fields"];
+
+    assert_eq!(fields["a"], bitfield(0, 0, 3, "unsigned int"));
+    assert_eq!(fields["b"], bitfield(0, 3, 5, "unsigned int"));
+    assert_eq!(
+        fields["c"],
+        json!({"type": {"kind": "base", "name": "int"}, "offset": 4, "anonymous": false})
+    );
+    assert_eq!(fields["d"], bitfield(8, 0, 4, "short unsigned int"));
+    assert_eq!(fields["e"], bitfield(8, 4, 20, "unsigned int"));
+    assert_eq!(fields["f"], bitfield(12, 4, 2, "unsigned int"));
+}
+
+#[test]
+fn bitfields_without_kind_flag() {
+    let isf = generate();
+    let user_types = &isf["user_types"];
+
+    assert_eq!(user_types["bits_kf0"], user_types["bits_kf1"]);
+    assert_eq!(isf["base_types"]["unsigned int"]["size"], json!(4));
+}
+
+/// ```c
+/// struct bogus {
+///     unsigned int a:33;
+///     unsigned int :3, b:31;
+///     unsigned char :6, c:12;
+/// };
+/// ```
+///
+/// All bitfields exceed their type, which starts at the byte where they
+/// begin.
+fn bogus_btf() -> BtfBuilder {
+    let mut btf = BtfBuilder::new();
+    btf.int("unsigned int", 4, false); // [1]
+    btf.int("unsigned char", 1, false); // [2]
+    btf.bitfields(
+        "bogus",
+        12,
+        &[("a", 1, 0, 33), ("b", 1, 35, 31), ("c", 2, 70, 12)],
+    ); // [3]
+    btf
+}
+
+#[test]
+fn bitfields_exceeding_their_type() {
+    let path = common::write_temp("bitfields", bogus_btf().bytes());
+    let isf = common::generate_isf(&["--btf", path.to_str().unwrap(), "--types-only"]);
+    fs::remove_file(&path).unwrap();
+
+    // Bitfields are clamped to the end of their type.
+    let value = serde_json::to_value(&isf).unwrap();
+    let fields = &value["user_types"]["bogus"]["fields"];
+    assert_eq!(fields["a"], bitfield(0, 0, 32, "unsigned int"));
+    assert_eq!(fields["b"], bitfield(4, 3, 29, "unsigned int"));
+    assert_eq!(fields["c"], bitfield(8, 6, 2, "unsigned char"));
+    assert!(isf.layout_problems().is_empty());
+    assert_eq!(
+        isf.check_layout().unwrap_err().to_string(),
+        "Layout verification failed: 3 bitfields exceeded their type and were fixed"
+    );
+}
+
+#[test]
+fn bitfields_exceeding_their_type_fail_strict() {
+    let path = common::write_temp("bitfields-strict", bogus_btf().bytes());
+    let output = common::run(&[
+        "generate",
+        "--types-only",
+        "--strict",
+        "--log-level",
+        "warn",
+        "--btf",
+        path.to_str().unwrap(),
+    ]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("bitfield `a` of `bogus` of 33 bits at bit 0 exceeds its 32 bit type, clamping it to 32 bits"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("3 bitfields exceeded their type and were fixed"),
+        "{}",
+        stderr
+    );
+}
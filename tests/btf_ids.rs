@@ -0,0 +1,82 @@
+//! Tests of the type IDs of `Btf`, which are tagged with the BTF object that
+//! they belong to.
+//!
+//! `tests/data/module/foo.ko` is described in `tests/module.rs`. Its BTF is
+//! split from `tests/data/funcs/funcs.btf`.
+
+use std::path::Path;
+
+use btf2json::btf::{Btf, IdOrigin, IdRange};
+use btf2json::cli::Cli;
+use btf2json::testutil::BtfBuilder;
+use clap::Parser;
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+
+fn two_ints() -> BtfBuilder {
+    let mut btf = BtfBuilder::new();
+    btf.int("int", 4, true);
+    btf.int("long", 8, true);
+    btf
+}
+
+#[test]
+fn ids_of_other_btf() {
+    let first = two_ints().build("first.btf").unwrap();
+    let second = two_ints().build("second.btf").unwrap();
+    let id = first.id(1).unwrap();
+    assert!(first.get_type_by_id(id).is_ok());
+    let err = second.get_type_by_id(id).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("belongs to a different BTF object"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn origin() {
+    let data = Path::new(DATA);
+    let (vmlinux, module) = (data.join("funcs/funcs.btf"), data.join("module/foo.ko"));
+    let nr_base_types = Btf::from_file(&vmlinux).unwrap().nr_types();
+    let cli = Cli::parse_from([
+        "btf2json",
+        "--btf",
+        vmlinux.to_str().unwrap(),
+        "--module",
+        module.to_str().unwrap(),
+        "--types-only",
+    ]);
+    let btf = Btf::try_from(cli.generate_args()).unwrap();
+    assert!(btf.nr_types() > nr_base_types);
+
+    assert_eq!(btf.id(0).unwrap().origin(), IdOrigin::Base);
+    assert_eq!(btf.id(nr_base_types - 1).unwrap().origin(), IdOrigin::Base);
+    assert_eq!(btf.id(nr_base_types).unwrap().origin(), IdOrigin::Split);
+    assert_eq!(
+        btf.id(btf.nr_types() - 1).unwrap().origin(),
+        IdOrigin::Split
+    );
+
+    // BTF that is not split has only base IDs.
+    let btf = two_ints().build("plain.btf").unwrap();
+    assert!(btf.types().all(|tx| tx.id.origin() == IdOrigin::Base));
+}
+
+#[test]
+fn id_range() {
+    let btf = two_ints().build("ranges.btf").unwrap();
+    let ids = |range: IdRange| range.map(u32::from).collect::<Vec<_>>();
+    assert_eq!(ids(btf.id_range(0..2)), [0, 1]);
+    assert_eq!(ids(btf.id_range(0..=2)), [0, 1, 2]);
+    assert_eq!(ids(btf.id_range(1..1)), Vec::<u32>::new());
+    assert_eq!(ids(btf.id_range(2..=2)), [2]);
+    assert_eq!(
+        btf.id_range(1..).take(3).map(u32::from).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    // The IDs are not limited to those of the types, and `..` ends with
+    // `u32::MAX`.
+    assert_eq!(ids(btf.id_range(u32::MAX - 1..)), [u32::MAX - 1, u32::MAX]);
+}
@@ -0,0 +1,123 @@
+//! Tests for the build information that is parsed from the banner.
+//!
+//! `tests/data/constdata/vmlinux` is described in `tests/banner.rs`.
+
+mod common;
+
+use std::path::Path;
+
+use btf2json::metadata::Build;
+use serde_json::{json, Value};
+
+fn build(kernel_version: Option<&str>, compiler: Option<&str>, build_date: Option<&str>) -> Build {
+    Build {
+        kernel_version: kernel_version.map(String::from),
+        compiler: compiler.map(String::from),
+        build_date: build_date.map(String::from),
+    }
+}
+
+#[test]
+fn gcc() {
+    assert_eq!(
+        Build::from_banner(
+            "Linux version 6.2.0-26-generic (buildd@lcy02-amd64-052) \
+             (x86_64-linux-gnu-gcc-11 (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0, GNU ld (GNU Binutils \
+             for Ubuntu) 2.38) #26~22.04.1-Ubuntu SMP PREEMPT_DYNAMIC Thu Jul 13 16:27:29 UTC 2023\n"
+        ),
+        build(
+            Some("6.2.0-26-generic"),
+            Some("x86_64-linux-gnu-gcc-11 (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0"),
+            Some("Thu Jul 13 16:27:29 UTC 2023"),
+        )
+    );
+    assert_eq!(
+        Build::from_banner(
+            "Linux version 4.15.0-20-generic (buildd@lgw01-amd64-039) (gcc version 7.3.0 \
+             (Ubuntu 7.3.0-16ubuntu3)) #21-Ubuntu SMP Tue Apr 24 06:16:15 UTC 2018"
+        ),
+        build(
+            Some("4.15.0-20-generic"),
+            Some("gcc version 7.3.0 (Ubuntu 7.3.0-16ubuntu3)"),
+            Some("Tue Apr 24 06:16:15 UTC 2018"),
+        )
+    );
+    assert_eq!(
+        Build::from_banner(
+            "Linux version 6.1.0-13-amd64 (debian-kernel@lists.debian.org) (gcc-12 (Debian \
+             12.2.0-14) 12.2.0, GNU ld (GNU Binutils for Debian) 2.40) #1 SMP PREEMPT_DYNAMIC \
+             Debian 6.1.55-1 (2023-09-29)"
+        ),
+        build(
+            Some("6.1.0-13-amd64"),
+            Some("gcc-12 (Debian 12.2.0-14) 12.2.0"),
+            Some("2023-09-29"),
+        )
+    );
+}
+
+#[test]
+fn clang() {
+    assert_eq!(
+        Build::from_banner(
+            "Linux version 6.6.0 (user@host) (clang version 17.0.6, LLD 17.0.6) #1 SMP \
+             PREEMPT_RT Mon Jan  1 00:00:00 CET 2024"
+        ),
+        build(
+            Some("6.6.0"),
+            Some("clang version 17.0.6"),
+            Some("Mon Jan  1 00:00:00 CET 2024"),
+        )
+    );
+}
+
+#[test]
+fn android() {
+    assert_eq!(
+        Build::from_banner(
+            "Linux version 6.1.25-android14-11-g34fde9ec08a3 (build-user@build-host) \
+             (Android (9796371, based on r487747) clang version 17.0.0 \
+             (https://android.googlesource.com/toolchain/llvm-project \
+             d9f89f4d16663d5012e5c09495f3b30ece3d2362), LLD 17.0.0) #1 SMP PREEMPT @0"
+        ),
+        build(
+            Some("6.1.25-android14-11-g34fde9ec08a3"),
+            Some(
+                "Android (9796371, based on r487747) clang version 17.0.0 \
+                 (https://android.googlesource.com/toolchain/llvm-project \
+                 d9f89f4d16663d5012e5c09495f3b30ece3d2362)"
+            ),
+            Some("@0"),
+        )
+    );
+}
+
+#[test]
+fn unparseable() {
+    assert_eq!(
+        Build::from_banner("Linux version 6.18.0"),
+        build(Some("6.18.0"), None, None)
+    );
+    assert_eq!(Build::from_banner("not a banner"), Build::default());
+}
+
+#[test]
+fn metadata() {
+    let btf = Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/constdata/vmlinux"
+    ));
+    let isf = |banner: &str| -> Value {
+        let isf = common::generate(&["--btf", btf.to_str().unwrap(), "--banner", banner]);
+        isf["metadata"]["linux"].clone()
+    };
+
+    let linux = isf("Linux version 6.18.0 (a@b) (gcc (GCC) 15.3.0, GNU ld 2.46) #1 SMP @0");
+    assert_eq!(linux["kernel_version"], json!("6.18.0"));
+    assert_eq!(linux["compiler"], json!("gcc (GCC) 15.3.0"));
+    assert_eq!(linux["build_date"], json!("@0"));
+
+    let linux = isf("Linux version 6.18.0");
+    assert!(linux.get("compiler").is_none());
+    assert!(linux.get("build_date").is_none());
+}
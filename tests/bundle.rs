@@ -0,0 +1,189 @@
+//! Tests for `--bundle`.
+//!
+//! The bundles are generated from `tests/data/vars`.
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::rc::Rc;
+
+use btf2json::bundle::{Artifact, Bundle, Verification};
+use btf2json::cli::Architecture;
+use btf2json::input::InputData;
+use btf2json::GenerationOptions;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use xz2::read::XzDecoder;
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars");
+
+fn run(args: &[&str]) -> Output {
+    let (btf, map) = (
+        Path::new(DATA).join("vars.btf"),
+        Path::new(DATA).join("System.map"),
+    );
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+/// Returns the path of the bundle of the test `name`, which does not exist.
+fn bundle_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("btf2json-bundle-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read_json(path: &Path) -> Value {
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+}
+
+/// Returns the names of the entries of `dir`, without the directory.
+fn entries(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|ent| ent.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn layout() {
+    let dir = bundle_dir("layout");
+    let output = run(&["--bundle", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stdout.is_empty());
+
+    assert_eq!(
+        entries(&dir),
+        [
+            "inputs",
+            "manifest.json",
+            "profile.json.xz",
+            "stats.json",
+            "warnings.json"
+        ]
+    );
+    assert_eq!(entries(&dir.join("inputs")), ["System.map", "vars.btf.BTF"]);
+    assert_eq!(
+        fs::read(dir.join("inputs/System.map")).unwrap(),
+        fs::read(Path::new(DATA).join("System.map")).unwrap()
+    );
+
+    // The profile is the ISF file that is generated without a bundle.
+    let isf: Value = serde_json::from_reader(XzDecoder::new(
+        File::open(dir.join("profile.json.xz")).unwrap(),
+    ))
+    .unwrap();
+    let expected: Value = serde_json::from_slice(&run(&[]).stdout).unwrap();
+    assert_eq!(isf["symbols"], expected["symbols"]);
+    assert_eq!(isf["user_types"], expected["user_types"]);
+
+    let stats = read_json(&dir.join("stats.json"));
+    assert_eq!(stats["symbols"], isf["symbols"].as_object().unwrap().len());
+    let warnings = read_json(&dir.join("warnings.json"));
+    assert_eq!(warnings["layout"], Value::Null);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn manifest() {
+    let dir = bundle_dir("manifest");
+    let output = run(&["--bundle", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let manifest = read_json(&dir.join("manifest.json"));
+    assert_eq!(manifest["producer"]["name"], "btf2json");
+    assert_eq!(manifest["producer"]["version"], env!("CARGO_PKG_VERSION"));
+    assert!(manifest["created"].is_string());
+    assert!(manifest["inputs"].is_object());
+
+    // All other files are listed with their hashes.
+    let outputs = manifest["outputs"].as_array().unwrap();
+    let mut paths: Vec<&str> = outputs
+        .iter()
+        .map(|output| output["path"].as_str().unwrap())
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "inputs/System.map",
+            "inputs/vars.btf.BTF",
+            "profile.json.xz",
+            "stats.json",
+            "warnings.json"
+        ]
+    );
+    for output in outputs {
+        let data = fs::read(dir.join(output["path"].as_str().unwrap())).unwrap();
+        assert_eq!(output["hash_type"], "sha256");
+        assert_eq!(
+            output["hash_value"],
+            format!("{:x}", Sha256::digest(&data)),
+            "{}",
+            output["path"]
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Reproducible bundles have no creation time.
+    let output = run(&["--bundle", dir.to_str().unwrap(), "--reproducible"]);
+    assert!(output.status.success(), "{:?}", output);
+    let manifest = read_json(&dir.join("manifest.json"));
+    assert!(manifest.get("created").is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn existing_dir() {
+    let dir = bundle_dir("existing");
+    fs::create_dir(&dir).unwrap();
+    let output = run(&["--bundle", dir.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("already exists"), "{}", stderr);
+    assert!(entries(&dir).is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Nothing is left behind if writing a bundle fails halfway.
+#[test]
+fn partial_failure() {
+    let isf = GenerationOptions::new()
+        .btf_file(Path::new(DATA).join("vars.btf"))
+        .arch(Architecture::X86_64)
+        .types_only(true)
+        .generate()
+        .unwrap();
+    let parent = bundle_dir("partial");
+    fs::create_dir(&parent).unwrap();
+    let dir = parent.join("bundle");
+
+    // The profile is written before the inputs, whose directory is missing.
+    let data = Rc::new(InputData::from(b"input".to_vec()));
+    let artifacts = [Artifact::new("missing/input", data, 0..5)];
+    let err = Bundle::new(&dir, true)
+        .write(&isf, &artifacts, &Verification::default())
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("missing"), "{:#}", err);
+    assert!(entries(&parent).is_empty(), "{:?}", entries(&parent));
+
+    Bundle::new(&dir, true)
+        .write(&isf, &[], &Verification::default())
+        .unwrap();
+    assert_eq!(entries(&parent), ["bundle"]);
+    fs::remove_dir_all(&parent).unwrap();
+}
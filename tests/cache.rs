@@ -0,0 +1,161 @@
+//! Tests for caching the analysis of BTF files with `--cache-dir`.
+//!
+//! The runs that compare the ISF files use the kernel in
+//! `tests/data/linux-6.18`, see `tests/golden.rs`, the others the small
+//! `tests/data/funcs`, see `tests/functions.rs`.
+
+mod common;
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use xz2::read::XzDecoder;
+
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+
+/// Returns a new, empty directory for the test `name`.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("btf2json-cache-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Returns the path of the only entry in the cache `dir`.
+fn entry(dir: &Path) -> PathBuf {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1, "{:?}", entries);
+    assert_eq!(entries[0].extension().unwrap(), "btfcache");
+    entries[0].clone()
+}
+
+/// Decompresses `name.xz` from `src` into `dst` and returns the path.
+fn unpack(src: &Path, dst: &Path, name: &str) -> PathBuf {
+    let mut data = Vec::new();
+    XzDecoder::new(File::open(src.join(format!("{}.xz", name))).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    let path = dst.join(name);
+    fs::write(&path, data).unwrap();
+    path
+}
+
+/// Returns the serialized ISF file generated from `btf` and `map`, with the
+/// cache in `cache_dir` if given.
+fn generate(btf: &Path, map: &Path, cache_dir: Option<&Path>) -> Vec<u8> {
+    let mut args: Vec<&OsStr> = vec![
+        "--btf".as_ref(),
+        btf.as_os_str(),
+        "--map".as_ref(),
+        map.as_os_str(),
+        "--banner".as_ref(),
+        "Linux version 6.18.0".as_ref(),
+    ];
+    if let Some(dir) = cache_dir {
+        args.extend(["--cache-dir".as_ref(), dir.as_os_str()]);
+    }
+    let args: Vec<_> = args.iter().map(|arg| arg.to_str().unwrap()).collect();
+    let mut out = Vec::new();
+    common::generate_isf(&args).dump_to(&mut out).unwrap();
+    out
+}
+
+/// Runs the binary with the cache in `dir` and returns the warnings about the
+/// cache.
+fn run(dir: &Path) -> String {
+    let output = common::run(&[
+        "generate",
+        "--log-level",
+        "warn",
+        "--banner",
+        "Linux version 6.18.0",
+        "--btf",
+        &format!("{}/funcs.btf", FUNCS),
+        "--map",
+        &format!("{}/System.map", FUNCS),
+        "--cache-dir",
+        dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .filter(|line| line.to_lowercase().contains("cache entry"))
+        .collect()
+}
+
+#[test]
+fn cached_runs_are_identical() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/linux-6.18");
+    let dst = temp_dir("identical");
+    let btf = unpack(&src, &dst, "vmlinux.btf");
+    let map = unpack(&src, &dst, "System.map");
+    let cache_dir = dst.join("cache");
+
+    let uncached = generate(&btf, &map, None);
+    assert!(!cache_dir.exists());
+    let cold = generate(&btf, &map, Some(&cache_dir));
+    let entry = entry(&cache_dir);
+    let warm = generate(&btf, &map, Some(&cache_dir));
+    fs::remove_dir_all(&dst).unwrap();
+
+    assert!(uncached == cold, "cold cache changes the ISF file");
+    assert!(uncached == warm, "warm cache changes the ISF file");
+    assert_eq!(entry.parent(), Some(cache_dir.as_path()));
+}
+
+#[test]
+fn corrupted_entry() {
+    let dir = temp_dir("corrupted");
+    assert_eq!(run(&dir), "");
+    let path = entry(&dir);
+    let valid = fs::read(&path).unwrap();
+    assert_eq!(&valid[..8], b"BTF2JSON");
+
+    // A flipped bit in the payload fails the checksum.
+    let mut data = valid.clone();
+    let len = data.len();
+    data[len - 40] ^= 1;
+    fs::write(&path, data).unwrap();
+    let stderr = run(&dir);
+    assert!(stderr.contains("is corrupted"), "{}", stderr);
+    assert!(stderr.contains("Checksum mismatch"), "{}", stderr);
+    // The rescan replaces the entry.
+    assert_eq!(fs::read(&path).unwrap(), valid);
+
+    // So does a truncated one.
+    fs::write(&path, &valid[..valid.len() / 2]).unwrap();
+    let stderr = run(&dir);
+    assert!(stderr.contains("is corrupted"), "{}", stderr);
+    assert_eq!(fs::read(&path).unwrap(), valid);
+    assert_eq!(run(&dir), "");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn entry_of_other_version() {
+    let dir = temp_dir("version");
+    run(&dir);
+    let path = entry(&dir);
+    let valid = fs::read(&path).unwrap();
+
+    // The tool version follows the magic, the format version, and its length.
+    let version = env!("CARGO_PKG_VERSION").as_bytes();
+    assert_eq!(&valid[16..16 + version.len()], version);
+    let mut data = valid.clone();
+    data[16..16 + version.len()].fill(b'9');
+    fs::write(&path, data).unwrap();
+
+    // Entries of other versions are replaced silently.
+    assert_eq!(run(&dir), "");
+    assert_eq!(fs::read(&path).unwrap(), valid);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
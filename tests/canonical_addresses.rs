@@ -0,0 +1,105 @@
+//! Tests for bringing symbol addresses into canonical form with
+//! `--canonicalize-addresses`.
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use btf2json::cli::Architecture;
+use btf2json::symbols::canonical_address;
+use serde_json::Value;
+
+/// System.map of a tool that drops the upper 32 bits, with a per-CPU variable.
+const TRUNCATED: &str = "\
+0000000081000000 T _stext
+0000000081000100 T vfs_read
+0000000000012340 D current_task
+0000000082000100 D linux_banner
+";
+
+fn generate(args: &[&str]) -> Value {
+    let map = common::write_temp(
+        &format!("canonical-addresses-{}.map", args.len()),
+        TRUNCATED,
+    );
+    let cli = [
+        "--btf",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars/vars.btf"),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+        "--no-rebase",
+    ];
+    let isf = common::generate(&[&cli, args].concat());
+    fs::remove_file(&map).unwrap();
+    isf
+}
+
+#[test]
+fn truncated_addresses() {
+    let x86_64 = Architecture::X86_64;
+    assert_eq!(canonical_address(x86_64, 0x81000000), 0xffffffff81000000);
+    assert_eq!(
+        canonical_address(x86_64, 0xffff81000000),
+        0xffffffff81000000
+    );
+    assert_eq!(
+        canonical_address(x86_64, 0x1ff888000000000),
+        0xffff888000000000
+    );
+    assert_eq!(
+        canonical_address(Architecture::Riscv64, 0x7fc0000000),
+        0xffffffffc0000000
+    );
+}
+
+#[test]
+fn tagged_addresses() {
+    let arm64 = Architecture::Arm64;
+    assert_eq!(
+        canonical_address(arm64, 0xf2ff800080010000),
+        0xffff800080010000
+    );
+    assert_eq!(
+        canonical_address(arm64, 0x00ff800080010000),
+        0xffff800080010000
+    );
+    assert_eq!(canonical_address(arm64, 0x0a00000000400000), 0x400000);
+}
+
+#[test]
+fn canonical_addresses() {
+    for (arch, addr) in [
+        (Architecture::X86_64, 0xffffffff81000000),
+        (Architecture::X86_64, 0xff11000000000000),
+        (Architecture::Arm64, 0xffff800080010000),
+        (Architecture::Riscv64, 0xffffffff80000000),
+        (Architecture::Ppc64le, 0xc000000000000000),
+        (Architecture::X86, 0xc1000000),
+    ] {
+        assert_eq!(canonical_address(arch, addr), addr, "{:?}", arch);
+    }
+    // Per-CPU offsets and absolute symbols are not kernel addresses.
+    assert_eq!(canonical_address(Architecture::X86_64, 0x12340), 0x12340);
+    assert_eq!(canonical_address(Architecture::Arm64, 0x12340), 0x12340);
+}
+
+#[test]
+fn canonicalized_map() {
+    let isf = generate(&["--canonicalize-addresses"]);
+    let address = |name: &str| isf["symbols"][name]["address"].as_u64().unwrap();
+    assert_eq!(address("_stext"), 0xffffffff81000000);
+    assert_eq!(address("vfs_read"), 0xffffffff81000100);
+    assert_eq!(address("current_task"), 0x12340);
+
+    // The addresses are kept by default.
+    let isf = generate(&[]);
+    assert_eq!(
+        isf["symbols"]["vfs_read"]["address"].as_u64(),
+        Some(0x81000100)
+    );
+}
@@ -0,0 +1,91 @@
+//! Tests of the signedness and kind of the character and boolean base types on
+//! BTF that is built with `btf2json::testutil`.
+
+use btf2json::cli::Architecture;
+use btf2json::testutil::{BtfBuilder, BTF_KIND_INT};
+use btf2json::version::KernelVersion;
+use btf2json::GenerationOptions;
+use serde_json::Value;
+
+/// Little endian architectures, s390x is covered by `char_signed`.
+const ARCHS: [Architecture; 6] = [
+    Architecture::X86_64,
+    Architecture::X86,
+    Architecture::Arm64,
+    Architecture::Arm,
+    Architecture::Riscv64,
+    Architecture::Ppc64le,
+];
+
+/// ```c
+/// char;           // signed, as on x86 before 6.2
+/// signed char;
+/// unsigned char;
+/// _Bool;          // with the signed flag
+/// wchar;          // 4 bytes with the char flag
+/// ```
+fn chars() -> BtfBuilder {
+    let mut btf = BtfBuilder::new();
+    btf.int("char", 1, true);
+    btf.int("signed char", 1, true);
+    btf.int("unsigned char", 1, false);
+    btf.add("_Bool", BTF_KIND_INT, false, 0, 1, &[(5 << 24) | 8]);
+    btf.add("wchar", BTF_KIND_INT, false, 0, 4, &[(2 << 24) | 32]);
+    btf
+}
+
+/// Generates the base types of `chars` for `arch` and the kernel `version`.
+fn base_types(arch: Architecture, version: Option<&str>) -> Value {
+    let mut options = GenerationOptions::new()
+        .btf_bytes("chars.btf", chars().bytes())
+        .arch(arch)
+        .types_only(true);
+    if let Some(version) = version {
+        options = options.banner(format!("Linux version {}", version));
+    }
+    let isf = options.generate().unwrap();
+    serde_json::from_str::<Value>(&isf.to_string()).unwrap()["base_types"].take()
+}
+
+#[test]
+fn char_signed() {
+    let old = Some(KernelVersion::new(6, 1, 0));
+    let new = Some(KernelVersion::new(6, 2, 0));
+    for arch in ARCHS.into_iter().chain([Architecture::S390x]) {
+        let x86 = matches!(arch, Architecture::X86_64 | Architecture::X86);
+        assert_eq!(arch.char_signed(old), Some(x86), "{:?}", arch);
+        assert_eq!(arch.char_signed(new), Some(false), "{:?}", arch);
+        assert_eq!(arch.char_signed(None), None, "{:?}", arch);
+    }
+}
+
+#[test]
+fn per_architecture() {
+    for arch in ARCHS {
+        let x86 = matches!(arch, Architecture::X86_64 | Architecture::X86);
+        // The BTF decides if the kernel version is unknown.
+        for (version, char_signed) in [(Some("6.1.0"), x86), (Some("6.18.0"), false), (None, true)]
+        {
+            let base = base_types(arch, version);
+            let context = format!("{:?} {:?}", arch, version);
+            assert_eq!(base["char"]["signed"], char_signed, "{}", context);
+            assert_eq!(base["char"]["kind"], "int", "{}", context);
+            assert_eq!(base["signed char"]["signed"], true, "{}", context);
+            assert_eq!(base["unsigned char"]["signed"], false, "{}", context);
+            assert_eq!(base["_Bool"]["signed"], false, "{}", context);
+            assert_eq!(base["_Bool"]["kind"], "bool", "{}", context);
+            assert_eq!(base["_Bool"]["size"], 1, "{}", context);
+        }
+    }
+}
+
+#[test]
+fn kinds() {
+    let base = base_types(Architecture::X86_64, Some("6.18.0"));
+    // Only single bytes are chars to Volatility.
+    assert_eq!(base["wchar"]["kind"], "int");
+    assert_eq!(base["wchar"]["size"], 4);
+    assert_eq!(base["void"]["kind"], "void");
+    assert_eq!(base["void"]["size"], 0);
+    assert_eq!(base["void"]["signed"], false);
+}
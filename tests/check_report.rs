@@ -0,0 +1,90 @@
+//! Tests for `--check` and `--check-report`.
+//!
+//! `tests/data/fwd/fwd.btf` is described in `tests/fwd_stubs.rs`.
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+const FWD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fwd/fwd.btf");
+
+fn run(args: &[&str]) -> Output {
+    common::run(&[&["--btf", FWD, "--types-only"], args].concat())
+}
+
+/// Runs the check and returns the report.
+fn report(name: &str, args: &[&str]) -> Value {
+    let path = env::temp_dir().join(format!(
+        "btf2json-check-report-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    let output = run(&[&["--check-report", path.to_str().unwrap()], args].concat());
+    assert!(output.status.success(), "{:?}", output);
+    let report = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    report
+}
+
+#[test]
+fn undefined_types() {
+    let opaque = json!({"kind": "struct", "name": "opaque"});
+    assert_eq!(
+        report("undefined", &[]),
+        json!({
+            "summary": {
+                "user_types": 1,
+                "undefined_types": 2,
+                "fields": 3,
+                "enums": 0,
+                "symbols": 0,
+                "descriptors": 0,
+            },
+            "user_types": [{
+                "kind": "struct",
+                "name": "user",
+                "fields": [
+                    {"name": "o", "undefined": opaque},
+                    {"name": "t", "undefined": opaque},
+                    {"name": "u", "undefined": {"kind": "union", "name": "uopaque"}},
+                ],
+            }],
+            "enums": [],
+            "symbols": [],
+            "descriptors": [],
+        })
+    );
+}
+
+#[test]
+fn no_problems() {
+    assert_eq!(
+        report("stubs", &["--emit-fwd-stubs"]),
+        json!({
+            "summary": {
+                "user_types": 0,
+                "undefined_types": 0,
+                "fields": 0,
+                "enums": 0,
+                "symbols": 0,
+                "descriptors": 0,
+            },
+            "user_types": [],
+            "enums": [],
+            "symbols": [],
+            "descriptors": [],
+        })
+    );
+}
+
+#[test]
+fn check_logs_problems() {
+    let output = run(&["--check"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("3 unique fields affected"), "{}", stderr);
+}
@@ -0,0 +1,63 @@
+//! Tests for name collisions between typedef aliases and other types.
+//!
+//! `tests/data/collisions/collisions.btf` is a hand-crafted BTF section that
+//! defines:
+//!
+//! ```c
+//! struct foo { int a; int b; };
+//! struct bar { int x; int y; int z; };
+//! typedef struct bar foo;               // Same category as `struct foo`.
+//! typedef struct { int v; } word;
+//! typedef unsigned int word;            // User type and base type.
+//! enum color { RED };
+//! typedef struct { int q; } color;      // Real enum and user type.
+//! typedef struct { int q; } pair_t;     // Same type as `color`.
+//! typedef enum { S_A } state;
+//! typedef int state;                    // Enum and base type.
+//! struct holder { word w; color c; state s; };
+//! ```
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(args: &[&str]) -> Value {
+    let path = common::data("collisions/collisions.btf");
+    common::generate(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())
+}
+
+#[test]
+fn real_name_beats_alias() {
+    let isf = generate(&[]);
+    let fields = isf["user_types"]["foo"]["fields"].as_object().unwrap();
+    assert_eq!(fields.keys().collect::<Vec<_>>(), ["a", "b"]);
+}
+
+#[test]
+fn across_categories() {
+    let isf = generate(&[]);
+    for (category, name) in [
+        ("user_types", "word"),
+        ("base_types", "word"),
+        ("user_types", "color"),
+        ("enums", "color"),
+        ("enums", "state"),
+        ("base_types", "state"),
+    ] {
+        assert!(isf[category].get(name).is_some(), "{} {}", category, name);
+    }
+
+    let isf = generate(&["--drop-colliding-aliases"]);
+    assert!(isf["base_types"].get("word").is_none());
+    assert!(isf["user_types"].get("color").is_none());
+    assert!(isf["base_types"].get("state").is_none());
+    assert!(isf["user_types"].get("word").is_some());
+    assert!(isf["enums"].get("color").is_some());
+    assert!(isf["enums"].get("state").is_some());
+
+    // References to dropped aliases use another name of the same type.
+    assert_eq!(
+        isf["user_types"]["holder"]["fields"]["c"]["type"],
+        json!({"kind": "struct", "name": "pair_t"})
+    );
+}
@@ -0,0 +1,65 @@
+//! Helpers shared by the integration tests.
+//!
+//! Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use btf2json::cli::Cli;
+use btf2json::error;
+use btf2json::isf::Isf;
+use btf2json::GenerationContext;
+use clap::Parser;
+use serde_json::Value;
+
+/// Returns the path of `path` in `tests/data`.
+pub fn data(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(path)
+}
+
+/// Returns the generation context of `btf2json generate` with `args`.
+pub fn context(args: &[&str]) -> error::Result<GenerationContext> {
+    GenerationContext::try_from(&Cli::parse_from(["btf2json"].iter().chain(args)))
+}
+
+/// Returns the ISF file that `btf2json generate` generates with `args`, or the
+/// error that the generation fails with.
+pub fn try_generate_isf(args: &[&str]) -> error::Result<Isf> {
+    Isf::try_from(context(args)?)
+}
+
+/// Returns the ISF file that `btf2json generate` generates with `args`.
+pub fn generate_isf(args: &[&str]) -> Isf {
+    try_generate_isf(args).unwrap()
+}
+
+/// Same as [`try_generate_isf`], as JSON.
+pub fn try_generate(args: &[&str]) -> error::Result<Value> {
+    Ok(serde_json::to_value(try_generate_isf(args)?).unwrap())
+}
+
+/// Same as [`generate_isf`], as JSON.
+pub fn generate(args: &[&str]) -> Value {
+    try_generate(args).unwrap()
+}
+
+/// Writes `contents` to a temporary file named after the process and `name`,
+/// which keeps the extension of `name`, and returns its path.
+pub fn write_temp(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let path = env::temp_dir().join(format!("btf2json-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Runs the `btf2json` binary with `args`.
+pub fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_btf2json"))
+        .args(args)
+        .output()
+        .unwrap()
+}
@@ -0,0 +1,62 @@
+//! Tests for `--compat dwarf2json`.
+//!
+//! `tests/data/compat/compat.btf` is a hand-crafted BTF section that defines:
+//!
+//! ```c
+//! struct inode {
+//!     int i_mode;
+//!     union { int i_nlink; unsigned int __i_nlink; };
+//!     struct { int a; int b; } i_pair;
+//!     enum { I_NEW = 1, I_DIRTY = 2 } i_state;
+//! };
+//! ```
+//!
+//! `tests/data/compat/dwarf2json.json` holds the same types in the format of
+//! dwarf2json.
+
+mod common;
+
+use std::fs::File;
+use std::path::Path;
+
+use serde_json::Value;
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/compat");
+
+fn generate(args: &[&str]) -> Value {
+    let path = Path::new(DATA).join("compat.btf");
+    common::generate(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())
+}
+
+/// Removes the `anonymous` flags of named fields, which dwarf2json omits.
+fn drop_named_flags(user_types: &mut Value) {
+    for ut in user_types.as_object_mut().unwrap().values_mut() {
+        for field in ut["fields"].as_object_mut().unwrap().values_mut() {
+            let field = field.as_object_mut().unwrap();
+            if field["anonymous"] == Value::Bool(false) {
+                field.remove("anonymous");
+            }
+        }
+    }
+}
+
+#[test]
+fn matches_dwarf2json() {
+    let expected: Value =
+        serde_json::from_reader(File::open(Path::new(DATA).join("dwarf2json.json")).unwrap())
+            .unwrap();
+    let mut isf = generate(&["--compat", "dwarf2json"]);
+    drop_named_flags(&mut isf["user_types"]);
+
+    assert_eq!(isf["user_types"], expected["user_types"]);
+    assert_eq!(isf["enums"], expected["enums"]);
+}
+
+#[test]
+fn default_names() {
+    let isf = generate(&[]);
+    let inode = &isf["user_types"]["inode"]["fields"];
+    assert_eq!(inode["unnamed_member_1"]["type"]["name"], "unnamed_union_3");
+    assert_eq!(inode["i_pair"]["type"]["name"], "unnamed_struct_4");
+    assert_eq!(inode["i_state"]["type"]["name"], "unnamed_enum_5");
+}
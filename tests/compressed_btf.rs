@@ -0,0 +1,109 @@
+//! Tests for `.BTF` sections that are compressed as a whole, like `.BTF.gz`.
+//!
+//! The section is `tests/data/funcs/funcs.btf`, see `tests/functions.rs`.
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use flate2::write::GzEncoder;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+
+fn btf() -> Vec<u8> {
+    fs::read(format!("{}/funcs.btf", FUNCS)).unwrap()
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+#[cfg(feature = "xz")]
+fn xz(data: &[u8]) -> Vec<u8> {
+    let mut enc = xz2::write::XzEncoder::new(Vec::new(), 6);
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+fn zstd(data: &[u8]) -> Vec<u8> {
+    ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest)
+}
+
+/// Generates the profile of the BTF file `data` that is called `name`.
+fn generate(name: &str, data: &[u8]) -> anyhow::Result<Value> {
+    let path = common::write_temp(name, data);
+    let map = format!("{}/System.map", FUNCS);
+    let isf = common::try_generate(&[
+        "--btf",
+        path.to_str().unwrap(),
+        "--map",
+        &map,
+        "--banner",
+        "Linux version 6.18.0",
+    ]);
+    fs::remove_file(&path).unwrap();
+    Ok(isf?)
+}
+
+/// Checks the profile of the compressed section `data` called `name`.
+fn assert_profile(name: &str, data: &[u8]) {
+    let isf = generate(name, data).unwrap();
+    assert_eq!(
+        isf["user_types"]["file"]["fields"]["f_pos"]["type"],
+        json!({"kind": "base", "name": "long int"}),
+        "{}",
+        name
+    );
+    // The name is the one of the file, the hash the one of the uncompressed
+    // section.
+    let types = &isf["metadata"]["linux"]["types"][0];
+    assert!(
+        types["name"].as_str().unwrap().ends_with(name),
+        "{}",
+        types["name"]
+    );
+    assert_eq!(
+        types["hash_value"],
+        json!(format!("{:x}", Sha256::digest(btf()))),
+        "{}",
+        name
+    );
+}
+
+#[test]
+fn gzip_btf() {
+    assert_profile("funcs.BTF.gz", &gzip(&btf()));
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn xz_btf() {
+    assert_profile("funcs.BTF.xz", &xz(&btf()));
+}
+
+#[test]
+fn zstd_btf() {
+    assert_profile("funcs.BTF.zst", &zstd(&btf()));
+}
+
+#[test]
+fn corrupt_stream() {
+    let mut data = gzip(&btf());
+    let len = data.len();
+    data[len / 2..].fill(0xaa);
+    let err = format!("{:#}", generate("corrupt.BTF.gz", &data).unwrap_err());
+    assert!(err.contains("failed to decompress --btf input"), "{}", err);
+
+    let data = zstd(&btf());
+    let err = format!(
+        "{:#}",
+        generate("truncated.BTF.zst", &data[..data.len() / 2]).unwrap_err()
+    );
+    assert!(err.contains("failed to decompress --btf input"), "{}", err);
+}
@@ -0,0 +1,132 @@
+//! Tests for the cross-checks of the banner, vmlinux, and System.map.
+//!
+//! Uses `tests/data/constdata/vmlinux`, see `tests/constant_data.rs`. Its
+//! banner is `Linux version 6.18.0-const`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use btf2json::consistency::Mismatch;
+
+const VMLINUX: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/constdata/vmlinux");
+
+/// Writes a System.map with `_stext`, `init_uts_ns`, and `linux_banner` at the
+/// given addresses.
+fn write_map(name: &str, stext: u64, init_uts_ns: u64, linux_banner: u64) -> PathBuf {
+    common::write_temp(
+        &format!("{}.map", name),
+        format!(
+            "{:016x} T _stext\n{:016x} D init_uts_ns\n{:016x} R linux_banner\n",
+            stext, init_uts_ns, linux_banner
+        ),
+    )
+}
+
+fn check(args: &[&str]) -> Vec<Mismatch> {
+    common::context(&[&["--btf", VMLINUX], args].concat())
+        .unwrap()
+        .check_consistency()
+}
+
+#[test]
+fn consistent() {
+    assert_eq!(check(&[]), []);
+    assert_eq!(
+        check(&[
+            "--banner",
+            "Linux version 6.18.0-const (builder@host) #1 SMP"
+        ]),
+        []
+    );
+
+    // KASLR shifts all symbols uniformly.
+    let map = write_map(
+        "consistent",
+        0xffffffff8a000000,
+        0xffffffff8b000010,
+        0xffffffff8b800100,
+    );
+    let mismatches = check(&["--map", map.to_str().unwrap()]);
+    fs::remove_file(&map).unwrap();
+    assert_eq!(mismatches, []);
+}
+
+#[test]
+fn release() {
+    let mismatches = check(&["--banner", "Linux version 6.5.0 (builder@host)"]);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(
+        mismatches[0].to_string(),
+        "Kernel release differs: banner has 6.5.0, vmlinux linux_banner has 6.18.0-const"
+    );
+}
+
+#[test]
+fn landmarks() {
+    let map = write_map(
+        "landmarks",
+        0xffffffff81000000,
+        0xffffffff82000010,
+        0xffffffff82900100,
+    );
+    let mismatches = check(&["--map", map.to_str().unwrap()]);
+    fs::remove_file(&map).unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(
+        mismatches[0].to_string(),
+        format!(
+            "Address of linux_banner differs: {} has 0xffffffff82900100, vmlinux has 0xffffffff82800100",
+            map.file_name().unwrap().to_str().unwrap()
+        )
+    );
+
+    // With KASLR, the shift of the first landmark is reported.
+    let map = write_map(
+        "landmarks-kaslr",
+        0xffffffff8a000000,
+        0xffffffff8b000010,
+        0xffffffff8b900100,
+    );
+    let mismatches = check(&["--map", map.to_str().unwrap()]);
+    fs::remove_file(&map).unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert!(mismatches[0]
+        .what
+        .contains("shifted by 0x9000000 like _stext"));
+    assert_eq!(
+        mismatches[0].values,
+        ["0xffffffff8b900100", "0xffffffff82800100"]
+    );
+}
+
+#[test]
+fn command_line() {
+    let run = |args: &[&str]| {
+        let cli = [
+            "generate",
+            "--btf",
+            VMLINUX,
+            "--banner",
+            "Linux version 6.5.0",
+        ];
+        let output = common::run(&[&cli, args].concat());
+        (
+            output.status.code().unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    let (code, stderr) = run(&[]);
+    assert_eq!(code, 0);
+    assert!(stderr.contains("warning: inconsistent inputs: Kernel release differs"));
+
+    let (code, stderr) = run(&["--strict"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("Inconsistent inputs: Kernel release differs"));
+
+    let (_, stderr) = run(&["--strict", "--no-consistency-check"]);
+    assert!(!stderr.contains("nconsistent inputs"), "{}", stderr);
+}
@@ -0,0 +1,65 @@
+//! Tests for constant data read from the sections of vmlinux.
+//!
+//! `tests/data/constdata/vmlinux` is a hand-crafted vmlinux with the symbols
+//!
+//! ```text
+//! ffffffff81000000 T _stext
+//! ffffffff83000008 B counter             // in .bss
+//! ffffffff82000010 D init_uts_ns         // "Linux\0\0\0localhost\0..."
+//! ffffffff82800100 R linux_banner
+//! ffffffff82800000 R linux_proc_banner   // "%s version %s (%s@%s)\n\0"
+//! ffffffff820000f8 D oob                 // exceeds its section
+//! ```
+
+mod common;
+
+use base64::prelude::*;
+use serde_json::Value;
+
+fn generate(constant_data: &[&str]) -> Value {
+    let btf = common::data("constdata/vmlinux");
+    common::generate(&[&["--btf", btf.to_str().unwrap()], constant_data].concat())
+}
+
+fn constant_data(isf: &Value, name: &str) -> Option<Vec<u8>> {
+    let data = isf["symbols"][name]["constant_data"].as_str()?;
+    Some(BASE64_STANDARD.decode(data).unwrap())
+}
+
+#[test]
+fn no_constant_data_by_default() {
+    let isf = generate(&[]);
+
+    assert_eq!(constant_data(&isf, "init_uts_ns"), None);
+    assert_eq!(constant_data(&isf, "linux_proc_banner"), None);
+}
+
+#[test]
+fn default_constant_data() {
+    let isf = generate(&["--constant-data"]);
+
+    assert_eq!(
+        constant_data(&isf, "init_uts_ns").unwrap(),
+        b"Linux\0\0\0localhost\0\0\0\0\0\0\0"
+    );
+    assert_eq!(
+        constant_data(&isf, "linux_proc_banner").unwrap(),
+        b"%s version %s (%s@%s)\n\0"
+    );
+}
+
+#[test]
+fn selected_constant_data() {
+    let isf = generate(&["--constant-data", "init_uts_ns,counter,oob,nosuch"]);
+
+    assert!(constant_data(&isf, "init_uts_ns").is_some());
+    assert_eq!(constant_data(&isf, "linux_proc_banner"), None);
+    // Symbols in .bss and outside of their section are skipped.
+    assert_eq!(constant_data(&isf, "counter"), None);
+    assert_eq!(constant_data(&isf, "oob"), None);
+    assert!(isf["symbols"]["nosuch"].is_null());
+    // The banner is unaffected.
+    assert!(constant_data(&isf, "linux_banner")
+        .unwrap()
+        .starts_with(b"Linux version 6.18.0-const"));
+}
@@ -0,0 +1,43 @@
+//! Tests for types that cannot be constructed.
+//!
+//! `tests/data/weird/weird.btf` is a hand-crafted BTF section that defines:
+//!
+//! ```c
+//! struct good { int a; };
+//! struct bad { int a; <.data> b; };  // member of a DATASEC type
+//! struct user { struct good g; struct bad p; };
+//! ```
+
+mod common;
+
+use btf2json::isf::Isf;
+
+const WEIRD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/weird/weird.btf");
+
+fn generate() -> Isf {
+    common::generate_isf(&["--btf", WEIRD, "--types-only"])
+}
+
+#[test]
+fn broken_types_are_skipped() {
+    let isf = generate();
+    assert_eq!(isf.skipped_types(), 1);
+
+    let value = serde_json::to_value(&isf).unwrap();
+    let user_types = value["user_types"].as_object().unwrap();
+    assert!(!user_types.contains_key("bad"));
+    assert_eq!(user_types["good"]["size"], 4);
+    assert!(user_types["user"]["fields"]["g"].is_object());
+}
+
+#[test]
+fn strict() {
+    let exit_code = |args: &[&str]| {
+        common::run(&[&["--btf", WEIRD, "--types-only"], args].concat())
+            .status
+            .code()
+            .unwrap()
+    };
+    assert_eq!(exit_code(&[]), 0);
+    assert_eq!(exit_code(&["--strict"]), 2);
+}
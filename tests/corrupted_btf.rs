@@ -0,0 +1,216 @@
+//! Tests that corrupted BTF, e.g., carved from a memory image, is survived
+//! instead of hanging the generation or failing it.
+//!
+//! The BTF is built with `btf2json::testutil`.
+
+mod common;
+
+use std::fs;
+
+use btf2json::testutil::{BtfBuilder, BTF_KIND_STRUCT};
+use serde_json::{json, Value};
+
+/// Generates the types of `btf`.
+fn generate(name: &str, btf: &BtfBuilder) -> Value {
+    let path = common::write_temp(name, btf.bytes());
+    let isf = common::try_generate(&["--btf", path.to_str().unwrap(), "--types-only"]);
+    fs::remove_file(&path).unwrap();
+    isf.unwrap()
+}
+
+/// ```c
+/// typedef b a;  // [2]
+/// typedef a b;  // [3]
+/// typedef c *p; // [5] -> [6]
+/// typedef p c;  // [6]
+///
+/// struct s {
+///     a x;
+///     c y;
+/// };
+/// ```
+#[test]
+fn typedef_cycles() {
+    let mut btf = BtfBuilder::new();
+    btf.int("int", 4, true); // [1]
+    btf.typedef_("a", 3); // [2]
+    btf.typedef_("b", 2); // [3]
+    btf.struct_("s", 16, &[("x", 2, 0), ("y", 6, 64)]); // [4]
+    btf.ptr(6); // [5]
+    btf.typedef_("c", 5); // [6]
+    let isf = generate("cycle", &btf);
+
+    let fields = &isf["user_types"]["s"]["fields"];
+    assert_eq!(fields["x"]["type"], json!({"kind": "base", "name": "void"}));
+    assert_eq!(
+        fields["y"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "base", "name": "void"}})
+    );
+    assert_eq!(fields["y"]["offset"], json!(8));
+}
+
+/// `struct s { int x; <type 42> y; long z; }`, with only 3 types.
+#[test]
+fn member_type_out_of_range() {
+    let mut btf = BtfBuilder::new();
+    btf.int("int", 4, true); // [1]
+    btf.int("long", 8, true); // [2]
+    btf.struct_("s", 16, &[("x", 1, 0), ("y", 42, 32), ("z", 2, 64)]); // [3]
+    let isf = generate("member", &btf);
+
+    let s = &isf["user_types"]["s"];
+    assert_eq!(s["size"], json!(16));
+    assert_eq!(
+        s["fields"],
+        json!({
+            "x": {"type": {"kind": "base", "name": "int"}, "offset": 0, "anonymous": false},
+            "z": {"type": {"kind": "base", "name": "long"}, "offset": 8, "anonymous": false},
+        })
+    );
+}
+
+/// `struct s { int x; }` of 4 bytes, with `x` at offset 8.
+#[test]
+fn layout_fails_strict() {
+    let mut btf = BtfBuilder::new();
+    btf.int("int", 4, true); // [1]
+    btf.struct_("s", 4, &[("x", 1, 64)]); // [2]
+    let path = common::write_temp("layout", btf.bytes());
+    let output = common::run(&[
+        "generate",
+        "--types-only",
+        "--strict",
+        "--btf",
+        path.to_str().unwrap(),
+    ]);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("[struct s::x] offset 8 exceeds size 4"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Layout verification failed"), "{}", stderr);
+}
+
+/// ```c
+/// struct before { int x; };   // [2]
+/// struct ? { int y; };        // [3], whose name offset is no string
+/// struct after { int x; };    // [4]
+/// typedef struct after a_t;   // [5]
+/// ```
+fn invalid_name() -> BtfBuilder {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    btf.struct_("before", 4, &[("x", int, 0)]);
+    // In the middle of "before", after "\0int\0x\0".
+    let y = btf.add_with_name_off(9, BTF_KIND_STRUCT, false, 0, 4, &[]);
+    assert_eq!(y, 3);
+    let after = btf.struct_("after", 4, &[("x", int, 0)]);
+    btf.typedef_("a_t", after);
+    btf
+}
+
+#[test]
+fn unparsable_record() {
+    let btf = invalid_name();
+    assert_eq!(btf.build("record").unwrap().skipped_records(), 1);
+    let isf = generate("record", &btf);
+    let mut user_types: Vec<&String> = isf["user_types"].as_object().unwrap().keys().collect();
+    user_types.sort();
+    assert_eq!(user_types, ["a_t", "after", "before"]);
+    assert_eq!(isf["base_types"]["int"]["size"], json!(4));
+}
+
+#[test]
+fn unparsable_record_fails_strict() {
+    let path = common::write_temp("record", invalid_name().bytes());
+    let run = |strict: bool| {
+        let args = ["generate", "--types-only", "--btf", path.to_str().unwrap()];
+        common::run(&[&args[..], if strict { &["--strict"] } else { &[] }].concat())
+    };
+    let (output, strict) = (run(false), run(true));
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1  skipped BTF records"), "{}", stderr);
+    assert_eq!(strict.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&strict.stderr);
+    assert!(
+        stderr.contains("1 BTF types could not be parsed"),
+        "{}",
+        stderr
+    );
+}
+
+/// ```c
+/// struct before { int x; };   // [2]
+/// <kind 25> ?;                // [3], with two 12 byte entries
+/// struct after { int x; };    // [4]
+/// <kind 25> ?;                // [5], with one 12 byte entry
+/// typedef struct after a_t;   // [6]
+/// ```
+fn unknown_kind() -> BtfBuilder {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    btf.struct_("before", 4, &[("x", int, 0)]);
+    assert_eq!(btf.add("new", 25, false, 2, 4, &[1, 2, 3, 4, 5, 6]), 3);
+    let after = btf.struct_("after", 4, &[("x", int, 0)]);
+    btf.add("", 25, false, 1, 4, &[7, 8, 9]);
+    btf.typedef_("a_t", after);
+    btf
+}
+
+#[test]
+fn unknown_kind_record() {
+    let btf = unknown_kind();
+    let built = btf.build("kind").unwrap();
+    assert_eq!(built.nr_types(), 7);
+    assert_eq!(built.skipped_records(), 2);
+    let isf = generate("kind", &btf);
+    let mut user_types: Vec<&String> = isf["user_types"].as_object().unwrap().keys().collect();
+    user_types.sort();
+    assert_eq!(user_types, ["a_t", "after", "before"]);
+    assert_eq!(
+        isf["user_types"]["a_t"]["fields"]["x"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+}
+
+/// A record of an unknown kind whose length fits no layout fails the run.
+#[test]
+fn unknown_kind_without_layout() {
+    let mut btf = BtfBuilder::new();
+    btf.int("int", 4, true);
+    btf.add("", 25, false, 1, 0, &[0; 5]);
+    let Err(err) = btf.build("kind") else {
+        panic!("BTF with an unknown kind of no layout was parsed");
+    };
+    let err = anyhow::Error::from(err);
+    assert!(
+        format!("{:#}", err).contains("unknown kind 25"),
+        "{:#}",
+        err
+    );
+}
+
+/// The BTF of a memory image whose records cannot all be parsed is found and
+/// its other types are generated.
+#[test]
+fn unparsable_records_in_image() {
+    for (name, btf) in [("record", invalid_name()), ("kind", unknown_kind())] {
+        let mut image = vec![0; 0x1000];
+        image.extend(btf.bytes());
+        let path = common::write_temp(&format!("{}.img", name), image);
+        let isf = common::try_generate(&["--image", path.to_str().unwrap(), "--types-only"]);
+        fs::remove_file(&path).unwrap();
+
+        let isf = isf.unwrap();
+        let mut user_types: Vec<&String> = isf["user_types"].as_object().unwrap().keys().collect();
+        user_types.sort();
+        assert_eq!(user_types, ["a_t", "after", "before"], "{}", name);
+    }
+}
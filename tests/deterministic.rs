@@ -0,0 +1,78 @@
+//! Tests that generating an ISF file is deterministic.
+//!
+//! The inputs are the kernel in `tests/data/linux-6.18`, see `tests/golden.rs`,
+//! whose many structs and enums make a differing order of fields or constants
+//! between two runs all but certain. The metadata of `--timestamp` and
+//! `--hostname` is checked with the small `tests/data/endian/tiny_le.btf`.
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use xz2::read::XzDecoder;
+
+/// Decompresses `name.xz` from `src` into `dst` and returns the path.
+fn unpack(src: &Path, dst: &Path, name: &str) -> PathBuf {
+    let mut data = Vec::new();
+    XzDecoder::new(File::open(src.join(format!("{}.xz", name))).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    let path = dst.join(name);
+    fs::write(&path, data).unwrap();
+    path
+}
+
+/// Returns the serialized ISF file generated from `btf` and `map`.
+fn generate(btf: &Path, map: &Path, banner: &str) -> Vec<u8> {
+    let mut isf = common::generate_isf(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        banner,
+    ]);
+    let _ = isf.fix_symbol_types();
+    let mut out = Vec::new();
+    isf.dump_to(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn repeated_runs_are_identical() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/linux-6.18");
+    let dst = env::temp_dir().join(format!("btf2json-deterministic-{}", std::process::id()));
+    fs::create_dir_all(&dst).unwrap();
+    let btf = unpack(&src, &dst, "vmlinux.btf");
+    let map = unpack(&src, &dst, "System.map");
+    let banner = fs::read_to_string(src.join("banner")).unwrap();
+
+    let first = generate(&btf, &map, banner.trim_end());
+    let second = generate(&btf, &map, banner.trim_end());
+    fs::remove_dir_all(&dst).unwrap();
+
+    assert!(first == second, "ISF files of two runs differ");
+}
+
+#[test]
+fn generation_metadata() {
+    let btf = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/endian/tiny_le.btf");
+    let generate = |args: &[&str]| -> Value {
+        common::generate(&[&["--btf", btf.to_str().unwrap(), "--types-only"], args].concat())
+    };
+
+    let plain = generate(&[]);
+    assert!(plain["metadata"]["producer"].get("datetime").is_none());
+    assert!(plain["metadata"]["producer"].get("host").is_none());
+
+    let mut stamped = generate(&["--timestamp", "--hostname"]);
+    let producer = stamped["metadata"]["producer"].as_object_mut().unwrap();
+    let datetime = producer.remove("datetime").unwrap();
+    assert!(datetime.as_str().unwrap().ends_with('Z'), "{}", datetime);
+    assert!(producer.remove("host").is_some());
+    assert_eq!(stamped, plain);
+}
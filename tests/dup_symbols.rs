@@ -0,0 +1,92 @@
+//! Tests for the policies for symbol names that appear more than once.
+
+mod common;
+
+use std::fs;
+
+use serde_json::{json, Value};
+
+/// System.map with the triple-duplicated `probe` and a real `probe__1`.
+const MAP: &str = "\
+ffffffff81000000 T _stext
+ffffffff81000300 t probe
+ffffffff81000100 t probe
+ffffffff81000200 t probe
+ffffffff81000400 T probe__1
+ffffffff82000100 D linux_banner
+";
+
+fn generate(policy: &str) -> Value {
+    let map = common::write_temp(&format!("dup-symbols-{}.map", policy), MAP);
+    let btf = common::data("vars/vars.btf");
+    let isf = common::generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+        "--dup-symbols",
+        policy,
+    ]);
+    fs::remove_file(&map).unwrap();
+    isf
+}
+
+/// Returns the addresses of all symbols whose name starts with `probe`.
+fn probes(isf: &Value) -> Vec<(String, u64)> {
+    let mut probes: Vec<(String, u64)> = isf["symbols"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .filter(|(name, _)| name.starts_with("probe"))
+        .map(|(name, sym)| (name.clone(), sym["address"].as_u64().unwrap()))
+        .collect();
+    probes.sort();
+    probes
+}
+
+#[test]
+fn drop_policy() {
+    let isf = generate("drop");
+
+    assert_eq!(probes(&isf), [("probe__1".into(), 0xffffffff81000400)]);
+}
+
+#[test]
+fn first_policy() {
+    let isf = generate("first");
+
+    assert_eq!(
+        isf["symbols"]["probe"]["address"],
+        json!(0xffffffff81000300u64)
+    );
+}
+
+#[test]
+fn lowest_addr_policy() {
+    let isf = generate("lowest-addr");
+
+    assert_eq!(
+        isf["symbols"]["probe"]["address"],
+        json!(0xffffffff81000100u64)
+    );
+}
+
+#[test]
+fn suffix_policy() {
+    let isf = generate("suffix");
+
+    // The real `probe__1` is not shadowed.
+    assert_eq!(
+        probes(&isf),
+        [
+            ("probe".into(), 0xffffffff81000300),
+            ("probe__1".into(), 0xffffffff81000400),
+            ("probe__1_1".into(), 0xffffffff81000100),
+            ("probe__2".into(), 0xffffffff81000200),
+        ]
+    );
+}
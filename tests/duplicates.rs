@@ -0,0 +1,62 @@
+//! Tests for distinct types that share a name.
+//!
+//! `tests/data/dups/dups.btf` is a hand-crafted BTF section that defines:
+//!
+//! ```c
+//! struct dup { int a; };              // ID 3
+//! struct dup { int a; int b; };       // ID 4
+//! struct tie { int x; };              // ID 5
+//! struct tie { int y; };              // ID 6
+//! struct same { int a; };             // ID 7
+//! struct same { int a; };             // ID 8
+//! enum color { RED };                 // ID 9
+//! enum color { RED };                 // ID 10
+//! ```
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(keep: bool) -> Value {
+    let path = common::data("dups/dups.btf");
+    let mut args = vec!["--btf", path.to_str().unwrap(), "--types-only"];
+    if keep {
+        args.push("--keep-duplicate-types");
+    }
+    common::generate(&args)
+}
+
+fn field_names(user_type: &Value) -> Vec<&String> {
+    let mut names: Vec<_> = user_type["fields"].as_object().unwrap().keys().collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn most_complete_definition_wins() {
+    let isf = generate(false);
+    let user_types = isf["user_types"].as_object().unwrap();
+
+    assert_eq!(field_names(&user_types["dup"]), ["a", "b"]);
+    assert_eq!(field_names(&user_types["tie"]), ["x"]);
+    assert_eq!(field_names(&user_types["same"]), ["a"]);
+    assert_eq!(isf["enums"]["color"]["constants"], json!({"RED": 0}));
+    assert!(user_types.keys().all(|name| !name.contains("__btf")));
+}
+
+#[test]
+fn keep_duplicate_types() {
+    let isf = generate(true);
+    let user_types = isf["user_types"].as_object().unwrap();
+
+    assert_eq!(field_names(&user_types["dup"]), ["a", "b"]);
+    assert_eq!(field_names(&user_types["dup__btf3"]), ["a"]);
+    assert_eq!(field_names(&user_types["tie"]), ["x"]);
+    assert_eq!(field_names(&user_types["tie__btf6"]), ["y"]);
+    // Identical definitions are merged.
+    assert_eq!(
+        user_types.keys().collect::<Vec<_>>(),
+        ["dup", "dup__btf3", "same", "tie", "tie__btf6"]
+    );
+    assert_eq!(isf["enums"].as_object().unwrap().len(), 1);
+}
@@ -0,0 +1,205 @@
+//! Tests for reading the types from DWARF with `--dwarf`.
+//!
+//! `tests/data/dwarf/vmlinux` is linked from two compilation units,
+//!
+//! ```c
+//! // a.c, gcc -g -gdwarf-5 -O0 -mcmodel=kernel -fno-pie -c a.c
+//! void _stext(void) {}
+//!
+//! struct list_head { struct list_head *next, *prev; };
+//! typedef unsigned int u32;
+//! enum task_state { TASK_RUNNING, TASK_DEAD = 64 };
+//! struct mm_struct;
+//! struct task_struct {
+//!     volatile long state;
+//!     int pid;
+//!     unsigned int flags:3, mode:5;
+//!     enum task_state exit_state;
+//!     char comm[16];
+//!     struct list_head tasks;
+//!     struct mm_struct *mm;
+//!     union { u32 raw; struct { unsigned short lo, hi; }; };
+//!     int matrix[2][3];
+//!     void (*fn)(int, ...);
+//!     const char *name;
+//!     _Bool on_cpu;
+//!     double load;
+//! };
+//! struct task_struct init_task;
+//! const char linux_banner[] = "Linux version 6.18.0-dwarf (builder@host) (gcc 15.3.0) #1 SMP\n";
+//! int do_exit(long code) { return code; }
+//! ```
+//!
+//! ```c
+//! // b.c, gcc -g -gdwarf-2 -gstrict-dwarf -O0 -mcmodel=kernel -fno-pie -c b.c
+//! struct list_head { struct list_head *next, *prev; };
+//! struct mm_struct {
+//!     struct list_head mmlist;
+//!     unsigned long start_code;
+//!     unsigned int map_count:31, locked:1;
+//! };
+//! struct task_struct;
+//! struct mm_struct init_mm;
+//! struct task_struct *current_task;
+//! unsigned long jiffies;
+//! static int counter;
+//! int *get_counter(void) { return &counter; }
+//! ```
+//!
+//! with `gcc -nostdlib -static -no-pie -Wl,-e,_stext
+//! -Wl,-Ttext=0xffffffff81000000 -Wl,--build-id=none a.o b.o`. DWARF 2 has
+//! the bitfield offsets from the most significant bit and member locations as
+//! expressions.
+
+mod common;
+
+use serde_json::{json, Value};
+
+const VMLINUX: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/dwarf/vmlinux");
+
+fn btf2json(args: &[&str]) -> (i32, Vec<u8>, String) {
+    let output = common::run(args);
+    (
+        output.status.code().unwrap(),
+        output.stdout,
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn generate() -> Value {
+    let (code, stdout, stderr) = btf2json(&["generate", "--dwarf", VMLINUX]);
+    assert_eq!(code, 0, "{}", stderr);
+    serde_json::from_slice(&stdout).unwrap()
+}
+
+fn field<'a>(isf: &'a Value, user: &str, field: &str) -> &'a Value {
+    &isf["user_types"][user]["fields"][field]
+}
+
+#[test]
+fn types() {
+    let isf = generate();
+
+    // Types of both units are merged by name, and declarations resolve to the
+    // definition in the other unit.
+    assert_eq!(
+        isf["user_types"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>(),
+        [
+            "list_head",
+            "mm_struct",
+            "task_struct",
+            "unnamed_struct_7",
+            "unnamed_union_8"
+        ]
+    );
+    assert_eq!(isf["user_types"]["task_struct"]["size"], json!(128));
+    assert_eq!(
+        field(&isf, "task_struct", "mm")["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "mm_struct"}})
+    );
+    assert_eq!(
+        isf["symbols"]["current_task"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "task_struct"}})
+    );
+
+    assert_eq!(
+        field(&isf, "task_struct", "tasks"),
+        &json!({"type": {"kind": "struct", "name": "list_head"}, "offset": 40, "anonymous": false})
+    );
+    assert_eq!(
+        field(&isf, "task_struct", "matrix")["type"],
+        json!({
+            "kind": "array",
+            "count": 2,
+            "subtype": {"kind": "array", "count": 3, "subtype": {"kind": "base", "name": "int"}}
+        })
+    );
+    assert_eq!(
+        field(&isf, "task_struct", "fn")["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "function"}})
+    );
+    assert_eq!(
+        field(&isf, "task_struct", "unnamed_member_8"),
+        &json!({"type": {"kind": "union", "name": "unnamed_union_8"}, "offset": 64, "anonymous": true})
+    );
+    assert_eq!(field(&isf, "unnamed_struct_7", "hi")["offset"], json!(2));
+    assert_eq!(
+        isf["enums"]["task_state"],
+        json!({"size": 4, "base": "unsigned int", "constants": {"TASK_DEAD": 64, "TASK_RUNNING": 0}})
+    );
+    assert_eq!(isf["base_types"]["double"]["kind"], json!("float"));
+    assert_eq!(isf["base_types"]["_Bool"]["kind"], json!("bool"));
+}
+
+#[test]
+fn bitfields() {
+    let isf = generate();
+    let bitfield = |user, name| {
+        let field = field(&isf, user, name);
+        let t = &field["type"];
+        assert_eq!(t["kind"], json!("bitfield"));
+        (
+            field["offset"].as_u64().unwrap() * 8 + t["bit_position"].as_u64().unwrap(),
+            t["bit_length"].as_u64().unwrap(),
+        )
+    };
+
+    // DWARF 5 has the offsets in bits from the start of the struct.
+    assert_eq!(bitfield("task_struct", "flags"), (96, 3));
+    assert_eq!(bitfield("task_struct", "mode"), (99, 5));
+    // DWARF 2 has them from the most significant bit of the storage unit.
+    assert_eq!(bitfield("mm_struct", "map_count"), (192, 31));
+    assert_eq!(bitfield("mm_struct", "locked"), (223, 1));
+    assert_eq!(field(&isf, "mm_struct", "start_code")["offset"], json!(16));
+}
+
+#[test]
+fn symbols_and_banner() {
+    let isf = generate();
+
+    // The symbols, their types, and the banner are read from the vmlinux.
+    let symbols = &isf["symbols"];
+    assert_eq!(
+        symbols["init_task"]["address"],
+        json!(0xffffffff81002040u64)
+    );
+    assert_eq!(
+        symbols["init_task"]["type"],
+        json!({"kind": "struct", "name": "task_struct"})
+    );
+    assert_eq!(
+        symbols["counter"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+    assert_eq!(symbols["do_exit"]["type"], json!({"kind": "function"}));
+    let linux = &isf["metadata"]["linux"];
+    assert_eq!(linux["kernel_version"], json!("6.18.0-dwarf"));
+    assert_eq!(linux["types"][0]["name"], json!("vmlinux"));
+}
+
+#[test]
+fn exclusive_with_btf() {
+    let (code, _, stderr) = btf2json(&["generate", "--dwarf", VMLINUX, "--btf", VMLINUX]);
+    assert_eq!(code, 3);
+    assert!(
+        stderr.contains("'--dwarf <DWARF>' cannot be used with '--btf <BTF>'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn without_debug_information() {
+    let single = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/single/vmlinux");
+    let (code, _, stderr) = btf2json(&["generate", "--dwarf", single]);
+    assert_eq!(code, 1);
+    assert!(
+        stderr.contains("No DWARF debug information (.debug_info section)"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,233 @@
+//! Tests that malformed ELF files lead to errors instead of panics.
+
+use btf2json::elf;
+
+const BANNER: &str = "Linux version 6.18.0 (btf2json@test) #1 SMP";
+/// Virtual address of the `.rodata` section.
+const RODATA_ADDR: u64 = 0xffffffff82000000;
+/// Stand-in for the contents of the `.BTF` section.
+const BTF: [u8; 25] = [
+    0x9f, 0xeb, 0x01, 0x00, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Section header fields.
+const SH_ADDR: usize = 16;
+const SH_OFFSET: usize = 24;
+const SH_SIZE: usize = 32;
+/// Symbol fields.
+const ST_SHNDX: usize = 6;
+const ST_VALUE: usize = 8;
+const ST_SIZE: usize = 16;
+
+/// A little endian ELF64 file with a `.BTF` section and a `linux_banner`
+/// symbol in `.rodata`.
+struct TestElf {
+    data: Vec<u8>,
+    /// Offset of the section header of `.BTF`.
+    btf_shdr: usize,
+    /// Offset of the section header of `.rodata`.
+    rodata_shdr: usize,
+    /// Offset of the symbol table entry of `linux_banner`.
+    banner_sym: usize,
+}
+
+impl TestElf {
+    fn new() -> Self {
+        let shstrtab = b"\0.BTF\0.rodata\0.symtab\0.strtab\0.shstrtab\0";
+        let strtab = b"\0linux_banner\0";
+
+        let mut data = vec![0; 64];
+        let btf_off = data.len();
+        data.extend(BTF);
+        let rodata_off = data.len();
+        data.extend(BANNER.as_bytes());
+        data.push(0);
+        data.resize(data.len().next_multiple_of(8), 0);
+        let symtab_off = data.len();
+        data.extend([0; 24]);
+        let banner_sym = data.len();
+        data.extend(1u32.to_le_bytes()); // st_name
+        data.push(0x11); // st_info: GLOBAL OBJECT
+        data.push(0); // st_other
+        data.extend(2u16.to_le_bytes()); // st_shndx: .rodata
+        data.extend(RODATA_ADDR.to_le_bytes());
+        data.extend((BANNER.len() as u64).to_le_bytes());
+        let strtab_off = data.len();
+        data.extend(strtab);
+        let shstrtab_off = data.len();
+        data.extend(shstrtab);
+        data.resize(data.len().next_multiple_of(8), 0);
+
+        let shoff = data.len();
+        let mut shdr = |name: u32, type_: u32, addr: u64, off: usize, size: usize, link: u32| {
+            data.extend(name.to_le_bytes());
+            data.extend(type_.to_le_bytes());
+            data.extend(2u64.to_le_bytes()); // sh_flags: ALLOC
+            data.extend(addr.to_le_bytes());
+            data.extend((off as u64).to_le_bytes());
+            data.extend((size as u64).to_le_bytes());
+            data.extend(link.to_le_bytes());
+            data.extend(1u32.to_le_bytes()); // sh_info
+            data.extend(8u64.to_le_bytes()); // sh_addralign
+            data.extend((if type_ == 2 { 24u64 } else { 0 }).to_le_bytes());
+        };
+        shdr(0, 0, 0, 0, 0, 0);
+        shdr(1, 1, 0, btf_off, BTF.len(), 0);
+        shdr(6, 1, RODATA_ADDR, rodata_off, BANNER.len() + 1, 0);
+        shdr(14, 2, 0, symtab_off, 48, 4);
+        shdr(22, 3, 0, strtab_off, strtab.len(), 0);
+        shdr(30, 3, 0, shstrtab_off, shstrtab.len(), 0);
+
+        let mut ehdr = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+        ehdr.extend(2u16.to_le_bytes()); // e_type: EXEC
+        ehdr.extend(62u16.to_le_bytes()); // e_machine: x86_64
+        ehdr.extend(1u32.to_le_bytes()); // e_version
+        ehdr.extend(0u64.to_le_bytes()); // e_entry
+        ehdr.extend(0u64.to_le_bytes()); // e_phoff
+        ehdr.extend((shoff as u64).to_le_bytes());
+        ehdr.extend(0u32.to_le_bytes()); // e_flags
+        ehdr.extend(64u16.to_le_bytes()); // e_ehsize
+        ehdr.extend(56u16.to_le_bytes()); // e_phentsize
+        ehdr.extend(0u16.to_le_bytes()); // e_phnum
+        ehdr.extend(64u16.to_le_bytes()); // e_shentsize
+        ehdr.extend(6u16.to_le_bytes()); // e_shnum
+        ehdr.extend(5u16.to_le_bytes()); // e_shstrndx
+        data[..64].copy_from_slice(&ehdr);
+
+        Self {
+            data,
+            btf_shdr: shoff + 64,
+            rodata_shdr: shoff + 2 * 64,
+            banner_sym,
+        }
+    }
+
+    fn set_u16(&mut self, off: usize, val: u16) -> &[u8] {
+        self.data[off..off + 2].copy_from_slice(&val.to_le_bytes());
+        &self.data
+    }
+
+    fn set_u64(&mut self, off: usize, val: u64) -> &[u8] {
+        self.data[off..off + 8].copy_from_slice(&val.to_le_bytes());
+        &self.data
+    }
+}
+
+#[test]
+fn well_formed() {
+    let elf = TestElf::new();
+    assert_eq!(elf::extract_btfsec(&elf.data).unwrap(), BTF);
+    assert_eq!(elf::get_banner(&elf.data).unwrap(), BANNER);
+}
+
+#[test]
+fn btf_section_out_of_bounds() {
+    for (field, val) in [
+        (SH_OFFSET, u64::MAX - 4),
+        (SH_SIZE, u64::MAX),
+        (SH_SIZE, 1 << 20),
+    ] {
+        let mut elf = TestElf::new();
+        let off = elf.btf_shdr + field;
+        let err = elf::extract_btfsec(elf.set_u64(off, val)).unwrap_err();
+        assert!(format!("{:#}", err).contains(".BTF"), "{:#}", err);
+    }
+}
+
+#[test]
+fn banner_out_of_bounds() {
+    let cases = [
+        (TestElf::new().banner_sym + ST_VALUE, 0),
+        (TestElf::new().banner_sym + ST_VALUE, u64::MAX),
+        (TestElf::new().banner_sym + ST_SIZE, u64::MAX),
+        (TestElf::new().banner_sym + ST_SIZE, 1 << 20),
+        (TestElf::new().rodata_shdr + SH_ADDR, RODATA_ADDR + 1),
+        (TestElf::new().rodata_shdr + SH_OFFSET, u64::MAX - 4),
+        (TestElf::new().rodata_shdr + SH_SIZE, 1 << 20),
+    ];
+    for (off, val) in cases {
+        let mut elf = TestElf::new();
+        let err = elf::get_banner(elf.set_u64(off, val)).unwrap_err();
+        assert!(format!("{:#}", err).contains(".rodata"), "{:#}", err);
+    }
+}
+
+#[test]
+fn banner_special_section() {
+    for (shndx, msg) in [
+        (0, "undefined"),
+        (0xfff1, "absolute"),
+        (0xfff2, "common"),
+        (0xff00, "reserved"),
+    ] {
+        let mut elf = TestElf::new();
+        let off = elf.banner_sym + ST_SHNDX;
+        let err = elf::get_banner(elf.set_u16(off, shndx)).unwrap_err();
+        assert!(format!("{:#}", err).contains(msg), "{:#}", err);
+    }
+}
+
+#[test]
+fn banner_in_relocatable() {
+    let mut elf = TestElf::new();
+    elf.set_u16(16, 1); // e_type: REL
+    let off = elf.banner_sym + ST_VALUE;
+    // Symbol values are offsets into the section.
+    assert_eq!(elf::get_banner(elf.set_u64(off, 0)).unwrap(), BANNER);
+}
+
+#[test]
+fn banner_nul_is_stripped() {
+    let mut elf = TestElf::new();
+    let off = elf.banner_sym + ST_SIZE;
+    let data = elf.set_u64(off, BANNER.len() as u64 + 1);
+    assert_eq!(elf::get_banner(data).unwrap(), BANNER);
+}
+
+/// `tests/data/banner/vmlinux-zeroed-shdr` has the section header of
+/// `.rodata`, which holds `linux_banner`, zeroed, and another copy of the
+/// banner in `.init.data`.
+#[test]
+fn banner_from_init_data() {
+    let data = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/banner/vmlinux-zeroed-shdr"
+    ))
+    .unwrap();
+    assert_eq!(
+        elf::get_banner(&data).unwrap(),
+        "Linux version 6.18.0-zeroed (builder@host) (gcc 14.2.0) #1 SMP PREEMPT_DYNAMIC\n"
+    );
+}
+
+#[test]
+fn truncated() {
+    let elf = TestElf::new();
+    for len in 0..elf.data.len() {
+        let _ = elf::extract_btfsec(&elf.data[..len]);
+        let _ = elf::get_banner(&elf.data[..len]);
+    }
+}
+
+#[test]
+fn fuzzed() {
+    let elf = TestElf::new();
+    // Deterministic xorshift, so failures are reproducible.
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..2000 {
+        let mut data = elf.data.clone();
+        for _ in 0..1 + next() % 4 {
+            let off = (next() % data.len() as u64) as usize;
+            data[off] = next() as u8;
+        }
+        let _ = elf::extract_btfsec(&data);
+        let _ = elf::get_banner(&data);
+    }
+}
@@ -0,0 +1,111 @@
+//! Tests for `--endian`, on `tests/data/endian/tiny_{le,be}.btf`, see
+//! `tests/big_endian.rs`.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+fn path(name: &str) -> PathBuf {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian")).join(name)
+}
+
+fn generate(name: &str, args: &[&str]) -> btf2json::error::Result<Value> {
+    let path = path(name);
+    let mut isf =
+        common::try_generate(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())?;
+    isf.as_object_mut().unwrap().remove("metadata");
+    Ok(isf)
+}
+
+fn endians(isf: &Value) -> Vec<&Value> {
+    isf["base_types"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|base| &base["endian"])
+        .collect()
+}
+
+fn run(name: &str, args: &[&str]) -> Output {
+    let path = path(name);
+    let cli = [
+        "generate",
+        "--btf",
+        path.to_str().unwrap(),
+        "--types-only",
+        "--log-level",
+        "warn",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+#[test]
+fn agreement() {
+    for (name, endian) in [("tiny_le.btf", "little"), ("tiny_be.btf", "big")] {
+        let isf = generate(name, &["--endian", endian]).unwrap();
+        assert_eq!(isf, generate(name, &[]).unwrap());
+
+        let output = run(name, &["--endian", endian]);
+        assert!(output.status.success(), "{:?}", output);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!stderr.contains("as of --endian"), "{}", stderr);
+    }
+}
+
+#[test]
+fn disagreement() {
+    // The types follow --endian, including the bit positions of bitfields.
+    let le_as_big = generate("tiny_le.btf", &["--endian", "big"]).unwrap();
+    let be = generate("tiny_be.btf", &[]).unwrap();
+    assert!(endians(&le_as_big).iter().all(|endian| **endian == "big"));
+    assert_eq!(le_as_big, be);
+
+    let output = run("tiny_le.btf", &["--endian", "big"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("is little endian, but the types are big endian as of --endian"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn fixed_endianness_of_arch() {
+    for (arch, endian) in [("s390x", "little"), ("x86_64", "big"), ("arm64", "big")] {
+        let err = generate("tiny_le.btf", &["--arch", arch, "--endian", endian])
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            err.contains(&format!("but --endian {} was given", endian)),
+            "{}",
+            err
+        );
+    }
+
+    let isf = generate("tiny_le.btf", &["--arch", "s390x", "--endian", "big"]).unwrap();
+    assert!(endians(&isf).iter().all(|endian| **endian == "big"));
+    let isf = generate("tiny_be.btf", &["--arch", "x86_64", "--endian", "little"]).unwrap();
+    assert!(endians(&isf).iter().all(|endian| **endian == "little"));
+}
+
+#[test]
+fn recorded_invocation() {
+    let path = path("tiny_le.btf");
+    let isf = common::generate(&[
+        "--btf",
+        path.to_str().unwrap(),
+        "--types-only",
+        "--endian",
+        "big",
+        "--record-invocation",
+    ]);
+    assert_eq!(
+        isf["metadata"]["producer"]["invocation"]["options"],
+        json!(["types-only", "endian=big"])
+    );
+}
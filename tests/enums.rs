@@ -0,0 +1,97 @@
+//! Tests for enums.
+//!
+//! `tests/data/enums/unnamed.btf` is a hand-crafted BTF section that defines
+//! enums with anonymous constants, which C does not allow but BTF does:
+//!
+//! ```c
+//! enum holes { = 5, HOLE_A = 1, = 7 };                  // ID 3
+//! enum clash { = 0, unnamed_constant_4_0 = 1 };         // ID 4
+//! ```
+//!
+//! `tests/data/enums/values.btf` defines enums whose constants use the full
+//! range of their signed or unsigned underlying type:
+//!
+//! ```c
+//! enum umask : unsigned long { UMASK_ALL = 0xffffffffffffffff, UMASK_TOP = 1UL << 63 };
+//! enum smask : long { SMASK_NEG = -1, SMASK_MIN = LONG_MIN };
+//! enum u32e : unsigned int { U32_TOP = 0x80000000, U32_ALL = 0xffffffff };
+//! enum s32e : int { S32_NEG = -1, S32_MIN = INT_MIN };
+//! ```
+//!
+//! `tests/data/enums/bases.btf` defines enums without a base type of the same
+//! size and signedness. Its base types are `unsigned char`, `int`, and a signed
+//! 4 byte integer named `__enum_base_3_signed`, which clashes with the base
+//! type that would be synthesized for `odd`:
+//!
+//! ```c
+//! enum small : signed char { SMALL_NEG = -1, SMALL_ONE = 1 };
+//! enum wide : short { WIDE_ONE = 1 };
+//! enum odd { ODD_ONE = 1 };               // 3 bytes
+//! struct user { enum small s; enum wide w; enum odd o; };
+//! ```
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(name: &str) -> Value {
+    let path = common::data("enums").join(name);
+    common::generate(&["--btf", path.to_str().unwrap(), "--types-only"])
+}
+
+#[test]
+fn unnamed_constants() {
+    let isf = generate("unnamed.btf");
+    let enums = &isf["enums"];
+
+    assert_eq!(
+        enums["holes"]["constants"],
+        json!({"unnamed_constant_3_0": 5, "HOLE_A": 1, "unnamed_constant_3_2": 7})
+    );
+    assert_eq!(
+        enums["clash"]["constants"],
+        json!({"unnamed_constant_4_0_1": 0, "unnamed_constant_4_0": 1})
+    );
+}
+
+#[test]
+fn signedness_of_constants() {
+    let isf = generate("values.btf");
+    let enums = &isf["enums"];
+
+    assert_eq!(
+        enums["umask"]["constants"],
+        json!({"UMASK_ALL": 0xffff_ffff_ffff_ffff_u64, "UMASK_TOP": 0x8000_0000_0000_0000_u64})
+    );
+    assert_eq!(
+        enums["smask"]["constants"],
+        json!({"SMASK_NEG": -1, "SMASK_MIN": i64::MIN})
+    );
+    assert_eq!(
+        enums["u32e"]["constants"],
+        json!({"U32_TOP": 0x8000_0000_u32, "U32_ALL": 0xffff_ffff_u32})
+    );
+    assert_eq!(
+        enums["s32e"]["constants"],
+        json!({"S32_NEG": -1, "S32_MIN": i32::MIN})
+    );
+}
+
+#[test]
+fn base_type_fallbacks() {
+    let isf = generate("bases.btf");
+    let enums = &isf["enums"];
+
+    // Same size, but different signedness.
+    assert_eq!(enums["small"]["base"], json!("unsigned char"));
+    assert_eq!(enums["small"]["constants"]["SMALL_NEG"], json!(-1));
+    // Synthesized.
+    assert_eq!(enums["wide"]["base"], json!("__enum_base_2_signed"));
+    assert_eq!(
+        isf["base_types"]["__enum_base_2_signed"],
+        json!({"size": 2, "signed": true, "kind": "int", "endian": "little"})
+    );
+    // Skipped, the rest of the profile is still generated.
+    assert!(enums.get("odd").is_none());
+    assert!(isf["user_types"]["user"]["fields"]["o"].is_object());
+}
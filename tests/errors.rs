@@ -0,0 +1,108 @@
+//! Tests for the errors of the library API.
+//!
+//! The fixtures are described in the tests that use them for generation:
+//! `tests/map_formats.rs`, `tests/anchors.rs`, and `tests/fwd_stubs.rs`.
+
+mod common;
+
+use btf2json::btf::Btf;
+use btf2json::cli::Architecture;
+use btf2json::error::VerificationKind;
+use btf2json::{Error, GenerationOptions};
+
+/// Returns the options to generate from `funcs.btf` and the System.map `map`.
+fn funcs_with_map(map: &str) -> GenerationOptions {
+    GenerationOptions::new()
+        .btf_file(common::data("funcs/funcs.btf"))
+        .map_file(common::data(map))
+        .arch(Architecture::X86_64)
+}
+
+#[test]
+fn not_btf_or_elf() {
+    let err = Btf::from_bytes("garbage", vec![0x42; 64]).err().unwrap();
+    assert!(
+        matches!(&err, Error::NotBtfOrElf { name, len: 64 } if name == "garbage"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn btf_parse() {
+    // BTF header whose type section is misaligned.
+    let mut data = vec![0x9f, 0xeb, 1, 0, 24, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0];
+    data.extend([0; 16]);
+    let err = Btf::from_bytes("bad.btf", data).err().unwrap();
+    let Error::BtfParse { name, source } = &err else {
+        panic!("{:?}", err)
+    };
+    assert_eq!(name, "bad.btf");
+    assert!(
+        format!("{:#}", source).contains("Misaligned"),
+        "{:#}",
+        source
+    );
+}
+
+#[test]
+fn map_parse() {
+    let err = funcs_with_map("mapformats/invalid.map")
+        .banner("Linux version 6.18.0")
+        .generate()
+        .err()
+        .unwrap();
+    assert!(
+        matches!(
+            &err,
+            Error::MapParse { name, line: 3, invalid: 2, lines: 11, .. } if name == "invalid.map"
+        ),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn missing_anchor_symbol() {
+    let err = funcs_with_map("anchors/none.map")
+        .banner("Linux version 6.18.0")
+        .generate()
+        .err()
+        .unwrap();
+    let Error::MissingAnchorSymbol { tried } = err else {
+        panic!("{:?}", err)
+    };
+    assert_eq!(tried[0], "_stext");
+}
+
+#[test]
+fn banner_not_found() {
+    let err = funcs_with_map("funcs/System.map").generate().err().unwrap();
+    assert!(matches!(err, Error::BannerNotFound), "{:?}", err);
+}
+
+#[test]
+fn isf_verification() {
+    let mut isf = GenerationOptions::new()
+        .btf_file(common::data("fwd/fwd.btf"))
+        .types_only(true)
+        .generate()
+        .unwrap();
+    let err = isf.check_user_types().err().unwrap();
+    assert!(
+        matches!(
+            err,
+            Error::IsfVerification {
+                kind: VerificationKind::UserTypes,
+                ..
+            }
+        ),
+        "{:?}",
+        err
+    );
+    assert!(err
+        .to_string()
+        .starts_with("User type verification failed: "));
+    assert!(isf.fix_user_types().is_err());
+    isf.fix_user_types().unwrap();
+}
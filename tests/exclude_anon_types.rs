@@ -0,0 +1,79 @@
+//! Tests for `--exclude-anon-types`, on synthetic BTF built with
+//! `btf2json::testutil` and on the golden ISF file of `tests/data/linux-6.18`.
+
+use btf2json::isf::Isf;
+use btf2json::testutil::BtfBuilder;
+use btf2json::GenerationOptions;
+
+/// ```c
+/// struct outer {
+///     union { int a; };        // anonymous member
+///     enum { A } e;            // anonymous enum
+///     struct { int b; } *p;    // pointer to an anonymous struct
+/// };
+/// struct { int c; };           // unreferenced
+/// union { int d; };            // unreferenced
+/// enum { B };                  // unreferenced
+/// ```
+fn generate(exclude_anon_types: bool) -> Isf {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let member = btf.union_("", 4, &[("a", int, 0)]);
+    let e = btf.enum_("", 4, &[("A", 0)]);
+    let pointee = btf.struct_("", 4, &[("b", int, 0)]);
+    let p = btf.ptr(pointee);
+    btf.struct_("outer", 16, &[("", member, 0), ("e", e, 32), ("p", p, 64)]);
+    btf.struct_("", 4, &[("c", int, 0)]);
+    btf.union_("", 4, &[("d", int, 0)]);
+    btf.enum_("", 4, &[("B", 0)]);
+    GenerationOptions::new()
+        .btf_bytes("anon.btf", btf.bytes())
+        .types_only(true)
+        .exclude_anon_types(exclude_anon_types)
+        .generate()
+        .unwrap()
+}
+
+fn user_types(isf: &Isf) -> Vec<String> {
+    isf.user_types().map(|(name, _)| name.to_owned()).collect()
+}
+
+#[test]
+fn unreferenced() {
+    let full = generate(false);
+    let isf = generate(true);
+    assert_eq!(user_types(&full).len(), 5);
+    assert_eq!(
+        user_types(&isf),
+        vec!["outer", "unnamed_struct_4", "unnamed_union_2"]
+    );
+    // Anonymous enums are kept whether referenced or not.
+    assert_eq!(isf.enums().count(), full.enums().count());
+    isf.check_user_types().unwrap();
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn golden() {
+    use std::path::Path;
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/linux-6.18/expected.json.xz"
+    );
+    let full = Isf::from_path(Path::new(path)).unwrap();
+    let mut isf = Isf::from_path(Path::new(path)).unwrap();
+    isf.exclude_anon_types().unwrap();
+
+    assert_eq!(
+        isf.check_user_types().is_ok(),
+        full.check_user_types().is_ok()
+    );
+    assert_eq!(isf.enums().count(), full.enums().count());
+    let kept = user_types(&isf);
+    for name in user_types(&full) {
+        if !name.starts_with("unnamed_") {
+            assert!(kept.contains(&name), "{}", name);
+        }
+    }
+}
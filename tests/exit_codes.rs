@@ -0,0 +1,47 @@
+//! Tests for the exit codes of the binary.
+//!
+//! `tests/data/fwd/fwd.btf` is described in `tests/fwd_stubs.rs`, its user type
+//! `user` references types that are only declared.
+
+mod common;
+
+use std::path::Path;
+
+const FWD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fwd/fwd.btf");
+
+fn exit_code(args: &[&str]) -> i32 {
+    let output = common::run(args);
+    output.status.code().unwrap()
+}
+
+#[test]
+fn success() {
+    // Without `--strict` broken references do not fail the generation.
+    assert_eq!(exit_code(&["--btf", FWD, "--types-only"]), 0);
+    assert_eq!(exit_code(&["--help"]), 0);
+    // Stubs fix the references.
+    assert_eq!(
+        exit_code(&["--btf", FWD, "--types-only", "--strict", "--emit-fwd-stubs"]),
+        0
+    );
+}
+
+#[test]
+fn generation_failed() {
+    let missing = Path::new(FWD).with_file_name("missing.btf");
+    assert_eq!(
+        exit_code(&["--btf", missing.to_str().unwrap(), "--types-only"]),
+        1
+    );
+}
+
+#[test]
+fn verification_failed() {
+    assert_eq!(exit_code(&["--btf", FWD, "--types-only", "--strict"]), 2);
+}
+
+#[test]
+fn bad_arguments() {
+    assert_eq!(exit_code(&["--no-such-flag"]), 3);
+    assert_eq!(exit_code(&["--btf", FWD, "--compress", "gz"]), 3);
+}
@@ -0,0 +1,78 @@
+//! Tests for the C interface, with a C program that is linked against the
+//! cdylib, which is built like in the README.
+//!
+//! Uses `tests/data/vars`, see `tests/variables.rs`.
+
+#![cfg(all(feature = "ffi", target_os = "linux"))]
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+const VARS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars");
+
+#[test]
+fn c_program() {
+    // A separate target directory avoids waiting for the lock of the one that
+    // runs the tests.
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi");
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "rustc",
+            "--lib",
+            "--features",
+            "ffi",
+            "--crate-type",
+            "cdylib",
+        ])
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let lib_dir = target_dir.join("debug");
+    assert!(lib_dir.join("libbtf2json.so").exists());
+
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let program = lib_dir.join(format!("ffi-generate-{}", std::process::id()));
+    let target = format!("{}-unknown-linux-gnu", env::consts::ARCH);
+    let status = cc::Build::new()
+        .cargo_metadata(false)
+        .opt_level(0)
+        .host(&target)
+        .target(&target)
+        .get_compiler()
+        .to_command()
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/ffi/generate.c"))
+        .arg("-o")
+        .arg(&program)
+        .arg(format!("-L{}", lib_dir.display()))
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lbtf2json")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    // The library path of `cargo test` takes precedence over the rpath and
+    // may have a stale cdylib.
+    let output = Command::new(&program)
+        .args([format!("{}/vars.btf", VARS), format!("{}/System.map", VARS)])
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&program).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(isf["symbols"].as_object().unwrap().len(), 7);
+    assert!(isf["base_types"]["int"].is_object());
+}
@@ -0,0 +1,51 @@
+/*
+ * Generates the ISF file from the BTF file and System.map given as arguments
+ * through the C interface and prints it. Exits with 0 if the error cases are
+ * reported as documented.
+ */
+
+#include <stdio.h>
+
+#include "btf2json.h"
+
+#define BANNER "Linux version 6.18.0"
+
+static int expect_error(const char *btf_path, int expected)
+{
+	char *json = (char *)1, *err = NULL;
+	int ret = btf2json_generate(btf_path, NULL, BANNER, &json, &err);
+
+	if (ret != expected || json != NULL || err == NULL) {
+		fprintf(stderr, "%s: expected %d, got %d\n", btf_path, expected,
+			ret);
+		return 0;
+	}
+	btf2json_free(err);
+	return 1;
+}
+
+int main(int argc, char **argv)
+{
+	char *json, *err;
+	int ret;
+
+	if (argc != 3) {
+		fprintf(stderr, "usage: %s BTF SYSTEM_MAP\n", argv[0]);
+		return 2;
+	}
+
+	if (!expect_error("/nonexistent/vmlinux", BTF2JSON_ERR_GENERATION) ||
+	    !expect_error(NULL, BTF2JSON_ERR_ARGUMENTS))
+		return 1;
+
+	ret = btf2json_generate(argv[1], argv[2], BANNER, &json, &err);
+	if (ret != BTF2JSON_OK) {
+		fprintf(stderr, "generation failed with %d: %s\n", ret, err);
+		btf2json_free(err);
+		return 1;
+	}
+	printf("%s\n", json);
+	btf2json_free(json);
+	btf2json_free(NULL);
+	return 0;
+}
@@ -0,0 +1,62 @@
+//! Tests for the stubs of `Isf::fix_user_types` and `--no-fixups`.
+//!
+//! `tests/data/fwd/fwd.btf` is described in `tests/fwd_stubs.rs`,
+//! `tests/data/endian/tiny_le.btf` in `tests/big_endian.rs`, its struct `task`
+//! has a field `mode` of type `enum mode`.
+
+mod common;
+
+use std::path::Path;
+
+use btf2json::isf::Isf;
+use serde_json::{json, Value};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+
+fn generate(btf: &str, args: &[&str]) -> Isf {
+    let path = Path::new(DATA).join(btf);
+    common::generate_isf(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())
+}
+
+fn stub(kind: &str) -> Value {
+    json!({"kind": kind, "size": 0, "fields": {}})
+}
+
+#[test]
+fn user_type_stubs() {
+    let mut isf = generate("fwd/fwd.btf", &[]);
+    assert!(isf.fix_user_types().is_err());
+    assert!(isf.check_user_types().is_ok());
+    // A second pass has nothing left to do.
+    assert!(isf.fix_user_types().is_ok());
+
+    let user_types = &serde_json::to_value(&isf).unwrap()["user_types"];
+    assert_eq!(user_types["opaque"], stub("struct"));
+    assert_eq!(user_types["uopaque"], stub("union"));
+}
+
+#[test]
+fn enum_stubs() {
+    let mut isf = generate("endian/tiny_le.btf", &["--exclude-types", "^mode$"]);
+    assert!(isf.check_user_types().is_err());
+    assert!(isf.fix_user_types().is_err());
+    assert!(isf.check_user_types().is_ok());
+
+    let enums = &serde_json::to_value(&isf).unwrap()["enums"];
+    assert_eq!(
+        enums["mode"],
+        json!({"size": 4, "base": "int", "constants": {}})
+    );
+}
+
+#[test]
+fn no_fixups() {
+    let isf = |args: &[&str]| -> Value {
+        let btf = format!("{}/fwd/fwd.btf", DATA);
+        let output = common::run(&[&["--btf", &btf, "--types-only"], args].concat());
+        assert!(output.status.success());
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    assert_eq!(isf(&[])["user_types"]["opaque"], stub("struct"));
+    assert!(isf(&["--no-fixups"])["user_types"].get("opaque").is_none());
+}
@@ -0,0 +1,101 @@
+//! Tests for symbol types from BTF functions.
+//!
+//! `tests/data/funcs/funcs.btf` is a hand-crafted BTF section that declares
+//! the following functions and variables:
+//!
+//! ```c
+//! struct file { long f_pos; };
+//!
+//! long vfs_read(struct file *file);
+//! static int do_init(void);
+//! static int helper(void);    // in two compilation units with
+//! static void helper(int x);  // different prototypes
+//! static int twice(void);     // in two compilation units with
+//! static int twice(void);     // the same prototype
+//! int both;                   // declared as variable
+//! int both(void);             // and function
+//! int datafn(void);           // a data symbol in the System.map
+//! void weakfn(int x);
+//! int nomap(void);            // not in the System.map
+//! ```
+//!
+//! `tests/data/funcs/System.map` lists all functions but `nomap`.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(rich_functions: bool) -> Value {
+    let (btf, map) = (
+        common::data("funcs/funcs.btf"),
+        common::data("funcs/System.map"),
+    );
+    let mut args = vec![
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    if rich_functions {
+        args.push("--rich-functions");
+    }
+    common::generate(&args)
+}
+
+#[test]
+fn symbol_types_from_functions() {
+    let isf = generate(false);
+    let symbols = &isf["symbols"];
+
+    for name in ["vfs_read", "do_init", "helper", "twice", "weakfn"] {
+        assert_eq!(
+            symbols[name]["type"],
+            json!({"kind": "function"}),
+            "{}",
+            name
+        );
+    }
+    assert_eq!(
+        symbols["datafn"]["type"],
+        json!({"kind": "base", "name": "void"})
+    );
+    assert!(symbols["nomap"].is_null());
+}
+
+#[test]
+fn symbol_types_from_rich_functions() {
+    let isf = generate(true);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(
+        symbols["vfs_read"]["type"],
+        json!({
+            "kind": "function",
+            "return_type": {"kind": "base", "name": "long int"},
+            "parameters": [{"kind": "pointer", "subtype": {"kind": "struct", "name": "file"}}],
+        })
+    );
+    assert_eq!(
+        symbols["twice"]["type"],
+        json!({"kind": "function", "return_type": {"kind": "base", "name": "int"}, "parameters": []})
+    );
+    // Ambiguous functions leave the type from the symdb.
+    assert_eq!(
+        symbols["helper"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "drm_fb_helper"}})
+    );
+}
+
+#[test]
+fn variables_take_precedence_over_functions() {
+    let isf = generate(false);
+
+    assert_eq!(
+        isf["symbols"]["both"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+}
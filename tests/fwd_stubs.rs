@@ -0,0 +1,78 @@
+//! Tests for `--emit-fwd-stubs`.
+//!
+//! `tests/data/fwd/fwd.btf` is a hand-crafted BTF section that defines:
+//!
+//! ```c
+//! struct real { int a; };
+//! struct opaque;
+//! union uopaque;
+//! struct real;
+//! typedef struct opaque opaque_t;
+//!
+//! struct user {
+//!     struct opaque *o;
+//!     union uopaque *u;
+//!     struct real *r;     // references the fwd declaration
+//!     opaque_t *t;
+//! };
+//! ```
+
+mod common;
+
+use btf2json::isf::Isf;
+use serde_json::{json, Value};
+
+fn generate_isf(stubs: bool) -> Isf {
+    let path = common::data("fwd/fwd.btf");
+    let mut args = vec!["--btf", path.to_str().unwrap(), "--types-only"];
+    if stubs {
+        args.push("--emit-fwd-stubs");
+    }
+    common::generate_isf(&args)
+}
+
+fn stub(kind: &str) -> Value {
+    json!({"kind": kind, "size": 0, "fields": {}})
+}
+
+#[test]
+fn fwd_declarations_are_undefined_without_stubs() {
+    let isf = generate_isf(false);
+    assert!(isf.check_user_types().is_err());
+}
+
+#[test]
+fn fwd_stubs() {
+    let isf = generate_isf(true);
+    assert!(isf.check_user_types().is_ok());
+
+    let user_types = &serde_json::to_value(&isf).unwrap()["user_types"];
+    assert_eq!(user_types["opaque"], stub("struct"));
+    assert_eq!(user_types["opaque_t"], stub("struct"));
+    assert_eq!(user_types["uopaque"], stub("union"));
+    assert_eq!(user_types["real"]["size"], json!(4));
+    assert!(user_types["real"]["fields"]["a"].is_object());
+}
+
+#[test]
+fn fwd_stubs_only_add_types() {
+    let mut without = serde_json::to_value(generate_isf(false)).unwrap();
+    let mut with = serde_json::to_value(generate_isf(true)).unwrap();
+
+    let added: Vec<String> = {
+        let with = with["user_types"].as_object_mut().unwrap();
+        let without = without["user_types"].as_object().unwrap();
+        let added = with
+            .keys()
+            .filter(|name| !without.contains_key(*name))
+            .cloned()
+            .collect();
+        with.retain(|name, _| without.contains_key(name));
+        added
+    };
+    assert_eq!(added, ["opaque", "opaque_t", "uopaque"]);
+
+    without["metadata"] = Value::Null;
+    with["metadata"] = Value::Null;
+    assert_eq!(with, without);
+}
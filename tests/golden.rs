@@ -0,0 +1,143 @@
+//! End-to-end tests that compare generated ISF files against golden files.
+//!
+//! Each case is a directory in `tests/data` that contains the xz-compressed
+//! BTF section (`vmlinux.btf.xz`) and System.map (`System.map.xz`) of a real
+//! kernel, its banner (`banner`), and the expected ISF file
+//! (`expected.json.xz`).
+//!
+//! To keep the repository small, the kernel inputs are trimmed: the BTF only
+//! keeps the types that are reachable from a few core structs (`task_struct`,
+//! `mm_struct`, `file`, `module`, `cred`, ...), the structs of the data
+//! symbols, all variables and a few functions. Structs that are only reached
+//! through pointers are replaced by forward declarations. The System.map only
+//! keeps the data symbols and the symbols of these functions.
+//!
+//! Set `BTF2JSON_UPDATE_GOLDEN=1` to overwrite the golden files with the
+//! current output instead of comparing against them.
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+const UPDATE_VAR: &str = "BTF2JSON_UPDATE_GOLDEN";
+const GOLDEN_NAME: &str = "expected.json.xz";
+/// Maximum number of differences that are reported.
+const MAX_DIFFS: usize = 20;
+
+fn data_dir(case: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+        .join(case)
+}
+
+/// Decompresses `name.xz` from `src` into `dst` and returns the path.
+fn unpack(src: &Path, dst: &Path, name: &str) -> PathBuf {
+    let mut data = Vec::new();
+    XzDecoder::new(File::open(src.join(format!("{}.xz", name))).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    let path = dst.join(name);
+    fs::write(&path, data).unwrap();
+    path
+}
+
+/// Runs the full generation pipeline on the inputs of `case`.
+fn generate(case: &str) -> Value {
+    let src = data_dir(case);
+    let dst = env::temp_dir().join(format!("btf2json-golden-{}-{}", case, std::process::id()));
+    fs::create_dir_all(&dst).unwrap();
+    let btf = unpack(&src, &dst, "vmlinux.btf");
+    let map = unpack(&src, &dst, "System.map");
+    let banner = fs::read_to_string(src.join("banner")).unwrap();
+
+    let mut isf = common::generate_isf(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        banner.trim_end(),
+    ]);
+    // Like the binary, do not fail if symbol types are broken.
+    let _ = isf.fix_symbol_types();
+    let value = serde_json::to_value(&isf).unwrap();
+
+    fs::remove_dir_all(&dst).unwrap();
+    value
+}
+
+/// Collects the JSON paths at which `expected` and `actual` differ.
+fn diff(path: &str, expected: &Value, actual: &Value, diffs: &mut Vec<String>) {
+    if diffs.len() >= MAX_DIFFS {
+        return;
+    }
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, ev) in e {
+                let sub = format!("{}/{}", path, key);
+                match a.get(key) {
+                    Some(av) => diff(&sub, ev, av, diffs),
+                    None => diffs.push(format!("{}: missing", sub)),
+                }
+            }
+            for key in a.keys().filter(|k| !e.contains_key(*k)) {
+                diffs.push(format!("{}/{}: unexpected", path, key));
+            }
+        }
+        (Value::Array(e), Value::Array(a)) if e.len() == a.len() => {
+            for (idx, (ev, av)) in e.iter().zip(a).enumerate() {
+                diff(&format!("{}/{}", path, idx), ev, av, diffs);
+            }
+        }
+        _ if expected != actual => diffs.push(format!("{}: {} != {}", path, expected, actual)),
+        _ => {}
+    }
+}
+
+fn check_golden(case: &str) {
+    let actual = generate(case);
+    let golden = data_dir(case).join(GOLDEN_NAME);
+
+    if env::var_os(UPDATE_VAR).is_some_and(|v| v == "1") {
+        let mut encoder = XzEncoder::new(File::create(&golden).unwrap(), 9);
+        serde_json::to_writer(&mut encoder, &actual).unwrap();
+        encoder.finish().unwrap().flush().unwrap();
+        return;
+    }
+
+    let expected: Value = match File::open(&golden) {
+        Ok(file) => serde_json::from_reader(XzDecoder::new(file)).unwrap(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            panic!(
+                "{} does not exist, run with {}=1",
+                golden.display(),
+                UPDATE_VAR
+            )
+        }
+        Err(err) => panic!("Unable to open {}: {}", golden.display(), err),
+    };
+    let mut diffs = Vec::new();
+    diff("", &expected, &actual, &mut diffs);
+    diffs.truncate(MAX_DIFFS);
+    assert!(
+        diffs.is_empty(),
+        "Output differs from {} (first {} differences):\n{}\nRun with {}=1 to update.",
+        golden.display(),
+        MAX_DIFFS,
+        diffs.join("\n"),
+        UPDATE_VAR
+    );
+}
+
+#[test]
+fn linux_6_18() {
+    check_golden("linux-6.18");
+}
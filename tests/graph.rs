@@ -0,0 +1,80 @@
+//! Tests for `btf2json graph`.
+//!
+//! `tests/data/typedefs/aliases.btf` is described in `tests/typedef_aliases.rs`.
+//! The expected graphs are in `tests/data/graph`.
+//!
+//! Set `BTF2JSON_UPDATE_GOLDEN=1` to overwrite the expected graphs with the
+//! current output instead of comparing against them.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+const UPDATE_VAR: &str = "BTF2JSON_UPDATE_GOLDEN";
+
+fn data_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"))
+}
+
+fn graph(args: &[&str]) -> Output {
+    let btf = data_dir().join("typedefs/aliases.btf");
+    common::run(&[&["graph", "--btf", btf.to_str().unwrap()], args].concat())
+}
+
+/// Compares `dot` with the expected graph `name`.
+fn assert_snapshot(name: &str, dot: &str) {
+    let path = data_dir().join("graph").join(name);
+    if env::var_os(UPDATE_VAR).is_some() {
+        fs::write(&path, dot).unwrap();
+        return;
+    }
+    assert_eq!(dot, fs::read_to_string(&path).unwrap(), "{}", name);
+}
+
+#[test]
+fn rooted() {
+    let output = graph(&["--root", "user", "--depth", "1"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    let dot = String::from_utf8(output.stdout).unwrap();
+    assert!(dot.contains(r#""struct user" -> "struct list" [label="p"];"#));
+    // The dependencies of the types at the maximum depth are not followed.
+    assert!(!dot.contains(r#""base int""#));
+    assert_snapshot("user-depth1.dot", &dot);
+
+    let output = graph(&["--root", "user"]);
+    let dot = String::from_utf8(output.stdout).unwrap();
+    assert!(dot.contains(r#""enum mode" -> "base int";"#), "{}", dot);
+    assert!(!dot.contains(r#""base char""#), "{}", dot);
+}
+
+#[test]
+fn all_types() {
+    let path = env::temp_dir().join(format!("btf2json-graph-{}.dot", std::process::id()));
+    let output = graph(&["--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("warning: the graph of all types has 30 nodes and 11 edges"),
+        "{}",
+        stderr
+    );
+    let dot = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_snapshot("all.dot", &dot);
+}
+
+#[test]
+fn unknown_root() {
+    let output = graph(&["--root", "nonexistent"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Root type `nonexistent` is not defined"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,237 @@
+//! Tests for recovering symbols from the kallsyms tables of memory images.
+//!
+//! The tables are built like `scripts/kallsyms` does, with the tokens being
+//! the printable characters and a few multi-character tokens, for the layout
+//! since Linux 6.4, where the addresses follow `kallsyms_token_index`, and
+//! the one before, where they precede `kallsyms_num_syms`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use btf2json::btf::Endian;
+use btf2json::image::{self, KallsymsEntry};
+use btf2json::isf::Isf;
+use serde_json::json;
+
+/// Tokens of more than one character, at the indices that are not printable.
+const MULTI_TOKENS: [&str; 4] = ["sym_", "vfs_", "linux_", "_text"];
+/// `kallsyms_relative_base`, the address of `_stext`.
+const BASE: u64 = 0xffffffff8a000000;
+
+#[derive(Clone, Copy)]
+enum Addresses {
+    /// `kallsyms_offsets` relative to `kallsyms_relative_base`.
+    Relative,
+    /// Like `Relative`, but with CONFIG_KALLSYMS_ABSOLUTE_PERCPU.
+    AbsolutePercpu,
+    /// `kallsyms_addresses`.
+    Absolute,
+}
+
+fn tokens() -> Vec<String> {
+    (0..=255u8)
+        .map(|t| match t {
+            0..=3 => MULTI_TOKENS[t as usize].to_owned(),
+            b'!'..=b'~' => char::from(t).to_string(),
+            _ => format!("~{}", t),
+        })
+        .collect()
+}
+
+/// Compresses `name` with the tokens of [`tokens`].
+fn compress(name: &str) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut rest = name;
+    while !rest.is_empty() {
+        if let Some(t) = MULTI_TOKENS.iter().position(|t| rest.starts_with(t)) {
+            compressed.push(t as u8);
+            rest = &rest[MULTI_TOKENS[t].len()..];
+        } else {
+            compressed.push(rest.as_bytes()[0]);
+            rest = &rest[1..];
+        }
+    }
+    compressed
+}
+
+fn align(buf: &mut Vec<u8>) {
+    buf.resize(buf.len().next_multiple_of(8), 0);
+}
+
+/// Appends the kallsyms tables of the little endian 64-bit kernel with the
+/// `symbols` to `buf`, which must be aligned.
+fn kallsyms_tables(buf: &mut Vec<u8>, symbols: &[KallsymsEntry], old: bool, addrs: Addresses) {
+    let mut names = Vec::new();
+    let mut markers = Vec::new();
+    for (i, sym) in symbols.iter().enumerate() {
+        if i % 256 == 0 {
+            markers.push(names.len() as u32);
+        }
+        let compressed = compress(&format!("{}{}", sym.kind, sym.name));
+        if compressed.len() >= 0x80 {
+            names.push(0x80 | (compressed.len() & 0x7f) as u8);
+            names.push((compressed.len() >> 7) as u8);
+        } else {
+            names.push(compressed.len() as u8);
+        }
+        names.extend(compressed);
+    }
+    let mut table = Vec::new();
+    let mut index = Vec::new();
+    for token in tokens() {
+        index.extend((table.len() as u16).to_le_bytes());
+        table.extend(token.as_bytes());
+        table.push(0);
+    }
+    let mut addresses = Vec::new();
+    match addrs {
+        Addresses::Relative | Addresses::AbsolutePercpu => {
+            for sym in symbols {
+                let offset = match addrs {
+                    Addresses::AbsolutePercpu if sym.addr < BASE => sym.addr as i32,
+                    Addresses::AbsolutePercpu => -1 - (sym.addr - BASE) as i32,
+                    _ => (sym.addr - BASE) as i32,
+                };
+                addresses.extend(offset.to_le_bytes());
+            }
+            align(&mut addresses);
+            addresses.extend(BASE.to_le_bytes());
+        }
+        Addresses::Absolute => {
+            for sym in symbols {
+                addresses.extend(sym.addr.to_le_bytes());
+            }
+        }
+    }
+
+    if old {
+        buf.extend(&addresses);
+        align(buf);
+        buf.extend((symbols.len() as u64).to_le_bytes());
+    } else {
+        buf.extend((symbols.len() as u32).to_le_bytes());
+    }
+    for part in [
+        names,
+        markers.iter().flat_map(|m| m.to_le_bytes()).collect(),
+        table,
+        index,
+    ] {
+        align(buf);
+        buf.extend(part);
+    }
+    if !old {
+        align(buf);
+        buf.extend(&addresses);
+    }
+}
+
+fn entry(addr: u64, kind: char, name: &str) -> KallsymsEntry {
+    KallsymsEntry {
+        name: name.to_owned(),
+        kind,
+        addr,
+    }
+}
+
+/// Returns 600 symbols, starting with `_stext` at [`BASE`].
+fn symbols() -> Vec<KallsymsEntry> {
+    let mut symbols = vec![
+        entry(BASE, 'T', "_stext"),
+        entry(BASE + 0x100, 'T', "vfs_read"),
+    ];
+    symbols.extend((0..596).map(|i| entry(BASE + 0x200 + 0x10 * i, 't', &format!("sym_{}", i))));
+    // Names of 128 tokens and more have a two-byte length since Linux 6.1.
+    symbols.push(entry(BASE + 0x10000, 't', &"x".repeat(200)));
+    symbols.push(entry(BASE + 0x1000100, 'D', "linux_banner"));
+    symbols
+}
+
+fn image(symbols: &[KallsymsEntry], old: bool, addrs: Addresses) -> Vec<u8> {
+    let mut image = b"junk before the tables\0".to_vec();
+    align(&mut image);
+    kallsyms_tables(&mut image, symbols, old, addrs);
+    image.extend([0xaa; 64]);
+    image
+}
+
+#[test]
+fn relative_offsets() {
+    let symbols = symbols();
+    for old in [false, true] {
+        let image = image(&symbols, old, Addresses::Relative);
+        assert_eq!(
+            image::kallsyms(&image, Endian::Little, 8),
+            Some(symbols.clone())
+        );
+    }
+}
+
+#[test]
+fn absolute_percpu() {
+    let mut symbols = symbols();
+    symbols.insert(0, entry(0x40, 'D', "runqueues"));
+    for old in [false, true] {
+        let image = image(&symbols, old, Addresses::AbsolutePercpu);
+        assert_eq!(
+            image::kallsyms(&image, Endian::Little, 8),
+            Some(symbols.clone())
+        );
+    }
+}
+
+#[test]
+fn absolute_addresses() {
+    let symbols = symbols();
+    for old in [false, true] {
+        let image = image(&symbols, old, Addresses::Absolute);
+        assert_eq!(
+            image::kallsyms(&image, Endian::Little, 8),
+            Some(symbols.clone())
+        );
+    }
+}
+
+#[test]
+fn no_tables() {
+    let image = vec![0; 4096];
+    assert_eq!(image::kallsyms(&image, Endian::Little, 8), None);
+}
+
+#[test]
+fn image_only() {
+    // Uses the types of `tests/data/funcs/funcs.btf`, see `tests/functions.rs`.
+    let mut raw = fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/funcs/funcs.btf"
+    ))
+    .unwrap();
+    align(&mut raw);
+    raw.extend(b"Linux version 6.18.0-image (builder@host) (gcc 14.2.0) #1 SMP\n\0");
+    raw.extend(b"%s version %s (%s@%s)\n\0");
+    align(&mut raw);
+    kallsyms_tables(&mut raw, &symbols(), false, Addresses::Relative);
+
+    let path = common::write_temp("kallsyms.raw", raw);
+    // Raw images have no ELF header to tell the architecture.
+    let ctx = common::context(&["--image", path.to_str().unwrap(), "--arch", "x86_64"]);
+    let name = format!(
+        "{}.kallsyms",
+        Path::new(&path).file_name().unwrap().to_str().unwrap()
+    );
+    fs::remove_file(&path).unwrap();
+    let isf = serde_json::to_value(Isf::try_from(ctx.unwrap()).unwrap()).unwrap();
+
+    // Rebased on `_stext`, like kallsyms.
+    let symbols = &isf["symbols"];
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff81000000u64));
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xffffffff81000100u64));
+    assert_eq!(symbols["vfs_read"]["type"]["kind"], "function");
+    assert!(symbols["sym_595"].is_object());
+    let linux = &isf["metadata"]["linux"];
+    assert_eq!(linux["symbols"][0]["name"], name);
+    assert_eq!(linux["kernel_version"], "6.18.0-image");
+}
@@ -0,0 +1,137 @@
+//! Tests for the symdb of builds without the `embedded-symdb` feature, which
+//! is loaded at runtime, and for the embedded one ignoring it.
+//!
+//! The symbols are from `tests/data/funcs`, see `tests/symdb.rs`.
+//! `tests/data/symdb/custom.symdb` types `_stext` as `int`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde_json::{json, Value};
+#[cfg(not(feature = "embedded-symdb"))]
+use sha2::{Digest, Sha256};
+
+/// Returns an empty temporary directory for `name`.
+fn tmp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!(
+        "btf2json-installed-symdb-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Generates with `$XDG_DATA_HOME` at `data_home` and `$BTF2JSON_SYMDB` set
+/// to `symdb`, if given.
+fn generate(data_home: &Path, symdb: Option<&Path>) -> Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_btf2json"));
+    cmd.arg("--btf")
+        .arg(common::data("funcs/funcs.btf"))
+        .arg("--map")
+        .arg(common::data("funcs/System.map"))
+        .args(["--banner", "Linux version 6.18.0", "--arch", "x86_64"])
+        .env("XDG_DATA_HOME", data_home)
+        .env_remove("BTF2JSON_SYMDB");
+    if let Some(symdb) = symdb {
+        cmd.env("BTF2JSON_SYMDB", symdb);
+    }
+    cmd.output().unwrap()
+}
+
+fn isf(output: &Output) -> Value {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[cfg(feature = "embedded-symdb")]
+#[test]
+fn embedded_ignores_installed() {
+    let dir = tmp_dir("embedded");
+    let isf = isf(&generate(&dir, Some(&dir.join("missing.symdb"))));
+
+    assert_eq!(
+        isf["metadata"]["linux"]["symbols"][1]["name"],
+        json!("dummy.symdb")
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(not(feature = "embedded-symdb"))]
+#[test]
+fn installed_in_data_home() {
+    let dir = tmp_dir("data-home");
+    // Packages may install the symdb as a link.
+    fs::create_dir(dir.join("btf2json")).unwrap();
+    let real = dir.join("custom-6.18.symdb");
+    fs::copy(common::data("symdb/custom.symdb"), &real).unwrap();
+    std::os::unix::fs::symlink(&real, dir.join("btf2json/symdb")).unwrap();
+    let isf = isf(&generate(&dir, None));
+
+    assert_eq!(
+        isf["symbols"]["_stext"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+    let source = &isf["metadata"]["linux"]["symbols"][1];
+    assert_eq!(source["kind"], json!("symdb"));
+    assert_eq!(source["name"], json!("custom-6.18.symdb"));
+    assert_eq!(
+        source["hash_value"],
+        json!(format!("{:x}", Sha256::digest(fs::read(&real).unwrap())))
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(not(feature = "embedded-symdb"))]
+#[test]
+fn installed_from_environment() {
+    let dir = tmp_dir("environment");
+    let isf = isf(&generate(&dir, Some(&common::data("symdb/custom.symdb"))));
+    assert_eq!(
+        isf["metadata"]["linux"]["symbols"][1]["name"],
+        json!("custom.symdb")
+    );
+
+    // A symdb given by the environment must exist.
+    let output = generate(&dir, Some(&dir.join("missing.symdb")));
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid installed symdb"), "{}", stderr);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(not(feature = "embedded-symdb"))]
+#[test]
+fn not_installed() {
+    let dir = tmp_dir("missing");
+    let output = generate(&dir, None);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "warning: no symdb available, symbols without type in the BTF file will be \
+             untyped; use --symdb or install one at {}",
+            dir.join("btf2json/symdb").display()
+        )),
+        "{}",
+        stderr
+    );
+    // The warning is counted in the summary.
+    assert!(stderr.contains("problematic symbols"), "{}", stderr);
+    let isf = isf(&output);
+    assert_eq!(
+        isf["symbols"]["_stext"]["type"],
+        json!({"kind": "base", "name": "void"})
+    );
+    let sources = isf["metadata"]["linux"]["symbols"].as_array().unwrap();
+    assert!(sources.iter().all(|source| source["kind"] != "symdb"));
+    fs::remove_dir_all(dir).unwrap();
+}
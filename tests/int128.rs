@@ -0,0 +1,86 @@
+//! Tests of base types of more than 8 bytes on BTF that is built with
+//! `btf2json::testutil`.
+
+use btf2json::cli::Architecture;
+use btf2json::isf::Isf;
+use btf2json::testutil::BtfBuilder;
+use btf2json::GenerationOptions;
+use serde_json::{json, Value};
+
+/// ```c
+/// typedef unsigned __int128 __u128;
+/// enum wide { WIDE_A };                       // 8 bytes
+///
+/// struct kvm_xmm {
+///     __int128 a;
+///     unsigned __int128 b;
+///     __u128 c;
+///     long long unsigned int d;
+///     long long unsigned int xmm[2] __aligned(16);
+/// };
+/// ```
+fn generate() -> Isf {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let u64_ = btf.int("long long unsigned int", 8, false);
+    let s128 = btf.int("__int128", 16, true);
+    let u128_ = btf.int("__int128 unsigned", 16, false);
+    let typedef = btf.typedef_("__u128", u128_);
+    btf.enum_("wide", 8, &[("WIDE_A", 0)]);
+    let xmm = btf.array(u64_, int, 2);
+    btf.struct_(
+        "kvm_xmm",
+        80,
+        &[
+            ("a", s128, 0),
+            ("b", u128_, 128),
+            ("c", typedef, 256),
+            ("d", u64_, 384),
+            ("xmm", xmm, 512),
+        ],
+    );
+    GenerationOptions::new()
+        .btf_bytes("int128.btf", btf.bytes())
+        .banner("Linux version 6.18.0")
+        .arch(Architecture::X86_64)
+        .types_only(true)
+        .generate()
+        .unwrap()
+}
+
+#[test]
+fn sizes() {
+    let isf: Value = serde_json::from_str(&generate().to_string()).unwrap();
+    let base_types = &isf["base_types"];
+    assert_eq!(base_types["__int128"]["size"], 16);
+    assert_eq!(base_types["__int128"]["signed"], true);
+    assert_eq!(base_types["__int128 unsigned"]["size"], 16);
+    assert_eq!(base_types["__int128 unsigned"]["signed"], false);
+    // The other spelling is an alias.
+    assert_eq!(
+        base_types["unsigned __int128"],
+        base_types["__int128 unsigned"]
+    );
+
+    // An enum of 8 bytes is not based on a 16-byte int.
+    assert_eq!(isf["enums"]["wide"]["size"], 8);
+    assert_eq!(isf["enums"]["wide"]["base"], "long long unsigned int");
+
+    let fields = &isf["user_types"]["kvm_xmm"]["fields"];
+    assert_eq!(isf["user_types"]["kvm_xmm"]["size"], 80);
+    assert_eq!(
+        fields["a"]["type"],
+        json!({"kind": "base", "name": "__int128"})
+    );
+    assert_eq!(fields["b"]["offset"], 16);
+    assert_eq!(fields["c"]["type"]["kind"], "base");
+    assert_eq!(fields["xmm"]["offset"], 64);
+    assert_eq!(fields["xmm"]["type"]["count"], 2);
+}
+
+#[test]
+fn checks() {
+    let isf = generate();
+    isf.check_user_types().unwrap();
+    isf.check_layout().unwrap();
+}
@@ -0,0 +1,77 @@
+//! Tests for recording the effective configuration with
+//! `--record-invocation`.
+//!
+//! The inputs are `tests/data/funcs`, see `tests/functions.rs`.
+
+use std::path::Path;
+
+use btf2json::cli::Cli;
+use btf2json::isf::Isf;
+use btf2json::GenerationContext;
+use clap::Parser;
+use serde_json::{json, Value};
+
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+
+fn producer(args: &[&str]) -> Value {
+    let (btf, map) = (
+        format!("{}/funcs.btf", FUNCS),
+        format!("{}/System.map", FUNCS),
+    );
+    let mut cli = vec![
+        "btf2json",
+        "--btf",
+        &btf,
+        "--map",
+        &map,
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    cli.extend(args);
+    let ctx = GenerationContext::try_from(&Cli::parse_from(cli)).unwrap();
+    let isf = serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap();
+    isf["metadata"]["producer"].clone()
+}
+
+#[test]
+fn not_recorded_by_default() {
+    assert!(producer(&[]).get("invocation").is_none());
+}
+
+#[test]
+fn basenames() {
+    let producer = producer(&[
+        "--record-invocation",
+        "--symbol-filter",
+        "^vfs_",
+        "--rich-functions",
+        "--dup-symbols",
+        "first",
+    ]);
+
+    assert_eq!(
+        producer["invocation"],
+        json!({
+            "inputs": {"btf": "funcs.btf", "map": "System.map"},
+            // Detected from the address of `_stext`.
+            "arch": "x86_64",
+            "anchor": "_stext",
+            "base_offset": 0xffffffff81000000u64,
+            "filters": {"symbol-filter": "^vfs_"},
+            "options": ["rich-functions", "dup-symbols=first"],
+        })
+    );
+}
+
+#[test]
+fn full_paths() {
+    let producer = producer(&["--record-invocation=full", "--no-rebase"]);
+    let invocation = &producer["invocation"];
+
+    assert_eq!(
+        invocation["inputs"]["btf"],
+        Path::new(FUNCS).join("funcs.btf").to_str().unwrap()
+    );
+    assert!(invocation.get("anchor").is_none());
+    assert!(invocation.get("base_offset").is_none());
+}
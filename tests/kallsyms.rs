@@ -0,0 +1,127 @@
+//! Tests for kallsyms files as symbol source.
+//!
+//! `tests/data/kallsyms/kallsyms` lists the variables of
+//! `tests/data/vars/vars.btf` with a KASLR shift of `0x1a000000`, interleaved
+//! with module symbols. `init_module` is in two modules, `counters` in the
+//! kernel and a module. `tests/data/kallsyms/kallsyms.zero` is the same file
+//! read without permission to see the addresses.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use btf2json::isf::Isf;
+use btf2json::GenerationContext;
+use serde_json::{json, Value};
+
+fn context(kallsyms: &str, arch: &str, args: &[&str]) -> anyhow::Result<GenerationContext> {
+    let (btf, kallsyms) = (
+        common::data("vars/vars.btf"),
+        common::data("kallsyms").join(kallsyms),
+    );
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--kallsyms",
+        kallsyms.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        arch,
+    ];
+    Ok(common::context(&[&cli, args].concat())?)
+}
+
+fn generate(kallsyms: &str) -> Value {
+    // The architecture is detected from the address of `_stext`.
+    let ctx = context(kallsyms, "auto", &[]).unwrap();
+    serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap()
+}
+
+#[test]
+fn kallsyms_are_rebased() {
+    let isf = generate("kallsyms");
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff81000000u64));
+    assert_eq!(symbols["jiffies"]["address"], json!(0xffffffff82000000u64));
+    assert_eq!(
+        symbols["linux_banner"]["address"],
+        json!(0xffffffff82000100u64)
+    );
+    assert_eq!(
+        isf["metadata"]["linux"]["symbols"][0]["name"],
+        json!("kallsyms")
+    );
+}
+
+#[test]
+fn module_symbols_are_ignored() {
+    let isf = generate("kallsyms");
+    let symbols = &isf["symbols"];
+
+    assert!(symbols["init_module"].is_null());
+    assert!(symbols["__key.0"].is_null());
+    // The kernel symbol is not ambiguous with the module symbol.
+    assert_eq!(symbols["counters"]["address"], json!(0xffffffff82000008u64));
+    assert_eq!(symbols.as_object().unwrap().len(), 5);
+}
+
+#[test]
+fn zero_addresses_are_rejected() {
+    let err = context("kallsyms.zero", "x86_64", &[]).err().unwrap();
+
+    assert!(format!("{:#}", err).contains("All addresses in kallsyms are zero"));
+    assert!(format!("{:#}", err).contains("kptr_restrict"));
+}
+
+#[test]
+fn zero_addresses_are_allowed() {
+    let ctx = context("kallsyms.zero", "x86_64", &["--allow-zero-addresses"]).unwrap();
+    let isf = serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap();
+
+    assert!(isf["symbols"]["jiffies"].is_object());
+}
+
+#[test]
+fn zero_addresses_in_system_map() {
+    let vars = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars/vars.btf");
+    let error = |path: &Path| {
+        let err = common::context(&[
+            "--btf",
+            vars,
+            "--map",
+            path.to_str().unwrap(),
+            "--banner",
+            "Linux version 6.18.0",
+            "--arch",
+            "x86_64",
+        ])
+        .err();
+        fs::remove_file(path).unwrap();
+        err.map(|err| format!("{:#}", anyhow::Error::from(err)))
+    };
+
+    let zero = "0000000000000000 T _stext\n0000000000000000 D jiffies\n";
+    let err = error(&common::write_temp("zero.map", zero)).unwrap();
+    assert!(
+        err.contains("All addresses in btf2json-") && err.contains("kptr_restrict"),
+        "{}",
+        err
+    );
+
+    // Per-CPU symbols are at zero anyway.
+    let percpu = "0000000000000000 D fixed_percpu_data\nffffffff81000000 T _stext\n\
+                  ffffffff82000000 D linux_banner\n";
+    assert_eq!(error(&common::write_temp("percpu.map", percpu)), None);
+
+    let stext = "0000000000000000 T _stext\nffffffff82000000 D jiffies\n";
+    let err = error(&common::write_temp("stext.map", stext)).unwrap();
+    assert!(
+        err.contains("The address of _stext and likely all others in btf2json-"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,186 @@
+//! Tests for parsing kernel versions and for `--kernel-version`, which takes
+//! precedence over the version in the banner.
+//!
+//! The symbols are from `tests/data/funcs`, see `tests/symdb.rs`, whose
+//! `versioned.symdb` types `datafn` as `long` on kernels `>=5.15 <6.2` and as
+//! `char` on kernels `>=6.2`.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use btf2json::cli::{Architecture, Cli, Command};
+use btf2json::isf::Isf;
+use btf2json::symbols::Banner;
+use btf2json::version::KernelVersion;
+use btf2json::GenerationContext;
+use clap::Parser;
+use serde_json::{json, Value};
+
+fn version(s: &str) -> Option<KernelVersion> {
+    s.parse().ok()
+}
+
+#[test]
+fn parse() {
+    assert_eq!(version("6.1"), Some(KernelVersion::new(6, 1, 0)));
+    assert_eq!(version("5.15.148"), Some(KernelVersion::new(5, 15, 148)));
+    assert_eq!(
+        version("5.15.0-91-generic"),
+        Some(KernelVersion::new(5, 15, 0))
+    );
+    assert_eq!(version("6.2.0-rc3+"), Some(KernelVersion::new(6, 2, 0)));
+    assert_eq!(version("6.2-rc3"), Some(KernelVersion::new(6, 2, 0)));
+    assert_eq!(version("4.19.0.123"), Some(KernelVersion::new(4, 19, 0)));
+    for invalid in ["", "6", "6.", "custom", "v6.1", "-6.1", "99999999999.1"] {
+        assert_eq!(version(invalid), None, "{:?}", invalid);
+    }
+}
+
+#[test]
+fn compare_and_display() {
+    let rc = version("6.2.0-rc3").unwrap();
+    assert_eq!(rc, version("6.2").unwrap());
+    assert!(version("5.15.148").unwrap() < version("6.1").unwrap());
+    assert!(version("6.10").unwrap() > version("6.9.12").unwrap());
+    assert_eq!(rc.to_string(), "6.2.0");
+    assert_eq!(
+        KernelVersion::from_banner("Linux version 5.15.0-91-generic (buildd@lcy02)"),
+        Some(KernelVersion::new(5, 15, 0))
+    );
+    assert_eq!(KernelVersion::from_banner("Linux version custom"), None);
+}
+
+fn args(args: &[&str]) -> Cli {
+    Cli::parse_from(["btf2json", "generate"].iter().chain(args.iter()))
+}
+
+#[test]
+fn precedence() {
+    let banner = Banner::new("Linux version 6.1.0-18-amd64");
+    let mangled = Banner::new("Linux version custom-kernel");
+    for (cli, banner, expected) in [
+        (&[][..], Some(&banner), Some("6.1")),
+        (&[], Some(&mangled), None),
+        (&[], None, None),
+        (&["--kernel-version", "6.2"], Some(&banner), Some("6.2")),
+        (&["--kernel-version", "6.2"], Some(&mangled), Some("6.2")),
+        (
+            &["--kernel-version", "5.15.0-91-generic"],
+            None,
+            Some("5.15"),
+        ),
+    ] {
+        let Some(Command::Generate(generate)) = args(cli).command else {
+            unreachable!()
+        };
+        assert_eq!(
+            generate.kernel_version(banner),
+            expected.and_then(version),
+            "{:?} {:?}",
+            cli,
+            banner.map(ToString::to_string)
+        );
+    }
+}
+
+#[test]
+fn invalid_argument() {
+    let err = Cli::try_parse_from(["btf2json", "--kernel-version", "custom"])
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("Invalid kernel version: custom"), "{}", err);
+}
+
+fn datafn_type(banner: &str, kernel_version: Option<&str>) -> Value {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let btf = data.join("funcs/funcs.btf");
+    let map = data.join("funcs/System.map");
+    let symdb = data.join("symdb/versioned.symdb");
+    let mut args: Vec<&OsStr> = vec![
+        "btf2json".as_ref(),
+        "--btf".as_ref(),
+        btf.as_os_str(),
+        "--map".as_ref(),
+        map.as_os_str(),
+        "--symdb".as_ref(),
+        symdb.as_os_str(),
+        "--banner".as_ref(),
+        banner.as_ref(),
+        "--arch".as_ref(),
+        "x86_64".as_ref(),
+    ];
+    if let Some(version) = kernel_version {
+        args.extend([OsStr::new("--kernel-version"), OsStr::new(version)]);
+    }
+    let ctx = GenerationContext::try_from(&Cli::parse_from(args)).unwrap();
+    let isf = serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap();
+    isf["symbols"]["datafn"]["type"]["name"].clone()
+}
+
+#[test]
+fn symdb_versions() {
+    assert_eq!(datafn_type("custom kernel", None), json!("void"));
+    assert_eq!(datafn_type("custom kernel", Some("5.15")), json!("long"));
+    assert_eq!(
+        datafn_type("custom kernel", Some("6.2.0-rc3")),
+        json!("char")
+    );
+    assert_eq!(datafn_type("Linux version 6.1.0", None), json!("long"));
+    assert_eq!(
+        datafn_type("Linux version 6.1.0", Some("6.2")),
+        json!("char")
+    );
+}
+
+#[test]
+fn char_signedness() {
+    let char_kind = |version: Option<KernelVersion>| {
+        let mut options = btf2json::GenerationOptions::new()
+            .btf_bytes("char.btf", {
+                let mut btf = btf2json::testutil::BtfBuilder::new();
+                btf.int("char", 1, true);
+                btf.bytes()
+            })
+            .arch(Architecture::X86_64)
+            .banner("Linux version 6.1.0")
+            .types_only(true);
+        if let Some(version) = version {
+            options = options.kernel_version(version);
+        }
+        let isf: Value = serde_json::from_str(&options.generate().unwrap().to_string()).unwrap();
+        isf["base_types"]["char"]["signed"].clone()
+    };
+    assert_eq!(char_kind(None), json!(true));
+    assert_eq!(char_kind(Some(KernelVersion::new(6, 2, 0))), json!(false));
+}
+
+#[test]
+fn recorded_invocation() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/funcs");
+    let btf = data.join("funcs.btf");
+    let cli = Cli::parse_from([
+        "btf2json".as_ref(),
+        "--btf".as_ref(),
+        btf.as_os_str(),
+        "--banner".as_ref(),
+        "custom kernel".as_ref(),
+        "--kernel-version".as_ref(),
+        "6.2-rc3".as_ref(),
+        "--record-invocation".as_ref(),
+        "--types-only".as_ref(),
+        "--arch".as_ref(),
+        "x86_64".as_ref(),
+    ]);
+    let ctx = GenerationContext::try_from(&cli).unwrap();
+    let isf = serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap();
+    let options = &isf["metadata"]["producer"]["invocation"]["options"];
+    assert!(
+        options
+            .as_array()
+            .unwrap()
+            .contains(&json!("kernel-version=6.2.0")),
+        "{}",
+        options
+    );
+}
@@ -0,0 +1,67 @@
+//! Tests for checking that fields fit into their user types.
+//!
+//! `tests/data/bitfields/bitfields.btf` is described in `tests/bitfields.rs`,
+//! its `struct bits_kf1` has a size of 16 bytes, `tests/data/arrays/flex.btf`
+//! in `tests/arrays.rs`.
+
+mod common;
+
+use btf2json::isf::Isf;
+use serde_json::{json, Value};
+
+fn generate(btf: &str) -> Value {
+    common::generate(&["--btf", common::data(btf).to_str().unwrap(), "--types-only"])
+}
+
+fn problems(isf: &Value) -> Vec<String> {
+    let isf = Isf::from_reader(serde_json::to_vec(isf).unwrap().as_slice()).unwrap();
+    isf.layout_problems()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn consistent() {
+    for btf in ["bitfields/bitfields.btf", "arrays/flex.btf"] {
+        let isf = generate(btf);
+        assert!(problems(&isf).is_empty(), "{}", btf);
+        let isf = Isf::from_reader(serde_json::to_vec(&isf).unwrap().as_slice()).unwrap();
+        assert!(isf.check_layout().is_ok());
+    }
+}
+
+#[test]
+fn violations() {
+    let mut isf = generate("bitfields/bitfields.btf");
+    let fields = &mut isf["user_types"]["bits_kf1"]["fields"];
+    fields["c"]["offset"] = json!(20);
+    fields["e"]["type"]["bit_position"] = json!(16);
+    fields["f"]["type"] = json!({
+        "kind": "array",
+        "count": 2,
+        "subtype": {"kind": "base", "name": "unsigned int"},
+    });
+    isf["user_types"]["bits_kf0"]["kind"] = json!("union");
+
+    let problems = problems(&isf);
+    assert_eq!(
+        problems,
+        [
+            "[union bits_kf0::c] union member at offset 4",
+            "[union bits_kf0::d] union member at offset 8",
+            "[union bits_kf0::e] union member at offset 8",
+            "[union bits_kf0::f] union member at offset 12",
+            "[struct bits_kf1::c] offset 20 exceeds size 16",
+            "[struct bits_kf1::e] bits 16..36 exceed the 32 bits of its type",
+            "[struct bits_kf1::f] array of 2 elements of 4 bytes at offset 12 exceeds size 16",
+        ]
+    );
+
+    let isf = Isf::from_reader(serde_json::to_vec(&isf).unwrap().as_slice()).unwrap();
+    let err = isf.check_layout().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Layout verification failed: 7 fields do not fit into their user types"
+    );
+}
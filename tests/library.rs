@@ -0,0 +1,377 @@
+//! Tests for using the crate as a library, without a command line.
+//!
+//! The BTF files are described in the tests that generate ISF files from them.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use btf2json::btf::{Btf, Endian};
+use btf2json::cli::{Architecture, Cli};
+use btf2json::isf::Isf;
+use btf2json::symbols::{Banner, SymbolKind, SymbolScope, Symbols, SymbolsBuilder};
+use btf2json::{ContextOptions, GenerationContext, GenerationOptions};
+use clap::Parser;
+use serde_json::Value;
+
+const TINY_LE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_le.btf");
+const TINY_BE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_be.btf");
+const DUPS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/dups/dups.btf");
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+const VARS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars");
+
+#[test]
+fn from_file() {
+    let btf = Btf::from_file(Path::new(TINY_LE)).unwrap();
+    assert_eq!(btf.name(), "tiny_le.btf");
+    assert_eq!(btf.endian, Endian::Little);
+    assert_eq!(&btf.raw()[..], fs::read(TINY_LE).unwrap());
+    assert_eq!(btf.section(), &btf.raw()[..]);
+}
+
+#[test]
+fn from_bytes_matches_from_file() {
+    for path in [TINY_LE, TINY_BE] {
+        let from_file = Btf::from_file(Path::new(path)).unwrap();
+        let from_bytes = Btf::from_bytes("tiny", fs::read(path).unwrap()).unwrap();
+        assert_eq!(from_bytes.name(), "tiny");
+        assert_eq!(from_bytes.endian, from_file.endian);
+        assert_eq!(from_bytes.nr_types(), from_file.nr_types());
+        assert_eq!(from_bytes.section_range(), from_file.section_range());
+        // IDs are tied to their `Btf`, compare their numbers.
+        let user_ids = |btf: &Btf| {
+            let user_ids = btf.gen_vol_id_sets().unwrap().0;
+            user_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(user_ids(&from_bytes), user_ids(&from_file));
+    }
+}
+
+#[test]
+fn from_bytes_rejects_garbage() {
+    let err = Btf::from_bytes("garbage", vec![0x42; 64]).err().unwrap();
+    assert!(
+        err.to_string().contains("neither .BTF section nor ELF"),
+        "{:#}",
+        err
+    );
+}
+
+#[test]
+fn types_in_id_order() {
+    let btf = Btf::from_file(Path::new(DUPS)).unwrap();
+    let ids: Vec<String> = btf.types().map(|tx| tx.id.to_string()).collect();
+    let expected: Vec<String> = (0..btf.nr_types()).map(|id| id.to_string()).collect();
+    assert_eq!(ids, expected);
+    assert_eq!(btf.types().next().unwrap().t.t.name(), "void");
+}
+
+#[test]
+fn structs_and_find_by_name() {
+    let btf = Btf::from_file(Path::new(DUPS)).unwrap();
+    let structs: Vec<String> = btf
+        .structs()
+        .map(|tx| btf.get_strtab_entry_by_id(tx.id).unwrap().to_string())
+        .collect();
+    assert_eq!(structs, ["dup", "dup", "tie", "tie", "same", "same"]);
+
+    let dups: Vec<String> = btf
+        .find_by_name("dup")
+        .iter()
+        .map(|tx| tx.id.to_string())
+        .collect();
+    assert_eq!(dups, ["3", "4"]);
+    assert_eq!(btf.find_by_name("color").len(), 2);
+    assert!(btf.find_by_name("missing").is_empty());
+}
+
+/// Returns the symbols of `tests/data/funcs`, see `tests/functions.rs`.
+fn funcs_symbols() -> Symbols {
+    let cli = Cli::parse_from([
+        "btf2json",
+        "--btf",
+        &format!("{}/funcs.btf", FUNCS),
+        "--map",
+        &format!("{}/System.map", FUNCS),
+        "--banner",
+        "Linux version 6.18.0",
+    ]);
+    let args = cli.generate_args();
+    let btf = Btf::try_from(args).unwrap();
+    SymbolsBuilder::try_from((args, Architecture::X86_64, &btf))
+        .unwrap()
+        .build()
+}
+
+#[test]
+fn symbols_lookup() {
+    let symbols = funcs_symbols();
+    assert_eq!(symbols.len(), 9);
+    assert!(!symbols.is_empty());
+
+    let do_init = symbols.get("do_init").unwrap();
+    assert_eq!(do_init.address(), 0xffffffff81000200);
+    assert_eq!(do_init.kind(), SymbolKind::T);
+    assert_eq!(do_init.scope(), SymbolScope::Local);
+    let weakfn = symbols.get("weakfn").unwrap();
+    assert_eq!(weakfn.kind(), SymbolKind::W);
+    assert_eq!(weakfn.scope(), SymbolScope::Global);
+    assert_eq!(symbols.get("both").unwrap().kind(), SymbolKind::D);
+    assert!(symbols.get("nomap").is_none());
+}
+
+#[test]
+fn symbols_by_address() {
+    let symbols = funcs_symbols();
+    assert_eq!(
+        symbols.sym_name_from_addr(0xffffffff81000100),
+        Some("vfs_read")
+    );
+    assert_eq!(
+        symbols.sym_name_from_addr(0xffffffff82000100),
+        Some("linux_banner")
+    );
+    assert_eq!(symbols.sym_name_from_addr(0xffffffff81000101), None);
+    assert_eq!(
+        symbols.sym_addr_from_name("vfs_read"),
+        Some(0xffffffff81000100)
+    );
+}
+
+#[test]
+fn symbols_iter() {
+    let symbols = funcs_symbols();
+    let mut names: Vec<&str> = symbols.iter().map(|(name, _)| name).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "_stext",
+            "both",
+            "datafn",
+            "do_init",
+            "helper",
+            "linux_banner",
+            "twice",
+            "vfs_read",
+            "weakfn"
+        ]
+    );
+    for (name, sym) in symbols.iter() {
+        assert_eq!(symbols.sym_addr_from_name(name), Some(sym.address()));
+    }
+}
+
+#[test]
+fn generate_from_memory() {
+    let banner = "Linux version 6.18.0";
+    let isf = GenerationOptions::new()
+        .btf_bytes("vars.btf", fs::read(format!("{}/vars.btf", VARS)).unwrap())
+        .map_bytes(
+            "System.map",
+            fs::read(format!("{}/System.map", VARS)).unwrap(),
+        )
+        .banner(banner)
+        .arch(Architecture::X86_64)
+        .generate()
+        .unwrap();
+    let json: Value = serde_json::from_str(&isf.to_string()).unwrap();
+    assert_eq!(json["symbols"]["jiffies"]["address"], 0xffffffff82000000u64);
+
+    // The same as generating from the files with the CLI.
+    let cli = Cli::parse_from([
+        "btf2json",
+        "--btf",
+        &format!("{}/vars.btf", VARS),
+        "--map",
+        &format!("{}/System.map", VARS),
+        "--banner",
+        banner,
+        "--arch",
+        "x86_64",
+    ]);
+    let from_cli = Isf::try_from(GenerationContext::try_from(&cli).unwrap()).unwrap();
+    assert_eq!(json, serde_json::to_value(&from_cli).unwrap());
+}
+
+#[test]
+fn context_from_parts() {
+    let path = format!("{}/funcs.btf", FUNCS);
+    let btf = Btf::from_file(Path::new(&path)).unwrap();
+    let options = || ContextOptions::new().arch(Architecture::X86_64);
+    let ctx = GenerationContext::new(btf, funcs_symbols(), options()).unwrap();
+    let isf = Isf::try_from(ctx).unwrap();
+    let json = serde_json::to_value(&isf).unwrap();
+    assert_eq!(
+        json["symbols"]["vfs_read"]["address"],
+        0xffffffff81000100u64
+    );
+    assert!(json["user_types"].get("file").is_some());
+
+    // The same with the partitioning of the types computed beforehand.
+    let btf = Btf::from_file(Path::new(&path)).unwrap();
+    let id_sets = btf.gen_vol_id_sets().unwrap();
+    let ctx = GenerationContext::with_id_sets(btf, funcs_symbols(), id_sets, options()).unwrap();
+    let isf = Isf::try_from(ctx).unwrap();
+    assert_eq!(json, serde_json::to_value(&isf).unwrap());
+
+    // Raw BTF has no architecture to fall back to.
+    let btf = Btf::from_file(Path::new(&path)).unwrap();
+    assert!(GenerationContext::new(btf, funcs_symbols(), ContextOptions::new()).is_err());
+
+    // The architecture and banner are those of the options.
+    let btf = Btf::from_file(Path::new(&path)).unwrap();
+    let options = ContextOptions::new()
+        .arch(Architecture::X86)
+        .banner(Banner::new("Linux version 6.18.0"));
+    let ctx = GenerationContext::new(btf, Symbols::default(), options).unwrap();
+    assert_eq!(ctx.banner().unwrap().to_string(), "Linux version 6.18.0\n");
+    let json = serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap();
+    assert_eq!(json["base_types"]["pointer"]["size"], 4);
+}
+
+#[test]
+fn generate_types_only_from_file() {
+    let isf = GenerationOptions::new()
+        .btf_file(TINY_LE)
+        .types_only(true)
+        .typedef_aliases(false)
+        .generate()
+        .unwrap();
+    let mut written = Vec::new();
+    isf.to_writer(&mut written).unwrap();
+    assert_eq!(written, isf.to_string().into_bytes());
+    assert_eq!(written, isf.to_vec().unwrap());
+
+    let json: Value = serde_json::from_slice(&written).unwrap();
+    assert!(json["user_types"].get("task").is_some());
+    assert!(json["user_types"].get("task_t").is_none());
+    assert!(json["symbols"].as_object().unwrap().is_empty());
+}
+
+#[test]
+fn accessors_match_json() {
+    let isf = GenerationOptions::new()
+        .btf_bytes("vars.btf", fs::read(format!("{}/vars.btf", VARS)).unwrap())
+        .map_file(format!("{}/System.map", VARS))
+        .banner("Linux version 6.18.0")
+        .arch(Architecture::X86_64)
+        .generate()
+        .unwrap();
+    let json: Value = serde_json::from_slice(&isf.to_vec().unwrap()).unwrap();
+
+    assert_eq!(
+        isf.user_types().len(),
+        json["user_types"].as_object().unwrap().len()
+    );
+    for (name, user) in isf.user_types() {
+        let expected = &json["user_types"][name];
+        assert_eq!(user.kind().as_str(), expected["kind"]);
+        assert_eq!(user.size(), expected["size"]);
+        assert_eq!(
+            user.fields().len(),
+            expected["fields"].as_object().unwrap().len()
+        );
+        for (field_name, field) in user.fields() {
+            let expected = &expected["fields"][field_name];
+            assert_eq!(field.offset(), expected["offset"]);
+            assert_eq!(field.is_anonymous(), expected["anonymous"]);
+            assert_eq!(
+                serde_json::to_value(field.type_descr()).unwrap(),
+                expected["type"]
+            );
+            assert!(user.get_field(field_name).is_some());
+        }
+    }
+    for (name, e) in isf.enums() {
+        let expected = &json["enums"][name];
+        assert_eq!(e.base(), expected["base"].as_str().unwrap());
+        for (constant, value) in expected["constants"].as_object().unwrap() {
+            assert_eq!(e.get_constant(constant), value.as_i64().map(i128::from));
+        }
+        assert!(isf.get_enum(name).is_some());
+    }
+    for (name, base) in isf.base_types() {
+        let expected = &json["base_types"][name];
+        assert_eq!(base.size(), expected["size"]);
+        assert_eq!(base.is_signed(), expected["signed"]);
+        assert_eq!(serde_json::to_value(base.kind()).unwrap(), expected["kind"]);
+        assert_eq!(
+            serde_json::to_value(base.endian()).unwrap(),
+            expected["endian"]
+        );
+        assert!(isf.get_base_type(name).is_some());
+    }
+    assert_eq!(
+        isf.symbols().len(),
+        json["symbols"].as_object().unwrap().len()
+    );
+    for (name, sym) in isf.symbols() {
+        let expected = &json["symbols"][name];
+        assert_eq!(sym.address(), expected["address"]);
+        assert_eq!(
+            serde_json::to_value(sym.type_descr()).unwrap(),
+            expected["type"]
+        );
+    }
+    assert!(isf.get_symbol("jiffies").is_some());
+    assert!(isf.get_symbol("missing").is_none());
+}
+
+/// Writer that accepts `left` bytes, then fails like a pipe without reader.
+struct FailingWriter {
+    left: usize,
+}
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let n = buf.len().min(self.left);
+        self.left -= n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn serialization_into_failing_writer() {
+    let isf = GenerationOptions::new()
+        .btf_file(TINY_LE)
+        .types_only(true)
+        .generate()
+        .unwrap();
+    let json = isf.to_vec().unwrap();
+    let len = json.len();
+    assert_eq!(isf.to_json_string().unwrap().as_bytes(), json);
+    assert_eq!(isf.to_string().as_bytes(), json);
+    assert_eq!(isf.summary(), "base 22, enum 1, user 3, symbol 0");
+
+    for left in [0, 1, len / 2, len] {
+        let err = isf.to_writer(FailingWriter { left }).err();
+        assert_eq!(err.is_some(), left < len, "{}", left);
+        // The newline does not fit.
+        let err = isf.dump_to(FailingWriter { left }).unwrap_err();
+        let err = err.downcast_ref::<io::Error>().map_or_else(
+            || {
+                err.downcast_ref::<serde_json::Error>()
+                    .unwrap()
+                    .io_error_kind()
+            },
+            |err| Some(err.kind()),
+        );
+        assert_eq!(err, Some(io::ErrorKind::BrokenPipe));
+    }
+}
+
+#[test]
+fn generate_requires_btf() {
+    assert!(GenerationOptions::new()
+        .types_only(true)
+        .generate()
+        .is_err());
+}
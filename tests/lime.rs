@@ -0,0 +1,119 @@
+//! Tests for LiME memory images.
+//!
+//! `tests/data/lime/two_ranges.lime` has two ranges of physical memory:
+//!
+//! | Physical address | Size  | Contents                                      |
+//! |------------------|-------|-----------------------------------------------|
+//! | 0x1000           | 0x100 | the banner at 0x1080                          |
+//! | 0x100000         | 0x200 | `tests/data/endian/tiny_le.btf` at 0x100010   |
+//!
+//! Each range is preceded by its 32 byte header, so the BTF section is at the
+//! file offset 0x150.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::prelude::*;
+use btf2json::lime::{self, Lime};
+use serde_json::{json, Value};
+
+const BANNER: &str = "Linux version 6.18.0 (btf2json@test) #1 SMP\n";
+
+fn data(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/lime")
+        .join(path)
+}
+
+fn two_ranges() -> Vec<u8> {
+    fs::read(data("two_ranges.lime")).unwrap()
+}
+
+fn generate(image: &Path) -> Value {
+    let map = data("System.map");
+    common::generate(&[
+        "--image",
+        image.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--arch",
+        "x86_64",
+    ])
+}
+
+#[test]
+fn address_translation() {
+    let raw = two_ranges();
+    assert!(lime::is_lime(&raw));
+    let lime = Lime::parse(&raw).unwrap();
+
+    assert_eq!(lime.segments(), [32..288, 320..832]);
+    assert_eq!(lime.phys_addr(0x150), Some(0x100010));
+    assert_eq!(lime.phys_addr(0x120), None);
+    assert_eq!(
+        lime.read_phys(&raw, 0x1080, BANNER.len()),
+        Some(BANNER.as_bytes())
+    );
+    assert_eq!(
+        lime.read_phys(&raw, 0x100010, 3),
+        Some(&[0x9f, 0xeb, 0x01][..])
+    );
+    // Reads end with the range, and the gap between the ranges is not mapped.
+    assert_eq!(lime.read_phys(&raw, 0x10ff, 16), Some(&[0][..]));
+    assert_eq!(lime.read_phys(&raw, 0x1100, 1), None);
+    assert_eq!(lime.read_phys(&raw, 0x100200, 1), None);
+}
+
+#[test]
+fn truncated_last_range() {
+    let raw = two_ranges();
+    let lime = Lime::parse(&raw[..0x300]).unwrap();
+    assert_eq!(lime.segments(), [32..288, 320..0x300]);
+    assert_eq!(lime.read_phys(&raw, 0x1001d0, 1), None);
+
+    // An image that ends in a header has no range for it.
+    let mut partial = raw.clone();
+    partial.extend(&raw[..16]);
+    let lime = Lime::parse(&partial).unwrap();
+    assert_eq!(lime.segments(), [32..288, 320..832]);
+
+    let mut invalid = raw.clone();
+    invalid[288] = 0;
+    let err = Lime::parse(&invalid).err().unwrap();
+    assert_eq!(err.to_string(), "Invalid LiME header at 0x120");
+}
+
+#[test]
+fn generate_from_lime() {
+    let isf = generate(&data("two_ranges.lime"));
+
+    assert_eq!(isf["user_types"]["task"]["size"], json!(40));
+    let banner = isf["symbols"]["linux_banner"]["constant_data"]
+        .as_str()
+        .unwrap();
+    assert_eq!(BASE64_STANDARD.decode(banner).unwrap(), BANNER.as_bytes());
+}
+
+#[test]
+fn padded_is_flat() {
+    // The padded format has the ranges at their physical addresses.
+    let raw = two_ranges();
+    let lime = Lime::parse(&raw).unwrap();
+    let mut padded = vec![0; 0x100200];
+    for (paddr, len) in [(0x1000, 0x100), (0x100000, 0x200)] {
+        padded[paddr..paddr + len]
+            .copy_from_slice(lime.read_phys(&raw, paddr as u64, len).unwrap());
+    }
+    assert!(!lime::is_lime(&padded));
+
+    let image = common::write_temp("lime-padded", &padded);
+    let isf = generate(&image);
+    fs::remove_file(&image).unwrap();
+    assert_eq!(
+        isf["user_types"],
+        generate(&data("two_ranges.lime"))["user_types"]
+    );
+}
@@ -0,0 +1,113 @@
+//! Tests for the linkage of symbols, which follows the case of the symbol type
+//! in the System.map, or the linkage of BTF functions.
+//!
+//! Uses `tests/data/vars`, whose System.map has `D jiffies` and
+//! `d stats_ptr`, `tests/data/funcs`, see `tests/functions.rs`, and BTF that is
+//! built with `btf2json::testutil`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use btf2json::cli::Architecture;
+use btf2json::testutil::{BtfBuilder, VOID};
+use btf2json::GenerationOptions;
+use serde_json::json;
+
+#[test]
+fn linkage_from_scope() {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    let isf = common::generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["jiffies"]["linkage"], json!("global"));
+    assert_eq!(symbols["stats_ptr"]["linkage"], json!("static"));
+    assert_eq!(symbols["linux_banner"]["linkage"], json!("global"));
+}
+
+#[test]
+fn linkage_from_btf_functions() {
+    let data = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs"));
+    let btf = data.join("funcs.btf");
+    // The case of the static do_init and the global vfs_read is swapped, like
+    // in kallsyms with other conventions.
+    let map = fs::read_to_string(data.join("System.map"))
+        .unwrap()
+        .replace(" T vfs_read", " t vfs_read")
+        .replace(" t do_init", " T do_init")
+        .replace(" D datafn", " d datafn");
+    let path = common::write_temp("linkage.map", map);
+    let isf = common::try_generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        path.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ]);
+    fs::remove_file(&path).unwrap();
+    let isf = isf.unwrap();
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["vfs_read"]["linkage"], json!("global"));
+    assert_eq!(symbols["do_init"]["linkage"], json!("static"));
+    // Symbols without a function in BTF keep their scope.
+    assert_eq!(symbols["datafn"]["linkage"], json!("static"));
+}
+
+/// BTF whose functions are all static, like the BTF of pahole, does not record
+/// their linkage, which is then the scope of their symbols.
+#[test]
+fn all_static_functions() {
+    let mut btf = BtfBuilder::new();
+    let proto = btf.func_proto(VOID, &[]);
+    btf.func("do_global", proto, false);
+    btf.func("do_static", proto, false);
+    let map = "\
+ffffffff81000000 T _stext
+ffffffff81000100 T do_global
+ffffffff81000200 t do_static
+ffffffff81000300 T other
+ffffffff82000000 D linux_banner
+";
+    let generate = |btf: &BtfBuilder| {
+        let isf = GenerationOptions::new()
+            .btf_bytes("funcs.btf", btf.bytes())
+            .map_bytes("System.map", map.as_bytes().to_vec())
+            .banner("Linux version 6.18.0")
+            .arch(Architecture::X86_64)
+            .generate()
+            .unwrap();
+        serde_json::to_value(isf).unwrap()
+    };
+    let isf = generate(&btf);
+    assert_eq!(
+        isf["symbols"]["do_global"]["type"]["kind"],
+        json!("function")
+    );
+    assert_eq!(isf["symbols"]["do_global"]["linkage"], json!("global"));
+    assert_eq!(isf["symbols"]["do_static"]["linkage"], json!("static"));
+
+    // A single global function with a symbol means that the BTF records the
+    // linkage.
+    btf.func("other", proto, true);
+    let isf = generate(&btf);
+    assert_eq!(isf["symbols"]["do_global"]["linkage"], json!("static"));
+    assert_eq!(isf["symbols"]["do_static"]["linkage"], json!("static"));
+}
@@ -0,0 +1,113 @@
+//! Tests for the `linux_banner` symbol that is added if no symbol source has
+//! it.
+//!
+//! The System.maps are the one of `tests/data/vars` without `linux_banner`.
+//! `tests/data/symtab/vmlinux` has `linux_banner` at `ffffffff82800100` in its
+//! symbol table, see `tests/symtab.rs`.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use base64::prelude::*;
+use serde_json::Value;
+
+const BANNER: &str = "Linux version 6.18.0";
+
+/// Writes the System.map of `tests/data/vars` without `linux_banner` to a
+/// temporary file, named after `test`.
+fn stripped_map(test: &str) -> PathBuf {
+    let map = fs::read_to_string(common::data("vars/System.map")).unwrap();
+    let path = common::write_temp(
+        &format!("linux-banner-{}.map", test),
+        map.lines()
+            .filter(|line| !line.ends_with(" linux_banner"))
+            .map(|line| format!("{}\n", line))
+            .collect::<String>(),
+    );
+    path
+}
+
+/// Returns the `linux_banner` symbol of the profile of `btf` with the
+/// stripped System.map and `args`.
+fn linux_banner(test: &str, btf: &str, args: &[&str]) -> Value {
+    let map = stripped_map(test);
+    let btf = common::data(btf);
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        BANNER,
+        "--arch",
+        "x86_64",
+        "--no-rebase",
+    ];
+    let isf = common::try_generate(&[&cli, args].concat());
+    fs::remove_file(&map).unwrap();
+    let isf = isf.unwrap();
+    let sym = isf["symbols"]["linux_banner"].clone();
+    let data = BASE64_STANDARD
+        .decode(sym["constant_data"].as_str().unwrap())
+        .unwrap();
+    assert_eq!(data, format!("{}\n", BANNER).as_bytes());
+    sym
+}
+
+#[test]
+fn from_symtab() {
+    // Over the address on the command line.
+    let sym = linux_banner(
+        "symtab",
+        "symtab/vmlinux",
+        &["--linux-banner-addr", "ffffffff82000100"],
+    );
+    assert_eq!(sym["address"], 0xffffffff82800100u64);
+}
+
+#[test]
+fn from_cli() {
+    let sym = linux_banner(
+        "cli",
+        "vars/vars.btf",
+        &["--linux-banner-addr", "ffffffff82000100"],
+    );
+    assert_eq!(sym["address"], 0xffffffff82000100u64);
+}
+
+#[test]
+fn at_zero() {
+    let sym = linux_banner("zero", "vars/vars.btf", &[]);
+    assert_eq!(sym["address"], 0);
+}
+
+#[test]
+fn warning() {
+    let map = stripped_map("warning");
+    let btf = common::data("vars/vars.btf");
+    let output = common::run(&[
+        "generate",
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        BANNER,
+        "--arch",
+        "x86_64",
+        "--log-format",
+        "json",
+        "--log-level",
+        "warn",
+    ]);
+    fs::remove_file(&map).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("\"code\":\"synthetic_linux_banner\""),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,186 @@
+//! Tests for `--log-format json`, see `tests/warnings.rs` for the warnings of
+//! `tests/data/fwd/fwd.btf`.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use btf2json::diagnostics;
+use serde_json::Value;
+
+const FWD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fwd/fwd.btf");
+
+fn run(args: &[&str]) -> Output {
+    let cli = [
+        "generate",
+        "--btf",
+        FWD,
+        "--types-only",
+        "--banner",
+        "Linux version 6.18.0",
+        "--log-format",
+        "json",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+/// Parses each line of standard error as a JSON object.
+fn events(output: &Output) -> Vec<Value> {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|err| panic!("{}: {}", err, line)))
+        .collect()
+}
+
+#[test]
+fn warnings() {
+    let output = run(&["--log-level", "warn"]);
+    assert!(output.status.success(), "{:?}", output);
+    let events = events(&output);
+    // The codes are documented with their phase.
+    for event in &events {
+        if event["code"].is_string() && event["code"] != "warning_summary" {
+            assert!(event["phase"].is_string(), "{}", event);
+        }
+    }
+
+    let fields: Vec<&Value> = events
+        .iter()
+        .filter(|event| event["code"] == "undefined_field_type")
+        .collect();
+    assert_eq!(fields.len(), 3, "{:?}", events);
+    for event in fields {
+        assert_eq!(event["level"], "warn");
+        assert_eq!(event["phase"], "types");
+        assert_eq!(event["type_name"], "user");
+        assert_eq!(event["kind"], "struct");
+        assert!(event["field"].is_string());
+        assert!(event["message"].is_string());
+    }
+    let stubs: Vec<&Value> = events
+        .iter()
+        .filter(|event| event["code"] == "undefined_type_stub")
+        .collect();
+    assert_eq!(stubs.len(), 2, "{:?}", events);
+    assert!(stubs.iter().any(|event| event["type_name"] == "opaque"));
+    assert!(events
+        .iter()
+        .any(|event| event["code"] == "failed_verification" && event["phase"] == "serialize"));
+
+    // The summary is the last object.
+    let summary = events.last().unwrap();
+    assert_eq!(summary["code"], "warning_summary");
+    assert_eq!(summary["total"], 6);
+    assert_eq!(summary["counts"]["undefined_fields"], 3);
+    assert_eq!(summary["counts"]["undefined_types"], 2);
+}
+
+#[test]
+fn warnings_as_errors() {
+    let output = run(&["--warnings-as-errors"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    assert!(output.stdout.is_empty());
+    let events = events(&output);
+    assert_eq!(events.last().unwrap()["code"], "warning_summary");
+}
+
+/// Errors that end the run are JSON objects as well.
+#[test]
+fn failing_runs() {
+    let output = run(&["--strict"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let errors: Vec<Value> = events(&output)
+        .into_iter()
+        .filter(|event| event["level"] == "error" && event["target"] == "btf2json")
+        .collect();
+    assert!(!errors.is_empty(), "{:?}", output);
+    assert!(errors.iter().any(|event| event["message"]
+        .as_str()
+        .unwrap()
+        .contains("undefined types")));
+
+    let output = run(&["--base-btf", "/nonexistent/vmlinux"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let last = events(&output).pop().unwrap();
+    assert_eq!(last["level"], "error");
+    assert!(
+        last["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Unable to gather information for ISF generation"),
+        "{}",
+        last
+    );
+}
+
+/// The deprecation warning of running without a subcommand is a JSON object.
+#[test]
+fn deprecated_invocation() {
+    let output = common::run(&["--btf", FWD, "--types-only", "--log-format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let events = events(&output);
+    assert_eq!(events[0]["level"], "warn");
+    assert!(events[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("deprecated"));
+}
+
+/// Warnings of an architecture of another byte order than the types have a
+/// code.
+#[test]
+fn arch_endian_mismatch() {
+    let output = run(&["--arch", "s390x", "--log-level", "warn"]);
+    assert!(output.status.success(), "{:?}", output);
+    let events = events(&output);
+    let event = events
+        .iter()
+        .find(|event| event["code"] == "arch_endian_mismatch")
+        .unwrap_or_else(|| panic!("{:?}", events));
+    assert_eq!(event["phase"], "symbols");
+    assert_eq!(event["endian"], "big");
+    assert_eq!(event["detected"], "little");
+}
+
+/// Returns the Rust files in `dir` and its subdirectories.
+fn sources(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(sources(&path));
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Warnings of the categories that are counted in the summary have a code
+/// that is documented with its phase.
+#[test]
+fn counted_warnings_have_codes() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    for path in sources(&src) {
+        let source = fs::read_to_string(&path).unwrap();
+        for (at, _) in source.match_indices("target: diagnostics::") {
+            let rest = &source[at..];
+            let rest = rest[rest.find(',').unwrap() + 1..].trim_start();
+            let code = rest
+                .strip_prefix("code = \"")
+                .and_then(|code| code.split('"').next())
+                .unwrap_or_else(|| {
+                    let line = source[..at].lines().count();
+                    panic!("{}:{} has no code", path.display(), line)
+                });
+            assert!(
+                diagnostics::phase(code).is_some(),
+                "{} of {} has no phase",
+                code,
+                path.display()
+            );
+        }
+    }
+}
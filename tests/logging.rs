@@ -0,0 +1,66 @@
+//! Tests for the log level and writing the log to a file.
+
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+fn run(args: &[&str]) -> Output {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    let cli = [
+        "generate",
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+#[test]
+fn log_file() {
+    let path = common::write_temp("log.log", "stale\n");
+
+    let output = run(&["--debug", "--log-file", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // The file is truncated and gets everything, standard error only warnings
+    // and errors.
+    assert!(!log.contains("stale"));
+    assert!(log.contains(" DEBUG "), "{}", log);
+    assert!(log.contains(" TRACE "), "{}", log);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains(" DEBUG ") && !stderr.contains(" INFO "));
+    assert!(!log.contains('\x1b'));
+}
+
+#[test]
+fn unopenable_log_file() {
+    let output = run(&["--log-file", "/nonexistent/btf2json.log"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Unable to open log file"), "{}", stderr);
+}
+
+#[test]
+fn log_level_overrides_flags() {
+    let output = run(&["--debug", "--log-level", "info"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(" INFO "), "{}", stderr);
+    assert!(!stderr.contains(" DEBUG "), "{}", stderr);
+
+    let output = run(&["--log-level", "off"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,83 @@
+//! Tests for System.map files in various formats.
+//!
+//! The maps in `tests/data/mapformats` list the symbols of
+//! `tests/data/funcs/System.map`:
+//!
+//! - `nm.map` with fields separated by multiple spaces and tabs,
+//! - `crlf.map` with Windows line endings and trailing blank lines,
+//! - `bom.map` with a byte order mark,
+//! - `extra.map` with a fourth field,
+//! - `tolerable.map` with 200 more symbols and one invalid line,
+//! - `invalid.map` with two invalid lines, the first being line 3.
+
+mod common;
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+fn generate(map: &Path) -> anyhow::Result<Value> {
+    let btf = common::data("funcs/funcs.btf");
+    let mut isf = common::try_generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ])?;
+    isf.as_object_mut().unwrap().remove("metadata");
+    Ok(isf)
+}
+
+fn generate_from(name: &str) -> anyhow::Result<Value> {
+    generate(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/mapformats")
+            .join(name),
+    )
+}
+
+fn reference() -> Value {
+    generate(Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/funcs/System.map"
+    )))
+    .unwrap()
+}
+
+#[test]
+fn tolerated_formats() {
+    let reference = reference();
+
+    for name in ["nm.map", "crlf.map", "bom.map", "extra.map"] {
+        assert_eq!(generate_from(name).unwrap(), reference, "{}", name);
+    }
+}
+
+#[test]
+fn few_invalid_lines_are_skipped() {
+    let isf = generate_from("tolerable.map").unwrap();
+    let symbols = isf["symbols"].as_object().unwrap();
+
+    assert_eq!(symbols.len(), 209);
+    assert_eq!(symbols["do_init"], reference()["symbols"]["do_init"]);
+    assert_eq!(
+        symbols["filler199"]["address"],
+        json!(0xffffffff830000c7u64)
+    );
+}
+
+#[test]
+fn invalid_lines_are_reported() {
+    let err = generate_from("invalid.map").unwrap_err();
+
+    assert!(
+        format!("{:#}", err)
+            .contains("2 of 11 lines of invalid.map are invalid, the first is line 3"),
+        "{:#}",
+        err
+    );
+}
@@ -0,0 +1,76 @@
+//! Tests for symbols synthesized from BTF variables without a System.map.
+//!
+//! `tests/data/mapless/vmlinux` is a hand-crafted, stripped vmlinux with the
+//! sections
+//!
+//! ```text
+//! .text           0xffffffff81000000
+//! .data           0xffffffff82000000
+//! .rodata         0xffffffff82800000
+//! .data..percpu   0x0
+//! .bss            0xffffffff83000000
+//! ```
+//!
+//! Its BTF declares the following variables:
+//!
+//! ```c
+//! unsigned long jiffies;      // .data + 0x10
+//! static int dup;             // .data + 0x18 and
+//! static int dup;             // .data + 0x20
+//! static int runqueues;       // .data..percpu + 0x40
+//! unsigned long counter;      // .bss + 0x8
+//! int nosec;                  // in a section that is not in the ELF file
+//! ```
+
+mod common;
+
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+fn generate(btf: &Path) -> anyhow::Result<Value> {
+    Ok(common::try_generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ])?)
+}
+
+#[test]
+fn symbols_from_btf_sections() {
+    let isf = generate(Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/mapless/vmlinux"
+    )))
+    .unwrap();
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["jiffies"]["address"], json!(0xffffffff82000010u64));
+    assert_eq!(symbols["counter"]["address"], json!(0xffffffff83000008u64));
+    assert_eq!(symbols["runqueues"]["address"], json!(0x40));
+    assert_eq!(
+        symbols["jiffies"]["type"],
+        json!({"kind": "base", "name": "long unsigned int"})
+    );
+    assert!(symbols["dup"].is_null());
+    assert!(symbols["nosec"].is_null());
+    // Without symbol table the banner is attached to a synthetic symbol.
+    assert_eq!(symbols["linux_banner"]["address"], json!(0));
+    assert_eq!(
+        symbols["linux_banner"]["constant_data"],
+        json!("TGludXggdmVyc2lvbiA2LjE4LjAK")
+    );
+}
+
+#[test]
+fn raw_btf_requires_symbol_source() {
+    let isf = generate(Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/endian/tiny_le.btf"
+    )));
+
+    assert!(isf.is_err());
+}
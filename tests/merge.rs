@@ -0,0 +1,108 @@
+//! Tests for merging symbol sources.
+//!
+//! The symbols are merged from `tests/data/merge/System.map`,
+//!
+//! ```text
+//! ffffffff82000010 D jiffies
+//! ffffffff83000010 B counter      // ffffffff83000008 in the symbol table
+//! ffffffff82000100 D maponly
+//! ```
+//!
+//! `tests/data/merge/kallsyms`,
+//!
+//! ```text
+//! ffffffff81400000 T _stext       // ffffffff81200000 in the symbol table
+//! 0000000000000000 t do_work      // zeroed, ffffffff81200100 in the symbol table
+//! ffffffff82400200 D kallonly
+//! ffffffff82000300 D maponly
+//! ```
+//!
+//! and the symbol table of `tests/data/symtab/vmlinux`, see `tests/symtab.rs`.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(no_rebase: bool) -> Value {
+    let btf = common::data("symtab/vmlinux");
+    let map = common::data("merge/System.map");
+    let kallsyms = common::data("merge/kallsyms");
+    let mut args = vec![
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--kallsyms",
+        kallsyms.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    if no_rebase {
+        args.push("--no-rebase");
+    }
+    common::generate(&args)
+}
+
+#[test]
+fn sources_are_merged() {
+    let isf = generate(true);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["maponly"]["address"], json!(0xffffffff82000100u64));
+    assert_eq!(symbols["kallonly"]["address"], json!(0xffffffff82400200u64));
+    assert_eq!(symbols["weak_fn"]["address"], json!(0xffffffff81200200u64));
+}
+
+#[test]
+fn precedence() {
+    let isf = generate(true);
+    let symbols = &isf["symbols"];
+
+    // System.map wins over the symbol table and kallsyms.
+    assert_eq!(symbols["counter"]["address"], json!(0xffffffff83000010u64));
+    assert_eq!(symbols["maponly"]["address"], json!(0xffffffff82000100u64));
+    // kallsyms wins over the symbol table.
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff81400000u64));
+}
+
+#[test]
+fn zero_addresses_are_filled() {
+    let isf = generate(true);
+
+    assert_eq!(
+        isf["symbols"]["do_work"]["address"],
+        json!(0xffffffff81200100u64)
+    );
+}
+
+#[test]
+fn rebased_on_stext_of_kallsyms() {
+    let isf = generate(false);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff81000000u64));
+    assert_eq!(symbols["kallonly"]["address"], json!(0xffffffff82000200u64));
+}
+
+#[test]
+fn all_sources_in_metadata() {
+    let isf = generate(true);
+    let sources: Vec<(&Value, &Value)> = isf["metadata"]["linux"]["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|source| (&source["kind"], &source["name"]))
+        .collect();
+
+    assert_eq!(
+        sources,
+        [
+            (&json!("system-map"), &json!("System.map")),
+            (&json!("system-map"), &json!("kallsyms")),
+            (&json!("symtab"), &json!("vmlinux")),
+            (&json!("symdb"), &json!("dummy.symdb")),
+        ]
+    );
+}
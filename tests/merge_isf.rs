@@ -0,0 +1,185 @@
+//! Tests for `btf2json merge`, on the profile of `tests/data/vars`, whose only
+//! user type is `struct stats { unsigned long hits; }`, and an extra file like
+//! the profile of a module of the same kernel.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use base64::prelude::*;
+use btf2json::cli::Prefer;
+use btf2json::isf::Isf;
+use btf2json::merge::Merged;
+use serde_json::{json, Value};
+
+const BANNER: &str = "Linux version 6.18.0";
+
+fn base() -> Value {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    common::generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        BANNER,
+        "--arch",
+        "x86_64",
+    ])
+}
+
+/// Returns the extra file, which redefines `stats` with `stats_size` and adds
+/// a type and a symbol.
+fn extra(stats_size: u64) -> Value {
+    let mut stats = base()["user_types"]["stats"].take();
+    stats["size"] = json!(stats_size);
+    let int = json!({"kind": "base", "name": "int"});
+    json!({
+        "metadata": {
+            "format": "6.2.0",
+            "producer": {"name": "btf2json", "version": "0.1.0"},
+            "linux": {
+                "symbols": [],
+                "types": [{
+                    "kind": "btf",
+                    "name": "vendor.ko",
+                    "hash_type": "sha256",
+                    "hash_value": "00",
+                }],
+            },
+        },
+        "user_types": {
+            "stats": stats,
+            "vendor_ctx": {
+                "kind": "struct",
+                "size": 4,
+                "fields": {"x": {"type": int, "offset": 0}},
+            },
+        },
+        "enums": {},
+        "base_types": {},
+        "symbols": {
+            "vendor_state": {
+                "address": 0xffffffffc0001000u64,
+                "type": {"kind": "struct", "name": "vendor_ctx"},
+            },
+        },
+    })
+}
+
+fn with_banner(mut isf: Value, banner: &str) -> Value {
+    isf["symbols"]["linux_banner"] = json!({
+        "address": 0xffffffff82000100u64,
+        "constant_data": BASE64_STANDARD.encode(format!("{}\n", banner)),
+    });
+    isf
+}
+
+#[test]
+fn entries_are_added() {
+    let merged = Merged::merge(base(), extra(8), Prefer::Base, false).unwrap();
+    assert!(merged.conflicts.is_empty());
+    assert!(merged.problems.is_empty(), "{:?}", merged.problems);
+    let isf = serde_json::to_value(&merged.isf).unwrap();
+    assert_eq!(isf["user_types"]["vendor_ctx"]["size"], 4);
+    assert_eq!(
+        isf["symbols"]["vendor_state"]["address"],
+        0xffffffffc0001000u64
+    );
+    assert!(isf["symbols"].get("jiffies").is_some());
+
+    // The sources of both files are kept.
+    let types = isf["metadata"]["linux"]["types"].as_array().unwrap();
+    let names: Vec<_> = types.iter().map(|source| &source["name"]).collect();
+    assert_eq!(names, ["vars.btf", "vendor.ko"]);
+    assert_eq!(
+        isf["metadata"]["linux"]["symbols"],
+        base()["metadata"]["linux"]["symbols"]
+    );
+}
+
+#[test]
+fn conflicts() {
+    let merged = Merged::merge(base(), extra(16), Prefer::Base, false).unwrap();
+    assert_eq!(merged.conflicts.len(), 1);
+    let conflict = merged.conflicts[0].to_string();
+    assert!(
+        conflict.starts_with("user_types.stats differs"),
+        "{}",
+        conflict
+    );
+    assert!(conflict.contains(".size: base 8, extra 16"), "{}", conflict);
+    let isf = serde_json::to_value(&merged.isf).unwrap();
+    assert_eq!(isf["user_types"]["stats"]["size"], 8);
+
+    let merged = Merged::merge(base(), extra(16), Prefer::Extra, false).unwrap();
+    assert_eq!(merged.conflicts.len(), 1);
+    let isf = serde_json::to_value(&merged.isf).unwrap();
+    assert_eq!(isf["user_types"]["stats"]["size"], 16);
+}
+
+#[test]
+fn symbols_of_missing_types_are_voided() {
+    let mut extra = extra(8);
+    extra["symbols"]["vendor_state"]["type"]["name"] = json!("vendor_missing");
+    let merged = Merged::merge(base(), extra, Prefer::Base, false).unwrap();
+    assert_eq!(merged.problems.len(), 1, "{:?}", merged.problems);
+    let isf = serde_json::to_value(&merged.isf).unwrap();
+    assert_eq!(
+        isf["symbols"]["vendor_state"]["type"],
+        json!({"kind": "base", "name": "void"})
+    );
+}
+
+#[test]
+fn banners() {
+    let extra = with_banner(extra(8), "Linux version 6.19.0");
+    let err = Merged::merge(base(), extra.clone(), Prefer::Base, false)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("different kernels"), "{}", err);
+    assert!(err.contains("Linux version 6.19.0"), "{}", err);
+
+    assert!(Merged::merge(base(), extra, Prefer::Base, true).is_ok());
+    let extra = with_banner(self::extra(8), BANNER);
+    assert!(Merged::merge(base(), extra, Prefer::Base, false).is_ok());
+}
+
+fn write(test: &str, file: &str, isf: &Value) -> PathBuf {
+    common::write_temp(&format!("merge-{}-{}.json", test, file), isf.to_string())
+}
+
+#[test]
+fn subcommand() {
+    let base = write("cli", "base", &base());
+    let extra = write("cli", "extra", &extra(16));
+    let merged = env::temp_dir().join(format!("btf2json-merge-cli-{}.json.gz", std::process::id()));
+    let output = common::run(&[
+        "merge",
+        base.to_str().unwrap(),
+        extra.to_str().unwrap(),
+        "-o",
+        merged.to_str().unwrap(),
+        "--prefer",
+        "extra",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("user_types.stats differs"), "{}", stderr);
+
+    let isf = Isf::from_path(&merged).unwrap();
+    let user_types: Vec<_> = isf
+        .user_types()
+        .map(|(name, user)| (name, user.size()))
+        .collect();
+    assert_eq!(user_types, [("stats", 16), ("vendor_ctx", 4)]);
+    for path in [base, extra, merged] {
+        fs::remove_file(path).unwrap();
+    }
+}
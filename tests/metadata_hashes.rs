@@ -0,0 +1,52 @@
+//! Tests for the hashes of the inputs in the metadata.
+//!
+//! The inputs are `tests/data/funcs`, see `tests/functions.rs`, and
+//! `tests/data/symtab/vmlinux`, see `tests/symtab.rs`.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+fn data(path: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(path)
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn sha256(path: &str) -> Value {
+    json!(format!("{:x}", Sha256::digest(fs::read(path).unwrap())))
+}
+
+fn metadata(args: &[&str]) -> Value {
+    let isf = common::generate(&[&["--banner", "Linux version 6.18.0"], args].concat());
+    isf["metadata"]["linux"].clone()
+}
+
+#[test]
+fn btf_and_system_map() {
+    let (btf, map) = (data("funcs/funcs.btf"), data("funcs/System.map"));
+    let linux = metadata(&["--btf", &btf, "--map", &map]);
+
+    assert_eq!(linux["types"][0]["name"], "funcs.btf");
+    assert_eq!(linux["types"][0]["hash_type"], "sha256");
+    assert_eq!(linux["types"][0]["hash_value"], sha256(&btf));
+    assert_eq!(linux["symbols"][0]["name"], "System.map");
+    assert_eq!(linux["symbols"][0]["hash_value"], sha256(&map));
+}
+
+#[test]
+fn symtab() {
+    let vmlinux = data("symtab/vmlinux");
+    let linux = metadata(&["--btf", &vmlinux]);
+
+    assert_eq!(linux["types"][0]["hash_value"], sha256(&vmlinux));
+    assert_eq!(linux["symbols"][0]["kind"], "symtab");
+    assert_eq!(linux["symbols"][0]["hash_value"], sha256(&vmlinux));
+}
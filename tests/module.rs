@@ -0,0 +1,131 @@
+//! Tests for profiles of kernel modules with `--module`.
+//!
+//! `tests/data/module/foo.ko` is a hand-crafted kernel module whose BTF is
+//! split from `tests/data/funcs/funcs.btf`, see `tests/functions.rs`. It
+//! defines:
+//!
+//! ```c
+//! struct file { int f_mode; unsigned int f_flags; };  // not the vmlinux one
+//! enum foo_state { FOO_IDLE, FOO_BUSY };
+//!
+//! struct foo_dev {
+//!     int id;
+//!     enum foo_state state;
+//!     struct file *filp;
+//!     struct foo_dev *next;
+//!     long count;
+//! };
+//!
+//! struct foo_dev foo_devices;          // .data+0x0
+//! long foo_count;                      // .bss+0x8
+//! static int foo_probe(struct foo_dev *dev);  // .text+0x10
+//! int foo_init(void);                  // .text+0x40
+//! static int do_init(void);            // .text+0x80, also in vmlinux
+//! ```
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(args: &[&str]) -> Value {
+    let (btf, module, map) = (
+        common::data("funcs/funcs.btf"),
+        common::data("module/foo.ko"),
+        common::data("funcs/System.map"),
+    );
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--module",
+        module.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    common::generate(&[&cli, args].concat())
+}
+
+#[test]
+fn module_types() {
+    let isf = generate(&[]);
+
+    let fields = &isf["user_types"]["foo_dev"]["fields"];
+    assert_eq!(
+        fields["state"]["type"],
+        json!({"kind": "enum", "name": "foo_state"})
+    );
+    assert_eq!(
+        fields["filp"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "foo::file"}})
+    );
+    assert_eq!(fields["count"]["offset"], json!(24));
+    assert!(isf["enums"]["foo_state"].is_object());
+    // The conflicting module type does not replace the vmlinux one.
+    assert!(isf["user_types"]["file"]["fields"]["f_pos"].is_object());
+    assert!(isf["user_types"]["foo::file"]["fields"]["f_mode"].is_object());
+}
+
+#[test]
+fn section_relative_symbols() {
+    let isf = generate(&[]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["foo_devices"]["address"], json!(0));
+    assert_eq!(
+        symbols["foo_devices"]["type"],
+        json!({"kind": "struct", "name": "foo_dev"})
+    );
+    assert_eq!(symbols["foo_count"]["address"], json!(8));
+    assert_eq!(
+        symbols["foo_count"]["type"],
+        json!({"kind": "base", "name": "long int"})
+    );
+    assert_eq!(symbols["foo_probe"]["address"], json!(0x10));
+    assert_eq!(symbols["foo_probe"]["linkage"], "static");
+    // Kernel symbols are rebased as usual and win over module symbols.
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xffffffff81000100u64));
+    assert_eq!(symbols["do_init"]["address"], json!(0xffffffff81000200u64));
+    assert!(symbols.get("printk").is_none());
+
+    let linux = &isf["metadata"]["linux"];
+    assert_eq!(linux["types"][0]["name"], "foo.ko");
+    assert_eq!(linux["types"][1]["name"], "funcs.btf");
+    assert_eq!(linux["module"]["name"], "foo");
+    assert_eq!(linux["module"]["addresses"], "section-relative");
+    assert_eq!(
+        linux["module"]["sections"][".text"],
+        json!({"symbols": ["foo_init", "foo_probe"]})
+    );
+    assert_eq!(
+        linux["module"]["sections"][".bss"],
+        json!({"symbols": ["foo_count"]})
+    );
+}
+
+#[test]
+fn module_base() {
+    let isf = generate(&[
+        "--module-base",
+        ".text=0xffffffffc0a00000",
+        "--module-base",
+        ".data=0xffffffffc0a04000",
+    ]);
+    let symbols = &isf["symbols"];
+
+    assert_eq!(symbols["foo_init"]["address"], json!(0xffffffffc0a00040u64));
+    assert_eq!(
+        symbols["foo_devices"]["address"],
+        json!(0xffffffffc0a04000u64)
+    );
+    // `.bss` has no load address.
+    assert!(symbols.get("foo_count").is_none());
+
+    let module = &isf["metadata"]["linux"]["module"];
+    assert_eq!(module["addresses"], "absolute");
+    assert_eq!(
+        module["sections"][".data"],
+        json!({"address": 0xffffffffc0a04000u64, "symbols": ["foo_devices"]})
+    );
+    assert!(module["sections"].get(".bss").is_none());
+}
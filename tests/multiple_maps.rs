@@ -0,0 +1,143 @@
+//! Tests for merging several System.map files given with `--map`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// System.map of the base kernel.
+const BASE: &str = "\
+ffffffff81000000 T _stext
+ffffffff81000100 T vfs_read
+ffffffff81000200 t probe
+ffffffff82000100 D linux_banner
+";
+
+/// System.map of a partition, disjoint from `BASE`.
+const PARTITION: &str = "\
+ffffffff83000000 T part_init
+ffffffff83000100 D part_table
+";
+
+/// System.map that overlaps `BASE`, with `vfs_read` at the same address and
+/// `probe` at another one.
+const OVERLAPPING: &str = "\
+ffffffff81000000 T _stext
+ffffffff81000100 T vfs_read
+ffffffff83000200 t probe
+ffffffff83000300 T part_exit
+";
+
+/// Writes the `maps` to temporary files, called after the test `name`.
+fn write_maps(name: &str, maps: &[&str]) -> Vec<PathBuf> {
+    maps.iter()
+        .enumerate()
+        .map(|(idx, contents)| {
+            common::write_temp(&format!("multiple-maps-{}-{}.map", name, idx), contents)
+        })
+        .collect()
+}
+
+fn generate(name: &str, maps: &[&str], args: &[&str]) -> anyhow::Result<Value> {
+    let paths = write_maps(name, maps);
+    let mut cli = vec![
+        "--btf",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars/vars.btf"),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    for path in &paths {
+        cli.extend(["--map", path.to_str().unwrap()]);
+    }
+    cli.extend(args);
+    let isf = common::try_generate(&cli);
+    for path in paths {
+        fs::remove_file(path).unwrap();
+    }
+    Ok(isf?)
+}
+
+fn address(isf: &Value, name: &str) -> Option<u64> {
+    isf["symbols"][name]["address"].as_u64()
+}
+
+#[test]
+fn disjoint_maps() {
+    let isf = generate("disjoint", &[BASE, PARTITION], &[]).unwrap();
+
+    assert_eq!(address(&isf, "vfs_read"), Some(0xffffffff81000100));
+    assert_eq!(address(&isf, "probe"), Some(0xffffffff81000200));
+    assert_eq!(address(&isf, "part_init"), Some(0xffffffff83000000));
+    assert_eq!(address(&isf, "part_table"), Some(0xffffffff83000100));
+
+    // Every map is a source with its own hash.
+    let symbols: Vec<&Value> = isf["metadata"]["linux"]["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|source| source["kind"] == json!("system-map"))
+        .collect();
+    assert_eq!(symbols.len(), 2);
+    for (source, contents) in symbols.iter().zip([BASE, PARTITION]) {
+        assert_eq!(
+            source["hash_value"],
+            json!(format!("{:x}", Sha256::digest(contents)))
+        );
+    }
+    assert_ne!(symbols[0]["name"], symbols[1]["name"]);
+}
+
+#[test]
+fn overlapping_maps() {
+    // The same symbol in both maps is not ambiguous, a name at different
+    // addresses is dropped by default.
+    let isf = generate("overlapping", &[BASE, OVERLAPPING], &[]).unwrap();
+    assert_eq!(address(&isf, "_stext"), Some(0xffffffff81000000));
+    assert_eq!(address(&isf, "vfs_read"), Some(0xffffffff81000100));
+    assert_eq!(address(&isf, "probe"), None);
+    assert_eq!(address(&isf, "part_exit"), Some(0xffffffff83000300));
+
+    let isf = generate(
+        "overlapping-first",
+        &[OVERLAPPING, BASE],
+        &["--dup-symbols", "first"],
+    )
+    .unwrap();
+    assert_eq!(address(&isf, "probe"), Some(0xffffffff83000200));
+
+    let isf = generate(
+        "overlapping-suffix",
+        &[BASE, OVERLAPPING],
+        &["--dup-symbols", "suffix"],
+    )
+    .unwrap();
+    assert_eq!(address(&isf, "probe"), Some(0xffffffff81000200));
+    assert_eq!(address(&isf, "probe__1"), Some(0xffffffff83000200));
+}
+
+#[test]
+fn anchor_in_one_map() {
+    // Only the maps of partitions, neither has _stext.
+    let other = "ffffffff83000300 T part_exit\nffffffff83000400 D linux_banner\n";
+    let err = generate("no-anchor", &[PARTITION, other], &[])
+        .err()
+        .unwrap();
+    let err = format!("{:#}", err);
+    assert!(err.contains("None of the System.map files"), "{}", err);
+    assert!(err.ends_with("has _stext"), "{}", err);
+
+    let isf = generate(
+        "anchor-symbol",
+        &[PARTITION, other],
+        &["--anchor-symbol", "part_init"],
+    )
+    .unwrap();
+    // The anchor is rebased to the start of the kernel image.
+    assert_eq!(address(&isf, "part_exit"), Some(0xffffffff81000300));
+}
@@ -0,0 +1,66 @@
+//! Tests for keeping the symbol addresses of the symbol source.
+//!
+//! Uses `tests/data/kallsyms/kallsyms`, which has `_stext` at
+//! `0xffffffff9b000000` and `jiffies` at `0xffffffff9c000000`.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+fn generate(source: &str, path: &Path, no_rebase: bool) -> anyhow::Result<Value> {
+    let btf = common::data("vars/vars.btf");
+    let mut args = vec![
+        "--btf",
+        btf.to_str().unwrap(),
+        source,
+        path.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    if no_rebase {
+        args.push("--no-rebase");
+    }
+    Ok(common::try_generate(&args)?)
+}
+
+#[test]
+fn addresses_are_kept() {
+    let kallsyms = common::data("kallsyms/kallsyms");
+    let rebased = generate("--kallsyms", &kallsyms, false).unwrap();
+    let kept = generate("--kallsyms", &kallsyms, true).unwrap();
+
+    assert_eq!(
+        rebased["symbols"]["jiffies"]["address"],
+        json!(0xffffffff82000000u64)
+    );
+    assert_eq!(
+        kept["symbols"]["jiffies"]["address"],
+        json!(0xffffffff9c000000u64)
+    );
+    assert_eq!(
+        kept["symbols"]["linux_banner"]["constant_data"],
+        rebased["symbols"]["linux_banner"]["constant_data"]
+    );
+    assert_eq!(kept["metadata"], rebased["metadata"]);
+}
+
+#[test]
+fn stext_only_required_for_rebasing() {
+    let map = common::write_temp(
+        "no-stext.map",
+        "ffffffff82000000 D jiffies\nffffffff82000100 D linux_banner\n",
+    );
+
+    assert!(generate("--map", &map, false).is_err());
+    let kept = generate("--map", &map, true).unwrap();
+    assert_eq!(
+        kept["symbols"]["jiffies"]["address"],
+        json!(0xffffffff82000000u64)
+    );
+    fs::remove_file(&map).unwrap();
+}
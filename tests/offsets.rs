@@ -0,0 +1,230 @@
+//! Tests for the `offsets` subcommand.
+//!
+//! `tests/data/endian/tiny_le.btf` is described in `tests/big_endian.rs`, its
+//! struct `task` has the typedef alias `task_t`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::process::Output;
+
+use btf2json::btf::Btf;
+use btf2json::offsets::Offsets;
+use btf2json::testutil::BtfBuilder;
+use serde_json::{json, Value};
+
+const TINY_LE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_le.btf");
+
+fn offsets(args: &[&str]) -> Output {
+    common::run(&[&["offsets", "--btf", TINY_LE, "--arch", "x86_64"][..], args].concat())
+}
+
+#[test]
+fn csv() {
+    let output = offsets(&[
+        "--fields",
+        "task.comm,task_t.flags",
+        "--fields",
+        "task.mode",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "field,offset,size,type,error\n\
+         task.comm,8,16,char[16],\n\
+         task_t.flags,24,8,long unsigned int *,\n\
+         task.mode,4,4,enum mode,\n"
+    );
+}
+
+#[test]
+fn failed_lookups() {
+    let output = offsets(&[
+        "--fields",
+        "task.nope,task.comm,nope.x,task",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let entries: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        entries,
+        json!([
+            {"field": "task.nope", "error": "no member `nope` in `task`"},
+            {"field": "task.comm", "offset": 8, "size": 16, "type": "char[16]"},
+            {"field": "nope.x", "error": "no struct or union `nope`"},
+            {"field": "task", "error": "expected <type>.<member>"},
+        ])
+    );
+}
+
+/// ```c
+/// struct list_head { struct list_head *next, *prev; };
+/// struct sched_entity { long vruntime; };
+/// struct task_struct {
+///     int pid;
+///     union {
+///         struct { int tgid; unsigned int flags:3; };
+///         long pad;
+///     };
+///     struct list_head tasks;
+///     struct sched_entity se;
+/// };
+/// typedef struct task_struct task_t;
+/// ```
+fn nested() -> Btf {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let uint = btf.int("unsigned int", 4, false);
+    let long = btf.int("long", 8, true);
+    // Refers to `list_head`, which follows.
+    let ptr = btf.ptr(long + 2);
+    let list = btf.struct_("list_head", 16, &[("next", ptr, 0), ("prev", ptr, 64)]);
+    assert_eq!(ptr, list - 1);
+    let se = btf.struct_("sched_entity", 8, &[("vruntime", long, 0)]);
+    let inner = btf.bitfields("", 8, &[("tgid", int, 0, 0), ("flags", uint, 32, 3)]);
+    let anon = btf.union_("", 8, &[("", inner, 0), ("pad", long, 0)]);
+    let task = btf.struct_(
+        "task_struct",
+        40,
+        &[
+            ("pid", int, 0),
+            ("", anon, 64),
+            ("tasks", list, 128),
+            ("se", se, 256),
+        ],
+    );
+    btf.typedef_("task_t", task);
+    btf.build("nested.btf").unwrap()
+}
+
+#[test]
+fn anonymous_and_nested_members() {
+    let fields: Vec<String> = [
+        "task_struct.tgid",
+        "task_t.flags",
+        "task_struct.tasks",
+        "task_struct.tasks.prev",
+        "task_struct.se.vruntime",
+        "task_struct.pid.x",
+    ]
+    .map(String::from)
+    .to_vec();
+    let offsets = Offsets::lookup(&nested(), &fields, Some(8));
+    let entries: Vec<_> = offsets
+        .entries()
+        .iter()
+        .map(|entry| {
+            (
+                entry.offset,
+                entry.size,
+                entry.type_name.as_deref(),
+                entry.error.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            (Some(8), Some(4), Some("int"), None),
+            (Some(12), None, Some("unsigned int:3"), None),
+            (Some(16), Some(16), Some("struct list_head"), None),
+            (Some(24), Some(8), Some("struct list_head *"), None),
+            (Some(32), Some(8), Some("long"), None),
+            (
+                None,
+                None,
+                None,
+                Some("`task_struct.pid` is no struct or union")
+            ),
+        ]
+    );
+    assert_eq!(offsets.failures(), 1);
+}
+
+/// Pointers have no size if the architecture is unknown.
+#[test]
+fn unknown_architecture() {
+    let path = env::temp_dir().join(format!("btf2json-offsets-{}.btf", std::process::id()));
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let ptr = btf.ptr(int);
+    btf.struct_("s", 8, &[("p", ptr, 0)]);
+    fs::write(&path, btf.bytes()).unwrap();
+    let output = common::run(&[
+        "offsets",
+        "--btf",
+        path.to_str().unwrap(),
+        "--fields",
+        "s.p",
+    ]);
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "field,offset,size,type,error\ns.p,0,,int *,\n"
+    );
+}
+
+/// Anonymous members are searched up to `--max-type-depth` levels deep.
+///
+/// ```c
+/// struct s { struct { struct { struct { int x; }; }; }; };
+/// ```
+#[test]
+fn max_type_depth() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let mut anon = btf.struct_("", 4, &[("x", int, 0)]);
+    for _ in 0..2 {
+        anon = btf.struct_("", 4, &[("", anon, 0)]);
+    }
+    btf.struct_("s", 4, &[("", anon, 0)]);
+    let mut btf = btf.build("deep.btf").unwrap();
+
+    let fields = [String::from("s.x")];
+    btf.set_max_type_depth(3);
+    let offsets = Offsets::lookup(&btf, &fields, Some(8));
+    assert_eq!(offsets.entries()[0].offset, Some(0));
+    btf.set_max_type_depth(2);
+    let offsets = Offsets::lookup(&btf, &fields, Some(8));
+    assert_eq!(
+        offsets.entries()[0].error.as_deref(),
+        Some("[2] type nested too deeply")
+    );
+}
+
+/// `struct s { struct s; };`, whose anonymous member is a cycle.
+#[test]
+fn cyclic_anonymous_member() {
+    let mut btf = BtfBuilder::new();
+    btf.int("int", 4, true);
+    assert_eq!(btf.struct_("s", 4, &[("", 2, 0)]), 2);
+    let btf = btf.build("cycle.btf").unwrap();
+
+    let offsets = Offsets::lookup(&btf, &[String::from("s.x")], Some(8));
+    assert_eq!(
+        offsets.entries()[0].error.as_deref(),
+        Some("[2] type nested too deeply")
+    );
+}
+
+/// `struct s { int x[0x40000000][0x40000000][16]; };`, whose size overflows.
+#[test]
+fn array_size_overflow() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let mut arr = btf.array(int, int, 16);
+    for _ in 0..2 {
+        arr = btf.array(arr, int, 0x4000_0000);
+    }
+    btf.struct_("s", 4, &[("x", arr, 0)]);
+    let btf = btf.build("overflow.btf").unwrap();
+
+    let offsets = Offsets::lookup(&btf, &[String::from("s.x")], Some(8));
+    assert_eq!(
+        offsets.entries()[0].error.as_deref(),
+        Some("[4] array size overflows")
+    );
+}
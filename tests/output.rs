@@ -0,0 +1,238 @@
+//! Tests for writing ISF files with `--output` and `--output-dir`.
+//!
+//! The ISF files are generated from `tests/data/vars`.
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::Output;
+
+#[cfg(feature = "xz")]
+use base64::prelude::*;
+#[cfg(feature = "xz")]
+use flate2::read::GzDecoder;
+use serde_json::Value;
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+
+fn run(args: &[&str]) -> Output {
+    run_with_banner("Linux version 6.18.0", args)
+}
+
+fn run_with_banner(banner: &str, args: &[&str]) -> Output {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        banner,
+        "--arch",
+        "x86_64",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+/// Returns an empty directory for the outputs of the test `name`.
+fn out_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("btf2json-output-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read_json(reader: impl Read) -> Value {
+    serde_json::from_reader(reader).unwrap()
+}
+
+fn stdout_isf() -> Value {
+    let output = run(&[]);
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn compression_from_extension() {
+    let dir = out_dir("ext");
+    let expected = stdout_isf();
+
+    for name in ["isf.json", "isf.json.gz", "isf.json.xz"] {
+        let path = dir.join(name);
+        let output = run(&["--output", path.to_str().unwrap()]);
+        assert!(output.status.success(), "{:?}", output);
+        assert!(output.stdout.is_empty());
+
+        let file = File::open(&path).unwrap();
+        let isf = match name {
+            "isf.json" => read_json(file),
+            "isf.json.gz" => read_json(GzDecoder::new(file)),
+            _ => read_json(XzDecoder::new(file)),
+        };
+        assert_eq!(isf, expected, "{}", name);
+    }
+    // No temporary files are left behind.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn explicit_compression() {
+    let dir = out_dir("explicit");
+    let path = dir.join("isf.json");
+
+    let output = run(&["-o", path.to_str().unwrap(), "--compress", "xz"]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf = read_json(XzDecoder::new(File::open(&path).unwrap()));
+    assert_eq!(isf, stdout_isf());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn existing_file_is_replaced() {
+    let dir = out_dir("replace");
+    let path = dir.join("isf.json");
+    fs::write(&path, "stale").unwrap();
+
+    let output = run(&["--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read_json(File::open(&path).unwrap()), stdout_isf());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unwritable_directory_is_reported() {
+    let path = out_dir("missing").join("nonexistent/isf.json");
+
+    let output = run(&["--output", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unable to create"), "{}", stderr);
+    assert!(stderr.contains("nonexistent"), "{}", stderr);
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn output_dir_named_after_banner() {
+    let dir = out_dir("banner").join("symbols");
+    let name = format!(
+        "{}.json.xz",
+        BASE64_URL_SAFE.encode("Linux version 6.18.0\n")
+    );
+    let path = dir.join(&name);
+
+    // The directory is created.
+    let output = run(&["--output-dir", dir.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n", path.display())
+    );
+    let isf = read_json(XzDecoder::new(File::open(&path).unwrap()));
+    assert_eq!(isf, stdout_isf());
+
+    // An existing file for the banner is only replaced with --force.
+    fs::write(&path, "stale").unwrap();
+    let output = run(&["--output-dir", dir.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--force"), "{}", stderr);
+    assert_eq!(fs::read(&path).unwrap(), b"stale");
+
+    let output = run(&["--output-dir", dir.to_str().unwrap(), "--force"]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf = read_json(XzDecoder::new(File::open(&path).unwrap()));
+    assert_eq!(isf, stdout_isf());
+    fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn banners_index() {
+    let dir = out_dir("index");
+    let index = dir.join("banners.json");
+    // Entries of other kernels and operating systems are kept.
+    fs::write(
+        &index,
+        r#"{"version": 1, "mac": {"RGFyd2luCg==": ["mac.json.xz"]}}"#,
+    )
+    .unwrap();
+
+    let mut names = Vec::new();
+    for banner in [
+        "Linux version 6.18.0",
+        "Linux version 6.18.1",
+        "Linux version 6.18.0",
+    ] {
+        let output = run_with_banner(
+            banner,
+            &[
+                "--output-dir",
+                dir.to_str().unwrap(),
+                "--force",
+                "--banners-index",
+                index.to_str().unwrap(),
+            ],
+        );
+        assert!(output.status.success(), "{:?}", output);
+        let path = PathBuf::from(String::from_utf8(output.stdout).unwrap().trim_end());
+        names.push(path.file_name().unwrap().to_str().unwrap().to_string());
+    }
+
+    // The banners are the constant data of linux_banner, which Volatility
+    // looks up, and regenerating a profile adds no duplicate.
+    let constant_data = |name: &str| {
+        let isf = read_json(XzDecoder::new(File::open(dir.join(name)).unwrap()));
+        isf["symbols"]["linux_banner"]["constant_data"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let mut linux = serde_json::Map::new();
+    for name in &names[..2] {
+        linux.insert(constant_data(name), serde_json::json!([name]));
+    }
+    assert_eq!(
+        read_json(File::open(&index).unwrap()),
+        serde_json::json!({
+            "version": 1,
+            "mac": {"RGFyd2luCg==": ["mac.json.xz"]},
+            "linux": linux,
+        })
+    );
+    assert_eq!(
+        BASE64_STANDARD.decode(constant_data(&names[1])).unwrap(),
+        b"Linux version 6.18.1\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn banners_index_requires_output_dir() {
+    let output = run(&["--banners-index", "banners.json"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn output_dir_conflicts_with_output() {
+    let dir = out_dir("conflict");
+    let path = dir.join("isf.json");
+
+    let output = run(&[
+        "--output-dir",
+        dir.to_str().unwrap(),
+        "--output",
+        path.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
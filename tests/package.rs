@@ -0,0 +1,123 @@
+//! Tests for reading vmlinux and System.map from kernel packages.
+//!
+//! The vmlinux in the packages in `tests/data/package` is the one of
+//! `tests/data/constdata`, see `tests/constant_data.rs`.
+//!
+//! - `linux-image-6.18.0-dbg_6.18.0-1_amd64.deb` has it at
+//!   `usr/lib/debug/boot/vmlinux-6.18.0`, a decoy at the less preferred
+//!   `usr/lib/debug/lib/modules/6.18.0/vmlinux`, and `boot/System.map-6.18.0`
+//!   with the additional symbol `map_only`, in an xz-compressed GNU tar
+//!   archive.
+//! - `kernel-debuginfo-6.18.0-1.x86_64.rpm` has it at
+//!   `usr/lib/debug/lib/modules/6.18.0/vmlinux` and a symbolic link to it at
+//!   `usr/lib/debug/boot/vmlinux-6.18.0`, in a gzip-compressed cpio archive.
+//! - `unknown-layout.deb` has it at `opt/kernel/vmlinux`.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "xz")]
+use base64::prelude::*;
+use btf2json::package::{self, VMLINUX_PATHS};
+use btf2json::{Error, GenerationOptions};
+use serde_json::Value;
+
+fn data(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data/package")
+        .join(path)
+}
+
+fn generate(options: GenerationOptions) -> Value {
+    let mut isf = options.generate().unwrap();
+    isf.fix_symbol_types().ok();
+    serde_json::from_str(&isf.to_string()).unwrap()
+}
+
+/// Returns the names of the symbol sources in the metadata of `isf`.
+fn symbol_sources(isf: &Value) -> Vec<&str> {
+    isf["metadata"]["linux"]["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|source| source["kind"] != "symdb")
+        .map(|source| source["name"].as_str().unwrap())
+        .collect()
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn deb() {
+    let isf = generate(
+        GenerationOptions::new().package_file(data("linux-image-6.18.0-dbg_6.18.0-1_amd64.deb")),
+    );
+
+    assert_eq!(
+        symbol_sources(&isf),
+        ["System.map-6.18.0", "vmlinux-6.18.0"]
+    );
+    assert!(isf["symbols"]["map_only"].is_object());
+    assert!(isf["symbols"]["counter"].is_object());
+    // The banner is read from vmlinux.
+    let banner = BASE64_STANDARD
+        .decode(
+            isf["symbols"]["linux_banner"]["constant_data"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(banner.starts_with(b"Linux version 6.18.0-const"));
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn map_takes_precedence() {
+    let map = b"ffffffff81000000 T _stext\nffffffff82000010 D given_map\n".to_vec();
+    let isf = generate(
+        GenerationOptions::new()
+            .package_file(data("linux-image-6.18.0-dbg_6.18.0-1_amd64.deb"))
+            .map_bytes("System.map", map),
+    );
+
+    assert_eq!(symbol_sources(&isf), ["System.map", "vmlinux-6.18.0"]);
+    assert!(isf["symbols"]["given_map"].is_object());
+    assert!(isf["symbols"].get("map_only").is_none());
+}
+
+#[test]
+fn rpm() {
+    let package = package::extract(&data("kernel-debuginfo-6.18.0-1.x86_64.rpm")).unwrap();
+    assert_eq!(package.vmlinux.0, "vmlinux");
+    assert!(package.system_map.is_none());
+
+    // Without System.map, the symbols are read from the symbol table.
+    let isf = generate(
+        GenerationOptions::new().package_file(data("kernel-debuginfo-6.18.0-1.x86_64.rpm")),
+    );
+    assert_eq!(symbol_sources(&isf), ["vmlinux"]);
+    assert!(isf["symbols"]["counter"].is_object());
+}
+
+#[test]
+fn unknown_layout() {
+    let err = GenerationOptions::new()
+        .package_file(data("unknown-layout.deb"))
+        .generate()
+        .err()
+        .unwrap();
+    let Error::PackageLayout { name, tried } = &err else {
+        panic!("{:?}", err)
+    };
+    assert_eq!(name, "unknown-layout.deb");
+    assert_eq!(*tried, VMLINUX_PATHS);
+    assert!(err.to_string().contains("usr/lib/debug/boot/vmlinux-*"));
+}
+
+#[test]
+fn not_a_package() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/constdata/vmlinux");
+    let err = package::extract(&path).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "vmlinux is neither a Debian nor an RPM package"
+    );
+}
@@ -0,0 +1,112 @@
+//! Tests for `--patch`, on the profile of `tests/data/vars`, whose only user
+//! type is `struct stats { unsigned long hits; }`.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use btf2json::error;
+use btf2json::isf::Isf;
+use serde_json::{json, Value};
+
+fn write_patch(test: &str, patch: &Value) -> PathBuf {
+    common::write_temp(&format!("patch-{}.json", test), patch.to_string())
+}
+
+fn generate(test: &str, patch: &Value) -> error::Result<Isf> {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    let path = write_patch(test, patch);
+    let isf = common::try_generate_isf(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+        "--patch",
+        path.to_str().unwrap(),
+    ]);
+    fs::remove_file(&path).unwrap();
+    isf
+}
+
+fn int(offset: u64) -> Value {
+    json!({"type": {"kind": "base", "name": "int"}, "offset": offset, "anonymous": false})
+}
+
+#[test]
+fn entries_are_merged() {
+    let patch = json!({
+        "user_types": {
+            "stats": {"kind": "struct", "size": 16, "fields": {"hits": int(8)}},
+            "vendor_ctx": {
+                "kind": "struct",
+                "size": 8,
+                "fields": {
+                    "mode": {"type": {"kind": "enum", "name": "vendor_mode"}, "offset": 0},
+                    "inner": {"type": {"kind": "struct", "name": "vendor_inner"}, "offset": 4},
+                },
+            },
+            "vendor_inner": {"kind": "struct", "size": 4, "fields": {"x": int(0)}},
+        },
+        "enums": {
+            "vendor_mode": {"size": 4, "base": "int", "constants": {"VENDOR_OFF": 0}},
+        },
+        "symbols": {
+            "vendor_state": {
+                "address": 0xffffffff82000200u64,
+                "type": {"kind": "struct", "name": "vendor_ctx"},
+            },
+            "dup": null,
+        },
+    });
+    let mut isf = generate("merged", &patch).unwrap();
+    let value: Value = serde_json::from_str(&isf.to_string()).unwrap();
+
+    assert_eq!(value["user_types"]["stats"]["size"], 16);
+    assert_eq!(value["user_types"]["stats"]["fields"]["hits"]["offset"], 8);
+    assert_eq!(value["user_types"]["vendor_ctx"]["size"], 8);
+    assert_eq!(value["enums"]["vendor_mode"]["constants"]["VENDOR_OFF"], 0);
+    assert_eq!(
+        value["symbols"]["vendor_state"]["address"],
+        0xffffffff82000200u64
+    );
+    assert!(value["symbols"].get("dup").is_none());
+    // Untouched entries are kept.
+    assert_eq!(
+        value["symbols"]["jiffies"]["address"],
+        0xffffffff82000000u64
+    );
+
+    // The patched types satisfy the references to them.
+    isf.check_user_types().unwrap();
+    isf.check_symbol_types().unwrap();
+    assert!(isf.apply_quirks().is_empty());
+}
+
+#[test]
+fn malformed_entry() {
+    let patch = json!({
+        "user_types": {
+            "vendor_ctx": {"kind": "struct", "fields": {}},
+        },
+    });
+    let err = generate("malformed", &patch).err().unwrap();
+    let err = format!("{:#}", anyhow::Error::from(err));
+    assert!(err.contains("user_types.vendor_ctx"), "{}", err);
+    assert!(err.contains("missing field `size`"), "{}", err);
+}
+
+#[test]
+fn unknown_section() {
+    let patch = json!({"types": {}});
+    let err = generate("section", &patch).err().unwrap();
+    let err = format!("{:#}", anyhow::Error::from(err));
+    assert!(err.contains("types: unknown section"), "{}", err);
+}
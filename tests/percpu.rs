@@ -0,0 +1,73 @@
+//! Tests for rebasing symbol maps with per-CPU and absolute symbols, which
+//! are not relocated with the kernel.
+//!
+//! The System.map is a slice of the one of an x86_64 kernel with a KASLR
+//! shift of `0x2e000000`. The types are from `tests/data/funcs/funcs.btf`.
+
+mod common;
+
+use std::fs;
+
+use serde_json::{json, Value};
+
+const MAP: &str = "\
+0000000000000000 D __per_cpu_start
+0000000000000000 D fixed_percpu_data
+00000000000001e0 A __kcfi_typeid_vfs_read
+0000000000001000 D cpu_debug_store
+0000000000014ec0 D runqueues
+000000000002e000 D __per_cpu_end
+00000000bb4a6ac1 A __kcfi_typeid_do_init
+ffffffffaf000000 T _text
+ffffffffaf000000 T startup_64
+ffffffffaf000000 T _stext
+ffffffffaf000100 T vfs_read
+ffffffffb0800000 D linux_banner
+ffffffffb0a00010 D jiffies
+ffffffffb1000000 A __abs_above_image
+";
+
+fn generate() -> Value {
+    let map = common::write_temp("percpu.map", MAP);
+    let btf = common::data("funcs/funcs.btf");
+    let isf = common::generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ]);
+    fs::remove_file(&map).unwrap();
+    isf["symbols"].clone()
+}
+
+#[test]
+fn kernel_symbols_are_rebased() {
+    let symbols = generate();
+
+    assert_eq!(symbols["_text"]["address"], json!(0xffffffff81000000u64));
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff81000000u64));
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xffffffff81000100u64));
+    assert_eq!(symbols["jiffies"]["address"], json!(0xffffffff82a00010u64));
+}
+
+#[test]
+fn percpu_and_absolute_symbols_are_kept() {
+    let symbols = generate();
+
+    for (name, addr) in [
+        ("__per_cpu_start", 0),
+        ("fixed_percpu_data", 0),
+        ("cpu_debug_store", 0x1000),
+        ("runqueues", 0x14ec0),
+        ("__per_cpu_end", 0x2e000),
+        ("__kcfi_typeid_vfs_read", 0x1e0),
+        ("__kcfi_typeid_do_init", 0xbb4a6ac1u64),
+        ("__abs_above_image", 0xffffffffb1000000),
+    ] {
+        assert_eq!(symbols[name]["address"], json!(addr), "{}", name);
+    }
+}
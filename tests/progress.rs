@@ -0,0 +1,118 @@
+//! Tests for reporting the progress of the generation.
+//!
+//! Uses `tests/data/vars`, see `tests/variables.rs`.
+
+mod common;
+
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use btf2json::cli::Architecture;
+use btf2json::progress::{Phase, Progress};
+use btf2json::GenerationOptions;
+use serde_json::Value;
+
+const VARS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars");
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Start(Phase, Option<usize>),
+    Finish(Phase, usize),
+}
+
+/// Records the start and end of the phases and the number of steps.
+#[derive(Default)]
+struct Recorder {
+    events: RefCell<Vec<Event>>,
+    steps: RefCell<usize>,
+}
+
+impl Progress for Recorder {
+    fn start(&self, phase: Phase, total: Option<usize>) {
+        self.events.borrow_mut().push(Event::Start(phase, total));
+    }
+
+    fn advance(&self, _phase: Phase, _done: usize) {
+        *self.steps.borrow_mut() += 1;
+    }
+
+    fn finish(&self, phase: Phase, done: usize) {
+        self.events.borrow_mut().push(Event::Finish(phase, done));
+    }
+}
+
+#[test]
+fn library_events() {
+    let recorder = Rc::new(Recorder::default());
+    GenerationOptions::new()
+        .btf_bytes("vars.btf", fs::read(format!("{}/vars.btf", VARS)).unwrap())
+        .map_file(format!("{}/System.map", VARS))
+        .banner("Linux version 6.18.0")
+        .arch(Architecture::X86_64)
+        .progress(recorder.clone())
+        .generate()
+        .unwrap();
+
+    let events = recorder.events.borrow();
+    let phases: Vec<Phase> = events
+        .iter()
+        .map(|event| match event {
+            Event::Start(phase, _) | Event::Finish(phase, _) => *phase,
+        })
+        .collect();
+    assert_eq!(
+        phases,
+        [
+            Phase::ScanTypes,
+            Phase::ScanTypes,
+            Phase::Symbols,
+            Phase::Symbols,
+            Phase::UserTypes,
+            Phase::UserTypes
+        ]
+    );
+    let Event::Start(_, Some(nr_types)) = events[0] else {
+        panic!("{:?}", events[0])
+    };
+    assert_eq!(events[1], Event::Finish(Phase::ScanTypes, nr_types));
+    assert_eq!(events[2], Event::Start(Phase::Symbols, None));
+    assert_eq!(events[3], Event::Finish(Phase::Symbols, 7));
+    assert!(*recorder.steps.borrow() >= nr_types);
+}
+
+#[test]
+fn stderr_lines() {
+    let output = common::run(&[
+        "generate",
+        "--btf",
+        &format!("{}/vars.btf", VARS),
+        "--map",
+        &format!("{}/System.map", VARS),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+        "--progress",
+    ]);
+    assert!(output.status.success());
+    serde_json::from_slice::<Value>(&output.stdout).unwrap();
+
+    // Standard error is not a terminal here, so there is a line per update.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains('\r'));
+    for phase in [
+        "scanning BTF types",
+        "building symbols",
+        "constructing user types",
+        "serializing",
+    ] {
+        assert!(
+            stderr
+                .lines()
+                .any(|line| line.contains(phase) && line.contains(" done in ")),
+            "{}",
+            stderr
+        );
+    }
+}
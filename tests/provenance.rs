@@ -0,0 +1,123 @@
+//! Tests for recording the BTF types of the entries with `--annotate-btf-ids`.
+//!
+//! `tests/data/typedefs/aliases.btf` is described in `tests/typedef_aliases.rs`,
+//! `tests/data/split/btf2json_test.btf` in `tests/split.rs`.
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use btf2json::btf::Btf;
+use btf2json::cli::Cli;
+use btf2json::isf::{Provenance, TypeOrigin};
+use clap::Parser;
+use xz2::read::XzDecoder;
+
+fn data_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"))
+}
+
+/// Generates the types of the BTF given with `args` and returns the
+/// provenance file.
+fn generate(name: &str, args: &[&Path]) -> Provenance {
+    let output = env::temp_dir().join(format!(
+        "btf2json-prov-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    let cli = [
+        "generate",
+        "--types-only",
+        "--annotate-btf-ids",
+        "-o",
+        output.to_str().unwrap(),
+    ];
+    let args: Vec<_> = args.iter().map(|arg| arg.to_str().unwrap()).collect();
+    assert!(common::run(&[&cli[..], &args].concat()).status.success());
+    let path = PathBuf::from(format!("{}.provenance.json", output.display()));
+    let provenance = Provenance::from_reader(File::open(&path).unwrap()).unwrap();
+    fs::remove_file(&output).unwrap();
+    fs::remove_file(&path).unwrap();
+    provenance
+}
+
+/// Checks that each entry is a name of its BTF type.
+fn assert_round_trip(provenance: &Provenance, args: &[&Path]) {
+    let mut cli = vec![Path::new("btf2json")];
+    cli.extend(args);
+    let btf = Btf::try_from(Cli::parse_from(cli).generate_args()).unwrap();
+    let (.., typedefs) = btf.gen_vol_id_sets().unwrap();
+    for entries in [
+        &provenance.user_types,
+        &provenance.enums,
+        &provenance.base_types,
+    ] {
+        for (name, origin) in entries {
+            let id = btf.id_range(origin.btf_id..=origin.btf_id).next().unwrap();
+            let names = btf.get_names_by_id(id, Some(&typedefs)).unwrap();
+            assert!(names.contains(name), "{} not in {:?}", name, names);
+            if let Some(primary) = &origin.alias_of {
+                assert!(names.contains(primary), "{} not in {:?}", primary, names);
+            }
+        }
+    }
+}
+
+fn origin(btf_id: u32, alias_of: Option<&str>, source: &str) -> TypeOrigin {
+    TypeOrigin {
+        btf_id,
+        alias_of: alias_of.map(str::to_owned),
+        source: source.to_owned(),
+    }
+}
+
+#[test]
+fn typedef_aliases() {
+    let btf = data_dir().join("typedefs/aliases.btf");
+    let args = [Path::new("--btf"), &btf];
+    let provenance = generate("aliases", &args);
+
+    let users = &provenance.user_types;
+    assert_eq!(users["list"], origin(2, None, "aliases.btf"));
+    assert_eq!(users["list_t"], origin(2, Some("list"), "aliases.btf"));
+    // Anonymous types are primarily named after their first typedef.
+    assert_eq!(users["first_t"], origin(4, None, "aliases.btf"));
+    assert_eq!(users["second_t"], origin(4, Some("first_t"), "aliases.btf"));
+    assert_eq!(
+        provenance.enums["mode_t"],
+        origin(7, Some("mode"), "aliases.btf")
+    );
+    assert_eq!(provenance.base_types["int"], origin(1, None, "aliases.btf"));
+    // `pointer` is not in BTF.
+    assert!(!provenance.base_types.contains_key("pointer"));
+    assert_round_trip(&provenance, &args);
+}
+
+#[test]
+fn split_btf() {
+    let base = env::temp_dir().join(format!("btf2json-prov-{}.btf", std::process::id()));
+    let mut data = Vec::new();
+    XzDecoder::new(File::open(data_dir().join("linux-6.18/vmlinux.btf.xz")).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    fs::write(&base, data).unwrap();
+    let split = data_dir().join("split/btf2json_test.btf");
+    let args = [Path::new("--base-btf"), &base, Path::new("--btf"), &split];
+    let provenance = generate("split", &args);
+
+    let base_name = base.file_name().unwrap().to_str().unwrap();
+    assert_eq!(
+        provenance.user_types["btf2json_test_mod"].source,
+        "btf2json_test.btf"
+    );
+    assert_eq!(
+        provenance.enums["btf2json_test_mode"].source,
+        "btf2json_test.btf"
+    );
+    assert_eq!(provenance.user_types["list_head"].source, base_name);
+    assert_round_trip(&provenance, &args);
+    fs::remove_file(&base).unwrap();
+}
@@ -0,0 +1,74 @@
+//! Tests for dropping types that are not reachable from symbols or roots.
+//!
+//! The types are pruned from the golden ISF file of `tests/data/linux-6.18`.
+
+#![cfg(feature = "xz")]
+
+use std::path::Path;
+
+use btf2json::isf::Isf;
+use serde_json::Value;
+
+const GOLDEN: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/linux-6.18/expected.json.xz"
+);
+
+fn load() -> Isf {
+    Isf::from_path(Path::new(GOLDEN)).unwrap()
+}
+
+fn prune(roots: &[&str]) -> (Isf, Value) {
+    let mut isf = load();
+    isf.prune(
+        &roots
+            .iter()
+            .map(|root| root.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    let value = serde_json::to_value(&isf).unwrap();
+    (isf, value)
+}
+
+fn names(isf: &Value, key: &str) -> Vec<String> {
+    isf[key].as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn closed_under_references() {
+    let full = load();
+    let full_ok = full.check_user_types().is_ok();
+    let full = serde_json::to_value(&full).unwrap();
+    let (isf, pruned) = prune(&[]);
+
+    assert_eq!(isf.check_user_types().is_ok(), full_ok);
+    assert_eq!(pruned["symbols"], full["symbols"]);
+    for key in ["user_types", "enums", "base_types"] {
+        for (name, elem) in pruned[key].as_object().unwrap() {
+            assert_eq!(elem, &full[key][name], "{}", name);
+        }
+    }
+    assert!(names(&pruned, "user_types").len() < names(&full, "user_types").len());
+    assert!(names(&pruned, "user_types").contains(&String::from("task_struct")));
+    assert!(names(&pruned, "base_types").contains(&String::from("pointer")));
+}
+
+#[test]
+fn roots() {
+    let full = serde_json::to_value(load()).unwrap();
+    let (_, pruned) = prune(&[]);
+    let kept = names(&pruned, "user_types");
+    let root = names(&full, "user_types")
+        .into_iter()
+        .find(|name| !kept.contains(name))
+        .unwrap();
+
+    let (isf, with_root) = prune(&[&root, "no_such_type"]);
+    assert!(names(&with_root, "user_types").contains(&root));
+    assert_eq!(with_root["user_types"][&root], full["user_types"][&root]);
+    assert_eq!(
+        isf.check_user_types().is_ok(),
+        load().check_user_types().is_ok()
+    );
+}
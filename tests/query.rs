@@ -0,0 +1,111 @@
+//! Tests for the `query` subcommand.
+//!
+//! `tests/data/endian/tiny_le.btf` is described in `tests/big_endian.rs`, its
+//! struct `task` has the typedef alias `task_t`. `tests/data/vars` has the
+//! symbol `jiffies`.
+
+mod common;
+
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+
+fn query(args: &[&str]) -> Output {
+    common::run(&[&["query"][..], args].concat())
+}
+
+fn query_type(args: &[&str]) -> Output {
+    let btf = format!("{}/endian/tiny_le.btf", DATA);
+    query(&[&["--btf", &btf][..], args].concat())
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn types() {
+    let isf: Value = serde_json::from_str(&stdout(&query_type(&["--type", "task"]))).unwrap();
+    let task = &isf["user_types"]["task"];
+    assert_eq!(task["size"], json!(40));
+    assert_eq!(
+        task["fields"]["mode"]["type"],
+        json!({"kind": "enum", "name": "mode"})
+    );
+
+    // Aliases resolve to the same type.
+    let isf: Value = serde_json::from_str(&stdout(&query_type(&["--type", "task_t"]))).unwrap();
+    assert_eq!(&isf["user_types"]["task_t"], task);
+
+    let isf: Value = serde_json::from_str(&stdout(&query_type(&["--type", "mode"]))).unwrap();
+    assert_eq!(isf["enums"]["mode"]["constants"]["ON"], json!(1));
+}
+
+#[test]
+fn c_style() {
+    let task = stdout(&query_type(&["--type", "task", "--c-style"]));
+    assert!(
+        task.starts_with("struct task { /* size: 40 */\n"),
+        "{}",
+        task
+    );
+    assert!(task.contains("\tchar comm[16];            /* offset: 8, size: 16 */\n"));
+    assert!(task.contains("\tlong unsigned int *flags; /* offset: 24, size: 8 */\n"));
+
+    let bits = stdout(&query_type(&["--type", "bits", "--c-style"]));
+    assert!(
+        bits.contains("\tint b : 5;  /* offset: 0, bits: 3..8 */\n"),
+        "{}",
+        bits
+    );
+
+    let mode = stdout(&query_type(&["--type", "mode", "--c-style"]));
+    assert!(mode.contains("\tBIG = 305419896,\n"), "{}", mode);
+}
+
+#[test]
+fn symbols() {
+    let vars = format!("{}/vars", DATA);
+    let args = [
+        "--btf",
+        &format!("{}/vars.btf", vars),
+        "--map",
+        &format!("{}/System.map", vars),
+        "--banner",
+        "Linux version 6.18.0",
+        "--symbol",
+    ];
+    let isf: Value =
+        serde_json::from_str(&stdout(&query(&[&args[..], &["jiffies"]].concat()))).unwrap();
+    assert_eq!(
+        isf["symbols"]["jiffies"]["address"],
+        json!(0xffffffff82000000u64)
+    );
+
+    let jiffies = stdout(&query(&[&args[..], &["jiffies", "--c-style"]].concat()));
+    assert!(
+        jiffies.ends_with(" jiffies; /* address: 0xffffffff82000000, size: 8 */\n"),
+        "{}",
+        jiffies
+    );
+}
+
+#[test]
+fn unknown_names() {
+    let output = query_type(&["--type", "tsk"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("close matches: task"), "{}", stderr);
+
+    // Either a type or a symbol is queried.
+    assert_eq!(query_type(&[]).status.code(), Some(3));
+    assert_eq!(
+        query_type(&["--type", "task", "--symbol", "jiffies"])
+            .status
+            .code(),
+        Some(3)
+    );
+}
@@ -0,0 +1,147 @@
+//! Tests for `--no-quirks` and `--quirks`.
+//!
+//! `tests/data/endian/tiny_le.btf` is described in `tests/big_endian.rs`. Its
+//! base types are `char`, `int`, `unsigned int`, and `long unsigned int`.
+//! `tests/data/fwd/fwd.btf` is described in `tests/fwd_stubs.rs`, and
+//! `tests/data/vars` in `tests/variables.rs`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use btf2json::cli::Cli;
+use clap::Parser;
+use serde_json::Value;
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+const TINY_LE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endian/tiny_le.btf");
+
+/// Generates the ISF file of the BTF file `btf` in `tests/data`, with `args`,
+/// and applies the quirks of the verification.
+fn generate(btf: &str, args: &[&str]) -> Value {
+    let path = Path::new(DATA).join(btf);
+    let mut isf = common::generate_isf(&[&["--btf", path.to_str().unwrap()], args].concat());
+    isf.apply_quirks();
+    serde_json::to_value(isf).unwrap()
+}
+
+fn base_type_names(isf: &Value) -> Vec<&str> {
+    isf["base_types"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn all_by_default() {
+    let isf = generate("endian/tiny_le.btf", &["--types-only"]);
+    let names = base_type_names(&isf);
+    assert!(names.contains(&"pointer"), "{:?}", names);
+    assert!(names.contains(&"unsigned long"), "{:?}", names);
+}
+
+#[test]
+fn no_quirks() {
+    let isf = generate("endian/tiny_le.btf", &["--types-only", "--no-quirks"]);
+    assert_eq!(
+        base_type_names(&isf),
+        ["char", "int", "long unsigned int", "unsigned int", "void"]
+    );
+}
+
+#[test]
+fn selected_quirks() {
+    let isf = generate(
+        "endian/tiny_le.btf",
+        &["--types-only", "--quirks", "standard-base"],
+    );
+    let names = base_type_names(&isf);
+    assert!(!names.contains(&"pointer"), "{:?}", names);
+    assert!(names.contains(&"unsigned long"), "{:?}", names);
+
+    let isf = generate(
+        "endian/tiny_le.btf",
+        &["--types-only", "--quirks", "pointer-base"],
+    );
+    let names = base_type_names(&isf);
+    assert!(names.contains(&"pointer"), "{:?}", names);
+    assert!(!names.contains(&"unsigned long"), "{:?}", names);
+
+    // The order of the list does not matter.
+    assert_eq!(
+        generate(
+            "endian/tiny_le.btf",
+            &[
+                "--types-only",
+                "--quirks",
+                "type-stubs,void-symbols,standard-base,pointer-base"
+            ]
+        ),
+        generate(
+            "endian/tiny_le.btf",
+            &[
+                "--types-only",
+                "--quirks",
+                "pointer-base,standard-base,void-symbols,type-stubs"
+            ]
+        )
+    );
+}
+
+#[test]
+fn type_stubs() {
+    for args in [
+        &["--no-quirks"][..],
+        &["--quirks", "pointer-base"],
+        &["--no-fixups"],
+    ] {
+        let isf = generate("fwd/fwd.btf", &[&["--types-only"], args].concat());
+        assert!(isf["user_types"].get("opaque").is_none(), "{:?}", args);
+    }
+    let isf = generate("fwd/fwd.btf", &["--types-only", "--quirks", "type-stubs"]);
+    assert!(isf["user_types"].get("opaque").is_some());
+}
+
+#[test]
+fn void_symbols() {
+    let tmp = env::temp_dir().join(format!("btf2json-quirks-{}", std::process::id()));
+    fs::create_dir_all(&tmp).unwrap();
+    let symdb = tmp.join("test.symdb");
+    fs::write(
+        &symdb,
+        "plain {\"kind\":\"struct\",\"name\":\"nonexistent\"}\n",
+    )
+    .unwrap();
+    let map = Path::new(DATA).join("vars/System.map");
+    let args = [
+        "--map",
+        map.to_str().unwrap(),
+        "--symdb",
+        symdb.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    let isf = generate("vars/vars.btf", &args);
+    assert_eq!(isf["symbols"]["plain"]["type"]["kind"], "base");
+    let isf = generate("vars/vars.btf", &[&args[..], &["--no-quirks"]].concat());
+    assert_eq!(isf["symbols"]["plain"]["type"]["kind"], "struct");
+    fs::remove_dir_all(&tmp).unwrap();
+}
+
+#[test]
+fn conflicting_options() {
+    let result = Cli::try_parse_from([
+        "btf2json",
+        "--btf",
+        TINY_LE,
+        "--no-quirks",
+        "--quirks",
+        "pointer-base",
+    ]);
+    assert!(result.is_err());
+    assert!(Cli::try_parse_from(["btf2json", "--btf", TINY_LE, "--quirks", "unknown"]).is_err());
+}
@@ -0,0 +1,135 @@
+//! Tests for the detection of structure layout randomization (RANDSTRUCT).
+//!
+//! The BTF files in `tests/data/randstruct` define `task_struct` as
+//!
+//! ```c
+//! struct task_struct { int pid; int tgid; };                       // ordered.btf
+//! struct task_struct { int tgid; int pid; };                       // shuffled.btf
+//! struct task_struct { long state; struct { int pid; int tgid; }; }; // wrapped.btf
+//! ```
+
+mod common;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use btf2json::btf::Btf;
+use btf2json::randstruct::{self, BuildHint};
+use btf2json::version::KernelVersion;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+
+fn path(name: &str) -> PathBuf {
+    common::data("randstruct").join(name)
+}
+
+fn load(name: &str) -> Btf {
+    Btf::from_file(&path(name)).unwrap()
+}
+
+#[test]
+fn detect() {
+    let report = randstruct::detect(&load("ordered.btf"), None);
+    assert!(!report.is_suspected(), "{:?}", report);
+    assert_eq!(report.checked, 1);
+
+    let report = randstruct::detect(&load("shuffled.btf"), None);
+    assert!(report.is_suspected());
+    assert!(!report.wrapper_found);
+    assert_eq!(
+        report.violations,
+        ["task_struct.pid (offset 4) follows task_struct.tgid (offset 0)"]
+    );
+
+    // Kernels before 4.13 cannot randomize layouts.
+    let report = randstruct::detect(&load("shuffled.btf"), Some(KernelVersion::new(4, 9, 0)));
+    assert!(!report.is_suspected());
+    assert_eq!(report.checked, 0);
+
+    let report = randstruct::detect(&load("wrapped.btf"), None);
+    assert!(report.is_suspected());
+    assert!(report.wrapper_found);
+    assert!(report.violations.is_empty());
+    assert_eq!(report.checked, 1);
+}
+
+/// Returns a vmlinux file with the kernel `config` embedded between
+/// `IKCFG_ST` and `IKCFG_ED`, as with `CONFIG_IKCONFIG`.
+fn with_config(config: &str) -> Vec<u8> {
+    let mut enc = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    enc.write_all(config.as_bytes()).unwrap();
+    [
+        b"\x7fELF padding IKCFG_ST",
+        &enc.finish().unwrap()[..],
+        b"IKCFG_ED",
+    ]
+    .concat()
+}
+
+#[test]
+fn build_hint() {
+    assert_eq!(randstruct::build_hint(b"\x7fELF"), None);
+    assert_eq!(
+        randstruct::build_hint(b"6.18.0 SMP preempt mod_unload RANDSTRUCT_5a3c\0"),
+        Some(BuildHint::Vermagic)
+    );
+    assert_eq!(
+        randstruct::build_hint(&with_config("CONFIG_RANDSTRUCT_FULL=y\n")),
+        Some(BuildHint::Config(true))
+    );
+    assert_eq!(
+        randstruct::build_hint(&with_config("CONFIG_GCC_PLUGIN_RANDSTRUCT=y\n")),
+        Some(BuildHint::Config(true))
+    );
+    // The config takes precedence over the vermagic string.
+    let raw = [
+        &with_config("CONFIG_RANDSTRUCT_NONE=y\n# CONFIG_RANDSTRUCT_FULL is not set\n")[..],
+        b" RANDSTRUCT_5a3c",
+    ]
+    .concat();
+    assert_eq!(randstruct::build_hint(&raw), Some(BuildHint::Config(false)));
+}
+
+/// Returns the warnings with the code `randstruct_suspected` of generating the
+/// types of the BTF file `name`.
+fn warnings(name: &str) -> Vec<Value> {
+    let output = common::run(&[
+        "generate",
+        "--btf",
+        path(name).to_str().unwrap(),
+        "--arch",
+        "x86_64",
+        "--types-only",
+        "--log-format",
+        "json",
+        "--log-level",
+        "warn",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .filter(|event| event["code"] == "randstruct_suspected")
+        .collect()
+}
+
+#[test]
+fn warning_per_finding() {
+    assert!(warnings("ordered.btf").is_empty());
+
+    let events = warnings("shuffled.btf");
+    assert_eq!(events.len(), 1, "{:?}", events);
+    assert_eq!(events[0]["level"], "warn");
+    assert_eq!(events[0]["phase"], "btf-scan");
+    assert_eq!(
+        events[0]["finding"],
+        "task_struct.pid (offset 4) follows task_struct.tgid (offset 0)"
+    );
+
+    let events = warnings("wrapped.btf");
+    assert_eq!(events.len(), 1, "{:?}", events);
+    assert_eq!(
+        events[0]["finding"],
+        "task_struct contains the randomized fields wrapper"
+    );
+}
@@ -0,0 +1,152 @@
+//! Tests for raw memory images and the translation of kernel virtual
+//! addresses to physical addresses with `--phys-base` and
+//! `--kernel-virt-base`.
+//!
+//! The images are for ppc64le, whose kernel image at `0xc000000000000000`
+//! maps the physical address 0 by default. They have a stale banner before the
+//! one at the address of `linux_banner`, which searching would find first.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::process::Output;
+
+use base64::prelude::*;
+use serde_json::Value;
+
+const BANNER: &str = "Linux version 6.18.0 (btf2json@test) #1 SMP\n";
+const STALE_BANNER: &str = "Linux version 5.15.0 (btf2json@test) #1 SMP\n";
+
+fn tiny_btf() -> Vec<u8> {
+    fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/endian/tiny_le.btf"
+    ))
+    .unwrap()
+}
+
+/// Returns a raw image with the banner at the physical address `banner`, a
+/// stale banner before it, and the BTF section after it.
+fn raw_image(banner: usize) -> Vec<u8> {
+    let mut raw = vec![0; banner];
+    raw[0x100..0x100 + STALE_BANNER.len()].copy_from_slice(STALE_BANNER.as_bytes());
+    raw.extend(BANNER.as_bytes());
+    raw.resize(banner + 0x100, 0);
+    raw.extend(tiny_btf());
+    raw
+}
+
+/// Generates from the raw `image` with `linux_banner` at 0xc000000000001000.
+fn generate(name: &str, image: &[u8], args: &[&str]) -> Output {
+    let image = common::write_temp(name, image);
+    let map = common::write_temp(
+        &format!("{}-map", name),
+        b"c000000000000000 T _stext\nc000000000001000 D linux_banner\n",
+    );
+    let cli = [
+        "generate",
+        "--image",
+        image.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--arch",
+        "ppc64le",
+        "--log-level",
+        "info",
+    ];
+    let output = common::run(&[&cli, args].concat());
+    fs::remove_file(image).unwrap();
+    fs::remove_file(map).unwrap();
+    output
+}
+
+fn banner(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let data = isf["symbols"]["linux_banner"]["constant_data"]
+        .as_str()
+        .unwrap();
+    String::from_utf8(BASE64_STANDARD.decode(data).unwrap()).unwrap()
+}
+
+#[test]
+fn default_placement() {
+    let output = generate("default", &raw_image(0x1000), &[]);
+    assert_eq!(banner(&output), BANNER);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "Assuming the kernel image at virtual 0xc000000000000000 at physical 0x0, which has \
+             the Linux banner at the address of linux_banner"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn explicit_placement() {
+    // The default placement finds the stale banner at 0x100.
+    let mut image = raw_image(0x3000);
+    image[0x1000..0x1000 + STALE_BANNER.len()].copy_from_slice(STALE_BANNER.as_bytes());
+    let output = generate(
+        "explicit",
+        &image,
+        &[
+            "--phys-base",
+            "0x2000",
+            "--kernel-virt-base",
+            "0xc000000000000000",
+        ],
+    );
+    assert_eq!(banner(&output), BANNER);
+
+    let output = generate(
+        "explicit-wrong",
+        &image,
+        &[
+            "--phys-base",
+            "0x4000",
+            "--kernel-virt-base",
+            "0xc000000000000000",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "No Linux banner at the address 0xc000000000001000 of linux_banner with the kernel \
+             image at virtual 0xc000000000000000 at physical 0x4000"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn unknown_placement_is_searched() {
+    let output = generate("unknown", &raw_image(0x2000), &[]);
+    assert_eq!(banner(&output), STALE_BANNER);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "using the one at physical address 0x100, i.e., assuming the kernel image at \
+             virtual 0xc000000000001000 at physical 0x100"
+        ),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn translation_needs_both_bases() {
+    let output = generate("one-base", &raw_image(0x1000), &["--phys-base", "0x2000"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--kernel-virt-base"), "{}", stderr);
+}
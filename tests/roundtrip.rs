@@ -0,0 +1,82 @@
+//! Tests for reading ISF files back in.
+//!
+//! The profiles are generated from `tests/data/vars`, taken from the golden
+//! files, or hand written in the format of dwarf2json (`tests/data/dwarf2json`).
+
+mod common;
+
+use std::fs::File;
+#[cfg(feature = "xz")]
+use std::path::Path;
+
+use btf2json::isf::Isf;
+use serde_json::Value;
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+
+fn data_path(path: &str) -> String {
+    format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), path)
+}
+
+fn generate() -> Value {
+    common::generate(&[
+        "--btf",
+        &data_path("vars/vars.btf"),
+        "--map",
+        &data_path("vars/System.map"),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ])
+}
+
+/// Loads `isf` and returns the serialized form of the loaded ISF file.
+fn roundtrip(isf: &Value) -> Value {
+    let loaded = Isf::from_reader(serde_json::to_vec(isf).unwrap().as_slice()).unwrap();
+    serde_json::to_value(&loaded).unwrap()
+}
+
+#[test]
+fn generated() {
+    let isf = generate();
+    assert_eq!(roundtrip(&isf), isf);
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn golden() {
+    let path = data_path("linux-6.18/expected.json.xz");
+    let isf: Value = serde_json::from_reader(XzDecoder::new(File::open(&path).unwrap())).unwrap();
+    let loaded = Isf::from_path(Path::new(&path)).unwrap();
+    assert_eq!(serde_json::to_value(&loaded).unwrap(), isf);
+}
+
+#[test]
+fn dwarf2json() {
+    let path = data_path("dwarf2json/profile.json");
+    let mut isf: Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+    let loaded = roundtrip(&isf);
+
+    // Missing defaults are filled in.
+    isf["symbols"]["_text"]["type"] = serde_json::json!({"kind": "base", "name": "void"});
+    for field in isf["user_types"]
+        .as_object_mut()
+        .unwrap()
+        .values_mut()
+        .flat_map(|user| user["fields"].as_object_mut().unwrap().values_mut())
+    {
+        field
+            .as_object_mut()
+            .unwrap()
+            .entry("anonymous")
+            .or_insert(Value::Bool(false));
+    }
+    assert_eq!(loaded, isf);
+}
+
+#[test]
+fn invalid() {
+    let err = Isf::from_reader(&b"{\"metadata\": {}}"[..]).err().unwrap();
+    assert!(err.to_string().contains("missing field"), "{:#}", err);
+}
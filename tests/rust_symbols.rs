@@ -0,0 +1,102 @@
+//! Tests for `--demangle-rust`.
+//!
+//! The System.map is synthetic, with the v0-mangled names of a kernel with
+//! Rust support next to C symbols. The types are from
+//! `tests/data/funcs/funcs.btf`.
+
+mod common;
+
+use std::fs;
+
+use serde_json::{json, Value};
+
+const CALL_PRINTK: &str = "_RNvNtCs1EKtwoKEMO2_6kernel5print11call_printk";
+const MUTEX_LOCK: &str = "_RNvMNtCs1EKtwoKEMO2_6kernel4syncINtB2_5MutexmE4lockB4_";
+/// Two symbols of crates with different hashes that demangle to
+/// `kernel::a::dup`.
+const DUP: [&str; 2] = [
+    "_RNvNtCs1EKtwoKEMO2_6kernel1a3dup",
+    "_RNvNtCs1EKtwoKEMO3_6kernel1a3dup",
+];
+
+fn generate(name: &str, args: &[&str]) -> Value {
+    let map = common::write_temp(
+        &format!("{}.map", name),
+        format!(
+            "ffffffff81000000 T _stext\n\
+             ffffffff81000100 T vfs_read\n\
+             ffffffff81000200 T {}\n\
+             ffffffff81000300 t {}\n\
+             ffffffff81000400 T {}\n\
+             ffffffff81000500 T {}\n\
+             ffffffff82000000 D linux_banner\n",
+            CALL_PRINTK, MUTEX_LOCK, DUP[0], DUP[1]
+        ),
+    );
+    let btf = common::data("funcs/funcs.btf");
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    let isf = common::generate(&[&cli, args].concat());
+    fs::remove_file(&map).unwrap();
+    isf["symbols"].clone()
+}
+
+#[test]
+fn mangled_by_default() {
+    let symbols = generate("rust-default", &[]);
+    assert_eq!(
+        symbols[CALL_PRINTK]["address"],
+        json!(0xffffffff81000200u64)
+    );
+    assert!(symbols.get("kernel::print::call_printk").is_none());
+}
+
+#[test]
+fn demangled() {
+    let symbols = generate("rust-demangle", &["--demangle-rust"]);
+    assert_eq!(
+        symbols["kernel::print::call_printk"]["address"],
+        json!(0xffffffff81000200u64)
+    );
+    assert_eq!(
+        symbols["<kernel::sync::Mutex<u32>>::lock"]["address"],
+        json!(0xffffffff81000300u64)
+    );
+    // The mangled names are kept.
+    assert_eq!(
+        symbols[CALL_PRINTK]["address"],
+        json!(0xffffffff81000200u64)
+    );
+    assert_eq!(symbols[MUTEX_LOCK]["address"], json!(0xffffffff81000300u64));
+    // C symbols are untouched.
+    assert_eq!(symbols["vfs_read"]["address"], json!(0xffffffff81000100u64));
+    assert_eq!(symbols["vfs_read"]["type"]["kind"], "function");
+    assert_eq!(symbols.as_object().unwrap().len(), 9);
+}
+
+#[test]
+fn collision() {
+    let symbols = generate("rust-collision", &["--demangle-rust"]);
+    assert!(symbols.get("kernel::a::dup").is_none());
+    assert_eq!(symbols[DUP[0]]["address"], json!(0xffffffff81000400u64));
+    assert_eq!(symbols[DUP[1]]["address"], json!(0xffffffff81000500u64));
+}
+
+#[test]
+fn filtered_by_demangled_name() {
+    let symbols = generate(
+        "rust-filter",
+        &["--demangle-rust", "--symbol-filter", "^kernel::print::"],
+    );
+    // `_stext` and `linux_banner` are always kept.
+    assert_eq!(
+        symbols.as_object().unwrap().keys().collect::<Vec<_>>(),
+        ["_stext", "kernel::print::call_printk", "linux_banner"]
+    );
+}
@@ -0,0 +1,75 @@
+//! Tests for `--scan`, which searches BTF files that are neither a `.BTF`
+//! section nor an ELF file for a BTF section.
+//!
+//! The blobs embed `tests/data/funcs/funcs.btf`, see `tests/functions.rs`, in
+//! junk bytes.
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use btf2json::isf::Isf;
+use serde_json::Value;
+
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+
+/// Returns `funcs.btf` at an unaligned offset in junk, after something that
+/// looks like a BTF header but whose sections are out of bounds.
+fn blob() -> Vec<u8> {
+    let mut blob: Vec<u8> = (0..0x1233u32).map(|i| (i * 7 + 3) as u8).collect();
+    blob.extend([0x9f, 0xeb, 0x01, 0x00, 0x18, 0, 0, 0]);
+    blob.extend([0xff; 16]);
+    blob.extend(fs::read(format!("{}/funcs.btf", FUNCS)).unwrap());
+    blob.extend((0..0x777u32).map(|i| (i * 13) as u8));
+    blob
+}
+
+fn generate(btf: &str, args: &[&str]) -> Result<Value, String> {
+    let map = format!("{}/System.map", FUNCS);
+    let cli = [
+        "--btf",
+        btf,
+        "--map",
+        &map,
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    let ctx = common::context(&[&cli, args].concat())
+        .map_err(|err| format!("{:#}", anyhow::Error::from(err)))?;
+    Ok(serde_json::to_value(Isf::try_from(ctx).unwrap()).unwrap())
+}
+
+#[test]
+fn embedded() {
+    let path = common::write_temp("scan.bin", blob());
+    let scanned = generate(path.to_str().unwrap(), &["--scan"]);
+    fs::remove_file(&path).unwrap();
+    let mut bare = generate(&format!("{}/funcs.btf", FUNCS), &[]).unwrap();
+    let mut scanned = scanned.unwrap();
+
+    // Only the name and hash of the BTF file differ.
+    let types = &mut scanned["metadata"]["linux"]["types"][0];
+    assert_eq!(types["name"], path.file_name().unwrap().to_str().unwrap());
+    *types = Value::Null;
+    bare["metadata"]["linux"]["types"][0] = Value::Null;
+    assert_eq!(scanned, bare);
+}
+
+#[test]
+fn requires_scan() {
+    let path = common::write_temp("noscan.bin", blob());
+    let err = generate(path.to_str().unwrap(), &[]).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(err.contains("neither .BTF section nor ELF"), "{}", err);
+}
+
+#[test]
+fn no_btf() {
+    let mut blob = blob();
+    blob.truncate(0x1300);
+    let path = common::write_temp("scan-nobtf.bin", &blob);
+    let err = generate(path.to_str().unwrap(), &["--scan"]).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(err.contains("No BTF section found"), "{}", err);
+}
@@ -0,0 +1,139 @@
+//! Tests for `--search-dir`, on directories of copies of
+//! `tests/data/single/vmlinux`, see `tests/single_file.rs`, and of
+//! `tests/data/constdata/vmlinux`, whose banner is `Linux version
+//! 6.18.0-const`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+const BANNER: &str =
+    "Linux version 6.18.0-single (builder@host) (gcc 14.2.0) #1 SMP PREEMPT_DYNAMIC";
+
+/// Creates an empty directory for the test `name`.
+fn tmp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!(
+        "btf2json-search-dir-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Copies `tests/data/single/vmlinux` to `name` in `dir`, with the build
+/// number `build` in its banner.
+fn copy_single(dir: &Path, name: &str, build: u8) {
+    let mut raw = fs::read(common::data("single/vmlinux")).unwrap();
+    let at = raw
+        .windows(BANNER.len())
+        .position(|window| window == BANNER.as_bytes())
+        .unwrap();
+    let hash = at + BANNER.find('#').unwrap();
+    raw[hash + 1] = build;
+    fs::write(dir.join(name), raw).unwrap();
+}
+
+fn generate(dir: &Path, banner: &str, args: &[&str]) -> btf2json::error::Result<Value> {
+    let cli = ["--search-dir", dir.to_str().unwrap(), "--banner", banner];
+    common::try_generate(&[&cli, args].concat())
+}
+
+fn error(result: btf2json::error::Result<Value>) -> String {
+    format!("{:#}", anyhow::Error::from(result.err().unwrap()))
+}
+
+#[test]
+fn exact_match() {
+    let dir = tmp_dir("exact");
+    copy_single(&dir, "vmlinux-6.18.0-single", b'1');
+    copy_single(&dir, "vmlinux-6.18.0-single.old", b'2');
+    fs::copy(
+        common::data("constdata/vmlinux"),
+        dir.join("vmlinux-6.18.0-const"),
+    )
+    .unwrap();
+
+    let isf = generate(&dir, BANNER, &[]).unwrap();
+    assert_eq!(
+        isf["metadata"]["linux"]["types"][0]["name"],
+        "vmlinux-6.18.0-single"
+    );
+    assert_eq!(isf["symbols"]["jiffies"]["address"], 0xffffffff82000010u64);
+
+    // The other build.
+    let banner = BANNER.replace("#1", "#2");
+    let isf = generate(&dir, &banner, &[]).unwrap();
+    assert_eq!(
+        isf["metadata"]["linux"]["types"][0]["name"],
+        "vmlinux-6.18.0-single.old"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn near_matches() {
+    let dir = tmp_dir("near");
+    copy_single(&dir, "vmlinux-6.18.0-single", b'1');
+    copy_single(&dir, "vmlinux-6.18.0-single.old", b'2');
+    fs::copy(
+        common::data("constdata/vmlinux"),
+        dir.join("vmlinux-6.18.0-const"),
+    )
+    .unwrap();
+
+    let err = error(generate(&dir, &BANNER.replace("#1", "#3"), &[]));
+    assert!(err.contains("these have the same release"), "{}", err);
+    assert!(err.contains("vmlinux-6.18.0-single: "), "{}", err);
+    assert!(err.contains("vmlinux-6.18.0-single.old: "), "{}", err);
+    assert!(!err.contains("6.18.0-const"), "{}", err);
+
+    let err = error(generate(&dir, "Linux version 6.19.0", &[]));
+    assert!(err.contains("No kernel in"), "{}", err);
+    assert!(!err.contains("same release"), "{}", err);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ambiguous() {
+    let dir = tmp_dir("ambiguous");
+    copy_single(&dir, "vmlinux-6.18.0-single", b'1');
+    copy_single(&dir, "vmlinux-6.18.0-single.copy", b'1');
+
+    let err = error(generate(&dir, BANNER, &[]));
+    assert!(err.contains("Several kernels"), "{}", err);
+    assert!(err.contains("vmlinux-6.18.0-single\n"), "{}", err);
+    assert!(err.contains("vmlinux-6.18.0-single.copy"), "{}", err);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn cached_banners() {
+    let dir = tmp_dir("cached");
+    let cache = dir.join("cache");
+    let kernels = dir.join("kernels");
+    fs::create_dir(&kernels).unwrap();
+    copy_single(&kernels, "vmlinux-6.18.0-single", b'1');
+    let args = ["--cache-dir", cache.to_str().unwrap()];
+
+    generate(&kernels, BANNER, &args).unwrap();
+    let cached = fs::read_to_string(cache.join("search-banners.json")).unwrap();
+    assert!(cached.contains("vmlinux-6.18.0-single"), "{}", cached);
+    assert!(cached.contains("#1 SMP"), "{}", cached);
+
+    // A modified kernel is read again, the size tells even if the modification
+    // time does not.
+    copy_single(&kernels, "vmlinux-6.18.0-single", b'2');
+    let path = kernels.join("vmlinux-6.18.0-single");
+    let mut raw = fs::read(&path).unwrap();
+    raw.push(0);
+    fs::write(&path, raw).unwrap();
+    let err = error(generate(&kernels, BANNER, &args));
+    assert!(err.contains("#2 SMP"), "{}", err);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,83 @@
+//! Tests for generating a profile from a debug vmlinux alone.
+//!
+//! `tests/data/single/vmlinux` is a hand-crafted x86_64 vmlinux with BTF for
+//!
+//! ```c
+//! unsigned long jiffies;
+//! struct task_struct { int pid; unsigned long state; } init_task;
+//! const char linux_banner[80];
+//! ```
+//!
+//! the banner `Linux version 6.18.0-single (builder@host) (gcc 14.2.0) #1 SMP
+//! PREEMPT_DYNAMIC` in `.rodata`, and a symbol table, for which `nm` prints
+//!
+//! ```text
+//! ffffffff81000000 T _stext
+//! ffffffff82000020 D init_task
+//! ffffffff82000010 D jiffies
+//! ffffffff82800000 R linux_banner
+//! ```
+//!
+//! `tests/data/single/vmlinux-stripped` is the same without symbol table. Its
+//! BTF has no data sections to synthesize symbols from.
+
+mod common;
+
+use serde_json::{json, Value};
+
+const SINGLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/single");
+
+fn btf2json(args: &[&str]) -> (i32, Vec<u8>, String) {
+    let output = common::run(args);
+    (
+        output.status.code().unwrap(),
+        output.stdout,
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn vmlinux_only() {
+    let vmlinux = format!("{}/vmlinux", SINGLE);
+    let (code, stdout, stderr) = btf2json(&["generate", "--btf", &vmlinux, "--strict"]);
+    assert_eq!(code, 0, "{}", stderr);
+    let isf: Value = serde_json::from_slice(&stdout).unwrap();
+
+    let symbols = &isf["symbols"];
+    assert_eq!(symbols["jiffies"]["address"], json!(0xffffffff82000010u64));
+    assert_eq!(
+        symbols["init_task"]["type"],
+        json!({"kind": "struct", "name": "task_struct"})
+    );
+    assert_eq!(
+        symbols["linux_banner"]["constant_data"],
+        "TGludXggdmVyc2lvbiA2LjE4LjAtc2luZ2xlIChidWlsZGVyQGhvc3QpIChnY2MgMTQuMi4wKSAjMSBTTVAgUFJFRU1QVF9EWU5BTUlDCg=="
+    );
+    assert!(isf["user_types"]["task_struct"].is_object());
+
+    let linux = &isf["metadata"]["linux"];
+    assert_eq!(linux["symbols"][0]["kind"], "symtab");
+    assert_eq!(linux["symbols"][0]["name"], "vmlinux");
+    assert_eq!(linux["kernel_version"], "6.18.0-single");
+
+    // The profile passes the checks of the checker.
+    let profile = std::env::temp_dir().join(format!("btf2json-single-{}.json", std::process::id()));
+    std::fs::write(&profile, &stdout).unwrap();
+    let result = btf2json(&["check", profile.to_str().unwrap()]);
+    std::fs::remove_file(&profile).unwrap();
+    assert_eq!(result.0, 0, "{}", result.2);
+}
+
+#[test]
+fn stripped_requires_map() {
+    let vmlinux = format!("{}/vmlinux-stripped", SINGLE);
+    let (code, _, stderr) = btf2json(&["generate", "--btf", &vmlinux]);
+    assert_eq!(code, 1);
+    assert!(
+        stderr.contains(
+            "vmlinux-stripped has no symbol table and no BTF variables, pass its System.map with --map"
+        ),
+        "{}",
+        stderr
+    );
+}
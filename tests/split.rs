@@ -0,0 +1,86 @@
+//! Tests for split BTF, e.g., of kernel modules.
+//!
+//! `tests/data/split/btf2json_test.btf` is a hand-crafted split BTF on top of
+//! the vmlinux BTF in `tests/data/linux-6.18`. It defines:
+//!
+//! ```c
+//! enum btf2json_test_mode { BTF2JSON_TEST_OFF, BTF2JSON_TEST_ON };
+//!
+//! struct btf2json_test_mod {
+//!     int refcnt;
+//!     enum btf2json_test_mode mode;
+//!     struct list_head list;
+//!     unsigned long *flags;
+//! };
+//! ```
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use serde_json::json;
+use xz2::read::XzDecoder;
+
+fn data_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"))
+}
+
+#[test]
+fn module_types_reference_base_types() {
+    let base = env::temp_dir().join(format!("btf2json-split-{}.btf", std::process::id()));
+    let mut data = Vec::new();
+    XzDecoder::new(File::open(data_dir().join("linux-6.18/vmlinux.btf.xz")).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    fs::write(&base, data).unwrap();
+
+    let module = data_dir().join("split/btf2json_test.btf");
+    let isf = common::generate(&[
+        "--base-btf",
+        base.to_str().unwrap(),
+        "--btf",
+        module.to_str().unwrap(),
+        "--types-only",
+    ]);
+    fs::remove_file(&base).unwrap();
+
+    let fields = &isf["user_types"]["btf2json_test_mod"]["fields"];
+    assert_eq!(
+        fields["refcnt"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+    assert_eq!(
+        fields["mode"]["type"],
+        json!({"kind": "enum", "name": "btf2json_test_mode"})
+    );
+    assert_eq!(
+        fields["list"]["type"],
+        json!({"kind": "struct", "name": "list_head"})
+    );
+    assert_eq!(fields["list"]["offset"], json!(8));
+    assert_eq!(
+        fields["flags"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "base", "name": "long unsigned int"}})
+    );
+    assert!(isf["enums"]["btf2json_test_mode"].is_object());
+    // Base types are part of the same profile.
+    assert!(isf["user_types"]["list_head"].is_object());
+    assert!(isf["user_types"]["task_struct"].is_object());
+    assert_eq!(
+        isf["metadata"]["linux"]["types"].as_array().unwrap().len(),
+        2
+    );
+}
+
+#[test]
+fn split_base_is_rejected() {
+    let module = data_dir().join("split/btf2json_test.btf");
+    let module = module.to_str().unwrap();
+    let err = common::try_generate(&["--base-btf", module, "--btf", module, "--types-only"])
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("is split BTF itself"), "{}", err);
+}
@@ -0,0 +1,57 @@
+//! Tests for `--stable-anon-names`.
+//!
+//! `tests/data/stable/a.btf` and `tests/data/stable/b.btf` are hand-crafted
+//! BTF sections that define the same types, but in a different order, and
+//! `b.btf` additionally defines `struct pad { unsigned int p; }`:
+//!
+//! ```c
+//! struct outer {
+//!     struct { int a; int a2; } x;
+//!     union { int b; int c; } y;
+//!     enum { E_A, E_B } z;
+//!     struct { int a; int a2; } w;
+//! };
+//! ```
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(btf: &str, args: &[&str]) -> Value {
+    let path = common::data("stable").join(btf);
+    common::generate(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())
+}
+
+fn field_types(isf: &Value) -> Vec<Value> {
+    let fields = &isf["user_types"]["outer"]["fields"];
+    ["x", "y", "z", "w"]
+        .iter()
+        .map(|f| fields[f]["type"].clone())
+        .collect()
+}
+
+#[test]
+fn independent_of_ids() {
+    let a = generate("a.btf", &["--stable-anon-names"]);
+    let b = generate("b.btf", &["--stable-anon-names"]);
+    assert_eq!(field_types(&a), field_types(&b));
+    assert_eq!(
+        field_types(&a),
+        [
+            json!({"kind": "struct", "name": "unnamed_struct_372700"}),
+            json!({"kind": "union", "name": "unnamed_union_1f5dce"}),
+            json!({"kind": "enum", "name": "unnamed_enum_555e7f"}),
+            // Same content as `x`.
+            json!({"kind": "struct", "name": "unnamed_struct_372700_1"}),
+        ]
+    );
+    for name in ["unnamed_struct_372700", "unnamed_struct_372700_1"] {
+        assert_eq!(a["user_types"][name], b["user_types"][name]);
+    }
+
+    // By default, the names follow the IDs.
+    assert_ne!(
+        field_types(&generate("a.btf", &[])),
+        field_types(&generate("b.btf", &[]))
+    );
+}
@@ -0,0 +1,42 @@
+//! Tests for the standard base types that Volatility expects.
+//!
+//! `tests/data/endian/tiny_le.btf` is described in `tests/big_endian.rs`. Its
+//! base types are `char`, `int`, `unsigned int`, and `long unsigned int`.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate(args: &[&str]) -> Value {
+    let path = common::data("endian/tiny_le.btf");
+    common::generate(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())
+}
+
+fn int(size: u8, signed: bool) -> Value {
+    json!({"size": size, "signed": signed, "kind": "int", "endian": "little"})
+}
+
+#[test]
+fn missing_spellings() {
+    let base_types = &generate(&[])["base_types"];
+    // Aliases of types in BTF.
+    assert_eq!(base_types["unsigned long"], int(8, false));
+    assert_eq!(base_types["long long unsigned int"], int(8, false));
+    // Synthesized.
+    assert_eq!(base_types["long long"], int(8, true));
+    assert_eq!(base_types["short"], int(2, true));
+    assert_eq!(base_types["unsigned char"], int(1, false));
+    assert_eq!(base_types["_Bool"]["kind"], json!("bool"));
+    assert_eq!(base_types["__int128"], int(16, true));
+    assert_eq!(base_types["__int128 unsigned"], int(16, false));
+    assert_eq!(base_types["unsigned __int128"], int(16, false));
+}
+
+#[test]
+fn long_follows_architecture() {
+    let base_types = &generate(&["--arch", "x86"])["base_types"];
+    assert_eq!(base_types["long"], int(4, true));
+    assert_eq!(base_types["long long"], int(8, true));
+    // There is no `__int128` on 32-bit architectures.
+    assert!(base_types.get("__int128").is_none());
+}
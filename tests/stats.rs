@@ -0,0 +1,54 @@
+//! Tests for the `stats` subcommand.
+//!
+//! `tests/data/endian/tiny_le.btf` is described in `tests/big_endian.rs`.
+//! `tests/data/vars` has 7 symbols, 6 of which are variables or functions in
+//! BTF.
+
+mod common;
+
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+
+fn stats(args: &[&str]) -> Output {
+    let output = common::run(&[&["stats"][..], args].concat());
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+#[test]
+fn table() {
+    let output = stats(&["--btf", &format!("{}/endian/tiny_le.btf", DATA)]);
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("structs               2\n"), "{}", table);
+    assert!(table.contains("typedefs              1\n"), "{}", table);
+    assert!(
+        table.ends_with("largest structs:\n  task  40\n  bits   4\n"),
+        "{}",
+        table
+    );
+    // Without a symbol source there are no symbol counts.
+    assert!(!table.contains("symbols"), "{}", table);
+}
+
+#[test]
+fn json_without_banner() {
+    let output = stats(&[
+        "--btf",
+        &format!("{}/vars/vars.btf", DATA),
+        "--map",
+        &format!("{}/vars/System.map", DATA),
+        "--format",
+        "json",
+    ]);
+    let stats: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["structs"], json!(1));
+    assert_eq!(stats["base_types"], json!(5));
+    assert_eq!(
+        stats["largest_structs"],
+        json!([{"name": "stats", "size": 8}])
+    );
+    assert_eq!(stats["symbols"], json!({"total": 7, "with_types": 6}));
+}
@@ -0,0 +1,78 @@
+//! Tests for inputs that are read from standard input.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+use serde_json::{json, Value};
+
+fn vars_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars"))
+}
+
+/// Runs btf2json with `args` and `stdin` on standard input.
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_btf2json"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn generate(map: &str, stdin: &[u8]) -> Value {
+    let btf = vars_dir().join("vars.btf");
+    let output = run(
+        &[
+            "--btf",
+            btf.to_str().unwrap(),
+            "--map",
+            map,
+            "--banner",
+            "Linux version 6.18.0",
+            "--arch",
+            "x86_64",
+        ],
+        stdin,
+    );
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn system_map_from_stdin() {
+    let map = vars_dir().join("System.map");
+    let from_file = generate(map.to_str().unwrap(), b"");
+    let from_stdin = generate("-", &std::fs::read(&map).unwrap());
+
+    assert_eq!(from_stdin["symbols"], from_file["symbols"]);
+    let (file_meta, stdin_meta) = (
+        &from_file["metadata"]["linux"]["symbols"][0],
+        &from_stdin["metadata"]["linux"]["symbols"][0],
+    );
+    assert_eq!(stdin_meta["name"], json!("<stdin>"));
+    assert_eq!(stdin_meta["hash_value"], file_meta["hash_value"]);
+}
+
+#[test]
+fn single_input_from_stdin() {
+    let output = run(
+        &[
+            "--btf",
+            "-",
+            "--map",
+            "-",
+            "--banner",
+            "Linux version 6.18.0",
+        ],
+        b"",
+    );
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Only one input can be read from standard input"));
+}
@@ -0,0 +1,46 @@
+//! Tests that standard output only carries the ISF.
+
+mod common;
+
+use std::process::Output;
+
+use serde_json::Value;
+
+fn run(args: &[&str]) -> Output {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+#[test]
+fn stdout_is_single_json_document() {
+    // Debug output must go to standard error.
+    let output = run(&["--debug"]);
+
+    assert!(output.status.success());
+    let mut docs = serde_json::Deserializer::from_slice(&output.stdout).into_iter::<Value>();
+    assert!(docs.next().unwrap().unwrap().is_object());
+    assert!(docs.next().is_none());
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn errors_go_to_stderr() {
+    let output = run(&["--base-btf", "/nonexistent"]);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unable to gather information"));
+}
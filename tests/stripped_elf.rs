@@ -0,0 +1,86 @@
+//! Tests for ELF files whose section headers are stripped or broken.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// File offset of the loadable segment.
+const SEGMENT_OFF: usize = 0x1000;
+/// Bytes of other data around the BTF section within the segment.
+const PADDING: usize = 0x100;
+
+fn tiny_btf() -> PathBuf {
+    Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/endian/tiny_le.btf"
+    ))
+    .to_owned()
+}
+
+/// Returns a little endian ELF64 file with a single loadable segment that
+/// contains `btf`, and with the given section header table fields.
+fn elf_with_btf(btf: &[u8], e_shoff: u64, e_shnum: u16) -> Vec<u8> {
+    let segment_len = (2 * PADDING + btf.len()) as u64;
+    let mut elf = Vec::new();
+    // ELF header
+    elf.extend(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+    elf.extend(2u16.to_le_bytes()); // e_type: EXEC
+    elf.extend(62u16.to_le_bytes()); // e_machine: x86_64
+    elf.extend(1u32.to_le_bytes()); // e_version
+    elf.extend(0u64.to_le_bytes()); // e_entry
+    elf.extend(64u64.to_le_bytes()); // e_phoff
+    elf.extend(e_shoff.to_le_bytes());
+    elf.extend(0u32.to_le_bytes()); // e_flags
+    elf.extend(64u16.to_le_bytes()); // e_ehsize
+    elf.extend(56u16.to_le_bytes()); // e_phentsize
+    elf.extend(1u16.to_le_bytes()); // e_phnum
+    elf.extend(64u16.to_le_bytes()); // e_shentsize
+    elf.extend(e_shnum.to_le_bytes());
+    elf.extend(0u16.to_le_bytes()); // e_shstrndx
+                                    // Program header
+    elf.extend(1u32.to_le_bytes()); // p_type: LOAD
+    elf.extend(4u32.to_le_bytes()); // p_flags: R
+    elf.extend((SEGMENT_OFF as u64).to_le_bytes());
+    elf.extend(0xffffffff81000000u64.to_le_bytes()); // p_vaddr
+    elf.extend(0xffffffff81000000u64.to_le_bytes()); // p_paddr
+    elf.extend(segment_len.to_le_bytes()); // p_filesz
+    elf.extend(segment_len.to_le_bytes()); // p_memsz
+    elf.extend(0x1000u64.to_le_bytes()); // p_align
+                                         // Segment
+    elf.resize(SEGMENT_OFF, 0);
+    elf.extend([0xaa; PADDING]);
+    elf.extend(btf);
+    elf.extend([0x55; PADDING]);
+    elf
+}
+
+fn generate(path: &Path) -> Value {
+    let mut isf = common::generate(&["--btf", path.to_str().unwrap(), "--types-only"]);
+    isf.as_object_mut().unwrap().remove("metadata");
+    isf
+}
+
+fn generate_from_elf(name: &str, elf: &[u8]) -> Value {
+    let path = common::write_temp(&format!("{}.elf", name), elf);
+    let isf = generate(&path);
+    fs::remove_file(&path).unwrap();
+    isf
+}
+
+#[test]
+fn zeroed_section_headers() {
+    let btf = fs::read(tiny_btf()).unwrap();
+    let isf = generate_from_elf("zeroed-shdrs", &elf_with_btf(&btf, 0, 0));
+    assert_eq!(isf, generate(&tiny_btf()));
+}
+
+#[test]
+fn section_headers_beyond_file() {
+    let btf = fs::read(tiny_btf()).unwrap();
+    let isf = generate_from_elf("broken-shdrs", &elf_with_btf(&btf, 0x100000, 8));
+    assert_eq!(isf, generate(&tiny_btf()));
+}
@@ -0,0 +1,92 @@
+//! Tests for the subcommands of the binary and the deprecated invocation
+//! without a subcommand.
+//!
+//! `tests/data/fwd/fwd.btf` is described in `tests/fwd_stubs.rs`, its user type
+//! `user` references types that are only declared. The profile of
+//! `tests/data/vars` is valid.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+
+/// Generates a profile with `args` into a temporary file for the test `name`.
+fn generate(name: &str, args: &[&str]) -> PathBuf {
+    let path = env::temp_dir().join(format!(
+        "btf2json-subcommands-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    let output = common::run(&[&["generate", "--output", path.to_str().unwrap()], args].concat());
+    assert!(output.status.success(), "{:?}", output);
+    path
+}
+
+#[test]
+fn legacy_invocation() {
+    let fwd = format!("{}/fwd/fwd.btf", DATA);
+    let args = ["--btf", &fwd, "--types-only"];
+
+    let new = common::run(&[&["generate"][..], &args].concat());
+    assert!(new.status.success());
+    assert!(!String::from_utf8_lossy(&new.stderr).contains("deprecated"));
+
+    let legacy = common::run(&args);
+    assert!(legacy.status.success());
+    assert_eq!(legacy.stdout, new.stdout);
+    let stderr = String::from_utf8_lossy(&legacy.stderr);
+    assert!(stderr.contains("deprecated"), "{}", stderr);
+    assert!(stderr.contains("btf2json generate"), "{}", stderr);
+
+    // Options go either before or after `generate`, not both.
+    assert_eq!(
+        common::run(&[&args[..], &["generate"]].concat())
+            .status
+            .code(),
+        Some(3)
+    );
+    // Global options go anywhere.
+    assert!(
+        common::run(&[&["generate", "--verbose"][..], &args].concat())
+            .status
+            .success()
+    );
+}
+
+#[test]
+fn check() {
+    let vars = format!("{}/vars", DATA);
+    let valid = generate(
+        "valid",
+        &[
+            "--btf",
+            &format!("{}/vars.btf", vars),
+            "--map",
+            &format!("{}/System.map", vars),
+            "--banner",
+            "Linux version 6.18.0",
+        ],
+    );
+    let output = common::run(&["check", valid.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(": ok\n"));
+    fs::remove_file(&valid).unwrap();
+
+    let broken = generate(
+        "broken",
+        &[
+            "--btf",
+            &format!("{}/fwd/fwd.btf", DATA),
+            "--types-only",
+            "--no-fixups",
+        ],
+    );
+    let output = common::run(&["check", broken.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("undefined"), "{}", stderr);
+    fs::remove_file(&broken).unwrap();
+}
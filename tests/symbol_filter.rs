@@ -0,0 +1,125 @@
+//! Tests for filtering symbols by name, kind, and scope.
+//!
+//! The symbols are from `tests/data/funcs`, see `tests/functions.rs`.
+
+mod common;
+
+use btf2json::cli::Cli;
+use clap::Parser;
+
+fn symbol_names(filters: &[&str]) -> Vec<String> {
+    let (btf, map) = (
+        common::data("funcs/funcs.btf"),
+        common::data("funcs/System.map"),
+    );
+    let args = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    let isf = common::generate(&[&args, filters].concat());
+    let mut names: Vec<String> = isf["symbols"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn no_filters() {
+    assert_eq!(symbol_names(&[]).len(), 9);
+}
+
+#[test]
+fn include_filter() {
+    assert_eq!(
+        symbol_names(&["--symbol-filter", "^vfs_"]),
+        ["_stext", "linux_banner", "vfs_read"]
+    );
+}
+
+#[test]
+fn exclude_filter() {
+    assert_eq!(
+        symbol_names(&["--symbol-exclude", "^(do_|weak)|t"]),
+        ["_stext", "helper", "linux_banner", "vfs_read"]
+    );
+}
+
+#[test]
+fn include_and_exclude_filter() {
+    assert_eq!(
+        symbol_names(&["--symbol-filter", "^[a-h]", "--symbol-exclude", "^b"]),
+        ["_stext", "datafn", "do_init", "helper", "linux_banner"]
+    );
+}
+
+#[test]
+fn kind_filter() {
+    // The case of the kinds does not matter.
+    assert_eq!(
+        symbol_names(&["--symbol-kinds", "t"]),
+        [
+            "_stext",
+            "do_init",
+            "helper",
+            "linux_banner",
+            "twice",
+            "vfs_read"
+        ]
+    );
+    assert_eq!(
+        symbol_names(&["--symbol-kinds", "W,d"]),
+        ["_stext", "both", "datafn", "linux_banner", "weakfn"]
+    );
+}
+
+#[test]
+fn globals_only() {
+    assert_eq!(
+        symbol_names(&["--globals-only"]),
+        [
+            "_stext",
+            "both",
+            "datafn",
+            "linux_banner",
+            "vfs_read",
+            "weakfn"
+        ]
+    );
+    assert_eq!(
+        symbol_names(&["--globals-only", "--symbol-kinds", "T,R"]),
+        ["_stext", "linux_banner", "vfs_read"]
+    );
+}
+
+#[test]
+fn invalid_kinds() {
+    let err = Cli::try_parse_from(["btf2json", "--symbol-kinds", "T,X"]).unwrap_err();
+    assert!(
+        err.to_string().contains("X is not a valid symbol kind"),
+        "{}",
+        err
+    );
+    let err = Cli::try_parse_from(["btf2json", "--symbol-kinds", "TD"]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("\"TD\" is not a single type letter"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn invalid_regex() {
+    let err = Cli::try_parse_from(["btf2json", "--symbol-filter", "("]).unwrap_err();
+    assert!(err.to_string().contains("regex"), "{}", err);
+}
@@ -0,0 +1,208 @@
+//! Tests for external symdb files.
+//!
+//! The symbols are from `tests/data/funcs`, see `tests/functions.rs`. Without
+//! symdb, `_stext` is typed by the embedded symdb and `datafn` is untyped.
+//! `tests/data/symdb/custom.symdb` has entries for `_stext`, `datafn`, and
+//! `vfs_read`. `tests/data/symdb/versioned.symdb` types `datafn` as `long` on
+//! kernels `>=5.15 <6.2` and as `char` on kernels `>=6.2`.
+
+mod common;
+
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+fn generate(symdb: Option<&str>) -> anyhow::Result<Value> {
+    generate_for(symdb, "Linux version 6.18.0")
+}
+
+fn generate_for(symdb: Option<&str>, banner: &str) -> anyhow::Result<Value> {
+    let (btf, map) = (
+        common::data("funcs/funcs.btf"),
+        common::data("funcs/System.map"),
+    );
+    let mut args = vec![
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        banner,
+        "--arch",
+        "x86_64",
+    ];
+    let symdb = symdb.map(|name| common::data("symdb").join(name));
+    if let Some(symdb) = &symdb {
+        args.extend(["--symdb", symdb.to_str().unwrap()]);
+    }
+    Ok(common::try_generate(&args)?)
+}
+
+#[test]
+fn embedded_symdb() {
+    let isf = generate(None).unwrap();
+
+    assert_eq!(
+        isf["symbols"]["_stext"]["type"],
+        json!({"kind": "array", "count": 0, "subtype": {"kind": "base", "name": "char"}})
+    );
+    assert_eq!(
+        isf["symbols"]["datafn"]["type"],
+        json!({"kind": "base", "name": "void"})
+    );
+    assert_eq!(
+        isf["metadata"]["linux"]["symbols"][1]["name"],
+        json!("dummy.symdb")
+    );
+}
+
+#[test]
+fn external_symdb_wins() {
+    let isf = generate(Some("custom.symdb")).unwrap();
+
+    assert_eq!(
+        isf["symbols"]["_stext"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+    assert_eq!(
+        isf["symbols"]["datafn"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "base", "name": "void"}})
+    );
+    // BTF still takes precedence over all symdbs.
+    assert_eq!(
+        isf["symbols"]["vfs_read"]["type"]["kind"],
+        json!("function")
+    );
+}
+
+#[test]
+fn external_symdb_in_metadata() {
+    let isf = generate(Some("custom.symdb")).unwrap();
+    let source = &isf["metadata"]["linux"]["symbols"][1];
+
+    assert_eq!(source["kind"], json!("symdb"));
+    assert_eq!(source["name"], json!("custom.symdb"));
+}
+
+#[test]
+fn malformed_lines_are_skipped() {
+    // The valid entries still apply, see `symdb check` for the others.
+    for symdb in ["malformed.symdb", "badtype.symdb"] {
+        let isf = generate(Some(symdb)).unwrap();
+        assert_eq!(
+            isf["symbols"]["_stext"]["type"],
+            json!({"kind": "base", "name": "int"})
+        );
+        assert_eq!(isf["symbols"]["datafn"]["type"]["name"], json!("void"));
+    }
+}
+
+fn versioned_type(banner: &str) -> Value {
+    let isf = generate_for(Some("versioned.symdb"), banner).unwrap();
+    isf["symbols"]["datafn"]["type"]["name"].clone()
+}
+
+#[test]
+fn versioned_entries() {
+    assert_eq!(versioned_type("Linux version 5.14.21"), json!("void"));
+    assert_eq!(versioned_type("Linux version 5.15"), json!("long"));
+    assert_eq!(versioned_type("Linux version 6.1.99 (gcc)"), json!("long"));
+    assert_eq!(versioned_type("Linux version 6.2.0"), json!("char"));
+    assert_eq!(versioned_type("Linux version 6.18.0"), json!("char"));
+}
+
+#[test]
+fn versioned_entries_with_distro_versions() {
+    assert_eq!(
+        versioned_type("Linux version 5.15.0-91-generic (buildd@lcy02-amd64-045)"),
+        json!("long")
+    );
+    assert_eq!(
+        versioned_type("Linux version 6.2.0-rc3+ #1 SMP"),
+        json!("char")
+    );
+}
+
+#[test]
+fn versioned_entries_need_kernel_version() {
+    let isf = generate_for(Some("versioned.symdb"), "custom kernel").unwrap();
+
+    assert_eq!(
+        isf["symbols"]["_stext"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+    assert_eq!(isf["symbols"]["datafn"]["type"]["name"], json!("void"));
+}
+
+#[test]
+fn invalid_version_is_skipped() {
+    let isf = generate(Some("badversion.symdb")).unwrap();
+    assert_eq!(isf["symbols"]["datafn"]["type"]["name"], json!("void"));
+}
+
+fn symdb(args: &[&str], symdb: &str) -> Output {
+    let path = common::data("symdb").join(symdb);
+    common::run(&[&["symdb"], args, &["--symdb", path.to_str().unwrap()]].concat())
+}
+
+#[test]
+fn check() {
+    let output = symdb(&["check"], "versioned.symdb");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "versioned.symdb: ok, 3 entries\n"
+    );
+
+    for (symdb, line) in [
+        (
+            "malformed.symdb",
+            "malformed.symdb:2: datafn: Expected `<name> <type>`",
+        ),
+        (
+            "badtype.symdb",
+            "badtype.symdb:2: datafn {\"kind\":\"nope\"}: Invalid type of datafn",
+        ),
+        ("badversion.symdb", "badversion.symdb:1: datafn >=5.x"),
+    ] {
+        let output = self::symdb(&["check"], symdb);
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(line), "{}", stderr);
+        assert!(stderr.contains(": 1 invalid lines"), "{}", stderr);
+    }
+}
+
+#[test]
+fn dump() {
+    let output = symdb(&["dump"], "versioned.symdb");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "int _stext;\n\
+         long datafn; /* >=5.15.0 <6.2.0 */\n\
+         char datafn; /* >=6.2.0 */\n"
+    );
+
+    let output = symdb(&["dump", "--format", "json"], "custom.symdb");
+    assert!(output.status.success(), "{:?}", output);
+    let entries: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        entries[1],
+        json!({
+            "line": 2,
+            "name": "datafn",
+            "type": {"kind": "pointer", "subtype": {"kind": "base", "name": "void"}},
+        })
+    );
+    // Empty lines are skipped.
+    assert_eq!(entries[2]["line"], 4);
+}
+
+#[test]
+fn dump_embedded() {
+    let output = common::run(&["symdb", "dump"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("void .E_copy;\n"), "{}", stdout);
+}
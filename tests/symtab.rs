@@ -0,0 +1,92 @@
+//! Tests for symbols from the ELF symbol table of vmlinux.
+//!
+//! `tests/data/symtab/vmlinux` is the vmlinux of `tests/data/mapless` with a
+//! symbol table, for which `nm` prints
+//!
+//! ```text
+//! ffffffff81200000 T _stext
+//! 0000000000001234 A abs_sym
+//! 0000000000000010 N btf_sym
+//! ffffffff83000008 B counter
+//! ffffffff81200100 t do_work
+//! ffffffff82000018 d dup
+//! ffffffff82000020 d dup
+//! ffffffff82000010 D jiffies
+//! ffffffff82800100 R linux_banner
+//! 0000000000000040 d runqueues
+//!                  U undef_sym
+//! ffffffff81200200 W weak_fn
+//! ffffffff82000040 V weak_obj
+//! ```
+//!
+//! It also has symbols for the file and the `.data` section.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate() -> Value {
+    let btf = common::data("symtab/vmlinux");
+    common::generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+    ])
+}
+
+#[test]
+fn symbols_from_symtab() {
+    let isf = generate();
+    let symbols = isf["symbols"].as_object().unwrap();
+
+    let mut names: Vec<&str> = symbols.keys().map(String::as_str).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "_stext",
+            "abs_sym",
+            "counter",
+            "do_work",
+            "jiffies",
+            "linux_banner",
+            "runqueues",
+            "weak_fn",
+            "weak_obj"
+        ]
+    );
+}
+
+#[test]
+fn symtab_is_rebased() {
+    let isf = generate();
+    let symbols = &isf["symbols"];
+
+    // Shifted by 0x200000 to move `_stext` to the default base offset.
+    assert_eq!(symbols["_stext"]["address"], json!(0xffffffff81000000u64));
+    assert_eq!(symbols["jiffies"]["address"], json!(0xffffffff81e00010u64));
+    // Per-CPU offsets are not rebased.
+    assert_eq!(symbols["runqueues"]["address"], json!(0x40));
+    assert_eq!(
+        symbols["jiffies"]["type"],
+        json!({"kind": "base", "name": "long unsigned int"})
+    );
+    assert_eq!(
+        symbols["linux_banner"]["address"],
+        json!(0xffffffff82600100u64)
+    );
+    assert_eq!(
+        symbols["linux_banner"]["constant_data"],
+        json!("TGludXggdmVyc2lvbiA2LjE4LjAK")
+    );
+}
+
+#[test]
+fn symtab_in_metadata() {
+    let isf = generate();
+    let source = &isf["metadata"]["linux"]["symbols"][0];
+
+    assert_eq!(source["kind"], json!("symtab"));
+    assert_eq!(source["name"], json!("vmlinux"));
+}
@@ -0,0 +1,281 @@
+//! Tests of the naming, the partitioning, and the construction of types on BTF
+//! that is built with `btf2json::testutil`.
+
+use btf2json::btf::Id;
+use btf2json::cli::Architecture;
+use btf2json::testutil::{BtfBuilder, VOID};
+use btf2json::GenerationOptions;
+use serde_json::{json, Value};
+
+/// Generates the types of `btf`.
+fn generate(btf: &BtfBuilder) -> Value {
+    let isf = GenerationOptions::new()
+        .btf_bytes("synthetic.btf", btf.bytes())
+        .banner("Linux version 6.18.0")
+        .arch(Architecture::X86_64)
+        .types_only(true)
+        .generate()
+        .unwrap();
+    serde_json::from_str(&isf.to_string()).unwrap()
+}
+
+fn names(map: &Value) -> Vec<&str> {
+    map.as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+fn ids<'a>(ids: impl IntoIterator<Item = &'a Id>) -> Vec<String> {
+    ids.into_iter().map(ToString::to_string).collect()
+}
+
+/// ```c
+/// struct list { int n; };                 // [2]
+/// typedef struct list list_t;             // [3]
+/// typedef struct { int x; } first_t;      // [4], [5]
+/// struct { long y; };                     // [7]
+/// typedef enum { E_A, E_B } e_t;          // [8], [9]
+/// ```
+#[test]
+fn naming() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let list = btf.struct_("list", 4, &[("n", int, 0)]);
+    btf.typedef_("list_t", list);
+    let anon = btf.struct_("", 4, &[("x", int, 0)]);
+    btf.typedef_("first_t", anon);
+    let long = btf.int("long", 8, true);
+    btf.struct_("", 8, &[("y", long, 0)]);
+    let e = btf.enum_("", 4, &[("E_A", 0), ("E_B", 1)]);
+    btf.typedef_("e_t", e);
+    let isf = generate(&btf);
+
+    // Typedefs are aliases of their types, which keep their own names.
+    assert_eq!(
+        names(&isf["user_types"]),
+        [
+            "first_t",
+            "list",
+            "list_t",
+            "unnamed_struct_4",
+            "unnamed_struct_7"
+        ]
+    );
+    assert_eq!(names(&isf["enums"]), ["e_t", "unnamed_enum_8"]);
+    assert_eq!(isf["base_types"]["long"]["size"], json!(8));
+    assert_eq!(isf["base_types"]["long"]["signed"], json!(true));
+    assert_eq!(isf["user_types"]["list_t"], isf["user_types"]["list"]);
+}
+
+/// ```c
+/// int;                      // [1]
+/// struct s;                 // [2]
+/// union u;                  // [3]
+/// struct s { struct s *p }; // [4], [5]
+/// union u { int i; };       // [6]
+/// enum e { E_A };           // [7]
+/// typedef struct s s_t;     // [8]
+/// typedef s_t s2_t;         // [9]
+/// ```
+#[test]
+fn vol_id_sets() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    btf.fwd("s", false);
+    btf.fwd("u", true);
+    btf.struct_("s", 8, &[("p", 5, 0)]);
+    btf.ptr(4);
+    btf.union_("u", 4, &[("i", int, 0)]);
+    btf.enum_("e", 4, &[("E_A", 0)]);
+    let s_t = btf.typedef_("s_t", 4);
+    let s2_t = btf.typedef_("s2_t", s_t);
+    let btf = btf.build("sets.btf").unwrap();
+
+    let (user_ids, enum_ids, basic_ids, fwd_ids, typedefs) = btf.gen_vol_id_sets().unwrap();
+    assert_eq!(ids(&user_ids), ["4", "6"]);
+    assert_eq!(ids(&enum_ids), ["7"]);
+    // `void` is a base type.
+    assert_eq!(ids(&basic_ids), ["0", "1"]);
+    assert_eq!(ids(&fwd_ids), ["2", "3"]);
+
+    let mut targets: Vec<_> = typedefs
+        .fw
+        .iter()
+        .map(|(id, target)| (id.to_string(), target.id.to_string()))
+        .collect();
+    targets.sort();
+    assert_eq!(
+        targets,
+        [
+            (s_t.to_string(), "4".to_string()),
+            (s2_t.to_string(), "4".to_string())
+        ]
+    );
+    let aliases = typedefs.bk.values().next().unwrap();
+    assert_eq!(ids(aliases), ["8", "9"]);
+}
+
+/// ```c
+/// struct s { int n; };        // [2]
+/// typedef struct s a_t;       // [3]
+/// typedef a_t b_t;            // [4]
+/// typedef b_t *p_t;           // [5], [6]
+/// typedef p_t q_t;            // [7]
+/// typedef b_t arr_t[2];       // [8], [9]
+/// typedef b_t c_t;            // [10]
+/// typedef int i_t;            // [11]
+/// ```
+///
+/// Only the typedefs that reach a type without indirections are its aliases,
+/// in ascending order of their IDs, also through chains of typedefs.
+#[test]
+fn typedef_names() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let s = btf.struct_("s", 4, &[("n", int, 0)]);
+    let a_t = btf.typedef_("a_t", s);
+    let b_t = btf.typedef_("b_t", a_t);
+    let p = btf.ptr(b_t);
+    let p_t = btf.typedef_("p_t", p);
+    btf.typedef_("q_t", p_t);
+    let arr = btf.array(b_t, int, 2);
+    btf.typedef_("arr_t", arr);
+    btf.typedef_("c_t", b_t);
+    btf.typedef_("i_t", int);
+    let btf = btf.build("typedefs.btf").unwrap();
+
+    let (.., typedefs) = btf.gen_vol_id_sets().unwrap();
+    let names = |id| {
+        btf.get_names_by_id(btf.id(id).unwrap(), Some(&typedefs))
+            .unwrap()
+    };
+    assert_eq!(names(s), ["s", "a_t", "b_t", "c_t"]);
+    assert_eq!(names(int), ["int", "i_t"]);
+    // Typedefs of pointers and arrays resolve to their element types.
+    assert_eq!(names(p), ["unnamed_ptr_5"]);
+    assert_eq!(names(arr), ["unnamed_array_8"]);
+    // Without typedefs, types only have their own names.
+    assert_eq!(
+        btf.get_names_by_id(btf.id(s).unwrap(), None).unwrap(),
+        ["s"]
+    );
+}
+
+/// ```c
+/// struct node {
+///     int key;
+///     struct node *next;
+///     char name[16];
+///     union { int a; unsigned int b; };
+///     void *data;
+///     unsigned int flags:3, mode:5;
+///     enum state { S_A } state;
+/// };
+/// ```
+#[test]
+fn user_fields() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let uint = btf.int("unsigned int", 4, false);
+    let char_ = btf.int("char", 1, true);
+    let name = btf.array(char_, int, 16);
+    let node_ptr = btf.ptr(9);
+    let anon = btf.union_("", 4, &[("a", int, 0), ("b", uint, 0)]);
+    let void_ptr = btf.ptr(VOID);
+    let state = btf.enum_("state", 4, &[("S_A", 0)]);
+    btf.bitfields(
+        "node",
+        48,
+        &[
+            ("key", int, 0, 0),
+            ("next", node_ptr, 64, 0),
+            ("name", name, 128, 0),
+            ("", anon, 256, 0),
+            ("data", void_ptr, 320, 0),
+            ("flags", uint, 384, 3),
+            ("mode", uint, 387, 5),
+            ("state", state, 352, 0),
+        ],
+    );
+    let isf = generate(&btf);
+
+    let node = &isf["user_types"]["node"];
+    assert_eq!(node["kind"], json!("struct"));
+    assert_eq!(node["size"], json!(48));
+    let fields = &node["fields"];
+    assert_eq!(
+        fields["key"],
+        json!({"type": {"kind": "base", "name": "int"}, "offset": 0, "anonymous": false})
+    );
+    assert_eq!(
+        fields["next"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "node"}})
+    );
+    assert_eq!(
+        fields["name"]["type"],
+        json!({"kind": "array", "count": 16, "subtype": {"kind": "base", "name": "char"}})
+    );
+    assert_eq!(
+        fields["data"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "base", "name": "void"}})
+    );
+    assert_eq!(
+        fields["state"]["type"],
+        json!({"kind": "enum", "name": "state"})
+    );
+    assert_eq!(fields["state"]["offset"], json!(44));
+    assert_eq!(
+        fields["flags"],
+        json!({
+            "type": {
+                "kind": "bitfield",
+                "bit_position": 0,
+                "bit_length": 3,
+                "type": {"kind": "base", "name": "unsigned int"}
+            },
+            "offset": 48,
+            "anonymous": false
+        })
+    );
+    assert_eq!(fields["mode"]["type"]["bit_position"], json!(3));
+
+    // Anonymous members are named after their index, their types after their
+    // IDs.
+    assert_eq!(
+        fields["unnamed_member_3"],
+        json!({
+            "type": {"kind": "union", "name": "unnamed_union_6"},
+            "offset": 32,
+            "anonymous": true
+        })
+    );
+    assert_eq!(
+        names(&isf["user_types"]["unnamed_union_6"]["fields"]),
+        ["a", "b"]
+    );
+}
+
+/// The type with the last ID is generated like all others.
+#[test]
+fn last_type() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    btf.struct_("last", 4, &[("n", int, 0)]);
+    let isf = generate(&btf);
+    assert_eq!(names(&isf["user_types"]), ["last"]);
+    assert_eq!(
+        isf["user_types"]["last"]["fields"]["n"]["type"],
+        json!({"kind": "base", "name": "int"})
+    );
+}
+
+/// BTF without any type but `void` yields a profile without types.
+#[test]
+fn only_void() {
+    let isf = generate(&BtfBuilder::new());
+    assert_eq!(names(&isf["user_types"]), Vec::<&str>::new());
+    assert_eq!(names(&isf["enums"]), Vec::<&str>::new());
+    assert!(names(&isf["base_types"]).contains(&"void"));
+}
@@ -0,0 +1,105 @@
+//! Tests for `--timings` and the durations of the phases in the library API,
+//! on `tests/data/funcs`, see `tests/functions.rs`.
+
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+use btf2json::GenerationOptions;
+
+const FUNCS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/funcs");
+
+/// Phases of a generation with symbols, in order.
+const PHASES: [&str; 9] = [
+    "btf-load",
+    "btf-scan",
+    "symbol-sources",
+    "symbol-types",
+    "base-types",
+    "user-types",
+    "enums",
+    "symbol-entries",
+    "rewrite",
+];
+
+fn run(args: &[&str]) -> Output {
+    let (btf, map) = (
+        format!("{}/funcs.btf", FUNCS),
+        format!("{}/System.map", FUNCS),
+    );
+    let cli = [
+        "generate",
+        "--btf",
+        &btf,
+        "--map",
+        &map,
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    let output = common::run(&[&cli, args].concat());
+    assert!(output.status.success(), "{:?}", output);
+    output
+}
+
+#[test]
+fn table() {
+    let stderr = String::from_utf8(run(&["--timings"]).stderr).unwrap();
+    let rows: Vec<Vec<&str>> = stderr
+        .lines()
+        .skip_while(|line| !line.starts_with("phase "))
+        .take_while(|line| !line.starts_with("Generation finished"))
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(rows[0], ["phase", "duration", "share"], "{}", stderr);
+
+    let labels: Vec<&str> = rows[1..].iter().map(|row| row[0]).collect();
+    let expected: Vec<&str> = PHASES
+        .into_iter()
+        .chain(["verification", "write", "total"])
+        .collect();
+    assert_eq!(labels, expected, "{}", stderr);
+    for row in &rows[1..] {
+        assert_eq!(row.len(), 4, "{:?}", row);
+        assert_eq!(row[2], "ms");
+        assert!(row[1].parse::<f64>().is_ok(), "{:?}", row);
+        assert!(row[3].ends_with('%'), "{:?}", row);
+    }
+    assert_eq!(rows.last().unwrap()[3], "100.0%");
+}
+
+#[test]
+fn no_table_by_default() {
+    let stderr = String::from_utf8(run(&[]).stderr).unwrap();
+    assert!(!stderr.contains("duration"), "{}", stderr);
+}
+
+#[test]
+fn library() {
+    let isf = GenerationOptions::new()
+        .btf_file(Path::new(FUNCS).join("funcs.btf"))
+        .map_file(Path::new(FUNCS).join("System.map"))
+        .banner("Linux version 6.18.0")
+        .generate()
+        .unwrap();
+    let timings = isf.timings();
+    let labels: Vec<&str> = timings.phases().iter().map(|timing| timing.label).collect();
+    assert_eq!(labels, PHASES);
+    assert_eq!(
+        timings.total(),
+        timings.phases().iter().map(|timing| timing.duration).sum()
+    );
+
+    // Callers add their own phases, and repeated phases are summed up.
+    let before = timings.get("rewrite").unwrap();
+    timings.time("write", || ());
+    timings.record("rewrite", std::time::Duration::from_secs(1));
+    assert!(timings.get("write").is_some());
+    assert_eq!(
+        timings.get("rewrite"),
+        Some(before + std::time::Duration::from_secs(1))
+    );
+    assert_eq!(timings.phases().len(), PHASES.len() + 1);
+}
@@ -0,0 +1,67 @@
+//! Tests that truncated and tiny input files lead to errors instead of panics.
+
+mod common;
+
+use std::fs;
+
+/// Valid little endian BTF header of an empty section.
+const EMPTY_BTF: [u8; 25] = [
+    0x9f, 0xeb, 0x01, 0x00, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Runs the generation on a BTF file with `contents` and returns the error.
+fn generate_err(name: &str, contents: &[u8]) -> String {
+    let path = common::write_temp(name, contents);
+    let res = common::context(&["--btf", path.to_str().unwrap(), "--types-only"]);
+    fs::remove_file(&path).unwrap();
+
+    match res {
+        Ok(_) => panic!("{} bytes of {} were accepted", contents.len(), name),
+        Err(err) => format!("{:#}", anyhow::Error::from(err)),
+    }
+}
+
+#[test]
+fn tiny_files() {
+    for len in [0, 1, 3, 20] {
+        let err = generate_err("tiny", &vec![0x42; len]);
+        assert!(err.contains("too small"), "{} bytes: {}", len, err);
+    }
+}
+
+#[test]
+fn truncated_magic() {
+    assert!(generate_err("magic-le", &[0x9f]).contains("too small"));
+    assert!(generate_err("magic-be", &[0xeb, 0x9f, 0x01]).contains("truncated"));
+    assert!(generate_err("elf", &[0x7f, 0x45, 0x4c, 0x46]).contains("ELF"));
+}
+
+#[test]
+fn truncated_header() {
+    let err = generate_err("header", &EMPTY_BTF[..20]);
+    assert!(err.contains("truncated"), "{}", err);
+}
+
+#[test]
+fn magic_followed_by_garbage() {
+    let mut garbage = EMPTY_BTF[..4].to_vec();
+    garbage.extend((0..64u8).map(|b| b.wrapping_mul(37)));
+    generate_err("garbage", &garbage);
+
+    // Sane header, but the type section holds garbage.
+    let mut garbage = EMPTY_BTF[..24].to_vec();
+    garbage[12] = 0x10;
+    garbage[16] = 0x10;
+    garbage.extend([0xff; 16]);
+    garbage.push(0);
+    generate_err("garbage-types", &garbage);
+}
+
+#[test]
+fn section_exceeds_file() {
+    let mut btf = EMPTY_BTF.to_vec();
+    btf[20] = 0xff;
+    let err = generate_err("exceeds", &btf);
+    assert!(err.contains("exceeds"), "{}", err);
+}
@@ -0,0 +1,93 @@
+//! Tests for tracing the generation of single user types.
+//!
+//! `tests/data/bitfields/bitfields.btf` is described in `tests/bitfields.rs`.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::process::Output;
+
+fn run(args: &[&str]) -> Output {
+    let btf = common::data("bitfields/bitfields.btf");
+    let cli = ["generate", "--types-only", "--btf", btf.to_str().unwrap()];
+    common::run(&[&cli, args].concat())
+}
+
+#[test]
+fn traced_type() {
+    let output = run(&["--log-level", "off", "--trace-type", "bits_kf1"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(
+        stderr.contains(r#"[10] definition named ["bits_kf1"], emitted as ["bits_kf1"]"#),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("[10] member 4: raw bit offset 68, bitfield size Some(20)"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("[10::e] resolves along ResolutionPath([]) to [1], named `unsigned int`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("[10::e] bitfield of 20 bits at bit 4 of its first byte"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            r#"[10::e] field {"type":{"kind":"bitfield","bit_position":4,"bit_length":20,"type":{"kind":"base","name":"unsigned int"}},"offset":8,"anonymous":false}"#
+        ),
+        "{}",
+        stderr
+    );
+    // Nothing but the traced type is logged.
+    assert!(
+        stderr
+            .lines()
+            .all(|line| line.contains("btf2json::trace_type] [10")),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn several_types() {
+    let output = run(&["--trace-type", "bits_kf0", "--trace-type", "nonexistent"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(r#"definition named ["bits_kf0"]"#),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("No user type is named `nonexistent`"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("bits_kf1"), "{}", stderr);
+}
+
+#[test]
+fn log_file() {
+    let path = env::temp_dir().join(format!("btf2json-trace-{}.log", std::process::id()));
+    let output = run(&[
+        "--trace-type",
+        "bits_kf1",
+        "--log-file",
+        path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(log.contains("[10::e] field "), "{}", log);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+}
@@ -0,0 +1,170 @@
+//! Tests for `--max-type-depth` on deep chains of pointers and typedefs, which
+//! must neither overflow the stack nor fail the generation.
+//!
+//! The BTF is built with `btf2json::testutil`. The descriptors are inspected
+//! without serializing them, as serde recurses.
+
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+
+use btf2json::testutil::BtfBuilder;
+use btf2json::v_types::TypeDescr;
+use btf2json::GenerationOptions;
+
+/// Number of pointers of `deep`.
+const POINTERS: u32 = 1000;
+
+/// ```c
+/// struct deep {
+///     int ****...**p; // POINTERS pointers
+///     t60 x;          // typedef int t1; typedef t1 t2; ... typedef t59 t60;
+/// };
+/// ```
+fn deep() -> BtfBuilder {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let mut p = int;
+    for _ in 0..POINTERS {
+        p = btf.ptr(p);
+    }
+    let mut t = int;
+    for n in 1..=60 {
+        t = btf.typedef_(&format!("t{}", n), t);
+    }
+    btf.struct_("deep", 16, &[("p", p, 0), ("x", t, 64)]);
+    btf
+}
+
+/// Returns the number of pointers of the fields of `deep` and what they point
+/// to, by field name.
+fn fields(max_type_depth: Option<usize>) -> HashMap<String, (usize, TypeDescr)> {
+    let mut options = GenerationOptions::new()
+        .btf_bytes("deep.btf", deep().bytes())
+        .types_only(true);
+    if let Some(depth) = max_type_depth {
+        options = options.max_type_depth(depth);
+    }
+    let isf = options.generate().unwrap();
+    let (_, deep) = isf.user_types().find(|(name, _)| *name == "deep").unwrap();
+    deep.fields()
+        .map(|(name, field)| {
+            let mut t = field.type_descr();
+            let mut n = 0;
+            while let TypeDescr::Pointer { subtype } = t {
+                t = subtype;
+                n += 1;
+            }
+            (name.to_owned(), (n, t.clone()))
+        })
+        .collect()
+}
+
+fn base(name: &str) -> TypeDescr {
+    TypeDescr::Base { name: name.into() }
+}
+
+#[test]
+fn default_limit() {
+    let fields = fields(None);
+    let (n, root) = &fields["p"];
+    assert!(*n < 128, "{}", n);
+    assert_eq!(root, &TypeDescr::new_void());
+    // 60 typedefs are within the limit.
+    assert_eq!(fields["x"], (0, base("int")));
+}
+
+#[test]
+fn raised_limit() {
+    let fields = fields(Some(2000));
+    assert_eq!(fields["p"], (POINTERS as usize, base("int")));
+    assert_eq!(fields["x"], (0, base("int")));
+}
+
+#[test]
+fn lowered_limit() {
+    let fields = fields(Some(10));
+    let (n, root) = &fields["p"];
+    assert!(*n < 10, "{}", n);
+    assert_eq!(root, &TypeDescr::new_void());
+    // The field is kept, with its type cut off.
+    assert_eq!(fields["x"], (0, TypeDescr::new_void()));
+}
+
+#[test]
+fn warnings() {
+    let path = common::write_temp("deep", deep().bytes());
+    let output = common::run(&[
+        "generate",
+        "--types-only",
+        "--log-level",
+        "warn",
+        "--btf",
+        path.to_str().unwrap(),
+        "--max-type-depth",
+        "100",
+    ]);
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("is longer than 100 types, resolving it to void there"),
+        "{}",
+        stderr
+    );
+}
+
+/// ```c
+/// struct callbacks {
+///     void (*f)(void (*g)(int **x));
+/// };
+/// ```
+///
+/// With rich function descriptors, the parameters nest in the function.
+#[test]
+fn nested_prototypes() {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let x = btf.ptr(int);
+    let x = btf.ptr(x);
+    let g = btf.func_proto(0, &[("x", x)]);
+    let g = btf.ptr(g);
+    let f = btf.func_proto(0, &[("g", g)]);
+    let f = btf.ptr(f);
+    btf.struct_("callbacks", 8, &[("f", f, 0)]);
+    let path = common::write_temp("protos", btf.bytes());
+    let output = common::run(&[
+        "generate",
+        "--types-only",
+        "--rich-functions",
+        "--log-level",
+        "warn",
+        "--max-type-depth",
+        "4",
+        "--btf",
+        path.to_str().unwrap(),
+    ]);
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("nests 7 levels deep, cutting it off with void at --max-type-depth 4"),
+        "{}",
+        stderr
+    );
+
+    let isf: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let void = serde_json::json!({"kind": "base", "name": "void"});
+    let g = serde_json::json!({
+        "kind": "pointer",
+        "subtype": {"kind": "function", "return_type": void, "parameters": [void]},
+    });
+    assert_eq!(
+        isf["user_types"]["callbacks"]["fields"]["f"]["type"],
+        serde_json::json!({
+            "kind": "pointer",
+            "subtype": {"kind": "function", "return_type": void, "parameters": [g]},
+        })
+    );
+}
@@ -0,0 +1,52 @@
+//! Tests for `--include-types` and `--exclude-types`.
+//!
+//! The BTF files are described in `tests/fwd_stubs.rs` and `tests/enums.rs`.
+
+mod common;
+
+use btf2json::isf::Isf;
+use serde_json::Value;
+
+fn generate(btf: &str, args: &[&str]) -> (Isf, Value) {
+    let path = common::data(btf);
+    let isf =
+        common::generate_isf(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat());
+    let value = serde_json::to_value(&isf).unwrap();
+    (isf, value)
+}
+
+fn names(isf: &Value, key: &str) -> Vec<String> {
+    isf[key].as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn exclude() {
+    let (isf, value) = generate("fwd/fwd.btf", &["--exclude-types", "^real$"]);
+    assert_eq!(names(&value, "user_types"), ["user"]);
+    // `user` references the dropped type.
+    assert!(isf.check_user_types().is_err());
+}
+
+#[test]
+fn include() {
+    let (_, value) = generate(
+        "fwd/fwd.btf",
+        &["--include-types", "^(user|opaque)$", "--emit-fwd-stubs"],
+    );
+    // Stubs are filtered like any other type.
+    assert_eq!(names(&value, "user_types"), ["opaque", "user"]);
+    assert_eq!(names(&value, "base_types"), ["pointer", "void"]);
+}
+
+#[test]
+fn enum_base_types_are_kept() {
+    let (_, value) = generate(
+        "enums/values.btf",
+        &["--include-types", "^umask$", "--exclude-types", "int$"],
+    );
+    assert_eq!(names(&value, "enums"), ["umask"]);
+    assert_eq!(
+        names(&value, "base_types"),
+        ["long unsigned int", "pointer", "void"]
+    );
+}
@@ -0,0 +1,51 @@
+//! Tests for type chains through `TYPE_TAG` and for `DECL_TAG` records.
+//!
+//! `tests/data/tags/tags.btf` is a hand-crafted BTF section that defines:
+//!
+//! ```c
+//! struct tagged {
+//!     int __user *uptr;
+//!     struct node __rcu *rptr;
+//!     unsigned long __percpu direct;
+//!     unsigned long __percpu arr[2];
+//! } __decl_tag("tagged_decl");
+//!
+//! typedef struct tagged __tag("t") tagged_t;
+//! ```
+//!
+//! Additionally, the first member of `struct tagged` has a declaration tag.
+
+mod common;
+
+use std::path::Path;
+
+use serde_json::json;
+
+#[test]
+fn tags_are_transparent() {
+    let path = Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/tags/tags.btf"
+    ));
+    let isf = common::generate(&["--btf", path.to_str().unwrap(), "--types-only"]);
+
+    let fields = &isf["user_types"]["tagged"]["fields"];
+    assert_eq!(
+        fields["uptr"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "base", "name": "int"}})
+    );
+    assert_eq!(
+        fields["rptr"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "node"}})
+    );
+    assert_eq!(
+        fields["direct"]["type"],
+        json!({"kind": "base", "name": "long unsigned int"})
+    );
+    assert_eq!(
+        fields["arr"]["type"],
+        json!({"kind": "array", "count": 2, "subtype": {"kind": "base", "name": "long unsigned int"}})
+    );
+    assert_eq!(isf["user_types"]["tagged_t"], isf["user_types"]["tagged"]);
+    assert_eq!(isf["user_types"].as_object().unwrap().len(), 3);
+}
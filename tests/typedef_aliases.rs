@@ -0,0 +1,69 @@
+//! Tests for `--no-typedef-aliases`.
+//!
+//! `tests/data/typedefs/aliases.btf` is a hand-crafted BTF section that
+//! defines:
+//!
+//! ```c
+//! struct list { int n; };
+//! typedef struct list list_t;
+//! typedef struct { int x; } first_t, second_t;
+//! enum mode { M_A, M_B };
+//! typedef enum mode mode_t;
+//! struct user { list_t l; second_t s; struct list *p; mode_t m; };
+//! ```
+
+mod common;
+
+use btf2json::isf::Isf;
+use serde_json::{json, Value};
+
+fn generate(args: &[&str]) -> Isf {
+    let path = common::data("typedefs/aliases.btf");
+    common::generate_isf(&[&["--btf", path.to_str().unwrap(), "--types-only"], args].concat())
+}
+
+fn names(map: &Value) -> Vec<&str> {
+    map.as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn aliases_by_default() {
+    let isf = serde_json::to_value(generate(&[])).unwrap();
+    assert_eq!(
+        names(&isf["user_types"]),
+        [
+            "first_t",
+            "list",
+            "list_t",
+            "second_t",
+            "unnamed_struct_4",
+            "user"
+        ]
+    );
+    assert_eq!(names(&isf["enums"]), ["mode", "mode_t"]);
+}
+
+#[test]
+fn primary_names_only() {
+    let isf = generate(&["--no-typedef-aliases"]);
+    assert!(isf.check_user_types().is_ok());
+
+    let isf = serde_json::to_value(isf).unwrap();
+    assert_eq!(names(&isf["user_types"]), ["first_t", "list", "user"]);
+    assert_eq!(names(&isf["enums"]), ["mode"]);
+
+    let fields = &isf["user_types"]["user"]["fields"];
+    assert_eq!(
+        fields["l"]["type"],
+        json!({"kind": "struct", "name": "list"})
+    );
+    assert_eq!(
+        fields["s"]["type"],
+        json!({"kind": "struct", "name": "first_t"})
+    );
+    assert_eq!(fields["m"]["type"], json!({"kind": "enum", "name": "mode"}));
+}
@@ -0,0 +1,57 @@
+//! Tests for generating profiles without symbols, i.e., without a symbol
+//! source, with `--types-only`.
+//!
+//! Uses the BTF of `tests/data/vars`, which declares a few variables and the
+//! types of them.
+
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+use serde_json::Value;
+
+fn run(args: &[&str]) -> Output {
+    let btf = common::data("vars/vars.btf");
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--arch",
+        "x86_64",
+        "--types-only",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+#[test]
+fn types_only() {
+    let output = run(&["--banner", "Linux version 6.18.0"]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(!isf["user_types"].as_object().unwrap().is_empty());
+
+    // The banner is attached to a synthetic symbol, which is the only one.
+    let symbols = isf["symbols"].as_object().unwrap();
+    assert_eq!(symbols.keys().collect::<Vec<_>>(), ["linux_banner"]);
+    assert_eq!(symbols["linux_banner"]["address"], 0);
+}
+
+#[test]
+fn without_banner() {
+    let output = run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(isf["symbols"].as_object().unwrap().is_empty());
+}
+
+/// Profiles without symbols are valid as well.
+#[test]
+fn validate() {
+    let output = run(&["--validate"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let path = common::write_temp("types-only.json", &output.stdout);
+    let output = common::run(&["validate", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
@@ -0,0 +1,68 @@
+//! Tests for the report of symbols without types.
+//!
+//! The inputs are `tests/data/vars`, see `tests/variables.rs`, where `plain`
+//! is the only symbol without a type.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+fn report(name: &str, symdb: Option<&str>) -> Value {
+    let data = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/vars"));
+    let tmp = env::temp_dir().join(format!("btf2json-untyped-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&tmp).unwrap();
+    let path = tmp.join("untyped.json");
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_btf2json"));
+    cmd.arg("generate")
+        .arg("--btf")
+        .arg(data.join("vars.btf"))
+        .arg("--map")
+        .arg(data.join("System.map"))
+        .args(["--banner", "Linux version 6.18.0", "--output"])
+        .arg(tmp.join("isf.json"))
+        .arg("--untyped-report")
+        .arg(&path);
+    if let Some(symdb) = symdb {
+        fs::write(tmp.join("test.symdb"), symdb).unwrap();
+        cmd.arg("--symdb").arg(tmp.join("test.symdb"));
+    }
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    fs::remove_dir_all(&tmp).unwrap();
+    report
+}
+
+#[test]
+fn no_type() {
+    let report = report("none", None);
+    assert_eq!(
+        report["summary"],
+        json!({"symbols": 7, "typed": 6, "untyped": 1, "no_type": 1, "undefined_type": 0})
+    );
+    assert_eq!(report["sources"]["symdb"], json!("dummy.symdb"));
+    assert_eq!(report["sources"]["btf_variables"], json!(3));
+    assert_eq!(report["sources"]["btf_functions"], json!(0));
+    assert_eq!(
+        report["untyped"],
+        json!({"D": [{"name": "plain", "reason": "no_type"}]})
+    );
+}
+
+#[test]
+fn undefined_type() {
+    let report = report(
+        "undefined",
+        Some("plain {\"kind\":\"struct\",\"name\":\"nonexistent\"}\n"),
+    );
+    assert_eq!(report["summary"]["undefined_type"], json!(1));
+    assert_eq!(report["summary"]["no_type"], json!(0));
+    assert_eq!(report["sources"]["symdb"], json!("test.symdb"));
+    assert_eq!(
+        report["untyped"],
+        json!({"D": [{"name": "plain", "reason": "undefined_type", "undefined": "struct nonexistent"}]})
+    );
+}
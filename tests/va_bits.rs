@@ -0,0 +1,113 @@
+//! Tests for the default base offset of arm64 kernels with different virtual
+//! address bits.
+//!
+//! The System.maps are synthetic, with `_stext` at a KASLR-shifted address and
+//! `vfs_read` 0x100 bytes and `linux_banner` 16 MiB later. The types are from
+//! `tests/data/funcs/funcs.btf`.
+
+mod common;
+
+use std::fs;
+
+use btf2json::cli::{Cli, VaBits};
+use clap::Parser;
+use serde_json::{json, Value};
+
+/// Returns the symbols for a System.map with `_stext` at `stext`.
+fn generate(name: &str, stext: u64, args: &[&str]) -> Value {
+    let map = common::write_temp(
+        &format!("{}.map", name),
+        format!(
+            "{:016x} T _stext\n{:016x} T vfs_read\n{:016x} D linux_banner\n",
+            stext,
+            stext + 0x100,
+            stext + 0x1000000
+        ),
+    );
+    let btf = common::data("funcs/funcs.btf");
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "arm64",
+    ];
+    let isf = common::generate(&[&cli, args].concat());
+    fs::remove_file(&map).unwrap();
+    isf["symbols"].clone()
+}
+
+fn assert_rebased(symbols: &Value, stext: u64) {
+    assert_eq!(symbols["_stext"]["address"], json!(stext));
+    assert_eq!(symbols["vfs_read"]["address"], json!(stext + 0x100));
+}
+
+#[test]
+fn base_offsets() {
+    let cases = [
+        (VaBits::Va39, 0xffffffc080010000),
+        (VaBits::Va42, 0xfffffe0080010000),
+        (VaBits::Va48, 0xffff800080010000),
+        (VaBits::Va52, 0xffff800080010000),
+    ];
+    for (va_bits, base_offset) in cases {
+        assert_eq!(va_bits.base_offset(), base_offset, "{:?}", va_bits);
+    }
+}
+
+#[test]
+fn detected_from_stext() {
+    let cases = [
+        (0xffffffd3a8210000, 0xffffffc080010000),
+        (0xfffffe1234010000, 0xfffffe0080010000),
+        (0xffffa1b2c3010000, 0xffff800080010000),
+        // Already at the default.
+        (0xffffffc080010000, 0xffffffc080010000),
+    ];
+    for (stext, base_offset) in cases {
+        assert_rebased(&generate("va-detect", stext, &[]), base_offset);
+    }
+}
+
+#[test]
+fn given_va_bits() {
+    let cases = [
+        ("39", 0xffffffd3a8210000, 0xffffffc080010000),
+        ("42", 0xfffffe1234010000, 0xfffffe0080010000),
+        ("48", 0xffffa1b2c3010000, 0xffff800080010000),
+        ("52", 0xffffa1b2c3010000, 0xffff800080010000),
+    ];
+    for (va_bits, stext, base_offset) in cases {
+        let symbols = generate("va-given", stext, &["--va-bits", va_bits]);
+        assert_rebased(&symbols, base_offset);
+    }
+}
+
+#[test]
+fn given_va_bits_win_over_mismatching_map() {
+    let symbols = generate("va-mismatch", 0xffffa1b2c3010000, &["--va-bits", "39"]);
+    assert_rebased(&symbols, 0xffffffc080010000);
+}
+
+#[test]
+fn unknown_layout_falls_back_to_48_bits() {
+    // 47 bits, e.g., with 16K pages.
+    let symbols = generate("va-unknown", 0xffffc00080010000, &[]);
+    assert_rebased(&symbols, 0xffff800080010000);
+}
+
+#[test]
+fn conflicts_with_base_offset() {
+    let err = Cli::try_parse_from([
+        "btf2json",
+        "--va-bits",
+        "39",
+        "--base-offset",
+        "ffffffc080010000",
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("--base-offset"), "{}", err);
+}
@@ -0,0 +1,146 @@
+//! Tests for checking ISF files against the ISF JSON schema.
+//!
+//! The ISF files are generated from `tests/data/vars`.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use serde_json::Value;
+
+fn run(args: &[&str]) -> Output {
+    let data = common::data("vars");
+    let (btf, map) = (data.join("vars.btf"), data.join("System.map"));
+    let cli = [
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+fn validate(path: &Path) -> Output {
+    common::run(&["validate", path.to_str().unwrap()])
+}
+
+/// Writes `isf` to a temporary file for the test `name`.
+fn write_isf(name: &str, isf: &Value) -> PathBuf {
+    let path = common::write_temp(
+        &format!("validate-{}.json", name),
+        serde_json::to_vec(isf).unwrap(),
+    );
+    path
+}
+
+fn generated_isf() -> Value {
+    let output = run(&[]);
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn generated_isf_is_valid() {
+    let output = run(&["--validate"]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(isf, generated_isf());
+}
+
+#[test]
+fn validate_existing_file() {
+    let path = write_isf("valid", &generated_isf());
+    let output = validate(&path);
+    assert!(output.status.success(), "{:?}", output);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn violations_are_reported() {
+    let mut isf = generated_isf();
+    let base = isf["base_types"].as_object_mut().unwrap();
+    let (name, _) = base.iter().next().unwrap();
+    let name = name.clone();
+    base[&name]["kind"] = Value::from("Int");
+
+    let path = write_isf("invalid", &isf);
+    let output = validate(&path);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let pointer = format!(
+        "/base_types/{}/kind",
+        name.replace('~', "~0").replace('/', "~1")
+    );
+    assert!(stderr.contains(&pointer), "{}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unknown_format_version() {
+    let mut isf = generated_isf();
+    isf["metadata"]["format"] = Value::from("0.0.1");
+
+    let path = write_isf("format", &isf);
+    let output = validate(&path);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("no schema for ISF format 0.0.1"),
+        "{}",
+        stderr
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn comment() {
+    let comment = "TICKET-42 https://example.com/artifacts/vmlinux";
+    let output = run(&["--validate", "--comment", comment]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(isf["metadata"]["producer"]["comment"], Value::from(comment));
+
+    for args in [&[][..], &["--comment", ""]] {
+        let output = run(args);
+        let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert!(isf["metadata"]["producer"].get("comment").is_none());
+    }
+}
+
+#[test]
+fn older_format_version() {
+    let output = run(&["--format-version", "4.1.0"]);
+    assert!(output.status.success(), "{:?}", output);
+    let isf: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(isf["metadata"]["format"], Value::from("4.1.0"));
+    assert!(isf["metadata"].get("linux").is_none());
+    let symbols = isf["symbols"].as_object().unwrap();
+    assert!(!symbols.is_empty());
+    assert!(symbols.values().all(|s| s.get("linkage").is_none()));
+
+    let isf = generated_isf();
+    assert_eq!(isf["metadata"]["format"], Value::from("6.2.0"));
+    assert!(isf["symbols"]["linux_banner"].get("linkage").is_some());
+
+    // There is no schema to check older versions against.
+    let output = run(&["--format-version", "4.1.0", "--validate"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn unsupported_format_version() {
+    let output = run(&["--format-version", "5.0.0"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("4.1.0") && stderr.contains("6.2.0"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,76 @@
+//! Tests for symbol types from BTF variables.
+//!
+//! `tests/data/vars/vars.btf` is a hand-crafted BTF section that declares the
+//! following variables in a `.data` DATASEC:
+//!
+//! ```c
+//! struct stats { unsigned long hits; };
+//!
+//! unsigned long long jiffies;
+//! int counters[4];
+//! struct stats *stats_ptr;
+//! static int dup;             // in two compilation units with
+//! static unsigned long dup;   // different types
+//! int nomap;                  // not in the System.map
+//! ```
+//!
+//! `tests/data/vars/System.map` lists all variables but `nomap`, plus a symbol
+//! `plain` without variable.
+
+mod common;
+
+use serde_json::{json, Value};
+
+fn generate() -> Value {
+    let (btf, map) = (
+        common::data("vars/vars.btf"),
+        common::data("vars/System.map"),
+    );
+    common::generate(&[
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ])
+}
+
+#[test]
+fn symbol_types_from_variables() {
+    let isf = generate();
+    let symbols = &isf["symbols"];
+
+    assert_eq!(
+        symbols["counters"]["type"],
+        json!({"kind": "array", "count": 4, "subtype": {"kind": "base", "name": "int"}})
+    );
+    assert_eq!(
+        symbols["stats_ptr"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "struct", "name": "stats"}})
+    );
+    assert_eq!(
+        symbols["plain"]["type"],
+        json!({"kind": "base", "name": "void"})
+    );
+    assert!(symbols["nomap"].is_null());
+}
+
+#[test]
+fn variables_take_precedence_over_symdb() {
+    let isf = generate();
+    let symbols = &isf["symbols"];
+
+    // The symdb has `unsigned long jiffies`.
+    assert_eq!(
+        symbols["jiffies"]["type"],
+        json!({"kind": "base", "name": "long long unsigned int"})
+    );
+    // Ambiguous variables leave the type from the symdb.
+    assert_eq!(
+        symbols["dup"]["type"],
+        json!({"kind": "pointer", "subtype": {"kind": "base", "name": "void"}})
+    );
+}
@@ -0,0 +1,151 @@
+//! Tests for the checks of `--check` and `--check-report` on enums, symbols,
+//! and type descriptors, on ISF files that are generated from BTF built with
+//! `btf2json::testutil`, broken, and read back.
+
+use btf2json::error::{Error, VerificationKind};
+use btf2json::isf::Isf;
+use btf2json::testutil::BtfBuilder;
+use btf2json::GenerationOptions;
+use serde_json::{json, Value};
+
+/// ```c
+/// enum e { E_A };
+/// struct s {
+///     int a:3;
+///     int *p;
+///     enum e e;  // added to the ISF file
+/// };
+/// int jiffies;
+/// struct s init_s;
+/// ```
+fn generate() -> Value {
+    let mut btf = BtfBuilder::new();
+    let int = btf.int("int", 4, true);
+    let p = btf.ptr(int);
+    btf.enum_("e", 4, &[("E_A", 0)]);
+    btf.bitfields("s", 16, &[("a", int, 0, 3), ("p", p, 64, 0)]);
+    let isf = GenerationOptions::new()
+        .btf_bytes("checks.btf", btf.bytes())
+        .types_only(true)
+        .generate()
+        .unwrap();
+    let mut isf = serde_json::to_value(isf).unwrap();
+    isf["user_types"]["s"]["fields"]["e"] = json!({
+        "offset": 12,
+        "type": {"kind": "enum", "name": "e"},
+    });
+    isf["symbols"] = json!({
+        "jiffies": {"address": 4096, "type": {"kind": "base", "name": "int"}},
+        "init_s": {"address": 8192, "type": {"kind": "struct", "name": "s"}},
+    });
+    isf
+}
+
+fn read(isf: &Value) -> Isf {
+    Isf::from_reader(serde_json::to_vec(isf).unwrap().as_slice()).unwrap()
+}
+
+fn kinds(errors: &[Error]) -> Vec<VerificationKind> {
+    errors
+        .iter()
+        .map(|err| match err {
+            Error::IsfVerification { kind, .. } => *kind,
+            err => panic!("{}", err),
+        })
+        .collect()
+}
+
+#[test]
+fn consistent() {
+    let isf = read(&generate());
+    let report = isf.check_report();
+    assert!(report.check().is_empty());
+    assert!(isf.check_enums().is_ok());
+    assert!(isf.check_descriptors().is_ok());
+    assert!(isf.check_symbol_types().is_ok());
+}
+
+#[test]
+fn undefined_enum_base() {
+    let mut isf = generate();
+    isf["enums"]["e"]["base"] = json!("no_such_int");
+    let isf = read(&isf);
+    let report = serde_json::to_value(isf.check_report()).unwrap();
+    assert_eq!(
+        report["enums"],
+        json!([{"name": "e", "base": "no_such_int"}])
+    );
+    assert_eq!(report["summary"]["enums"], 1);
+    assert_eq!(
+        kinds(&isf.check_report().check()),
+        [VerificationKind::Enums]
+    );
+    assert!(isf.check_enums().is_err());
+}
+
+#[test]
+fn undefined_symbol_types() {
+    let mut isf = generate();
+    // Undefined types nested in arrays and bitfields are found as well.
+    isf["symbols"]["jiffies"]["type"] = json!({
+        "kind": "array",
+        "count": 2,
+        "subtype": {"kind": "struct", "name": "missing"},
+    });
+    isf["symbols"]["init_s"]["type"] = json!({
+        "kind": "bitfield",
+        "bit_position": 0,
+        "bit_length": 3,
+        "type": {"kind": "base", "name": "u3"},
+    });
+    let mut isf = read(&isf);
+    let report = serde_json::to_value(isf.check_report()).unwrap();
+    assert_eq!(
+        report["symbols"],
+        json!([
+            {"name": "init_s", "undefined": {"kind": "", "name": "u3"}},
+            {"name": "jiffies", "undefined": {"kind": "struct", "name": "missing"}},
+        ])
+    );
+    assert_eq!(
+        kinds(&isf.check_report().check()),
+        [VerificationKind::SymbolTypes]
+    );
+
+    // The report shows the problems before the symbols are set to void.
+    assert!(isf.fix_symbol_types().is_err());
+    assert!(isf.check_report().check().is_empty());
+}
+
+#[test]
+fn malformed_descriptors() {
+    let mut isf = generate();
+    isf["user_types"]["s"]["fields"]["a"]["type"]["type"] = json!({"kind": "struct", "name": "s"});
+    isf["symbols"]["jiffies"]["type"] = json!({
+        "kind": "bitfield",
+        "bit_position": 0,
+        "bit_length": 3,
+        "type": {"kind": "pointer", "subtype": {"kind": "base", "name": "int"}},
+    });
+    isf["base_types"].as_object_mut().unwrap().remove("pointer");
+    let isf = read(&isf);
+    let problems: Vec<String> = isf
+        .descriptor_problems()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "[struct s::a] bitfield of struct s",
+            "[symbol jiffies] bitfield of pointer",
+            "[base_types] base type `pointer` is undefined, but 2 descriptors are pointers",
+        ]
+    );
+    let report = serde_json::to_value(isf.check_report()).unwrap();
+    assert_eq!(report["summary"]["descriptors"], 3);
+    assert_eq!(
+        kinds(&isf.check_report().check()),
+        [VerificationKind::Descriptors]
+    );
+}
@@ -0,0 +1,200 @@
+//! Tests for ELF vmcores as memory images.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use base64::prelude::*;
+use btf2json::btf::Endian;
+use btf2json::vmcore::Vmcore;
+use serde_json::{json, Value};
+
+const BANNER: &str = "Linux version 6.18.0 (btf2json@test) #1 SMP\n";
+/// Link-time address of `linux_banner`.
+const BANNER_ADDR: u64 = 0xffffffff82000000;
+/// KASLR offset of the kernel in the vmcore.
+const KERNEL_OFFSET: u64 = 0x1a000000;
+const SYSTEM_MAP: &str = "ffffffff81000000 T _stext\nffffffff82000000 D linux_banner\n";
+
+fn tiny_btf(endian: &str) -> Vec<u8> {
+    fs::read(format!(
+        "{}/tests/data/endian/tiny_{}.btf",
+        env!("CARGO_MANIFEST_DIR"),
+        endian
+    ))
+    .unwrap()
+}
+
+/// Writes integers in the byte order of the vmcore.
+#[derive(Clone, Copy)]
+struct Writer {
+    big_endian: bool,
+}
+
+impl Writer {
+    fn u16(self, buf: &mut Vec<u8>, n: u16) {
+        buf.extend(if self.big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        });
+    }
+
+    fn u32(self, buf: &mut Vec<u8>, n: u32) {
+        buf.extend(if self.big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        });
+    }
+
+    fn u64(self, buf: &mut Vec<u8>, n: u64) {
+        buf.extend(if self.big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        });
+    }
+
+    /// Returns an ELF note.
+    fn note(self, name: &[u8], type_: u32, desc: &[u8]) -> Vec<u8> {
+        let mut note = Vec::new();
+        self.u32(&mut note, name.len() as u32 + 1);
+        self.u32(&mut note, desc.len() as u32);
+        self.u32(&mut note, type_);
+        note.extend(name);
+        note.push(0);
+        note.resize(note.len().next_multiple_of(4), 0);
+        note.extend(desc);
+        note.resize(note.len().next_multiple_of(4), 0);
+        note
+    }
+
+    /// Returns an ELF64 vmcore of `machine` with the given segments, which
+    /// are pairs of virtual address and contents, and the VMCOREINFO `info`.
+    fn vmcore(self, machine: u16, segments: &[(u64, Vec<u8>)], info: &str) -> Vec<u8> {
+        let mut notes = self.note(b"CORE", 1, &[0; 32]);
+        notes.extend(self.note(b"VMCOREINFO", 0, info.as_bytes()));
+        let phnum = segments.len() + 1;
+
+        let mut elf = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0".to_vec();
+        elf[5] = if self.big_endian { 2 } else { 1 }; // EI_DATA
+        self.u16(&mut elf, 4); // e_type: CORE
+        self.u16(&mut elf, machine);
+        self.u32(&mut elf, 1); // e_version
+        self.u64(&mut elf, 0); // e_entry
+        self.u64(&mut elf, 64); // e_phoff
+        self.u64(&mut elf, 0); // e_shoff
+        self.u32(&mut elf, 0); // e_flags
+        self.u16(&mut elf, 64); // e_ehsize
+        self.u16(&mut elf, 56); // e_phentsize
+        self.u16(&mut elf, phnum as u16);
+        elf.extend([0; 6]); // e_shentsize, e_shnum, e_shstrndx
+
+        let mut data = vec![(4u32, 0u64, notes)];
+        data.extend(segments.iter().map(|(vaddr, d)| (1, *vaddr, d.clone())));
+        let mut offset = (64 + 56 * phnum) as u64;
+        for (type_, vaddr, d) in data.iter() {
+            self.u32(&mut elf, *type_);
+            self.u32(&mut elf, 7); // p_flags: RWX
+            self.u64(&mut elf, offset);
+            self.u64(&mut elf, *vaddr);
+            self.u64(&mut elf, 0); // p_paddr
+            self.u64(&mut elf, d.len() as u64); // p_filesz
+            self.u64(&mut elf, d.len() as u64); // p_memsz
+            self.u64(&mut elf, 0); // p_align
+            offset += d.len() as u64;
+        }
+        for (_, _, d) in data {
+            elf.extend(d);
+        }
+        elf
+    }
+}
+
+/// Returns a vmcore of `machine` with the banner and the BTF section in the
+/// relocated kernel image, and decoys in other segments.
+fn kdump_vmcore(machine: u16, big_endian: bool) -> Vec<u8> {
+    let endian = if big_endian { "be" } else { "le" };
+    let mut kernel = BANNER.as_bytes().to_vec();
+    kernel.resize(0x100, 0);
+    kernel.extend(tiny_btf(endian));
+
+    // Valid BTF header without a string section, in memory that is not mapped.
+    let mut decoy = tiny_btf(endian)[..24].to_vec();
+    decoy.extend([0xcc; 0x100]);
+
+    Writer { big_endian }.vmcore(
+        machine,
+        &[
+            (0, decoy),
+            (BANNER_ADDR + KERNEL_OFFSET, kernel),
+            (0xffff888000000000, vec![0x11; 0x100]),
+        ],
+        &format!("OSRELEASE=6.18.0\nKERNELOFFSET={:x}\n", KERNEL_OFFSET),
+    )
+}
+
+fn generate(image: &Path, map: &Path) -> anyhow::Result<Value> {
+    Ok(common::try_generate(&[
+        "--image",
+        image.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+    ])?)
+}
+
+#[test]
+fn vmcore_with_system_map() {
+    let image = common::write_temp("vmcore", kdump_vmcore(62, false));
+    let map = common::write_temp("vmcore-map", SYSTEM_MAP.as_bytes());
+    let isf = generate(&image, &map);
+    fs::remove_file(&image).unwrap();
+    fs::remove_file(&map).unwrap();
+    let isf = isf.unwrap();
+
+    assert_eq!(isf["user_types"]["task"]["size"], json!(40));
+    let banner = isf["symbols"]["linux_banner"]["constant_data"]
+        .as_str()
+        .unwrap();
+    assert_eq!(BASE64_STANDARD.decode(banner).unwrap(), BANNER.as_bytes());
+}
+
+/// The byte order of big endian vmcores, e.g., of s390x, is taken from their
+/// ELF header.
+#[test]
+fn big_endian_vmcore() {
+    let raw = kdump_vmcore(22, true);
+    let vmcore = Vmcore::parse(&raw).unwrap();
+    assert_eq!(vmcore.endian(), Endian::Big);
+    assert_eq!(vmcore.kernel_offset(), KERNEL_OFFSET);
+    let banner = vmcore
+        .read_virt(&raw, BANNER_ADDR + KERNEL_OFFSET, BANNER.len())
+        .unwrap();
+    assert_eq!(banner, BANNER.as_bytes());
+
+    let image = common::write_temp("vmcore-be", &raw);
+    let map = common::write_temp("vmcore-be-map", SYSTEM_MAP.as_bytes());
+    let isf = generate(&image, &map);
+    fs::remove_file(&image).unwrap();
+    fs::remove_file(&map).unwrap();
+    let isf = isf.unwrap();
+    assert_eq!(isf["user_types"]["task"]["size"], json!(40));
+}
+
+#[test]
+fn compressed_kdump_is_rejected() {
+    let image = common::write_temp("kdump", b"KDUMP   \x06\0\0\0");
+    let map = common::write_temp("kdump-map", SYSTEM_MAP.as_bytes());
+    let err = generate(&image, &map).unwrap_err();
+    fs::remove_file(&image).unwrap();
+    fs::remove_file(&map).unwrap();
+
+    assert!(
+        format!("{:#}", err).contains("makedumpfile -E"),
+        "{:#}",
+        err
+    );
+}
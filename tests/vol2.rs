@@ -0,0 +1,169 @@
+//! Tests of the Volatility 2 profiles of `btf2json vol2`.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use btf2json::cli::Architecture;
+use btf2json::testutil::BtfBuilder;
+use btf2json::vol2::{Profile, SYSTEM_MAP_NAME, VTYPES_NAME};
+use btf2json::GenerationOptions;
+use flate2::read::DeflateDecoder;
+use flate2::Crc;
+
+fn u16_at(zip: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes(zip[off..off + 2].try_into().unwrap())
+}
+
+fn u32_at(zip: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(zip[off..off + 4].try_into().unwrap())
+}
+
+/// Returns the files of `zip` by name, checking their sizes and CRCs.
+fn unzip(zip: &[u8]) -> BTreeMap<String, String> {
+    let end = zip.len() - 22;
+    assert_eq!(u32_at(zip, end), 0x06054b50);
+    let nr_files = u16_at(zip, end + 10);
+    let mut central = u32_at(zip, end + 16) as usize;
+
+    let mut files = BTreeMap::new();
+    for _ in 0..nr_files {
+        assert_eq!(u32_at(zip, central), 0x02014b50);
+        let name_len = u16_at(zip, central + 28) as usize;
+        let name = &zip[central + 46..central + 46 + name_len];
+        let local = u32_at(zip, central + 42) as usize;
+        assert_eq!(u32_at(zip, local), 0x04034b50);
+        // The local header repeats the central one.
+        assert_eq!(zip[local + 4..local + 30], zip[central + 6..central + 32]);
+        assert_eq!(u16_at(zip, local + 8), 8);
+
+        let start = local + 30 + name_len;
+        let compressed = &zip[start..start + u32_at(zip, local + 18) as usize];
+        let mut contents = String::new();
+        DeflateDecoder::new(compressed)
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents.len(), u32_at(zip, local + 22) as usize);
+        let mut crc = Crc::new();
+        crc.update(contents.as_bytes());
+        assert_eq!(crc.sum(), u32_at(zip, local + 14));
+
+        files.insert(String::from_utf8(name.to_vec()).unwrap(), contents);
+        central += 46 + name_len;
+    }
+    files
+}
+
+/// ```c
+/// struct list_head { struct list_head *next, *prev; };
+/// enum state { S_RUN, S_STOP = 4 };
+/// struct task {
+///     struct list_head tasks;
+///     char comm[16];
+///     unsigned int flags:3, mode:5;
+///     union { enum state state; long unsigned int raw; };
+///     void (*fn)(void);
+///     _Bool on_cpu;
+/// };
+/// ```
+#[test]
+fn vtypes() {
+    let mut btf = BtfBuilder::new();
+    let list_head = btf.struct_("list_head", 16, &[("next", 2, 0), ("prev", 2, 64)]);
+    btf.ptr(list_head);
+    let int = btf.int("int", 4, true);
+    let uint = btf.int("unsigned int", 4, false);
+    let char_ = btf.int("char", 1, true);
+    let ulong = btf.int("long unsigned int", 8, false);
+    let bool_ = btf.int("_Bool", 1, false);
+    let comm = btf.array(char_, int, 16);
+    let state = btf.enum_("state", 4, &[("S_RUN", 0), ("S_STOP", 4)]);
+    let anon = btf.union_("", 8, &[("state", state, 0), ("raw", ulong, 0)]);
+    let proto = btf.add("", 13, false, 0, 0, &[]);
+    let fn_ptr = btf.ptr(proto);
+    btf.bitfields(
+        "task",
+        56,
+        &[
+            ("tasks", list_head, 0, 0),
+            ("comm", comm, 128, 0),
+            ("flags", uint, 256, 3),
+            ("mode", uint, 259, 5),
+            ("", anon, 320, 0),
+            ("fn", fn_ptr, 384, 0),
+            ("on_cpu", bool_, 448, 0),
+        ],
+    );
+    let isf = GenerationOptions::new()
+        .btf_bytes("synthetic.btf", btf.bytes())
+        .banner("Linux version 6.18.0")
+        .arch(Architecture::X86_64)
+        .types_only(true)
+        .generate()
+        .unwrap();
+    let profile = Profile::new(&isf, Some("Linux version 6.18.0\n"));
+    let vtypes = profile.vtypes();
+
+    assert!(vtypes.starts_with("# Volatility 2 vtypes generated by btf2json"));
+    assert!(vtypes.contains("\n# Linux version 6.18.0\nlinux_types = {\n"));
+    assert!(vtypes.contains(
+        "    'list_head': [16, {\n        \
+         'next': [0, ['pointer', ['list_head']]],\n        \
+         'prev': [8, ['pointer', ['list_head']]],\n    }],\n"
+    ));
+    for line in [
+        "'comm': [16, ['array', 16, ['char']]]",
+        "'flags': [32, ['BitField', {'start_bit': 0, 'end_bit': 3, 'native_type': 'unsigned int'}]]",
+        "'mode': [32, ['BitField', {'start_bit': 3, 'end_bit': 8, 'native_type': 'unsigned int'}]]",
+        "'fn': [48, ['pointer', ['void']]]",
+        "'on_cpu': [56, ['unsigned char']]",
+        // The fields of anonymous members are also fields of their parent.
+        "'unnamed_member_4': [40, ['unnamed_union_10']]",
+        "'raw': [40, ['unsigned long']]",
+        "'state': [40, ['Enumeration', {'target': 'int', 'choices': {0: 'S_RUN', 4: 'S_STOP'}}]]",
+    ] {
+        assert!(
+            vtypes.contains(&format!("        {},\n", line)),
+            "{}\n{}",
+            line,
+            vtypes
+        );
+    }
+    assert!(vtypes.contains("    'unnamed_union_10': [8, {\n"));
+}
+
+#[test]
+fn profile_zip() {
+    let (btf, map) = (
+        common::data("funcs/funcs.btf"),
+        common::data("funcs/System.map"),
+    );
+    let output = common::run(&[
+        "vol2",
+        "--btf",
+        btf.to_str().unwrap(),
+        "--map",
+        map.to_str().unwrap(),
+        "--banner",
+        "Linux version 6.18.0",
+        "--arch",
+        "x86_64",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let files = unzip(&output.stdout);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        [SYSTEM_MAP_NAME, VTYPES_NAME]
+    );
+    assert!(files[VTYPES_NAME].contains("    'file': [8, {\n        'f_pos': [0, ['long']],\n"));
+    let map: Vec<_> = files[SYSTEM_MAP_NAME].lines().collect();
+    assert_eq!(map[0], "ffffffff81000000 T _stext");
+    assert!(map.contains(&"ffffffff81000500 W weakfn"));
+    assert!(map.contains(&"ffffffff82000100 D linux_banner"));
+}
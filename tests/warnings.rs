@@ -0,0 +1,81 @@
+//! Tests for the summary of the warnings and `--warnings-as-errors`.
+//!
+//! The fields of `struct user` in `tests/data/fwd/fwd.btf`, see
+//! `tests/fwd_stubs.rs`, reference the undefined types `struct opaque` and
+//! `union uopaque`, unless `--emit-fwd-stubs` defines them.
+
+mod common;
+
+use std::process::Output;
+
+const FWD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fwd/fwd.btf");
+
+fn run(args: &[&str]) -> Output {
+    let cli = [
+        "generate",
+        "--btf",
+        FWD,
+        "--types-only",
+        "--banner",
+        "Linux version 6.18.0",
+    ];
+    common::run(&[&cli, args].concat())
+}
+
+#[test]
+fn summary() {
+    // At any log level.
+    let output = run(&["--log-level", "error"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("WARN "), "{}", stderr);
+    let summary = stderr
+        .split_once("Generation finished with ")
+        .map(|(_, summary)| summary)
+        .unwrap_or_else(|| panic!("{}", stderr));
+    let lines: Vec<&str> = summary.lines().map(str::trim).collect();
+    assert_eq!(
+        lines,
+        [
+            "6 warnings",
+            "3  fields of undefined types",
+            "2  undefined types",
+            "1  failed verifications",
+        ]
+    );
+}
+
+#[test]
+fn warnings_as_errors() {
+    let output = run(&["--warnings-as-errors"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Failing because of --warnings-as-errors with 6 warnings"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("2  undefined types"), "{}", stderr);
+
+    // Defining the types fixes the profile.
+    let output = run(&["--warnings-as-errors", "--emit-fwd-stubs"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("warnings"), "{}", stderr);
+}
+
+#[test]
+fn not_in_batch() {
+    let output = common::run(&[
+        "batch",
+        "--input-dir",
+        ".",
+        "--output-dir",
+        "out",
+        "--warnings-as-errors",
+    ]);
+    assert_eq!(output.status.code(), Some(3), "{:?}", output);
+}
@@ -0,0 +1,52 @@
+//! Tests that the in-memory core builds for wasm32, i.e., without the default
+//! features.
+//!
+//! Ignored by default, as it needs the `wasm32-unknown-unknown` target. The
+//! `wasm32` job of `.github/workflows/ci.yml` installs the target and runs it
+//! with `cargo test --test wasm -- --ignored`.
+
+use std::path::Path;
+use std::process::Command;
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+#[test]
+#[ignore = "needs the wasm32-unknown-unknown target"]
+fn check_wasm32() {
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let sysroot = String::from_utf8(sysroot.stdout).unwrap();
+    assert!(
+        Path::new(sysroot.trim())
+            .join("lib/rustlib")
+            .join(TARGET)
+            .exists(),
+        "target {} is not installed, run `rustup target add {}`",
+        TARGET,
+        TARGET
+    );
+
+    // A separate target directory avoids waiting for the lock of the one that
+    // runs the tests.
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--lib",
+            "--no-default-features",
+            "--target",
+            TARGET,
+        ])
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(Path::new(env!("CARGO_TARGET_TMPDIR")).join("wasm32"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}